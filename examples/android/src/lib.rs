@@ -1,8 +1,9 @@
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
 use bevy::transform::components::Transform;
+use bevy_oxr::foveation::{XrFoveationLevel, XrFoveationSettings};
 use bevy_oxr::graphics::extensions::XrExtensions;
-use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::graphics::{XrAppInfo, XrSessionConfig};
 use bevy_oxr::passthrough::{PausePassthrough, ResumePassthrough, XrPassthroughState};
 use bevy_oxr::xr_init::xr_only;
 use bevy_oxr::xr_input::hands::common::HandInputDebugRenderer;
@@ -18,15 +19,27 @@ fn main() {
     let mut xr_extensions = XrExtensions::default();
     xr_extensions.enable_fb_passthrough();
     xr_extensions.enable_hand_tracking();
+    xr_extensions.enable_eye_tracked_foveation();
     App::new()
         .add_plugins(DefaultXrPlugins {
             reqeusted_extensions: xr_extensions,
             app_info: XrAppInfo {
                 name: "Bevy OXR Android Example".into(),
             },
+            session_config: XrSessionConfig {
+                foveation: Some(XrFoveationSettings {
+                    level: XrFoveationLevel::High,
+                    dynamic: true,
+                    vertical_offset: 0.0,
+                    eye_tracked: true,
+                }),
+                ..default()
+            },
+            android_permissions: vec!["com.oculus.permission.EYE_TRACKING".into()],
             ..Default::default()
         })
         // .add_plugins(OpenXrDebugRenderer)
+        // logs the frame time, to compare the different foveation levels
         .add_plugins(LogDiagnosticsPlugin::default())
         .add_plugins(FrameTimeDiagnosticsPlugin)
         .add_plugins(HandInputDebugRenderer)
@@ -34,7 +47,7 @@ fn main() {
         .add_systems(Startup, setup)
         .add_systems(
             Update,
-            (proto_locomotion, toggle_passthrough).run_if(xr_only()),
+            (proto_locomotion, toggle_passthrough, cycle_foveation_level).run_if(xr_only()),
         )
         .add_systems(Update, debug_hand_render.run_if(xr_only()))
         .add_systems(Startup, spawn_controllers_example)
@@ -122,3 +135,21 @@ fn toggle_passthrough(
         }
     }
 }
+
+fn cycle_foveation_level(
+    keys: Res<ButtonInput<KeyCode>>,
+    foveation: Option<ResMut<XrFoveationSettings>>,
+) {
+    let Some(mut foveation) = foveation else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::KeyF) {
+        foveation.level = match foveation.level {
+            XrFoveationLevel::None => XrFoveationLevel::Low,
+            XrFoveationLevel::Low => XrFoveationLevel::Medium,
+            XrFoveationLevel::Medium => XrFoveationLevel::High,
+            XrFoveationLevel::High => XrFoveationLevel::None,
+        };
+        info!("Foveation level: {:?}", foveation.level);
+    }
+}
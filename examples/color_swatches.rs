@@ -0,0 +1,93 @@
+//! Shows unlit swatches of known sRGB colors, compare them against a reference image to check
+//! that the swapchain format and color space are handled correctly by the runtime.
+
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::prelude::*;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::resources::{XrColorSpace, XrFormat, XrSwapchain};
+use bevy_oxr::xr_init::XrSetup;
+use bevy_oxr::xr_input::xr_camera::XrCamera;
+use bevy_oxr::DefaultXrPlugins;
+
+/// Gray ramp in the top row, primaries and secondaries in the bottom row, all in sRGB
+const SWATCHES: [[[u8; 3]; 6]; 2] = [
+    [
+        [0, 0, 0],
+        [51, 51, 51],
+        [102, 102, 102],
+        [153, 153, 153],
+        [204, 204, 204],
+        [255, 255, 255],
+    ],
+    [
+        [255, 0, 0],
+        [0, 255, 0],
+        [0, 0, 255],
+        [0, 255, 255],
+        [255, 0, 255],
+        [255, 255, 0],
+    ],
+];
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            app_info: XrAppInfo {
+                name: "Bevy OXR Color Swatches".into(),
+            },
+            ..default()
+        })
+        .insert_resource(ClearColor(Color::rgb_u8(128, 128, 128)))
+        .add_systems(Startup, spawn_swatches)
+        .add_systems(XrSetup, print_color_space)
+        .add_systems(Update, disable_tonemapping)
+        .run();
+}
+
+fn spawn_swatches(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(Rectangle::new(0.2, 0.2));
+    for (row, colors) in SWATCHES.iter().enumerate() {
+        for (column, [r, g, b]) in colors.iter().copied().enumerate() {
+            commands.spawn(PbrBundle {
+                mesh: mesh.clone(),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgb_u8(r, g, b),
+                    unlit: true,
+                    ..default()
+                }),
+                transform: Transform::from_xyz(
+                    (column as f32 - 2.5) * 0.25,
+                    1.75 - row as f32 * 0.25,
+                    -1.5,
+                ),
+                ..default()
+            });
+        }
+    }
+}
+
+/// Tonemapping would change the colors of the swatches
+fn disable_tonemapping(mut cameras: Query<&mut Tonemapping, Added<XrCamera>>) {
+    for mut tonemapping in &mut cameras {
+        *tonemapping = Tonemapping::None;
+    }
+}
+
+fn print_color_space(
+    format: Res<XrFormat>,
+    swapchain: Res<XrSwapchain>,
+    color_space: Res<XrColorSpace>,
+) {
+    info!(
+        "Swapchain format {:?}, rendering with {:?} in the {:?} color space",
+        **format,
+        swapchain.view_format(),
+        *color_space
+    );
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_oxr::xr_input::grab::{GrabEnd, GrabStart, Hover, XrGrabPlugin, XrGrabbable, XrGrabbers};
+use bevy_oxr::xr_input::haptics::XrHapticEvent;
+use bevy_oxr::xr_input::pose_history::XrPoseHistory;
+use bevy_oxr::xr_input::trackers::{OpenXRLeftController, OpenXRRightController};
+use bevy_oxr::xr_input::Hand;
+use bevy_rapier3d::prelude::*;
+
+/// Balls on a shelf out of reach, pull them over with the grips and throw them
+pub struct DistanceGrabPlugin;
+
+impl Plugin for DistanceGrabPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(XrGrabPlugin)
+            .add_systems(Startup, spawn_shelf)
+            .add_systems(Update, (add_pose_histories, hold_and_throw, hover_feedback));
+    }
+}
+
+fn spawn_shelf(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Cuboid::new(1.5, 0.05, 0.4)),
+            material: materials.add(StandardMaterial::from(Color::rgb(0.4, 0.3, 0.2))),
+            transform: Transform::from_xyz(0.0, 1.0, -3.0),
+            ..default()
+        },
+        RigidBody::Fixed,
+        Collider::cuboid(0.75, 0.025, 0.2),
+    ));
+    let mesh = meshes.add(Sphere::new(0.06));
+    for i in 0..5 {
+        commands.spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: materials.add(StandardMaterial::from(Color::hsl(
+                    i as f32 * 72.0,
+                    0.8,
+                    0.5,
+                ))),
+                transform: Transform::from_xyz(-0.6 + i as f32 * 0.3, 1.1, -3.0),
+                ..default()
+            },
+            RigidBody::Dynamic,
+            Collider::ball(0.06),
+            Velocity::zero(),
+            Ccd::enabled(),
+            XrGrabbable::default(),
+        ));
+    }
+}
+
+/// The release velocity is fitted over the last few frames of the controller
+#[allow(clippy::type_complexity)]
+fn add_pose_histories(
+    mut commands: Commands,
+    controllers: Query<
+        Entity,
+        (
+            Or<(Added<OpenXRLeftController>, Added<OpenXRRightController>)>,
+            Without<XrPoseHistory>,
+        ),
+    >,
+) {
+    for controller in &controllers {
+        commands.entity(controller).insert(XrPoseHistory::default());
+    }
+}
+
+/// Held balls follow the hand instead of the simulation until they're thrown
+fn hold_and_throw(
+    mut starts: EventReader<GrabStart>,
+    mut ends: EventReader<GrabEnd>,
+    mut bodies: Query<(&mut RigidBody, &mut Velocity)>,
+) {
+    // a ball passed to the other hand ends its grab before the new one starts
+    for end in ends.read() {
+        if let Ok((mut body, mut velocity)) = bodies.get_mut(end.entity) {
+            *body = RigidBody::Dynamic;
+            velocity.linvel = end.linear_velocity;
+            velocity.angvel = end.angular_velocity;
+        }
+    }
+    for start in starts.read() {
+        if let Ok((mut body, _)) = bodies.get_mut(start.entity) {
+            *body = RigidBody::KinematicPositionBased;
+        }
+    }
+}
+
+/// Clicks when a hand points at another ball and outlines the balls the hands would grab
+fn hover_feedback(
+    mut hovers: EventReader<Hover>,
+    mut haptics: EventWriter<XrHapticEvent>,
+    grabbers: Res<XrGrabbers>,
+    transforms: Query<&GlobalTransform>,
+    mut gizmos: Gizmos,
+) {
+    for hover in hovers.read() {
+        if hover.candidate.is_some() {
+            haptics.send(XrHapticEvent::new(hover.hand, 0.2, Duration::ZERO));
+        }
+    }
+    for hand in [Hand::Left, Hand::Right] {
+        let Some(candidate) = grabbers.get(hand).hovered else {
+            continue;
+        };
+        if let Ok(transform) = transforms.get(candidate.entity) {
+            gizmos.sphere(transform.translation(), Quat::IDENTITY, 0.08, Color::WHITE);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use bevy_oxr::xr_init::XrSetup;
+use bevy_oxr::xr_input::actions::{
+    ActionHandednes, ActionType, SetupActionSets, XrBinding, XrButton,
+};
+use bevy_oxr::xr_input::input_contexts::XrInputContext;
+use bevy_oxr::xr_input::interactions::{Touched, XRInteractable, XRInteractableState, XRSelection};
+use bevy_rapier3d::prelude::*;
+
+use crate::{update_grabbables, Grabbable};
+
+const FLASHLIGHT_SET: &str = "flashlight";
+const TOGGLE: &str = "toggle";
+
+/// A flashlight on the workbench, while it's held the triggers toggle it instead of doing
+/// whatever they do in the `oculus_input` set
+pub struct FlashlightPlugin;
+
+impl Plugin for FlashlightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(XrSetup, setup_flashlight_actions)
+            .add_systems(Startup, spawn_flashlight)
+            .add_systems(
+                Update,
+                (
+                    update_flashlight_context.after(update_grabbables),
+                    toggle_flashlight,
+                ),
+            );
+    }
+}
+
+#[derive(Component)]
+struct Flashlight;
+
+fn setup_flashlight_actions(mut action_sets: ResMut<SetupActionSets>) {
+    // above the oculus_input set, so the triggers only toggle the flashlight while it's held
+    let set = action_sets.add_action_set(FLASHLIGHT_SET, "Flashlight".into(), 1);
+    set.start_disabled();
+    set.new_action(
+        TOGGLE,
+        "Toggle Flashlight".into(),
+        ActionType::Bool,
+        ActionHandednes::Single,
+    );
+    set.suggest_binding(
+        "/interaction_profiles/oculus/touch_controller",
+        &[
+            XrBinding::new(TOGGLE, "/user/hand/left/input/trigger/value"),
+            XrBinding::new(TOGGLE, "/user/hand/right/input/trigger/value"),
+        ],
+    );
+}
+
+fn spawn_flashlight(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn((
+            PbrBundle {
+                mesh: meshes.add(Cuboid::new(0.04, 0.04, 0.2)),
+                material: materials.add(StandardMaterial::from(Color::rgb(0.2, 0.2, 0.25))),
+                transform: Transform::from_xyz(0.3, 1.0, 0.0),
+                ..default()
+            },
+            RigidBody::Dynamic,
+            Collider::cuboid(0.02, 0.02, 0.1),
+            ColliderDebugColor(Color::hsl(50.0, 1.0, 0.5)),
+            XRInteractable,
+            XRInteractableState::default(),
+            Grabbable,
+            Touched(false),
+            Flashlight,
+        ))
+        .with_children(|parent| {
+            parent.spawn(SpotLightBundle {
+                spot_light: SpotLight {
+                    intensity: 200_000.0,
+                    range: 10.0,
+                    outer_angle: 0.4,
+                    inner_angle: 0.3,
+                    shadows_enabled: true,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, -0.1),
+                visibility: Visibility::Hidden,
+                ..default()
+            });
+        });
+}
+
+/// The flashlight's action set is enabled while a hand holds it
+fn update_flashlight_context(
+    mut commands: Commands,
+    flashlights: Query<(Entity, Has<XrInputContext>), With<Flashlight>>,
+    selections: Query<&XRSelection>,
+) {
+    for (flashlight, has_context) in &flashlights {
+        let held = selections
+            .iter()
+            .any(|selection| matches!(selection, XRSelection::Full(held) if *held == flashlight));
+        match (held, has_context) {
+            (true, false) => {
+                commands
+                    .entity(flashlight)
+                    .insert(XrInputContext::new(FLASHLIGHT_SET));
+            }
+            (false, true) => {
+                commands.entity(flashlight).remove::<XrInputContext>();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn toggle_flashlight(
+    buttons: Res<ButtonInput<XrButton>>,
+    flashlights: Query<&Children, With<Flashlight>>,
+    mut lights: Query<&mut Visibility, With<SpotLight>>,
+) {
+    if !buttons.just_pressed(XrButton::new(FLASHLIGHT_SET, TOGGLE)) {
+        return;
+    }
+    for children in &flashlights {
+        let mut lights = lights.iter_many_mut(children);
+        while let Some(mut visibility) = lights.fetch_next() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use bevy::diagnostic::{DiagnosticPath, DiagnosticsStore};
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy_oxr::latency::{XrInputLatency, XrLatencyPlugin};
+use bevy_oxr::layers::{XrCompositionLayer, XrCompositionLayerBundle};
+use bevy_oxr::xr_init::xr_only;
+
+/// Shows the rolling latency stats on a panel next to the player
+pub struct LatencyPanelPlugin;
+
+impl Plugin for LatencyPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(XrLatencyPlugin)
+            .add_systems(Startup, spawn_latency_panel)
+            .add_systems(Update, update_latency_panel.run_if(xr_only()));
+    }
+}
+
+#[derive(Component)]
+struct LatencyText;
+
+fn spawn_latency_panel(mut commands: Commands) {
+    let camera = commands
+        .spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    is_active: false,
+                    clear_color: ClearColorConfig::Custom(Color::rgba(0.0, 0.0, 0.0, 0.6)),
+                    ..default()
+                },
+                ..default()
+            },
+            RenderLayers::layer(3),
+        ))
+        .id();
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    padding: UiRect::all(Val::Px(16.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            TargetCamera(camera),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 28.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                LatencyText,
+            ));
+        });
+    commands.spawn(XrCompositionLayerBundle::new(
+        XrCompositionLayer::quad(Vec2::new(0.4, 0.2), UVec2::new(512, 256)).with_camera(camera),
+        Transform::from_xyz(-0.8, 1.2, -1.0).looking_to(Vec3::new(0.6, 0.0, -1.0), Vec3::Y),
+    ));
+}
+
+fn update_latency_panel(
+    diagnostics: Res<DiagnosticsStore>,
+    mut latencies: EventReader<XrInputLatency>,
+    mut last_press: Local<Option<Duration>>,
+    mut texts: Query<&mut Text, With<LatencyText>>,
+) {
+    if let Some(latency) = latencies.read().filter(|latency| latency.pressed).last() {
+        *last_press = Some(latency.change_to_display());
+    }
+    let stat = |path: &DiagnosticPath| {
+        let Some(diagnostic) = diagnostics.get(path) else {
+            return "-".to_string();
+        };
+        let max = diagnostic.values().copied().reduce(f64::max);
+        match (diagnostic.average(), max) {
+            (Some(average), Some(max)) => format!("{:.1} ms avg, {:.1} ms max", average, max),
+            _ => "-".to_string(),
+        }
+    };
+    let text = format!(
+        "motion to photon: {}\nbutton to photon: {}\nbutton to sync: {}\nsubmit to photon: {}\nlast press: {}",
+        stat(&XrLatencyPlugin::MOTION_TO_PHOTON),
+        stat(&XrLatencyPlugin::BUTTON_TO_PHOTON),
+        stat(&XrLatencyPlugin::BUTTON_TO_SYNC),
+        stat(&XrLatencyPlugin::SUBMIT_TO_PHOTON),
+        last_press.map_or("-".to_string(), |latency| format!(
+            "{:.1} ms",
+            latency.as_secs_f64() * 1000.0
+        )),
+    );
+    for mut latency_text in &mut texts {
+        latency_text.sections[0].value.clone_from(&text);
+    }
+}
@@ -1,3 +1,6 @@
+mod distance_grab;
+mod flashlight;
+mod latency_panel;
 mod setup;
 
 use std::time::Duration;
@@ -5,41 +8,53 @@ use std::time::Duration;
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     ecs::schedule::ScheduleLabel,
-    log::info,
+    log::{info, warn},
     math::primitives::{Capsule3d, Cuboid},
     prelude::{
-        bevy_main, default, App, Assets, Color, Commands, Component, Entity, Event, EventReader,
-        EventWriter, FixedUpdate, GlobalTransform, IntoSystemConfigs, IntoSystemSetConfigs, Mesh,
-        PbrBundle, PostUpdate, Query, Res, ResMut, Resource, Schedule, SpatialBundle,
-        StandardMaterial, Startup, Transform, Update, Vec3, With, Without, World,
+        bevy_main, default, Added, App, Assets, Camera3dBundle, Color, Commands, Component,
+        Direction3d, Entity, Event, EventReader, EventWriter, FixedUpdate, Gizmos, GlobalTransform,
+        IntoSystemConfigs, IntoSystemSetConfigs, Local, Mesh, PbrBundle, PositionType, PostUpdate,
+        Query, Res, ResMut, Resource, Schedule, SpatialBundle, StandardMaterial, Startup, Style,
+        TextBundle, TextStyle, Transform, Update, Val, Vec3, With, Without, World,
     },
     render::mesh::Meshable,
     time::{Fixed, Time, Timer, TimerMode},
     transform::TransformSystem,
 };
 use bevy_oxr::{
+    capture::{CaptureXrFrame, XrFrameCaptured},
+    comfort_vignette::XrComfortVignettePlugin,
     graphics::{extensions::XrExtensions, XrAppInfo},
     input::XrInput,
-    resources::{XrFrameState, XrSession},
-    xr_init::xr_only,
+    performance_hud::XrPerformanceHudPlugin,
+    resources::{XrFrameState, XrSession, XrViews},
+    simulator::XrSimulator,
+    xr_init::{xr_only, xr_unavailable_only, XrStatus, XrUnavailableReason},
     xr_input::{
         actions::XrActionSets,
         debug_gizmos::OpenXrDebugRenderer,
         hands::common::{HandInputDebugRenderer, HandResource, HandsResource},
         hands::HandBone,
+        haptics::XrHapticEvent,
         interactions::{
             draw_interaction_gizmos, draw_socket_gizmos, interactions, socket_interactions,
             update_interactable_states, InteractionEvent, Touched, XRDirectInteractor,
             XRInteractable, XRInteractableState, XRInteractorState, XRSelection,
         },
         oculus_touch::OculusController,
-        prototype_locomotion::{proto_locomotion, PrototypeLocomotionConfig},
-        trackers::{OpenXRController, OpenXRLeftController, OpenXRRightController, OpenXRTracker},
-        Hand,
+        prototype_locomotion::{proto_locomotion, PrototypeLocomotionConfig, RotationType},
+        tracked_controllers::{XrController, XrControllerInput, XrControllerPlugin},
+        trackers::{XrAimPose, XrTrackingRoot},
+        turning::{XrTurnInput, XrTurnMode, XrTurnSettings, XrTurningPlugin},
+        xr_camera::Eye,
+        Hand, Vec3Conv,
     },
     DefaultXrPlugins,
 };
 
+use crate::distance_grab::DistanceGrabPlugin;
+use crate::flashlight::FlashlightPlugin;
+use crate::latency_panel::LatencyPanelPlugin;
 use crate::setup::setup_scene;
 use bevy_rapier3d::prelude::*;
 
@@ -51,6 +66,14 @@ pub fn main() {
     let mut app = App::new();
     let mut xr_extensions = XrExtensions::default();
     xr_extensions.enable_fb_passthrough();
+    //set BEVY_OXR_BACKEND=d3d12 to compare the rendering of the backends
+    let mut xr_plugins = DefaultXrPlugins::default();
+    if let Ok(backend) = std::env::var("BEVY_OXR_BACKEND") {
+        match backend.parse() {
+            Ok(backend) => xr_plugins.backend_preference = vec![backend],
+            Err(err) => warn!("{}", err),
+        }
+    }
 
     app
         //lets get the usual diagnostic stuff added
@@ -62,19 +85,39 @@ pub fn main() {
             app_info: XrAppInfo {
                 name: "Bevy OXR Demo".into(),
             },
-            ..Default::default()
+            //the gpu time of the eyes is logged with the other diagnostics
+            gpu_timing: true,
+            ..xr_plugins
         })
         //lets add the debug renderer for the controllers
         .add_plugins(OpenXrDebugRenderer)
+        //and a panel with the input latency
+        .add_plugins(LatencyPanelPlugin)
         //rapier goes here
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::default().with_default_system_setup(false))
         // .add_plugins(RapierDebugRenderPlugin::default())
         //lets setup the starting scene
         .add_systems(Startup, setup_scene)
-        .add_systems(Startup, spawn_controllers_example) //you need to spawn controllers or it crashes TODO:: Fix this
+        .add_plugins(XrControllerPlugin)
+        .add_systems(Update, setup_controller_interactors)
         //add locomotion
         .add_systems(Update, proto_locomotion.run_if(xr_only()))
-        .insert_resource(PrototypeLocomotionConfig::default())
+        .add_systems(Update, teleport.run_if(xr_only()).after(proto_locomotion))
+        .insert_resource(PrototypeLocomotionConfig {
+            rotation_type: RotationType::Disabled,
+            ..default()
+        })
+        //turn with the right thumbstick
+        .add_plugins(XrTurningPlugin)
+        .insert_resource(XrTurnSettings {
+            input: XrTurnInput::Thumbstick(Hand::Right),
+            mode: XrTurnMode::Snap { degrees: 45.0 },
+            ..default()
+        })
+        //darken the periphery while moving with the stick
+        .add_plugins(XrComfortVignettePlugin)
+        //frame times and dropped frames in front of the head
+        .add_plugins(XrPerformanceHudPlugin::default())
         //lets add the interaction systems
         .add_event::<InteractionEvent>()
         .add_systems(Update, prototype_interaction_input.run_if(xr_only()))
@@ -86,6 +129,10 @@ pub fn main() {
         )
         //add the grabbable system
         .add_systems(Update, update_grabbables.after(update_interactable_states))
+        //a flashlight that takes over the triggers while it's held
+        .add_plugins(FlashlightPlugin)
+        //balls out of reach to pull over and throw
+        .add_plugins(DistanceGrabPlugin)
         //draw the interaction gizmos
         .add_systems(
             Update,
@@ -94,6 +141,7 @@ pub fn main() {
                 .after(update_interactable_states),
         )
         .add_systems(Update, draw_socket_gizmos.after(update_interactable_states))
+        .add_systems(Update, draw_aim_pointers.run_if(xr_only()))
         //add our cube spawning system
         .add_event::<SpawnCubeRequest>()
         .insert_resource(SpawnCubeTimer(Timer::from_seconds(
@@ -104,6 +152,8 @@ pub fn main() {
         .add_systems(Update, cube_spawner.after(request_cube_spawn))
         //test capsule
         .add_systems(Startup, spawn_capsule)
+        //fall back to a window camera without a headset
+        .add_systems(Startup, show_vr_not_detected.run_if(xr_unavailable_only()))
         //physics hands
         // .add_plugins(OpenXrHandInput)
         .add_plugins(HandInputDebugRenderer)
@@ -118,7 +168,10 @@ pub fn main() {
             left: Timer::from_seconds(0.25, TimerMode::Once),
             right: Timer::from_seconds(0.25, TimerMode::Once),
         })
-        .add_systems(Update, watch_ghost_timers.before(handle_ghost_hand_events));
+        .add_systems(Update, watch_ghost_timers.before(handle_ghost_hand_events))
+        //press y to save a screenshot of both eyes
+        .add_systems(Update, capture_on_y_button.run_if(xr_only()))
+        .add_systems(Update, save_captured_frames);
 
     //configure rapier sets
     let mut physics_schedule = Schedule::new(PhysicsSchedule);
@@ -179,29 +232,78 @@ fn configure_physics(mut rapier_config: ResMut<RapierConfiguration>) {
     }
 }
 
-fn spawn_controllers_example(mut commands: Commands) {
-    //left hand
-    commands.spawn((
-        OpenXRLeftController,
-        OpenXRController,
-        OpenXRTracker,
-        SpatialBundle::default(),
-        XRDirectInteractor,
-        XRInteractorState::default(),
-        XRSelection::default(),
-        Hand::Left,
-    ));
-    //right hand
-    commands.spawn((
-        OpenXRRightController,
-        OpenXRController,
-        OpenXRTracker,
-        SpatialBundle::default(),
-        XRDirectInteractor,
-        XRInteractorState::default(),
-        XRSelection::default(),
-        Hand::Right,
-    ));
+fn setup_controller_interactors(
+    mut commands: Commands,
+    controllers: Query<(Entity, &XrController), Added<XrController>>,
+) {
+    for (entity, controller) in &controllers {
+        commands.entity(entity).insert((
+            XRDirectInteractor,
+            XRInteractorState::default(),
+            XRSelection::default(),
+            controller.hand,
+        ));
+    }
+}
+
+/// The aim pose points where the controller points, unlike the grip pose the controllers follow
+fn draw_aim_pointers(mut gizmos: Gizmos, aim_poses: Query<&GlobalTransform, With<XrAimPose>>) {
+    for aim_pose in &aim_poses {
+        gizmos.ray(
+            aim_pose.translation(),
+            aim_pose.forward() * 2.0,
+            Color::WHITE,
+        );
+    }
+}
+
+/// How far away from the controller the teleport can aim
+const TELEPORT_RANGE: f32 = 10.0;
+
+/// Hold B to aim at the floor with the right controller, release it to teleport there
+fn teleport(
+    mut gizmos: Gizmos,
+    mut target: Local<Option<Vec3>>,
+    controllers: Query<(&XrController, &XrControllerInput)>,
+    aim_poses: Query<(&XrAimPose, &GlobalTransform)>,
+    views: Res<XrViews>,
+    mut root: Query<&mut Transform, With<XrTrackingRoot>>,
+    mut haptics: EventWriter<XrHapticEvent>,
+) {
+    let aiming = controllers
+        .iter()
+        .any(|(controller, input)| controller.hand == Hand::Right && input.secondary_button);
+    if !aiming {
+        let Some(target) = target.take() else {
+            return;
+        };
+        let (Some(view), Ok(mut root)) = (views.first(), root.get_single_mut()) else {
+            return;
+        };
+        XrTrackingRoot::teleport_head_to(&mut root, view.pose.position.to_vec3(), target);
+        haptics.send(XrHapticEvent::new(Hand::Right, 0.5, Duration::ZERO));
+        return;
+    }
+    let Some((_, aim_pose)) = aim_poses.iter().find(|(aim, _)| aim.0 == Hand::Right) else {
+        return;
+    };
+    //intersect the pointer with the floor of the tracking space
+    let floor = root
+        .get_single()
+        .map(|root| root.translation.y)
+        .unwrap_or(0.0);
+    let origin = aim_pose.translation();
+    let direction = aim_pose.forward();
+    let distance = (floor - origin.y) / direction.y;
+    if direction.y >= 0.0 || distance > TELEPORT_RANGE {
+        *target = None;
+        gizmos.ray(origin, direction * TELEPORT_RANGE, Color::RED);
+        return;
+    }
+    let hit = origin + direction * distance;
+    gizmos.line(origin, hit, Color::CYAN);
+    gizmos.circle(hit, Direction3d::Y, 0.3, Color::CYAN);
+    *target = Some(hit);
 }
 
 fn spawn_capsule(
@@ -548,6 +650,86 @@ fn request_cube_spawn(
     }
 }
 
+fn capture_on_y_button(
+    oculus_controller: Res<OculusController>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    session: Res<XrSession>,
+    action_sets: Res<XrActionSets>,
+    mut writer: EventWriter<CaptureXrFrame>,
+    mut was_pressed: Local<bool>,
+) {
+    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let pressed = controller.y_button();
+    if pressed && !*was_pressed {
+        writer.send(CaptureXrFrame);
+    }
+    *was_pressed = pressed;
+}
+
+fn save_captured_frames(mut captured: EventReader<XrFrameCaptured>, mut count: Local<u32>) {
+    for frame in captured.read() {
+        for (eye, name) in [(Eye::Left, "left"), (Eye::Right, "right")] {
+            let path = format!("xr_capture_{}_{}.png", *count, name);
+            let result = frame
+                .to_image(eye)
+                .try_into_dynamic()
+                .map_err(|err| err.to_string())
+                .and_then(|image| image.save(&path).map_err(|err| err.to_string()));
+            match result {
+                Ok(()) => info!("Saved {}", path),
+                Err(err) => warn!("Unable to save {}: {}", path, err),
+            }
+        }
+        *count += 1;
+    }
+}
+
+/// Without a headset the scene is shown through a regular camera on the window,
+/// unless the simulator brings its own
+fn show_vr_not_detected(
+    mut commands: Commands,
+    status: Res<XrStatus>,
+    simulator: Option<Res<XrSimulator>>,
+) {
+    if simulator.is_none() {
+        commands.spawn(Camera3dBundle {
+            transform: Transform::from_xyz(0.0, 1.6, 3.0)
+                .looking_at(Vec3::new(0.0, 0.8, 0.0), Vec3::Y),
+            ..default()
+        });
+    }
+    let reason = match *status {
+        XrStatus::Unavailable(XrUnavailableReason::Simulated) => {
+            "simulating one, move with WASD and look around with the right mouse button"
+        }
+        XrStatus::Unavailable(XrUnavailableReason::LoaderMissing) => "no OpenXR loader was found",
+        XrStatus::Unavailable(XrUnavailableReason::RuntimeUnavailable) => {
+            "the OpenXR runtime isn't running"
+        }
+        XrStatus::Unavailable(XrUnavailableReason::FormFactorUnavailable) => {
+            "no headset is connected"
+        }
+        _ => "the OpenXR runtime failed to start",
+    };
+    commands.spawn(
+        TextBundle::from_section(
+            format!("VR not detected: {}", reason),
+            TextStyle {
+                font_size: 32.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        }),
+    );
+}
+
 fn cube_spawner(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -574,47 +756,18 @@ fn cube_spawner(
     }
 }
 
-//TODO: find a real place for this
 fn prototype_interaction_input(
-    oculus_controller: Res<OculusController>,
-    frame_state: Res<XrFrameState>,
-    xr_input: Res<XrInput>,
-    session: Res<XrSession>,
-    mut right_interactor_query: Query<
-        &mut XRInteractorState,
-        (
-            With<XRDirectInteractor>,
-            With<OpenXRRightController>,
-            Without<OpenXRLeftController>,
-        ),
-    >,
-    mut left_interactor_query: Query<
-        &mut XRInteractorState,
-        (
-            With<XRDirectInteractor>,
-            With<OpenXRLeftController>,
-            Without<OpenXRRightController>,
-        ),
+    mut interactor_query: Query<
+        (&XrControllerInput, &mut XRInteractorState),
+        (With<XRDirectInteractor>, With<XrController>),
     >,
-    action_sets: Res<XrActionSets>,
 ) {
-    //get controller
-    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
-    //get controller triggers
-    let left_trigger = controller.trigger(Hand::Left);
-    let right_trigger = controller.trigger(Hand::Right);
-    //get the interactors and do state stuff
-    let mut left_state = left_interactor_query.single_mut();
-    if left_trigger > 0.8 {
-        *left_state = XRInteractorState::Selecting;
-    } else {
-        *left_state = XRInteractorState::Idle;
-    }
-    let mut right_state = right_interactor_query.single_mut();
-    if right_trigger > 0.8 {
-        *right_state = XRInteractorState::Selecting;
-    } else {
-        *right_state = XRInteractorState::Idle;
+    for (input, mut state) in &mut interactor_query {
+        //grab with the grip, held tools can use the trigger
+        *state = match input.squeeze > 0.8 {
+            true => XRInteractorState::Selecting,
+            false => XRInteractorState::Idle,
+        };
     }
 }
 
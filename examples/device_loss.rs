@@ -0,0 +1,101 @@
+//! Recovering from a lost render device. D simulates a loss, and so does a timer every 20 seconds
+//! so it works with the headset on. The cube turns red while the session is started again with
+//! new swapchains, and white once it's back.
+
+use bevy::prelude::*;
+use bevy_oxr::device_loss::{XrDeviceLoss, XrDeviceLost};
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::session_recovery::XrRecoveryEvent;
+use bevy_oxr::DefaultXrPlugins;
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            app_info: XrAppInfo {
+                name: "Bevy OXR Device Loss Example".into(),
+            },
+            ..default()
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, (simulate_loss, show_recovery))
+        .run();
+}
+
+#[derive(Component)]
+struct StatusCube;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Plane3d::default().mesh().size(5.0, 5.0)),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3)),
+        ..default()
+    });
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Cuboid::new(0.2, 0.2, 0.2)),
+            material: materials.add(Color::WHITE),
+            transform: Transform::from_xyz(0.0, 1.2, -1.0),
+            ..default()
+        },
+        StatusCube,
+    ));
+    commands.spawn(PointLightBundle {
+        point_light: PointLight {
+            intensity: 1_500_000.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+}
+
+fn simulate_loss(
+    loss: Res<XrDeviceLoss>,
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time<Real>>,
+    mut timer: Local<Option<Timer>>,
+) {
+    let timer = timer.get_or_insert_with(|| Timer::from_seconds(20.0, TimerMode::Repeating));
+    if timer.tick(time.delta()).just_finished() || keys.just_pressed(KeyCode::KeyD) {
+        loss.simulate("simulated by the example");
+    }
+}
+
+fn show_recovery(
+    mut lost: EventReader<XrDeviceLost>,
+    mut recovery: EventReader<XrRecoveryEvent>,
+    cubes: Query<&Handle<StandardMaterial>, With<StatusCube>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mut color = None;
+    for event in lost.read() {
+        warn!(
+            "recovering the graphics device: {} (simulated: {})",
+            event.reason, event.simulated
+        );
+        color = Some(Color::RED);
+    }
+    for event in recovery.read() {
+        info!("{:?}", event);
+        match event {
+            XrRecoveryEvent::Recovered => color = Some(Color::WHITE),
+            XrRecoveryEvent::GaveUp => error!("restart the app to get back to VR"),
+            _ => {}
+        }
+    }
+    let Some(color) = color else {
+        return;
+    };
+    for handle in &cubes {
+        if let Some(material) = materials.get_mut(handle) {
+            material.base_color = color;
+        }
+    }
+}
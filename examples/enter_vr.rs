@@ -0,0 +1,147 @@
+//! Starts as a regular window app, the button enters VR and leaves it again.
+//! The session is created and destroyed every time, the app keeps running in between.
+
+use bevy::prelude::*;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::xr_init::{
+    EndXrSession, ExitAppOnSessionExit, StartSessionOnStartup, StartXrSession, XrStatus,
+};
+use bevy_oxr::xr_input::xr_camera::XrFlatscreenCamera;
+use bevy_oxr::DefaultXrPlugins;
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    App::new()
+        .add_plugins(
+            DefaultXrPlugins {
+                app_info: XrAppInfo {
+                    name: "Bevy OXR Enter VR Example".into(),
+                },
+                ..default()
+            }
+            .build()
+            .disable::<StartSessionOnStartup>(),
+        )
+        .insert_resource(ExitAppOnSessionExit::Never)
+        .add_systems(Startup, setup)
+        .add_systems(Update, (toggle_vr, update_button_text))
+        .run();
+}
+
+#[derive(Component)]
+struct VrButton;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Plane3d::default().mesh().size(5.0, 5.0)),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3)),
+        ..default()
+    });
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Cuboid::new(0.2, 0.2, 0.2)),
+        material: materials.add(Color::rgb(0.8, 0.7, 0.6)),
+        transform: Transform::from_xyz(0.0, 1.2, -1.0),
+        ..default()
+    });
+    commands.spawn(PointLightBundle {
+        point_light: PointLight {
+            intensity: 1500.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+    commands.spawn((
+        Camera3dBundle {
+            transform: Transform::from_xyz(0.0, 1.6, 2.0)
+                .looking_at(Vec3::new(0.0, 1.0, -1.0), Vec3::Y),
+            ..default()
+        },
+        XrFlatscreenCamera,
+    ));
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                padding: UiRect::all(Val::Px(16.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::all(Val::Px(12.0)),
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                    VrButton,
+                ))
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 32.0,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ));
+                });
+        });
+}
+
+fn toggle_vr(
+    status: Res<XrStatus>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<VrButton>)>,
+    mut start: EventWriter<StartXrSession>,
+    mut end: EventWriter<EndXrSession>,
+) {
+    if !buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+    match *status {
+        XrStatus::Disabled => {
+            start.send_default();
+        }
+        XrStatus::Enabled => {
+            end.send_default();
+        }
+        _ => {}
+    }
+}
+
+fn update_button_text(
+    status: Res<XrStatus>,
+    buttons: Query<&Children, With<VrButton>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !status.is_changed() {
+        return;
+    }
+    let label = match *status {
+        XrStatus::Disabled => "Play in VR",
+        XrStatus::Enabled => "Exit VR",
+        XrStatus::Enabling | XrStatus::Disabling => "...",
+        XrStatus::Unavailable(_) => "VR not available",
+    };
+    for children in &buttons {
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = label.into();
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Rendering into the eyes without cameras or the render graph, with plain wgpu calls through
+//! [`XrFrameImages`]. A compute shader fills a buffer with a gradient per eye, which is then
+//! copied into the array layers of the swapchain image. Swapchain images can't be bound as
+//! storage textures, so a renderer would usually copy or draw its output into them like this.
+
+use bevy::prelude::*;
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::{Render, RenderApp};
+use bevy_oxr::external_rendering::{XrExternalRenderingPlugin, XrFrameImages};
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::xr_init::{xr_only, XrBeginFrame, XrEndFrame};
+use bevy_oxr::DefaultXrPlugins;
+
+const SHADER: &str = r"
+struct Params {
+    width: u32,
+    height: u32,
+    row_pixels: u32,
+    bgra: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> pixels: array<u32>;
+
+@compute @workgroup_size(8, 8, 1)
+fn clear(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.width || id.y >= params.height {
+        return;
+    }
+    // the left eye is red, the right one green, both get brighter to the right
+    var color = select(vec3<f32>(0.1, 0.6, 0.2), vec3<f32>(0.8, 0.2, 0.1), id.z == 0u);
+    color *= 0.25 + 0.75 * f32(id.x) / f32(params.width);
+    var rgba = vec4<f32>(color, 1.0);
+    if params.bgra == 1u {
+        rgba = rgba.bgra;
+    }
+    pixels[(id.z * params.height + id.y) * params.row_pixels + id.x] = pack4x8unorm(rgba);
+}
+";
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    let mut app = App::new();
+    app.add_plugins(DefaultXrPlugins {
+        app_info: XrAppInfo {
+            name: "Bevy OXR External Renderer Example".into(),
+        },
+        ..default()
+    })
+    .add_plugins(XrExternalRenderingPlugin);
+
+    app.sub_app_mut(RenderApp).add_systems(
+        Render,
+        clear_eyes
+            .run_if(xr_only())
+            .after(XrBeginFrame)
+            .before(XrEndFrame),
+    );
+
+    app.run();
+}
+
+/// What the renderer keeps between frames, the buffer is recreated when the resolution changes
+struct Clear {
+    pipeline: wgpu::ComputePipeline,
+    params: wgpu::Buffer,
+    pixels: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    size: UVec3,
+}
+
+impl Clear {
+    fn new(device: &wgpu::Device, size: UVec3) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("clear_eyes"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("clear_eyes"),
+            layout: None,
+            module: &shader,
+            entry_point: "clear",
+        });
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("clear_eyes_params"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let pixels = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("clear_eyes_pixels"),
+            size: (row_bytes(size.x) * size.y * size.z) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("clear_eyes"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: pixels.as_entire_binding(),
+                },
+            ],
+        });
+        Self {
+            pipeline,
+            params,
+            pixels,
+            bind_group,
+            size,
+        }
+    }
+}
+
+/// Rows of buffer to texture copies have to be aligned to 256 bytes
+fn row_bytes(width: u32) -> u32 {
+    (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+fn clear_eyes(
+    mut images: XrFrameImages,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    mut clear: Local<Option<Clear>>,
+) {
+    if !images.should_render() {
+        return;
+    }
+    if let Err(err) = images.acquire() {
+        warn!("{}", err);
+        return;
+    }
+    let image = match images.wait() {
+        Ok(image) => image,
+        Err(err) => {
+            warn!("{}", err);
+            return;
+        }
+    };
+    let bgra = match image.texture.format().remove_srgb_suffix() {
+        wgpu::TextureFormat::Rgba8Unorm => 0u32,
+        wgpu::TextureFormat::Bgra8Unorm => 1,
+        format => {
+            // the frame is ended without layers
+            warn_once!("Clearing {:?} swapchains isn't supported", format);
+            return;
+        }
+    };
+    let size = image.size.extend(image.texture.depth_or_array_layers());
+    let device = device.wgpu_device();
+    if clear.as_ref().map_or(true, |clear| clear.size != size) {
+        *clear = Some(Clear::new(device, size));
+    }
+    let clear = clear.as_ref().unwrap();
+    let params = [size.x, size.y, row_bytes(size.x) / 4, bgra];
+    queue.write_buffer(
+        &clear.params,
+        0,
+        &params
+            .iter()
+            .flat_map(|p| p.to_ne_bytes())
+            .collect::<Vec<_>>(),
+    );
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("clear_eyes"),
+    });
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("clear_eyes"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&clear.pipeline);
+        pass.set_bind_group(0, &clear.bind_group, &[]);
+        pass.dispatch_workgroups(size.x.div_ceil(8), size.y.div_ceil(8), size.z);
+    }
+    for layer in 0..size.z {
+        encoder.copy_buffer_to_texture(
+            wgpu::ImageCopyBuffer {
+                buffer: &clear.pixels,
+                layout: wgpu::ImageDataLayout {
+                    offset: (row_bytes(size.x) * size.y * layer) as u64,
+                    bytes_per_row: Some(row_bytes(size.x)),
+                    rows_per_image: Some(size.y),
+                },
+            },
+            wgpu::ImageCopyTexture {
+                texture: image.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+    // the runtime reads the image once it's released, so the work has to be submitted first
+    queue.submit([encoder.finish()]);
+    if let Err(err) = images.release() {
+        warn!("{}", err);
+    }
+}
@@ -0,0 +1,165 @@
+//! A render graph node that writes into the swapchain image of the right eye directly, through
+//! [`XrViewTargets`]. It runs after all cameras rendered and blends a red fullscreen triangle
+//! over the right eye, the left eye stays untouched.
+
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use bevy::render::graph::CameraDriverLabel;
+use bevy::render::render_graph::{
+    Node, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel,
+};
+use bevy::render::render_resource::RenderPipeline;
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::RenderApp;
+use bevy::utils::HashMap;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::resources::XrViewTargets;
+use bevy_oxr::xr_input::xr_camera::Eye;
+use bevy_oxr::DefaultXrPlugins;
+
+const SHADER: &str = r"
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fragment() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.0, 0.0, 0.25);
+}
+";
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    let mut app = App::new();
+    app.add_plugins(DefaultXrPlugins {
+        app_info: XrAppInfo {
+            name: "Bevy OXR Eye Tint Example".into(),
+        },
+        ..default()
+    })
+    .add_systems(Startup, setup);
+
+    let render_app = app.sub_app_mut(RenderApp);
+    let mut graph = render_app.world.resource_mut::<RenderGraph>();
+    graph.add_node(TintLabel, TintNode::default());
+    // the cameras are done rendering into the eyes after the camera driver
+    graph.add_node_edge(CameraDriverLabel, TintLabel);
+
+    app.run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Plane3d::default().mesh().size(5.0, 5.0)),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3)),
+        ..default()
+    });
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Cuboid::new(0.3, 0.3, 0.3)),
+        material: materials.add(Color::rgb(0.8, 0.7, 0.6)),
+        transform: Transform::from_xyz(0.0, 1.0, -1.0),
+        ..default()
+    });
+    commands.spawn(PointLightBundle {
+        point_light: PointLight {
+            intensity: 1_500_000.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct TintLabel;
+
+/// The swapchain format is only known once the session started, so the pipeline is created the
+/// first time the node runs with a format
+#[derive(Default)]
+struct TintNode {
+    pipelines: Mutex<HashMap<wgpu::TextureFormat, RenderPipeline>>,
+}
+
+impl TintNode {
+    fn pipeline(&self, device: &RenderDevice, format: wgpu::TextureFormat) -> RenderPipeline {
+        let mut pipelines = self.pipelines.lock().unwrap();
+        pipelines
+            .entry(format)
+            .or_insert_with(|| {
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("eye_tint"),
+                    source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+                });
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("eye_tint"),
+                    layout: None,
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vertex",
+                        buffers: &[],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fragment",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: default(),
+                    depth_stencil: None,
+                    multisample: default(),
+                    multiview: None,
+                })
+            })
+            .clone()
+    }
+}
+
+impl Node for TintNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        // only there between acquiring and releasing the swapchain image
+        let Some(target) = world
+            .get_resource::<XrViewTargets>()
+            .and_then(|targets| targets.get(Eye::Right))
+        else {
+            return Ok(());
+        };
+        let pipeline = self.pipeline(world.resource::<RenderDevice>(), target.format);
+        let mut pass =
+            render_context
+                .command_encoder()
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("eye_tint"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &target.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+        pass.set_pipeline(&pipeline);
+        pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
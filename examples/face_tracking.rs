@@ -0,0 +1,66 @@
+//! Drives the morph targets of a head model with the tracked face.
+//! Put a glTF head at `assets/head.glb` whose morph targets are named after
+//! [`FaceExpression::name`], like `jaw_drop`, targets with other names are left alone.
+
+use bevy::prelude::*;
+use bevy::render::mesh::morph::MorphWeights;
+use bevy_oxr::graphics::extensions::XrExtensions;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::xr_input::face_tracking::{FaceExpression, XrFaceExpressions, XrFaceTrackingPlugin};
+use bevy_oxr::DefaultXrPlugins;
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    let mut reqeusted_extensions = XrExtensions::default();
+    reqeusted_extensions.enable_face_tracking();
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            reqeusted_extensions,
+            app_info: XrAppInfo {
+                name: "Bevy OXR Face Tracking Example".into(),
+            },
+            ..default()
+        })
+        .add_plugins(XrFaceTrackingPlugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, apply_face_expressions)
+        .run();
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn(SceneBundle {
+        scene: asset_server.load("head.glb#Scene0"),
+        transform: Transform::from_xyz(0.0, 1.6, -0.5)
+            .with_rotation(Quat::from_rotation_y(std::f32::consts::PI)),
+        ..default()
+    });
+    commands.spawn(DirectionalLightBundle {
+        transform: Transform::from_xyz(1.0, 3.0, 2.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+}
+
+fn apply_face_expressions(
+    expressions: Option<Res<XrFaceExpressions>>,
+    meshes: Res<Assets<Mesh>>,
+    mut heads: Query<(&Handle<Mesh>, &mut MorphWeights)>,
+) {
+    let Some(expressions) = expressions.filter(|expressions| expressions.valid) else {
+        return;
+    };
+    for (mesh, mut weights) in &mut heads {
+        let Some(names) = meshes.get(mesh).and_then(|mesh| mesh.morph_target_names()) else {
+            continue;
+        };
+        for (name, weight) in names.iter().zip(weights.weights_mut()) {
+            if let Some(expression) = FaceExpression::ALL
+                .into_iter()
+                .find(|expression| expression.name() == name)
+            {
+                *weight = expressions.weight(expression);
+            }
+        }
+    }
+}
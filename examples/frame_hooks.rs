@@ -0,0 +1,104 @@
+//! Shifting the predicted display time with frame hooks, like compositing middleware does.
+//! The views are located and the frames ended a few milliseconds later than the runtime
+//! predicted, grabbing the right controller toggles the offset. With the offset the world
+//! should wobble slightly when turning the head, as it's rendered for the wrong time.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_oxr::frame_hooks::XrFrameHooks;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::xr_init::xr_only;
+use bevy_oxr::xr_input::tracked_controllers::{XrController, XrControllerInput};
+use bevy_oxr::xr_input::Hand;
+use bevy_oxr::DefaultXrPlugins;
+
+const OFFSET: Duration = Duration::from_millis(20);
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            app_info: XrAppInfo {
+                name: "Bevy OXR Frame Hooks Example".into(),
+            },
+            ..default()
+        })
+        .init_resource::<Offset>()
+        .add_systems(Startup, (setup, add_hooks))
+        .add_systems(Update, toggle_offset.run_if(xr_only()))
+        .run();
+}
+
+/// Shared with the hooks, which run in the main and the render world
+#[derive(Resource, Clone, Default)]
+struct Offset(Arc<AtomicBool>);
+
+impl Offset {
+    fn get(&self) -> Duration {
+        match self.0.load(Ordering::Relaxed) {
+            true => OFFSET,
+            false => Duration::ZERO,
+        }
+    }
+}
+
+fn add_hooks(hooks: Res<XrFrameHooks>, offset: Res<Offset>) {
+    // the views have to be located for the time the frame is shown at
+    let locate_offset = offset.clone();
+    hooks.on_locate_views(move |params| {
+        params.display_time = params.display_time + locate_offset.get();
+    });
+    let end_offset = offset.clone();
+    hooks.on_end_frame(move |params| {
+        params.display_time = params.display_time + end_offset.get();
+    });
+}
+
+fn toggle_offset(
+    controllers: Query<(&XrController, &XrControllerInput), Changed<XrControllerInput>>,
+    offset: Res<Offset>,
+    mut squeezed: Local<bool>,
+) {
+    for (controller, input) in &controllers {
+        if controller.hand != Hand::Right {
+            continue;
+        }
+        let now = input.squeeze > 0.5;
+        if now && !*squeezed {
+            let enabled = !offset.0.fetch_xor(true, Ordering::Relaxed);
+            info!("Display time offset: {:?}", enabled.then_some(OFFSET));
+        }
+        *squeezed = now;
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Plane3d::default().mesh().size(5.0, 5.0)),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3)),
+        ..default()
+    });
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Cuboid::new(0.2, 0.2, 0.2)),
+        material: materials.add(Color::rgb(0.8, 0.7, 0.6)),
+        transform: Transform::from_xyz(0.0, 1.5, -1.0),
+        ..default()
+    });
+    commands.spawn(PointLightBundle {
+        point_light: PointLight {
+            intensity: 1_500_000.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+}
@@ -0,0 +1,127 @@
+//! Checks what the app sees of scripted input, frame by frame. The script presses a button,
+//! swings the right controller in an arc, loses its tracking for 200 ms and releases the button.
+//! The app exits once the script played, and panics if the input wasn't seen at the frames of
+//! the script.
+
+use std::f32::consts::PI;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::input_script::XrInputScript;
+use bevy_oxr::recording::{XrReplay, XrReplayFinished};
+use bevy_oxr::startup::XrStartupPolicy;
+use bevy_oxr::xr_input::actions::XrButton;
+use bevy_oxr::xr_input::tracked_controllers::{XrController, XrControllerInput};
+use bevy_oxr::xr_input::Hand;
+use bevy_oxr::DefaultXrPlugins;
+
+const FIRE: XrButton = XrButton {
+    action_set: "gameplay",
+    action: "fire",
+};
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    let script = XrInputScript::new()
+        .press(1.0, FIRE)
+        .input(
+            Hand::Right,
+            1.0,
+            XrControllerInput {
+                trigger: 1.0,
+                trigger_touched: true,
+                ..default()
+            },
+        )
+        .controller_arc(
+            Hand::Right,
+            1.0,
+            2.0,
+            Vec3::new(0.0, 1.3, 0.0),
+            Vec3::Y,
+            PI / 2.0,
+        )
+        .lose_tracking(Hand::Right, 2.0, 0.2)
+        .release(2.5, FIRE)
+        .until(3.0);
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            app_info: XrAppInfo {
+                name: "Bevy OXR Input Script Example".into(),
+            },
+            startup_policy: XrStartupPolicy::Simulate,
+            ..default()
+        })
+        .add_plugins(script.replay(XrInputScript::FRAME_PERIOD))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            XrInputScript::FRAME_PERIOD,
+        ))
+        .init_resource::<Seen>()
+        .add_systems(Update, (record_edges, check_results).chain())
+        .run();
+}
+
+/// The frames the app saw the changes at
+#[derive(Resource, Default)]
+struct Seen {
+    pressed: Vec<usize>,
+    released: Vec<usize>,
+    trigger_pulled: Vec<usize>,
+    tracking_lost: Vec<usize>,
+    tracking_found: Vec<usize>,
+    controller_was_active: bool,
+}
+
+fn record_edges(
+    replay: Res<XrReplay>,
+    buttons: Res<ButtonInput<XrButton>>,
+    controllers: Query<(&XrController, Ref<XrControllerInput>)>,
+    mut seen: ResMut<Seen>,
+) {
+    let Some(frame) = replay.frame() else {
+        return;
+    };
+    if buttons.just_pressed(FIRE) {
+        seen.pressed.push(frame);
+    }
+    if buttons.just_released(FIRE) {
+        seen.released.push(frame);
+    }
+    for (controller, input) in &controllers {
+        if controller.hand != Hand::Right {
+            continue;
+        }
+        if input.is_changed() && input.trigger == 1.0 {
+            seen.trigger_pulled.push(frame);
+        }
+        match (seen.controller_was_active, controller.active) {
+            (true, false) => seen.tracking_lost.push(frame),
+            (false, true) if frame > 0 => seen.tracking_found.push(frame),
+            _ => {}
+        }
+        seen.controller_was_active = controller.active;
+    }
+}
+
+fn check_results(
+    mut finished: EventReader<XrReplayFinished>,
+    seen: Res<Seen>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if finished.read().count() == 0 {
+        return;
+    }
+    let frame = |time: f32| XrInputScript::frame_at(time, XrInputScript::FRAME_PERIOD);
+    assert_eq!(seen.pressed, [frame(1.0)], "the button is pressed once");
+    assert_eq!(seen.released, [frame(2.5)], "the button is released once");
+    // the input comes back with the tracking
+    assert_eq!(seen.trigger_pulled, [frame(1.0), frame(2.2)]);
+    assert_eq!(seen.tracking_lost, [frame(2.0)]);
+    assert_eq!(seen.tracking_found, [frame(2.2)]);
+    info!("The app saw the scripted input at the scripted frames");
+    exit.send(AppExit);
+}
@@ -0,0 +1,73 @@
+//! A latency test scene: a quad attached to the head and a quad fixed in the world.
+//! The head locked quad is placed with the head pose of the main world, while the views are
+//! located again right before rendering. Turning the head quickly makes the head locked quad lag
+//! behind by the difference, the world quad should stay perfectly still.
+
+use bevy::prelude::*;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::xr_input::trackers::OpenXRLeftEye;
+use bevy_oxr::DefaultXrPlugins;
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            app_info: XrAppInfo {
+                name: "Bevy OXR Latency Example".into(),
+            },
+            ..default()
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, attach_head_locked_quad)
+        .run();
+}
+
+#[derive(Resource)]
+struct QuadAssets {
+    mesh: Handle<Mesh>,
+    head_locked: Handle<StandardMaterial>,
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(Rectangle::new(0.2, 0.2));
+    commands.spawn(PbrBundle {
+        mesh: mesh.clone(),
+        material: materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            unlit: true,
+            ..default()
+        }),
+        transform: Transform::from_xyz(0.0, 1.5, -1.0),
+        ..default()
+    });
+    let head_locked = materials.add(StandardMaterial {
+        base_color: Color::rgba(1.0, 0.2, 0.2, 0.5),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+    commands.insert_resource(QuadAssets { mesh, head_locked });
+}
+
+/// The eye cameras are spawned when the session starts
+fn attach_head_locked_quad(
+    mut commands: Commands,
+    assets: Res<QuadAssets>,
+    eyes: Query<Entity, Added<OpenXRLeftEye>>,
+) {
+    for eye in &eyes {
+        commands.entity(eye).with_children(|eye| {
+            eye.spawn(PbrBundle {
+                mesh: assets.mesh.clone(),
+                material: assets.head_locked.clone(),
+                transform: Transform::from_xyz(0.0, 0.0, -1.0),
+                ..default()
+            });
+        });
+    }
+}
@@ -0,0 +1,197 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::view::RenderLayers;
+use bevy_oxr::graphics::extensions::XrExtensions;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::layers::{XrCompositionLayer, XrCompositionLayerBundle};
+use bevy_oxr::xr_init::xr_only;
+use bevy_oxr::DefaultXrPlugins;
+
+const TEST_TEXTURE_SIZE: UVec2 = UVec2::new(256, 128);
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    let mut reqeusted_extensions = XrExtensions::default();
+    reqeusted_extensions.enable_cylinder_layer();
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            reqeusted_extensions,
+            app_info: XrAppInfo {
+                name: "Bevy OXR Layers Example".into(),
+            },
+            ..default()
+        })
+        .add_systems(Startup, (setup, setup_cylinder))
+        .add_systems(
+            Update,
+            (move_panel, toggle_panel, stream_test_texture).run_if(xr_only()),
+        )
+        .run();
+}
+
+#[derive(Component)]
+struct Panel;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // plane
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Plane3d::new(Vec3::Y).mesh()),
+        material: materials.add(StandardMaterial::from(Color::rgb(0.3, 0.5, 0.3))),
+        ..default()
+    });
+    // light
+    commands.spawn(PointLightBundle {
+        point_light: PointLight {
+            intensity: 1500.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+
+    // the ui is rendered by its own camera into the swapchain of the quad layer
+    let ui_camera = commands
+        .spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    is_active: false,
+                    clear_color: ClearColorConfig::Custom(Color::rgba(0.1, 0.1, 0.1, 0.8)),
+                    ..default()
+                },
+                ..default()
+            },
+            RenderLayers::layer(1),
+        ))
+        .id();
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            TargetCamera(ui_camera),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Hello from a quad layer!\nPress space to hide me",
+                TextStyle {
+                    font_size: 48.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+    commands.spawn((
+        XrCompositionLayerBundle::new(
+            XrCompositionLayer::quad(Vec2::new(1.0, 0.5), UVec2::new(1024, 512))
+                .with_camera(ui_camera),
+            Transform::from_xyz(0.0, 1.5, -1.5),
+        ),
+        Panel,
+    ));
+}
+
+fn move_panel(time: Res<Time>, mut panels: Query<&mut Transform, With<Panel>>) {
+    for mut transform in &mut panels {
+        transform.translation.y = 1.5 + (time.elapsed_seconds() * 0.5).sin() * 0.2;
+    }
+}
+
+fn toggle_panel(keys: Res<ButtonInput<KeyCode>>, mut panels: Query<&mut Visibility, With<Panel>>) {
+    if !keys.just_pressed(KeyCode::Space) {
+        return;
+    }
+    for mut visibility in &mut panels {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+#[derive(Resource)]
+struct TestTexture(Handle<Image>);
+
+fn setup_cylinder(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let image = images.add(Image::new_fill(
+        Extent3d {
+            width: TEST_TEXTURE_SIZE.x,
+            height: TEST_TEXTURE_SIZE.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ));
+    let camera = commands
+        .spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    is_active: false,
+                    ..default()
+                },
+                ..default()
+            },
+            RenderLayers::layer(2),
+        ))
+        .id();
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(2048.0, 1024.0)),
+                ..default()
+            },
+            texture: image.clone(),
+            ..default()
+        },
+        RenderLayers::layer(2),
+    ));
+    commands.insert_resource(TestTexture(image));
+    // a curved screen 2m away, covering 90 degrees of the view
+    commands.spawn(XrCompositionLayerBundle::new(
+        XrCompositionLayer::cylinder(2.0, PI / 2.0, 2.0, UVec2::new(2048, 1024))
+            .with_camera(camera)
+            .with_sort_order(-1),
+        Transform::from_xyz(0.0, 1.5, 0.0),
+    ));
+}
+
+/// Writes a new frame of a scrolling test pattern into the texture shown on the cylinder
+fn stream_test_texture(
+    time: Res<Time>,
+    texture: Res<TestTexture>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Some(image) = images.get_mut(&texture.0) else {
+        return;
+    };
+    let offset = (time.elapsed_seconds() * 64.0) as u32;
+    for (i, pixel) in image.data.chunks_exact_mut(4).enumerate() {
+        let x = i as u32 % TEST_TEXTURE_SIZE.x + offset;
+        let y = i as u32 / TEST_TEXTURE_SIZE.x;
+        let checker = ((x / 16) + (y / 16)) % 2 == 0;
+        let bar = (x / 32) % 8;
+        pixel.copy_from_slice(&if checker {
+            [255, 255, 255, 255]
+        } else {
+            [(bar * 32) as u8, 64, 255 - (bar * 32) as u8, 255]
+        });
+    }
+}
@@ -0,0 +1,77 @@
+//! Runs as an overlay on top of another xr app, start that app first.
+//! Shows a spinning cube in front of the user that is only visible while the main app is.
+
+use bevy::prelude::*;
+use bevy_oxr::graphics::extensions::XrExtensions;
+use bevy_oxr::graphics::{XrAppInfo, XrSessionConfig};
+use bevy_oxr::overlay::{XrMainSessionVisibilityChanged, XrOverlaySettings};
+use bevy_oxr::DefaultXrPlugins;
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    let mut reqeusted_extensions = XrExtensions::default();
+    reqeusted_extensions.enable_overlay();
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            reqeusted_extensions,
+            app_info: XrAppInfo {
+                name: "Bevy OXR Overlay Example".into(),
+            },
+            session_config: XrSessionConfig {
+                overlay: Some(XrOverlaySettings { placement: 1 }),
+                ..default()
+            },
+            ..default()
+        })
+        // everything that isn't drawn lets the main app show through
+        .insert_resource(ClearColor(Color::NONE))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (spin_cube, follow_main_session_visibility))
+        .run();
+}
+
+#[derive(Component)]
+struct OverlayCube;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Cuboid::new(0.2, 0.2, 0.2)),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.8, 0.4, 0.1),
+                unlit: true,
+                ..default()
+            }),
+            transform: Transform::from_xyz(0.0, 1.5, -1.0),
+            ..default()
+        },
+        OverlayCube,
+    ));
+}
+
+fn spin_cube(time: Res<Time>, mut cubes: Query<&mut Transform, With<OverlayCube>>) {
+    for mut transform in &mut cubes {
+        transform.rotate_y(time.delta_seconds());
+    }
+}
+
+fn follow_main_session_visibility(
+    mut events: EventReader<XrMainSessionVisibilityChanged>,
+    mut cubes: Query<&mut Visibility, With<OverlayCube>>,
+) {
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    for mut visibility in &mut cubes {
+        *visibility = match event.visible {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+    }
+}
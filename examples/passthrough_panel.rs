@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy_oxr::graphics::extensions::XrExtensions;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::layers::{XrCompositionLayer, XrCompositionLayerBundle, XrLayerAlpha};
+use bevy_oxr::passthrough::EnablePassthroughStartup;
+use bevy_oxr::xr_init::xr_only;
+use bevy_oxr::DefaultXrPlugins;
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    let mut reqeusted_extensions = XrExtensions::default();
+    reqeusted_extensions.enable_fb_passthrough();
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            reqeusted_extensions,
+            app_info: XrAppInfo {
+                name: "Bevy OXR Passthrough Panel Example".into(),
+            },
+            ..default()
+        })
+        .add_plugins(EnablePassthroughStartup)
+        // the projection layer has to be transparent for the passthrough to show through
+        .insert_resource(ClearColor(Color::NONE))
+        .add_systems(Startup, setup)
+        .add_systems(Update, cycle_alpha.run_if(xr_only()))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // a cube on the floor, to see the panel composited on top of the projection layer
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Cuboid::new(0.2, 0.2, 0.2)),
+        material: materials.add(StandardMaterial::from(Color::rgb(0.8, 0.7, 0.6))),
+        transform: Transform::from_xyz(0.0, 0.1, -1.0),
+        ..default()
+    });
+    commands.spawn(PointLightBundle {
+        point_light: PointLight {
+            intensity: 1500.0,
+            ..default()
+        },
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+
+    // half transparent black, which is the same premultiplied or not
+    let ui_camera = commands
+        .spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    is_active: false,
+                    clear_color: ClearColorConfig::Custom(Color::rgba(0.0, 0.0, 0.0, 0.5)),
+                    ..default()
+                },
+                ..default()
+            },
+            RenderLayers::layer(1),
+        ))
+        .id();
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            TargetCamera(ui_camera),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 48.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                AlphaLabel,
+            ));
+        });
+    commands.spawn(XrCompositionLayerBundle::new(
+        XrCompositionLayer::quad(Vec2::new(1.0, 0.5), UVec2::new(1024, 512))
+            .with_camera(ui_camera)
+            .with_alpha(XrLayerAlpha::Premultiplied),
+        Transform::from_xyz(0.0, 1.5, -1.5),
+    ));
+}
+
+#[derive(Component)]
+struct AlphaLabel;
+
+/// Switches the blending of the panel with space, opaque hides the passthrough behind it
+/// and unpremultiplied darkens the edges of the text
+fn cycle_alpha(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut layers: Query<&mut XrCompositionLayer>,
+    mut labels: Query<&mut Text, With<AlphaLabel>>,
+) {
+    for mut layer in &mut layers {
+        if keys.just_pressed(KeyCode::Space) {
+            layer.alpha = match layer.alpha {
+                XrLayerAlpha::Premultiplied => XrLayerAlpha::Unpremultiplied,
+                XrLayerAlpha::Unpremultiplied => XrLayerAlpha::Opaque,
+                XrLayerAlpha::Opaque => XrLayerAlpha::Premultiplied,
+            };
+        }
+        for mut text in &mut labels {
+            text.sections[0].value = format!("{:?} alpha\nPress space to switch", layer.alpha);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! A stress scene with pipelined rendering enabled: a few thousand spinning cubes around the
+//! player, so the main and render world both have enough work to overlap.
+//! The frame timings are logged every second, the number of dropped frames should stay flat.
+
+use bevy::prelude::*;
+use bevy::time::common_conditions::on_timer;
+use bevy_oxr::frame_diagnostics::XrFrameDiagnostics;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::DefaultXrPlugins;
+use std::time::Duration;
+
+const CUBES_PER_SIDE: i32 = 16;
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            app_info: XrAppInfo {
+                name: "Bevy OXR Pipelined Stress Example".into(),
+            },
+            pipelined_rendering: true,
+            ..default()
+        })
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
+                spin_cubes,
+                log_frame_diagnostics.run_if(on_timer(Duration::from_secs(1))),
+            ),
+        )
+        .run();
+}
+
+#[derive(Component)]
+struct Spin(f32);
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(Cuboid::new(0.1, 0.1, 0.1));
+    let materials: Vec<_> = (0..8)
+        .map(|i| materials.add(Color::hsl(i as f32 * 45.0, 0.7, 0.5)))
+        .collect();
+    let half = CUBES_PER_SIDE / 2;
+    for x in -half..half {
+        for y in 0..CUBES_PER_SIDE {
+            for z in -half..half {
+                // leave room for the player
+                if x.abs() < 2 && z.abs() < 2 {
+                    continue;
+                }
+                let index = (x + y + z).rem_euclid(materials.len() as i32) as usize;
+                commands.spawn((
+                    PbrBundle {
+                        mesh: mesh.clone(),
+                        material: materials[index].clone(),
+                        transform: Transform::from_xyz(
+                            x as f32 * 0.4,
+                            y as f32 * 0.4,
+                            z as f32 * 0.4,
+                        ),
+                        ..default()
+                    },
+                    Spin(0.5 + index as f32 * 0.25),
+                ));
+            }
+        }
+    }
+    commands.spawn(DirectionalLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+}
+
+fn spin_cubes(time: Res<Time>, mut cubes: Query<(&mut Transform, &Spin)>) {
+    cubes.par_iter_mut().for_each(|(mut transform, spin)| {
+        transform.rotate_y(spin.0 * time.delta_seconds());
+    });
+}
+
+fn log_frame_diagnostics(diagnostics: Res<XrFrameDiagnostics>) {
+    info!(
+        "wait {:.2}ms, render {:.2}ms, begin to end {:.2}ms, {} of {} frames dropped",
+        diagnostics.wait_time.as_secs_f64() * 1000.0,
+        diagnostics.render_time.as_secs_f64() * 1000.0,
+        diagnostics.begin_to_end_time.as_secs_f64() * 1000.0,
+        diagnostics.dropped_frames,
+        diagnostics.frames,
+    );
+}
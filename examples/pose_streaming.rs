@@ -0,0 +1,160 @@
+//! Streams the tracked poses over UDP to the same app and shows them as a mirrored avatar in
+//! front of the player. The snapshots are sent at about 20 Hz with an irregular interval, the
+//! snapshot buffer smooths them out again. Any other transport works the same way, the crate
+//! only encodes the snapshots.
+
+use std::f32::consts::PI;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::tracked_snapshot::{XrSnapshotBuffer, XrSnapshotCapture, XrTrackedSnapshot};
+use bevy_oxr::DefaultXrPlugins;
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_nonblocking(true).unwrap();
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            app_info: XrAppInfo {
+                name: "Bevy OXR Pose Streaming Example".into(),
+            },
+            ..default()
+        })
+        .insert_resource(Loopback(socket))
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (send_snapshots, receive_snapshots, draw_avatar).chain(),
+        )
+        .run();
+}
+
+/// Sends to its own address
+#[derive(Resource)]
+struct Loopback(UdpSocket);
+
+/// The avatar shows the poses relative to its transform
+#[derive(Component)]
+struct RemoteAvatar;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Plane3d::default().mesh().size(5.0, 5.0)),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3)),
+        ..default()
+    });
+    commands.spawn(PointLightBundle {
+        point_light: PointLight {
+            intensity: 1_500_000.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+    // facing the player
+    commands.spawn((
+        SpatialBundle::from_transform(
+            Transform::from_xyz(0.0, 0.0, -1.5).with_rotation(Quat::from_rotation_y(PI)),
+        ),
+        XrSnapshotBuffer::default(),
+        RemoteAvatar,
+    ));
+}
+
+fn send_snapshots(
+    capture: XrSnapshotCapture,
+    socket: Res<Loopback>,
+    time: Res<Time<Real>>,
+    mut next: Local<Duration>,
+    mut sequence: Local<u32>,
+) {
+    if time.elapsed() < *next {
+        return;
+    }
+    // between 30 and 70 ms, like a busy network
+    *sequence = sequence.wrapping_add(1);
+    let jitter = sequence.wrapping_mul(2_654_435_761) % 40;
+    *next = time.elapsed() + Duration::from_millis(30 + jitter as u64);
+    let Some(snapshot) = capture.capture(*sequence) else {
+        return;
+    };
+    let socket = &socket.0;
+    if let Err(err) = socket
+        .local_addr()
+        .and_then(|address| socket.send_to(&snapshot.encode(), address))
+    {
+        warn!("Unable to send the snapshot: {}", err);
+    }
+}
+
+fn receive_snapshots(
+    socket: Res<Loopback>,
+    time: Res<Time<Real>>,
+    mut avatars: Query<&mut XrSnapshotBuffer>,
+) {
+    let mut bytes = [0; 2048];
+    while let Ok(len) = socket.0.recv(&mut bytes) {
+        let snapshot = match XrTrackedSnapshot::decode(&bytes[..len]) {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                warn!("Unable to decode the snapshot: {}", err);
+                continue;
+            }
+        };
+        for mut buffer in &mut avatars {
+            buffer.push(snapshot.clone(), time.elapsed());
+        }
+    }
+}
+
+fn draw_avatar(
+    mut gizmos: Gizmos,
+    time: Res<Time<Real>>,
+    avatars: Query<(&XrSnapshotBuffer, &GlobalTransform), With<RemoteAvatar>>,
+) {
+    for (buffer, avatar) in &avatars {
+        let Some(snapshot) = buffer.sample(time.elapsed()) else {
+            continue;
+        };
+        let to_world = |transform: Transform| avatar.mul_transform(transform).compute_transform();
+        if let Some(head) = snapshot.head {
+            let head = to_world(head.transform());
+            gizmos.cuboid(head.with_scale(Vec3::new(0.18, 0.1, 0.12)), Color::WHITE);
+        }
+        for controller in snapshot.controllers.iter().flatten() {
+            let controller = to_world(controller.transform());
+            gizmos.sphere(
+                controller.translation,
+                controller.rotation,
+                0.04,
+                Color::CYAN,
+            );
+            gizmos.ray(
+                controller.translation,
+                *controller.forward() * 0.1,
+                Color::CYAN,
+            );
+        }
+        for joints in snapshot.hands.iter().flatten() {
+            for joint in joints {
+                let joint_transform = to_world(joint.pose.transform());
+                gizmos.sphere(
+                    joint_transform.translation,
+                    joint_transform.rotation,
+                    joint.radius,
+                    Color::ORANGE,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Resizing the swapchain while the session runs. The swapchain follows the resolution the
+//! runtime recommends, and every few seconds it switches between half and the full recommended
+//! resolution, space does the same right away. A frame hook counts the submitted frames, so
+//! the log shows the frame loop going on at every size.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy_oxr::frame_hooks::XrFrameHooks;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::resources::XrResolution;
+use bevy_oxr::swapchain_resize::{ResizeXrSwapchain, XrViewConfigurationChanged};
+use bevy_oxr::xr_init::xr_only;
+use bevy_oxr::DefaultXrPlugins;
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            app_info: XrAppInfo {
+                name: "Bevy OXR Swapchain Resize Example".into(),
+            },
+            ..default()
+        })
+        .init_resource::<SubmittedFrames>()
+        .init_resource::<HalfResolution>()
+        .add_systems(Startup, (setup, count_submitted_frames))
+        .add_systems(
+            Update,
+            (follow_runtime, toggle_resolution, report_resize).run_if(xr_only()),
+        )
+        .run();
+}
+
+#[derive(Resource, Default)]
+struct HalfResolution(bool);
+
+/// Frames ended by the render world, written by the frame hook
+#[derive(Resource, Clone, Default)]
+struct SubmittedFrames(Arc<AtomicU32>);
+
+fn count_submitted_frames(hooks: Res<XrFrameHooks>, frames: Res<SubmittedFrames>) {
+    let frames = frames.0.clone();
+    hooks.on_end_frame(move |_| {
+        frames.fetch_add(1, Ordering::Relaxed);
+    });
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Plane3d::default().mesh().size(5.0, 5.0)),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3)),
+        ..default()
+    });
+    // thin stripes show the lower resolution best
+    let mesh = meshes.add(Cuboid::new(0.01, 1.0, 0.01));
+    let material = materials.add(Color::rgb(0.9, 0.9, 0.9));
+    for i in 0..20 {
+        commands.spawn(PbrBundle {
+            mesh: mesh.clone(),
+            material: material.clone(),
+            transform: Transform::from_xyz(-0.5 + i as f32 * 0.05, 1.0, -1.0),
+            ..default()
+        });
+    }
+    commands.spawn(PointLightBundle {
+        point_light: PointLight {
+            intensity: 1_500_000.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+}
+
+/// Resizes to the new recommended resolution, unless the example is running at half of it
+fn follow_runtime(
+    mut changed: EventReader<XrViewConfigurationChanged>,
+    mut resize: EventWriter<ResizeXrSwapchain>,
+    resolution: Res<XrResolution>,
+    half: Res<HalfResolution>,
+) {
+    let Some(event) = changed.read().last() else {
+        return;
+    };
+    if !half.0 && event.recommended_resolution != **resolution {
+        resize.send(ResizeXrSwapchain { resolution: None });
+    }
+}
+
+fn toggle_resolution(
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut since_toggle: Local<f32>,
+    mut half: ResMut<HalfResolution>,
+    mut resize: EventWriter<ResizeXrSwapchain>,
+    resolution: Res<XrResolution>,
+) {
+    *since_toggle += time.delta_seconds();
+    if *since_toggle < 5.0 && !keys.just_pressed(KeyCode::Space) {
+        return;
+    }
+    *since_toggle = 0.0;
+    half.0 = !half.0;
+    resize.send(ResizeXrSwapchain {
+        resolution: half.0.then_some(**resolution / 2),
+    });
+}
+
+fn report_resize(
+    resolution: Res<XrResolution>,
+    frames: Res<SubmittedFrames>,
+    mut last: Local<Option<(UVec2, u32)>>,
+) {
+    if !resolution.is_changed() {
+        return;
+    }
+    let submitted = frames.0.load(Ordering::Relaxed);
+    if let Some((size, at)) = *last {
+        info!(
+            "Submitted {} frames at {}, now rendering at {}",
+            submitted - at,
+            size,
+            **resolution
+        );
+    }
+    *last = Some((**resolution, submitted));
+}
@@ -0,0 +1,252 @@
+//! A settings menu on a panel in front of the player, point at the buttons with the controllers
+//! and pull the trigger to press them. Clicking the cube changes its color as well.
+
+use bevy::prelude::*;
+use bevy_oxr::graphics::XrAppInfo;
+use bevy_oxr::world_ui::{WorldSpaceUi, WorldSpaceUiFollow, WorldSpaceUiPlugin};
+use bevy_oxr::xr_input::pointer::{XrPointerEvent, XrPointerEventKind, XrPointerTarget};
+use bevy_oxr::xr_input::tracked_controllers::XrControllerPlugin;
+use bevy_oxr::DefaultXrPlugins;
+
+fn main() {
+    color_eyre::install().unwrap();
+
+    App::new()
+        .add_plugins(DefaultXrPlugins {
+            app_info: XrAppInfo {
+                name: "Bevy OXR World UI Example".into(),
+            },
+            ..default()
+        })
+        .add_plugins((XrControllerPlugin, WorldSpaceUiPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (press_buttons, click_cube, update_labels, spin_cube),
+        )
+        .run();
+}
+
+#[derive(Resource)]
+struct Settings {
+    spin: bool,
+    color: usize,
+    follow: bool,
+}
+
+const COLORS: [Color; 4] = [
+    Color::rgb(0.8, 0.7, 0.6),
+    Color::rgb(0.8, 0.2, 0.2),
+    Color::rgb(0.2, 0.7, 0.3),
+    Color::rgb(0.2, 0.4, 0.9),
+];
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum SettingButton {
+    Spin,
+    Color,
+    Follow,
+}
+
+#[derive(Component)]
+struct Cube;
+
+#[derive(Component)]
+struct Menu;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(Settings {
+        spin: true,
+        color: 0,
+        follow: false,
+    });
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Plane3d::default().mesh().size(5.0, 5.0)),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3)),
+        ..default()
+    });
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Cuboid::new(0.3, 0.3, 0.3)),
+            material: materials.add(COLORS[0]),
+            transform: Transform::from_xyz(0.8, 1.2, -1.5),
+            ..default()
+        },
+        Cube,
+        XrPointerTarget::Aabb,
+    ));
+    commands.spawn(PointLightBundle {
+        point_light: PointLight {
+            intensity: 1500.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+
+    let root = commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceEvenly,
+                ..default()
+            },
+            background_color: Color::rgba(0.1, 0.1, 0.1, 0.9).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Settings",
+                TextStyle {
+                    font_size: 48.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            for button in [
+                SettingButton::Spin,
+                SettingButton::Color,
+                SettingButton::Follow,
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(400.0),
+                                padding: UiRect::all(Val::Px(16.0)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        button,
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font_size: 32.0,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        })
+        .id();
+    commands.spawn((
+        SpatialBundle::from_transform(Transform::from_xyz(0.0, 1.3, -1.0)),
+        WorldSpaceUi::new(root, Vec2::new(0.6, 0.45), UVec2::new(600, 450))
+            .with_follow(WorldSpaceUiFollow::Billboard),
+        Menu,
+    ));
+}
+
+fn press_buttons(
+    mut settings: ResMut<Settings>,
+    buttons: Query<(&Interaction, &SettingButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            SettingButton::Spin => settings.spin = !settings.spin,
+            SettingButton::Color => settings.color = (settings.color + 1) % COLORS.len(),
+            SettingButton::Follow => settings.follow = !settings.follow,
+        }
+    }
+}
+
+fn click_cube(
+    mut settings: ResMut<Settings>,
+    mut events: EventReader<XrPointerEvent>,
+    cube: Query<(), With<Cube>>,
+) {
+    for event in events.read() {
+        if event.kind == XrPointerEventKind::Click && cube.contains(event.target) {
+            settings.color = (settings.color + 1) % COLORS.len();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_labels(
+    settings: Res<Settings>,
+    mut buttons: Query<(
+        Ref<Interaction>,
+        &SettingButton,
+        &Children,
+        &mut BackgroundColor,
+    )>,
+    mut texts: Query<&mut Text>,
+    cube: Query<&Handle<StandardMaterial>, With<Cube>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut menu: Query<&mut WorldSpaceUi, With<Menu>>,
+) {
+    for (interaction, button, children, mut background) in &mut buttons {
+        if interaction.is_changed() {
+            *background = match *interaction {
+                Interaction::Pressed => Color::rgb(0.35, 0.75, 0.35),
+                Interaction::Hovered => Color::rgb(0.25, 0.25, 0.25),
+                Interaction::None => Color::rgb(0.15, 0.15, 0.15),
+            }
+            .into();
+        }
+        if !settings.is_changed() {
+            continue;
+        }
+        let label = match button {
+            SettingButton::Spin => format!("Spin: {}", if settings.spin { "on" } else { "off" }),
+            SettingButton::Color => format!("Color: {}", settings.color + 1),
+            SettingButton::Follow => {
+                format!(
+                    "Follow head: {}",
+                    if settings.follow { "on" } else { "off" }
+                )
+            }
+        };
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = label.clone();
+        }
+    }
+    if !settings.is_changed() {
+        return;
+    }
+    for handle in &cube {
+        if let Some(material) = materials.get_mut(handle) {
+            material.base_color = COLORS[settings.color];
+        }
+    }
+    for mut menu in &mut menu {
+        menu.follow = match settings.follow {
+            true => WorldSpaceUiFollow::Head {
+                distance: 1.0,
+                speed: 2.0,
+            },
+            false => WorldSpaceUiFollow::Billboard,
+        };
+    }
+}
+
+fn spin_cube(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut cube: Query<&mut Transform, With<Cube>>,
+) {
+    if !settings.spin {
+        return;
+    }
+    for mut transform in &mut cube {
+        transform.rotate_y(time.delta_seconds());
+    }
+}
@@ -2,9 +2,16 @@ use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 
 use bevy::prelude::*;
 use bevy::transform::components::Transform;
-use bevy_oxr::graphics::XrAppInfo;
-use bevy_oxr::input::XrInput;
-use bevy_oxr::resources::{XrFrameState, XrSession};
+use bevy_oxr::graphics::extensions::XrExtensions;
+use bevy_oxr::graphics::{enumerate_swapchain_formats, XrApiLayers, XrAppInfo};
+use bevy_oxr::input::{RecenterXrSpace, XrInput};
+use bevy_oxr::mirror::XrMirrorMode;
+use bevy_oxr::resources::{
+    XrEnvironmentBlendMode, XrFormat, XrFrameState, XrSession, XrSupportedBlendModes, XrViews,
+};
+use bevy_oxr::user_presence::XrUserPresenceChanged;
+use bevy_oxr::visibility_mask::XrVisibilityMasks;
+use openxr as xr;
 
 use bevy_oxr::xr_init::{xr_only, EndXrSession, StartXrSession, XrSetup};
 use bevy_oxr::xr_input::actions::XrActionSets;
@@ -25,12 +32,21 @@ use bevy_oxr::DefaultXrPlugins;
 fn main() {
     color_eyre::install().unwrap();
 
+    let mut reqeusted_extensions = XrExtensions::default();
+    reqeusted_extensions.enable_visibility_mask();
+
     info!("Running `openxr-6dof` skill");
     App::new()
         .add_plugins(DefaultXrPlugins {
+            reqeusted_extensions,
             app_info: XrAppInfo {
                 name: "Bevy OXR Example".into(),
             },
+            mirror: XrMirrorMode::LeftEye,
+            api_layers: XrApiLayers {
+                debug: true,
+                ..default()
+            },
             ..default()
         })
         // .add_plugins(OpenXrDebugRenderer) //new debug renderer adds gizmos to
@@ -39,7 +55,7 @@ fn main() {
         .add_systems(Startup, setup)
         .add_systems(Update, proto_locomotion.run_if(xr_only()))
         .insert_resource(PrototypeLocomotionConfig::default())
-        .add_systems(XrSetup, spawn_controllers_example)
+        .add_systems(XrSetup, (spawn_controllers_example, print_swapchain_format))
         .add_plugins(HandInputDebugRenderer)
         .add_systems(
             Update,
@@ -67,6 +83,11 @@ fn main() {
         .add_systems(Update, update_interactable_states)
         .add_systems(Update, update_grabbables.after(update_interactable_states))
         .add_systems(Update, start_stop_session)
+        .add_systems(Update, recenter_on_menu_button.run_if(xr_only()))
+        .add_systems(Update, toggle_blend_mode.run_if(xr_only()))
+        .add_systems(Update, cycle_mirror_mode)
+        .add_systems(Update, pause_while_headset_removed)
+        .add_systems(Update, print_visibility_mask_savings.run_if(xr_only()))
         .add_event::<InteractionEvent>()
         .run();
 }
@@ -84,6 +105,77 @@ fn start_stop_session(
     }
 }
 
+fn pause_while_headset_removed(
+    mut changed: EventReader<XrUserPresenceChanged>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if let Some(event) = changed.read().last() {
+        info!("headset {}", if event.present { "on" } else { "off" });
+        match event.present {
+            true => time.unpause(),
+            false => time.pause(),
+        }
+    }
+}
+
+fn cycle_mirror_mode(keyboard: Res<ButtonInput<KeyCode>>, mut mirror: ResMut<XrMirrorMode>) {
+    if !keyboard.just_pressed(KeyCode::KeyM) {
+        return;
+    }
+    *mirror = match *mirror {
+        XrMirrorMode::None => XrMirrorMode::LeftEye,
+        XrMirrorMode::LeftEye => XrMirrorMode::RightEye,
+        XrMirrorMode::RightEye => XrMirrorMode::SideBySide,
+        XrMirrorMode::SideBySide => XrMirrorMode::None,
+    };
+    info!("Mirror mode: {:?}", *mirror);
+}
+
+fn recenter_on_menu_button(
+    oculus_controller: Res<OculusController>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    session: Res<XrSession>,
+    action_sets: Res<XrActionSets>,
+    mut recenter: EventWriter<RecenterXrSpace>,
+    mut was_pressed: Local<bool>,
+) {
+    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let pressed = controller.menu_button();
+    if pressed && !*was_pressed {
+        recenter.send_default();
+    }
+    *was_pressed = pressed;
+}
+
+/// switch between VR and AR when pressing the B button
+#[allow(clippy::too_many_arguments)]
+fn toggle_blend_mode(
+    oculus_controller: Res<OculusController>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    session: Res<XrSession>,
+    action_sets: Res<XrActionSets>,
+    supported: Option<Res<XrSupportedBlendModes>>,
+    mut blend_mode: ResMut<XrEnvironmentBlendMode>,
+    mut was_pressed: Local<bool>,
+) {
+    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let pressed = controller.b_button();
+    if pressed && !*was_pressed {
+        let next = match **blend_mode {
+            xr::EnvironmentBlendMode::OPAQUE => xr::EnvironmentBlendMode::ALPHA_BLEND,
+            _ => xr::EnvironmentBlendMode::OPAQUE,
+        };
+        if supported.map_or(false, |modes| modes.contains(&next)) {
+            **blend_mode = next;
+        } else {
+            info!("{:?} is not supported by this runtime", next);
+        }
+    }
+    *was_pressed = pressed;
+}
+
 /// set up a simple 3D scene
 fn setup(
     mut commands: Commands,
@@ -250,3 +342,38 @@ pub fn update_grabbables(
         }
     }
 }
+
+/// Logs how many fragments per eye the visibility mask saves, compare the frame times
+/// from the diagnostics with and without `enable_visibility_mask` for the actual savings
+fn print_visibility_mask_savings(
+    masks: Option<Res<XrVisibilityMasks>>,
+    views: Res<XrViews>,
+    mut printed: Local<bool>,
+) {
+    let Some(masks) = masks else {
+        *printed = false;
+        return;
+    };
+    if masks.is_changed() {
+        *printed = false;
+    }
+    if *printed || views.len() < masks.0.len() {
+        return;
+    }
+    for (i, (mask, view)) in masks.0.iter().zip(views.iter()).enumerate() {
+        info!(
+            "The visibility mask hides {:.1}% of view {}",
+            mask.hidden_fraction(&view.fov) * 100.0,
+            i
+        );
+    }
+    *printed = true;
+}
+
+fn print_swapchain_format(format: Res<XrFormat>, session: Res<XrSession>) {
+    info!(
+        "Negotiated swapchain format {:?}, the runtime supports {:?}",
+        **format,
+        enumerate_swapchain_formats(&session)
+    );
+}
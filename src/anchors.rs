@@ -0,0 +1,1019 @@
+use std::ffi::c_char;
+use std::ptr;
+
+use bevy::prelude::*;
+use bevy::utils::{HashMap, Uuid};
+use openxr as xr;
+
+use crate::{
+    input::XrInput,
+    resources::{XrFrameState, XrInstance, XrSession},
+    xr_init::{xr_only, XrCleanup, XrPostSetup},
+    xr_input::{trackers::OpenXRTrackingRoot, QuatConv, Vec3Conv, XrTrackingUpdate},
+};
+
+/// Keeps entities with [`XrAnchor`] at the pose of a spatial anchor, needs `XR_FB_spatial_entity`
+/// or `XR_MSFT_spatial_anchor`, see
+/// [`XrExtensions::enable_spatial_anchors`](crate::graphics::extensions::XrExtensions::enable_spatial_anchors).
+/// Persisting anchors also needs `XR_FB_spatial_entity_storage` and `XR_FB_spatial_entity_query`
+/// or `XR_MSFT_spatial_anchor_persistence`.
+///
+/// Anchors only exist while the session runs, when it ends the loaded anchor entities are despawned
+/// and [`XrAnchor`] is removed from the others.
+pub struct XrAnchorPlugin {
+    /// Spawns an entity with [`XrAnchor`] for every persisted anchor when the session starts,
+    /// see [`XrAnchorLoaded`]
+    pub load_persisted: bool,
+}
+
+impl Default for XrAnchorPlugin {
+    fn default() -> Self {
+        Self {
+            load_persisted: true,
+        }
+    }
+}
+
+impl Plugin for XrAnchorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<XrPersistAnchor>();
+        app.add_event::<XrUnpersistAnchor>();
+        app.add_event::<XrAnchorCreated>();
+        app.add_event::<XrAnchorPersisted>();
+        app.add_event::<XrAnchorLoaded>();
+        let load_persisted = self.load_persisted;
+        app.add_systems(
+            XrPostSetup,
+            move |mut commands: Commands,
+                  instance: Res<XrInstance>,
+                  session: Res<XrSession>,
+                  loaded: EventWriter<XrAnchorLoaded>| {
+                setup_anchors(&mut commands, &instance, &session, load_persisted, loaded)
+            },
+        );
+        app.add_systems(
+            PreUpdate,
+            (
+                handle_spatial_entity_events,
+                create_anchors,
+                persist_anchors,
+                locate_anchors,
+            )
+                .chain()
+                .in_set(XrTrackingUpdate)
+                .run_if(xr_only()),
+        );
+        app.add_systems(Last, remove_despawned_anchors);
+        app.add_systems(XrCleanup, cleanup_anchors);
+    }
+}
+
+/// Identifies an anchor across sessions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct XrAnchorUuid(pub Uuid);
+
+impl std::fmt::Display for XrAnchorUuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Keeps the entity at the pose of a spatial anchor. Inserting it on an entity creates an anchor
+/// at the entity's current transform, which is in world space, so the entity shouldn't have a parent.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct XrAnchor {
+    /// Set once the runtime created or loaded the anchor
+    pub uuid: Option<XrAnchorUuid>,
+    /// Whether the runtime stored the anchor, see [`XrPersistAnchor`]
+    pub persisted: bool,
+    /// Whether the runtime located the anchor this frame, the transform keeps its last value otherwise
+    pub tracked: bool,
+    /// The pose of the anchor in the session's stage space, below the [`OpenXRTrackingRoot`]
+    pub pose: Transform,
+}
+
+/// Stores the anchor of an entity so it's loaded in later sessions
+#[derive(Clone, Copy, Debug, Event)]
+pub struct XrPersistAnchor(pub Entity);
+
+/// Removes the anchor of an entity from the storage, the anchor itself stays
+#[derive(Clone, Copy, Debug, Event)]
+pub struct XrUnpersistAnchor(pub Entity);
+
+/// Sent once the runtime created the anchor of an entity
+#[derive(Clone, Copy, Debug, Event)]
+pub struct XrAnchorCreated {
+    pub entity: Entity,
+    pub uuid: XrAnchorUuid,
+}
+
+/// Sent once the runtime stored or removed an anchor requested with [`XrPersistAnchor`]
+/// or [`XrUnpersistAnchor`]
+#[derive(Clone, Copy, Debug, Event)]
+pub struct XrAnchorPersisted {
+    pub entity: Entity,
+    pub uuid: XrAnchorUuid,
+    pub persisted: bool,
+}
+
+/// Sent for every persisted anchor loaded when the session started
+#[derive(Clone, Copy, Debug, Event)]
+pub struct XrAnchorLoaded {
+    pub entity: Entity,
+    pub uuid: XrAnchorUuid,
+}
+
+/// Completion of an asynchronous `XR_FB_spatial_entity` request, polled with the other session events
+#[derive(Clone, Copy, Debug, Event)]
+pub enum XrSpatialEntityEvent {
+    AnchorCreated {
+        request: xr::AsyncRequestIdFB,
+        result: xr::sys::Result,
+        space: xr::sys::Space,
+        uuid: XrAnchorUuid,
+    },
+    ComponentStatusSet {
+        request: xr::AsyncRequestIdFB,
+        result: xr::sys::Result,
+        component: xr::sys::SpaceComponentTypeFB,
+        enabled: bool,
+    },
+    QueryResultsAvailable {
+        request: xr::AsyncRequestIdFB,
+    },
+    QueryComplete {
+        request: xr::AsyncRequestIdFB,
+        result: xr::sys::Result,
+    },
+    Saved {
+        request: xr::AsyncRequestIdFB,
+        result: xr::sys::Result,
+    },
+    Erased {
+        request: xr::AsyncRequestIdFB,
+        result: xr::sys::Result,
+    },
+    /// The room setup of `XR_FB_scene_capture` finished
+    SceneCaptured {
+        request: xr::AsyncRequestIdFB,
+        result: xr::sys::Result,
+    },
+}
+
+impl XrSpatialEntityEvent {
+    pub(crate) fn from_event(event: xr::Event) -> Option<Self> {
+        use xr::Event::*;
+        Some(match event {
+            SpatialAnchorCreateCompleteFB(e) => Self::AnchorCreated {
+                request: e.request_id(),
+                result: e.result(),
+                space: e.space(),
+                uuid: XrAnchorUuid(Uuid::from_bytes(e.uuid().data)),
+            },
+            SpaceSetStatusCompleteFB(e) => Self::ComponentStatusSet {
+                request: e.request_id(),
+                result: e.result(),
+                component: e.component_type(),
+                enabled: e.enabled(),
+            },
+            SpaceQueryResultsAvailableFB(e) => Self::QueryResultsAvailable {
+                request: e.request_id(),
+            },
+            SpaceQueryCompleteFB(e) => Self::QueryComplete {
+                request: e.request_id(),
+                result: e.result(),
+            },
+            SpaceSaveCompleteFB(e) => Self::Saved {
+                request: e.request_id(),
+                result: e.result(),
+            },
+            SpaceEraseCompleteFB(e) => Self::Erased {
+                request: e.request_id(),
+                result: e.result(),
+            },
+            SceneCaptureCompleteFB(e) => Self::SceneCaptured {
+                request: e.request_id(),
+                result: e.result(),
+            },
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AnchorBackend {
+    Fb,
+    Msft,
+}
+
+/// FB requests waiting for their completion event
+enum PendingRequest {
+    Create(Entity),
+    /// Saves the anchor once it's storable
+    EnableStorable(Entity),
+    EnableLocatable,
+    Save(Entity),
+    Erase(Entity),
+    Load,
+}
+
+struct MsftAnchor {
+    handle: xr::sys::SpatialAnchorMSFT,
+    destroy: xr::sys::pfn::DestroySpatialAnchorMSFT,
+}
+
+impl Drop for MsftAnchor {
+    fn drop(&mut self) {
+        unsafe { (self.destroy)(self.handle) };
+    }
+}
+
+struct MsftAnchorStore {
+    handle: xr::sys::SpatialAnchorStoreConnectionMSFT,
+    destroy: xr::sys::pfn::DestroySpatialAnchorStoreConnectionMSFT,
+}
+
+impl Drop for MsftAnchorStore {
+    fn drop(&mut self) {
+        unsafe { (self.destroy)(self.handle) };
+    }
+}
+
+struct AnchorSpace {
+    space: xr::Space,
+    // dropped after the space
+    msft_anchor: Option<MsftAnchor>,
+}
+
+#[derive(Resource)]
+struct XrAnchorSpaces {
+    backend: AnchorBackend,
+    spaces: HashMap<Entity, AnchorSpace>,
+    pending: HashMap<xr::AsyncRequestIdFB, PendingRequest>,
+    store: Option<MsftAnchorStore>,
+}
+
+/// Marks the entities spawned for persisted anchors
+#[derive(Component)]
+struct LoadedAnchor;
+
+fn check(result: xr::sys::Result) -> xr::Result<()> {
+    match result.into_raw() < 0 {
+        true => Err(result),
+        false => Ok(()),
+    }
+}
+
+fn to_posef(transform: &Transform) -> xr::Posef {
+    let (rotation, translation) = (transform.rotation, transform.translation);
+    xr::Posef {
+        orientation: xr::Quaternionf {
+            x: rotation.x,
+            y: rotation.y,
+            z: rotation.z,
+            w: rotation.w,
+        },
+        position: xr::Vector3f {
+            x: translation.x,
+            y: translation.y,
+            z: translation.z,
+        },
+    }
+}
+
+fn msft_anchor_name(uuid: XrAnchorUuid) -> xr::sys::SpatialAnchorPersistenceNameMSFT {
+    let mut name = xr::sys::SpatialAnchorPersistenceNameMSFT {
+        name: [0; xr::sys::MAX_SPATIAL_ANCHOR_NAME_SIZE_MSFT],
+    };
+    let uuid = uuid.to_string();
+    for (dst, src) in name.name.iter_mut().zip(uuid.bytes()) {
+        *dst = src as c_char;
+    }
+    name
+}
+
+fn setup_anchors(
+    commands: &mut Commands,
+    instance: &XrInstance,
+    session: &XrSession,
+    load_persisted: bool,
+    mut loaded: EventWriter<XrAnchorLoaded>,
+) {
+    let exts = instance.exts();
+    let backend = match (exts.fb_spatial_entity, exts.msft_spatial_anchor) {
+        (Some(_), _) => AnchorBackend::Fb,
+        (None, Some(_)) => AnchorBackend::Msft,
+        (None, None) => {
+            warn!("The runtime doesn't support spatial anchors");
+            return;
+        }
+    };
+    let mut anchors = XrAnchorSpaces {
+        backend,
+        spaces: HashMap::new(),
+        pending: HashMap::new(),
+        store: None,
+    };
+    if let Some(persistence) = exts.msft_spatial_anchor_persistence {
+        let mut store = xr::sys::SpatialAnchorStoreConnectionMSFT::NULL;
+        let result = unsafe {
+            (persistence.create_spatial_anchor_store_connection)(session.as_raw(), &mut store)
+        };
+        match check(result) {
+            Ok(()) => {
+                anchors.store = Some(MsftAnchorStore {
+                    handle: store,
+                    destroy: persistence.destroy_spatial_anchor_store_connection,
+                })
+            }
+            Err(err) => warn!("Unable to connect to the spatial anchor store: {}", err),
+        }
+    }
+    if load_persisted {
+        let result = match backend {
+            AnchorBackend::Fb => query_persisted_fb_anchors(instance, session, &mut anchors),
+            AnchorBackend::Msft => {
+                load_persisted_msft_anchors(commands, instance, session, &mut anchors, &mut loaded)
+            }
+        };
+        if let Err(err) = result {
+            warn!("Unable to load the persisted spatial anchors: {}", err);
+        }
+    }
+    commands.insert_resource(anchors);
+}
+
+fn query_persisted_fb_anchors(
+    instance: &XrInstance,
+    session: &XrSession,
+    anchors: &mut XrAnchorSpaces,
+) -> xr::Result<()> {
+    if instance.exts().fb_spatial_entity_query.is_none()
+        || instance.exts().fb_spatial_entity_storage.is_none()
+    {
+        return Ok(());
+    }
+    let request = query_fb_spaces(instance, session, xr::sys::SpaceComponentTypeFB::STORABLE)?;
+    anchors.pending.insert(request, PendingRequest::Load);
+    Ok(())
+}
+
+/// Starts loading the locally stored spaces that have a component,
+/// the results arrive as [`XrSpatialEntityEvent::QueryResultsAvailable`]
+pub(crate) fn query_fb_spaces(
+    instance: &XrInstance,
+    session: &XrSession,
+    component: xr::sys::SpaceComponentTypeFB,
+) -> xr::Result<xr::AsyncRequestIdFB> {
+    let Some(query) = instance.exts().fb_spatial_entity_query else {
+        return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let location = xr::sys::SpaceStorageLocationFilterInfoFB {
+        ty: xr::sys::SpaceStorageLocationFilterInfoFB::TYPE,
+        next: ptr::null(),
+        location: xr::sys::SpaceStorageLocationFB::LOCAL,
+    };
+    let filter = xr::sys::SpaceComponentFilterInfoFB {
+        ty: xr::sys::SpaceComponentFilterInfoFB::TYPE,
+        next: &location as *const _ as _,
+        component_type: component,
+    };
+    let info = xr::sys::SpaceQueryInfoFB {
+        ty: xr::sys::SpaceQueryInfoFB::TYPE,
+        next: ptr::null(),
+        query_action: xr::sys::SpaceQueryActionFB::LOAD,
+        max_result_count: u32::MAX,
+        timeout: xr::Duration::NONE,
+        filter: &filter as *const _ as _,
+        exclude_filter: ptr::null(),
+    };
+    let mut request = xr::AsyncRequestIdFB::default();
+    check(unsafe { (query.query_spaces)(session.as_raw(), &info as *const _ as _, &mut request) })?;
+    Ok(request)
+}
+
+fn load_persisted_msft_anchors(
+    commands: &mut Commands,
+    instance: &XrInstance,
+    session: &XrSession,
+    anchors: &mut XrAnchorSpaces,
+    loaded: &mut EventWriter<XrAnchorLoaded>,
+) -> xr::Result<()> {
+    let (Some(persistence), Some(store)) = (
+        instance.exts().msft_spatial_anchor_persistence,
+        &anchors.store,
+    ) else {
+        return Ok(());
+    };
+    let store = store.handle;
+    let mut count = 0;
+    check(unsafe {
+        (persistence.enumerate_persisted_spatial_anchor_names)(
+            store,
+            0,
+            &mut count,
+            ptr::null_mut(),
+        )
+    })?;
+    let empty = xr::sys::SpatialAnchorPersistenceNameMSFT {
+        name: [0; xr::sys::MAX_SPATIAL_ANCHOR_NAME_SIZE_MSFT],
+    };
+    let mut names = vec![empty; count as usize];
+    check(unsafe {
+        (persistence.enumerate_persisted_spatial_anchor_names)(
+            store,
+            count,
+            &mut count,
+            names.as_mut_ptr(),
+        )
+    })?;
+    names.truncate(count as usize);
+    for name in names {
+        let bytes = name
+            .name
+            .iter()
+            .take_while(|c| **c != 0)
+            .map(|c| *c as u8)
+            .collect::<Vec<_>>();
+        let Some(uuid) = std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|name| Uuid::parse_str(name).ok())
+        else {
+            warn!("Skipping the persisted spatial anchor with a name that isn't a uuid");
+            continue;
+        };
+        let info = xr::sys::SpatialAnchorFromPersistedAnchorCreateInfoMSFT {
+            ty: xr::sys::SpatialAnchorFromPersistedAnchorCreateInfoMSFT::TYPE,
+            next: ptr::null(),
+            spatial_anchor_store: store,
+            spatial_anchor_persistence_name: name,
+        };
+        let mut anchor = xr::sys::SpatialAnchorMSFT::NULL;
+        check(unsafe {
+            (persistence.create_spatial_anchor_from_persisted_name)(
+                session.as_raw(),
+                &info,
+                &mut anchor,
+            )
+        })?;
+        let space = create_msft_anchor_space(instance, session, anchor)?;
+        let uuid = XrAnchorUuid(uuid);
+        let entity = commands
+            .spawn((
+                SpatialBundle::default(),
+                XrAnchor {
+                    uuid: Some(uuid),
+                    persisted: true,
+                    ..default()
+                },
+                LoadedAnchor,
+            ))
+            .id();
+        anchors.spaces.insert(entity, space);
+        loaded.send(XrAnchorLoaded { entity, uuid });
+    }
+    Ok(())
+}
+
+/// Takes ownership of the anchor, it's destroyed with the space
+fn create_msft_anchor_space(
+    instance: &XrInstance,
+    session: &XrSession,
+    anchor: xr::sys::SpatialAnchorMSFT,
+) -> xr::Result<AnchorSpace> {
+    let msft = instance.exts().msft_spatial_anchor.unwrap();
+    let anchor = MsftAnchor {
+        handle: anchor,
+        destroy: msft.destroy_spatial_anchor,
+    };
+    let info = xr::sys::SpatialAnchorSpaceCreateInfoMSFT {
+        ty: xr::sys::SpatialAnchorSpaceCreateInfoMSFT::TYPE,
+        next: ptr::null(),
+        anchor: anchor.handle,
+        pose_in_anchor_space: xr::Posef::IDENTITY,
+    };
+    let mut space = xr::sys::Space::NULL;
+    check(unsafe { (msft.create_spatial_anchor_space)(session.as_raw(), &info, &mut space) })?;
+    Ok(AnchorSpace {
+        space: unsafe { xr::Space::reference_from_raw(xr::Session::clone(session), space) },
+        msft_anchor: Some(anchor),
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_spatial_entity_events(
+    mut commands: Commands,
+    mut events: EventReader<XrSpatialEntityEvent>,
+    anchors: Option<ResMut<XrAnchorSpaces>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    mut anchor_query: Query<&mut XrAnchor>,
+    mut created: EventWriter<XrAnchorCreated>,
+    mut persisted: EventWriter<XrAnchorPersisted>,
+    mut loaded: EventWriter<XrAnchorLoaded>,
+) {
+    let Some(mut anchors) = anchors else {
+        events.clear();
+        return;
+    };
+    for event in events.read() {
+        match *event {
+            XrSpatialEntityEvent::AnchorCreated {
+                request,
+                result,
+                space,
+                uuid,
+            } => {
+                let Some(PendingRequest::Create(entity)) = anchors.pending.remove(&request) else {
+                    continue;
+                };
+                if let Err(err) = check(result) {
+                    warn!("Unable to create a spatial anchor: {}", err);
+                    continue;
+                }
+                let space =
+                    unsafe { xr::Space::reference_from_raw(xr::Session::clone(&session), space) };
+                let Ok(mut anchor) = anchor_query.get_mut(entity) else {
+                    // the entity was despawned in the meantime, dropping the space destroys the anchor
+                    continue;
+                };
+                anchor.uuid = Some(uuid);
+                anchors.spaces.insert(
+                    entity,
+                    AnchorSpace {
+                        space,
+                        msft_anchor: None,
+                    },
+                );
+                created.send(XrAnchorCreated { entity, uuid });
+            }
+            XrSpatialEntityEvent::ComponentStatusSet {
+                request,
+                result,
+                component,
+                enabled,
+            } => match anchors.pending.remove(&request) {
+                Some(PendingRequest::EnableStorable(entity)) => {
+                    if check(result).is_err() || !enabled {
+                        warn!("Unable to make spatial anchor storable: {}", result);
+                        continue;
+                    }
+                    if let Err(err) = save_fb_anchor(&instance, &session, &mut anchors, entity) {
+                        warn!("Unable to persist a spatial anchor: {}", err);
+                    }
+                }
+                Some(PendingRequest::EnableLocatable) => {
+                    if check(result).is_err() {
+                        warn!("Unable to enable the {:?} component: {}", component, result);
+                    }
+                }
+                _ => {}
+            },
+            XrSpatialEntityEvent::QueryResultsAvailable { request } => {
+                if !matches!(anchors.pending.get(&request), Some(PendingRequest::Load)) {
+                    continue;
+                }
+                let results = match retrieve_fb_query_results(&instance, &session, request) {
+                    Ok(results) => results,
+                    Err(err) => {
+                        warn!("Unable to retrieve the persisted spatial anchors: {}", err);
+                        continue;
+                    }
+                };
+                for result in results {
+                    let uuid = XrAnchorUuid(Uuid::from_bytes(result.uuid.data));
+                    match enable_fb_component(
+                        &instance,
+                        result.space,
+                        xr::sys::SpaceComponentTypeFB::LOCATABLE,
+                    ) {
+                        Ok(Some(request)) => {
+                            anchors
+                                .pending
+                                .insert(request, PendingRequest::EnableLocatable);
+                        }
+                        Ok(None) => {}
+                        Err(err) => warn!(
+                            "Unable to locate persisted spatial anchor {}: {}",
+                            uuid, err
+                        ),
+                    }
+                    let space = unsafe {
+                        xr::Space::reference_from_raw(xr::Session::clone(&session), result.space)
+                    };
+                    let entity = commands
+                        .spawn((
+                            SpatialBundle::default(),
+                            XrAnchor {
+                                uuid: Some(uuid),
+                                persisted: true,
+                                ..default()
+                            },
+                            LoadedAnchor,
+                        ))
+                        .id();
+                    anchors.spaces.insert(
+                        entity,
+                        AnchorSpace {
+                            space,
+                            msft_anchor: None,
+                        },
+                    );
+                    loaded.send(XrAnchorLoaded { entity, uuid });
+                }
+            }
+            XrSpatialEntityEvent::QueryComplete { request, result } => {
+                if anchors.pending.remove(&request).is_some() {
+                    if let Err(err) = check(result) {
+                        warn!("Unable to load the persisted spatial anchors: {}", err);
+                    }
+                }
+            }
+            XrSpatialEntityEvent::Saved { request, result }
+            | XrSpatialEntityEvent::Erased { request, result } => {
+                let (entity, saved) = match anchors.pending.remove(&request) {
+                    Some(PendingRequest::Save(entity)) => (entity, true),
+                    Some(PendingRequest::Erase(entity)) => (entity, false),
+                    _ => continue,
+                };
+                if let Err(err) = check(result) {
+                    warn!("Unable to change the storage of a spatial anchor: {}", err);
+                    continue;
+                }
+                let Ok(mut anchor) = anchor_query.get_mut(entity) else {
+                    continue;
+                };
+                anchor.persisted = saved;
+                if let Some(uuid) = anchor.uuid {
+                    persisted.send(XrAnchorPersisted {
+                        entity,
+                        uuid,
+                        persisted: saved,
+                    });
+                }
+            }
+            XrSpatialEntityEvent::SceneCaptured { .. } => {}
+        }
+    }
+}
+
+pub(crate) fn retrieve_fb_query_results(
+    instance: &XrInstance,
+    session: &XrSession,
+    request: xr::AsyncRequestIdFB,
+) -> xr::Result<Vec<xr::sys::SpaceQueryResultFB>> {
+    let query = instance.exts().fb_spatial_entity_query.unwrap();
+    let mut results = xr::sys::SpaceQueryResultsFB {
+        ty: xr::sys::SpaceQueryResultsFB::TYPE,
+        next: ptr::null_mut(),
+        result_capacity_input: 0,
+        result_count_output: 0,
+        results: ptr::null_mut(),
+    };
+    check(unsafe {
+        (query.retrieve_space_query_results)(session.as_raw(), request, &mut results)
+    })?;
+    let empty = xr::sys::SpaceQueryResultFB {
+        space: xr::sys::Space::NULL,
+        uuid: xr::sys::UuidEXT { data: [0; 16] },
+    };
+    let mut buffer = vec![empty; results.result_count_output as usize];
+    results.result_capacity_input = buffer.len() as u32;
+    results.results = buffer.as_mut_ptr();
+    check(unsafe {
+        (query.retrieve_space_query_results)(session.as_raw(), request, &mut results)
+    })?;
+    buffer.truncate(results.result_count_output as usize);
+    Ok(buffer)
+}
+
+/// Enables a component of a space unless it already is, returns the request if one was started,
+/// it completes with [`XrSpatialEntityEvent::ComponentStatusSet`]
+pub(crate) fn enable_fb_component(
+    instance: &XrInstance,
+    space: xr::sys::Space,
+    component: xr::sys::SpaceComponentTypeFB,
+) -> xr::Result<Option<xr::AsyncRequestIdFB>> {
+    let fb = instance.exts().fb_spatial_entity.unwrap();
+    let mut status = xr::sys::SpaceComponentStatusFB {
+        ty: xr::sys::SpaceComponentStatusFB::TYPE,
+        next: ptr::null_mut(),
+        enabled: false.into(),
+        change_pending: false.into(),
+    };
+    check(unsafe { (fb.get_space_component_status)(space, component, &mut status) })?;
+    if bool::from(status.enabled) {
+        return Ok(None);
+    }
+    let info = xr::sys::SpaceComponentStatusSetInfoFB {
+        ty: xr::sys::SpaceComponentStatusSetInfoFB::TYPE,
+        next: ptr::null(),
+        component_type: component,
+        enabled: true.into(),
+        timeout: xr::Duration::NONE,
+    };
+    let mut request = xr::AsyncRequestIdFB::default();
+    check(unsafe { (fb.set_space_component_status)(space, &info, &mut request) })?;
+    Ok(Some(request))
+}
+
+fn save_fb_anchor(
+    instance: &XrInstance,
+    session: &XrSession,
+    anchors: &mut XrAnchorSpaces,
+    entity: Entity,
+) -> xr::Result<()> {
+    let Some(storage) = instance.exts().fb_spatial_entity_storage else {
+        return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let Some(space) = anchors
+        .spaces
+        .get(&entity)
+        .map(|space| space.space.as_raw())
+    else {
+        return Ok(());
+    };
+    if let Some(request) =
+        enable_fb_component(instance, space, xr::sys::SpaceComponentTypeFB::STORABLE)?
+    {
+        anchors
+            .pending
+            .insert(request, PendingRequest::EnableStorable(entity));
+        return Ok(());
+    }
+    let info = xr::sys::SpaceSaveInfoFB {
+        ty: xr::sys::SpaceSaveInfoFB::TYPE,
+        next: ptr::null(),
+        space,
+        location: xr::sys::SpaceStorageLocationFB::LOCAL,
+        persistence_mode: xr::sys::SpacePersistenceModeFB::INDEFINITE,
+    };
+    let mut request = xr::AsyncRequestIdFB::default();
+    check(unsafe { (storage.save_space)(session.as_raw(), &info, &mut request) })?;
+    anchors
+        .pending
+        .insert(request, PendingRequest::Save(entity));
+    Ok(())
+}
+
+fn erase_fb_anchor(
+    instance: &XrInstance,
+    session: &XrSession,
+    anchors: &mut XrAnchorSpaces,
+    entity: Entity,
+) -> xr::Result<()> {
+    let Some(storage) = instance.exts().fb_spatial_entity_storage else {
+        return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let Some(space) = anchors
+        .spaces
+        .get(&entity)
+        .map(|space| space.space.as_raw())
+    else {
+        return Ok(());
+    };
+    let info = xr::sys::SpaceEraseInfoFB {
+        ty: xr::sys::SpaceEraseInfoFB::TYPE,
+        next: ptr::null(),
+        space,
+        location: xr::sys::SpaceStorageLocationFB::LOCAL,
+    };
+    let mut request = xr::AsyncRequestIdFB::default();
+    check(unsafe { (storage.erase_space)(session.as_raw(), &info, &mut request) })?;
+    anchors
+        .pending
+        .insert(request, PendingRequest::Erase(entity));
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_anchors(
+    anchors: Option<ResMut<XrAnchorSpaces>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+    mut new_anchors: Query<(Entity, &mut XrAnchor, &Transform), Added<XrAnchor>>,
+    root: Query<&GlobalTransform, With<OpenXRTrackingRoot>>,
+    mut created: EventWriter<XrAnchorCreated>,
+) {
+    let Some(mut anchors) = anchors else {
+        return;
+    };
+    let root = root
+        .get_single()
+        .map(|root| root.compute_transform())
+        .unwrap_or_default();
+    for (entity, mut anchor, transform) in &mut new_anchors {
+        if anchor.uuid.is_some() || anchors.spaces.contains_key(&entity) {
+            continue;
+        }
+        let local =
+            Transform::from_matrix(root.compute_matrix().inverse() * transform.compute_matrix());
+        let pose = to_posef(&local);
+        let result = match anchors.backend {
+            AnchorBackend::Fb => {
+                let fb = instance.exts().fb_spatial_entity.unwrap();
+                let info = xr::sys::SpatialAnchorCreateInfoFB {
+                    ty: xr::sys::SpatialAnchorCreateInfoFB::TYPE,
+                    next: ptr::null(),
+                    space: input.stage.as_raw(),
+                    pose_in_space: pose,
+                    time: frame_state.predicted_display_time,
+                };
+                let mut request = xr::AsyncRequestIdFB::default();
+                check(unsafe { (fb.create_spatial_anchor)(session.as_raw(), &info, &mut request) })
+                    .map(|()| {
+                        anchors
+                            .pending
+                            .insert(request, PendingRequest::Create(entity));
+                    })
+            }
+            AnchorBackend::Msft => {
+                let msft = instance.exts().msft_spatial_anchor.unwrap();
+                let info = xr::sys::SpatialAnchorCreateInfoMSFT {
+                    ty: xr::sys::SpatialAnchorCreateInfoMSFT::TYPE,
+                    next: ptr::null(),
+                    space: input.stage.as_raw(),
+                    pose,
+                    time: frame_state.predicted_display_time,
+                };
+                let mut handle = xr::sys::SpatialAnchorMSFT::NULL;
+                check(unsafe { (msft.create_spatial_anchor)(session.as_raw(), &info, &mut handle) })
+                    .and_then(|()| create_msft_anchor_space(&instance, &session, handle))
+                    .map(|space| {
+                        let uuid = XrAnchorUuid(Uuid::new_v4());
+                        anchor.uuid = Some(uuid);
+                        anchors.spaces.insert(entity, space);
+                        created.send(XrAnchorCreated { entity, uuid });
+                    })
+            }
+        };
+        if let Err(err) = result {
+            warn!("Unable to create a spatial anchor: {}", err);
+        }
+    }
+}
+
+fn persist_anchors(
+    anchors: Option<ResMut<XrAnchorSpaces>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    mut persist: EventReader<XrPersistAnchor>,
+    mut unpersist: EventReader<XrUnpersistAnchor>,
+    mut anchor_query: Query<&mut XrAnchor>,
+    mut persisted: EventWriter<XrAnchorPersisted>,
+) {
+    let Some(mut anchors) = anchors else {
+        persist.clear();
+        unpersist.clear();
+        return;
+    };
+    let requests = persist
+        .read()
+        .map(|XrPersistAnchor(entity)| (*entity, true))
+        .chain(
+            unpersist
+                .read()
+                .map(|XrUnpersistAnchor(entity)| (*entity, false)),
+        )
+        .collect::<Vec<_>>();
+    for (entity, persist) in requests {
+        let Ok(mut anchor) = anchor_query.get_mut(entity) else {
+            warn!("Entity {:?} has no spatial anchor to persist", entity);
+            continue;
+        };
+        let Some(uuid) = anchor.uuid else {
+            warn!("The spatial anchor of {:?} isn't created yet", entity);
+            continue;
+        };
+        let result = match anchors.backend {
+            AnchorBackend::Fb if persist => {
+                save_fb_anchor(&instance, &session, &mut anchors, entity)
+            }
+            AnchorBackend::Fb => erase_fb_anchor(&instance, &session, &mut anchors, entity),
+            AnchorBackend::Msft => persist_msft_anchor(&instance, &anchors, entity, uuid, persist)
+                .map(|()| {
+                    anchor.persisted = persist;
+                    persisted.send(XrAnchorPersisted {
+                        entity,
+                        uuid,
+                        persisted: persist,
+                    });
+                }),
+        };
+        if let Err(err) = result {
+            warn!(
+                "Unable to change the storage of spatial anchor {}: {}",
+                uuid, err
+            );
+        }
+    }
+}
+
+fn persist_msft_anchor(
+    instance: &XrInstance,
+    anchors: &XrAnchorSpaces,
+    entity: Entity,
+    uuid: XrAnchorUuid,
+    persist: bool,
+) -> xr::Result<()> {
+    let (Some(persistence), Some(store)) = (
+        instance.exts().msft_spatial_anchor_persistence,
+        &anchors.store,
+    ) else {
+        return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let name = msft_anchor_name(uuid);
+    if !persist {
+        return check(unsafe { (persistence.unpersist_spatial_anchor)(store.handle, &name) });
+    }
+    let Some(anchor) = anchors
+        .spaces
+        .get(&entity)
+        .and_then(|space| space.msft_anchor.as_ref())
+    else {
+        return Ok(());
+    };
+    let info = xr::sys::SpatialAnchorPersistenceInfoMSFT {
+        ty: xr::sys::SpatialAnchorPersistenceInfoMSFT::TYPE,
+        next: ptr::null(),
+        spatial_anchor_persistence_name: name,
+        spatial_anchor: anchor.handle,
+    };
+    check(unsafe { (persistence.persist_spatial_anchor)(store.handle, &info) })
+}
+
+fn locate_anchors(
+    anchors: Option<Res<XrAnchorSpaces>>,
+    input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+    mut anchor_query: Query<(Entity, &mut XrAnchor, &mut Transform)>,
+    root: Query<&GlobalTransform, With<OpenXRTrackingRoot>>,
+) {
+    let Some(anchors) = anchors else {
+        return;
+    };
+    let root = root.get_single().ok();
+    for (entity, mut anchor, mut transform) in &mut anchor_query {
+        let Some(space) = anchors.spaces.get(&entity) else {
+            continue;
+        };
+        let location = match space
+            .space
+            .locate(&input.stage, frame_state.predicted_display_time)
+        {
+            Ok(location) => location,
+            Err(err) => {
+                warn!("Unable to locate a spatial anchor: {}", err);
+                anchor.tracked = false;
+                continue;
+            }
+        };
+        anchor.tracked = location.location_flags.contains(
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
+        );
+        if !anchor.tracked {
+            continue;
+        }
+        anchor.pose = Transform {
+            translation: location.pose.position.to_vec3(),
+            rotation: location.pose.orientation.to_quat(),
+            ..default()
+        };
+        let world = match root {
+            Some(root) => root.mul_transform(anchor.pose).compute_transform(),
+            None => anchor.pose,
+        };
+        transform.translation = world.translation;
+        transform.rotation = world.rotation;
+    }
+}
+
+/// Destroys the anchors of despawned entities and of entities `XrAnchor` was removed from
+fn remove_despawned_anchors(
+    anchors: Option<ResMut<XrAnchorSpaces>>,
+    mut removed: RemovedComponents<XrAnchor>,
+) {
+    let Some(mut anchors) = anchors else {
+        return;
+    };
+    for entity in removed.read() {
+        anchors.spaces.remove(&entity);
+    }
+}
+
+fn cleanup_anchors(
+    mut commands: Commands,
+    anchor_query: Query<(Entity, Has<LoadedAnchor>), With<XrAnchor>>,
+) {
+    for (entity, loaded) in &anchor_query {
+        match loaded {
+            true => commands.entity(entity).despawn_recursive(),
+            false => {
+                commands.entity(entity).remove::<XrAnchor>();
+            }
+        }
+    }
+    commands.remove_resource::<XrAnchorSpaces>();
+}
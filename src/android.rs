@@ -0,0 +1,121 @@
+//! Keeps the app working when it's sent to the background on Android headsets. The examples show
+//! how to build an APK with `xbuild` or `cargo-apk`.
+
+use bevy::prelude::*;
+use bevy::window::ApplicationLifetime;
+
+use crate::xr_init::{StartXrSession, XrFocusGained, XrPollEvents, XrStatus};
+
+/// Requests the permissions on startup and restarts the session after the app was paused. The
+/// runtime stops the session when the app is paused, which cleans it up like any stopped
+/// session, and a new session is started once the app is resumed.
+#[derive(Default)]
+pub struct XrAndroidPlugin {
+    /// Android permissions requested from the user when the app starts,
+    /// like `com.oculus.permission.EYE_TRACKING`. They also need to be in the manifest. Ignored
+    /// on other platforms.
+    pub permissions: Vec<String>,
+}
+
+impl Plugin for XrAndroidPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(target_os = "android")]
+        if !self.permissions.is_empty() {
+            if let Err(err) = request_permissions(&self.permissions) {
+                warn!("Unable to request the Android permissions: {}", err);
+            }
+        }
+        app.init_resource::<XrRestartOnResume>();
+        app.add_systems(PreUpdate, restart_after_pause.after(XrPollEvents));
+    }
+}
+
+/// A session was running when the app was paused
+#[derive(Resource, Default)]
+struct XrRestartOnResume {
+    suspended: bool,
+    restart: bool,
+}
+
+fn restart_after_pause(
+    mut restart: ResMut<XrRestartOnResume>,
+    mut lifetime: EventReader<ApplicationLifetime>,
+    mut focus_gained: EventReader<XrFocusGained>,
+    status: Res<XrStatus>,
+    mut start_session: EventWriter<StartXrSession>,
+) {
+    for event in lifetime.read() {
+        match event {
+            ApplicationLifetime::Suspended => {
+                info!("The app was paused");
+                restart.suspended = true;
+                restart.restart = matches!(*status, XrStatus::Enabled | XrStatus::Enabling);
+            }
+            ApplicationLifetime::Resumed => {
+                info!("The app was resumed");
+                restart.suspended = false;
+            }
+            ApplicationLifetime::Started => {}
+        }
+    }
+    // the runtime kept the session running
+    if focus_gained.read().count() > 0 {
+        restart.restart = false;
+    }
+    // the events of the runtime are only read again after resuming,
+    // so the session is stopped and cleaned up after that
+    if restart.restart && !restart.suspended && *status == XrStatus::Disabled {
+        info!("Starting a new OpenXR session after the app was paused");
+        restart.restart = false;
+        start_session.send_default();
+    }
+}
+
+/// Asks the user for the permissions that weren't granted yet through the activity
+#[cfg(target_os = "android")]
+fn request_permissions(permissions: &[String]) -> jni::errors::Result<()> {
+    use jni::objects::{JObject, JValue};
+
+    // PackageManager.PERMISSION_GRANTED
+    const PERMISSION_GRANTED: i32 = 0;
+
+    let ctx = ndk_context::android_context();
+    let vm = unsafe { jni::JavaVM::from_raw(ctx.vm().cast()) }?;
+    let env = vm.attach_current_thread()?;
+    let activity = unsafe { JObject::from_raw(ctx.context().cast()) };
+    let mut missing = Vec::new();
+    for permission in permissions {
+        let name = env.new_string(permission)?;
+        let granted = env
+            .call_method(
+                activity,
+                "checkSelfPermission",
+                "(Ljava/lang/String;)I",
+                &[JValue::Object(name.into())],
+            )?
+            .i()?;
+        if granted != PERMISSION_GRANTED {
+            missing.push(name);
+        }
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+    info!("Requesting the Android permissions {:?}", permissions);
+    let array = env.new_object_array(missing.len() as i32, "java/lang/String", JObject::null())?;
+    for (index, name) in missing.into_iter().enumerate() {
+        env.set_object_array_element(array, index as i32, name)?;
+    }
+    // the answer arrives in onRequestPermissionsResult, which native activities can't override,
+    // the extensions that need the permissions check it themselves
+    env.call_method(
+        activity,
+        "requestPermissions",
+        "([Ljava/lang/String;I)V",
+        &[
+            JValue::Object(unsafe { JObject::from_raw(array) }),
+            JValue::Int(0),
+        ],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Spatial audio heard from the headset, so emitters are heard from where the player is,
+//! locomotion included.
+
+use bevy::audio::{AudioSink, AudioSinkPlayback, SpatialAudioSink, SpatialListener};
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::resources::XrViews;
+use crate::xr_init::{xr_only, XrCleanup, XrPostSetup, XrSessionState, XrStatus};
+use crate::xr_input::trackers::OpenXRTrackingRoot;
+use crate::xr_input::{QuatConv, Vec3Conv, XrTrackingUpdate};
+
+/// Moves the spatial audio listener to the headset while the session runs and turns the audio
+/// down while it isn't focused, see [`XrAudioSettings`]. The [`XrAudioListener`] is spawned below
+/// the [`OpenXRTrackingRoot`].
+pub struct XrAudioPlugin;
+
+impl Plugin for XrAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrAudioSettings>();
+        app.add_systems(XrPostSetup, spawn_listener);
+        app.add_systems(
+            PreUpdate,
+            update_listener.in_set(XrTrackingUpdate).run_if(xr_only()),
+        );
+        app.add_systems(Update, duck_unfocused_audio);
+        app.add_systems(XrCleanup, cleanup_listener);
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrAudioSettings {
+    /// Distance between the ears of the listener in meters
+    pub ear_gap: f32,
+    /// Volume factor applied to all playing sounds while the session isn't focused, like while
+    /// the system menu is open. `None` leaves the volume alone.
+    pub unfocused_volume: Option<f32>,
+}
+
+impl Default for XrAudioSettings {
+    fn default() -> Self {
+        Self {
+            ear_gap: 0.18,
+            unfocused_volume: Some(0.0),
+        }
+    }
+}
+
+/// The listener following the headset, its transform is relative to the tracking root
+#[derive(Component, Clone, Copy, Debug)]
+pub struct XrAudioListener;
+
+/// Another [`SpatialListener`] of the app, taken off while the headset is listening because
+/// bevy only supports a single one
+#[derive(Component)]
+struct HiddenListener(SpatialListener);
+
+fn spawn_listener(
+    mut commands: Commands,
+    settings: Res<XrAudioSettings>,
+    root: Query<Entity, With<OpenXRTrackingRoot>>,
+    listeners: Query<(Entity, &SpatialListener), Without<XrAudioListener>>,
+) {
+    let Ok(root) = root.get_single() else {
+        warn!("No tracking root to spawn the audio listener below");
+        return;
+    };
+    for (entity, listener) in &listeners {
+        commands
+            .entity(entity)
+            .remove::<SpatialListener>()
+            .insert(HiddenListener(listener.clone()));
+    }
+    commands.entity(root).with_children(|parent| {
+        parent.spawn((
+            SpatialBundle::default(),
+            Name::new("Audio Listener"),
+            SpatialListener::new(settings.ear_gap),
+            XrAudioListener,
+        ));
+    });
+}
+
+fn update_listener(
+    views: Res<XrViews>,
+    mut listener: Query<&mut Transform, With<XrAudioListener>>,
+) {
+    let (Some(first), Ok(mut transform)) = (views.first(), listener.get_single_mut()) else {
+        return;
+    };
+    // between the eyes
+    let position = views
+        .iter()
+        .map(|view| view.pose.position.to_vec3())
+        .sum::<Vec3>()
+        / views.len() as f32;
+    *transform =
+        Transform::from_translation(position).with_rotation(first.pose.orientation.to_quat());
+}
+
+fn cleanup_listener(
+    mut commands: Commands,
+    listener: Query<Entity, With<XrAudioListener>>,
+    hidden: Query<(Entity, &HiddenListener)>,
+) {
+    for entity in &listener {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (entity, hidden) in &hidden {
+        commands
+            .entity(entity)
+            .remove::<HiddenListener>()
+            .insert(hidden.0.clone());
+    }
+}
+
+/// Scales the volume of every sink while unfocused and restores it afterwards,
+/// sounds started in the meantime are turned down as well
+fn duck_unfocused_audio(
+    settings: Res<XrAudioSettings>,
+    status: Res<XrStatus>,
+    session_state: Res<XrSessionState>,
+    sinks: Query<(Entity, &AudioSink)>,
+    spatial_sinks: Query<(Entity, &SpatialAudioSink)>,
+    mut ducked: Local<EntityHashMap<f32>>,
+) {
+    let unfocused = *status == XrStatus::Enabled && **session_state != xr::SessionState::FOCUSED;
+    let factor = settings.unfocused_volume.filter(|_| unfocused);
+    let sinks = sinks
+        .iter()
+        .map(|(entity, sink)| (entity, sink as &dyn AudioSinkPlayback))
+        .chain(
+            spatial_sinks
+                .iter()
+                .map(|(entity, sink)| (entity, sink as &dyn AudioSinkPlayback)),
+        );
+    match factor {
+        Some(factor) => {
+            for (entity, sink) in sinks {
+                if !ducked.contains_key(&entity) {
+                    ducked.insert(entity, sink.volume());
+                    sink.set_volume(sink.volume() * factor);
+                }
+            }
+        }
+        None if !ducked.is_empty() => {
+            for (entity, sink) in sinks {
+                if let Some(volume) = ducked.get(&entity) {
+                    sink.set_volume(*volume);
+                }
+            }
+            ducked.clear();
+        }
+        None => {}
+    }
+}
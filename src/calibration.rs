@@ -0,0 +1,236 @@
+//! Correcting the floor height and the forward direction the runtime reports, with
+//! [`CalibrateXrSpace`] like after the player put a controller on the floor.
+
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+
+use crate::input::XrInput;
+use crate::resources::{XrFrameState, XrSession};
+use crate::xr_init::{xr_only, XrPollEvents, XrWaitFrame};
+use crate::xr_input::oculus_touch::OculusController;
+use crate::xr_input::Hand;
+
+/// Applies the [`XrCalibration`]. With a `path` it's loaded when the app starts and saved
+/// whenever it changes, so it's applied again on the next run.
+pub struct XrCalibrationPlugin {
+    /// The file the calibration is loaded from and saved to, `None` keeps it in memory
+    pub path: Option<PathBuf>,
+}
+
+impl Default for XrCalibrationPlugin {
+    fn default() -> Self {
+        Self {
+            path: Some("xr_calibration.txt".into()),
+        }
+    }
+}
+
+impl Plugin for XrCalibrationPlugin {
+    fn build(&self, app: &mut App) {
+        let calibration = match &self.path {
+            Some(path) => XrCalibration::load(path).unwrap_or_else(|err| {
+                warn!("Unable to load the calibration from {:?}: {}", path, err);
+                default()
+            }),
+            None => default(),
+        };
+        app.register_type::<XrCalibration>();
+        app.insert_resource(calibration);
+        app.add_event::<CalibrateXrSpace>();
+        app.add_systems(
+            PreUpdate,
+            (calibrate_xr_space, apply_calibration)
+                .chain()
+                .run_if(xr_only())
+                .after(XrPollEvents)
+                .before(XrWaitFrame),
+        );
+        if let Some(path) = self.path.clone() {
+            app.add_systems(
+                Last,
+                (move |calibration: Res<XrCalibration>| {
+                    if calibration.is_added() {
+                        return;
+                    }
+                    if let Err(err) = calibration.save(&path) {
+                        warn!("Unable to save the calibration to {:?}: {}", path, err);
+                    }
+                })
+                .run_if(resource_changed::<XrCalibration>),
+            );
+        }
+    }
+}
+
+/// The offset of the stage space from the reference space of the runtime. Everything is located
+/// in the stage space, so the views, controllers, hands and anchors all include it.
+/// [`RecenterXrSpace`](crate::input::RecenterXrSpace) recenters within the calibrated space.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[reflect(Resource)]
+pub struct XrCalibration {
+    /// How far the floor is above the one the runtime reports, in meters
+    pub height_offset: f32,
+    /// How far forward is turned to the left of the one the runtime reports, in radians
+    pub yaw_offset: f32,
+}
+
+impl XrCalibration {
+    /// The pose of the calibrated space in the reference space of the runtime
+    pub fn transform(&self) -> Transform {
+        Transform::from_xyz(0.0, self.height_offset, 0.0)
+            .with_rotation(Quat::from_rotation_y(self.yaw_offset))
+    }
+
+    /// Reads the calibration from a file, a missing file has no calibration
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, XrCalibrationError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(default()),
+            Err(err) => return Err(err.into()),
+        };
+        Self::parse(&text)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), XrCalibrationError> {
+        std::fs::write(path, self.to_text())?;
+        Ok(())
+    }
+
+    /// One `name: value` line per offset
+    pub fn to_text(&self) -> String {
+        format!(
+            "height_offset: {}\nyaw_offset: {}\n",
+            self.height_offset, self.yaw_offset
+        )
+    }
+
+    /// Reads the lines written by [`XrCalibration::to_text`], missing offsets are zero
+    pub fn parse(text: &str) -> Result<Self, XrCalibrationError> {
+        let mut calibration = Self::default();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let invalid = || XrCalibrationError::Parse(line.to_owned());
+            let (name, value) = line.split_once(':').ok_or_else(invalid)?;
+            let value = value.trim().parse::<f32>().map_err(|_| invalid())?;
+            match name.trim() {
+                "height_offset" => calibration.height_offset = value,
+                "yaw_offset" => calibration.yaw_offset = value,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(calibration)
+    }
+}
+
+#[derive(Debug)]
+pub enum XrCalibrationError {
+    Io(std::io::Error),
+    /// The line that couldn't be read
+    Parse(String),
+}
+
+impl std::fmt::Display for XrCalibrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XrCalibrationError::Io(err) => write!(f, "{}", err),
+            XrCalibrationError::Parse(line) => write!(f, "Invalid calibration line: {}", line),
+        }
+    }
+}
+
+impl std::error::Error for XrCalibrationError {}
+
+impl From<std::io::Error> for XrCalibrationError {
+    fn from(value: std::io::Error) -> Self {
+        XrCalibrationError::Io(value)
+    }
+}
+
+/// Changes the [`XrCalibration`] from the current poses
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub enum CalibrateXrSpace {
+    /// Puts the floor `clearance` meters below the grip pose of a controller, the grip is a
+    /// few centimeters above the floor while the controller lies on it
+    FloorBelowController { hand: Hand, clearance: f32 },
+    /// Turns forward to the direction the headset is facing
+    ForwardToHeadset,
+    /// Goes back to the floor and forward direction of the runtime
+    Reset,
+}
+
+fn calibrate_xr_space(
+    mut events: EventReader<CalibrateXrSpace>,
+    mut calibration: ResMut<XrCalibration>,
+    xr_input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+    oculus_controller: Option<Res<OculusController>>,
+) {
+    let time = xr_input.pose_time(frame_state.predicted_display_time.into());
+    for event in events.read() {
+        let space = match event {
+            CalibrateXrSpace::FloorBelowController { hand, .. } => {
+                let spaces = oculus_controller
+                    .as_ref()
+                    .and_then(|controller| controller.grip_space.as_ref());
+                let Some(spaces) = spaces else {
+                    warn!("Unable to calibrate the floor without controllers");
+                    continue;
+                };
+                match hand {
+                    Hand::Left => &spaces.left,
+                    Hand::Right => &spaces.right,
+                }
+            }
+            CalibrateXrSpace::ForwardToHeadset => &xr_input.head,
+            CalibrateXrSpace::Reset => {
+                *calibration = default();
+                continue;
+            }
+        };
+        let state = match xr_input.locate(space, time) {
+            Ok(state) => state,
+            Err(err) => {
+                warn!("Unable to calibrate: {}", err);
+                continue;
+            }
+        };
+        // the stage is only ever turned around the up axis, so the offsets add up
+        match *event {
+            CalibrateXrSpace::FloorBelowController { clearance, .. } => {
+                let Some(position) = state.position else {
+                    warn!("Unable to calibrate the floor, the controller isn't tracked");
+                    continue;
+                };
+                calibration.height_offset += position.y - clearance;
+            }
+            CalibrateXrSpace::ForwardToHeadset => {
+                let Some(orientation) = state.orientation else {
+                    warn!("Unable to calibrate forward, the headset isn't tracked");
+                    continue;
+                };
+                let (yaw, _, _) = orientation.to_euler(EulerRot::YXZ);
+                calibration.yaw_offset += yaw;
+            }
+            CalibrateXrSpace::Reset => {}
+        }
+    }
+}
+
+/// Recreates the stage space when the [`XrCalibration`] differs from the one it was created with
+fn apply_calibration(
+    calibration: Res<XrCalibration>,
+    mut xr_input: ResMut<XrInput>,
+    session: Res<XrSession>,
+) {
+    if xr_input.calibration == *calibration {
+        return;
+    }
+    if let Err(err) = xr_input.set_calibration(&session, *calibration) {
+        error!("Unable to apply the calibration: {}", err);
+    }
+}
@@ -0,0 +1,315 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Buffer, Extent3d, TextureDimension, TextureFormat};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::{Render, RenderApp, RenderSet};
+
+use crate::resources::{XrResolution, XrSwapchain};
+use crate::xr_end_frame;
+use crate::xr_init::{xr_after_wait_only, xr_only, xr_render_only};
+use crate::xr_input::xr_camera::Eye;
+
+/// Captures the eye images of the next rendered frame exactly as they are submitted to the
+/// compositor, the result arrives a few frames later as an [`XrFrameCaptured`] event
+#[derive(Clone, Copy, Debug, Default, Event)]
+pub struct CaptureXrFrame;
+
+/// The eye images of a captured frame as sRGB encoded RGBA8 pixels, row by row without padding
+#[derive(Clone, Debug, Event)]
+pub struct XrFrameCaptured {
+    pub resolution: UVec2,
+    pub left: Vec<u8>,
+    pub right: Vec<u8>,
+}
+
+impl XrFrameCaptured {
+    /// The focus views of quad views aren't captured, they return the context view of their side
+    pub fn eye(&self, eye: Eye) -> &[u8] {
+        match eye {
+            Eye::Left | Eye::LeftFocus => &self.left,
+            Eye::Right | Eye::RightFocus => &self.right,
+        }
+    }
+
+    /// Creates an [`Image`] of one eye, which can be saved with [`Image::try_into_dynamic`]
+    pub fn to_image(&self, eye: Eye) -> Image {
+        Image::new(
+            Extent3d {
+                width: self.resolution.x,
+                height: self.resolution.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            self.eye(eye).to_vec(),
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::MAIN_WORLD,
+        )
+    }
+}
+
+pub struct FrameCapturePlugin;
+
+impl Plugin for FrameCapturePlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = channel();
+        app.add_event::<CaptureXrFrame>();
+        app.add_event::<XrFrameCaptured>();
+        app.init_resource::<XrCaptureRequests>();
+        app.insert_resource(CapturedFrameReceiver(Mutex::new(receiver)));
+        app.add_plugins(ExtractResourcePlugin::<XrCaptureRequests>::default());
+        app.add_systems(
+            PreUpdate,
+            (
+                count_capture_requests.run_if(on_event::<CaptureXrFrame>()),
+                receive_captured_frames,
+            ),
+        );
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.insert_resource(CapturedFrameSender(sender));
+        render_app.init_resource::<PendingCaptures>();
+        render_app.add_systems(
+            Render,
+            (
+                // the swapchain image is released when the frame ends
+                copy_swapchain_image
+                    .run_if(xr_only())
+                    .run_if(xr_after_wait_only())
+                    .run_if(xr_render_only())
+                    .before(xr_end_frame),
+                map_captured_frames,
+            )
+                .chain()
+                .in_set(RenderSet::Cleanup),
+        );
+    }
+}
+
+/// How many captures were requested so far, the render world captures a frame whenever this is
+/// ahead of the number of frames it captured
+#[derive(Clone, Copy, Debug, Default, Resource, ExtractResource)]
+struct XrCaptureRequests(u64);
+
+#[derive(Resource)]
+struct CapturedFrameReceiver(Mutex<Receiver<XrFrameCaptured>>);
+
+#[derive(Resource)]
+struct CapturedFrameSender(Sender<XrFrameCaptured>);
+
+/// Frames that were copied into a buffer which isn't mapped yet
+#[derive(Default, Resource)]
+struct PendingCaptures {
+    captured: u64,
+    frames: Vec<PendingCapture>,
+}
+
+struct PendingCapture {
+    buffer: Buffer,
+    mapped: Mutex<Receiver<Result<(), wgpu::BufferAsyncError>>>,
+    format: wgpu::TextureFormat,
+    resolution: UVec2,
+    padded_bytes_per_row: u32,
+}
+
+fn count_capture_requests(
+    mut events: EventReader<CaptureXrFrame>,
+    mut requests: ResMut<XrCaptureRequests>,
+) {
+    requests.0 += events.read().count() as u64;
+}
+
+fn receive_captured_frames(
+    receiver: Res<CapturedFrameReceiver>,
+    mut captured: EventWriter<XrFrameCaptured>,
+) {
+    captured.send_batch(receiver.0.lock().unwrap().try_iter());
+}
+
+fn copy_swapchain_image(
+    requests: Res<XrCaptureRequests>,
+    mut pending: ResMut<PendingCaptures>,
+    swapchain: Res<XrSwapchain>,
+    resolution: Res<XrResolution>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    if requests.0 <= pending.captured {
+        return;
+    }
+    // every request that came in until now is answered by this frame
+    pending.captured = requests.0;
+    let texture = swapchain.color_texture();
+    let format = texture.format();
+    let Some(block_size) = format.block_copy_size(None) else {
+        warn!(
+            "Unable to capture swapchain images with format {:?}",
+            format
+        );
+        return;
+    };
+    let _span = info_span!("xr_capture_frame").entered();
+    // one layer per eye, or a single one in mono mode
+    let layers = texture.depth_or_array_layers();
+    let padded_bytes_per_row =
+        (resolution.x * block_size).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let buffer = render_device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("xr_capture_buffer"),
+        size: padded_bytes_per_row as u64 * resolution.y as u64 * layers as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = render_device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("xr_capture_encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(resolution.y),
+            },
+        },
+        wgpu::Extent3d {
+            width: resolution.x,
+            height: resolution.y,
+            depth_or_array_layers: layers,
+        },
+    );
+    render_queue.submit([encoder.finish()]);
+    let (sender, mapped) = channel();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+    pending.frames.push(PendingCapture {
+        buffer,
+        mapped: Mutex::new(mapped),
+        format,
+        resolution: **resolution,
+        padded_bytes_per_row,
+    });
+}
+
+/// Converts the frames whose buffers are mapped and hands them to the main world
+fn map_captured_frames(
+    mut pending: ResMut<PendingCaptures>,
+    render_device: Res<RenderDevice>,
+    sender: Res<CapturedFrameSender>,
+) {
+    if pending.frames.is_empty() {
+        return;
+    }
+    render_device.poll(wgpu::Maintain::Poll);
+    pending.frames.retain(|frame| {
+        let result = match frame.mapped.lock().unwrap().try_recv() {
+            Ok(result) => result,
+            Err(std::sync::mpsc::TryRecvError::Empty) => return true,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => return false,
+        };
+        if let Err(err) = result {
+            warn!("Unable to map the captured frame: {}", err);
+            return false;
+        }
+        let captured = {
+            let data = frame.buffer.slice(..).get_mapped_range();
+            let image_size = (frame.padded_bytes_per_row * frame.resolution.y) as usize;
+            let (left, right) = data.split_at(image_size);
+            // a mono frame only has one layer, which both eyes see
+            let right = if right.is_empty() { left } else { right };
+            match (frame.to_rgba8(left), frame.to_rgba8(right)) {
+                (Some(left), Some(right)) => Some(XrFrameCaptured {
+                    resolution: frame.resolution,
+                    left,
+                    right,
+                }),
+                _ => None,
+            }
+        };
+        frame.buffer.unmap();
+        match captured {
+            Some(captured) => {
+                let _ = sender.0.send(captured);
+            }
+            None => warn!(
+                "Unable to convert captured frame from format {:?}",
+                frame.format
+            ),
+        }
+        false
+    });
+}
+
+impl PendingCapture {
+    /// Removes the row padding and converts the pixels to sRGB encoded RGBA8.
+    /// Non sRGB formats are shown by the compositor as linear, so they're encoded here.
+    fn to_rgba8(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let block_size = self.format.block_copy_size(None)? as usize;
+        let row_size = self.resolution.x as usize * block_size;
+        let mut out =
+            Vec::with_capacity(self.resolution.x as usize * self.resolution.y as usize * 4);
+        for row in data.chunks_exact(self.padded_bytes_per_row as usize) {
+            for texel in row[..row_size].chunks_exact(block_size) {
+                out.extend_from_slice(&convert_texel(self.format, texel)?);
+            }
+        }
+        Some(out)
+    }
+}
+
+fn convert_texel(format: wgpu::TextureFormat, texel: &[u8]) -> Option<[u8; 4]> {
+    use wgpu::TextureFormat::*;
+    let linear = |r: f32, g: f32, b: f32, a: f32| Color::rgba_linear(r, g, b, a).as_rgba_u8();
+    let unorm8 = |x: u8| x as f32 / 255.0;
+    let unorm16 = |i: usize| u16::from_le_bytes([texel[i], texel[i + 1]]) as f32 / 65535.0;
+    let float16 = |i: usize| f16_to_f32(u16::from_le_bytes([texel[i], texel[i + 1]]));
+    let float32 =
+        |i: usize| f32::from_le_bytes([texel[i], texel[i + 1], texel[i + 2], texel[i + 3]]);
+    Some(match format {
+        Rgba8UnormSrgb => [texel[0], texel[1], texel[2], texel[3]],
+        Bgra8UnormSrgb => [texel[2], texel[1], texel[0], texel[3]],
+        Rgba8Unorm => linear(
+            unorm8(texel[0]),
+            unorm8(texel[1]),
+            unorm8(texel[2]),
+            unorm8(texel[3]),
+        ),
+        Bgra8Unorm => linear(
+            unorm8(texel[2]),
+            unorm8(texel[1]),
+            unorm8(texel[0]),
+            unorm8(texel[3]),
+        ),
+        Rgb10a2Unorm => {
+            let bits = u32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]);
+            let channel = |shift: u32| ((bits >> shift) & 0x3ff) as f32 / 1023.0;
+            linear(
+                channel(0),
+                channel(10),
+                channel(20),
+                (bits >> 30) as f32 / 3.0,
+            )
+        }
+        Rgba16Unorm => linear(unorm16(0), unorm16(2), unorm16(4), unorm16(6)),
+        Rgba16Float => linear(float16(0), float16(2), float16(4), float16(6)),
+        Rgba32Float => linear(float32(0), float32(4), float32(8), float32(12)),
+        _ => return None,
+    })
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
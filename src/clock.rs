@@ -0,0 +1,118 @@
+//! Converts between the runtime's [`XrTime`] and [`Instant`], to correlate action change times,
+//! predicted display times and timestamps of other sensors.
+//!
+//! With `XR_KHR_convert_timespec_time`, or `XR_KHR_win32_convert_performance_counter_time` on
+//! windows, the runtime converts the times exactly, both are requested by default. Otherwise the
+//! offset between the clocks is estimated from the frames the runtime hands out, which is only
+//! accurate to about a display period and unavailable before the first frame was waited for.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime};
+
+use openxr as xr;
+
+use crate::resources::{XrInstance, XrTime};
+
+/// Estimated runtime time minus [`epoch_nanos`], `i64::MIN` until the first frame
+static ESTIMATED_OFFSET: AtomicI64 = AtomicI64::new(i64::MIN);
+
+fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+/// Signed nanoseconds of `instant` since the [`epoch`]
+fn epoch_nanos(instant: Instant) -> i64 {
+    let epoch = epoch();
+    match instant.checked_duration_since(epoch) {
+        Some(since) => since.as_nanos() as i64,
+        None => -((epoch - instant).as_nanos() as i64),
+    }
+}
+
+/// Refines the estimated offset with a frame that was waited for at `waited_at`.
+/// `xrWaitFrame` returns about a display period before the predicted display time, so each frame
+/// bounds the offset. The estimate follows the smallest bound and slowly drifts up again,
+/// in case the runtime returned early once.
+pub(crate) fn observe_frame(frame_state: &xr::FrameState, waited_at: Instant) {
+    let sample = frame_state.predicted_display_time.as_nanos()
+        - frame_state.predicted_display_period.as_nanos()
+        - epoch_nanos(waited_at);
+    let _ = ESTIMATED_OFFSET.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |estimate| {
+        Some(match estimate {
+            i64::MIN => sample,
+            estimate if sample < estimate => sample,
+            estimate => estimate + (sample - estimate) / 64,
+        })
+    });
+}
+
+impl XrInstance {
+    /// Whether the runtime converts times exactly instead of them being estimated
+    pub fn supports_time_conversion(&self) -> bool {
+        #[cfg(not(windows))]
+        return self.exts().khr_convert_timespec_time.is_some();
+        #[cfg(windows)]
+        return self
+            .exts()
+            .khr_win32_convert_performance_counter_time
+            .is_some();
+    }
+
+    /// The runtime time minus the nanoseconds since the [`epoch`]
+    fn time_offset(&self) -> Option<i64> {
+        if self.supports_time_conversion() {
+            // Instant uses the clocks the extensions convert from, so sampling both right
+            // after one another gives the offset to well below a microsecond
+            match self.now() {
+                Ok(now) => return Some(now.as_nanos() - epoch_nanos(Instant::now())),
+                Err(err) => bevy::log::warn!("Unable to convert the current time: {}", err),
+            }
+        }
+        let estimate = ESTIMATED_OFFSET.load(Ordering::Relaxed);
+        (estimate != i64::MIN).then_some(estimate)
+    }
+
+    /// The runtime time at `instant`, `None` if the runtime can't convert times and no frame
+    /// was waited for yet
+    pub fn to_xr_time(&self, instant: Instant) -> Option<XrTime> {
+        Some(XrTime(self.time_offset()? + epoch_nanos(instant)))
+    }
+
+    /// The [`Instant`] at the runtime time `time`, `None` if the runtime can't convert times and
+    /// no frame was waited for yet
+    pub fn from_xr_time(&self, time: XrTime) -> Option<Instant> {
+        let nanos = time.0 - self.time_offset()?;
+        match nanos >= 0 {
+            true => epoch().checked_add(Duration::from_nanos(nanos as u64)),
+            false => epoch().checked_sub(Duration::from_nanos(nanos.unsigned_abs())),
+        }
+    }
+
+    /// The current runtime time, see [`XrInstance::to_xr_time`]
+    pub fn xr_time_now(&self) -> Option<XrTime> {
+        self.to_xr_time(Instant::now())
+    }
+
+    /// The system time at the runtime time `time`. Unlike runtime times and instants, system
+    /// times can be compared between devices whose clocks are synchronized, like over NTP.
+    pub fn to_system_time(&self, time: XrTime) -> Option<SystemTime> {
+        let instant = self.from_xr_time(time)?;
+        let (now, system_now) = (Instant::now(), SystemTime::now());
+        match instant.checked_duration_since(now) {
+            Some(ahead) => system_now.checked_add(ahead),
+            None => system_now.checked_sub(now - instant),
+        }
+    }
+
+    /// The runtime time at the system time `time`, see [`XrInstance::to_system_time`]
+    pub fn from_system_time(&self, time: SystemTime) -> Option<XrTime> {
+        let (now, system_now) = (Instant::now(), SystemTime::now());
+        let instant = match time.duration_since(system_now) {
+            Ok(ahead) => now.checked_add(ahead)?,
+            Err(behind) => now.checked_sub(behind.duration())?,
+        };
+        self.to_xr_time(instant)
+    }
+}
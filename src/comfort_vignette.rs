@@ -0,0 +1,448 @@
+//! A tunnel vignette against motion sickness, darkening the periphery of the xr cameras while
+//! the player moves artificially.
+
+use bevy::asset::load_internal_asset;
+use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
+use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
+use bevy::ecs::query::QueryItem;
+use bevy::prelude::*;
+use bevy::render::camera::ExtractedCamera;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+};
+use bevy::render::render_resource::binding_types::{texture_2d, uniform_buffer_sized};
+use bevy::render::render_resource::{
+    BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, BufferInitDescriptor, BufferUsages,
+    CachedRenderPipelineId, ColorTargetState, ColorWrites, FragmentState, MultisampleState,
+    Operations, PipelineCache, PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor,
+    RenderPipelineDescriptor, ShaderStages, SpecializedRenderPipeline, SpecializedRenderPipelines,
+    TextureFormat, TextureSampleType,
+};
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::view::ViewTarget;
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy::transform::TransformSystem;
+use openxr as xr;
+
+use crate::resources::XrViews;
+use crate::xr_init::{xr_only, XrSetup, XrShouldRender};
+use crate::xr_input::trackers::{OpenXRHMD, OpenXRTrackingRoot};
+use crate::xr_input::xr_camera::XrCamera;
+
+const COMFORT_VIGNETTE_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x9e3a_41c7_6b02_4d58_a1f4_7c2e_53b9_0d16);
+
+/// Darkens the periphery of the xr cameras with the settings of [`XrComfortVignette`]. The motion
+/// is measured from the [`OpenXRTrackingRoot`], so it works with any locomotion that moves or
+/// turns the root. The vignette is drawn after the main pass post processing of each view, so it
+/// covers the effects of the app instead of being covered by them.
+pub struct XrComfortVignettePlugin;
+
+impl Plugin for XrComfortVignettePlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            COMFORT_VIGNETTE_SHADER_HANDLE,
+            "comfort_vignette.wgsl",
+            Shader::from_wgsl
+        );
+        app.register_type::<XrComfortVignette>();
+        app.register_type::<XrVignetteIntensity>();
+        app.init_resource::<XrComfortVignette>();
+        app.init_resource::<XrVignetteIntensity>();
+        app.init_resource::<VignetteMotion>();
+        app.add_plugins((
+            ExtractResourcePlugin::<XrComfortVignette>::default(),
+            ExtractResourcePlugin::<XrVignetteIntensity>::default(),
+        ));
+        app.add_systems(XrSetup, reset_vignette);
+        app.add_systems(
+            PostUpdate,
+            update_vignette_intensity
+                .after(TransformSystem::TransformPropagate)
+                .run_if(xr_only()),
+        );
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .init_resource::<SpecializedRenderPipelines<XrComfortVignettePipeline>>()
+            .add_systems(
+                Render,
+                prepare_vignette_pipelines.in_set(RenderSet::Prepare),
+            )
+            .add_render_graph_node::<ViewNodeRunner<XrComfortVignetteNode>>(
+                Core3d,
+                XrComfortVignetteLabel,
+            )
+            // the post processing of the app runs before the end of the main pass post processing
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::EndMainPassPostProcessing,
+                    XrComfortVignetteLabel,
+                    Node3d::Upscaling,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .init_resource::<XrComfortVignettePipeline>();
+    }
+}
+
+/// Maps a motion to how much it darkens the view, from 0.0 at `start` to 1.0 at `full`
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XrVignetteInput {
+    pub start: f32,
+    pub full: f32,
+}
+
+impl XrVignetteInput {
+    pub fn new(start: f32, full: f32) -> Self {
+        Self { start, full }
+    }
+
+    pub fn amount(&self, value: f32) -> f32 {
+        ((value - self.start) / (self.full - self.start).max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+}
+
+/// Shapes the strongest input before it's scaled by [`XrComfortVignette::max_intensity`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum XrVignetteCurve {
+    Linear,
+    /// Eases in and out, small motions barely show
+    #[default]
+    SmoothStep,
+    /// The input raised to this power
+    Power(f32),
+}
+
+impl XrVignetteCurve {
+    pub fn apply(&self, amount: f32) -> f32 {
+        let amount = amount.clamp(0.0, 1.0);
+        match *self {
+            XrVignetteCurve::Linear => amount,
+            XrVignetteCurve::SmoothStep => amount * amount * (3.0 - 2.0 * amount),
+            XrVignetteCurve::Power(power) => amount.powf(power.max(0.0)),
+        }
+    }
+}
+
+/// The look of the vignette and the motions that drive it. The strongest input sets the
+/// intensity, so any of them alone can darken the view fully. It's reflected and can be tuned
+/// from an inspector while the app runs.
+#[derive(Clone, Copy, Debug, PartialEq, Resource, ExtractResource, Reflect)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[reflect(Resource)]
+pub struct XrComfortVignette {
+    pub enabled: bool,
+    /// The darkest the periphery gets, from 0.0 to 1.0
+    pub max_intensity: f32,
+    /// Angle from the view direction in radians where the darkening starts
+    pub inner_radius: f32,
+    /// Angle from the view direction in radians where the darkening is complete
+    pub outer_radius: f32,
+    pub color: Color,
+    pub curve: XrVignetteCurve,
+    /// Seconds to follow a rising intensity
+    pub attack: f32,
+    /// Seconds to follow a falling intensity
+    pub release: f32,
+    /// Speed of the tracking root in meters per second, the headset moving inside the play
+    /// area doesn't count
+    pub linear_speed: Option<XrVignetteInput>,
+    /// Turning speed of the tracking root in radians per second
+    pub angular_speed: Option<XrVignetteInput>,
+    /// Acceleration of the headset inside the play area in meters per second squared
+    pub head_acceleration: Option<XrVignetteInput>,
+    /// The tracking root moving farther in a frame is a teleport and doesn't darken the view
+    pub teleport_distance: f32,
+    /// The tracking root turning more in a frame is a snap turn and doesn't darken the view
+    pub snap_turn_angle: f32,
+}
+
+impl Default for XrComfortVignette {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_intensity: 1.0,
+            inner_radius: 30f32.to_radians(),
+            outer_radius: 50f32.to_radians(),
+            color: Color::BLACK,
+            curve: XrVignetteCurve::default(),
+            attack: 0.15,
+            release: 0.4,
+            linear_speed: Some(XrVignetteInput::new(0.2, 3.0)),
+            angular_speed: Some(XrVignetteInput::new(10f32.to_radians(), 90f32.to_radians())),
+            head_acceleration: None,
+            teleport_distance: 0.5,
+            snap_turn_angle: 15f32.to_radians(),
+        }
+    }
+}
+
+/// The current darkness of the periphery, from 0.0 to [`XrComfortVignette::max_intensity`].
+/// Written every frame from the motion of the tracking root.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Resource, ExtractResource, Reflect, Deref)]
+#[reflect(Resource)]
+pub struct XrVignetteIntensity(pub f32);
+
+/// The poses of the previous frame
+#[derive(Resource, Default)]
+struct VignetteMotion {
+    root: Option<GlobalTransform>,
+    head: Option<Vec3>,
+    head_velocity: Option<Vec3>,
+}
+
+fn reset_vignette(mut motion: ResMut<VignetteMotion>, mut intensity: ResMut<XrVignetteIntensity>) {
+    *motion = VignetteMotion::default();
+    intensity.0 = 0.0;
+}
+
+fn update_vignette_intensity(
+    time: Res<Time>,
+    settings: Res<XrComfortVignette>,
+    mut intensity: ResMut<XrVignetteIntensity>,
+    mut motion: ResMut<VignetteMotion>,
+    root: Query<&GlobalTransform, With<OpenXRTrackingRoot>>,
+    head: Query<&Transform, (With<OpenXRHMD>, Without<OpenXRTrackingRoot>)>,
+) {
+    let delta = time.delta_seconds();
+    let (Ok(root), Ok(head)) = (root.get_single(), head.get_single()) else {
+        return;
+    };
+    if delta <= 0.0 {
+        return;
+    }
+    let mut amount: f32 = 0.0;
+    if let Some(previous) = motion.root {
+        // the same head pose under both roots, so turning around the head doesn't count as moving
+        let distance = root
+            .transform_point(head.translation)
+            .distance(previous.transform_point(head.translation));
+        let angle = root
+            .to_scale_rotation_translation()
+            .1
+            .angle_between(previous.to_scale_rotation_translation().1);
+        if let Some(input) = settings.linear_speed {
+            if distance <= settings.teleport_distance {
+                amount = amount.max(input.amount(distance / delta));
+            }
+        }
+        if let Some(input) = settings.angular_speed {
+            if angle <= settings.snap_turn_angle {
+                amount = amount.max(input.amount(angle / delta));
+            }
+        }
+    }
+    let head_velocity = motion
+        .head
+        .map(|previous| (head.translation - previous) / delta);
+    if let (Some(input), Some(velocity), Some(previous)) = (
+        settings.head_acceleration,
+        head_velocity,
+        motion.head_velocity,
+    ) {
+        amount = amount.max(input.amount(velocity.distance(previous) / delta));
+    }
+    *motion = VignetteMotion {
+        root: Some(*root),
+        head: Some(head.translation),
+        head_velocity,
+    };
+
+    let target = match settings.enabled {
+        true => settings.curve.apply(amount) * settings.max_intensity.clamp(0.0, 1.0),
+        false => 0.0,
+    };
+    let response = match target > intensity.0 {
+        true => settings.attack,
+        false => settings.release,
+    };
+    let blend = match response > 0.0 {
+        true => 1.0 - (-delta / response).exp(),
+        false => 1.0,
+    };
+    let next = intensity.0 + (target - intensity.0) * blend;
+    if next != intensity.0 {
+        intensity.0 = next;
+    }
+}
+
+#[derive(Resource)]
+struct XrComfortVignettePipeline {
+    layout: BindGroupLayout,
+}
+
+impl FromWorld for XrComfortVignettePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "xr_comfort_vignette_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: false }),
+                    uniform_buffer_sized(false, None),
+                ),
+            ),
+        );
+        Self { layout }
+    }
+}
+
+impl SpecializedRenderPipeline for XrComfortVignettePipeline {
+    /// The format of the main texture of the view
+    type Key = TextureFormat;
+
+    fn specialize(&self, format: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("xr_comfort_vignette_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: fullscreen_shader_vertex_state(),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                shader: COMFORT_VIGNETTE_SHADER_HANDLE,
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+        }
+    }
+}
+
+/// The pipeline a view draws the vignette with
+#[derive(Component)]
+struct XrComfortVignetteView(CachedRenderPipelineId);
+
+fn prepare_vignette_pipelines(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<XrComfortVignettePipeline>>,
+    pipeline: Res<XrComfortVignettePipeline>,
+    views: Query<(Entity, &ViewTarget), With<XrCamera>>,
+) {
+    for (entity, target) in &views {
+        let id = pipelines.specialize(&pipeline_cache, &pipeline, target.main_texture_format());
+        commands.entity(entity).insert(XrComfortVignetteView(id));
+    }
+}
+
+/// Uniform data of the shader, the padding keeps the std140 layout
+fn vignette_data(settings: &XrComfortVignette, intensity: f32, fov: &xr::Fovf) -> Vec<u8> {
+    let outer_radius = settings.outer_radius.max(settings.inner_radius + 0.001);
+    let [red, green, blue, alpha] = settings.color.as_linear_rgba_f32();
+    [
+        fov.angle_left.tan(),
+        fov.angle_up.tan(),
+        fov.angle_right.tan(),
+        fov.angle_down.tan(),
+        red,
+        green,
+        blue,
+        alpha,
+        settings.inner_radius,
+        outer_radius,
+        intensity,
+        0.0,
+    ]
+    .into_iter()
+    .flat_map(f32::to_le_bytes)
+    .collect()
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct XrComfortVignetteLabel;
+
+#[derive(Default)]
+struct XrComfortVignetteNode;
+
+impl ViewNode for XrComfortVignetteNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static ExtractedCamera,
+        &'static XrCamera,
+        &'static XrComfortVignetteView,
+    );
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (target, camera, xr_camera, vignette_view): QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        if !world
+            .get_resource::<XrShouldRender>()
+            .is_some_and(|should_render| **should_render)
+        {
+            return Ok(());
+        }
+        let (Some(settings), Some(intensity), Some(views)) = (
+            world.get_resource::<XrComfortVignette>(),
+            world.get_resource::<XrVignetteIntensity>(),
+            world.get_resource::<XrViews>(),
+        ) else {
+            return Ok(());
+        };
+        // a vignette that can't be seen costs a full screen pass
+        if !settings.enabled || intensity.0 <= 0.001 {
+            return Ok(());
+        }
+        let Some(view) = views.get(xr_camera.eye() as usize) else {
+            return Ok(());
+        };
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(vignette_view.0)
+        else {
+            return Ok(());
+        };
+        let render_device = render_context.render_device();
+        let uniform = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("xr_comfort_vignette_uniform_buffer"),
+            contents: &vignette_data(settings, intensity.0, &view.fov),
+            usage: BufferUsages::UNIFORM,
+        });
+        let post_process = target.post_process_write();
+        let bind_group = render_device.create_bind_group(
+            "xr_comfort_vignette_bind_group",
+            &world.resource::<XrComfortVignettePipeline>().layout,
+            &BindGroupEntries::sequential((post_process.source, uniform.as_entire_binding())),
+        );
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("xr_comfort_vignette_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        // only the rendered part of the image, see the dynamic resolution
+        if let Some(viewport) = &camera.viewport {
+            render_pass.set_camera_viewport(viewport);
+        }
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
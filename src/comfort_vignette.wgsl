@@ -0,0 +1,27 @@
+// Darkens the periphery of a view. The radii are angles from the view direction, so both eyes
+// get the same vignette with their asymmetric fields of view.
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct ComfortVignette {
+    // tangents of the left and up angle of the view
+    tan_min: vec2<f32>,
+    // tangents of the right and down angle of the view
+    tan_max: vec2<f32>,
+    color: vec4<f32>,
+    inner_radius: f32,
+    outer_radius: f32,
+    intensity: f32,
+    _padding: f32,
+}
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> vignette: ComfortVignette;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureLoad(screen_texture, vec2<i32>(in.position.xy), 0);
+    let tangent = mix(vignette.tan_min, vignette.tan_max, in.uv);
+    let angle = atan(length(tangent));
+    let amount = smoothstep(vignette.inner_radius, vignette.outer_radius, angle) * vignette.intensity;
+    return vec4<f32>(mix(color.rgb, vignette.color.rgb, amount), color.a);
+}
@@ -0,0 +1,180 @@
+//! Forwards the messages of the runtime and of validation layers into bevy's log and names our
+//! OpenXR objects, enabled with [`DefaultXrPlugins::debug_utils`](crate::DefaultXrPlugins::debug_utils).
+
+use std::borrow::Cow;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use bevy::prelude::*;
+use openxr as xr;
+use xr::sys::{DebugUtilsMessageSeverityFlagsEXT as Severity, DebugUtilsMessageTypeFlagsEXT};
+
+use crate::resources::{XrInstance, XrSession, XrSwapchain};
+use crate::xr_init::XrPostSetup;
+use crate::xr_input::actions::XrActionSets;
+
+/// Creates the debug messenger once the instance exists, it needs
+/// [`XrExtensions::enable_debug_utils`](crate::graphics::extensions::XrExtensions::enable_debug_utils).
+/// Messages are logged with the `openxr` target, so they can be filtered like the ones of any
+/// other crate.
+pub struct XrDebugUtilsPlugin {
+    /// The severities that are forwarded, defaults to warnings and errors
+    pub severities: Severity,
+}
+
+impl Default for XrDebugUtilsPlugin {
+    fn default() -> Self {
+        Self {
+            severities: Severity::WARNING | Severity::ERROR,
+        }
+    }
+}
+
+impl Plugin for XrDebugUtilsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(XrPostSetup, name_session_objects);
+    }
+
+    // the instance is created while the OpenXrPlugin is built
+    fn finish(&self, app: &mut App) {
+        let Some(instance) = app.world.get_resource::<XrInstance>() else {
+            return;
+        };
+        let Some(debug_utils) = instance.exts().ext_debug_utils else {
+            warn!("XR_EXT_debug_utils isn't enabled, OpenXR messages won't be logged");
+            return;
+        };
+        let create_info = xr::sys::DebugUtilsMessengerCreateInfoEXT {
+            ty: xr::sys::DebugUtilsMessengerCreateInfoEXT::TYPE,
+            next: ptr::null(),
+            message_severities: self.severities,
+            message_types: DebugUtilsMessageTypeFlagsEXT::GENERAL
+                | DebugUtilsMessageTypeFlagsEXT::VALIDATION
+                | DebugUtilsMessageTypeFlagsEXT::PERFORMANCE
+                | DebugUtilsMessageTypeFlagsEXT::CONFORMANCE,
+            user_callback: Some(forward_message),
+            user_data: ptr::null_mut(),
+        };
+        let mut handle = xr::sys::DebugUtilsMessengerEXT::NULL;
+        let result = unsafe {
+            (debug_utils.create_debug_utils_messenger)(instance.as_raw(), &create_info, &mut handle)
+        };
+        if result.into_raw() < 0 {
+            warn!("Unable to create the OpenXR debug messenger: {}", result);
+            return;
+        }
+        set_object_name(
+            instance,
+            xr::sys::ObjectType::INSTANCE,
+            instance.as_raw().into_raw(),
+            "bevy_oxr instance",
+        );
+        let messenger = XrDebugMessenger {
+            instance: instance.clone(),
+            handle,
+        };
+        app.insert_resource(messenger);
+    }
+}
+
+/// Destroys the messenger when dropped, it keeps the instance alive until then
+#[derive(Resource)]
+pub struct XrDebugMessenger {
+    instance: XrInstance,
+    handle: xr::sys::DebugUtilsMessengerEXT,
+}
+
+impl Drop for XrDebugMessenger {
+    fn drop(&mut self) {
+        if let Some(debug_utils) = self.instance.exts().ext_debug_utils {
+            unsafe { (debug_utils.destroy_debug_utils_messenger)(self.handle) };
+        }
+    }
+}
+
+/// Names an OpenXR object in runtime messages, does nothing without `XR_EXT_debug_utils`
+pub fn set_object_name(
+    instance: &xr::Instance,
+    object_type: xr::sys::ObjectType,
+    object_handle: u64,
+    name: &str,
+) {
+    let Some(debug_utils) = instance.exts().ext_debug_utils else {
+        return;
+    };
+    let Ok(name) = CString::new(name) else {
+        return;
+    };
+    let info = xr::sys::DebugUtilsObjectNameInfoEXT {
+        ty: xr::sys::DebugUtilsObjectNameInfoEXT::TYPE,
+        next: ptr::null(),
+        object_type,
+        object_handle,
+        object_name: name.as_ptr(),
+    };
+    let result = unsafe { (debug_utils.set_debug_utils_object_name)(instance.as_raw(), &info) };
+    if result.into_raw() < 0 {
+        warn!("Unable to name the OpenXR object {:?}: {}", name, result);
+    }
+}
+
+fn name_session_objects(
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    swapchain: Res<XrSwapchain>,
+    action_sets: Option<Res<XrActionSets>>,
+) {
+    set_object_name(
+        &instance,
+        xr::sys::ObjectType::SESSION,
+        session.as_raw().into_raw(),
+        "bevy_oxr session",
+    );
+    set_object_name(
+        &instance,
+        xr::sys::ObjectType::SWAPCHAIN,
+        swapchain.as_raw().into_raw(),
+        "bevy_oxr color swapchain",
+    );
+    for (name, object_type, handle) in action_sets.iter().flat_map(|sets| sets.raw_handles()) {
+        set_object_name(&instance, object_type, handle, &name);
+    }
+}
+
+/// Called by the runtime from any thread, it must not unwind and only borrows the message
+unsafe extern "system" fn forward_message(
+    severity: Severity,
+    types: DebugUtilsMessageTypeFlagsEXT,
+    data: *const xr::sys::DebugUtilsMessengerCallbackDataEXT,
+    _user_data: *mut c_void,
+) -> xr::sys::Bool32 {
+    if !data.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+            let data = &*data;
+            let message_id = c_str(data.message_id);
+            let function_name = c_str(data.function_name);
+            let message = c_str(data.message);
+            if severity.contains(Severity::ERROR) {
+                error!(target: "openxr", "[{}] {} ({:?}): {}", message_id, function_name, types, message);
+            } else if severity.contains(Severity::WARNING) {
+                warn!(target: "openxr", "[{}] {} ({:?}): {}", message_id, function_name, types, message);
+            } else if severity.contains(Severity::INFO) {
+                info!(target: "openxr", "[{}] {} ({:?}): {}", message_id, function_name, types, message);
+            } else {
+                trace!(target: "openxr", "[{}] {} ({:?}): {}", message_id, function_name, types, message);
+            }
+        }));
+    }
+    // the call that caused the message must not be aborted
+    xr::sys::FALSE
+}
+
+/// Only allocates if the runtime passed invalid UTF-8
+unsafe fn c_str<'a>(ptr: *const c_char) -> Cow<'a, str> {
+    if ptr.is_null() {
+        Cow::Borrowed("")
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy()
+    }
+}
@@ -0,0 +1,225 @@
+//! Detects a lost render device, like after a driver reset, and hands the session to the
+//! [session recovery](crate::session_recovery) instead of panicking on the next wgpu error, see
+//! the `device_loss` example.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+
+use crate::xr_init::{ExitAppOnSessionExit, XrPollEvents, XrStatus};
+
+/// Sends [`XrDeviceLost`] when the render device is lost. The
+/// [`XrSessionRecoveryPlugin`](crate::session_recovery::XrSessionRecoveryPlugin) cleans up the
+/// session and starts a new one with new swapchains, unless the device itself is gone.
+pub struct XrDeviceLossPlugin;
+
+impl Plugin for XrDeviceLossPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrDeviceLoss>();
+        app.add_event::<XrDeviceLost>();
+        app.add_systems(Startup, watch_render_device);
+        app.add_systems(PreUpdate, handle_device_loss.before(XrPollEvents));
+    }
+}
+
+/// The render device was lost, the session recovery takes over. Only simulated losses get a
+/// new session, the render device is created through the OpenXR instance and can't be replaced.
+#[derive(Event, Clone, Debug, PartialEq, Eq)]
+pub struct XrDeviceLost {
+    pub reason: String,
+    /// Sent through [`XrDeviceLoss::simulate`], the device still works
+    pub simulated: bool,
+}
+
+#[derive(Debug, Default)]
+struct LossState {
+    /// A loss the main world didn't handle yet
+    pending: Option<(String, bool)>,
+    /// Set once the device reported its loss, it doesn't come back after that
+    lost: bool,
+}
+
+/// Shared with the callbacks of the render device
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrDeviceLoss(Arc<Mutex<LossState>>);
+
+impl XrDeviceLoss {
+    /// Handles a device loss with the next frame while the device keeps working, the session is
+    /// restarted like after a real loss
+    pub fn simulate(&self, reason: impl Into<String>) {
+        let mut state = self.0.lock().unwrap();
+        state.pending.get_or_insert((reason.into(), true));
+    }
+
+    /// Whether the render device reported that it's lost, simulated losses don't count
+    pub fn is_lost(&self) -> bool {
+        self.0.lock().unwrap().lost
+    }
+
+    fn report(&self, reason: String) {
+        let mut state = self.0.lock().unwrap();
+        if state.lost {
+            return;
+        }
+        error!("The render device was lost: {}", reason);
+        state.lost = true;
+        state.pending = Some((reason, false));
+    }
+}
+
+fn watch_render_device(render_device: Option<Res<RenderDevice>>, loss: Res<XrDeviceLoss>) {
+    let Some(render_device) = render_device else {
+        return;
+    };
+    let device = render_device.wgpu_device();
+    let callback_loss = loss.clone();
+    device.set_device_lost_callback(move |reason, message| {
+        // dropping the device on exit destroys it
+        if matches!(reason, wgpu::DeviceLostReason::Unknown) {
+            callback_loss.report(message);
+        }
+    });
+    let error_loss = loss.clone();
+    device.on_uncaptured_error(Box::new(move |error| {
+        // calls on the lost device keep failing until the session is cleaned up
+        if error_loss.is_lost() {
+            warn!("wgpu error on the lost render device: {}", error);
+            return;
+        }
+        // the same as the default handler of wgpu
+        error!("Handling wgpu errors as fatal by default");
+        panic!("wgpu error: {}\n", error);
+    }));
+}
+
+fn handle_device_loss(
+    loss: Res<XrDeviceLoss>,
+    status: Res<XrStatus>,
+    exit_type: Res<ExitAppOnSessionExit>,
+    mut events: EventWriter<XrDeviceLost>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let Some((reason, simulated)) = loss.0.lock().unwrap().pending.take() else {
+        return;
+    };
+    events.send(XrDeviceLost { reason, simulated });
+    // the session recovery doesn't run for apps that exit with the session
+    if *exit_type == ExitAppOnSessionExit::Always
+        && matches!(*status, XrStatus::Enabled | XrStatus::Enabling)
+    {
+        app_exit.send_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use bevy::ecs::event::Event;
+
+    use super::*;
+    use crate::error::XrErrorEvent;
+    use crate::resources::XrSessionRunning;
+    use crate::session_recovery::{XrRecoveryEvent, XrSessionRecoveryPlugin};
+    use crate::xr_init::{CleanupXrData, StartXrSession, XrInstanceLost, XrSessionStateChanged};
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(XrStatus::Enabled)
+            .insert_resource(XrSessionRunning::new(AtomicBool::new(true)))
+            .init_resource::<ExitAppOnSessionExit>()
+            .add_event::<XrSessionStateChanged>()
+            .add_event::<XrErrorEvent>()
+            .add_event::<XrInstanceLost>()
+            .add_event::<CleanupXrData>()
+            .add_event::<StartXrSession>()
+            .add_plugins((XrDeviceLossPlugin, XrSessionRecoveryPlugin));
+        app.update();
+        app
+    }
+
+    fn drain<E: Event>(app: &mut App) -> Vec<E> {
+        app.world.resource_mut::<Events<E>>().drain().collect()
+    }
+
+    /// Runs a frame after the cleanup or the new session changed the status
+    fn update_with_status(app: &mut App, status: XrStatus) {
+        *app.world.resource_mut::<XrStatus>() = status;
+        app.update();
+    }
+
+    #[test]
+    fn simulated_loss_restarts_the_session() {
+        let mut app = app();
+        app.world.resource::<XrDeviceLoss>().simulate("test");
+        app.update();
+        assert_eq!(
+            drain::<XrDeviceLost>(&mut app),
+            [XrDeviceLost {
+                reason: "test".into(),
+                simulated: true,
+            }]
+        );
+        assert_eq!(drain::<CleanupXrData>(&mut app).len(), 1);
+        assert!(!app
+            .world
+            .resource::<XrSessionRunning>()
+            .load(Ordering::Relaxed));
+        assert_eq!(
+            drain::<XrRecoveryEvent>(&mut app),
+            [XrRecoveryEvent::SessionLost]
+        );
+
+        update_with_status(&mut app, XrStatus::Disabled);
+        assert_eq!(drain::<StartXrSession>(&mut app).len(), 1);
+        assert_eq!(
+            drain::<XrRecoveryEvent>(&mut app),
+            [XrRecoveryEvent::Reconnecting { attempt: 1 }]
+        );
+
+        update_with_status(&mut app, XrStatus::Enabled);
+        assert_eq!(
+            drain::<XrRecoveryEvent>(&mut app),
+            [XrRecoveryEvent::Recovered]
+        );
+        assert!(!app.world.resource::<XrDeviceLoss>().is_lost());
+    }
+
+    #[test]
+    fn lost_device_gives_up() {
+        let mut app = app();
+        app.world.resource::<XrDeviceLoss>().report("lost".into());
+        app.update();
+        assert_eq!(
+            drain::<XrDeviceLost>(&mut app),
+            [XrDeviceLost {
+                reason: "lost".into(),
+                simulated: false,
+            }]
+        );
+        assert_eq!(drain::<CleanupXrData>(&mut app).len(), 1);
+
+        update_with_status(&mut app, XrStatus::Disabled);
+        assert!(drain::<StartXrSession>(&mut app).is_empty());
+        assert_eq!(
+            drain::<XrRecoveryEvent>(&mut app),
+            [XrRecoveryEvent::SessionLost, XrRecoveryEvent::GaveUp]
+        );
+    }
+
+    #[test]
+    fn loss_is_reported_once() {
+        let mut app = app();
+        let loss = app.world.resource::<XrDeviceLoss>().clone();
+        loss.report("lost".into());
+        loss.report("lost again".into());
+        loss.simulate("simulated");
+        app.update();
+        assert_eq!(drain::<XrDeviceLost>(&mut app).len(), 1);
+        app.update();
+        assert!(drain::<XrDeviceLost>(&mut app).is_empty());
+    }
+}
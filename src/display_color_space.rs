@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::graphics::XrSessionConfig;
+use crate::resources::XrSession;
+use crate::xr_init::XrSetup;
+
+/// The color space the compositor assumes the submitted images are in, it converts them to the
+/// color space of the display. Needs `XR_FB_color_space`,
+/// see [`XrExtensions::enable_display_color_space`](crate::graphics::extensions::XrExtensions::enable_display_color_space).
+///
+/// Not to be confused with [`XrColorSpace`](crate::resources::XrColorSpace),
+/// which is about the encoding of the swapchain images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum XrDisplayColorSpace {
+    /// No color correction is applied
+    Unmanaged,
+    Rec2020,
+    Rec709,
+    RiftCV1,
+    RiftS,
+    Quest,
+    P3,
+    AdobeRgb,
+}
+
+impl From<XrDisplayColorSpace> for xr::ColorSpaceFB {
+    fn from(value: XrDisplayColorSpace) -> Self {
+        match value {
+            XrDisplayColorSpace::Unmanaged => xr::ColorSpaceFB::UNMANAGED,
+            XrDisplayColorSpace::Rec2020 => xr::ColorSpaceFB::REC2020,
+            XrDisplayColorSpace::Rec709 => xr::ColorSpaceFB::REC709,
+            XrDisplayColorSpace::RiftCV1 => xr::ColorSpaceFB::RIFT_CV1,
+            XrDisplayColorSpace::RiftS => xr::ColorSpaceFB::RIFT_S,
+            XrDisplayColorSpace::Quest => xr::ColorSpaceFB::QUEST,
+            XrDisplayColorSpace::P3 => xr::ColorSpaceFB::P3,
+            XrDisplayColorSpace::AdobeRgb => xr::ColorSpaceFB::ADOBE_RGB,
+        }
+    }
+}
+
+impl TryFrom<xr::ColorSpaceFB> for XrDisplayColorSpace {
+    type Error = xr::ColorSpaceFB;
+
+    fn try_from(value: xr::ColorSpaceFB) -> Result<Self, Self::Error> {
+        Ok(match value {
+            xr::ColorSpaceFB::UNMANAGED => XrDisplayColorSpace::Unmanaged,
+            xr::ColorSpaceFB::REC2020 => XrDisplayColorSpace::Rec2020,
+            xr::ColorSpaceFB::REC709 => XrDisplayColorSpace::Rec709,
+            xr::ColorSpaceFB::RIFT_CV1 => XrDisplayColorSpace::RiftCV1,
+            xr::ColorSpaceFB::RIFT_S => XrDisplayColorSpace::RiftS,
+            xr::ColorSpaceFB::QUEST => XrDisplayColorSpace::Quest,
+            xr::ColorSpaceFB::P3 => XrDisplayColorSpace::P3,
+            xr::ColorSpaceFB::ADOBE_RGB => XrDisplayColorSpace::AdobeRgb,
+            other => return Err(other),
+        })
+    }
+}
+
+impl XrSession {
+    /// The color spaces the compositor can convert from, unknown values are skipped.
+    /// Returns `ERROR_EXTENSION_NOT_PRESENT` if `XR_FB_color_space` isn't enabled.
+    pub fn enumerate_display_color_spaces(&self) -> xr::Result<Vec<XrDisplayColorSpace>> {
+        if self.instance().exts().fb_color_space.is_none() {
+            return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+        }
+        Ok(self
+            .enumerate_color_spaces()?
+            .into_iter()
+            .filter_map(|color_space| color_space.try_into().ok())
+            .collect())
+    }
+
+    /// Tells the compositor which color space the submitted images are in.
+    /// Returns `ERROR_EXTENSION_NOT_PRESENT` if `XR_FB_color_space` isn't enabled.
+    pub fn set_display_color_space(&self, color_space: XrDisplayColorSpace) -> xr::Result<()> {
+        if self.instance().exts().fb_color_space.is_none() {
+            return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+        }
+        self.set_color_space(color_space.into())
+    }
+}
+
+pub struct DisplayColorSpacePlugin;
+
+impl Plugin for DisplayColorSpacePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<XrDisplayColorSpace>();
+        app.add_systems(XrSetup, apply_display_color_space);
+    }
+}
+
+fn apply_display_color_space(config: Res<XrSessionConfig>, session: Res<XrSession>) {
+    let Some(color_space) = config.display_color_space else {
+        return;
+    };
+    match session.set_display_color_space(color_space) {
+        Ok(()) => info!("Using display color space {:?}", color_space),
+        Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT) => {
+            warn!("Unable to set the display color space: XR_FB_color_space is not enabled")
+        }
+        Err(err) => warn!(
+            "Unable to set the display color space to {:?}: {}, supported: {:?}",
+            color_space,
+            err,
+            session.enumerate_display_color_spaces()
+        ),
+    }
+}
@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::{xr_only, XrCleanup, XrSetup};
+
+/// The refresh rate of the headset display in Hz.
+/// Only exists while a session is running and `XR_FB_display_refresh_rate` is enabled,
+/// see [`XrExtensions::enable_display_refresh_rate`](crate::graphics::extensions::XrExtensions::enable_display_refresh_rate).
+#[derive(Clone, Debug, Resource)]
+pub struct XrDisplayRefreshRate {
+    pub current: f32,
+    /// All refresh rates the display can run at, in ascending order
+    pub supported: Vec<f32>,
+}
+
+/// Sent when the runtime switched the display to a different refresh rate,
+/// either because it was requested or on its own.
+#[derive(Clone, Copy, Debug, Event)]
+pub struct XrDisplayRefreshRateChanged {
+    pub from: f32,
+    pub to: f32,
+}
+
+/// Asks the runtime to switch the display to this refresh rate,
+/// the change is reported through [`XrDisplayRefreshRateChanged`] once it happened.
+/// A rate of `0.0` lets the runtime choose.
+#[derive(Clone, Copy, Debug, Event)]
+pub struct RequestXrDisplayRefreshRate(pub f32);
+
+pub struct DisplayRefreshRatePlugin;
+
+impl Plugin for DisplayRefreshRatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RequestXrDisplayRefreshRate>();
+        app.add_systems(XrSetup, setup_display_refresh_rate);
+        app.add_systems(XrCleanup, cleanup_display_refresh_rate);
+        app.add_systems(
+            PreUpdate,
+            update_display_refresh_rate
+                .run_if(resource_exists::<XrDisplayRefreshRate>)
+                .run_if(on_event::<XrDisplayRefreshRateChanged>())
+                .after(crate::xr_poll_events),
+        );
+        app.add_systems(
+            Update,
+            request_display_refresh_rate
+                .run_if(xr_only())
+                .run_if(on_event::<RequestXrDisplayRefreshRate>()),
+        );
+    }
+}
+
+fn setup_display_refresh_rate(
+    mut commands: Commands,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+) {
+    if instance.exts().fb_display_refresh_rate.is_none() {
+        return;
+    }
+    let refresh_rate = session.get_display_refresh_rate().and_then(|current| {
+        let mut supported = session.enumerate_display_refresh_rates()?;
+        supported.sort_by(f32::total_cmp);
+        Ok(XrDisplayRefreshRate { current, supported })
+    });
+    match refresh_rate {
+        Ok(refresh_rate) => {
+            info!(
+                "Display refresh rate: {} Hz, supported: {:?}",
+                refresh_rate.current, refresh_rate.supported
+            );
+            commands.insert_resource(refresh_rate);
+        }
+        Err(err) => warn!("Unable to query the display refresh rate: {}", err),
+    }
+}
+
+fn cleanup_display_refresh_rate(mut commands: Commands) {
+    commands.remove_resource::<XrDisplayRefreshRate>();
+}
+
+fn update_display_refresh_rate(
+    mut events: EventReader<XrDisplayRefreshRateChanged>,
+    mut refresh_rate: ResMut<XrDisplayRefreshRate>,
+) {
+    if let Some(event) = events.read().last() {
+        refresh_rate.current = event.to;
+    }
+}
+
+fn request_display_refresh_rate(
+    mut events: EventReader<RequestXrDisplayRefreshRate>,
+    refresh_rate: Option<Res<XrDisplayRefreshRate>>,
+    session: Res<XrSession>,
+) {
+    let Some(RequestXrDisplayRefreshRate(rate)) = events.read().last().copied() else {
+        return;
+    };
+    let Some(refresh_rate) = refresh_rate else {
+        warn!(
+            "Unable to change the display refresh rate: XR_FB_display_refresh_rate is not enabled"
+        );
+        return;
+    };
+    if rate != 0.0 && !refresh_rate.supported.contains(&rate) {
+        warn!(
+            "Display refresh rate {} Hz is not supported, supported rates: {:?}",
+            rate, refresh_rate.supported
+        );
+        return;
+    }
+    if let Err(err) = session.request_display_refresh_rate(rate) {
+        warn!(
+            "Unable to request display refresh rate {} Hz: {}",
+            rate, err
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy::render::camera::{CameraUpdateSystem, Viewport};
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+
+use crate::frame_diagnostics::{finish_wait, XrFrameDiagnostics};
+use crate::resources::{XrFrameTime, XrResolution};
+use crate::xr_init::{xr_after_wait_only, xr_only, XrSetup};
+use crate::xr_input::xr_camera::XrCamera;
+
+/// Lowers the part of the swapchain images the xr cameras render into while frames take too long
+/// and raises it again once there is headroom. The swapchain isn't recreated,
+/// only the image rect submitted to the compositor shrinks. The scale is stored in [`XrViewportScale`].
+#[derive(Clone, Copy, Debug, PartialEq, Resource, Reflect)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[reflect(Resource)]
+pub struct XrDynamicResolution {
+    pub enabled: bool,
+    /// Smallest fraction of the swapchain width and height that is rendered into
+    pub min_scale: f32,
+    /// Largest fraction of the swapchain width and height that is rendered into, at most 1.0
+    pub max_scale: f32,
+    /// Fraction of the display period a frame may take before the scale is lowered
+    pub budget: f32,
+    /// Fraction of the display period below which the scale is raised again
+    pub headroom: f32,
+    /// How many frames in a row have to be over budget or have headroom before the scale changes
+    pub frames: u32,
+    /// How much the scale changes at once
+    pub step: f32,
+}
+
+impl Default for XrDynamicResolution {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_scale: 0.6,
+            max_scale: 1.0,
+            budget: 0.9,
+            headroom: 0.7,
+            frames: 10,
+            step: 0.05,
+        }
+    }
+}
+
+/// The fraction of the swapchain width and height the xr cameras render into this frame
+#[derive(Clone, Copy, Debug, PartialEq, Resource, ExtractResource, Reflect, Deref)]
+#[reflect(Resource)]
+pub struct XrViewportScale(pub f32);
+
+impl Default for XrViewportScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+impl XrViewportScale {
+    /// The size of the rendered part of an image with the size of `resolution`
+    pub fn viewport_size(&self, resolution: UVec2) -> UVec2 {
+        (resolution.as_vec2() * self.0)
+            .round()
+            .as_uvec2()
+            .clamp(UVec2::ONE, resolution.max(UVec2::ONE))
+    }
+}
+
+pub struct XrDynamicResolutionPlugin;
+
+impl Plugin for XrDynamicResolutionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<XrDynamicResolution>();
+        app.register_type::<XrViewportScale>();
+        app.init_resource::<XrDynamicResolution>();
+        app.init_resource::<XrViewportScale>();
+        app.init_resource::<FrameBudgetCounter>();
+        app.add_plugins(ExtractResourcePlugin::<XrViewportScale>::default());
+        app.add_systems(XrSetup, reset_viewport_scale);
+        app.add_systems(
+            PreUpdate,
+            update_viewport_scale
+                .after(finish_wait)
+                .run_if(xr_only())
+                .run_if(xr_after_wait_only())
+                .run_if(resource_exists::<XrFrameDiagnostics>),
+        );
+        app.add_systems(
+            PostUpdate,
+            update_xr_camera_viewports
+                .before(CameraUpdateSystem)
+                .run_if(xr_only()),
+        );
+    }
+}
+
+/// Frames in a row that were over budget when positive, or had headroom when negative
+#[derive(Clone, Copy, Debug, Default, Resource)]
+struct FrameBudgetCounter(i32);
+
+fn reset_viewport_scale(
+    mut scale: ResMut<XrViewportScale>,
+    mut counter: ResMut<FrameBudgetCounter>,
+    dynamic_resolution: Res<XrDynamicResolution>,
+) {
+    scale.0 = match dynamic_resolution.enabled {
+        true => dynamic_resolution.max_scale.clamp(0.0, 1.0),
+        false => 1.0,
+    };
+    counter.0 = 0;
+}
+
+fn update_viewport_scale(
+    dynamic_resolution: Res<XrDynamicResolution>,
+    frame_diagnostics: Res<XrFrameDiagnostics>,
+    frame_time: Res<XrFrameTime>,
+    mut scale: ResMut<XrViewportScale>,
+    mut counter: ResMut<FrameBudgetCounter>,
+) {
+    if !dynamic_resolution.enabled {
+        if scale.0 != 1.0 {
+            scale.0 = 1.0;
+        }
+        return;
+    }
+    let period = frame_time.predicted_display_period.as_secs_f32();
+    if period <= 0.0 {
+        return;
+    }
+    let frame = frame_diagnostics.begin_to_end_time.as_secs_f32() / period;
+    counter.0 = match frame {
+        frame if frame > dynamic_resolution.budget => counter.0.max(0) + 1,
+        frame if frame < dynamic_resolution.headroom => counter.0.min(0) - 1,
+        _ => 0,
+    };
+    if counter.0.unsigned_abs() < dynamic_resolution.frames.max(1) {
+        return;
+    }
+    let max_scale = dynamic_resolution.max_scale.clamp(0.0, 1.0);
+    let min_scale = dynamic_resolution.min_scale.clamp(0.0, max_scale);
+    let step = match counter.0 > 0 {
+        true => -dynamic_resolution.step,
+        false => dynamic_resolution.step,
+    };
+    let new_scale = (scale.0 + step).clamp(min_scale, max_scale);
+    if new_scale != scale.0 {
+        debug!("Changing the xr viewport scale to {}", new_scale);
+        scale.0 = new_scale;
+    }
+    counter.0 = 0;
+}
+
+fn update_xr_camera_viewports(
+    scale: Res<XrViewportScale>,
+    resolution: Res<XrResolution>,
+    mut cameras: Query<&mut Camera, With<XrCamera>>,
+) {
+    let viewport = (scale.0 < 1.0).then(|| Viewport {
+        physical_position: UVec2::ZERO,
+        physical_size: scale.viewport_size(**resolution),
+        ..default()
+    });
+    for mut camera in &mut cameras {
+        let unchanged = match (&camera.viewport, &viewport) {
+            (None, None) => true,
+            (Some(current), Some(viewport)) => {
+                current.physical_position == viewport.physical_position
+                    && current.physical_size == viewport.physical_size
+            }
+            _ => false,
+        };
+        if !unchanged {
+            camera.viewport = viewport.clone();
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! Errors the plugin runs into. Setup functions return them, failures in the frame loop are
+//! logged and sent as [`XrErrorEvent`]s instead of panicking.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::session_recovery::XrRecoverySettings;
+use crate::xr_init::ExitAppOnSessionExit;
+
+pub type Result<T, E = XrError> = std::result::Result<T, E>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum XrError {
+    /// An OpenXR call returned an error
+    Runtime {
+        /// The name of the OpenXR function, like `xrBeginFrame`
+        call: &'static str,
+        result: xr::sys::Result,
+        context: Option<String>,
+    },
+    /// A name given to the plugin breaks the rules OpenXR has for it
+    InvalidName {
+        /// What is named, like `action`
+        kind: &'static str,
+        name: String,
+        reason: &'static str,
+    },
+    /// The graphics api failed
+    Graphics(String),
+    /// More layers were enabled in the [`XrLayerStack`](crate::layers::XrLayerStack) than the
+    /// runtime accepts per frame, the layers in front were dropped
+    TooManyLayers { count: usize, max: u32 },
+}
+
+impl XrError {
+    /// The result code of the OpenXR call that failed
+    pub fn result(&self) -> Option<xr::sys::Result> {
+        match self {
+            XrError::Runtime { result, .. } => Some(*result),
+            _ => None,
+        }
+    }
+
+    /// The session or the whole instance is gone, like when the runtime crashed
+    pub fn is_session_lost(&self) -> bool {
+        matches!(
+            self.result(),
+            Some(xr::sys::Result::ERROR_SESSION_LOST | xr::sys::Result::ERROR_INSTANCE_LOST)
+        )
+    }
+
+    /// Describes what was being done when the error happened
+    pub fn with_context(self, context: impl Into<String>) -> Self {
+        match self {
+            XrError::Runtime { call, result, .. } => XrError::Runtime {
+                call,
+                result,
+                context: Some(context.into()),
+            },
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for XrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XrError::Runtime {
+                call,
+                result,
+                context: Some(context),
+            } => write!(
+                f,
+                "{} failed while {}: {} ({:?})",
+                call, context, result, result
+            ),
+            XrError::Runtime { call, result, .. } => {
+                write!(f, "{} failed: {} ({:?})", call, result, result)
+            }
+            XrError::InvalidName { kind, name, reason } => {
+                write!(f, "Invalid {} name {:?}: {}", kind, name, reason)
+            }
+            XrError::Graphics(message) => write!(f, "Graphics error: {}", message),
+            XrError::TooManyLayers { count, max } => write!(
+                f,
+                "{} layers were submitted but the runtime only accepts {} per frame, \
+                 disable layers in the XrLayerStack",
+                count, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for XrError {}
+
+/// Attaches the name of the failed OpenXR call to its result code
+pub trait XrResultExt<T> {
+    fn call(self, call: &'static str) -> Result<T>;
+}
+
+impl<T> XrResultExt<T> for xr::Result<T> {
+    fn call(self, call: &'static str) -> Result<T> {
+        self.map_err(|result| XrError::Runtime {
+            call,
+            result,
+            context: None,
+        })
+    }
+}
+
+/// An error the plugin ran into while the app was running
+#[derive(Event, Clone, Debug, PartialEq, Eq)]
+pub struct XrErrorEvent {
+    pub error: XrError,
+    /// No more frames can be submitted, the app shuts down with [`AppExit`] after this event.
+    /// A lost session is recovered by the
+    /// [`XrSessionRecoveryPlugin`](crate::session_recovery::XrSessionRecoveryPlugin) instead,
+    /// unless [`ExitAppOnSessionExit::Always`] is set.
+    pub fatal: bool,
+}
+
+/// Errors reported by systems of both worlds, sent as [`XrErrorEvent`]s by the main world
+#[derive(Resource, Clone, Default)]
+pub struct XrErrors(Arc<Mutex<Vec<XrErrorEvent>>>);
+
+impl XrErrors {
+    /// Something didn't work, but the app can go on
+    pub(crate) fn warn(&self, error: XrError) {
+        warn!("{}", error);
+        self.push(error, false);
+    }
+
+    /// The frame loop can't go on
+    pub(crate) fn fatal(&self, error: XrError) {
+        error!("{}", error);
+        self.push(error, true);
+    }
+
+    /// A call of the frame loop failed, that's only fatal if no more frames can be submitted
+    pub(crate) fn frame(&self, error: XrError) {
+        if error.is_session_lost() || error.result() == Some(xr::sys::Result::ERROR_RUNTIME_FAILURE)
+        {
+            self.fatal(error);
+        } else {
+            self.warn(error);
+        }
+    }
+
+    fn push(&self, error: XrError, fatal: bool) {
+        self.0.lock().unwrap().push(XrErrorEvent { error, fatal });
+    }
+}
+
+pub(crate) fn send_errors(
+    errors: Res<XrErrors>,
+    recovery: Option<Res<XrRecoverySettings>>,
+    exit_type: Res<ExitAppOnSessionExit>,
+    mut events: EventWriter<XrErrorEvent>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let reported = std::mem::take(&mut *errors.0.lock().unwrap());
+    let recovers = |error: &XrError| {
+        recovery.is_some()
+            && *exit_type != ExitAppOnSessionExit::Always
+            && error.result() == Some(xr::sys::Result::ERROR_SESSION_LOST)
+    };
+    if reported
+        .iter()
+        .any(|event| event.fatal && !recovers(&event.error))
+    {
+        error!("Shutting down after a fatal OpenXR error");
+        app_exit.send_default();
+    }
+    events.send_batch(reported);
+}
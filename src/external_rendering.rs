@@ -0,0 +1,117 @@
+//! Rendering into the swapchain with an own renderer instead of bevy cameras, while the plugin
+//! still runs the session and the frame loop. See the `external_renderer` example.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureView;
+use bevy::render::RenderApp;
+use openxr as xr;
+
+use crate::error::{Result, XrError, XrResultExt};
+use crate::resources::{XrFrameInFlight, XrResolution, XrSwapchain};
+
+/// Leaves acquiring, waiting on and releasing the swapchain image to [`XrFrameImages`].
+/// Cameras rendering into the eyes don't get an image anymore.
+pub struct XrExternalRenderingPlugin;
+
+impl Plugin for XrExternalRenderingPlugin {
+    fn build(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .insert_resource(XrExternalRendering);
+    }
+}
+
+/// Render world marker of the [`XrExternalRenderingPlugin`]
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct XrExternalRendering;
+
+/// The swapchain image of a frame, valid until it's released
+pub struct XrSwapchainImage<'a> {
+    /// The index of the image in the swapchain, which cycles through a few images
+    pub index: usize,
+    /// The whole image, with one array layer per view, or a single one in mono view
+    /// configurations
+    pub texture: &'a wgpu::Texture,
+    /// A view of the array layer of every view, in the order of
+    /// [`XrViews`](crate::resources::XrViews). In mono view configurations both views show
+    /// the only array layer.
+    pub views: &'a [TextureView],
+    /// The size of each view
+    pub size: UVec2,
+    /// The format of the views, the texture itself can have a different one
+    pub format: wgpu::TextureFormat,
+}
+
+/// The swapchain calls of a frame for renderers outside the render graph, only available in the
+/// render world while XR is running. Every frame a system in [`Render`](bevy::render::Render),
+/// after [`XrBeginFrame`](crate::xr_init::XrBeginFrame) and before
+/// [`XrEndFrame`](crate::xr_init::XrEndFrame), calls [`acquire`](Self::acquire) if
+/// [`should_render`](Self::should_render), then [`wait`](Self::wait), submits its command
+/// buffers and calls [`release`](Self::release). The projection layer is only submitted for a
+/// released image, an image that wasn't released ends the frame without layers.
+#[derive(SystemParam)]
+pub struct XrFrameImages<'w> {
+    swapchain: Res<'w, XrSwapchain>,
+    resolution: Res<'w, XrResolution>,
+    in_flight: ResMut<'w, XrFrameInFlight>,
+}
+
+impl XrFrameImages<'_> {
+    /// `true` if the frame was begun and the image can be acquired, `false` while the runtime
+    /// doesn't show the frame or beginning it failed
+    pub fn should_render(&self) -> bool {
+        *self.in_flight == XrFrameInFlight::Begun
+    }
+
+    /// Acquires the swapchain image of this frame, returning its index
+    pub fn acquire(&mut self) -> Result<usize> {
+        self.expect(XrFrameInFlight::Begun, "xrAcquireSwapchainImage")?;
+        self.swapchain
+            .acquire_image()
+            .call("xrAcquireSwapchainImage")?;
+        *self.in_flight = XrFrameInFlight::Acquired;
+        Ok(self.swapchain.image_index())
+    }
+
+    /// Waits until the acquired image can be rendered into
+    pub fn wait(&mut self) -> Result<XrSwapchainImage<'_>> {
+        self.expect(XrFrameInFlight::Acquired, "xrWaitSwapchainImage")?;
+        self.swapchain.wait_image().call("xrWaitSwapchainImage")?;
+        *self.in_flight = XrFrameInFlight::Ready;
+        Ok(self.image().unwrap())
+    }
+
+    /// The image between waiting on and releasing it
+    pub fn image(&self) -> Option<XrSwapchainImage<'_>> {
+        (*self.in_flight == XrFrameInFlight::Ready).then(|| XrSwapchainImage {
+            index: self.swapchain.image_index(),
+            texture: self.swapchain.color_texture(),
+            views: self.swapchain.render_views(),
+            size: **self.resolution,
+            format: self.swapchain.view_format(),
+        })
+    }
+
+    /// Hands the image to the runtime, the command buffers rendering into it have to be
+    /// submitted before
+    pub fn release(&mut self) -> Result<()> {
+        self.expect(XrFrameInFlight::Ready, "xrReleaseSwapchainImage")?;
+        self.swapchain
+            .release_image()
+            .call("xrReleaseSwapchainImage")?;
+        *self.in_flight = XrFrameInFlight::Released;
+        Ok(())
+    }
+
+    /// Fails like the runtime would for a call in the wrong order, without making the call
+    fn expect(&self, state: XrFrameInFlight, call: &'static str) -> Result<()> {
+        if *self.in_flight == state {
+            return Ok(());
+        }
+        Err(XrError::Runtime {
+            call,
+            result: xr::sys::Result::ERROR_CALL_ORDER_INVALID,
+            context: Some(format!("the frame is {:?}", *self.in_flight)),
+        })
+    }
+}
@@ -0,0 +1,296 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::graphics::XrSessionConfig;
+use crate::resources::{XrInstance, XrSession, XrSwapchain};
+use crate::system_properties::XrSystemProperties;
+use crate::xr_init::{xr_only, XrCleanup, XrSetup, XrWaitFrame};
+
+/// How much the resolution is reduced towards the edges of the view
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum XrFoveationLevel {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl From<XrFoveationLevel> for xr::FoveationLevelFB {
+    fn from(value: XrFoveationLevel) -> Self {
+        match value {
+            XrFoveationLevel::None => xr::FoveationLevelFB::NONE,
+            XrFoveationLevel::Low => xr::FoveationLevelFB::LOW,
+            XrFoveationLevel::Medium => xr::FoveationLevelFB::MEDIUM,
+            XrFoveationLevel::High => xr::FoveationLevelFB::HIGH,
+        }
+    }
+}
+
+/// Fixed foveated rendering applied to the main swapchain.
+/// Needs `XR_FB_foveation`, `XR_FB_foveation_configuration` and `XR_FB_swapchain_update_state`,
+/// see [`XrExtensions::enable_foveation`](crate::graphics::extensions::XrExtensions::enable_foveation).
+///
+/// Inserted during [`XrSetup`] if [`XrSessionConfig::foveation`] is set,
+/// changing the resource applies the new settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Resource, Reflect)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[reflect(Resource)]
+pub struct XrFoveationSettings {
+    pub level: XrFoveationLevel,
+    /// Let the runtime lower the level when there is enough gpu headroom
+    pub dynamic: bool,
+    /// Vertical offset of the foveation center in degrees, positive values move it up
+    pub vertical_offset: f32,
+    /// Move the foveation center to where the user looks. Needs `XR_META_foveation_eye_tracked`,
+    /// see [`XrExtensions::enable_eye_tracked_foveation`](crate::graphics::extensions::XrExtensions::enable_eye_tracked_foveation),
+    /// and on Quest the `com.oculus.permission.EYE_TRACKING` permission.
+    /// Falls back to fixed foveation if the system has no eye tracker or the user declined,
+    /// [`XrActiveFoveation`] has the mode that is actually used.
+    pub eye_tracked: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum XrFoveationMode {
+    /// Foveation isn't supported or wasn't applied yet
+    #[default]
+    None,
+    /// The foveation center stays in the middle of the view
+    Fixed,
+    /// The runtime moves the foveation center with the eyes
+    EyeTracked,
+}
+
+/// The foveation that is applied to the swapchain, updated every frame while eye tracked
+#[derive(Clone, Copy, Debug, Default, PartialEq, Resource)]
+pub struct XrActiveFoveation {
+    pub mode: XrFoveationMode,
+    /// The foveation center of each eye in normalized device coordinates,
+    /// `None` while the eye tracker doesn't know where the user looks
+    pub centers: Option<[Vec2; 2]>,
+}
+
+pub struct FoveationPlugin;
+
+impl Plugin for FoveationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<XrFoveationSettings>();
+        app.add_systems(XrSetup, setup_foveation);
+        app.add_systems(XrCleanup, |mut commands: Commands| {
+            commands.remove_resource::<XrActiveFoveation>()
+        });
+        app.add_systems(
+            PreUpdate,
+            update_eye_tracked_foveation
+                .after(XrWaitFrame)
+                .run_if(xr_only())
+                .run_if(resource_exists::<XrActiveFoveation>),
+        );
+        app.add_systems(
+            PostUpdate,
+            apply_foveation
+                .run_if(xr_only())
+                .run_if(resource_exists_and_changed::<XrFoveationSettings>),
+        );
+    }
+}
+
+fn setup_foveation(
+    mut commands: Commands,
+    config: Res<XrSessionConfig>,
+    settings: Option<ResMut<XrFoveationSettings>>,
+) {
+    match (settings, config.foveation) {
+        // the swapchain of a new session has to be updated again
+        (Some(mut settings), _) => settings.set_changed(),
+        (None, Some(settings)) => commands.insert_resource(settings),
+        (None, None) => {}
+    }
+}
+
+fn apply_foveation(
+    mut commands: Commands,
+    settings: Res<XrFoveationSettings>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    swapchain: Res<XrSwapchain>,
+    system_properties: Option<Res<XrSystemProperties>>,
+) {
+    let mut settings = *settings;
+    if settings.eye_tracked
+        && !system_properties.is_some_and(|properties| properties.foveation_eye_tracked)
+    {
+        warn!("The system doesn't support eye tracked foveation, using fixed foveation");
+        settings.eye_tracked = false;
+    }
+    match set_foveation(&instance, &session, &swapchain, &settings) {
+        Ok(mode) => {
+            info!("Applied {:?} foveation settings: {:?}", mode, settings);
+            commands.insert_resource(XrActiveFoveation {
+                mode,
+                centers: None,
+            });
+        }
+        Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT) => {
+            warn!("Foveation is not supported by the runtime, skipping foveation settings")
+        }
+        Err(err) => warn!("Unable to apply foveation settings: {}", err),
+    }
+}
+
+/// Reads where the runtime put the foveation centers, falls back to fixed foveation
+/// when the eye tracker stops working, like when the permission was revoked
+fn update_eye_tracked_foveation(
+    mut active: ResMut<XrActiveFoveation>,
+    settings: Option<Res<XrFoveationSettings>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    swapchain: Res<XrSwapchain>,
+) {
+    if active.mode != XrFoveationMode::EyeTracked {
+        return;
+    }
+    let Some(eye_tracked) = instance.exts().meta_foveation_eye_tracked else {
+        return;
+    };
+    let mut state = xr::sys::FoveationEyeTrackedStateMETA {
+        ty: xr::sys::FoveationEyeTrackedStateMETA::TYPE,
+        next: ptr::null_mut(),
+        foveation_center: [xr::Vector2f::default(); xr::sys::FOVEATION_CENTER_SIZE_META],
+        flags: xr::sys::FoveationEyeTrackedStateFlagsMETA::EMPTY,
+    };
+    let result =
+        unsafe { (eye_tracked.get_foveation_eye_tracked_state)(session.as_raw(), &mut state) };
+    if result.into_raw() >= 0 {
+        let centers = state
+            .flags
+            .contains(xr::sys::FoveationEyeTrackedStateFlagsMETA::VALID)
+            .then(|| {
+                state
+                    .foveation_center
+                    .map(|center| Vec2::new(center.x, center.y))
+            });
+        if active.centers != centers {
+            active.centers = centers;
+        }
+        return;
+    }
+    warn!(
+        "Unable to get the eye tracked foveation state, using fixed foveation: {}",
+        result
+    );
+    let Some(mut settings) = settings.map(|settings| *settings) else {
+        return;
+    };
+    settings.eye_tracked = false;
+    *active = match set_foveation(&instance, &session, &swapchain, &settings) {
+        Ok(mode) => XrActiveFoveation {
+            mode,
+            centers: None,
+        },
+        Err(err) => {
+            warn!("Unable to apply fixed foveation: {}", err);
+            default()
+        }
+    };
+}
+
+fn cvt(x: xr::sys::Result) -> xr::Result<xr::sys::Result> {
+    if x.into_raw() >= 0 {
+        Ok(x)
+    } else {
+        Err(x)
+    }
+}
+
+/// Creates a foveation profile from `settings` and applies it to the main swapchain.
+/// Returns `ERROR_EXTENSION_NOT_PRESENT` if the foveation extensions aren't enabled.
+/// Eye tracked foveation falls back to fixed foveation if `XR_META_foveation_eye_tracked`
+/// isn't enabled or the runtime refuses the profile, the mode that was applied is returned.
+pub fn set_foveation(
+    instance: &XrInstance,
+    session: &XrSession,
+    swapchain: &XrSwapchain,
+    settings: &XrFoveationSettings,
+) -> xr::Result<XrFoveationMode> {
+    if settings.eye_tracked && instance.exts().meta_foveation_eye_tracked.is_some() {
+        match update_foveation(instance, session, swapchain, settings, true) {
+            Ok(()) => return Ok(XrFoveationMode::EyeTracked),
+            Err(err @ xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT) => return Err(err),
+            Err(err) => warn!(
+                "Unable to apply eye tracked foveation, using fixed foveation: {}",
+                err
+            ),
+        }
+    }
+    update_foveation(instance, session, swapchain, settings, false)?;
+    Ok(XrFoveationMode::Fixed)
+}
+
+fn update_foveation(
+    instance: &XrInstance,
+    session: &XrSession,
+    swapchain: &XrSwapchain,
+    settings: &XrFoveationSettings,
+    eye_tracked: bool,
+) -> xr::Result<()> {
+    let exts = instance.exts();
+    let (Some(foveation), Some(update_state), true) = (
+        exts.fb_foveation,
+        exts.fb_swapchain_update_state,
+        exts.fb_foveation_configuration.is_some(),
+    ) else {
+        return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let mut level_info = xr::sys::FoveationLevelProfileCreateInfoFB {
+        ty: xr::sys::FoveationLevelProfileCreateInfoFB::TYPE,
+        next: ptr::null_mut(),
+        level: settings.level.into(),
+        vertical_offset: settings.vertical_offset,
+        dynamic: match settings.dynamic {
+            true => xr::FoveationDynamicFB::LEVEL_ENABLED,
+            false => xr::FoveationDynamicFB::DISABLED,
+        },
+    };
+    let mut eye_tracked_info = xr::sys::FoveationEyeTrackedProfileCreateInfoMETA {
+        ty: xr::sys::FoveationEyeTrackedProfileCreateInfoMETA::TYPE,
+        next: ptr::null(),
+        flags: xr::sys::FoveationEyeTrackedProfileCreateFlagsMETA::EMPTY,
+    };
+    if eye_tracked {
+        level_info.next = &mut eye_tracked_info as *mut _ as _;
+    }
+    let create_info = xr::sys::FoveationProfileCreateInfoFB {
+        ty: xr::sys::FoveationProfileCreateInfoFB::TYPE,
+        next: &mut level_info as *mut _ as _,
+    };
+    let mut profile = xr::sys::FoveationProfileFB::NULL;
+    unsafe {
+        cvt((foveation.create_foveation_profile)(
+            session.as_raw(),
+            &create_info,
+            &mut profile,
+        ))?;
+        let state = xr::sys::SwapchainStateFoveationFB {
+            ty: xr::sys::SwapchainStateFoveationFB::TYPE,
+            next: ptr::null_mut(),
+            flags: xr::SwapchainStateFoveationFlagsFB::EMPTY,
+            profile,
+        };
+        let result = cvt((update_state.update_swapchain)(
+            swapchain.as_raw(),
+            &state as *const _ as _,
+        ));
+        // the swapchain keeps the settings, the profile isn't needed anymore
+        (foveation.destroy_foveation_profile)(profile);
+        result?;
+    }
+    Ok(())
+}
@@ -0,0 +1,251 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+use bevy::render::renderer::render_system;
+use bevy::render::{Render, RenderApp, RenderSet};
+
+use crate::resources::{XrFrameTime, XrTime};
+use crate::xr_init::{xr_after_wait_only, xr_only, xr_render_only, XrSetup};
+use crate::xr_input::actions::{sync_actions, XrActionSync};
+use crate::{xr_begin_frame, xr_end_frame, xr_pre_frame, xr_skip_frame, xr_wait_frame};
+
+/// Timings of the last frame, reset when a session starts.
+/// The timings of the render world are from the frame before, as it runs after the main world.
+#[derive(Clone, Copy, Debug, Default, Resource)]
+pub struct XrFrameDiagnostics {
+    /// Time spent in `xrWaitFrame`
+    pub wait_time: Duration,
+    /// Cpu time the render world needed from acquiring the swapchain images to submitting the frame
+    pub render_time: Duration,
+    /// Time spent acquiring and waiting for the swapchain images
+    pub acquire_time: Duration,
+    /// Time spent syncing the actions
+    pub action_sync_time: Duration,
+    /// How far the predicted display time advanced since the last frame,
+    /// a multiple of [`XrFrameDiagnostics::predicted_display_period`] when frames were dropped
+    pub display_period: Duration,
+    /// The display period the runtime predicted for this frame
+    pub predicted_display_period: Duration,
+    /// Time between `xrBeginFrame` and `xrEndFrame`
+    pub begin_to_end_time: Duration,
+    /// The predicted display time advanced by more than one display period,
+    /// so at least one frame wasn't shown in time
+    pub dropped: bool,
+    /// Number of frames that weren't shown in time
+    pub dropped_frames: u64,
+    pub frames: u64,
+    /// Gpu time of the left eye's passes, measured by the
+    /// [`XrGpuTimingPlugin`](crate::gpu_timing::XrGpuTimingPlugin) a few frames ago
+    pub left_eye_gpu_time: Option<Duration>,
+    /// Gpu time of the right eye's passes
+    pub right_eye_gpu_time: Option<Duration>,
+    /// Gpu time from the start of the first eye to the end of the last one
+    pub gpu_time: Option<Duration>,
+}
+
+/// Collects [`XrFrameDiagnostics`] and adds them to bevy's [`Diagnostics`],
+/// times are in milliseconds. They're shown by `LogDiagnosticsPlugin` and keep their history
+/// across sessions. The gpu time of the eyes is measured by the
+/// [`XrGpuTimingPlugin`](crate::gpu_timing::XrGpuTimingPlugin).
+pub struct XrFrameDiagnosticsPlugin;
+
+impl XrFrameDiagnosticsPlugin {
+    pub const WAIT_TIME: DiagnosticPath = DiagnosticPath::const_new("xr/wait_time");
+    pub const RENDER_TIME: DiagnosticPath = DiagnosticPath::const_new("xr/render_time");
+    pub const BEGIN_TO_END_TIME: DiagnosticPath = DiagnosticPath::const_new("xr/begin_to_end_time");
+    pub const DROPPED_FRAMES: DiagnosticPath = DiagnosticPath::const_new("xr/dropped_frames");
+    pub const ACQUIRE_TIME: DiagnosticPath = DiagnosticPath::const_new("xr/acquire_time");
+    pub const ACTION_SYNC_TIME: DiagnosticPath = DiagnosticPath::const_new("xr/action_sync_time");
+    pub const DISPLAY_PERIOD: DiagnosticPath = DiagnosticPath::const_new("xr/display_period");
+    pub const PREDICTED_DISPLAY_PERIOD: DiagnosticPath =
+        DiagnosticPath::const_new("xr/predicted_display_period");
+}
+
+impl Plugin for XrFrameDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        let timestamps = FrameTimestamps::default();
+        app.register_diagnostic(Diagnostic::new(Self::WAIT_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::RENDER_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::BEGIN_TO_END_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::DROPPED_FRAMES).with_smoothing_factor(0.0))
+            .register_diagnostic(Diagnostic::new(Self::ACQUIRE_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::ACTION_SYNC_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::DISPLAY_PERIOD).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::PREDICTED_DISPLAY_PERIOD).with_suffix("ms"));
+        app.init_resource::<XrFrameDiagnostics>();
+        app.insert_resource(timestamps.clone());
+        app.add_systems(XrSetup, reset_frame_diagnostics);
+        app.add_systems(
+            PreUpdate,
+            (
+                start_wait.before(xr_wait_frame),
+                finish_wait
+                    .after(xr_wait_frame)
+                    .run_if(xr_after_wait_only()),
+                (
+                    start_action_sync.before(sync_actions),
+                    finish_action_sync.after(sync_actions),
+                )
+                    .in_set(XrActionSync),
+            )
+                .run_if(xr_only()),
+        );
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.insert_resource(timestamps);
+        render_app.add_systems(
+            Render,
+            (
+                begin_frame.after(xr_begin_frame).before(xr_pre_frame),
+                finish_acquire
+                    .run_if(xr_render_only())
+                    .after(xr_pre_frame)
+                    .before(render_system),
+                start_render
+                    .run_if(xr_render_only())
+                    .before(xr_pre_frame)
+                    .before(render_system)
+                    .after(RenderSet::ExtractCommands),
+                finish_render
+                    .after(xr_end_frame)
+                    .after(xr_skip_frame)
+                    .in_set(RenderSet::Cleanup),
+            )
+                .run_if(xr_only())
+                .run_if(xr_after_wait_only()),
+        );
+    }
+}
+
+/// Shared by both worlds, only holds plain values so nothing is allocated per frame
+#[derive(Clone, Default, Resource)]
+pub(crate) struct FrameTimestamps(Arc<Mutex<Timestamps>>);
+
+#[derive(Default)]
+struct Timestamps {
+    wait_start: Option<Instant>,
+    begin: Option<Instant>,
+    render_start: Option<Instant>,
+    render_time: Duration,
+    acquire_time: Duration,
+    action_sync_start: Option<Instant>,
+    begin_to_end_time: Duration,
+    last_display_time: Option<XrTime>,
+}
+
+fn reset_frame_diagnostics(
+    mut frame_diagnostics: ResMut<XrFrameDiagnostics>,
+    timestamps: Res<FrameTimestamps>,
+) {
+    *frame_diagnostics = default();
+    *timestamps.0.lock().unwrap() = default();
+}
+
+fn start_wait(timestamps: Res<FrameTimestamps>) {
+    timestamps.0.lock().unwrap().wait_start = Some(Instant::now());
+}
+
+pub(crate) fn finish_wait(
+    timestamps: Res<FrameTimestamps>,
+    frame_time: Res<XrFrameTime>,
+    mut frame_diagnostics: ResMut<XrFrameDiagnostics>,
+    mut diagnostics: Diagnostics,
+) {
+    let now = Instant::now();
+    let mut timestamps = timestamps.0.lock().unwrap();
+    let wait_time = timestamps
+        .wait_start
+        .take()
+        .map_or(Duration::ZERO, |start| now - start);
+    let display_time = frame_time.predicted_display_time;
+    let period = frame_time.predicted_display_period.as_nanos() as i64;
+    let display_period = timestamps
+        .last_display_time
+        .map_or(0, |last| display_time.as_nanos() - last.as_nanos());
+    // allow for some jitter in the predicted times
+    let dropped =
+        timestamps.last_display_time.is_some() && period > 0 && display_period > period * 3 / 2;
+    timestamps.last_display_time = Some(display_time);
+
+    let frame_diagnostics = &mut *frame_diagnostics;
+    frame_diagnostics.wait_time = wait_time;
+    frame_diagnostics.render_time = timestamps.render_time;
+    frame_diagnostics.acquire_time = timestamps.acquire_time;
+    frame_diagnostics.display_period = Duration::from_nanos(display_period.max(0) as u64);
+    frame_diagnostics.predicted_display_period = Duration::from_nanos(period.max(0) as u64);
+    frame_diagnostics.begin_to_end_time = timestamps.begin_to_end_time;
+    frame_diagnostics.dropped = dropped;
+    frame_diagnostics.dropped_frames += dropped as u64;
+    frame_diagnostics.frames += 1;
+
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    diagnostics.add_measurement(&XrFrameDiagnosticsPlugin::WAIT_TIME, || millis(wait_time));
+    diagnostics.add_measurement(&XrFrameDiagnosticsPlugin::RENDER_TIME, || {
+        millis(frame_diagnostics.render_time)
+    });
+    diagnostics.add_measurement(&XrFrameDiagnosticsPlugin::BEGIN_TO_END_TIME, || {
+        millis(frame_diagnostics.begin_to_end_time)
+    });
+    diagnostics.add_measurement(&XrFrameDiagnosticsPlugin::DROPPED_FRAMES, || {
+        frame_diagnostics.dropped_frames as f64
+    });
+    diagnostics.add_measurement(&XrFrameDiagnosticsPlugin::ACQUIRE_TIME, || {
+        millis(frame_diagnostics.acquire_time)
+    });
+    if display_period > 0 {
+        diagnostics.add_measurement(&XrFrameDiagnosticsPlugin::DISPLAY_PERIOD, || {
+            millis(frame_diagnostics.display_period)
+        });
+    }
+    diagnostics.add_measurement(&XrFrameDiagnosticsPlugin::PREDICTED_DISPLAY_PERIOD, || {
+        millis(frame_diagnostics.predicted_display_period)
+    });
+}
+
+fn start_action_sync(timestamps: Res<FrameTimestamps>) {
+    timestamps.0.lock().unwrap().action_sync_start = Some(Instant::now());
+}
+
+fn finish_action_sync(
+    timestamps: Res<FrameTimestamps>,
+    mut frame_diagnostics: ResMut<XrFrameDiagnostics>,
+    mut diagnostics: Diagnostics,
+) {
+    let Some(start) = timestamps.0.lock().unwrap().action_sync_start.take() else {
+        return;
+    };
+    let action_sync_time = start.elapsed();
+    frame_diagnostics.action_sync_time = action_sync_time;
+    diagnostics.add_measurement(&XrFrameDiagnosticsPlugin::ACTION_SYNC_TIME, || {
+        action_sync_time.as_secs_f64() * 1000.0
+    });
+}
+
+fn begin_frame(timestamps: Res<FrameTimestamps>) {
+    timestamps.0.lock().unwrap().begin = Some(Instant::now());
+}
+
+fn finish_acquire(timestamps: Res<FrameTimestamps>) {
+    let now = Instant::now();
+    let mut timestamps = timestamps.0.lock().unwrap();
+    timestamps.acquire_time = timestamps.begin.map_or(Duration::ZERO, |begin| now - begin);
+}
+
+fn start_render(timestamps: Res<FrameTimestamps>) {
+    timestamps.0.lock().unwrap().render_start = Some(Instant::now());
+}
+
+fn finish_render(timestamps: Res<FrameTimestamps>) {
+    let now = Instant::now();
+    let mut timestamps = timestamps.0.lock().unwrap();
+    // frames the runtime doesn't want rendered end right away
+    timestamps.render_time = timestamps
+        .render_start
+        .take()
+        .map_or(Duration::ZERO, |start| now - start);
+    timestamps.begin_to_end_time = timestamps
+        .begin
+        .take()
+        .map_or(Duration::ZERO, |begin| now - begin);
+}
@@ -0,0 +1,193 @@
+//! Hooks into the frame loop for compositing middleware like injection layers, they can change
+//! the info passed to `xrBeginFrame`, `xrEndFrame` and `xrLocateViews`. See the `frame_hooks`
+//! example.
+
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy::render::extract_resource::ExtractResource;
+use openxr as xr;
+
+use crate::resources::XrTime;
+
+type Hook<T> = Box<dyn FnMut(&mut T) + Send>;
+
+#[derive(Default)]
+struct Hooks {
+    begin_frame: Vec<Hook<xr::sys::FrameBeginInfo>>,
+    end_frame: Vec<Hook<xr::sys::FrameEndInfo>>,
+    locate_views: Vec<Hook<xr::sys::ViewLocateInfo>>,
+}
+
+/// Callbacks run before the frame loop calls into the runtime, in the order they were
+/// registered. Shared between the main world, which locates the views, and the render world,
+/// which begins and ends the frames. The hooks run with a lock held, so they can't register
+/// other hooks.
+#[derive(Resource, Clone, Default, ExtractResource)]
+pub struct XrFrameHooks(Arc<Mutex<Hooks>>);
+
+/// The parts of `XrFrameEndInfo` the typed hooks can change
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrFrameEndParams {
+    pub display_time: XrTime,
+    pub environment_blend_mode: xr::EnvironmentBlendMode,
+}
+
+/// The parts of `XrViewLocateInfo` the typed hooks can change
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrViewLocateParams {
+    pub display_time: XrTime,
+}
+
+impl XrFrameHooks {
+    /// Runs `hook` before every `xrBeginFrame`
+    pub fn on_begin_frame(&self, mut hook: impl FnMut() + Send + 'static) {
+        // SAFETY: the info isn't touched
+        unsafe { self.on_raw_begin_frame(move |_| hook()) }
+    }
+
+    /// Runs `hook` before every `xrEndFrame`, also for frames ended without layers
+    pub fn on_end_frame(&self, mut hook: impl FnMut(&mut XrFrameEndParams) + Send + 'static) {
+        let raw = move |info: &mut xr::sys::FrameEndInfo| {
+            let mut params = XrFrameEndParams {
+                display_time: info.display_time.into(),
+                environment_blend_mode: info.environment_blend_mode,
+            };
+            hook(&mut params);
+            info.display_time = params.display_time.into();
+            info.environment_blend_mode = params.environment_blend_mode;
+        };
+        // SAFETY: only plain values are changed, the runtime validates them
+        unsafe { self.on_raw_end_frame(raw) }
+    }
+
+    /// Runs `hook` before the views of a frame are located
+    pub fn on_locate_views(&self, mut hook: impl FnMut(&mut XrViewLocateParams) + Send + 'static) {
+        let raw = move |info: &mut xr::sys::ViewLocateInfo| {
+            let mut params = XrViewLocateParams {
+                display_time: info.display_time.into(),
+            };
+            hook(&mut params);
+            info.display_time = params.display_time.into();
+        };
+        // SAFETY: only the time is changed
+        unsafe { self.on_raw_locate_views(raw) }
+    }
+
+    /// Runs `hook` with the info passed to every `xrBeginFrame`
+    ///
+    /// # Safety
+    ///
+    /// Structs chained through `next` have to stay valid until the call returns, and `ty`
+    /// can't be changed.
+    pub unsafe fn on_raw_begin_frame(
+        &self,
+        hook: impl FnMut(&mut xr::sys::FrameBeginInfo) + Send + 'static,
+    ) {
+        self.0.lock().unwrap().begin_frame.push(Box::new(hook));
+    }
+
+    /// Runs `hook` with the info passed to every `xrEndFrame`. The secondary view
+    /// configurations may already be chained through `next`, chained structs have to keep it.
+    ///
+    /// # Safety
+    ///
+    /// Structs chained through `next` have to stay valid until the call returns. `ty` and the
+    /// layers can't be changed, they point into memory the plugin frees after the call.
+    pub unsafe fn on_raw_end_frame(
+        &self,
+        hook: impl FnMut(&mut xr::sys::FrameEndInfo) + Send + 'static,
+    ) {
+        self.0.lock().unwrap().end_frame.push(Box::new(hook));
+    }
+
+    /// Runs `hook` with the info passed to `xrLocateViews` for the views of a frame. Views
+    /// located through [`locate_views_at`](crate::locate_views_at) don't run the hooks.
+    ///
+    /// # Safety
+    ///
+    /// Structs chained through `next` have to stay valid until the call returns. `ty` and the
+    /// view configuration can't be changed, and `space` has to stay a valid space of the
+    /// session.
+    pub unsafe fn on_raw_locate_views(
+        &self,
+        hook: impl FnMut(&mut xr::sys::ViewLocateInfo) + Send + 'static,
+    ) {
+        self.0.lock().unwrap().locate_views.push(Box::new(hook));
+    }
+
+    /// Removes all hooks
+    pub fn clear(&self) {
+        *self.0.lock().unwrap() = default();
+    }
+
+    pub(crate) fn begin_frame<G: xr::Graphics>(&self, session: &xr::Session<G>) -> xr::Result<()> {
+        let mut info = xr::sys::FrameBeginInfo {
+            ty: xr::sys::FrameBeginInfo::TYPE,
+            next: ptr::null(),
+        };
+        for hook in &mut self.0.lock().unwrap().begin_frame {
+            hook(&mut info);
+        }
+        let result = unsafe { (session.instance().fp().begin_frame)(session.as_raw(), &info) };
+        match result.into_raw() < 0 {
+            true => Err(result),
+            false => Ok(()),
+        }
+    }
+
+    /// `xrEndFrame`, with a single secondary view configuration chained like
+    /// [`xr::FrameStream::end_secondary`] does
+    pub(crate) fn end_frame<G: xr::Graphics>(
+        &self,
+        session: &xr::Session<G>,
+        display_time: xr::Time,
+        environment_blend_mode: xr::EnvironmentBlendMode,
+        layers: &[&xr::CompositionLayerBase<'_, G>],
+        secondary: Option<xr::SecondaryEndInfo<'_, '_, '_, G>>,
+    ) -> xr::Result<()> {
+        let secondary_layers = secondary.map(|secondary| {
+            [xr::sys::SecondaryViewConfigurationLayerInfoMSFT {
+                ty: xr::sys::SecondaryViewConfigurationLayerInfoMSFT::TYPE,
+                next: ptr::null(),
+                view_configuration_type: secondary.ty,
+                environment_blend_mode: secondary.environment_blend_mode,
+                layer_count: secondary.layers.len() as u32,
+                layers: secondary.layers.as_ptr() as *const _,
+            }]
+        });
+        let secondary_info = secondary_layers.as_ref().map(|layers| {
+            xr::sys::SecondaryViewConfigurationFrameEndInfoMSFT {
+                ty: xr::sys::SecondaryViewConfigurationFrameEndInfoMSFT::TYPE,
+                next: ptr::null(),
+                view_configuration_count: 1,
+                view_configuration_layers_info: layers.as_ptr(),
+            }
+        });
+        let mut info = xr::sys::FrameEndInfo {
+            ty: xr::sys::FrameEndInfo::TYPE,
+            next: secondary_info
+                .as_ref()
+                .map_or(ptr::null(), |info| info as *const _ as *const _),
+            display_time,
+            environment_blend_mode,
+            layer_count: layers.len() as u32,
+            layers: layers.as_ptr() as *const _,
+        };
+        for hook in &mut self.0.lock().unwrap().end_frame {
+            hook(&mut info);
+        }
+        let result = unsafe { (session.instance().fp().end_frame)(session.as_raw(), &info) };
+        match result.into_raw() < 0 {
+            true => Err(result),
+            false => Ok(()),
+        }
+    }
+
+    pub(crate) fn locate_views(&self, info: &mut xr::sys::ViewLocateInfo) {
+        for hook in &mut self.0.lock().unwrap().locate_views {
+            hook(info);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Measures how long the gpu needs to render each eye, enabled with
+//! [`DefaultXrPlugins::gpu_timing`](crate::DefaultXrPlugins::gpu_timing).
+
+use std::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::ecs::query::QueryItem;
+use bevy::pbr::graph::NodePbr;
+use bevy::prelude::*;
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraph, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode,
+    ViewNodeRunner,
+};
+use bevy::render::render_resource::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, MapMode,
+};
+use bevy::render::renderer::{render_system, RenderContext, RenderDevice, RenderQueue};
+use bevy::render::{Render, RenderApp, RenderSet};
+use wgpu::{QuerySet, QuerySetDescriptor, QueryType};
+
+use crate::frame_diagnostics::{finish_wait, XrFrameDiagnostics};
+use crate::visibility_mask::XrVisibilityMaskLabel;
+use crate::xr_init::xr_only;
+use crate::xr_input::xr_camera::XrCamera;
+
+/// Frames whose timestamps can be in flight at once
+const SLOTS: usize = 3;
+/// The begin and end of both eyes
+const QUERIES: u32 = 4;
+
+/// Adds the gpu time of the eyes to [`XrFrameDiagnostics`] and bevy's diagnostics, from
+/// timestamps written before the first and after the last pass of the eye cameras. Needs the
+/// `TIMESTAMP_QUERY` feature, which the render device requests when the adapter supports it. The
+/// timestamps are read back a few frames later, so the rendering never waits for the gpu.
+pub struct XrGpuTimingPlugin;
+
+impl XrGpuTimingPlugin {
+    pub const LEFT_EYE_TIME: DiagnosticPath = DiagnosticPath::const_new("xr/gpu_left_eye_time");
+    pub const RIGHT_EYE_TIME: DiagnosticPath = DiagnosticPath::const_new("xr/gpu_right_eye_time");
+    pub const GPU_TIME: DiagnosticPath = DiagnosticPath::const_new("xr/gpu_time");
+}
+
+impl Plugin for XrGpuTimingPlugin {
+    fn build(&self, app: &mut App) {
+        let results = GpuTimes::default();
+        app.register_diagnostic(Diagnostic::new(Self::LEFT_EYE_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::RIGHT_EYE_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::GPU_TIME).with_suffix("ms"));
+        app.insert_resource(results.clone());
+        app.add_systems(
+            PreUpdate,
+            publish_gpu_times
+                .after(finish_wait)
+                .run_if(resource_exists::<XrFrameDiagnostics>)
+                .run_if(xr_only()),
+        );
+        app.sub_app_mut(RenderApp).insert_resource(results);
+    }
+
+    // the render device and all the nodes of the graph exist once every plugin is built
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        let device = render_app.world.resource::<RenderDevice>();
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            warn!("The render device doesn't support timestamp queries, gpu timing is disabled");
+            return;
+        }
+        let timer = GpuTimer::new(device, render_app.world.resource::<RenderQueue>());
+        render_app
+            .insert_resource(timer)
+            .add_systems(
+                Render,
+                read_back_timestamps
+                    .after(render_system)
+                    .in_set(RenderSet::Render),
+            )
+            .add_render_graph_node::<ViewNodeRunner<GpuTimestampNode<false>>>(
+                Core3d,
+                GpuTimestampLabel::Begin,
+            )
+            .add_render_graph_node::<ViewNodeRunner<GpuTimestampNode<true>>>(
+                Core3d,
+                GpuTimestampLabel::End,
+            )
+            .add_render_graph_edge(Core3d, GpuTimestampLabel::Begin, Node3d::Prepass)
+            .add_render_graph_edge(Core3d, Node3d::Upscaling, GpuTimestampLabel::End);
+        // these passes run before the prepass, but only when their plugins are added
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        if let Some(graph) = graph.get_sub_graph_mut(Core3d) {
+            let _ = graph.try_add_node_edge(GpuTimestampLabel::Begin, NodePbr::ShadowPass);
+            let _ = graph.try_add_node_edge(GpuTimestampLabel::Begin, XrVisibilityMaskLabel);
+        }
+    }
+}
+
+/// The latest results, shared by both worlds
+#[derive(Clone, Default, Resource)]
+struct GpuTimes(Arc<Mutex<Option<[Duration; 3]>>>);
+
+fn publish_gpu_times(
+    results: Res<GpuTimes>,
+    mut frame_diagnostics: ResMut<XrFrameDiagnostics>,
+    mut diagnostics: Diagnostics,
+) {
+    let Some([left, right, total]) = results.0.lock().unwrap().take() else {
+        return;
+    };
+    frame_diagnostics.left_eye_gpu_time = Some(left);
+    frame_diagnostics.right_eye_gpu_time = Some(right);
+    frame_diagnostics.gpu_time = Some(total);
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    diagnostics.add_measurement(&XrGpuTimingPlugin::LEFT_EYE_TIME, || millis(left));
+    diagnostics.add_measurement(&XrGpuTimingPlugin::RIGHT_EYE_TIME, || millis(right));
+    diagnostics.add_measurement(&XrGpuTimingPlugin::GPU_TIME, || millis(total));
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, RenderLabel)]
+enum GpuTimestampLabel {
+    Begin,
+    End,
+}
+
+const FREE: u8 = 0;
+const MAPPING: u8 = 1;
+const MAPPED: u8 = 2;
+
+/// The queries of one frame, the slot is only written again once its results were read
+struct Slot {
+    query_set: QuerySet,
+    resolve: Buffer,
+    readback: Buffer,
+    state: Arc<AtomicU8>,
+}
+
+#[derive(Resource)]
+struct GpuTimer {
+    slots: Vec<Slot>,
+    /// The slot the nodes write into this frame
+    current: usize,
+    /// One bit per query written into the current slot
+    written: AtomicU32,
+    /// Nanoseconds per tick
+    period: f32,
+}
+
+impl GpuTimer {
+    fn new(device: &RenderDevice, queue: &RenderQueue) -> Self {
+        let size = QUERIES as u64 * std::mem::size_of::<u64>() as u64;
+        let slots = (0..SLOTS)
+            .map(|_| Slot {
+                query_set: device.wgpu_device().create_query_set(&QuerySetDescriptor {
+                    label: Some("xr_gpu_timestamps"),
+                    ty: QueryType::Timestamp,
+                    count: QUERIES,
+                }),
+                resolve: device.create_buffer(&BufferDescriptor {
+                    label: Some("xr_gpu_timestamps_resolve"),
+                    size,
+                    usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                readback: device.create_buffer(&BufferDescriptor {
+                    label: Some("xr_gpu_timestamps_readback"),
+                    size,
+                    usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                state: Arc::new(AtomicU8::new(FREE)),
+            })
+            .collect();
+        Self {
+            slots,
+            current: 0,
+            written: AtomicU32::new(0),
+            period: queue.get_timestamp_period(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct GpuTimestampNode<const END: bool>;
+
+impl<const END: bool> ViewNode for GpuTimestampNode<END> {
+    type ViewQuery = &'static XrCamera;
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        camera: QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let Some(timer) = world.get_resource::<GpuTimer>() else {
+            return Ok(());
+        };
+        let slot = &timer.slots[timer.current];
+        // still waiting for the results of an earlier frame
+        if slot.state.load(Ordering::Acquire) != FREE {
+            return Ok(());
+        }
+        let index = camera.eye() as u32 * 2 + END as u32;
+        // the focus views of quad views aren't measured
+        if index >= QUERIES {
+            return Ok(());
+        }
+        render_context
+            .command_encoder()
+            .write_timestamp(&slot.query_set, index);
+        timer.written.fetch_or(1 << index, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Reads the slots the gpu is done with and resolves the queries written this frame
+fn read_back_timestamps(
+    mut timer: ResMut<GpuTimer>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    results: Res<GpuTimes>,
+) {
+    device.poll(wgpu::Maintain::Poll);
+    let period = timer.period as f64;
+    for slot in &timer.slots {
+        if slot.state.load(Ordering::Acquire) != MAPPED {
+            continue;
+        }
+        let ticks: [u64; QUERIES as usize] = {
+            let data = slot.readback.slice(..).get_mapped_range();
+            std::array::from_fn(|i| u64::from_le_bytes(data[i * 8..i * 8 + 8].try_into().unwrap()))
+        };
+        slot.readback.unmap();
+        slot.state.store(FREE, Ordering::Release);
+        let time = |begin: u64, end: u64| {
+            Duration::from_nanos((end.saturating_sub(begin) as f64 * period) as u64)
+        };
+        let [left_begin, left_end, right_begin, right_end] = ticks;
+        *results.0.lock().unwrap() = Some([
+            time(left_begin, left_end),
+            time(right_begin, right_end),
+            time(left_begin.min(right_begin), left_end.max(right_end)),
+        ]);
+    }
+
+    let written = timer.written.swap(0, Ordering::Relaxed);
+    // frames where an eye wasn't rendered just reuse the slot
+    if written != (1 << QUERIES) - 1 {
+        return;
+    }
+    let slot = &timer.slots[timer.current];
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("xr_gpu_timestamps_resolve"),
+    });
+    encoder.resolve_query_set(&slot.query_set, 0..QUERIES, &slot.resolve, 0);
+    encoder.copy_buffer_to_buffer(&slot.resolve, 0, &slot.readback, 0, slot.readback.size());
+    queue.submit([encoder.finish()]);
+    slot.state.store(MAPPING, Ordering::Release);
+    let state = slot.state.clone();
+    slot.readback
+        .slice(..)
+        .map_async(MapMode::Read, move |result| {
+            let next = if result.is_ok() { MAPPED } else { FREE };
+            state.store(next, Ordering::Release);
+        });
+    timer.current = (timer.current + 1) % SLOTS;
+}
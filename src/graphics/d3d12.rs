@@ -1,10 +1,12 @@
+use std::ffi::c_void;
+use std::ptr;
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 
 // use anyhow::Context;
-use bevy::math::uvec2;
 use bevy::prelude::*;
 use bevy::render::renderer::{RenderAdapter, RenderAdapterInfo, RenderDevice, RenderQueue};
+use bevy::render::settings::WgpuSettings;
 use bevy::window::RawHandleWrapper;
 use eyre::{Context, ContextCompat};
 use openxr as xr;
@@ -15,12 +17,15 @@ use winapi::shared::dxgiformat::{self, DXGI_FORMAT};
 use winapi::um::{d3d12 as winapi_d3d12, d3dcommon};
 use xr::EnvironmentBlendMode;
 
+use crate::error::XrResultExt;
 use crate::graphics::extensions::XrExtensions;
 use crate::input::XrInput;
+use crate::overlay::create_overlay_session;
 
 use crate::resources::{
-    OXrSessionSetupInfo, Swapchain, SwapchainInner, XrEnvironmentBlendMode, XrFormat, XrFrameState,
-    XrFrameWaiter, XrInstance, XrResolution, XrSession, XrSessionRunning, XrSwapchain, XrViews,
+    OXrSessionSetupInfo, Swapchain, SwapchainImages, SwapchainInner, XrEnvironmentBlendMode,
+    XrFormat, XrFrameState, XrFrameWaiter, XrInstance, XrResolution, XrSession, XrSessionRunning,
+    XrSwapchain, XrViews,
 };
 
 #[cfg(all(feature = "d3d12", windows))]
@@ -28,16 +33,19 @@ use crate::resources::D3D12OXrSessionSetupInfo;
 #[cfg(feature = "vulkan")]
 use crate::resources::VulkanOXrSessionSetupInfo;
 
-use super::{XrAppInfo, XrPreferdBlendMode};
+use super::{device_features_and_limits, XrAppInfo, XrPreferdBlendMode, XrSessionConfig};
 use crate::VIEW_TYPE;
 
+#[allow(clippy::too_many_arguments)]
 pub fn initialize_xr_instance(
     window: Option<RawHandleWrapper>,
     xr_entry: xr::Entry,
     reqeusted_extensions: XrExtensions,
     available_extensions: XrExtensions,
+    api_layers: &[&str],
     prefered_blend_mode: XrPreferdBlendMode,
     app_info: XrAppInfo,
+    wgpu_settings: Option<&WgpuSettings>,
 ) -> eyre::Result<(
     XrInstance,
     OXrSessionSetupInfo,
@@ -58,21 +66,22 @@ pub fn initialize_xr_instance(
         (available_extensions & reqeusted_extensions).into();
     enabled_extensions.khr_d3d12_enable = true;
 
-    let available_layers = xr_entry.enumerate_layers()?;
-    //info!("available xr layers: {:#?}", available_layers);
-
-    let xr_instance = xr_entry.create_instance(
-        &xr::ApplicationInfo {
-            application_name: &app_info.name,
-            engine_name: "Bevy",
-            ..Default::default()
-        },
-        &enabled_extensions,
-        &[],
-    )?;
+    let xr_instance = xr_entry
+        .create_instance(
+            &xr::ApplicationInfo {
+                application_name: &app_info.name,
+                engine_name: "Bevy",
+                ..Default::default()
+            },
+            &enabled_extensions,
+            api_layers,
+        )
+        .call("xrCreateInstance")?;
     info!("created OpenXR instance");
-    let instance_props = xr_instance.properties()?;
-    let xr_system_id = xr_instance.system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)?;
+    let instance_props = xr_instance.properties().call("xrGetInstanceProperties")?;
+    let xr_system_id = xr_instance
+        .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+        .call("xrGetSystem")?;
     info!("created OpenXR system");
     let system_props = xr_instance.system_properties(xr_system_id).unwrap();
     info!(
@@ -138,20 +147,26 @@ pub fn initialize_xr_instance(
     let wgpu_instance =
         unsafe { wgpu::Instance::from_hal::<wgpu_hal::api::Dx12>(wgpu_raw_instance) };
 
-    let wgpu_features = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
-        | wgpu::Features::MULTIVIEW
-        | wgpu::Features::MULTI_DRAW_INDIRECT_COUNT
-        | wgpu::Features::MULTI_DRAW_INDIRECT;
-
-    let wgpu_limits = wgpu::Limits {
-        max_bind_groups: 8,
-        max_storage_buffer_binding_size: wgpu_exposed_adapter
-            .capabilities
-            .limits
-            .max_storage_buffer_binding_size,
-        max_push_constant_size: 4,
-        ..Default::default()
-    };
+    // timestamps are only used by the XrGpuTimingPlugin, so they're optional
+    let (wgpu_features, wgpu_limits) = device_features_and_limits(
+        wgpu_settings,
+        wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+            | wgpu::Features::MULTIVIEW
+            | wgpu::Features::MULTI_DRAW_INDIRECT_COUNT
+            | wgpu::Features::MULTI_DRAW_INDIRECT,
+        wgpu::Features::TIMESTAMP_QUERY,
+        wgpu::Limits {
+            max_bind_groups: 8,
+            max_storage_buffer_binding_size: wgpu_exposed_adapter
+                .capabilities
+                .limits
+                .max_storage_buffer_binding_size,
+            max_push_constant_size: 4,
+            ..Default::default()
+        },
+        wgpu_exposed_adapter.features,
+        &wgpu_exposed_adapter.capabilities.limits,
+    )?;
 
     let wgpu_open_device = unsafe {
         wgpu_exposed_adapter
@@ -214,6 +229,7 @@ pub fn start_xr_session(
     window: Option<RawHandleWrapper>,
     ptrs: &OXrSessionSetupInfo,
     xr_instance: &XrInstance,
+    session_config: &XrSessionConfig,
     render_device: &RenderDevice,
     render_adapter: &RenderAdapter,
     wgpu_instance: &Instance,
@@ -236,18 +252,41 @@ pub fn start_xr_session(
         OXrSessionSetupInfo::D3D12(v) => v,
         _ => eyre::bail!("Wrong Graphics Api"),
     };
-    let (session, frame_wait, frame_stream) = unsafe {
-        xr_instance.create_session::<xr::D3D12>(
-            setup_info.xr_system_id,
-            &xr::d3d::SessionCreateInfoD3D12 {
-                device: setup_info.raw_device.cast(),
-                queue: setup_info.raw_queue.cast(),
+    // the frames are begun and ended through the session, see `XrFrameHooks`
+    let (session, frame_wait, _frame_stream) =
+        match super::overlay_settings(xr_instance, session_config) {
+            Some(overlay) => unsafe {
+                let binding = xr::sys::GraphicsBindingD3D12KHR {
+                    ty: xr::sys::GraphicsBindingD3D12KHR::TYPE,
+                    next: ptr::null(),
+                    device: setup_info.raw_device.cast(),
+                    queue: setup_info.raw_queue.cast(),
+                };
+                create_overlay_session::<xr::D3D12>(
+                    xr_instance,
+                    setup_info.xr_system_id,
+                    &binding as *const _ as *const c_void,
+                    &overlay,
+                )
             },
-        )
-    }?;
+            None => unsafe {
+                xr_instance.create_session::<xr::D3D12>(
+                    setup_info.xr_system_id,
+                    &xr::d3d::SessionCreateInfoD3D12 {
+                        device: setup_info.raw_device.cast(),
+                        queue: setup_info.raw_queue.cast(),
+                    },
+                )
+            },
+        }
+        .call("xrCreateSession")?;
 
-    let views =
-        xr_instance.enumerate_view_configuration_views(setup_info.xr_system_id, VIEW_TYPE)?;
+    let views = xr_instance
+        .enumerate_view_configuration_views(
+            setup_info.xr_system_id,
+            session_config.view_config.view_type(),
+        )
+        .call("xrEnumerateViewConfigurationViews")?;
     let surface = window.map(|wrapper| unsafe {
         // SAFETY: Plugins should be set up on the main thread.
         let handle = wrapper.get_handle();
@@ -255,57 +294,243 @@ pub fn start_xr_session(
             .create_surface(handle)
             .expect("Failed to create wgpu surface")
     });
-    let swapchain_format = surface
-        .as_ref()
-        .map(|surface| surface.get_capabilities(wgpu_adapter).formats[0])
-        .unwrap_or(wgpu::TextureFormat::Rgba8UnormSrgb);
+    let preferred_formats = match session_config.swapchain_formats.is_empty() {
+        true => surface
+            .as_ref()
+            .map(|surface| surface.get_capabilities(wgpu_adapter).formats[0])
+            .into_iter()
+            .chain([wgpu::TextureFormat::Rgba8UnormSrgb])
+            .collect(),
+        false => session_config.swapchain_formats.clone(),
+    };
+    let supported_formats = enumerate_swapchain_formats(&session)?;
+    let swapchain_format = super::select_swapchain_format(&preferred_formats, &supported_formats)?;
+    info!(
+        "Using swapchain format {:?}, supported formats: {:?}",
+        swapchain_format, supported_formats
+    );
 
-    // TODO: Log swapchain format
+    let resolution = super::swapchain_resolution(
+        xr_instance,
+        setup_info.xr_system_id,
+        &views,
+        session_config.render_scale,
+    )?;
+    let view_count = session_config.view_config.view_count();
 
-    let resolution = uvec2(
-        views[0].recommended_image_rect_width,
-        views[0].recommended_image_rect_height,
+    // viewing the swapchain images with another format isn't supported on d3d12 yet
+    let view_format = super::select_view_format(
+        swapchain_format,
+        session_config.swapchain_view_format,
+        false,
     );
+    let swapchain = create_view_swapchain(
+        xr_instance,
+        &session,
+        wgpu_device,
+        swapchain_format,
+        view_format,
+        resolution,
+        view_count,
+        session_config.depth_layer,
+    )?;
+
+    Ok((
+        XrSession::D3D12(session.clone()),
+        resolution.into(),
+        swapchain_format.into(),
+        // TODO: this shouldn't be in here
+        AtomicBool::new(false).into(),
+        frame_wait.into(),
+        Swapchain::D3D12(swapchain).into(),
+        XrInput::new(xr_instance, &session.into_any_graphics(), session_config)?,
+        Vec::default().into(),
+        // TODO: Feels wrong to return a FrameState here, we probably should just wait for the next frame
+        xr::FrameState {
+            predicted_display_time: xr::Time::from_nanos(1),
+            predicted_display_period: xr::Duration::from_nanos(1),
+            should_render: true,
+        }
+        .into(),
+    ))
+}
 
+/// Creates the swapchain the views render into and the depth swapchain submitted with it, when
+/// the session starts and when it's resized
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_view_swapchain(
+    xr_instance: &XrInstance,
+    session: &xr::Session<xr::D3D12>,
+    wgpu_device: &wgpu::Device,
+    swapchain_format: wgpu::TextureFormat,
+    view_format: wgpu::TextureFormat,
+    resolution: UVec2,
+    view_count: u32,
+    depth_layer: bool,
+) -> eyre::Result<SwapchainInner<xr::D3D12>> {
     let handle = session
         .create_swapchain(&xr::SwapchainCreateInfo {
             create_flags: xr::SwapchainCreateFlags::EMPTY,
             usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT
-                | xr::SwapchainUsageFlags::SAMPLED,
+                | xr::SwapchainUsageFlags::SAMPLED
+                | xr::SwapchainUsageFlags::TRANSFER_SRC,
             format: wgpu_to_d3d12(swapchain_format).expect("Unsupported texture format"),
-            // The Vulkan graphics pipeline we create is not set up for multisampling,
-            // so we hardcode this to 1. If we used a proper multisampling setup, we
-            // could set this to `views[0].recommended_swapchain_sample_count`.
+            // bevy renders into its own multisampled texture and resolves into the swapchain
+            // image, see `XrSessionConfig::samples`
             sample_count: 1,
             width: resolution.x,
             height: resolution.y,
             face_count: 1,
-            array_size: 2,
+            array_size: view_count,
             mip_count: 1,
         })
-        .unwrap();
+        .call("xrCreateSwapchain")?;
+
+    let buffers = swapchain_textures(
+        wgpu_device,
+        &handle,
+        swapchain_format,
+        resolution,
+        view_count,
+        // sampled by the desktop mirror and copied from by frame captures
+        wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::TEXTURE_BINDING,
+    )?;
 
-    let images = handle.enumerate_images().unwrap();
+    let depth = if depth_layer && xr_instance.exts().khr_composition_layer_depth.is_some() {
+        let depth_format = wgpu::TextureFormat::Depth32Float;
+        let dxgi_depth_format = wgpu_to_d3d12(depth_format).expect("Unsupported texture format");
+        if session
+            .enumerate_swapchain_formats()?
+            .contains(&dxgi_depth_format)
+        {
+            let handle = session.create_swapchain(&xr::SwapchainCreateInfo {
+                create_flags: xr::SwapchainCreateFlags::EMPTY,
+                usage_flags: xr::SwapchainUsageFlags::DEPTH_STENCIL_ATTACHMENT
+                    | xr::SwapchainUsageFlags::TRANSFER_DST,
+                format: dxgi_depth_format,
+                sample_count: 1,
+                width: resolution.x,
+                height: resolution.y,
+                face_count: 1,
+                array_size: view_count,
+                mip_count: 1,
+            })?;
+            let buffers = swapchain_textures(
+                wgpu_device,
+                &handle,
+                depth_format,
+                resolution,
+                view_count,
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+            )?;
+            Some(SwapchainImages::new(handle, buffers))
+        } else {
+            warn!("Runtime doesn't support Depth32Float swapchains, not submitting depth");
+            None
+        }
+    } else {
+        None
+    };
 
-    let buffers = images
+    Ok(SwapchainInner::new(
+        session.clone(),
+        handle,
+        buffers,
+        view_format,
+        depth,
+    ))
+}
+
+/// Creates a single layer color swapchain, used for composition layers
+pub(crate) fn create_layer_swapchain(
+    session: &xr::Session<xr::D3D12>,
+    wgpu_device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    resolution: UVec2,
+) -> xr::Result<SwapchainImages<xr::D3D12>> {
+    let handle = session.create_swapchain(&xr::SwapchainCreateInfo {
+        create_flags: xr::SwapchainCreateFlags::EMPTY,
+        usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT | xr::SwapchainUsageFlags::SAMPLED,
+        format: wgpu_to_d3d12(format).expect("Unsupported texture format"),
+        sample_count: 1,
+        width: resolution.x,
+        height: resolution.y,
+        face_count: 1,
+        array_size: 1,
+        mip_count: 1,
+    })?;
+    let buffers = swapchain_textures(
+        wgpu_device,
+        &handle,
+        format,
+        resolution,
+        1,
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+    )?;
+    Ok(SwapchainImages::new(handle, buffers))
+}
+
+/// A static swapchain with the six faces of a cube, written once by copying into it
+pub(crate) fn create_cube_swapchain(
+    session: &xr::Session<xr::D3D12>,
+    wgpu_device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    size: u32,
+) -> xr::Result<SwapchainImages<xr::D3D12>> {
+    let handle = session.create_swapchain(&xr::SwapchainCreateInfo {
+        create_flags: xr::SwapchainCreateFlags::STATIC_IMAGE,
+        usage_flags: xr::SwapchainUsageFlags::TRANSFER_DST | xr::SwapchainUsageFlags::SAMPLED,
+        format: wgpu_to_d3d12(format).expect("Unsupported texture format"),
+        sample_count: 1,
+        width: size,
+        height: size,
+        face_count: 6,
+        array_size: 1,
+        mip_count: 1,
+    })?;
+    let buffers = swapchain_textures(
+        wgpu_device,
+        &handle,
+        format,
+        UVec2::splat(size),
+        6,
+        wgpu::TextureUsages::COPY_DST,
+    )?;
+    Ok(SwapchainImages::new(handle, buffers))
+}
+
+fn swapchain_textures(
+    wgpu_device: &wgpu::Device,
+    handle: &xr::Swapchain<xr::D3D12>,
+    format: wgpu::TextureFormat,
+    resolution: UVec2,
+    array_size: u32,
+    usage: wgpu::TextureUsages,
+) -> xr::Result<Vec<wgpu::Texture>> {
+    let images = handle.enumerate_images()?;
+
+    Ok(images
         .into_iter()
-        .map(|color_image| {
+        .map(|image| {
             info!("image map swapchain");
             let wgpu_hal_texture = unsafe {
                 <Dx12 as Api>::Device::texture_from_raw(
-                    d3d12::ComPtr::from_raw(color_image as *mut _),
-                    swapchain_format,
+                    d3d12::ComPtr::from_raw(image as *mut _),
+                    format,
                     wgpu::TextureDimension::D2,
                     wgpu::Extent3d {
                         width: resolution.x,
                         height: resolution.y,
-                        depth_or_array_layers: 2,
+                        depth_or_array_layers: array_size,
                     },
                     1,
                     1,
                 )
             };
-            let texture = unsafe {
+            unsafe {
                 wgpu_device.create_texture_from_hal::<Dx12>(
                     wgpu_hal_texture,
                     &wgpu::TextureDescriptor {
@@ -313,46 +538,19 @@ pub fn start_xr_session(
                         size: wgpu::Extent3d {
                             width: resolution.x,
                             height: resolution.y,
-                            depth_or_array_layers: 2,
+                            depth_or_array_layers: array_size,
                         },
                         mip_level_count: 1,
                         sample_count: 1,
                         dimension: wgpu::TextureDimension::D2,
-                        format: swapchain_format,
-                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                            | wgpu::TextureUsages::COPY_DST,
+                        format,
+                        usage,
                         view_formats: &[],
                     },
                 )
-            };
-            texture
-        })
-        .collect();
-
-    Ok((
-        XrSession::D3D12(session.clone()),
-        resolution.into(),
-        swapchain_format.into(),
-        // TODO: this shouldn't be in here
-        AtomicBool::new(false).into(),
-        frame_wait.into(),
-        Swapchain::D3D12(SwapchainInner {
-            stream: Mutex::new(frame_stream),
-            handle: Mutex::new(handle),
-            buffers,
-            image_index: Mutex::new(0),
+            }
         })
-        .into(),
-        XrInput::new(xr_instance, &session.into_any_graphics())?,
-        Vec::default().into(),
-        // TODO: Feels wrong to return a FrameState here, we probably should just wait for the next frame
-        xr::FrameState {
-            predicted_display_time: xr::Time::from_nanos(1),
-            predicted_display_period: xr::Duration::from_nanos(1),
-            should_render: true,
-        }
-        .into(),
-    ))
+        .collect())
 }
 
 // Extracted from https://github.com/gfx-rs/wgpu/blob/1161a22f4fbb4fc204eb06f2ac4243f83e0e980d/wgpu-hal/src/dx12/adapter.rs#L73-L94
@@ -384,6 +582,16 @@ fn get_device_feature_level(
     max_feature_level
 }
 
+pub(crate) fn enumerate_swapchain_formats(
+    session: &xr::Session<xr::D3D12>,
+) -> xr::Result<Vec<wgpu::TextureFormat>> {
+    Ok(session
+        .enumerate_swapchain_formats()?
+        .into_iter()
+        .filter_map(|format| super::format_from_raw(format, wgpu_to_d3d12))
+        .collect())
+}
+
 fn wgpu_to_d3d12(format: wgpu::TextureFormat) -> Option<DXGI_FORMAT> {
     // Copied wholesale from:
     // https://github.com/gfx-rs/wgpu/blob/v0.19/wgpu-hal/src/auxil/dxgi/conv.rs#L12-L94
@@ -1,6 +1,11 @@
+use bevy::prelude::Resource;
 use openxr::ExtensionSet;
 use std::ops;
 
+use crate::overlay::EXTX_OVERLAY_EXTENSION_NAME;
+use crate::user_presence::EXT_USER_PRESENCE_EXTENSION_NAME;
+use crate::xr_input::vive_trackers::HTCX_VIVE_TRACKER_EXTENSION_NAME;
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct XrExtensions(ExtensionSet);
 impl XrExtensions {
@@ -26,6 +31,129 @@ impl XrExtensions {
         self.0.ext_hand_tracking = false;
         self
     }
+    /// Needed by [`HandMeshPlugin`](crate::xr_input::hands::hand_mesh::HandMeshPlugin)
+    pub fn enable_hand_tracking_mesh(&mut self) -> &mut Self {
+        self.0.fb_hand_tracking_mesh = true;
+        self
+    }
+    pub fn disable_hand_tracking_mesh(&mut self) -> &mut Self {
+        self.0.fb_hand_tracking_mesh = false;
+        self
+    }
+    /// Needed by [`XrEyeGazePlugin`](crate::xr_input::eye_gaze::XrEyeGazePlugin)
+    pub fn enable_eye_gaze_interaction(&mut self) -> &mut Self {
+        self.0.ext_eye_gaze_interaction = true;
+        self
+    }
+    pub fn disable_eye_gaze_interaction(&mut self) -> &mut Self {
+        self.0.ext_eye_gaze_interaction = false;
+        self
+    }
+    /// Needed by [`XrFaceTrackingPlugin`](crate::xr_input::face_tracking::XrFaceTrackingPlugin)
+    pub fn enable_face_tracking(&mut self) -> &mut Self {
+        self.0.fb_face_tracking = true;
+        self
+    }
+    pub fn disable_face_tracking(&mut self) -> &mut Self {
+        self.0.fb_face_tracking = false;
+        self
+    }
+    /// Needed by [`XrBodyTrackingPlugin`](crate::xr_input::body_tracking::XrBodyTrackingPlugin)
+    pub fn enable_body_tracking(&mut self) -> &mut Self {
+        self.0.fb_body_tracking = true;
+        self
+    }
+    pub fn disable_body_tracking(&mut self) -> &mut Self {
+        self.0.fb_body_tracking = false;
+        self
+    }
+    /// Used by [`XrControllerModelPlugin`](crate::xr_input::controller_models::XrControllerModelPlugin)
+    pub fn enable_controller_model(&mut self) -> &mut Self {
+        self.0.msft_controller_model = true;
+        self
+    }
+    pub fn disable_controller_model(&mut self) -> &mut Self {
+        self.0.msft_controller_model = false;
+        self
+    }
+    /// Used by [`XrActionSets::apply_pcm`](crate::xr_input::actions::XrActionSets::apply_pcm)
+    pub fn enable_haptic_pcm(&mut self) -> &mut Self {
+        self.0.fb_haptic_pcm = true;
+        self
+    }
+    pub fn disable_haptic_pcm(&mut self) -> &mut Self {
+        self.0.fb_haptic_pcm = false;
+        self
+    }
+    /// Needed by [`XrViveTrackerPlugin`](crate::xr_input::vive_trackers::XrViveTrackerPlugin)
+    pub fn enable_vive_trackers(&mut self) -> &mut Self {
+        self.disable_vive_trackers();
+        self.0
+            .other
+            .push(HTCX_VIVE_TRACKER_EXTENSION_NAME.to_string());
+        self
+    }
+    pub fn disable_vive_trackers(&mut self) -> &mut Self {
+        self.0
+            .other
+            .retain(|ext| ext != HTCX_VIVE_TRACKER_EXTENSION_NAME);
+        self
+    }
+    /// Needed for [`XrComponent::PalmPose`](crate::xr_input::paths::XrComponent::PalmPose)
+    /// bindings, they fall back to the grip pose otherwise
+    pub fn enable_palm_pose(&mut self) -> &mut Self {
+        self.0.ext_palm_pose = true;
+        self
+    }
+    pub fn disable_palm_pose(&mut self) -> &mut Self {
+        self.0.ext_palm_pose = false;
+        self
+    }
+    /// Needed by [`XrAnchorPlugin`](crate::anchors::XrAnchorPlugin), enables the anchor and
+    /// storage extensions of both Meta and Microsoft, the runtime supports one of them at most
+    pub fn enable_spatial_anchors(&mut self) -> &mut Self {
+        self.0.fb_spatial_entity = true;
+        self.0.fb_spatial_entity_query = true;
+        self.0.fb_spatial_entity_storage = true;
+        self.0.msft_spatial_anchor = true;
+        self.0.msft_spatial_anchor_persistence = true;
+        self
+    }
+    pub fn disable_spatial_anchors(&mut self) -> &mut Self {
+        self.0.fb_spatial_entity = false;
+        self.0.fb_spatial_entity_query = false;
+        self.0.fb_spatial_entity_storage = false;
+        self.0.msft_spatial_anchor = false;
+        self.0.msft_spatial_anchor_persistence = false;
+        self
+    }
+    /// Needed by [`XrScenePlugin`](crate::scene::XrScenePlugin), the room setup with
+    /// [`XrRequestSceneCapture`](crate::scene::XrRequestSceneCapture) only works if the
+    /// runtime also supports `XR_FB_scene_capture`
+    pub fn enable_scene(&mut self) -> &mut Self {
+        self.0.fb_scene = true;
+        self.0.fb_scene_capture = true;
+        self.0.fb_spatial_entity = true;
+        self.0.fb_spatial_entity_query = true;
+        self
+    }
+    /// Only disables the scene extensions, the spatial entity extensions shared with
+    /// [`XrExtensions::enable_spatial_anchors`] stay as they are
+    pub fn disable_scene(&mut self) -> &mut Self {
+        self.0.fb_scene = false;
+        self.0.fb_scene_capture = false;
+        self
+    }
+    /// Needed for [`XrSessionConfig::reference_space`](crate::graphics::XrSessionConfig::reference_space)
+    /// UNBOUNDED_MSFT, enabled automatically when that space is requested
+    pub fn enable_unbounded_reference_space(&mut self) -> &mut Self {
+        self.0.msft_unbounded_reference_space = true;
+        self
+    }
+    pub fn disable_unbounded_reference_space(&mut self) -> &mut Self {
+        self.0.msft_unbounded_reference_space = false;
+        self
+    }
     pub fn enable_local_floor(&mut self) -> &mut Self {
         self.0.ext_local_floor = true;
         self
@@ -34,6 +162,189 @@ impl XrExtensions {
         self.0.ext_local_floor = false;
         self
     }
+    pub fn enable_depth_layer(&mut self) -> &mut Self {
+        self.0.khr_composition_layer_depth = true;
+        self
+    }
+    pub fn disable_depth_layer(&mut self) -> &mut Self {
+        self.0.khr_composition_layer_depth = false;
+        self
+    }
+    pub fn enable_display_refresh_rate(&mut self) -> &mut Self {
+        self.0.fb_display_refresh_rate = true;
+        self
+    }
+    pub fn disable_display_refresh_rate(&mut self) -> &mut Self {
+        self.0.fb_display_refresh_rate = false;
+        self
+    }
+    pub fn enable_display_color_space(&mut self) -> &mut Self {
+        self.0.fb_color_space = true;
+        self
+    }
+    pub fn disable_display_color_space(&mut self) -> &mut Self {
+        self.0.fb_color_space = false;
+        self
+    }
+    pub fn enable_performance_settings(&mut self) -> &mut Self {
+        self.0.ext_performance_settings = true;
+        self
+    }
+    pub fn disable_performance_settings(&mut self) -> &mut Self {
+        self.0.ext_performance_settings = false;
+        self
+    }
+    pub fn enable_first_person_observer(&mut self) -> &mut Self {
+        self.0.msft_secondary_view_configuration = true;
+        self.0.msft_first_person_observer = true;
+        self
+    }
+    pub fn disable_first_person_observer(&mut self) -> &mut Self {
+        self.0.msft_secondary_view_configuration = false;
+        self.0.msft_first_person_observer = false;
+        self
+    }
+    pub fn enable_foveation(&mut self) -> &mut Self {
+        self.0.fb_foveation = true;
+        self.0.fb_foveation_configuration = true;
+        self.0.fb_swapchain_update_state = true;
+        self
+    }
+    pub fn disable_foveation(&mut self) -> &mut Self {
+        self.0.fb_foveation = false;
+        self.0.fb_foveation_configuration = false;
+        self.0.fb_swapchain_update_state = false;
+        self
+    }
+    /// Foveation that follows the eyes, see [`XrFoveationSettings::eye_tracked`](crate::foveation::XrFoveationSettings::eye_tracked)
+    pub fn enable_eye_tracked_foveation(&mut self) -> &mut Self {
+        self.enable_foveation();
+        self.0.meta_foveation_eye_tracked = true;
+        self
+    }
+    pub fn disable_eye_tracked_foveation(&mut self) -> &mut Self {
+        self.0.meta_foveation_eye_tracked = false;
+        self
+    }
+    pub fn enable_overlay(&mut self) -> &mut Self {
+        self.disable_overlay();
+        self.0.other.push(EXTX_OVERLAY_EXTENSION_NAME.to_string());
+        self
+    }
+    pub fn disable_overlay(&mut self) -> &mut Self {
+        self.0
+            .other
+            .retain(|ext| ext != EXTX_OVERLAY_EXTENSION_NAME);
+        self
+    }
+    pub fn enable_visibility_mask(&mut self) -> &mut Self {
+        self.0.khr_visibility_mask = true;
+        self
+    }
+    pub fn disable_visibility_mask(&mut self) -> &mut Self {
+        self.0.khr_visibility_mask = false;
+        self
+    }
+    pub fn enable_cylinder_layer(&mut self) -> &mut Self {
+        self.0.khr_composition_layer_cylinder = true;
+        self
+    }
+    pub fn disable_cylinder_layer(&mut self) -> &mut Self {
+        self.0.khr_composition_layer_cylinder = false;
+        self
+    }
+    /// Needed for [`XrSkybox`](crate::skybox::XrSkybox) to be composited by the runtime
+    pub fn enable_cube_layer(&mut self) -> &mut Self {
+        self.0.khr_composition_layer_cube = true;
+        self
+    }
+    pub fn disable_cube_layer(&mut self) -> &mut Self {
+        self.0.khr_composition_layer_cube = false;
+        self
+    }
+    /// Lets the runtime emulate dpads on sticks and trackpads, see [`XrDpadBinding`](crate::xr_input::dpad::XrDpadBinding)
+    pub fn enable_dpad_binding(&mut self) -> &mut Self {
+        self.0.khr_binding_modification = true;
+        self.0.ext_dpad_binding = true;
+        self
+    }
+    pub fn disable_dpad_binding(&mut self) -> &mut Self {
+        self.0.khr_binding_modification = false;
+        self.0.ext_dpad_binding = false;
+        self
+    }
+    /// Needed by [`XrDebugUtilsPlugin`](crate::debug_utils::XrDebugUtilsPlugin)
+    pub fn enable_debug_utils(&mut self) -> &mut Self {
+        self.0.ext_debug_utils = true;
+        self
+    }
+    pub fn disable_debug_utils(&mut self) -> &mut Self {
+        self.0.ext_debug_utils = false;
+        self
+    }
+    pub fn enable_equirect_layer(&mut self) -> &mut Self {
+        self.0.khr_composition_layer_equirect2 = true;
+        self
+    }
+    pub fn disable_equirect_layer(&mut self) -> &mut Self {
+        self.0.khr_composition_layer_equirect2 = false;
+        self
+    }
+    /// Needed for [`XrCompositionLayer::exclude_from_capture`](crate::layers::XrCompositionLayer::exclude_from_capture)
+    pub fn enable_secure_content_layers(&mut self) -> &mut Self {
+        self.0.fb_composition_layer_secure_content = true;
+        self
+    }
+    pub fn disable_secure_content_layers(&mut self) -> &mut Self {
+        self.0.fb_composition_layer_secure_content = false;
+        self
+    }
+    pub fn enable_quad_views(&mut self) -> &mut Self {
+        self.0.varjo_quad_views = true;
+        self
+    }
+    pub fn disable_quad_views(&mut self) -> &mut Self {
+        self.0.varjo_quad_views = false;
+        self
+    }
+    /// Lets the runtime convert between [`XrTime`](crate::resources::XrTime) and
+    /// [`Instant`](std::time::Instant) exactly, see [`clock`](crate::clock)
+    pub fn enable_time_conversion(&mut self) -> &mut Self {
+        #[cfg(not(windows))]
+        {
+            self.0.khr_convert_timespec_time = true;
+        }
+        #[cfg(windows)]
+        {
+            self.0.khr_win32_convert_performance_counter_time = true;
+        }
+        self
+    }
+    pub fn disable_time_conversion(&mut self) -> &mut Self {
+        #[cfg(not(windows))]
+        {
+            self.0.khr_convert_timespec_time = false;
+        }
+        #[cfg(windows)]
+        {
+            self.0.khr_win32_convert_performance_counter_time = false;
+        }
+        self
+    }
+    /// Lets the runtime report [`XrUserPresenceChanged`](crate::user_presence::XrUserPresenceChanged)
+    pub fn enable_user_presence(&mut self) -> &mut Self {
+        self.disable_user_presence();
+        self.0
+            .other
+            .push(EXT_USER_PRESENCE_EXTENSION_NAME.to_string());
+        self
+    }
+    pub fn disable_user_presence(&mut self) -> &mut Self {
+        self.0
+            .other
+            .retain(|ext| ext != EXT_USER_PRESENCE_EXTENSION_NAME);
+        self
+    }
 }
 impl From<ExtensionSet> for XrExtensions {
     fn from(value: ExtensionSet) -> Self {
@@ -50,7 +361,11 @@ impl Default for XrExtensions {
         let mut exts = ExtensionSet::default();
         exts.ext_hand_tracking = true;
         exts.ext_local_floor = true;
-        Self(exts)
+        exts.other
+            .push(EXT_USER_PRESENCE_EXTENSION_NAME.to_string());
+        let mut exts = Self(exts);
+        exts.enable_time_conversion();
+        exts
     }
 }
 impl ops::BitAnd for XrExtensions {
@@ -58,155 +373,11 @@ impl ops::BitAnd for XrExtensions {
 
     fn bitand(self, rhs: Self) -> Self::Output {
         let mut out = ExtensionSet::default();
-        out.ext_local_floor = self.0.ext_local_floor && rhs.0.ext_local_floor;
-        out.almalence_digital_lens_control =
-            self.0.almalence_digital_lens_control && rhs.0.almalence_digital_lens_control;
-        out.epic_view_configuration_fov =
-            self.0.epic_view_configuration_fov && rhs.0.epic_view_configuration_fov;
-        out.ext_performance_settings =
-            self.0.ext_performance_settings && rhs.0.ext_performance_settings;
-        out.ext_thermal_query = self.0.ext_thermal_query && rhs.0.ext_thermal_query;
-        out.ext_debug_utils = self.0.ext_debug_utils && rhs.0.ext_debug_utils;
-        out.ext_eye_gaze_interaction =
-            self.0.ext_eye_gaze_interaction && rhs.0.ext_eye_gaze_interaction;
-        out.ext_view_configuration_depth_range =
-            self.0.ext_view_configuration_depth_range && rhs.0.ext_view_configuration_depth_range;
-        out.ext_conformance_automation =
-            self.0.ext_conformance_automation && rhs.0.ext_conformance_automation;
-        out.ext_hand_tracking = self.0.ext_hand_tracking && rhs.0.ext_hand_tracking;
-        out.ext_dpad_binding = self.0.ext_dpad_binding && rhs.0.ext_dpad_binding;
-        out.ext_hand_joints_motion_range =
-            self.0.ext_hand_joints_motion_range && rhs.0.ext_hand_joints_motion_range;
-        out.ext_samsung_odyssey_controller =
-            self.0.ext_samsung_odyssey_controller && rhs.0.ext_samsung_odyssey_controller;
-        out.ext_hp_mixed_reality_controller =
-            self.0.ext_hp_mixed_reality_controller && rhs.0.ext_hp_mixed_reality_controller;
-        out.ext_palm_pose = self.0.ext_palm_pose && rhs.0.ext_palm_pose;
-        out.ext_uuid = self.0.ext_uuid && rhs.0.ext_uuid;
-        // out.extx_overlay = self.0.extx_overlay && rhs.0.extx_overlay;
-        out.fb_composition_layer_image_layout =
-            self.0.fb_composition_layer_image_layout && rhs.0.fb_composition_layer_image_layout;
-        out.fb_composition_layer_alpha_blend =
-            self.0.fb_composition_layer_alpha_blend && rhs.0.fb_composition_layer_alpha_blend;
-        out.fb_swapchain_update_state =
-            self.0.fb_swapchain_update_state && rhs.0.fb_swapchain_update_state;
-        out.fb_composition_layer_secure_content =
-            self.0.fb_composition_layer_secure_content && rhs.0.fb_composition_layer_secure_content;
-        out.fb_display_refresh_rate =
-            self.0.fb_display_refresh_rate && rhs.0.fb_display_refresh_rate;
-        out.fb_color_space = self.0.fb_color_space && rhs.0.fb_color_space;
-        out.fb_hand_tracking_mesh = self.0.fb_hand_tracking_mesh && rhs.0.fb_hand_tracking_mesh;
-        out.fb_hand_tracking_aim = self.0.fb_hand_tracking_aim && rhs.0.fb_hand_tracking_aim;
-        out.fb_hand_tracking_capsules =
-            self.0.fb_hand_tracking_capsules && rhs.0.fb_hand_tracking_capsules;
-        out.fb_spatial_entity = self.0.fb_spatial_entity && rhs.0.fb_spatial_entity;
-        out.fb_foveation = self.0.fb_foveation && rhs.0.fb_foveation;
-        out.fb_foveation_configuration =
-            self.0.fb_foveation_configuration && rhs.0.fb_foveation_configuration;
-        out.fb_keyboard_tracking = self.0.fb_keyboard_tracking && rhs.0.fb_keyboard_tracking;
-        out.fb_triangle_mesh = self.0.fb_triangle_mesh && rhs.0.fb_triangle_mesh;
-        out.fb_passthrough = self.0.fb_passthrough && rhs.0.fb_passthrough;
-        out.fb_render_model = self.0.fb_render_model && rhs.0.fb_render_model;
-        out.fb_spatial_entity_query =
-            self.0.fb_spatial_entity_query && rhs.0.fb_spatial_entity_query;
-        out.fb_spatial_entity_storage =
-            self.0.fb_spatial_entity_storage && rhs.0.fb_spatial_entity_storage;
-        out.fb_foveation_vulkan = self.0.fb_foveation_vulkan && rhs.0.fb_foveation_vulkan;
-        out.fb_swapchain_update_state_opengl_es =
-            self.0.fb_swapchain_update_state_opengl_es && rhs.0.fb_swapchain_update_state_opengl_es;
-        out.fb_swapchain_update_state_vulkan =
-            self.0.fb_swapchain_update_state_vulkan && rhs.0.fb_swapchain_update_state_vulkan;
-        out.fb_space_warp = self.0.fb_space_warp && rhs.0.fb_space_warp;
-        out.fb_scene = self.0.fb_scene && rhs.0.fb_scene;
-        out.fb_spatial_entity_container =
-            self.0.fb_spatial_entity_container && rhs.0.fb_spatial_entity_container;
-        out.fb_passthrough_keyboard_hands =
-            self.0.fb_passthrough_keyboard_hands && rhs.0.fb_passthrough_keyboard_hands;
-        out.fb_composition_layer_settings =
-            self.0.fb_composition_layer_settings && rhs.0.fb_composition_layer_settings;
-        out.htc_vive_cosmos_controller_interaction = self.0.htc_vive_cosmos_controller_interaction
-            && rhs.0.htc_vive_cosmos_controller_interaction;
-        out.htc_facial_tracking = self.0.htc_facial_tracking && rhs.0.htc_facial_tracking;
-        out.htc_vive_focus3_controller_interaction = self.0.htc_vive_focus3_controller_interaction
-            && rhs.0.htc_vive_focus3_controller_interaction;
-        out.htc_hand_interaction = self.0.htc_hand_interaction && rhs.0.htc_hand_interaction;
-        out.htc_vive_wrist_tracker_interaction =
-            self.0.htc_vive_wrist_tracker_interaction && rhs.0.htc_vive_wrist_tracker_interaction;
-        // out.htcx_vive_tracker_interaction =
-        //     self.0.htcx_vive_tracker_interaction && rhs.0.htcx_vive_tracker_interaction;
-        out.huawei_controller_interaction =
-            self.0.huawei_controller_interaction && rhs.0.huawei_controller_interaction;
-        out.khr_composition_layer_cube =
-            self.0.khr_composition_layer_cube && rhs.0.khr_composition_layer_cube;
-        out.khr_composition_layer_depth =
-            self.0.khr_composition_layer_depth && rhs.0.khr_composition_layer_depth;
-        out.khr_vulkan_swapchain_format_list =
-            self.0.khr_vulkan_swapchain_format_list && rhs.0.khr_vulkan_swapchain_format_list;
-        out.khr_composition_layer_cylinder =
-            self.0.khr_composition_layer_cylinder && rhs.0.khr_composition_layer_cylinder;
-        out.khr_composition_layer_equirect =
-            self.0.khr_composition_layer_equirect && rhs.0.khr_composition_layer_equirect;
-        out.khr_opengl_enable = self.0.khr_opengl_enable && rhs.0.khr_opengl_enable;
-        out.khr_opengl_es_enable = self.0.khr_opengl_es_enable && rhs.0.khr_opengl_es_enable;
-        out.khr_vulkan_enable = self.0.khr_vulkan_enable && rhs.0.khr_vulkan_enable;
-        out.khr_visibility_mask = self.0.khr_visibility_mask && rhs.0.khr_visibility_mask;
-        out.khr_composition_layer_color_scale_bias = self.0.khr_composition_layer_color_scale_bias
-            && rhs.0.khr_composition_layer_color_scale_bias;
-        out.khr_convert_timespec_time =
-            self.0.khr_convert_timespec_time && rhs.0.khr_convert_timespec_time;
-        out.khr_loader_init = self.0.khr_loader_init && rhs.0.khr_loader_init;
-        out.khr_vulkan_enable2 = self.0.khr_vulkan_enable2 && rhs.0.khr_vulkan_enable2;
-        out.khr_composition_layer_equirect2 =
-            self.0.khr_composition_layer_equirect2 && rhs.0.khr_composition_layer_equirect2;
-        out.khr_binding_modification =
-            self.0.khr_binding_modification && rhs.0.khr_binding_modification;
-        out.khr_swapchain_usage_input_attachment_bit =
-            self.0.khr_swapchain_usage_input_attachment_bit
-                && rhs.0.khr_swapchain_usage_input_attachment_bit;
-        out.meta_vulkan_swapchain_create_info =
-            self.0.meta_vulkan_swapchain_create_info && rhs.0.meta_vulkan_swapchain_create_info;
-        out.meta_performance_metrics =
-            self.0.meta_performance_metrics && rhs.0.meta_performance_metrics;
-        out.ml_ml2_controller_interaction =
-            self.0.ml_ml2_controller_interaction && rhs.0.ml_ml2_controller_interaction;
-        out.mnd_headless = self.0.mnd_headless && rhs.0.mnd_headless;
-        out.mnd_swapchain_usage_input_attachment_bit =
-            self.0.mnd_swapchain_usage_input_attachment_bit
-                && rhs.0.mnd_swapchain_usage_input_attachment_bit;
-        // out.mndx_egl_enable = self.0.mndx_egl_enable && rhs.0.mndx_egl_enable;
-        out.msft_unbounded_reference_space =
-            self.0.msft_unbounded_reference_space && rhs.0.msft_unbounded_reference_space;
-        out.msft_spatial_anchor = self.0.msft_spatial_anchor && rhs.0.msft_spatial_anchor;
-        out.msft_spatial_graph_bridge =
-            self.0.msft_spatial_graph_bridge && rhs.0.msft_spatial_graph_bridge;
-        out.msft_hand_interaction = self.0.msft_hand_interaction && rhs.0.msft_hand_interaction;
-        out.msft_hand_tracking_mesh =
-            self.0.msft_hand_tracking_mesh && rhs.0.msft_hand_tracking_mesh;
-        out.msft_secondary_view_configuration =
-            self.0.msft_secondary_view_configuration && rhs.0.msft_secondary_view_configuration;
-        out.msft_first_person_observer =
-            self.0.msft_first_person_observer && rhs.0.msft_first_person_observer;
-        out.msft_controller_model = self.0.msft_controller_model && rhs.0.msft_controller_model;
-        out.msft_composition_layer_reprojection =
-            self.0.msft_composition_layer_reprojection && rhs.0.msft_composition_layer_reprojection;
-        out.msft_spatial_anchor_persistence =
-            self.0.msft_spatial_anchor_persistence && rhs.0.msft_spatial_anchor_persistence;
-        out.oculus_audio_device_guid =
-            self.0.oculus_audio_device_guid && rhs.0.oculus_audio_device_guid;
-        out.ultraleap_hand_tracking_forearm =
-            self.0.ultraleap_hand_tracking_forearm && rhs.0.ultraleap_hand_tracking_forearm;
-        out.valve_analog_threshold = self.0.valve_analog_threshold && rhs.0.valve_analog_threshold;
-        out.varjo_quad_views = self.0.varjo_quad_views && rhs.0.varjo_quad_views;
-        out.varjo_foveated_rendering =
-            self.0.varjo_foveated_rendering && rhs.0.varjo_foveated_rendering;
-        out.varjo_composition_layer_depth_test =
-            self.0.varjo_composition_layer_depth_test && rhs.0.varjo_composition_layer_depth_test;
-        out.varjo_environment_depth_estimation =
-            self.0.varjo_environment_depth_estimation && rhs.0.varjo_environment_depth_estimation;
-        out.varjo_marker_tracking = self.0.varjo_marker_tracking && rhs.0.varjo_marker_tracking;
-        out.varjo_view_offset = self.0.varjo_view_offset && rhs.0.varjo_view_offset;
-        and_android_only_exts(&self, &rhs, &mut out);
-        and_windows_only_exts(&self, &rhs, &mut out);
+        common_exts::and(&self.0, &rhs.0, &mut out);
+        #[cfg(target_os = "android")]
+        android_only_exts::and(&self.0, &rhs.0, &mut out);
+        #[cfg(windows)]
+        windows_only_exts::and(&self.0, &rhs.0, &mut out);
         for ext in self.0.other {
             if rhs.0.other.contains(&ext) {
                 out.other.push(ext);
@@ -216,37 +387,188 @@ impl ops::BitAnd for XrExtensions {
     }
 }
 
-#[cfg(not(target_os = "android"))]
-fn and_android_only_exts(lhs: &XrExtensions, rhs: &XrExtensions, out: &mut ExtensionSet) {}
-#[cfg(not(windows))]
-fn and_windows_only_exts(lhs: &XrExtensions, rhs: &XrExtensions, out: &mut ExtensionSet) {}
-#[cfg(target_os = "android")]
-fn and_android_only_exts(lhs: &XrExtensions, rhs: &XrExtensions, out: &mut ExtensionSet) {
-    out.oculus_android_session_state_enable =
-        lhs.0.oculus_android_session_state_enable && rhs.0.oculus_android_session_state_enable;
-    out.khr_loader_init_android = lhs.0.khr_loader_init_android && rhs.0.khr_loader_init_android;
-    out.fb_android_surface_swapchain_create =
-        lhs.0.fb_android_surface_swapchain_create && rhs.0.fb_android_surface_swapchain_create;
-    out.fb_swapchain_update_state_android_surface = lhs.0.fb_swapchain_update_state_android_surface
-        && rhs.0.fb_swapchain_update_state_android_surface;
-    out.khr_android_thread_settings =
-        lhs.0.khr_android_thread_settings && rhs.0.khr_android_thread_settings;
-    out.khr_android_surface_swapchain =
-        lhs.0.khr_android_surface_swapchain && rhs.0.khr_android_surface_swapchain;
-    out.khr_android_create_instance =
-        lhs.0.khr_android_create_instance && rhs.0.khr_android_create_instance;
+impl XrExtensions {
+    /// The names of the enabled extensions, like `XR_EXT_hand_tracking`
+    pub fn names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        common_exts::names(&self.0, &mut names);
+        #[cfg(target_os = "android")]
+        android_only_exts::names(&self.0, &mut names);
+        #[cfg(windows)]
+        windows_only_exts::names(&self.0, &mut names);
+        names.extend(self.0.other.iter().cloned());
+        names
+    }
+
+    /// The names of the extensions enabled in `self` but not in `other`
+    pub fn difference(&self, other: &XrExtensions) -> Vec<String> {
+        let other = other.names();
+        self.names()
+            .into_iter()
+            .filter(|name| !other.contains(name))
+            .collect()
+    }
+}
+
+/// The field names of [`ExtensionSet`] are the extension names without the `XR_` prefix
+/// and with a lowercase vendor
+fn extension_name(field: &str) -> String {
+    let (vendor, name) = field.split_once('_').unwrap_or((field, ""));
+    format!("XR_{}_{}", vendor.to_uppercase(), name)
 }
+
+macro_rules! extension_fields {
+    ($module:ident; $($field:ident),* $(,)?) => {
+        mod $module {
+            use openxr::ExtensionSet;
+
+            pub(super) fn and(lhs: &ExtensionSet, rhs: &ExtensionSet, out: &mut ExtensionSet) {
+                $(out.$field = lhs.$field && rhs.$field;)*
+            }
+
+            pub(super) fn names(exts: &ExtensionSet, names: &mut Vec<String>) {
+                $(if exts.$field {
+                    names.push(super::extension_name(stringify!($field)));
+                })*
+            }
+        }
+    };
+}
+
+extension_fields!(
+    common_exts;
+    ext_local_floor,
+    almalence_digital_lens_control,
+    epic_view_configuration_fov,
+    ext_performance_settings,
+    ext_thermal_query,
+    ext_debug_utils,
+    ext_eye_gaze_interaction,
+    ext_view_configuration_depth_range,
+    ext_conformance_automation,
+    ext_hand_tracking,
+    ext_dpad_binding,
+    ext_hand_joints_motion_range,
+    ext_samsung_odyssey_controller,
+    ext_hp_mixed_reality_controller,
+    ext_palm_pose,
+    ext_uuid,
+    // extx_overlay,
+    fb_composition_layer_image_layout,
+    fb_composition_layer_alpha_blend,
+    fb_swapchain_update_state,
+    fb_composition_layer_secure_content,
+    fb_display_refresh_rate,
+    fb_color_space,
+    fb_hand_tracking_mesh,
+    fb_hand_tracking_aim,
+    fb_hand_tracking_capsules,
+    fb_spatial_entity,
+    fb_foveation,
+    fb_foveation_configuration,
+    fb_keyboard_tracking,
+    fb_triangle_mesh,
+    fb_passthrough,
+    fb_render_model,
+    fb_spatial_entity_query,
+    fb_spatial_entity_storage,
+    fb_foveation_vulkan,
+    fb_swapchain_update_state_opengl_es,
+    fb_swapchain_update_state_vulkan,
+    fb_space_warp,
+    fb_scene,
+    fb_scene_capture,
+    fb_spatial_entity_container,
+    fb_passthrough_keyboard_hands,
+    fb_composition_layer_settings,
+    fb_face_tracking,
+    fb_body_tracking,
+    fb_haptic_pcm,
+    htc_vive_cosmos_controller_interaction,
+    htc_facial_tracking,
+    htc_vive_focus3_controller_interaction,
+    htc_hand_interaction,
+    htc_vive_wrist_tracker_interaction,
+    // htcx_vive_tracker_interaction,
+    huawei_controller_interaction,
+    khr_composition_layer_cube,
+    khr_composition_layer_depth,
+    khr_vulkan_swapchain_format_list,
+    khr_composition_layer_cylinder,
+    khr_composition_layer_equirect,
+    khr_opengl_enable,
+    khr_opengl_es_enable,
+    khr_vulkan_enable,
+    khr_visibility_mask,
+    khr_composition_layer_color_scale_bias,
+    khr_convert_timespec_time,
+    khr_loader_init,
+    khr_vulkan_enable2,
+    khr_composition_layer_equirect2,
+    khr_binding_modification,
+    khr_swapchain_usage_input_attachment_bit,
+    meta_foveation_eye_tracked,
+    meta_vulkan_swapchain_create_info,
+    meta_performance_metrics,
+    ml_ml2_controller_interaction,
+    mnd_headless,
+    mnd_swapchain_usage_input_attachment_bit,
+    // mndx_egl_enable,
+    msft_unbounded_reference_space,
+    msft_spatial_anchor,
+    msft_spatial_graph_bridge,
+    msft_hand_interaction,
+    msft_hand_tracking_mesh,
+    msft_secondary_view_configuration,
+    msft_first_person_observer,
+    msft_controller_model,
+    msft_composition_layer_reprojection,
+    msft_spatial_anchor_persistence,
+    oculus_audio_device_guid,
+    ultraleap_hand_tracking_forearm,
+    valve_analog_threshold,
+    varjo_quad_views,
+    varjo_foveated_rendering,
+    varjo_composition_layer_depth_test,
+    varjo_environment_depth_estimation,
+    varjo_marker_tracking,
+    varjo_view_offset,
+);
+#[cfg(target_os = "android")]
+extension_fields!(
+    android_only_exts;
+    oculus_android_session_state_enable,
+    khr_loader_init_android,
+    fb_android_surface_swapchain_create,
+    fb_swapchain_update_state_android_surface,
+    khr_android_thread_settings,
+    khr_android_surface_swapchain,
+    khr_android_create_instance,
+);
 #[cfg(windows)]
-fn and_windows_only_exts(lhs: &XrExtensions, rhs: &XrExtensions, out: &mut ExtensionSet) {
-    out.ext_win32_appcontainer_compatible =
-        lhs.0.ext_win32_appcontainer_compatible && rhs.0.ext_win32_appcontainer_compatible;
-    out.khr_d3d11_enable = lhs.0.khr_d3d11_enable && rhs.0.khr_d3d11_enable;
-    out.khr_d3d12_enable = lhs.0.khr_d3d12_enable && rhs.0.khr_d3d12_enable;
-    out.khr_win32_convert_performance_counter_time =
-        lhs.0.khr_win32_convert_performance_counter_time
-            && rhs.0.khr_win32_convert_performance_counter_time;
-    out.msft_perception_anchor_interop =
-        lhs.0.msft_perception_anchor_interop && rhs.0.msft_perception_anchor_interop;
-    out.msft_holographic_window_attachment =
-        lhs.0.msft_holographic_window_attachment && rhs.0.msft_holographic_window_attachment;
+extension_fields!(
+    windows_only_exts;
+    ext_win32_appcontainer_compatible,
+    khr_d3d11_enable,
+    khr_d3d12_enable,
+    khr_win32_convert_performance_counter_time,
+    msft_perception_anchor_interop,
+    msft_holographic_window_attachment,
+);
+
+/// The requested extensions the instance was created with
+#[derive(Clone, Debug, Resource)]
+pub struct XrEnabledExtensions {
+    pub enabled: XrExtensions,
+    /// Names of the requested extensions the runtime doesn't support
+    pub missing: Vec<String>,
+}
+
+impl XrEnabledExtensions {
+    pub fn new(requested: &XrExtensions, available: &XrExtensions) -> Self {
+        Self {
+            enabled: available.clone() & requested.clone(),
+            missing: requested.difference(available),
+        }
+    }
 }
@@ -0,0 +1,507 @@
+use std::ffi::c_void;
+use std::num::NonZeroU32;
+use std::ptr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use bevy::render::renderer::{RenderAdapter, RenderAdapterInfo, RenderDevice, RenderQueue};
+use bevy::render::settings::WgpuSettings;
+use bevy::window::RawHandleWrapper;
+use eyre::ContextCompat;
+use glow::HasContext;
+use openxr as xr;
+use wgpu::Instance;
+use wgpu_hal::api::Gles;
+use xr::EnvironmentBlendMode;
+
+use crate::error::XrResultExt;
+use crate::graphics::extensions::XrExtensions;
+use crate::input::XrInput;
+use crate::overlay::create_overlay_session;
+
+use crate::resources::{
+    GlesOXrSessionSetupInfo, OXrSessionSetupInfo, Swapchain, SwapchainImages, SwapchainInner,
+    XrEnvironmentBlendMode, XrFormat, XrFrameState, XrFrameWaiter, XrInstance, XrResolution,
+    XrSession, XrSessionRunning, XrSwapchain, XrViews,
+};
+use crate::VIEW_TYPE;
+
+use super::{device_features_and_limits, XrAppInfo, XrPreferdBlendMode, XrSessionConfig};
+
+/// Makes the EGL context wgpu renders with current while OpenXR calls need it,
+/// wgpu only keeps it current while it uses it itself
+#[derive(Clone)]
+pub struct GlesContext(pub(crate) RenderDevice);
+
+impl GlesContext {
+    pub(crate) fn with<R>(&self, f: impl FnOnce() -> R) -> R {
+        unsafe {
+            self.0.wgpu_device().as_hal::<Gles, _, _>(|device| {
+                let _lock = device.map(|device| device.context().lock());
+                f()
+            })
+        }
+        .expect("the render device wasn't created with the GLES backend")
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_xr_instance(
+    _window: Option<RawHandleWrapper>,
+    xr_entry: xr::Entry,
+    reqeusted_extensions: XrExtensions,
+    available_extensions: XrExtensions,
+    api_layers: &[&str],
+    prefered_blend_mode: XrPreferdBlendMode,
+    app_info: XrAppInfo,
+    wgpu_settings: Option<&WgpuSettings>,
+) -> eyre::Result<(
+    XrInstance,
+    OXrSessionSetupInfo,
+    XrEnvironmentBlendMode,
+    RenderDevice,
+    RenderQueue,
+    RenderAdapterInfo,
+    RenderAdapter,
+    Instance,
+)> {
+    assert!(available_extensions.raw().khr_opengl_es_enable);
+
+    let mut enabled_extensions: xr::ExtensionSet =
+        (available_extensions & reqeusted_extensions).into();
+    enabled_extensions.khr_opengl_es_enable = true;
+    enabled_extensions.khr_android_create_instance = true;
+
+    let xr_instance = xr_entry
+        .create_instance(
+            &xr::ApplicationInfo {
+                application_name: &app_info.name,
+                engine_name: "Bevy",
+                ..Default::default()
+            },
+            &enabled_extensions,
+            api_layers,
+        )
+        .call("xrCreateInstance")?;
+    info!("created OpenXR instance");
+    let instance_props = xr_instance.properties().call("xrGetInstanceProperties")?;
+    let xr_system_id = xr_instance
+        .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+        .call("xrGetSystem")?;
+    info!("created OpenXR system");
+    let system_props = xr_instance.system_properties(xr_system_id).unwrap();
+    info!(
+        "loaded OpenXR runtime: {} {} {}",
+        instance_props.runtime_name,
+        instance_props.runtime_version,
+        if system_props.system_name.is_empty() {
+            "<unnamed>"
+        } else {
+            &system_props.system_name
+        }
+    );
+
+    let blend_modes = xr_instance.enumerate_environment_blend_modes(xr_system_id, VIEW_TYPE)?;
+    let blend_mode: EnvironmentBlendMode = match prefered_blend_mode {
+        XrPreferdBlendMode::Opaque if blend_modes.contains(&EnvironmentBlendMode::OPAQUE) => {
+            bevy::log::info!("Using Opaque");
+            EnvironmentBlendMode::OPAQUE
+        }
+        XrPreferdBlendMode::Additive if blend_modes.contains(&EnvironmentBlendMode::ADDITIVE) => {
+            bevy::log::info!("Using Additive");
+            EnvironmentBlendMode::ADDITIVE
+        }
+        XrPreferdBlendMode::AlphaBlend
+            if blend_modes.contains(&EnvironmentBlendMode::ALPHA_BLEND) =>
+        {
+            bevy::log::info!("Using AlphaBlend");
+            EnvironmentBlendMode::ALPHA_BLEND
+        }
+        _ => {
+            bevy::log::info!("Using Opaque");
+            EnvironmentBlendMode::OPAQUE
+        }
+    };
+
+    // the runtime has to be asked for the requirements before a session can be created
+    let reqs = xr_instance.graphics_requirements::<xr::OpenGlEs>(xr_system_id)?;
+
+    // wgpu creates the EGL display and context, they are handed to the runtime with the session
+    let wgpu_instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::GL,
+        flags: wgpu::InstanceFlags::from_build_config(),
+        ..Default::default()
+    });
+    let wgpu_adapter = wgpu_instance
+        .enumerate_adapters(wgpu::Backends::GL)
+        .into_iter()
+        .next()
+        .context("failed to find a GLES adapter")?;
+
+    let (gl_version, context) = unsafe {
+        wgpu_adapter.as_hal::<Gles, _, _>(|adapter| {
+            adapter.map(|adapter| {
+                let context = adapter.adapter_context();
+                let version = context.lock().version().clone();
+                (
+                    xr::Version::new(version.major as u16, version.minor as u16, 0),
+                    context.raw_context(),
+                )
+            })
+        })
+    }
+    .context("the adapter wasn't created with the GLES backend")?;
+    if gl_version < reqs.min_api_version_supported
+        || gl_version.major() > reqs.max_api_version_supported.major()
+    {
+        eyre::bail!(
+            "OpenXR runtime requires OpenGL ES version >= {}, < {}.0.0, the context has {}",
+            reqs.min_api_version_supported,
+            reqs.max_api_version_supported.major() + 1,
+            gl_version
+        );
+    }
+    let (display, config) = unsafe { wgpu_instance.as_hal::<Gles>() }
+        .map(|instance| {
+            (
+                instance.raw_display().as_ptr(),
+                instance.egl_config().as_ptr(),
+            )
+        })
+        .context("the instance wasn't created with the GLES backend")?;
+    info!("created GLES adapter with OpenGL ES {}", gl_version);
+
+    let (wgpu_features, wgpu_limits) = device_features_and_limits(
+        wgpu_settings,
+        wgpu::Features::empty(),
+        wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+            | wgpu::Features::MULTIVIEW
+            | wgpu::Features::TIMESTAMP_QUERY,
+        wgpu_adapter.limits(),
+        wgpu_adapter.features(),
+        &wgpu_adapter.limits(),
+    )?;
+    let (wgpu_device, wgpu_queue) = futures_lite::future::block_on(wgpu_adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            required_features: wgpu_features,
+            required_limits: wgpu_limits,
+        },
+        None,
+    ))?;
+
+    Ok((
+        xr_instance.into(),
+        OXrSessionSetupInfo::Gles(GlesOXrSessionSetupInfo {
+            display,
+            config,
+            context,
+            xr_system_id,
+        }),
+        blend_mode.into(),
+        wgpu_device.into(),
+        RenderQueue(wgpu_queue.into()),
+        RenderAdapterInfo(wgpu_adapter.get_info()),
+        RenderAdapter(wgpu_adapter.into()),
+        wgpu_instance.into(),
+    ))
+}
+
+pub fn start_xr_session(
+    _window: Option<RawHandleWrapper>,
+    ptrs: &OXrSessionSetupInfo,
+    xr_instance: &XrInstance,
+    session_config: &XrSessionConfig,
+    render_device: &RenderDevice,
+    _render_adapter: &RenderAdapter,
+    _wgpu_instance: &Instance,
+) -> eyre::Result<(
+    XrSession,
+    XrResolution,
+    XrFormat,
+    XrSessionRunning,
+    XrFrameWaiter,
+    XrSwapchain,
+    XrInput,
+    XrViews,
+    XrFrameState,
+)> {
+    let wgpu_device = render_device.wgpu_device();
+    let context = GlesContext(render_device.clone());
+
+    #[allow(unreachable_patterns)]
+    let setup_info = match ptrs {
+        OXrSessionSetupInfo::Gles(g) => g,
+        _ => eyre::bail!("Wrong Graphics Api"),
+    };
+    let system = xr_instance
+        .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+        .call("xrGetSystem")?;
+    // runtimes create their own resources in the context, so it has to be current
+    // the frames are begun and ended through the session, see `XrFrameHooks`
+    let (session, frame_wait, _frame_stream) =
+        context.with(
+            || match super::overlay_settings(xr_instance, session_config) {
+                Some(overlay) => unsafe {
+                    let binding = xr::sys::GraphicsBindingOpenGLESAndroidKHR {
+                        ty: xr::sys::GraphicsBindingOpenGLESAndroidKHR::TYPE,
+                        next: ptr::null(),
+                        display: setup_info.display,
+                        config: setup_info.config,
+                        context: setup_info.context,
+                    };
+                    create_overlay_session::<xr::OpenGlEs>(
+                        xr_instance,
+                        system,
+                        &binding as *const _ as *const c_void,
+                        &overlay,
+                    )
+                },
+                None => unsafe {
+                    xr_instance.create_session::<xr::OpenGlEs>(
+                        system,
+                        &xr::opengles::SessionCreateInfo::Android {
+                            display: setup_info.display,
+                            config: setup_info.config,
+                            context: setup_info.context,
+                        },
+                    )
+                },
+            },
+        )?;
+
+    let views = xr_instance
+        .enumerate_view_configuration_views(
+            setup_info.xr_system_id,
+            session_config.view_config.view_type(),
+        )
+        .call("xrEnumerateViewConfigurationViews")?;
+    // there is no window surface on Android to take the preferred format from
+    let preferred_formats = match session_config.swapchain_formats.is_empty() {
+        true => vec![wgpu::TextureFormat::Rgba8UnormSrgb],
+        false => session_config.swapchain_formats.clone(),
+    };
+    let supported_formats = enumerate_swapchain_formats(&session)?;
+    let swapchain_format = super::select_swapchain_format(&preferred_formats, &supported_formats)?;
+    info!(
+        "Using swapchain format {:?}, supported formats: {:?}",
+        swapchain_format, supported_formats
+    );
+
+    let resolution = super::swapchain_resolution(
+        xr_instance,
+        setup_info.xr_system_id,
+        &views,
+        session_config.render_scale,
+    )?;
+    let view_count = session_config.view_config.view_count();
+
+    // GLES swapchain images can't be viewed with another format
+    let view_format = super::select_view_format(
+        swapchain_format,
+        session_config.swapchain_view_format,
+        false,
+    );
+    let handle = context.with(|| {
+        session
+            .create_swapchain(&xr::SwapchainCreateInfo {
+                create_flags: xr::SwapchainCreateFlags::EMPTY,
+                usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT
+                    | xr::SwapchainUsageFlags::SAMPLED
+                    | xr::SwapchainUsageFlags::TRANSFER_SRC,
+                format: wgpu_to_gles(swapchain_format).context("unsupported swapchain format")?,
+                // bevy renders into its own multisampled texture and resolves into the swapchain
+                // image, see `XrSessionConfig::samples`
+                sample_count: 1,
+                width: resolution.x,
+                height: resolution.y,
+                face_count: 1,
+                array_size: view_count,
+                mip_count: 1,
+            })
+            .map_err(eyre::Report::from)
+    })?;
+
+    let buffers = swapchain_textures(
+        wgpu_device,
+        &handle,
+        swapchain_format,
+        resolution,
+        view_count,
+        // sampled by the desktop mirror and copied from by frame captures
+        wgpu_hal::TextureUses::COLOR_TARGET
+            | wgpu_hal::TextureUses::COPY_DST
+            | wgpu_hal::TextureUses::COPY_SRC
+            | wgpu_hal::TextureUses::RESOURCE,
+        wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::TEXTURE_BINDING,
+    )?;
+
+    let depth =
+        if session_config.depth_layer && xr_instance.exts().khr_composition_layer_depth.is_some() {
+            let depth_format = wgpu::TextureFormat::Depth32Float;
+            let raw_depth_format = wgpu_to_gles(depth_format).unwrap();
+            if session
+                .enumerate_swapchain_formats()?
+                .contains(&raw_depth_format)
+            {
+                let handle = context.with(|| {
+                    session.create_swapchain(&xr::SwapchainCreateInfo {
+                        create_flags: xr::SwapchainCreateFlags::EMPTY,
+                        usage_flags: xr::SwapchainUsageFlags::DEPTH_STENCIL_ATTACHMENT
+                            | xr::SwapchainUsageFlags::TRANSFER_DST,
+                        format: raw_depth_format,
+                        sample_count: 1,
+                        width: resolution.x,
+                        height: resolution.y,
+                        face_count: 1,
+                        array_size: view_count,
+                        mip_count: 1,
+                    })
+                })?;
+                let buffers = swapchain_textures(
+                    wgpu_device,
+                    &handle,
+                    depth_format,
+                    resolution,
+                    view_count,
+                    wgpu_hal::TextureUses::DEPTH_STENCIL_WRITE | wgpu_hal::TextureUses::COPY_DST,
+                    wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+                )?;
+                Some(SwapchainImages::new(handle, buffers))
+            } else {
+                warn!("Runtime doesn't support Depth32Float swapchains, not submitting depth");
+                None
+            }
+        } else {
+            None
+        };
+
+    Ok((
+        XrSession::Gles(session.clone()),
+        resolution.into(),
+        swapchain_format.into(),
+        // TODO: this shouldn't be in here
+        AtomicBool::new(false).into(),
+        frame_wait.into(),
+        Swapchain::Gles(
+            SwapchainInner::new(session.clone(), handle, buffers, view_format, depth),
+            context,
+        )
+        .into(),
+        XrInput::new(xr_instance, &session.into_any_graphics(), session_config)?,
+        Vec::default().into(),
+        // TODO: Feels wrong to return a FrameState here, we probably should just wait for the next frame
+        xr::FrameState {
+            predicted_display_time: xr::Time::from_nanos(1),
+            predicted_display_period: xr::Duration::from_nanos(1),
+            should_render: true,
+        }
+        .into(),
+    ))
+}
+
+fn swapchain_textures(
+    wgpu_device: &wgpu::Device,
+    handle: &xr::Swapchain<xr::OpenGlEs>,
+    format: wgpu::TextureFormat,
+    resolution: UVec2,
+    array_size: u32,
+    hal_usage: wgpu_hal::TextureUses,
+    usage: wgpu::TextureUsages,
+) -> eyre::Result<Vec<wgpu::Texture>> {
+    let images = handle.enumerate_images()?;
+    let size = wgpu::Extent3d {
+        width: resolution.x,
+        height: resolution.y,
+        depth_or_array_layers: array_size,
+    };
+
+    images
+        .into_iter()
+        .map(|image| {
+            info!("image map swapchain");
+            let name = NonZeroU32::new(image).context("runtime returned an invalid texture")?;
+            let wgpu_hal_texture = unsafe {
+                wgpu_device.as_hal::<Gles, _, _>(|device| {
+                    device.map(|device| {
+                        // the runtime owns the textures, with a drop guard wgpu doesn't delete them
+                        device.texture_from_raw(
+                            name,
+                            &wgpu_hal::TextureDescriptor {
+                                label: Some("bevy_openxr swapchain"), // unused internally
+                                size,
+                                mip_level_count: 1,
+                                sample_count: 1,
+                                dimension: wgpu::TextureDimension::D2,
+                                format,
+                                usage: hal_usage,
+                                memory_flags: wgpu_hal::MemoryFlags::empty(),
+                                view_formats: vec![],
+                            },
+                            Some(Box::new(())),
+                        )
+                    })
+                })
+            }
+            .flatten()
+            .context("the render device wasn't created with the GLES backend")?;
+            Ok(unsafe {
+                wgpu_device.create_texture_from_hal::<Gles>(
+                    wgpu_hal_texture,
+                    &wgpu::TextureDescriptor {
+                        label: Some("bevy_openxr swapchain"),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format,
+                        usage,
+                        view_formats: &[],
+                    },
+                )
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn enumerate_swapchain_formats(
+    session: &xr::Session<xr::OpenGlEs>,
+) -> xr::Result<Vec<wgpu::TextureFormat>> {
+    Ok(session
+        .enumerate_swapchain_formats()?
+        .into_iter()
+        .filter_map(|format| super::format_from_raw(format, wgpu_to_gles))
+        .collect())
+}
+
+/// The sized internal format of a swapchain, OpenXR uses the GL enums for GLES swapchains.
+/// sRGB formats have their own enum, `GL_SRGB8_ALPHA8` is decoded when sampled while
+/// `GL_RGBA8` is taken as linear.
+fn wgpu_to_gles(format: wgpu::TextureFormat) -> Option<u32> {
+    use wgpu::TextureFormat as Tf;
+    Some(match format {
+        Tf::Rgba8UnormSrgb => glow::SRGB8_ALPHA8,
+        Tf::Rgba8Unorm => glow::RGBA8,
+        Tf::Rgba8Snorm => glow::RGBA8_SNORM,
+        Tf::Rgb10a2Unorm => glow::RGB10_A2,
+        Tf::Rg11b10Float => glow::R11F_G11F_B10F,
+        Tf::Rgba16Float => glow::RGBA16F,
+        Tf::Rgba32Float => glow::RGBA32F,
+        Tf::R8Unorm => glow::R8,
+        Tf::Rg8Unorm => glow::RG8,
+        Tf::R16Float => glow::R16F,
+        Tf::Rg16Float => glow::RG16F,
+        Tf::R32Float => glow::R32F,
+        Tf::Depth16Unorm => glow::DEPTH_COMPONENT16,
+        Tf::Depth24Plus => glow::DEPTH_COMPONENT24,
+        Tf::Depth24PlusStencil8 => glow::DEPTH24_STENCIL8,
+        Tf::Depth32Float => glow::DEPTH_COMPONENT32F,
+        Tf::Depth32FloatStencil8 => glow::DEPTH32F_STENCIL8,
+        // BGRA and 16 bit normalized formats aren't part of GLES
+        _ => return None,
+    })
+}
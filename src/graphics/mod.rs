@@ -2,22 +2,35 @@ pub mod extensions;
 
 #[cfg(all(feature = "d3d12", windows))]
 mod d3d12;
+#[cfg(all(feature = "gles", target_os = "android"))]
+mod gles;
 #[cfg(feature = "vulkan")]
 mod vulkan;
 
+#[cfg(all(feature = "gles", target_os = "android"))]
+pub(crate) use gles::GlesContext;
+
 use bevy::ecs::query::With;
+use bevy::ecs::system::Resource;
 use bevy::ecs::system::{Query, SystemState};
 use bevy::ecs::world::World;
+use bevy::reflect::Reflect;
+use bevy::render::extract_resource::ExtractResource;
 use bevy::render::renderer::{
     RenderAdapter, RenderAdapterInfo, RenderDevice, RenderInstance, RenderQueue,
 };
+use bevy::render::settings::{WgpuSettings, WgpuSettingsPriority};
 use bevy::window::{PrimaryWindow, RawHandleWrapper};
 use wgpu::Instance;
 
+use crate::display_color_space::XrDisplayColorSpace;
+use crate::foveation::XrFoveationSettings;
 use crate::input::XrInput;
+use crate::layers::{LayerSwapchain, XrLayerAlpha};
+use crate::overlay::{overlay_supported, XrOverlaySettings};
 use crate::resources::{
-    XrEnvironmentBlendMode, XrFormat, XrFrameState, XrFrameWaiter, XrInstance, XrResolution,
-    XrSession, XrSessionRunning, XrSwapchain, XrViews,
+    Swapchain, XrEnvironmentBlendMode, XrFormat, XrFrameState, XrFrameWaiter, XrInstance,
+    XrResolution, XrSession, XrSessionRunning, XrSwapchain, XrViews,
 };
 use crate::OXrSessionSetupInfo;
 
@@ -51,10 +64,217 @@ impl Default for XrAppInfo {
     }
 }
 
+/// OpenXR API layers to load with the instance, in addition to the ones enabled
+/// through the environment
+#[derive(Clone, Debug, Default)]
+pub struct XrApiLayers {
+    /// Names of the layers, creating the instance fails if one of them isn't installed
+    pub layers: Vec<String>,
+    /// Enables [`CORE_VALIDATION_API_LAYER`] in debug builds, if it's installed
+    pub debug: bool,
+}
+
+/// Checks that the app uses the OpenXR api correctly and logs what it does wrong
+pub const CORE_VALIDATION_API_LAYER: &str = "XR_APILAYER_LUNARG_core_validation";
+
+/// The API layers installed on this machine, useful for support requests
+pub fn enumerate_api_layers() -> eyre::Result<Vec<xr::ApiLayerProperties>> {
+    let xr_entry = xr_entry()?;
+    #[cfg(target_os = "android")]
+    xr_entry.initialize_android_loader()?;
+    Ok(xr_entry.enumerate_layers()?)
+}
+
+/// The names of the layers the instance is created with
+fn enabled_api_layers(xr_entry: &xr::Entry, api_layers: &XrApiLayers) -> eyre::Result<Vec<String>> {
+    let available = xr_entry.enumerate_layers()?;
+    let is_available = |name: &str| available.iter().any(|layer| layer.layer_name == name);
+    let mut enabled = Vec::new();
+    for name in &api_layers.layers {
+        if !is_available(name) {
+            eyre::bail!(
+                "The OpenXR API layer {} is not installed, available layers: {:?}",
+                name,
+                available
+                    .iter()
+                    .map(|layer| &layer.layer_name)
+                    .collect::<Vec<_>>()
+            );
+        }
+        enabled.push(name.clone());
+    }
+    if api_layers.debug
+        && cfg!(debug_assertions)
+        && !enabled.iter().any(|name| name == CORE_VALIDATION_API_LAYER)
+    {
+        match is_available(CORE_VALIDATION_API_LAYER) {
+            true => enabled.push(CORE_VALIDATION_API_LAYER.into()),
+            false => bevy::log::warn!(
+                "Unable to enable OpenXR validation: {} is not installed",
+                CORE_VALIDATION_API_LAYER
+            ),
+        }
+    }
+    if !enabled.is_empty() {
+        bevy::log::info!("Enabling OpenXR API layers: {:?}", enabled);
+    }
+    Ok(enabled)
+}
+
+/// Options used when creating an OpenXR session.
+/// How many images are rendered each frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum XrViewConfig {
+    /// One image per eye
+    #[default]
+    Stereo,
+    /// A single image from between the eyes that is shown to both of them,
+    /// about halves the rendering cost but loses depth perception.
+    /// Only one xr camera is spawned and [`XrViews`] contains a single view.
+    Mono,
+    /// A wide context view and a narrow, sharper focus view per eye, which the compositor blends
+    /// into each other. [`XrViews`] contains the two context views followed by the two focus views
+    /// and a camera is spawned for each of them, see [`Eye`](crate::xr_input::xr_camera::Eye).
+    /// Enables `XR_VARJO_quad_views` and falls back to [`XrViewConfig::Stereo`] if the runtime
+    /// doesn't support it. All views share one swapchain with the largest recommended resolution.
+    Quad,
+}
+
+impl XrViewConfig {
+    /// The number of images rendered each frame, which is also the number of swapchain array layers
+    pub fn view_count(self) -> u32 {
+        match self {
+            XrViewConfig::Stereo => 2,
+            XrViewConfig::Mono => 1,
+            XrViewConfig::Quad => 4,
+        }
+    }
+
+    /// The view configuration the session is begun with, mono combines the views of a stereo one
+    pub fn view_type(self) -> xr::ViewConfigurationType {
+        match self {
+            XrViewConfig::Stereo | XrViewConfig::Mono => xr::ViewConfigurationType::PRIMARY_STEREO,
+            XrViewConfig::Quad => xr::ViewConfigurationType::PRIMARY_QUAD_VARJO,
+        }
+    }
+
+    /// Falls back to stereo if the runtime doesn't support the view configuration
+    pub(crate) fn resolve(self, instance: &XrInstance) -> Self {
+        if self != XrViewConfig::Quad {
+            return self;
+        }
+        let supported = instance.exts().varjo_quad_views.is_some()
+            && instance
+                .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+                .and_then(|system| instance.enumerate_view_configurations(system))
+                .is_ok_and(|supported| supported.contains(&self.view_type()));
+        if supported {
+            return self;
+        }
+        bevy::log::warn!("The runtime doesn't support quad views, rendering in stereo instead");
+        XrViewConfig::Stereo
+    }
+}
+
+#[derive(Clone, Debug, Resource, ExtractResource)]
+pub struct XrSessionConfig {
+    /// The reference space that views and tracked poses are located in.
+    /// Falls back to LOCAL_FLOOR, STAGE and finally LOCAL if the runtime doesn't support it,
+    /// the space that was actually used is stored in [`XrInput::stage_type`].
+    /// LOCAL_FLOOR itself is emulated with LOCAL and the floor height of STAGE.
+    /// UNBOUNDED_MSFT enables `XR_MSFT_unbounded_reference_space` and falls back to LOCAL.
+    pub reference_space: xr::ReferenceSpaceType,
+    /// How far below the headset the floor is assumed to be when LOCAL_FLOOR is emulated on a
+    /// runtime without a STAGE space, see [`XrInput::emulated_floor`]
+    pub default_eye_height: f32,
+    /// Submit the depth buffer alongside the color images so the runtime can use it for reprojection.
+    /// Needs `XR_KHR_composition_layer_depth` to be enabled, see [`XrExtensions::enable_depth_layer`].
+    pub depth_layer: bool,
+    /// Foveated rendering applied to the main swapchain once the session started,
+    /// skipped with a warning if the runtime doesn't support it.
+    /// Needs the foveation extensions, see [`XrExtensions::enable_foveation`].
+    pub foveation: Option<XrFoveationSettings>,
+    /// MSAA sample count the xr cameras render with, bevy renders into its own multisampled
+    /// textures and resolves them into the swapchain images. `None` uses the
+    /// [`Msaa`](bevy::render::view::Msaa) resource of the app.
+    /// Lowered to the highest sample count the runtime and the gpu support, the `Msaa` resource
+    /// is set to the value actually used. Changing `Msaa` while the session runs reallocates the
+    /// textures, unsupported sample counts are lowered the same way.
+    pub samples: Option<u32>,
+    /// Swapchain formats in order of preference, the first one the runtime supports is used.
+    /// When empty the format of the window surface is preferred, then `Rgba8UnormSrgb`.
+    /// Falls back to the format the runtime prefers, the chosen format is stored in [`XrFormat`].
+    pub swapchain_formats: Vec<wgpu::TextureFormat>,
+    /// Format the xr cameras render into the swapchain with, has to be the sRGB or non sRGB
+    /// variant of the swapchain format. The compositor interprets the images according to the
+    /// swapchain format, so this is only needed for runtimes that don't follow the spec.
+    /// Needs `XR_KHR_vulkan_swapchain_format_list` on Vulkan and isn't supported on D3D12 and GLES.
+    /// `None` renders with the swapchain format, see [`XrColorSpace`](crate::resources::XrColorSpace).
+    pub swapchain_view_format: Option<wgpu::TextureFormat>,
+    /// Renders the extra view the runtime asks for during mixed reality capture with another camera.
+    /// Needs `XR_MSFT_secondary_view_configuration` and `XR_MSFT_first_person_observer`,
+    /// see [`XrExtensions::enable_first_person_observer`].
+    pub first_person_observer: bool,
+    /// How many views are rendered each frame, set to the configuration that was actually used
+    /// once the session started
+    pub view_config: XrViewConfig,
+    /// Create the session as an overlay on top of another app,
+    /// needs `XR_EXTX_overlay`, see [`XrExtensions::enable_overlay`].
+    pub overlay: Option<XrOverlaySettings>,
+    /// The color space the compositor assumes the images are in, set once the session started.
+    /// Needs `XR_FB_color_space`, see [`XrExtensions::enable_display_color_space`].
+    /// `None` keeps the default of the runtime.
+    pub display_color_space: Option<XrDisplayColorSpace>,
+    /// Scales the recommended per eye resolution the swapchain is created with, above 1.0
+    /// supersamples for clarity, below 1.0 saves gpu time. Clamped to the largest swapchain
+    /// the runtime supports, the resolution actually used is stored in [`XrResolution`].
+    /// Read when a session starts, restart the session for a new scale to take effect.
+    pub render_scale: f32,
+    /// How the main projection layer is blended with passthrough and the composition layers
+    /// behind it. `None` blends with premultiplied alpha while passthrough, an overlay session
+    /// or a skybox is active and submits it opaque otherwise.
+    pub projection_alpha: Option<XrLayerAlpha>,
+}
+impl Default for XrSessionConfig {
+    fn default() -> Self {
+        Self {
+            reference_space: xr::ReferenceSpaceType::LOCAL_FLOOR_EXT,
+            default_eye_height: 1.6,
+            depth_layer: false,
+            foveation: None,
+            samples: None,
+            swapchain_formats: Vec::new(),
+            swapchain_view_format: None,
+            first_person_observer: false,
+            view_config: XrViewConfig::Stereo,
+            overlay: None,
+            display_color_space: None,
+            render_scale: 1.0,
+            projection_alpha: None,
+        }
+    }
+}
+
+/// The overlay settings to create the session with, `None` for a regular session
+fn overlay_settings(
+    xr_instance: &XrInstance,
+    session_config: &XrSessionConfig,
+) -> Option<XrOverlaySettings> {
+    let overlay = session_config.overlay?;
+    if !overlay_supported(xr_instance) {
+        bevy::log::warn!(
+            "XR_EXTX_overlay is not supported, creating a regular session instead of an overlay"
+        );
+        return None;
+    }
+    Some(overlay)
+}
+
 pub fn start_xr_session(
     window: Option<RawHandleWrapper>,
     session_setup_data: &OXrSessionSetupInfo,
     xr_instance: &XrInstance,
+    session_config: &XrSessionConfig,
     render_device: &RenderDevice,
     render_adapter: &RenderAdapter,
     wgpu_instance: &Instance,
@@ -75,6 +295,7 @@ pub fn start_xr_session(
             window,
             session_setup_data,
             xr_instance,
+            session_config,
             render_device,
             render_adapter,
             wgpu_instance,
@@ -84,18 +305,371 @@ pub fn start_xr_session(
             window,
             session_setup_data,
             xr_instance,
+            session_config,
+            render_device,
+            render_adapter,
+            wgpu_instance,
+        ),
+        #[cfg(all(feature = "gles", target_os = "android"))]
+        OXrSessionSetupInfo::Gles(_) => gles::start_xr_session(
+            window,
+            session_setup_data,
+            xr_instance,
+            session_config,
             render_device,
             render_adapter,
             wgpu_instance,
         ),
     }
 }
+pub(crate) fn create_layer_swapchain(
+    session: &XrSession,
+    render_device: &RenderDevice,
+    format: wgpu::TextureFormat,
+    resolution: bevy::math::UVec2,
+) -> eyre::Result<LayerSwapchain> {
+    let wgpu_device = render_device.wgpu_device();
+    Ok(match session {
+        #[cfg(feature = "vulkan")]
+        XrSession::Vulkan(session) => LayerSwapchain::Vulkan(vulkan::create_layer_swapchain(
+            session,
+            wgpu_device,
+            format,
+            resolution,
+        )?),
+        #[cfg(all(feature = "d3d12", windows))]
+        XrSession::D3D12(session) => LayerSwapchain::D3D12(d3d12::create_layer_swapchain(
+            session,
+            wgpu_device,
+            format,
+            resolution,
+        )?),
+        // the images would be upside down, the projection layer is flipped through its fov
+        #[cfg(all(feature = "gles", target_os = "android"))]
+        XrSession::Gles(_) => {
+            eyre::bail!("Additional swapchains aren't supported with the GLES backend")
+        }
+    })
+}
+
+/// A new swapchain for the views with the format, view format and depth of `swapchain`, but
+/// another resolution
+pub(crate) fn resize_swapchain(
+    xr_instance: &XrInstance,
+    session: &XrSession,
+    swapchain: &Swapchain,
+    render_device: &RenderDevice,
+    format: wgpu::TextureFormat,
+    resolution: bevy::math::UVec2,
+    view_count: u32,
+) -> eyre::Result<Swapchain> {
+    let wgpu_device = render_device.wgpu_device();
+    #[allow(unreachable_patterns)]
+    let swapchain = match (session, swapchain) {
+        #[cfg(feature = "vulkan")]
+        (XrSession::Vulkan(session), Swapchain::Vulkan(swapchain)) => {
+            Swapchain::Vulkan(vulkan::create_view_swapchain(
+                xr_instance,
+                session,
+                wgpu_device,
+                format,
+                swapchain.view_format,
+                resolution,
+                view_count,
+                swapchain.depth.is_some(),
+            )?)
+        }
+        #[cfg(all(feature = "d3d12", windows))]
+        (XrSession::D3D12(session), Swapchain::D3D12(swapchain)) => {
+            Swapchain::D3D12(d3d12::create_view_swapchain(
+                xr_instance,
+                session,
+                wgpu_device,
+                format,
+                swapchain.view_format,
+                resolution,
+                view_count,
+                swapchain.depth.is_some(),
+            )?)
+        }
+        #[cfg(all(feature = "gles", target_os = "android"))]
+        (XrSession::Gles(_), _) => {
+            eyre::bail!("Resizing the swapchain isn't supported with the GLES backend")
+        }
+        _ => eyre::bail!("The swapchain doesn't belong to the session"),
+    };
+    Ok(swapchain)
+}
+
+pub(crate) fn create_cube_swapchain(
+    session: &XrSession,
+    render_device: &RenderDevice,
+    format: wgpu::TextureFormat,
+    size: u32,
+) -> eyre::Result<LayerSwapchain> {
+    let wgpu_device = render_device.wgpu_device();
+    Ok(match session {
+        #[cfg(feature = "vulkan")]
+        XrSession::Vulkan(session) => LayerSwapchain::Vulkan(vulkan::create_cube_swapchain(
+            session,
+            wgpu_device,
+            format,
+            size,
+        )?),
+        #[cfg(all(feature = "d3d12", windows))]
+        XrSession::D3D12(session) => LayerSwapchain::D3D12(d3d12::create_cube_swapchain(
+            session,
+            wgpu_device,
+            format,
+            size,
+        )?),
+        #[cfg(all(feature = "gles", target_os = "android"))]
+        XrSession::Gles(_) => {
+            eyre::bail!("Additional swapchains aren't supported with the GLES backend")
+        }
+    })
+}
+
+/// Swapchain formats supported by the runtime that have a wgpu equivalent,
+/// in the order the runtime prefers them
+pub fn enumerate_swapchain_formats(session: &XrSession) -> xr::Result<Vec<wgpu::TextureFormat>> {
+    match session {
+        #[cfg(feature = "vulkan")]
+        XrSession::Vulkan(session) => vulkan::enumerate_swapchain_formats(session),
+        #[cfg(all(feature = "d3d12", windows))]
+        XrSession::D3D12(session) => d3d12::enumerate_swapchain_formats(session),
+        #[cfg(all(feature = "gles", target_os = "android"))]
+        XrSession::Gles(session) => gles::enumerate_swapchain_formats(session),
+    }
+}
+
+/// Color formats a swapchain can be converted back to, the backends only map from wgpu formats
+const SWAPCHAIN_FORMAT_CANDIDATES: [wgpu::TextureFormat; 15] = [
+    wgpu::TextureFormat::Rgba8UnormSrgb,
+    wgpu::TextureFormat::Bgra8UnormSrgb,
+    wgpu::TextureFormat::Rgba8Unorm,
+    wgpu::TextureFormat::Bgra8Unorm,
+    wgpu::TextureFormat::Rgba8Snorm,
+    wgpu::TextureFormat::Rgb10a2Unorm,
+    wgpu::TextureFormat::Rg11b10Float,
+    wgpu::TextureFormat::Rgba16Float,
+    wgpu::TextureFormat::Rgba16Unorm,
+    wgpu::TextureFormat::Rgba32Float,
+    wgpu::TextureFormat::R8Unorm,
+    wgpu::TextureFormat::Rg8Unorm,
+    wgpu::TextureFormat::R16Float,
+    wgpu::TextureFormat::Rg16Float,
+    wgpu::TextureFormat::R32Float,
+];
+
+/// Finds the wgpu format a backend specific format was converted from
+pub(crate) fn format_from_raw<T: PartialEq>(
+    raw: T,
+    to_raw: impl Fn(wgpu::TextureFormat) -> Option<T>,
+) -> Option<wgpu::TextureFormat> {
+    SWAPCHAIN_FORMAT_CANDIDATES
+        .into_iter()
+        .find(|&format| to_raw(format).is_some_and(|f| f == raw))
+}
+
+/// Picks the first preferred format the runtime supports,
+/// or the format the runtime prefers if there is none
+pub(crate) fn select_swapchain_format(
+    preferences: &[wgpu::TextureFormat],
+    supported: &[wgpu::TextureFormat],
+) -> eyre::Result<wgpu::TextureFormat> {
+    if let Some(format) = preferences.iter().find(|f| supported.contains(f)) {
+        return Ok(*format);
+    }
+    let Some(format) = supported.first() else {
+        eyre::bail!("The runtime doesn't support any swapchain format usable with wgpu");
+    };
+    bevy::log::warn!(
+        "None of the preferred swapchain formats {:?} are supported, using {:?}",
+        preferences,
+        format
+    );
+    Ok(*format)
+}
+
+/// The recommended per eye resolution scaled by `render_scale`,
+/// clamped to the maximum size of the views and of swapchain images
+pub(crate) fn swapchain_resolution(
+    xr_instance: &XrInstance,
+    system: xr::SystemId,
+    views: &[xr::ViewConfigurationView],
+    render_scale: f32,
+) -> xr::Result<bevy::math::UVec2> {
+    let graphics_properties = xr_instance.system_properties(system)?.graphics_properties;
+    // all views share one array swapchain, so it fits the largest one of them
+    let view = views
+        .iter()
+        .copied()
+        .reduce(|a, b| xr::ViewConfigurationView {
+            recommended_image_rect_width: a
+                .recommended_image_rect_width
+                .max(b.recommended_image_rect_width),
+            max_image_rect_width: a.max_image_rect_width.min(b.max_image_rect_width),
+            recommended_image_rect_height: a
+                .recommended_image_rect_height
+                .max(b.recommended_image_rect_height),
+            max_image_rect_height: a.max_image_rect_height.min(b.max_image_rect_height),
+            recommended_swapchain_sample_count: a
+                .recommended_swapchain_sample_count
+                .max(b.recommended_swapchain_sample_count),
+            max_swapchain_sample_count: a
+                .max_swapchain_sample_count
+                .min(b.max_swapchain_sample_count),
+        })
+        .ok_or(xr::sys::Result::ERROR_VIEW_CONFIGURATION_TYPE_UNSUPPORTED)?;
+    let scale = |recommended: u32, max_view: u32, max_swapchain: u32| {
+        let max = max_view.min(max_swapchain).max(1);
+        ((recommended as f32 * render_scale).round() as u32).clamp(1, max)
+    };
+    let resolution = bevy::math::uvec2(
+        scale(
+            view.recommended_image_rect_width,
+            view.max_image_rect_width,
+            graphics_properties.max_swapchain_image_width,
+        ),
+        scale(
+            view.recommended_image_rect_height,
+            view.max_image_rect_height,
+            graphics_properties.max_swapchain_image_height,
+        ),
+    );
+    if render_scale != 1.0 {
+        bevy::log::info!(
+            "Using render scale {}, the per eye resolution is {} instead of {}x{}",
+            render_scale,
+            resolution,
+            view.recommended_image_rect_width,
+            view.recommended_image_rect_height
+        );
+    }
+    Ok(resolution)
+}
+
+/// Checks that the requested view format can be used to render into a swapchain of `swapchain_format`
+pub(crate) fn select_view_format(
+    swapchain_format: wgpu::TextureFormat,
+    requested: Option<wgpu::TextureFormat>,
+    supports_view_formats: bool,
+) -> wgpu::TextureFormat {
+    match requested {
+        None => swapchain_format,
+        Some(format) if format == swapchain_format => format,
+        Some(format) if format.remove_srgb_suffix() != swapchain_format.remove_srgb_suffix() => {
+            bevy::log::warn!(
+                "Swapchain view format {:?} isn't compatible with the swapchain format {:?}, using {:?}",
+                format,
+                swapchain_format,
+                swapchain_format
+            );
+            swapchain_format
+        }
+        Some(format) if !supports_view_formats => {
+            bevy::log::warn!(
+                "Unable to render with the swapchain view format {:?}, using {:?}",
+                format,
+                swapchain_format
+            );
+            swapchain_format
+        }
+        Some(format) => format,
+    }
+}
+
+/// Sample counts bevy can render the xr views with, in ascending order
+pub(crate) fn supported_msaa_samples(
+    instance: &XrInstance,
+    render_adapter: &RenderAdapter,
+    format: wgpu::TextureFormat,
+    view_config: XrViewConfig,
+) -> xr::Result<Vec<u32>> {
+    let system = instance.system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)?;
+    let views = instance.enumerate_view_configuration_views(system, view_config.view_type())?;
+    let max_samples = views
+        .iter()
+        .map(|view| view.max_swapchain_sample_count)
+        .min()
+        .unwrap_or(1);
+    let flags = render_adapter.get_texture_format_features(format).flags;
+    // these are the sample counts bevy's `Msaa` can represent
+    Ok([1, 2, 4, 8]
+        .into_iter()
+        .filter(|&samples| {
+            samples == 1 || (samples <= max_samples && flags.sample_count_supported(samples))
+        })
+        .collect())
+}
+
+/// The features and limits the device of a backend is created with. Without `settings` they
+/// are what the XR rendering needs, see [`OpenXrPlugin::wgpu_settings`](crate::OpenXrPlugin::wgpu_settings)
+/// for what gets added otherwise. The runtime picks the adapter, so requested features and
+/// limits it doesn't support are an error instead of picking another adapter.
+pub(crate) fn device_features_and_limits(
+    settings: Option<&WgpuSettings>,
+    required_features: wgpu::Features,
+    optional_features: wgpu::Features,
+    xr_limits: wgpu::Limits,
+    adapter_features: wgpu::Features,
+    adapter_limits: &wgpu::Limits,
+) -> eyre::Result<(wgpu::Features, wgpu::Limits)> {
+    let mut optional_features = optional_features & adapter_features;
+    let Some(settings) = settings else {
+        return Ok((required_features | optional_features, xr_limits));
+    };
+    let missing_features = settings.features - adapter_features;
+    if !missing_features.is_empty() {
+        eyre::bail!(
+            "The adapter the XR runtime picked doesn't support the requested wgpu features: {:?}",
+            missing_features
+        );
+    }
+    if let Some(disabled) = settings.disabled_features {
+        optional_features -= disabled;
+    }
+    let limits = match settings.priority {
+        WgpuSettingsPriority::Functionality => adapter_limits.clone(),
+        _ => wgpu::Limits {
+            max_bind_groups: settings
+                .limits
+                .max_bind_groups
+                .max(xr_limits.max_bind_groups),
+            max_push_constant_size: settings
+                .limits
+                .max_push_constant_size
+                .max(xr_limits.max_push_constant_size),
+            ..settings.limits.clone()
+        },
+    };
+    let mut missing_limits = Vec::new();
+    limits.check_limits_with_fail_fn(adapter_limits, false, |name, requested, allowed| {
+        missing_limits.push(format!("{} {} (supported: {})", name, requested, allowed))
+    });
+    if !missing_limits.is_empty() {
+        eyre::bail!(
+            "The adapter the XR runtime picked doesn't support the requested wgpu limits: {}",
+            missing_limits.join(", ")
+        );
+    }
+    Ok((
+        required_features | optional_features | settings.features,
+        limits,
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn initialize_xr_instance(
     backend_preference: &[Backend],
     window: Option<RawHandleWrapper>,
     reqeusted_extensions: XrExtensions,
+    strict_extensions: bool,
+    api_layers: &XrApiLayers,
     prefered_blend_mode: XrPreferdBlendMode,
     app_info: XrAppInfo,
+    wgpu_settings: Option<&WgpuSettings>,
 ) -> eyre::Result<(
     XrInstance,
     OXrSessionSetupInfo,
@@ -115,12 +689,31 @@ pub fn initialize_xr_instance(
     xr_entry.initialize_android_loader()?;
 
     let available_extensions: XrExtensions = xr_entry.enumerate_extensions()?.into();
+    let missing_extensions = reqeusted_extensions.difference(&available_extensions);
+    if !missing_extensions.is_empty() {
+        if strict_extensions {
+            eyre::bail!(
+                "The OpenXR runtime doesn't support the required extensions: {:?}",
+                missing_extensions
+            );
+        }
+        bevy::log::warn!(
+            "The OpenXR runtime doesn't support the requested extensions: {:?}",
+            missing_extensions
+        );
+    }
+    let api_layers = enabled_api_layers(&xr_entry, api_layers)?;
+    let api_layers: Vec<_> = api_layers.iter().map(String::as_str).collect();
 
     for backend in backend_preference {
         match backend {
             #[cfg(feature = "vulkan")]
             Backend::Vulkan => {
                 if !available_extensions.raw().khr_vulkan_enable2 {
+                    bevy::log::info!(
+                        "Skipping the {:?} backend: the runtime doesn't support XR_KHR_vulkan_enable2",
+                        backend
+                    );
                     continue;
                 }
                 return vulkan::initialize_xr_instance(
@@ -128,13 +721,19 @@ pub fn initialize_xr_instance(
                     xr_entry,
                     reqeusted_extensions,
                     available_extensions,
+                    &api_layers,
                     prefered_blend_mode,
                     app_info,
+                    wgpu_settings,
                 );
             }
             #[cfg(all(feature = "d3d12", windows))]
             Backend::D3D12 => {
                 if !available_extensions.raw().khr_d3d12_enable {
+                    bevy::log::info!(
+                        "Skipping the {:?} backend: the runtime doesn't support XR_KHR_D3D12_enable",
+                        backend
+                    );
                     continue;
                 }
                 return d3d12::initialize_xr_instance(
@@ -142,8 +741,30 @@ pub fn initialize_xr_instance(
                     xr_entry,
                     reqeusted_extensions,
                     available_extensions,
+                    &api_layers,
+                    prefered_blend_mode,
+                    app_info,
+                    wgpu_settings,
+                );
+            }
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Backend::Gles => {
+                if !available_extensions.raw().khr_opengl_es_enable {
+                    bevy::log::info!(
+                        "Skipping the {:?} backend: the runtime doesn't support XR_KHR_opengl_es_enable",
+                        backend
+                    );
+                    continue;
+                }
+                return gles::initialize_xr_instance(
+                    window,
+                    xr_entry,
+                    reqeusted_extensions,
+                    available_extensions,
+                    &api_layers,
                     prefered_blend_mode,
                     app_info,
+                    wgpu_settings,
                 );
             }
         }
@@ -154,12 +775,17 @@ pub fn initialize_xr_instance(
     );
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn try_full_init(
     world: &mut World,
     backend_preference: &[Backend],
     reqeusted_extensions: XrExtensions,
+    strict_extensions: bool,
+    api_layers: &XrApiLayers,
     prefered_blend_mode: XrPreferdBlendMode,
     app_info: XrAppInfo,
+    session_config: XrSessionConfig,
+    wgpu_settings: Option<&WgpuSettings>,
 ) -> eyre::Result<(
     RenderDevice,
     RenderQueue,
@@ -183,10 +809,14 @@ pub fn try_full_init(
         backend_preference,
         primary_window.clone(),
         reqeusted_extensions,
+        strict_extensions,
+        api_layers,
         prefered_blend_mode,
         app_info,
+        wgpu_settings,
     )?;
     world.insert_resource(xr_instance);
+    world.insert_resource(setup_info.backend());
     world.insert_non_send_resource(setup_info);
     // TODO: move BlendMode the session init?
     world.insert_resource(blend_mode);
@@ -209,6 +839,7 @@ pub fn try_full_init(
         primary_window,
         setup_info,
         xr_instance,
+        &session_config,
         &render_device,
         &render_adapter,
         &wgpu_instance,
@@ -1,12 +1,13 @@
 use std::ffi::{c_void, CString};
+use std::ptr;
 use std::sync::atomic::AtomicBool;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 // use anyhow::Context;
 use ash::vk::{self, Handle};
-use bevy::math::uvec2;
 use bevy::prelude::*;
 use bevy::render::renderer::{RenderAdapter, RenderAdapterInfo, RenderDevice, RenderQueue};
+use bevy::render::settings::WgpuSettings;
 use bevy::window::RawHandleWrapper;
 use eyre::{Context, ContextCompat};
 use openxr as xr;
@@ -14,25 +15,30 @@ use wgpu::Instance;
 use wgpu_hal::{api::Vulkan as V, Api};
 use xr::EnvironmentBlendMode;
 
+use crate::error::XrResultExt;
 use crate::graphics::extensions::XrExtensions;
 use crate::input::XrInput;
+use crate::overlay::create_overlay_session;
 
 use crate::resources::{
-    OXrSessionSetupInfo, Swapchain, SwapchainInner, VulkanOXrSessionSetupInfo,
+    OXrSessionSetupInfo, Swapchain, SwapchainImages, SwapchainInner, VulkanOXrSessionSetupInfo,
     XrEnvironmentBlendMode, XrFormat, XrFrameState, XrFrameWaiter, XrInstance, XrResolution,
     XrSession, XrSessionRunning, XrSwapchain, XrViews,
 };
 use crate::VIEW_TYPE;
 
-use super::{XrAppInfo, XrPreferdBlendMode};
+use super::{device_features_and_limits, XrAppInfo, XrPreferdBlendMode, XrSessionConfig};
 
+#[allow(clippy::too_many_arguments)]
 pub fn initialize_xr_instance(
     window: Option<RawHandleWrapper>,
     xr_entry: xr::Entry,
     reqeusted_extensions: XrExtensions,
     available_extensions: XrExtensions,
+    api_layers: &[&str],
     prefered_blend_mode: XrPreferdBlendMode,
     app_info: XrAppInfo,
+    wgpu_settings: Option<&WgpuSettings>,
 ) -> eyre::Result<(
     XrInstance,
     OXrSessionSetupInfo,
@@ -57,21 +63,22 @@ pub fn initialize_xr_instance(
         enabled_extensions.khr_android_create_instance = true;
     }
 
-    let available_layers = xr_entry.enumerate_layers()?;
-    // info!("available OpenXR layers: {:#?}", available_layers);
-
-    let xr_instance = xr_entry.create_instance(
-        &xr::ApplicationInfo {
-            application_name: &app_info.name,
-            engine_name: "Bevy",
-            ..Default::default()
-        },
-        &enabled_extensions,
-        &[],
-    )?;
+    let xr_instance = xr_entry
+        .create_instance(
+            &xr::ApplicationInfo {
+                application_name: &app_info.name,
+                engine_name: "Bevy",
+                ..Default::default()
+            },
+            &enabled_extensions,
+            api_layers,
+        )
+        .call("xrCreateInstance")?;
     info!("created OpenXR instance");
-    let instance_props = xr_instance.properties()?;
-    let xr_system_id = xr_instance.system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)?;
+    let instance_props = xr_instance.properties().call("xrGetInstanceProperties")?;
+    let xr_system_id = xr_instance
+        .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+        .call("xrGetSystem")?;
     info!("created OpenXR system");
     let system_props = xr_instance.system_properties(xr_system_id).unwrap();
     info!(
@@ -203,15 +210,31 @@ pub fn initialize_xr_instance(
         )?
     };
 
-    let wgpu_features = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
-        | wgpu::Features::MULTIVIEW
-        | wgpu::Features::MULTI_DRAW_INDIRECT_COUNT
-        | wgpu::Features::MULTI_DRAW_INDIRECT;
-
     let wgpu_exposed_adapter = wgpu_vk_instance
         .expose_adapter(vk_physical_device)
         .context("failed to expose adapter")?;
 
+    // timestamps are only used by the XrGpuTimingPlugin, so they're optional
+    let (wgpu_features, wgpu_limits) = device_features_and_limits(
+        wgpu_settings,
+        wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+            | wgpu::Features::MULTIVIEW
+            | wgpu::Features::MULTI_DRAW_INDIRECT_COUNT
+            | wgpu::Features::MULTI_DRAW_INDIRECT,
+        wgpu::Features::TIMESTAMP_QUERY,
+        wgpu::Limits {
+            max_bind_groups: 8,
+            max_storage_buffer_binding_size: wgpu_exposed_adapter
+                .capabilities
+                .limits
+                .max_storage_buffer_binding_size,
+            max_push_constant_size: 4,
+            ..Default::default()
+        },
+        wgpu_exposed_adapter.features,
+        &wgpu_exposed_adapter.capabilities.limits,
+    )?;
+
     let enabled_extensions = wgpu_exposed_adapter
         .adapter
         .required_device_extensions(wgpu_features);
@@ -281,14 +304,7 @@ pub fn initialize_xr_instance(
             &wgpu::DeviceDescriptor {
                 label: None,
                 required_features: wgpu_features,
-                required_limits: wgpu::Limits {
-                    max_bind_groups: 8,
-                    max_storage_buffer_binding_size: wgpu_adapter
-                        .limits()
-                        .max_storage_buffer_binding_size,
-                    max_push_constant_size: 4,
-                    ..Default::default()
-                },
+                required_limits: wgpu_limits,
             },
             None,
         )
@@ -315,6 +331,7 @@ pub fn start_xr_session(
     window: Option<RawHandleWrapper>,
     ptrs: &OXrSessionSetupInfo,
     xr_instance: &XrInstance,
+    session_config: &XrSessionConfig,
     render_device: &RenderDevice,
     render_adapter: &RenderAdapter,
     wgpu_instance: &Instance,
@@ -337,21 +354,50 @@ pub fn start_xr_session(
         OXrSessionSetupInfo::Vulkan(v) => v,
         _ => eyre::bail!("Wrong Graphics Api"),
     };
-    let (session, frame_wait, frame_stream) = unsafe {
-        xr_instance.create_session::<xr::Vulkan>(
-            xr_instance.system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)?,
-            &xr::vulkan::SessionCreateInfo {
-                instance: setup_info.vk_instance_ptr,
-                physical_device: setup_info.physical_device_ptr,
-                device: setup_info.device_ptr,
-                queue_family_index: setup_info.queue_family_index,
-                queue_index: 0,
+    let system = xr_instance
+        .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+        .call("xrGetSystem")?;
+    // the frames are begun and ended through the session, see `XrFrameHooks`
+    let (session, frame_wait, _frame_stream) =
+        match super::overlay_settings(xr_instance, session_config) {
+            Some(overlay) => unsafe {
+                let binding = xr::sys::GraphicsBindingVulkanKHR {
+                    ty: xr::sys::GraphicsBindingVulkanKHR::TYPE,
+                    next: ptr::null(),
+                    instance: setup_info.vk_instance_ptr,
+                    physical_device: setup_info.physical_device_ptr,
+                    device: setup_info.device_ptr,
+                    queue_family_index: setup_info.queue_family_index,
+                    queue_index: 0,
+                };
+                create_overlay_session::<xr::Vulkan>(
+                    xr_instance,
+                    system,
+                    &binding as *const _ as *const c_void,
+                    &overlay,
+                )
             },
-        )
-    }?;
+            None => unsafe {
+                xr_instance.create_session::<xr::Vulkan>(
+                    system,
+                    &xr::vulkan::SessionCreateInfo {
+                        instance: setup_info.vk_instance_ptr,
+                        physical_device: setup_info.physical_device_ptr,
+                        device: setup_info.device_ptr,
+                        queue_family_index: setup_info.queue_family_index,
+                        queue_index: 0,
+                    },
+                )
+            },
+        }
+        .call("xrCreateSession")?;
 
-    let views =
-        xr_instance.enumerate_view_configuration_views(setup_info.xr_system_id, VIEW_TYPE)?;
+    let views = xr_instance
+        .enumerate_view_configuration_views(
+            setup_info.xr_system_id,
+            session_config.view_config.view_type(),
+        )
+        .call("xrEnumerateViewConfigurationViews")?;
     let surface = window.map(|wrapper| unsafe {
         // SAFETY: Plugins should be set up on the main thread.
         let handle = wrapper.get_handle();
@@ -359,66 +405,319 @@ pub fn start_xr_session(
             .create_surface(handle)
             .expect("Failed to create wgpu surface")
     });
-    let swapchain_format = surface
-        .as_ref()
-        .map(|surface| surface.get_capabilities(wgpu_adapter).formats[0])
-        .unwrap_or(wgpu::TextureFormat::Rgba8UnormSrgb);
-
-    // TODO: Log swapchain format
+    let preferred_formats = match session_config.swapchain_formats.is_empty() {
+        true => surface
+            .as_ref()
+            .map(|surface| surface.get_capabilities(wgpu_adapter).formats[0])
+            .into_iter()
+            .chain([wgpu::TextureFormat::Rgba8UnormSrgb])
+            .collect(),
+        false => session_config.swapchain_formats.clone(),
+    };
+    let supported_formats = enumerate_swapchain_formats(&session)?;
+    let swapchain_format = super::select_swapchain_format(&preferred_formats, &supported_formats)?;
+    info!(
+        "Using swapchain format {:?}, supported formats: {:?}",
+        swapchain_format, supported_formats
+    );
 
-    let resolution = uvec2(
-        views[0].recommended_image_rect_width,
-        views[0].recommended_image_rect_height,
+    let resolution = super::swapchain_resolution(
+        xr_instance,
+        setup_info.xr_system_id,
+        &views,
+        session_config.render_scale,
+    )?;
+    let view_count = session_config.view_config.view_count();
+
+    let view_format = super::select_view_format(
+        swapchain_format,
+        session_config.swapchain_view_format,
+        xr_instance
+            .exts()
+            .khr_vulkan_swapchain_format_list
+            .is_some(),
     );
+    let swapchain = create_view_swapchain(
+        xr_instance,
+        &session,
+        wgpu_device,
+        swapchain_format,
+        view_format,
+        resolution,
+        view_count,
+        session_config.depth_layer,
+    )?;
 
-    let handle = session
-        .create_swapchain(&xr::SwapchainCreateInfo {
-            create_flags: xr::SwapchainCreateFlags::EMPTY,
-            usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT
-                | xr::SwapchainUsageFlags::SAMPLED,
-            format: wgpu_to_vulkan(swapchain_format).as_raw() as _,
-            // The Vulkan graphics pipeline we create is not set up for multisampling,
-            // so we hardcode this to 1. If we used a proper multisampling setup, we
-            // could set this to `views[0].recommended_swapchain_sample_count`.
-            sample_count: 1,
-            width: resolution.x,
-            height: resolution.y,
-            face_count: 1,
-            array_size: 2,
-            mip_count: 1,
-        })
-        .unwrap();
+    Ok((
+        XrSession::Vulkan(session.clone()),
+        resolution.into(),
+        swapchain_format.into(),
+        // TODO: this shouldn't be in here
+        AtomicBool::new(false).into(),
+        frame_wait.into(),
+        Swapchain::Vulkan(swapchain).into(),
+        XrInput::new(xr_instance, &session.into_any_graphics(), session_config)?,
+        Vec::default().into(),
+        // TODO: Feels wrong to return a FrameState here, we probably should just wait for the next frame
+        xr::FrameState {
+            predicted_display_time: xr::Time::from_nanos(1),
+            predicted_display_period: xr::Duration::from_nanos(1),
+            should_render: true,
+        }
+        .into(),
+    ))
+}
+
+/// Creates the swapchain the views render into and the depth swapchain submitted with it, when
+/// the session starts and when it's resized
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_view_swapchain(
+    xr_instance: &XrInstance,
+    session: &xr::Session<xr::Vulkan>,
+    wgpu_device: &wgpu::Device,
+    swapchain_format: wgpu::TextureFormat,
+    view_format: wgpu::TextureFormat,
+    resolution: UVec2,
+    view_count: u32,
+    depth_layer: bool,
+) -> eyre::Result<SwapchainInner<xr::Vulkan>> {
+    let swapchain_info = xr::SwapchainCreateInfo {
+        create_flags: xr::SwapchainCreateFlags::EMPTY,
+        usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT
+            | xr::SwapchainUsageFlags::SAMPLED
+            | xr::SwapchainUsageFlags::TRANSFER_SRC,
+        format: wgpu_to_vulkan(swapchain_format).as_raw() as _,
+        // bevy renders into its own multisampled texture and resolves into the swapchain
+        // image, see `XrSessionConfig::samples`
+        sample_count: 1,
+        width: resolution.x,
+        height: resolution.y,
+        face_count: 1,
+        array_size: view_count,
+        mip_count: 1,
+    };
+    let (handle, view_formats) = match view_format == swapchain_format {
+        true => (
+            session
+                .create_swapchain(&swapchain_info)
+                .call("xrCreateSwapchain")?,
+            vec![],
+        ),
+        false => (
+            create_swapchain_with_view_formats(session, &swapchain_info, &[view_format])?,
+            vec![view_format],
+        ),
+    };
+
+    let buffers = swapchain_textures(
+        wgpu_device,
+        &handle,
+        swapchain_format,
+        &view_formats,
+        resolution,
+        view_count,
+        // sampled by the desktop mirror and copied from by frame captures
+        wgpu_hal::TextureUses::COLOR_TARGET
+            | wgpu_hal::TextureUses::COPY_DST
+            | wgpu_hal::TextureUses::COPY_SRC
+            | wgpu_hal::TextureUses::RESOURCE,
+        wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::TEXTURE_BINDING,
+    )?;
+
+    let depth = if depth_layer && xr_instance.exts().khr_composition_layer_depth.is_some() {
+        let depth_format = wgpu::TextureFormat::Depth32Float;
+        if session
+            .enumerate_swapchain_formats()?
+            .contains(&(wgpu_to_vulkan(depth_format).as_raw() as _))
+        {
+            let handle = session.create_swapchain(&xr::SwapchainCreateInfo {
+                create_flags: xr::SwapchainCreateFlags::EMPTY,
+                usage_flags: xr::SwapchainUsageFlags::DEPTH_STENCIL_ATTACHMENT
+                    | xr::SwapchainUsageFlags::TRANSFER_DST,
+                format: wgpu_to_vulkan(depth_format).as_raw() as _,
+                sample_count: 1,
+                width: resolution.x,
+                height: resolution.y,
+                face_count: 1,
+                array_size: view_count,
+                mip_count: 1,
+            })?;
+            let buffers = swapchain_textures(
+                wgpu_device,
+                &handle,
+                depth_format,
+                &[],
+                resolution,
+                view_count,
+                wgpu_hal::TextureUses::DEPTH_STENCIL_WRITE | wgpu_hal::TextureUses::COPY_DST,
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+            )?;
+            Some(SwapchainImages::new(handle, buffers))
+        } else {
+            warn!("Runtime doesn't support Depth32Float swapchains, not submitting depth");
+            None
+        }
+    } else {
+        None
+    };
+
+    Ok(SwapchainInner::new(
+        session.clone(),
+        handle,
+        buffers,
+        view_format,
+        depth,
+    ))
+}
 
-    let images = handle.enumerate_images().unwrap();
+/// Creates a single layer color swapchain, used for composition layers
+pub(crate) fn create_layer_swapchain(
+    session: &xr::Session<xr::Vulkan>,
+    wgpu_device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    resolution: UVec2,
+) -> xr::Result<SwapchainImages<xr::Vulkan>> {
+    let handle = session.create_swapchain(&xr::SwapchainCreateInfo {
+        create_flags: xr::SwapchainCreateFlags::EMPTY,
+        usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT | xr::SwapchainUsageFlags::SAMPLED,
+        format: wgpu_to_vulkan(format).as_raw() as _,
+        sample_count: 1,
+        width: resolution.x,
+        height: resolution.y,
+        face_count: 1,
+        array_size: 1,
+        mip_count: 1,
+    })?;
+    let buffers = swapchain_textures(
+        wgpu_device,
+        &handle,
+        format,
+        &[],
+        resolution,
+        1,
+        wgpu_hal::TextureUses::COLOR_TARGET | wgpu_hal::TextureUses::COPY_DST,
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+    )?;
+    Ok(SwapchainImages::new(handle, buffers))
+}
+
+/// A static swapchain with the six faces of a cube, written once by copying into it
+pub(crate) fn create_cube_swapchain(
+    session: &xr::Session<xr::Vulkan>,
+    wgpu_device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    size: u32,
+) -> xr::Result<SwapchainImages<xr::Vulkan>> {
+    let handle = session.create_swapchain(&xr::SwapchainCreateInfo {
+        create_flags: xr::SwapchainCreateFlags::STATIC_IMAGE,
+        usage_flags: xr::SwapchainUsageFlags::TRANSFER_DST | xr::SwapchainUsageFlags::SAMPLED,
+        format: wgpu_to_vulkan(format).as_raw() as _,
+        sample_count: 1,
+        width: size,
+        height: size,
+        face_count: 6,
+        array_size: 1,
+        mip_count: 1,
+    })?;
+    let buffers = swapchain_textures(
+        wgpu_device,
+        &handle,
+        format,
+        &[],
+        UVec2::splat(size),
+        6,
+        wgpu_hal::TextureUses::COPY_DST,
+        wgpu::TextureUsages::COPY_DST,
+    )?;
+    Ok(SwapchainImages::new(handle, buffers))
+}
+
+/// Creates a swapchain that can be viewed with `view_formats` in addition to the format it was
+/// created with, needs `XR_KHR_vulkan_swapchain_format_list`
+fn create_swapchain_with_view_formats(
+    session: &xr::Session<xr::Vulkan>,
+    info: &xr::SwapchainCreateInfo<xr::Vulkan>,
+    view_formats: &[wgpu::TextureFormat],
+) -> xr::Result<xr::Swapchain<xr::Vulkan>> {
+    let mut formats: Vec<u32> = vec![info.format];
+    formats.extend(
+        view_formats
+            .iter()
+            .map(|&format| wgpu_to_vulkan(format).as_raw() as u32),
+    );
+    let format_list = xr::sys::VulkanSwapchainFormatListCreateInfoKHR {
+        ty: xr::sys::VulkanSwapchainFormatListCreateInfoKHR::TYPE,
+        next: ptr::null(),
+        view_format_count: formats.len() as u32,
+        view_formats: formats.as_ptr(),
+    };
+    let raw_info = xr::sys::SwapchainCreateInfo {
+        ty: xr::sys::SwapchainCreateInfo::TYPE,
+        next: &format_list as *const _ as *const c_void,
+        create_flags: info.create_flags,
+        usage_flags: info.usage_flags | xr::SwapchainUsageFlags::MUTABLE_FORMAT,
+        format: info.format as _,
+        sample_count: info.sample_count,
+        width: info.width,
+        height: info.height,
+        face_count: info.face_count,
+        array_size: info.array_size,
+        mip_count: info.mip_count,
+    };
+    let mut handle = xr::sys::Swapchain::NULL;
+    unsafe {
+        let result =
+            (session.instance().fp().create_swapchain)(session.as_raw(), &raw_info, &mut handle);
+        if result.into_raw() < 0 {
+            return Err(result);
+        }
+        Ok(xr::Swapchain::from_raw(session.clone(), handle))
+    }
+}
 
-    let buffers = images
+#[allow(clippy::too_many_arguments)]
+fn swapchain_textures(
+    wgpu_device: &wgpu::Device,
+    handle: &xr::Swapchain<xr::Vulkan>,
+    format: wgpu::TextureFormat,
+    view_formats: &[wgpu::TextureFormat],
+    resolution: UVec2,
+    array_size: u32,
+    hal_usage: wgpu_hal::TextureUses,
+    usage: wgpu::TextureUsages,
+) -> xr::Result<Vec<wgpu::Texture>> {
+    let images = handle.enumerate_images()?;
+
+    Ok(images
         .into_iter()
-        .map(|color_image| {
+        .map(|image| {
             info!("image map swapchain");
-            let color_image = vk::Image::from_raw(color_image);
+            let image = vk::Image::from_raw(image);
             let wgpu_hal_texture = unsafe {
                 <V as Api>::Device::texture_from_raw(
-                    color_image,
+                    image,
                     &wgpu_hal::TextureDescriptor {
                         label: Some("bevy_openxr swapchain"), // unused internally
                         size: wgpu::Extent3d {
                             width: resolution.x,
                             height: resolution.y,
-                            depth_or_array_layers: 2,
+                            depth_or_array_layers: array_size,
                         },
                         mip_level_count: 1,
                         sample_count: 1,
                         dimension: wgpu::TextureDimension::D2,
-                        format: swapchain_format,
-                        usage: wgpu_hal::TextureUses::COLOR_TARGET
-                            | wgpu_hal::TextureUses::COPY_DST,
+                        format,
+                        usage: hal_usage,
                         memory_flags: wgpu_hal::MemoryFlags::empty(),
-                        view_formats: vec![],
+                        view_formats: view_formats.to_vec(),
                     },
                     None,
                 )
             };
-            let texture = unsafe {
+            unsafe {
                 wgpu_device.create_texture_from_hal::<V>(
                     wgpu_hal_texture,
                     &wgpu::TextureDescriptor {
@@ -426,46 +725,31 @@ pub fn start_xr_session(
                         size: wgpu::Extent3d {
                             width: resolution.x,
                             height: resolution.y,
-                            depth_or_array_layers: 2,
+                            depth_or_array_layers: array_size,
                         },
                         mip_level_count: 1,
                         sample_count: 1,
                         dimension: wgpu::TextureDimension::D2,
-                        format: swapchain_format,
-                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                            | wgpu::TextureUsages::COPY_DST,
-                        view_formats: &[],
+                        format,
+                        usage,
+                        view_formats,
                     },
                 )
-            };
-            texture
+            }
         })
-        .collect();
+        .collect())
+}
 
-    Ok((
-        XrSession::Vulkan(session.clone()),
-        resolution.into(),
-        swapchain_format.into(),
-        // TODO: this shouldn't be in here
-        AtomicBool::new(false).into(),
-        frame_wait.into(),
-        Swapchain::Vulkan(SwapchainInner {
-            stream: Mutex::new(frame_stream),
-            handle: Mutex::new(handle),
-            buffers,
-            image_index: Mutex::new(0),
+pub(crate) fn enumerate_swapchain_formats(
+    session: &xr::Session<xr::Vulkan>,
+) -> xr::Result<Vec<wgpu::TextureFormat>> {
+    Ok(session
+        .enumerate_swapchain_formats()?
+        .into_iter()
+        .filter_map(|format| {
+            super::format_from_raw(format, |f| Some(wgpu_to_vulkan(f).as_raw() as _))
         })
-        .into(),
-        XrInput::new(xr_instance, &session.into_any_graphics())?,
-        Vec::default().into(),
-        // TODO: Feels wrong to return a FrameState here, we probably should just wait for the next frame
-        xr::FrameState {
-            predicted_display_time: xr::Time::from_nanos(1),
-            predicted_display_period: xr::Duration::from_nanos(1),
-            should_render: true,
-        }
-        .into(),
-    ))
+        .collect())
 }
 
 fn wgpu_to_vulkan(format: wgpu::TextureFormat) -> vk::Format {
@@ -1,9 +1,45 @@
 use std::sync::Arc;
 
-use bevy::{prelude::*, render::extract_resource::ExtractResource};
+use bevy::{prelude::*, render::extract_resource::ExtractResource, utils::warn_once};
 use openxr as xr;
 use xr::{FrameState, FrameWaiter, ViewConfigurationType};
 
+use crate::{
+    calibration::XrCalibration,
+    graphics::XrSessionConfig,
+    resources::{XrFrameState, XrPredictionOffset, XrSession, XrTime},
+    xr_input::{
+        trackers::{OpenXRTrackingRoot, XrSpaceState, XrVelocity},
+        QuatConv, Vec3Conv,
+    },
+};
+
+/// Send this to re-center the stage space on the current headset position and yaw
+#[derive(Event, Clone, Copy, Default)]
+pub struct RecenterXrSpace;
+
+/// Sent when the runtime moved the origin of a reference space, for example because the user
+/// recentered from the system menu
+#[derive(Event, Clone, Copy, Debug)]
+pub struct XrReferenceSpaceChanged {
+    pub space_type: xr::ReferenceSpaceType,
+    /// The new origin of the space, relative to the previous one
+    pub pose_in_previous_space: xr::Posef,
+}
+
+/// A LOCAL_FLOOR space emulated with a LOCAL space that is moved down to the floor,
+/// for runtimes without `XR_EXT_local_floor`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrEmulatedFloor {
+    /// How far the floor is below the origin of the LOCAL space
+    pub eye_height: f32,
+    /// The height was taken from the STAGE space, otherwise it's
+    /// [`XrSessionConfig::default_eye_height`] and only a guess
+    pub from_stage: bool,
+    /// The runtime has a STAGE space to take the floor height from
+    stage_available: bool,
+}
+
 #[derive(Clone, Resource, ExtractResource)]
 pub struct XrInput {
     //pub action_set: xr::ActionSet,
@@ -11,13 +47,30 @@ pub struct XrInput {
     //pub right_space: Arc<xr::Space>,
     //pub left_space: Arc<xr::Space>,
     pub stage: Arc<xr::Space>,
+    /// The reference space type `stage` was created with,
+    /// might differ from the requested one if the runtime didn't support it
+    pub stage_type: xr::ReferenceSpaceType,
+    /// `Some` if LOCAL_FLOOR was requested but isn't supported by the runtime, `stage` is a
+    /// LOCAL space moved down to the floor then. Apps can ask the user to calibrate their height
+    /// while the floor height isn't [`XrEmulatedFloor::from_stage`].
+    pub emulated_floor: Option<XrEmulatedFloor>,
+    /// Offset of `stage` from the calibrated origin of `stage_type`, set by [`XrInput::recenter`]
+    pub stage_offset: xr::Posef,
+    /// A copy of the [`XrCalibration`] resource, between the origin of `stage_type` and the
+    /// recentered `stage`
+    pub calibration: XrCalibration,
     pub head: Arc<xr::Space>,
+    /// A copy of the [`XrPredictionOffset`] resource, so both worlds locate at the same time
+    pub prediction_offset: XrPredictionOffset,
+    /// The view configuration the session renders, the views are located with it
+    pub view_type: xr::ViewConfigurationType,
 }
 
 impl XrInput {
     pub fn new(
         instance: &xr::Instance,
         session: &xr::Session<xr::AnyGraphics>,
+        session_config: &XrSessionConfig,
         // frame_state: &FrameState,
     ) -> xr::Result<Self> {
         // let right_hand_subaction_path = instance.string_to_path("/user/hand/right").unwrap();
@@ -50,13 +103,24 @@ impl XrInput {
         //     xr::Posef::IDENTITY,
         // )?;
 
-        let stage = match instance.exts().ext_local_floor {
-            None => session
-                .create_reference_space(xr::ReferenceSpaceType::STAGE, xr::Posef::IDENTITY)?,
-            Some(_) => session.create_reference_space(
-                xr::ReferenceSpaceType::LOCAL_FLOOR_EXT,
-                xr::Posef::IDENTITY,
-            )?,
+        let available = available_reference_spaces(instance, session)?;
+        let reference_space = session_config.reference_space;
+        let emulated_floor = (reference_space == xr::ReferenceSpaceType::LOCAL_FLOOR_EXT
+            && !available.contains(&reference_space))
+        .then(|| {
+            warn!(
+                "Reference space {:?} not supported by the runtime, emulating it with LOCAL",
+                reference_space
+            );
+            XrEmulatedFloor {
+                eye_height: session_config.default_eye_height,
+                from_stage: false,
+                stage_available: available.contains(&xr::ReferenceSpaceType::STAGE),
+            }
+        });
+        let stage_type = match emulated_floor {
+            Some(_) => reference_space,
+            None => select_reference_space(&available, reference_space),
         };
         let head =
             session.create_reference_space(xr::ReferenceSpaceType::VIEW, xr::Posef::IDENTITY)?;
@@ -79,8 +143,343 @@ impl XrInput {
             //hand_pose,
             // right_space: Arc::new(right_space),
             // left_space: Arc::new(left_space),
-            stage: Arc::new(stage),
+            stage: Arc::new(create_stage_space(
+                session,
+                stage_type,
+                emulated_floor,
+                XrCalibration::default(),
+                xr::Posef::IDENTITY,
+            )?),
+            stage_type,
+            emulated_floor,
+            stage_offset: xr::Posef::IDENTITY,
+            calibration: default(),
             head: Arc::new(head),
+            prediction_offset: default(),
+            view_type: session_config.view_config.view_type(),
         })
     }
+
+    /// Creates the stage space with an offset from the calibrated origin of `stage_type`
+    fn create_stage(
+        &self,
+        session: &xr::Session<xr::AnyGraphics>,
+        offset: xr::Posef,
+    ) -> xr::Result<xr::Space> {
+        create_stage_space(
+            session,
+            self.stage_type,
+            self.emulated_floor,
+            self.calibration,
+            offset,
+        )
+    }
+
+    /// The pose of the stage space in the space it's created from,
+    /// which is LOCAL for an emulated floor
+    pub(crate) fn stage_pose(&self) -> xr::Posef {
+        stage_pose(self.emulated_floor, self.calibration, self.stage_offset)
+    }
+
+    /// Recreates the stage space with another calibration, keeping the recentered offset
+    pub fn set_calibration(
+        &mut self,
+        session: &xr::Session<xr::AnyGraphics>,
+        calibration: XrCalibration,
+    ) -> xr::Result<()> {
+        let previous = std::mem::replace(&mut self.calibration, calibration);
+        match self.create_stage(session, self.stage_offset) {
+            Ok(stage) => {
+                self.stage = Arc::new(stage);
+                Ok(())
+            }
+            Err(err) => {
+                self.calibration = previous;
+                Err(err)
+            }
+        }
+    }
+}
+
+impl XrInput {
+    /// Locates any space relative to the stage space, see [`XrSpaceState::locate`]
+    pub fn locate(&self, space: &xr::Space, time: XrTime) -> xr::Result<XrSpaceState> {
+        XrSpaceState::locate(space, &self.stage, time)
+    }
+
+    /// The time poses are located at for a frame displayed at `predicted_display_time`,
+    /// shifted by the [`XrPredictionOffset`]
+    pub fn pose_time(&self, predicted_display_time: XrTime) -> XrTime {
+        self.prediction_offset.apply(predicted_display_time)
+    }
+
+    /// Pose and velocity of the headset relative to the stage space
+    pub fn head_state(&self, time: XrTime) -> xr::Result<XrSpaceState> {
+        self.locate(&self.head, time)
+    }
+
+    /// Velocity of the headset relative to the stage space,
+    /// `None` if the runtime couldn't provide a valid velocity
+    pub fn head_velocity(&self, time: XrTime) -> xr::Result<Option<XrVelocity>> {
+        Ok(self.head_state(time)?.velocity())
+    }
+
+    /// Recreates the stage space so its origin is below the headset, facing the same direction.
+    /// For LOCAL and UNBOUNDED spaces the headset height is used as well.
+    /// The [`XrCalibration`] stays applied, so the floor keeps its calibrated height.
+    pub fn recenter(
+        &mut self,
+        session: &xr::Session<xr::AnyGraphics>,
+        time: xr::Time,
+    ) -> xr::Result<()> {
+        let base = self.create_stage(session, xr::Posef::IDENTITY)?;
+        let location = self.head.locate(&base, time)?;
+        if !location.location_flags.contains(
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
+        ) {
+            warn!("Unable to recenter, headset pose is not valid");
+            return Ok(());
+        }
+        let (yaw, _, _) = location.pose.orientation.to_quat().to_euler(EulerRot::YXZ);
+        let orientation = Quat::from_rotation_y(yaw);
+        let mut position = location.pose.position;
+        if !matches!(
+            self.stage_type,
+            xr::ReferenceSpaceType::LOCAL | xr::ReferenceSpaceType::UNBOUNDED_MSFT
+        ) {
+            position.y = 0.0;
+        }
+        let offset = xr::Posef {
+            orientation: xr::Quaternionf {
+                x: orientation.x,
+                y: orientation.y,
+                z: orientation.z,
+                w: orientation.w,
+            },
+            position,
+        };
+        self.stage = Arc::new(self.create_stage(session, offset)?);
+        self.stage_offset = offset;
+        Ok(())
+    }
+
+    /// Takes the floor height of an emulated LOCAL_FLOOR space from the STAGE space.
+    /// Returns `false` if the runtime has no STAGE space or it couldn't be located yet.
+    pub fn update_emulated_floor(
+        &mut self,
+        session: &xr::Session<xr::AnyGraphics>,
+        time: xr::Time,
+    ) -> xr::Result<bool> {
+        let Some(floor) = self.emulated_floor.filter(|floor| floor.stage_available) else {
+            return Ok(false);
+        };
+        let local =
+            session.create_reference_space(xr::ReferenceSpaceType::LOCAL, xr::Posef::IDENTITY)?;
+        let stage =
+            session.create_reference_space(xr::ReferenceSpaceType::STAGE, xr::Posef::IDENTITY)?;
+        let location = stage.locate(&local, time)?;
+        if !location
+            .location_flags
+            .contains(xr::SpaceLocationFlags::POSITION_VALID)
+        {
+            return Ok(false);
+        }
+        self.emulated_floor = Some(XrEmulatedFloor {
+            eye_height: -location.pose.position.y,
+            from_stage: true,
+            ..floor
+        });
+        self.stage = Arc::new(self.create_stage(session, self.stage_offset)?);
+        info!(
+            "Emulating LOCAL_FLOOR with an eye height of {:.2}m from the STAGE space",
+            -location.pose.position.y
+        );
+        Ok(true)
+    }
+}
+
+pub(crate) fn recenter_xr_space(
+    mut events: EventReader<RecenterXrSpace>,
+    mut xr_input: ResMut<XrInput>,
+    session: Res<XrSession>,
+    frame_state: Res<XrFrameState>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    if let Err(err) = xr_input.recenter(&session, frame_state.predicted_display_time) {
+        error!("Unable to recenter stage space: {}", err);
+    }
+}
+
+/// Takes the height of an emulated LOCAL_FLOOR space from the STAGE space once it can be located
+pub(crate) fn update_emulated_floor(
+    mut xr_input: ResMut<XrInput>,
+    session: Res<XrSession>,
+    frame_state: Res<XrFrameState>,
+) {
+    if !xr_input
+        .emulated_floor
+        .is_some_and(|floor| floor.stage_available && !floor.from_stage)
+    {
+        return;
+    }
+    // the first frame hasn't been waited for yet, so this is retried every frame
+    if let Err(err) = xr_input.update_emulated_floor(&session, frame_state.predicted_display_time) {
+        warn_once!(
+            "Unable to take the floor height from the STAGE space: {}",
+            err
+        );
+    }
+}
+
+/// Copies a changed [`XrPredictionOffset`] into the [`XrInput`], which is extracted to the
+/// render world with it
+pub(crate) fn apply_prediction_offset(
+    offset: Res<XrPredictionOffset>,
+    mut xr_input: ResMut<XrInput>,
+) {
+    if xr_input.prediction_offset != *offset {
+        xr_input.prediction_offset = *offset;
+    }
+}
+
+/// Moves the tracking root by the offset the runtime applied to the stage space,
+/// so tracked content stays where it was in the world.
+/// UNBOUNDED spaces are re-anchored often while the user walks around, so those aren't logged.
+/// An emulated floor takes its height from the STAGE space again after LOCAL or STAGE changed.
+pub(crate) fn apply_reference_space_change(
+    mut events: EventReader<XrReferenceSpaceChanged>,
+    mut xr_input: ResMut<XrInput>,
+    mut tracking_root: Query<&mut Transform, With<OpenXRTrackingRoot>>,
+) {
+    for event in events.read() {
+        if matches!(
+            event.space_type,
+            xr::ReferenceSpaceType::LOCAL | xr::ReferenceSpaceType::STAGE
+        ) {
+            if let Some(floor) = &mut xr_input.emulated_floor {
+                floor.from_stage = false;
+            }
+        }
+        let base_type = match xr_input.emulated_floor {
+            Some(_) => xr::ReferenceSpaceType::LOCAL,
+            None => xr_input.stage_type,
+        };
+        if event.space_type != base_type {
+            continue;
+        }
+        if event.space_type != xr::ReferenceSpaceType::UNBOUNDED_MSFT {
+            info!("Reference space {:?} changed", event.space_type);
+        }
+        let offset = pose_to_transform(&xr_input.stage_pose());
+        let delta = pose_to_transform(&event.pose_in_previous_space);
+        let delta = Transform::from_matrix(
+            offset.compute_matrix().inverse() * delta.compute_matrix() * offset.compute_matrix(),
+        );
+        for mut root in &mut tracking_root {
+            *root = root.mul_transform(delta);
+        }
+    }
+}
+
+pub(crate) fn pose_to_transform(pose: &xr::Posef) -> Transform {
+    Transform::from_translation(pose.position.to_vec3()).with_rotation(pose.orientation.to_quat())
+}
+
+pub(crate) fn transform_to_pose(transform: &Transform) -> xr::Posef {
+    let (rotation, translation) = (transform.rotation, transform.translation);
+    xr::Posef {
+        orientation: xr::Quaternionf {
+            x: rotation.x,
+            y: rotation.y,
+            z: rotation.z,
+            w: rotation.w,
+        },
+        position: xr::Vector3f {
+            x: translation.x,
+            y: translation.y,
+            z: translation.z,
+        },
+    }
+}
+
+fn create_stage_space(
+    session: &xr::Session<xr::AnyGraphics>,
+    stage_type: xr::ReferenceSpaceType,
+    emulated_floor: Option<XrEmulatedFloor>,
+    calibration: XrCalibration,
+    offset: xr::Posef,
+) -> xr::Result<xr::Space> {
+    let base_type = match emulated_floor {
+        Some(_) => xr::ReferenceSpaceType::LOCAL,
+        None => stage_type,
+    };
+    session.create_reference_space(base_type, stage_pose(emulated_floor, calibration, offset))
+}
+
+fn stage_pose(
+    emulated_floor: Option<XrEmulatedFloor>,
+    calibration: XrCalibration,
+    offset: xr::Posef,
+) -> xr::Posef {
+    let mut pose = match calibration == XrCalibration::default() {
+        true => offset,
+        false => transform_to_pose(
+            &calibration
+                .transform()
+                .mul_transform(pose_to_transform(&offset)),
+        ),
+    };
+    if let Some(floor) = emulated_floor {
+        pose.position.y -= floor.eye_height;
+    }
+    pose
+}
+
+/// The reference spaces the session can create
+fn available_reference_spaces(
+    instance: &xr::Instance,
+    session: &xr::Session<xr::AnyGraphics>,
+) -> xr::Result<Vec<xr::ReferenceSpaceType>> {
+    let mut available = session.enumerate_reference_spaces()?;
+    if instance.exts().ext_local_floor.is_some()
+        && !available.contains(&xr::ReferenceSpaceType::LOCAL_FLOOR_EXT)
+    {
+        available.push(xr::ReferenceSpaceType::LOCAL_FLOOR_EXT);
+    }
+    Ok(available)
+}
+
+fn select_reference_space(
+    available: &[xr::ReferenceSpaceType],
+    requested: xr::ReferenceSpaceType,
+) -> xr::ReferenceSpaceType {
+    if available.contains(&requested) {
+        return requested;
+    }
+    // UNBOUNDED isn't floor relative either, so LOCAL keeps content at the expected height
+    if requested == xr::ReferenceSpaceType::UNBOUNDED_MSFT {
+        warn!(
+            "Reference space {:?} not supported by the runtime, falling back to {:?}, \
+            XR_MSFT_unbounded_reference_space might not be enabled",
+            requested,
+            xr::ReferenceSpaceType::LOCAL
+        );
+        return xr::ReferenceSpaceType::LOCAL;
+    }
+    // LOCAL is required to be supported by every runtime
+    let fallback = [
+        xr::ReferenceSpaceType::LOCAL_FLOOR_EXT,
+        xr::ReferenceSpaceType::STAGE,
+        xr::ReferenceSpaceType::LOCAL,
+    ]
+    .into_iter()
+    .find(|space| available.contains(space))
+    .unwrap_or(xr::ReferenceSpaceType::LOCAL);
+    warn!(
+        "Reference space {:?} not supported by the runtime, falling back to {:?}",
+        requested, fallback
+    );
+    fallback
 }
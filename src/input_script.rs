@@ -0,0 +1,478 @@
+//! Scripted input for tests, an [`XrInputScript`] schedules poses, controller input and buttons
+//! that are replayed through the simulator one frame per update. See the `input_script` example.
+
+use std::ops::Range;
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::recording::{XrRecordedController, XrRecordedFrame, XrRecording, XrReplayPlugin};
+use crate::xr_input::actions::XrButton;
+use crate::xr_input::tracked_controllers::XrControllerInput;
+use crate::xr_input::Hand;
+
+/// Poses that are interpolated between their keyframes and held before the first and after the
+/// last one
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XrPoseTrack {
+    /// Sorted by time
+    keyframes: Vec<(f32, Transform)>,
+}
+
+impl XrPoseTrack {
+    /// Replaces a keyframe at the same time
+    pub fn insert(&mut self, time: f32, transform: Transform) {
+        let index = self.keyframes.partition_point(|(at, _)| *at < time);
+        match self.keyframes.get_mut(index) {
+            Some((at, keyframe)) if *at == time => *keyframe = transform,
+            _ => self.keyframes.insert(index, (time, transform)),
+        }
+    }
+
+    pub fn keyframes(&self) -> &[(f32, Transform)] {
+        &self.keyframes
+    }
+
+    /// The interpolated pose, `None` without keyframes
+    pub fn sample(&self, time: f32) -> Option<Transform> {
+        let next = self.keyframes.partition_point(|(at, _)| *at <= time);
+        let previous = next.checked_sub(1).map(|index| self.keyframes[index]);
+        let ((from_time, from), (to_time, to)) = match (previous, self.keyframes.get(next)) {
+            (Some(previous), Some(&next)) => (previous, next),
+            (Some((_, transform)), None) | (None, Some(&(_, transform))) => return Some(transform),
+            (None, None) => return None,
+        };
+        let t = (time - from_time) / (to_time - from_time);
+        Some(Transform {
+            translation: from.translation.lerp(to.translation, t),
+            rotation: from.rotation.slerp(to.rotation, t),
+            scale: Vec3::ONE,
+        })
+    }
+}
+
+/// A timeline of input, times are seconds since the start of the script. Every update plays
+/// exactly one frame of the [`replay`](Self::replay), so a run is deterministic no matter how
+/// long the updates take.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XrInputScript {
+    /// Distance between the eyes in meters
+    pub ipd: f32,
+    head: XrPoseTrack,
+    controllers: [XrPoseTrack; 2],
+    /// The input of a controller from a time on
+    inputs: [Vec<(f32, XrControllerInput)>; 2],
+    tracking_lost: [Vec<Range<f32>>; 2],
+    /// Pressed when `true`, released otherwise
+    buttons: Vec<(f32, XrButton, bool)>,
+    end: f32,
+}
+
+impl Default for XrInputScript {
+    fn default() -> Self {
+        let pose = |x: f32, y: f32, z: f32| {
+            let mut track = XrPoseTrack::default();
+            track.insert(0.0, Transform::from_xyz(x, y, z));
+            track
+        };
+        Self {
+            ipd: 0.063,
+            head: pose(0.0, 1.6, 0.0),
+            controllers: [pose(-0.2, 1.3, -0.4), pose(0.2, 1.3, -0.4)],
+            inputs: default(),
+            tracking_lost: default(),
+            buttons: Vec::new(),
+            end: 0.0,
+        }
+    }
+}
+
+fn index(hand: Hand) -> usize {
+    match hand {
+        Hand::Left => 0,
+        Hand::Right => 1,
+    }
+}
+
+impl XrInputScript {
+    /// 90 Hz, like most headsets
+    pub const FRAME_PERIOD: Duration = Duration::from_nanos(11_111_111);
+
+    /// The head is 1.6 meters above the floor and the controllers are held in front of it
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn extend(&mut self, time: f32) {
+        self.end = self.end.max(time);
+    }
+
+    /// The head reaches `transform` at `time`, relative to the tracking root
+    pub fn head(mut self, time: f32, transform: Transform) -> Self {
+        self.head.insert(time, transform);
+        self.extend(time);
+        self
+    }
+
+    /// The controller reaches `transform` at `time`, relative to the tracking root
+    pub fn controller(mut self, hand: Hand, time: f32, transform: Transform) -> Self {
+        self.controllers[index(hand)].insert(time, transform);
+        self.extend(time);
+        self
+    }
+
+    /// Swings the controller from its pose at `start` by `angle` radians around the line through
+    /// `center` along `axis`, keeping it pointing the same way relative to the arc
+    pub fn controller_arc(
+        mut self,
+        hand: Hand,
+        start: f32,
+        end: f32,
+        center: Vec3,
+        axis: Vec3,
+        angle: f32,
+    ) -> Self {
+        let track = &mut self.controllers[index(hand)];
+        let Some(from) = track.sample(start) else {
+            return self;
+        };
+        // a keyframe every 5 degrees is close enough to a circle
+        let steps = (angle.abs() / 5f32.to_radians()).ceil().max(1.0) as u32;
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            let rotation = Quat::from_axis_angle(axis.normalize(), angle * t);
+            let mut transform = from;
+            transform.rotate_around(center, rotation);
+            track.insert(start + (end - start) * t, transform);
+        }
+        self.extend(end);
+        self
+    }
+
+    /// The controller reports `input` from `time` on, until the next input of the controller
+    pub fn input(mut self, hand: Hand, time: f32, input: XrControllerInput) -> Self {
+        let inputs = &mut self.inputs[index(hand)];
+        let index = inputs.partition_point(|(at, _)| *at <= time);
+        inputs.insert(index, (time, input));
+        self.extend(time);
+        self
+    }
+
+    /// The controller isn't tracked for `duration` seconds, it's inactive and releases its input
+    pub fn lose_tracking(mut self, hand: Hand, time: f32, duration: f32) -> Self {
+        self.tracking_lost[index(hand)].push(time..time + duration);
+        self.extend(time + duration);
+        self
+    }
+
+    pub fn press(mut self, time: f32, button: XrButton) -> Self {
+        self.buttons.push((time, button, true));
+        self.extend(time);
+        self
+    }
+
+    pub fn release(mut self, time: f32, button: XrButton) -> Self {
+        self.buttons.push((time, button, false));
+        self.extend(time);
+        self
+    }
+
+    /// Presses `button` at `time` and releases it `duration` seconds later
+    pub fn click(self, time: f32, button: XrButton, duration: f32) -> Self {
+        self.press(time, button).release(time + duration, button)
+    }
+
+    /// Keeps the script going until `time`, after the last scheduled change
+    pub fn until(mut self, time: f32) -> Self {
+        self.extend(time);
+        self
+    }
+
+    /// The last scheduled time
+    pub fn end(&self) -> f32 {
+        self.end
+    }
+
+    /// The index of the frame that plays a change scheduled at `time`, changes are rounded to
+    /// the closest frame so they don't depend on float precision
+    pub fn frame_at(time: f32, frame_period: Duration) -> usize {
+        (time.max(0.0) / frame_period.as_secs_f32()).round() as usize
+    }
+
+    fn buttons_at(&self, frame: usize, frame_period: Duration) -> Vec<XrButton> {
+        let mut changes = self
+            .buttons
+            .iter()
+            .filter(|(at, ..)| Self::frame_at(*at, frame_period) <= frame)
+            .collect::<Vec<_>>();
+        // the order they were scheduled in decides between changes at the same time
+        changes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut pressed = Vec::new();
+        for (_, button, press) in changes {
+            pressed.retain(|pressed| pressed != button);
+            if *press {
+                pressed.push(*button);
+            }
+        }
+        pressed
+    }
+
+    fn controller_at(
+        &self,
+        hand: Hand,
+        frame: usize,
+        frame_period: Duration,
+    ) -> Option<XrRecordedController> {
+        let index = index(hand);
+        let frame_at = |time: f32| Self::frame_at(time, frame_period);
+        if self.tracking_lost[index]
+            .iter()
+            .any(|lost| (frame_at(lost.start)..frame_at(lost.end)).contains(&frame))
+        {
+            return None;
+        }
+        let input = self.inputs[index]
+            .iter()
+            .rev()
+            .find(|(at, _)| frame_at(*at) <= frame)
+            .map(|(_, input)| *input)
+            .unwrap_or_default();
+        Some(XrRecordedController {
+            transform: self.controllers[index].sample(frame as f32 * frame_period.as_secs_f32())?,
+            input,
+        })
+    }
+
+    /// The frame with the index `frame`, poses are interpolated at its time
+    pub fn frame(&self, frame: usize, frame_period: Duration) -> XrRecordedFrame {
+        let time = frame_period * frame as u32;
+        let head = self.head.sample(time.as_secs_f32()).unwrap_or_default();
+        let eye = |x: f32| head.mul_transform(Transform::from_xyz(x, 0.0, 0.0));
+        XrRecordedFrame {
+            time,
+            views: [eye(-self.ipd / 2.0), eye(self.ipd / 2.0)],
+            controllers: [Hand::Left, Hand::Right]
+                .map(|hand| self.controller_at(hand, frame, frame_period)),
+            buttons: self.buttons_at(frame, frame_period),
+        }
+    }
+
+    /// A frame every `frame_period` from the start to the [`end`](Self::end) of the script
+    pub fn to_recording(&self, frame_period: Duration) -> XrRecording {
+        XrRecording {
+            frames: (0..=Self::frame_at(self.end, frame_period))
+                .map(|frame| self.frame(frame, frame_period))
+                .collect(),
+        }
+    }
+
+    /// Plays the script one frame per update
+    pub fn replay(&self, frame_period: Duration) -> XrReplayPlugin {
+        XrReplayPlugin::new(self.to_recording(frame_period)).as_fast_as_possible()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::XrReplay;
+    use crate::simulator::SimulatedController;
+    use crate::xr_init::{XrStatus, XrUnavailableReason};
+    use crate::xr_input::actions::XrActionSync;
+    use crate::xr_input::tracked_controllers::XrController;
+    use crate::xr_input::XrTrackingUpdate;
+
+    const FIRE: XrButton = XrButton {
+        action_set: "gameplay",
+        action: "fire",
+    };
+    const PERIOD: Duration = XrInputScript::FRAME_PERIOD;
+
+    /// What the app saw after the input was synced in one update
+    #[derive(Clone, Debug)]
+    struct Seen {
+        frame: usize,
+        pressed: bool,
+        just_pressed: bool,
+        just_released: bool,
+        input: XrControllerInput,
+        active: bool,
+        translation: Vec3,
+    }
+
+    #[derive(Resource, Default)]
+    struct SeenFrames(Vec<Seen>);
+
+    fn see(
+        replay: Res<XrReplay>,
+        buttons: Res<ButtonInput<XrButton>>,
+        controllers: Query<
+            (&XrController, &XrControllerInput, &Transform),
+            With<SimulatedController>,
+        >,
+        mut seen: ResMut<SeenFrames>,
+    ) {
+        let Some(frame) = replay.frame() else {
+            return;
+        };
+        if replay.is_finished() {
+            return;
+        }
+        let (controller, input, transform) = controllers
+            .iter()
+            .find(|(controller, ..)| controller.hand == Hand::Right)
+            .unwrap();
+        seen.0.push(Seen {
+            frame,
+            pressed: buttons.pressed(FIRE),
+            just_pressed: buttons.just_pressed(FIRE),
+            just_released: buttons.just_released(FIRE),
+            input: *input,
+            active: controller.active,
+            translation: transform.translation,
+        });
+    }
+
+    /// Replays the script through the simulator like an app without a headset
+    fn replay(script: &XrInputScript) -> Vec<Seen> {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(XrStatus::Unavailable(XrUnavailableReason::Simulated))
+            .init_resource::<ButtonInput<XrButton>>()
+            .init_resource::<SeenFrames>()
+            .configure_sets(PreUpdate, (XrActionSync, XrTrackingUpdate).chain())
+            .add_plugins(script.replay(PERIOD))
+            .add_systems(Update, see);
+        app.finish();
+        app.cleanup();
+        let frames = XrInputScript::frame_at(script.end(), PERIOD) + 1;
+        for _ in 0..=frames {
+            app.update();
+        }
+        assert!(app.world.resource::<XrReplay>().is_finished());
+        std::mem::take(&mut app.world.resource_mut::<SeenFrames>().0)
+    }
+
+    fn frames(seen: &[Seen], filter: impl Fn(&Seen) -> bool) -> Vec<usize> {
+        seen.iter()
+            .filter(|seen| filter(seen))
+            .map(|seen| seen.frame)
+            .collect()
+    }
+
+    #[test]
+    fn every_frame_is_seen_once() {
+        let script = XrInputScript::new().until(0.5);
+        let seen = replay(&script);
+        assert_eq!(
+            seen.iter().map(|seen| seen.frame).collect::<Vec<_>>(),
+            (0..=XrInputScript::frame_at(0.5, PERIOD)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn button_edges_are_seen_on_their_frames() {
+        let script = XrInputScript::new()
+            .click(0.1, FIRE, 0.2)
+            .click(0.4, FIRE, 0.1)
+            .until(0.6);
+        let seen = replay(&script);
+        let at = |time| XrInputScript::frame_at(time, PERIOD);
+        assert_eq!(frames(&seen, |seen| seen.just_pressed), [at(0.1), at(0.4)]);
+        assert_eq!(frames(&seen, |seen| seen.just_released), [at(0.3), at(0.5)]);
+        assert_eq!(
+            frames(&seen, |seen| seen.pressed),
+            (at(0.1)..at(0.3))
+                .chain(at(0.4)..at(0.5))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn held_button_is_pressed_once() {
+        let script = XrInputScript::new()
+            .press(0.1, FIRE)
+            .press(0.2, FIRE)
+            .until(0.3);
+        let seen = replay(&script);
+        assert_eq!(
+            frames(&seen, |seen| seen.just_pressed),
+            [XrInputScript::frame_at(0.1, PERIOD)]
+        );
+        assert!(frames(&seen, |seen| seen.just_released).is_empty());
+        assert!(seen.last().unwrap().pressed);
+    }
+
+    #[test]
+    fn later_change_wins_on_the_same_frame() {
+        let script = XrInputScript::new()
+            .press(0.1, FIRE)
+            .release(0.1 + PERIOD.as_secs_f32() / 4.0, FIRE);
+        let frame = script.frame(XrInputScript::frame_at(0.1, PERIOD), PERIOD);
+        assert!(frame.buttons.is_empty());
+    }
+
+    #[test]
+    fn controller_input_is_synced_on_its_frame() {
+        let pulled = XrControllerInput {
+            trigger: 1.0,
+            trigger_touched: true,
+            ..default()
+        };
+        let script = XrInputScript::new()
+            .input(Hand::Right, 0.2, pulled)
+            .input(Hand::Right, 0.4, XrControllerInput::default())
+            .until(0.5);
+        let seen = replay(&script);
+        let at = |time| XrInputScript::frame_at(time, PERIOD);
+        assert_eq!(
+            frames(&seen, |seen| seen.input == pulled),
+            (at(0.2)..at(0.4)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn lost_tracking_deactivates_and_releases_the_controller() {
+        let script = XrInputScript::new()
+            .input(
+                Hand::Right,
+                0.0,
+                XrControllerInput {
+                    squeeze: 1.0,
+                    ..default()
+                },
+            )
+            .controller(Hand::Right, 0.2, Transform::from_xyz(0.2, 1.3, -0.4))
+            .controller(Hand::Right, 0.6, Transform::from_xyz(0.6, 1.3, -0.4))
+            .lose_tracking(Hand::Right, 0.3, 0.1);
+        let seen = replay(&script);
+        let at = |time| XrInputScript::frame_at(time, PERIOD);
+        let lost = (at(0.3)..at(0.4)).collect::<Vec<_>>();
+        assert_eq!(frames(&seen, |seen| !seen.active), lost);
+        assert_eq!(
+            frames(&seen, |seen| seen.input == XrControllerInput::default()),
+            lost
+        );
+        // the pose is held while lost and moves on once it's found again
+        let held = seen[at(0.3) - 1].translation;
+        assert!(lost.iter().all(|&frame| seen[frame].translation == held));
+        let found = &seen[at(0.4)];
+        let expected = script.frame(at(0.4), PERIOD).controllers[1].unwrap();
+        assert_eq!(found.translation, expected.transform.translation);
+        assert!(found.translation.x > held.x);
+    }
+
+    #[test]
+    fn poses_are_interpolated_and_held() {
+        let mut track = XrPoseTrack::default();
+        assert_eq!(track.sample(0.0), None);
+        track.insert(1.0, Transform::from_xyz(0.0, 0.0, 0.0));
+        track.insert(2.0, Transform::from_xyz(2.0, 0.0, 0.0));
+        assert_eq!(track.sample(0.0).unwrap().translation, Vec3::ZERO);
+        assert_eq!(track.sample(1.5).unwrap().translation, Vec3::X);
+        assert_eq!(track.sample(3.0).unwrap().translation, Vec3::X * 2.0);
+        track.insert(2.0, Transform::from_xyz(4.0, 0.0, 0.0));
+        assert_eq!(track.keyframes().len(), 2);
+        assert_eq!(track.sample(1.5).unwrap().translation, Vec3::X * 2.0);
+    }
+}
@@ -0,0 +1,248 @@
+//! Estimates how long input takes to reach the display. Add the [`XrLatencyPlugin`] to a build
+//! to measure it, without the plugin nothing is recorded.
+//!
+//! Every frame is timestamped when its views were located, when the actions were synced and
+//! when it was submitted with `xrEndFrame`, and compared with its predicted display time. Bool
+//! actions that changed with a sync are reported as [`XrInputLatency`] events once the frame
+//! showing the change was submitted, measured from the time the runtime saw the change.
+//! The instants are converted with the [`clock`](crate::clock), without a runtime that converts
+//! times they're only accurate to about a display period.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+use bevy::render::{Render, RenderApp, RenderSet};
+use openxr as xr;
+
+use crate::resources::{XrFrameState, XrInstance, XrSession, XrTime};
+use crate::xr_init::{xr_after_wait_only, xr_only, XrSetup, XrWaitFrame};
+use crate::xr_input::actions::{sync_actions, XrActionSets, XrActionSync, XrButton};
+use crate::{xr_end_frame, xr_skip_frame};
+
+/// Frames that weren't submitted by then are dropped, like the ones the runtime didn't want
+const MAX_PENDING_FRAMES: usize = 8;
+
+/// Records the latency of every frame and sends [`XrInputLatency`] events,
+/// the latencies are added to bevy's [`Diagnostics`] in milliseconds
+pub struct XrLatencyPlugin;
+
+impl XrLatencyPlugin {
+    /// From the runtime seeing a button change to the predicted display time of the frame
+    /// that synced it
+    pub const BUTTON_TO_PHOTON: DiagnosticPath = DiagnosticPath::const_new("xr/button_to_photon");
+    /// From the runtime seeing a button change to the app syncing it
+    pub const BUTTON_TO_SYNC: DiagnosticPath = DiagnosticPath::const_new("xr/button_to_sync");
+    /// From locating the views to the predicted display time, the time the runtime has to
+    /// predict the head pose for
+    pub const MOTION_TO_PHOTON: DiagnosticPath = DiagnosticPath::const_new("xr/motion_to_photon");
+    /// From `xrEndFrame` to the predicted display time
+    pub const SUBMIT_TO_PHOTON: DiagnosticPath = DiagnosticPath::const_new("xr/submit_to_photon");
+}
+
+impl Plugin for XrLatencyPlugin {
+    fn build(&self, app: &mut App) {
+        let frames = LatencyFrames::default();
+        app.register_diagnostic(Diagnostic::new(Self::BUTTON_TO_PHOTON).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::BUTTON_TO_SYNC).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::MOTION_TO_PHOTON).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::SUBMIT_TO_PHOTON).with_suffix("ms"));
+        app.add_event::<XrInputLatency>();
+        app.insert_resource(frames.clone());
+        app.add_systems(XrSetup, reset_latency_frames);
+        app.add_systems(
+            PreUpdate,
+            (
+                start_latency_frame
+                    .after(XrWaitFrame)
+                    .before(XrActionSync)
+                    .run_if(xr_after_wait_only()),
+                record_button_changes
+                    .after(sync_actions)
+                    .in_set(XrActionSync),
+                report_latency.after(XrActionSync),
+            )
+                .run_if(xr_only()),
+        );
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.insert_resource(frames);
+        render_app.add_systems(
+            Render,
+            record_submission
+                .after(xr_end_frame)
+                .after(xr_skip_frame)
+                .in_set(RenderSet::Cleanup)
+                .run_if(xr_only())
+                .run_if(xr_after_wait_only()),
+        );
+    }
+}
+
+/// A bool action changed and the frame that synced the change was submitted
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct XrInputLatency {
+    pub button: XrButton,
+    pub pressed: bool,
+    /// When the runtime saw the change
+    pub change_time: XrTime,
+    /// When the actions were synced
+    pub sync_time: XrTime,
+    /// When the frame was submitted
+    pub submit_time: XrTime,
+    /// When the frame is predicted to be shown
+    pub display_time: XrTime,
+}
+
+impl XrInputLatency {
+    pub fn change_to_sync(&self) -> Duration {
+        between(self.change_time, self.sync_time)
+    }
+    pub fn change_to_submit(&self) -> Duration {
+        between(self.change_time, self.submit_time)
+    }
+    /// The button to photon latency
+    pub fn change_to_display(&self) -> Duration {
+        between(self.change_time, self.display_time)
+    }
+}
+
+fn between(from: XrTime, to: XrTime) -> Duration {
+    Duration::from_nanos((to.as_nanos() - from.as_nanos()).max(0) as u64)
+}
+
+/// Shared by both worlds, the render world marks the frames it submitted
+#[derive(Clone, Default, Resource)]
+struct LatencyFrames(Arc<Mutex<VecDeque<LatencyFrame>>>);
+
+struct LatencyFrame {
+    display_time: XrTime,
+    located_at: Instant,
+    synced_at: Option<Instant>,
+    submitted_at: Option<Instant>,
+    /// Buttons that changed with the sync of this frame, with their new state and change time
+    changes: Vec<(XrButton, bool, XrTime)>,
+}
+
+fn reset_latency_frames(frames: Res<LatencyFrames>) {
+    frames.0.lock().unwrap().clear();
+}
+
+fn start_latency_frame(frames: Res<LatencyFrames>, frame_state: Res<XrFrameState>) {
+    let mut frames = frames.0.lock().unwrap();
+    if frames.len() >= MAX_PENDING_FRAMES {
+        frames.pop_front();
+    }
+    frames.push_back(LatencyFrame {
+        display_time: frame_state.predicted_display_time.into(),
+        located_at: Instant::now(),
+        synced_at: None,
+        submitted_at: None,
+        changes: Vec::new(),
+    });
+}
+
+fn record_button_changes(
+    frames: Res<LatencyFrames>,
+    frame_state: Res<XrFrameState>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Res<XrSession>,
+) {
+    let synced_at = Instant::now();
+    let Some(action_sets) = action_sets.filter(|action_sets| action_sets.has_synced()) else {
+        return;
+    };
+    let mut frames = frames.0.lock().unwrap();
+    let display_time = XrTime::from(frame_state.predicted_display_time);
+    let Some(frame) = frames
+        .back_mut()
+        .filter(|frame| frame.display_time == display_time && frame.synced_at.is_none())
+    else {
+        return;
+    };
+    frame.synced_at = Some(synced_at);
+    for button in action_sets.enabled_buttons() {
+        let Ok(state) = action_sets.get_state::<bool>(
+            &session,
+            button.action_set,
+            button.action,
+            xr::Path::NULL,
+        ) else {
+            continue;
+        };
+        if state.is_active && state.changed {
+            frame
+                .changes
+                .push((button, state.value, state.last_change_time));
+        }
+    }
+}
+
+fn record_submission(frames: Res<LatencyFrames>, frame_state: Res<XrFrameState>) {
+    let now = Instant::now();
+    let display_time = XrTime::from(frame_state.predicted_display_time);
+    let mut frames = frames.0.lock().unwrap();
+    if let Some(frame) = frames
+        .iter_mut()
+        .find(|frame| frame.display_time == display_time)
+    {
+        frame.submitted_at = Some(now);
+    }
+}
+
+fn report_latency(
+    frames: Res<LatencyFrames>,
+    instance: Res<XrInstance>,
+    mut latencies: EventWriter<XrInputLatency>,
+    mut diagnostics: Diagnostics,
+) {
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let mut frames = frames.0.lock().unwrap();
+    while let Some(frame) = frames.front() {
+        let Some(submitted_at) = frame.submitted_at else {
+            // frames are submitted in order, one that was skipped is never going to be
+            if frames.iter().any(|frame| frame.submitted_at.is_some()) {
+                frames.pop_front();
+                continue;
+            }
+            break;
+        };
+        let frame = frames.pop_front().unwrap();
+        let (Some(located_time), Some(submit_time)) = (
+            instance.to_xr_time(frame.located_at),
+            instance.to_xr_time(submitted_at),
+        ) else {
+            continue;
+        };
+        diagnostics.add_measurement(&XrLatencyPlugin::MOTION_TO_PHOTON, || {
+            millis(between(located_time, frame.display_time))
+        });
+        diagnostics.add_measurement(&XrLatencyPlugin::SUBMIT_TO_PHOTON, || {
+            millis(between(submit_time, frame.display_time))
+        });
+        let Some(sync_time) = frame
+            .synced_at
+            .and_then(|synced_at| instance.to_xr_time(synced_at))
+        else {
+            continue;
+        };
+        for (button, pressed, change_time) in frame.changes {
+            let latency = XrInputLatency {
+                button,
+                pressed,
+                change_time,
+                sync_time,
+                submit_time,
+                display_time: frame.display_time,
+            };
+            diagnostics.add_measurement(&XrLatencyPlugin::BUTTON_TO_SYNC, || {
+                millis(latency.change_to_sync())
+            });
+            diagnostics.add_measurement(&XrLatencyPlugin::BUTTON_TO_PHOTON, || {
+                millis(latency.change_to_display())
+            });
+            latencies.send(latency);
+        }
+    }
+}
@@ -0,0 +1,814 @@
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use bevy::ecs::entity::EntityHashSet;
+use bevy::prelude::*;
+use bevy::render::camera::{
+    CameraUpdateSystem, ManualTextureView, ManualTextureViewHandle, ManualTextureViews,
+    RenderTarget,
+};
+use bevy::render::extract_resource::ExtractResource;
+use bevy::render::render_resource::TextureView;
+use bevy::render::renderer::{render_system, RenderDevice};
+use bevy::render::{Extract, ExtractSchedule, Render, RenderApp, RenderSet};
+use openxr as xr;
+use xr::{CompositionLayerBase, CompositionLayerFlags};
+
+use crate::error::XrError;
+use crate::graphics;
+use crate::resources::{SwapchainImages, XrFormat, XrInstance, XrSession};
+use crate::xr_init::{xr_after_wait_only, xr_only, xr_render_only, XrCleanup, XrShouldRender};
+use crate::xr_input::xr_camera::RootTransform;
+
+/// Adds support for [`XrCompositionLayer`]s
+pub struct CompositionLayerPlugin;
+
+impl Plugin for CompositionLayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (
+                sync_layer_stack,
+                create_layer_swapchains,
+                update_layer_cameras,
+            )
+                .chain()
+                .run_if(xr_only())
+                .before(CameraUpdateSystem),
+        );
+        app.add_systems(XrCleanup, cleanup_layer_swapchains);
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.add_systems(ExtractSchedule, extract_layers.run_if(xr_only()));
+        render_app.add_systems(
+            Render,
+            acquire_layer_images
+                .run_if(xr_only())
+                .run_if(xr_after_wait_only())
+                .run_if(xr_render_only())
+                .before(render_system)
+                .after(RenderSet::ExtractCommands),
+        );
+    }
+}
+
+/// The shape a composition layer is displayed with
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum XrLayerShape {
+    /// A flat rectangle, facing the +Z axis of the entity. `size` is in meters.
+    Quad { size: Vec2 },
+    /// A section of the inside of a cylinder around the +Y axis of the entity, centered on its -Z axis.
+    /// Requires `XR_KHR_composition_layer_cylinder`.
+    Cylinder {
+        radius: f32,
+        /// Angle in radians of the visible section of the cylinder
+        central_angle: f32,
+        /// Width divided by height of the visible section
+        aspect_ratio: f32,
+    },
+    /// A section of the inside of a sphere around the entity, in equirectangular projection.
+    /// A `radius` of zero or infinity places the layer at infinite distance.
+    /// Requires `XR_KHR_composition_layer_equirect2`.
+    Equirect {
+        radius: f32,
+        /// Horizontal angle in radians of the visible section of the sphere
+        central_horizontal_angle: f32,
+        /// Angle in radians above the horizon the section extends to
+        upper_vertical_angle: f32,
+        /// Angle in radians below the horizon the section extends to, usually negative
+        lower_vertical_angle: f32,
+    },
+}
+
+impl XrLayerShape {
+    /// Returns the name of the extension this shape needs if it isn't enabled on `instance`
+    fn missing_extension(&self, instance: &xr::Instance) -> Option<&'static str> {
+        let exts = instance.exts();
+        match self {
+            XrLayerShape::Quad { .. } => None,
+            XrLayerShape::Cylinder { .. } => exts
+                .khr_composition_layer_cylinder
+                .is_none()
+                .then_some("XR_KHR_composition_layer_cylinder"),
+            XrLayerShape::Equirect { .. } => exts
+                .khr_composition_layer_equirect2
+                .is_none()
+                .then_some("XR_KHR_composition_layer_equirect2"),
+        }
+    }
+}
+
+/// How the compositor blends a layer with the layers behind it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum XrLayerAlpha {
+    /// The alpha channel is ignored, the layer covers everything behind it
+    Opaque,
+    /// The colors are already multiplied with the alpha channel. This is what bevy renders when
+    /// the camera clears to a transparent color and draws alpha blended materials on top.
+    #[default]
+    Premultiplied,
+    /// The colors aren't multiplied with the alpha channel, the compositor does it,
+    /// for layers showing images with straight alpha
+    Unpremultiplied,
+}
+
+impl XrLayerAlpha {
+    pub(crate) fn layer_flags(self) -> CompositionLayerFlags {
+        match self {
+            XrLayerAlpha::Opaque => CompositionLayerFlags::EMPTY,
+            XrLayerAlpha::Premultiplied => CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA,
+            XrLayerAlpha::Unpremultiplied => {
+                CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA
+                    | CompositionLayerFlags::UNPREMULTIPLIED_ALPHA
+            }
+        }
+    }
+}
+
+/// A layer of the [`XrLayerStack`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum XrLayerId {
+    /// The passthrough layer, only submitted while passthrough is running
+    Passthrough,
+    /// The cube layer of the [`XrSkybox`](crate::skybox::XrSkybox), only submitted while it has one
+    Skybox,
+    /// The projection layer the cameras render into
+    Projection,
+    /// The [`XrCompositionLayer`] on this entity
+    Composition(Entity),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub struct XrLayerStackEntry {
+    pub id: XrLayerId,
+    /// Layers with a higher sort key are composited on top
+    pub sort_key: i32,
+    /// Disabled layers aren't submitted, but keep their place and their swapchain
+    pub enabled: bool,
+}
+
+/// The order layers are submitted in, from back to front. Layers with the same sort key stay
+/// in the order they were registered in, so the defaults put passthrough and the skybox at the
+/// back, then the projection layer at 0. [`XrCompositionLayer`]s register themselves with their
+/// [`XrCompositionLayer::sort_order`], so layers with a sort order of 0 end up in front of the
+/// projection layer. Layers can be reordered and disabled at runtime, the changes apply to the
+/// next submitted frame.
+#[derive(Resource, ExtractResource, Clone, Debug, PartialEq)]
+pub struct XrLayerStack {
+    entries: Vec<XrLayerStackEntry>,
+    max_layer_count: Option<u32>,
+}
+
+impl Default for XrLayerStack {
+    fn default() -> Self {
+        let mut stack = Self {
+            entries: Vec::new(),
+            max_layer_count: None,
+        };
+        stack.register(XrLayerId::Passthrough, i32::MIN);
+        stack.register(XrLayerId::Skybox, i32::MIN);
+        stack.register(XrLayerId::Projection, 0);
+        stack
+    }
+}
+
+impl XrLayerStack {
+    /// Adds an enabled layer, or moves a registered layer to `sort_key` keeping its enabled flag
+    pub fn register(&mut self, id: XrLayerId, sort_key: i32) {
+        let enabled = match self.entries.iter().position(|entry| entry.id == id) {
+            Some(index) if self.entries[index].sort_key == sort_key => return,
+            Some(index) => self.entries.remove(index).enabled,
+            None => true,
+        };
+        // after every layer with the same key, so registering keeps the order stable
+        let index = self
+            .entries
+            .partition_point(|entry| entry.sort_key <= sort_key);
+        self.entries.insert(
+            index,
+            XrLayerStackEntry {
+                id,
+                sort_key,
+                enabled,
+            },
+        );
+    }
+
+    pub fn remove(&mut self, id: XrLayerId) -> Option<XrLayerStackEntry> {
+        let index = self.entries.iter().position(|entry| entry.id == id)?;
+        Some(self.entries.remove(index))
+    }
+
+    /// Returns `false` if the layer isn't registered
+    pub fn set_enabled(&mut self, id: XrLayerId, enabled: bool) -> bool {
+        match self.entries.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) => {
+                entry.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, id: XrLayerId) -> Option<&XrLayerStackEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    /// `false` for layers that aren't registered
+    pub fn is_enabled(&self, id: XrLayerId) -> bool {
+        self.get(id).is_some_and(|entry| entry.enabled)
+    }
+
+    /// All layers from back to front
+    pub fn iter(&self) -> impl Iterator<Item = &XrLayerStackEntry> {
+        self.entries.iter()
+    }
+
+    /// The enabled layers from back to front, in the order they are submitted
+    pub fn enabled(&self) -> impl Iterator<Item = XrLayerId> + '_ {
+        self.entries
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| entry.id)
+    }
+
+    /// The most layers the runtime accepts per frame, from the
+    /// [`XrSystemProperties`](crate::system_properties::XrSystemProperties)
+    pub fn max_layer_count(&self) -> Option<u32> {
+        self.max_layer_count
+    }
+
+    pub(crate) fn set_max_layer_count(&mut self, max_layer_count: u32) {
+        self.max_layer_count = Some(max_layer_count);
+    }
+
+    /// Fails if `count` layers are more than the runtime accepts
+    pub fn check_layer_count(&self, count: usize) -> Result<(), XrError> {
+        match self.max_layer_count {
+            Some(max) if count > max as usize => Err(XrError::TooManyLayers { count, max }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A layer that is composited by the runtime instead of being rendered into the eye buffers,
+/// which keeps text and UI sharp under reprojection.
+/// The pose of the layer is taken from the [`GlobalTransform`] of the entity,
+/// hiding the entity stops the layer from being submitted.
+#[derive(Component, Clone, Debug)]
+pub struct XrCompositionLayer {
+    pub shape: XrLayerShape,
+    /// Resolution of the swapchain backing the layer
+    pub resolution: UVec2,
+    /// The sort key of the layer in the [`XrLayerStack`]. Layers with a negative sort order are
+    /// composited behind the main projection layer, all others in front of it, layers with a
+    /// higher sort order are drawn on top
+    pub sort_order: i32,
+    /// How the layer is blended with the layers behind it
+    pub alpha: XrLayerAlpha,
+    /// Camera that renders into the layer, its render target is set automatically
+    pub camera: Option<Entity>,
+    /// Leave the layer out of the recordings, screenshots and casting of the runtime.
+    /// Needs `XR_FB_composition_layer_secure_content`, the layer is captured without it.
+    pub exclude_from_capture: bool,
+}
+
+impl XrCompositionLayer {
+    pub fn quad(size: Vec2, resolution: UVec2) -> Self {
+        Self {
+            shape: XrLayerShape::Quad { size },
+            resolution,
+            sort_order: 0,
+            alpha: XrLayerAlpha::Premultiplied,
+            camera: None,
+            exclude_from_capture: false,
+        }
+    }
+
+    pub fn cylinder(radius: f32, central_angle: f32, aspect_ratio: f32, resolution: UVec2) -> Self {
+        Self {
+            shape: XrLayerShape::Cylinder {
+                radius,
+                central_angle,
+                aspect_ratio,
+            },
+            resolution,
+            sort_order: 0,
+            alpha: XrLayerAlpha::Premultiplied,
+            camera: None,
+            exclude_from_capture: false,
+        }
+    }
+
+    pub fn equirect(
+        radius: f32,
+        central_horizontal_angle: f32,
+        upper_vertical_angle: f32,
+        lower_vertical_angle: f32,
+        resolution: UVec2,
+    ) -> Self {
+        Self {
+            shape: XrLayerShape::Equirect {
+                radius,
+                central_horizontal_angle,
+                upper_vertical_angle,
+                lower_vertical_angle,
+            },
+            resolution,
+            sort_order: 0,
+            alpha: XrLayerAlpha::Premultiplied,
+            camera: None,
+            exclude_from_capture: false,
+        }
+    }
+
+    pub fn with_sort_order(mut self, sort_order: i32) -> Self {
+        self.sort_order = sort_order;
+        self
+    }
+
+    pub fn with_alpha(mut self, alpha: XrLayerAlpha) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn with_camera(mut self, camera: Entity) -> Self {
+        self.camera = Some(camera);
+        self
+    }
+
+    pub fn excluded_from_capture(mut self) -> Self {
+        self.exclude_from_capture = true;
+        self
+    }
+}
+
+#[derive(Bundle)]
+pub struct XrCompositionLayerBundle {
+    pub layer: XrCompositionLayer,
+    pub spatial: SpatialBundle,
+    pub root_transform: RootTransform,
+}
+
+impl XrCompositionLayerBundle {
+    pub fn new(layer: XrCompositionLayer, transform: Transform) -> Self {
+        Self {
+            layer,
+            spatial: SpatialBundle::from_transform(transform),
+            root_transform: default(),
+        }
+    }
+}
+
+/// Added to an [`XrCompositionLayer`] once its swapchain was created
+#[derive(Component, Clone)]
+pub struct XrLayerSwapchain {
+    swapchain: Arc<LayerSwapchain>,
+    resolution: UVec2,
+    handle: ManualTextureViewHandle,
+}
+
+impl XrLayerSwapchain {
+    /// The texture view cameras have to render into to draw into this layer
+    pub fn texture_view_handle(&self) -> ManualTextureViewHandle {
+        self.handle
+    }
+}
+
+pub enum LayerSwapchain {
+    #[cfg(feature = "vulkan")]
+    Vulkan(SwapchainImages<xr::Vulkan>),
+    #[cfg(all(feature = "d3d12", windows))]
+    D3D12(SwapchainImages<xr::D3D12>),
+}
+
+impl LayerSwapchain {
+    pub(crate) fn acquire_image(&self) -> xr::Result<()> {
+        match self {
+            #[cfg(feature = "vulkan")]
+            LayerSwapchain::Vulkan(images) => images.acquire_image(),
+            #[cfg(all(feature = "d3d12", windows))]
+            LayerSwapchain::D3D12(images) => images.acquire_image(),
+        }
+    }
+
+    pub(crate) fn wait_image(&self) -> xr::Result<()> {
+        match self {
+            #[cfg(feature = "vulkan")]
+            LayerSwapchain::Vulkan(images) => images.wait_image(),
+            #[cfg(all(feature = "d3d12", windows))]
+            LayerSwapchain::D3D12(images) => images.wait_image(),
+        }
+    }
+
+    pub(crate) fn release_image(&self) -> xr::Result<()> {
+        match self {
+            #[cfg(feature = "vulkan")]
+            LayerSwapchain::Vulkan(images) => images.release_image(),
+            #[cfg(all(feature = "d3d12", windows))]
+            LayerSwapchain::D3D12(images) => images.release_image(),
+        }
+    }
+
+    pub(crate) fn texture(&self) -> &wgpu::Texture {
+        match self {
+            #[cfg(feature = "vulkan")]
+            LayerSwapchain::Vulkan(images) => images.texture(),
+            #[cfg(all(feature = "d3d12", windows))]
+            LayerSwapchain::D3D12(images) => images.texture(),
+        }
+    }
+
+    pub(crate) fn view(&self) -> TextureView {
+        match self {
+            #[cfg(feature = "vulkan")]
+            LayerSwapchain::Vulkan(images) => images.view(0),
+            #[cfg(all(feature = "d3d12", windows))]
+            LayerSwapchain::D3D12(images) => images.view(0),
+        }
+    }
+
+    pub(crate) fn as_raw(&self) -> xr::sys::Swapchain {
+        match self {
+            #[cfg(feature = "vulkan")]
+            LayerSwapchain::Vulkan(images) => images.as_raw(),
+            #[cfg(all(feature = "d3d12", windows))]
+            LayerSwapchain::D3D12(images) => images.as_raw(),
+        }
+    }
+}
+
+/// Registers new [`XrCompositionLayer`]s in the [`XrLayerStack`], follows changes of their sort
+/// order and removes despawned ones
+fn sync_layer_stack(
+    mut stack: ResMut<XrLayerStack>,
+    layers: Query<(Entity, &XrCompositionLayer), Changed<XrCompositionLayer>>,
+    mut removed: RemovedComponents<XrCompositionLayer>,
+) {
+    for entity in removed.read() {
+        stack.remove(XrLayerId::Composition(entity));
+    }
+    for (entity, layer) in &layers {
+        let id = XrLayerId::Composition(entity);
+        if stack.get(id).map(|entry| entry.sort_key) != Some(layer.sort_order) {
+            stack.register(id, layer.sort_order);
+        }
+    }
+}
+
+static NEXT_TEXTURE_VIEW_HANDLE: AtomicU32 = AtomicU32::new(0x584c_0000);
+
+#[allow(clippy::too_many_arguments)]
+fn create_layer_swapchains(
+    mut commands: Commands,
+    layers: Query<(Entity, &XrCompositionLayer, Option<&XrLayerSwapchain>)>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    render_device: Res<RenderDevice>,
+    format: Res<XrFormat>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+    mut missing_extension_reported: Local<EntityHashSet>,
+) {
+    for (entity, layer, existing) in &layers {
+        if existing.is_some_and(|swapchain| swapchain.resolution == layer.resolution) {
+            continue;
+        }
+        if let Some(extension) = layer.shape.missing_extension(&instance) {
+            // only report once, the entity keeps being retried until it gets a swapchain
+            if !missing_extension_reported.contains(&entity) {
+                error!(
+                    "Unable to create {:?} composition layer: {} is not enabled or not supported by the runtime",
+                    layer.shape, extension
+                );
+                missing_extension_reported.insert(entity);
+            }
+            continue;
+        }
+        let swapchain = match graphics::create_layer_swapchain(
+            &session,
+            &render_device,
+            **format,
+            layer.resolution,
+        ) {
+            Ok(swapchain) => swapchain,
+            Err(err) => {
+                error!("Unable to create composition layer swapchain: {}", err);
+                continue;
+            }
+        };
+        let handle = existing.map_or_else(
+            || ManualTextureViewHandle(NEXT_TEXTURE_VIEW_HANDLE.fetch_add(1, Ordering::Relaxed)),
+            |swapchain| swapchain.handle,
+        );
+        // the main world only needs this to know the size and format of the render target
+        manual_texture_views.insert(
+            handle,
+            ManualTextureView {
+                texture_view: swapchain.view(),
+                size: layer.resolution,
+                format: **format,
+            },
+        );
+        commands.entity(entity).insert(XrLayerSwapchain {
+            swapchain: Arc::new(swapchain),
+            resolution: layer.resolution,
+            handle,
+        });
+    }
+}
+
+fn update_layer_cameras(
+    layers: Query<(
+        Entity,
+        &XrCompositionLayer,
+        &XrLayerSwapchain,
+        Option<&InheritedVisibility>,
+    )>,
+    mut cameras: Query<&mut Camera>,
+    should_render: Res<XrShouldRender>,
+    stack: Res<XrLayerStack>,
+) {
+    for (entity, layer, swapchain, visibility) in &layers {
+        let Some(mut camera) = layer.camera.and_then(|e| cameras.get_mut(e).ok()) else {
+            continue;
+        };
+        if !matches!(camera.target, RenderTarget::TextureView(handle) if handle == swapchain.handle)
+        {
+            camera.target = RenderTarget::TextureView(swapchain.handle);
+        }
+        // the layers aren't submitted while the runtime doesn't show the frame
+        let visible = visibility.map_or(true, |v| v.get())
+            && stack.is_enabled(XrLayerId::Composition(entity))
+            && **should_render;
+        if camera.is_active != visible {
+            camera.is_active = visible;
+        }
+    }
+}
+
+fn cleanup_layer_swapchains(
+    mut commands: Commands,
+    layers: Query<(Entity, &XrCompositionLayer, &XrLayerSwapchain)>,
+    mut cameras: Query<&mut Camera>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+) {
+    for (entity, layer, swapchain) in &layers {
+        manual_texture_views.remove(&swapchain.handle);
+        commands.entity(entity).remove::<XrLayerSwapchain>();
+        if let Some(mut camera) = layer.camera.and_then(|e| cameras.get_mut(e).ok()) {
+            camera.is_active = false;
+        }
+    }
+}
+
+/// A composition layer ready to be submitted, lives in the render world
+#[derive(Component)]
+pub struct ExtractedXrLayer {
+    /// The main world entity of the layer
+    pub(crate) entity: Entity,
+    shape: XrLayerShape,
+    alpha: XrLayerAlpha,
+    swapchain: Arc<LayerSwapchain>,
+    resolution: UVec2,
+    handle: ManualTextureViewHandle,
+    pose: xr::Posef,
+    /// Only set if the runtime can leave the layer out
+    exclude_from_capture: bool,
+}
+
+#[allow(clippy::type_complexity)]
+fn extract_layers(
+    mut commands: Commands,
+    layers: Extract<
+        Query<(
+            Entity,
+            &XrCompositionLayer,
+            &XrLayerSwapchain,
+            &GlobalTransform,
+            Option<&RootTransform>,
+            Option<&InheritedVisibility>,
+        )>,
+    >,
+    stack: Extract<Res<XrLayerStack>>,
+    instance: Extract<Option<Res<XrInstance>>>,
+) {
+    let secure_content = instance.as_ref().is_some_and(|instance| {
+        instance
+            .exts()
+            .fb_composition_layer_secure_content
+            .is_some()
+    });
+    for (entity, layer, swapchain, transform, root, visibility) in &layers {
+        if !visibility.map_or(true, |v| v.get())
+            || !stack.is_enabled(XrLayerId::Composition(entity))
+        {
+            continue;
+        }
+        let root = root.map_or(GlobalTransform::IDENTITY, |root| **root);
+        let relative =
+            Transform::from_matrix(root.compute_matrix().inverse() * transform.compute_matrix());
+        let rotation = relative.rotation.normalize();
+        commands.spawn(ExtractedXrLayer {
+            entity,
+            shape: layer.shape,
+            alpha: layer.alpha,
+            swapchain: swapchain.swapchain.clone(),
+            resolution: swapchain.resolution,
+            handle: swapchain.handle,
+            pose: xr::Posef {
+                orientation: xr::Quaternionf {
+                    x: rotation.x,
+                    y: rotation.y,
+                    z: rotation.z,
+                    w: rotation.w,
+                },
+                position: xr::Vector3f {
+                    x: relative.translation.x,
+                    y: relative.translation.y,
+                    z: relative.translation.z,
+                },
+            },
+            exclude_from_capture: layer.exclude_from_capture && secure_content,
+        });
+    }
+}
+
+fn acquire_layer_images(
+    layers: Query<&ExtractedXrLayer>,
+    format: Res<XrFormat>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+) {
+    let _span = info_span!("xr_acquire_layer_images").entered();
+    for layer in &layers {
+        if let Err(err) = layer
+            .swapchain
+            .acquire_image()
+            .and_then(|_| layer.swapchain.wait_image())
+        {
+            warn!("Unable to acquire composition layer image: {}", err);
+            continue;
+        }
+        manual_texture_views.insert(
+            layer.handle,
+            ManualTextureView {
+                texture_view: layer.swapchain.view(),
+                size: layer.resolution,
+                format: **format,
+            },
+        );
+    }
+}
+
+impl ExtractedXrLayer {
+    pub(crate) fn release_image(&self) -> xr::Result<()> {
+        self.swapchain.release_image()
+    }
+
+    pub(crate) fn to_raw(&self, space: &xr::Space) -> RawCompositionLayer {
+        let sub_image = xr::sys::SwapchainSubImage {
+            swapchain: self.swapchain.as_raw(),
+            image_rect: xr::Rect2Di {
+                offset: xr::Offset2Di { x: 0, y: 0 },
+                extent: xr::Extent2Di {
+                    width: self.resolution.x as _,
+                    height: self.resolution.y as _,
+                },
+            },
+            image_array_index: 0,
+        };
+        let layer_flags = self.alpha.layer_flags();
+        let secure_content = self.exclude_from_capture.then(|| {
+            Box::new(xr::sys::CompositionLayerSecureContentFB {
+                ty: xr::sys::CompositionLayerSecureContentFB::TYPE,
+                next: ptr::null(),
+                flags: xr::sys::CompositionLayerSecureContentFlagsFB::EXCLUDE_LAYER,
+            })
+        });
+        let next = secure_content
+            .as_deref()
+            .map_or(ptr::null(), |secure_content| {
+                secure_content as *const xr::sys::CompositionLayerSecureContentFB as *const _
+            });
+        let layer = match self.shape {
+            XrLayerShape::Quad { size } => RawLayer::Quad(xr::sys::CompositionLayerQuad {
+                ty: xr::sys::CompositionLayerQuad::TYPE,
+                next,
+                layer_flags,
+                space: space.as_raw(),
+                eye_visibility: xr::EyeVisibility::BOTH,
+                sub_image,
+                pose: self.pose,
+                size: xr::Extent2Df {
+                    width: size.x,
+                    height: size.y,
+                },
+            }),
+            XrLayerShape::Cylinder {
+                radius,
+                central_angle,
+                aspect_ratio,
+            } => RawLayer::Cylinder(xr::sys::CompositionLayerCylinderKHR {
+                ty: xr::sys::CompositionLayerCylinderKHR::TYPE,
+                next,
+                layer_flags,
+                space: space.as_raw(),
+                eye_visibility: xr::EyeVisibility::BOTH,
+                sub_image,
+                pose: self.pose,
+                radius,
+                central_angle,
+                aspect_ratio,
+            }),
+            XrLayerShape::Equirect {
+                radius,
+                central_horizontal_angle,
+                upper_vertical_angle,
+                lower_vertical_angle,
+            } => RawLayer::Equirect(xr::sys::CompositionLayerEquirect2KHR {
+                ty: xr::sys::CompositionLayerEquirect2KHR::TYPE,
+                next,
+                layer_flags,
+                space: space.as_raw(),
+                eye_visibility: xr::EyeVisibility::BOTH,
+                sub_image,
+                pose: self.pose,
+                radius,
+                central_horizontal_angle,
+                upper_vertical_angle,
+                lower_vertical_angle,
+            }),
+        };
+        RawCompositionLayer {
+            layer,
+            _secure_content: secure_content,
+        }
+    }
+}
+
+enum RawLayer {
+    Quad(xr::sys::CompositionLayerQuad),
+    Cylinder(xr::sys::CompositionLayerCylinderKHR),
+    Equirect(xr::sys::CompositionLayerEquirect2KHR),
+    Cube(xr::sys::CompositionLayerCubeKHR),
+}
+
+/// A composition layer in the form it is passed to the runtime
+pub struct RawCompositionLayer {
+    layer: RawLayer,
+    /// Chained to the layer, boxed so the `next` pointer stays valid when the layer is moved
+    _secure_content: Option<Box<xr::sys::CompositionLayerSecureContentFB>>,
+}
+
+// SAFETY: the layers only contain handles, values and `next` pointers that are null or point to
+// the boxed structs owned by the layer
+unsafe impl Send for RawCompositionLayer {}
+unsafe impl Sync for RawCompositionLayer {}
+
+impl RawCompositionLayer {
+    /// A cube layer, `swapchain` has to be a cube swapchain
+    pub(crate) fn cube(
+        swapchain: &LayerSwapchain,
+        orientation: Quat,
+        space: &xr::Space,
+    ) -> RawCompositionLayer {
+        let orientation = orientation.normalize();
+        RawCompositionLayer {
+            layer: RawLayer::Cube(xr::sys::CompositionLayerCubeKHR {
+                ty: xr::sys::CompositionLayerCubeKHR::TYPE,
+                next: ptr::null(),
+                layer_flags: CompositionLayerFlags::EMPTY,
+                space: space.as_raw(),
+                eye_visibility: xr::EyeVisibility::BOTH,
+                swapchain: swapchain.as_raw(),
+                image_array_index: 0,
+                orientation: xr::Quaternionf {
+                    x: orientation.x,
+                    y: orientation.y,
+                    z: orientation.z,
+                    w: orientation.w,
+                },
+            }),
+            _secure_content: None,
+        }
+    }
+
+    pub(crate) fn as_base<G: xr::Graphics>(&self) -> &CompositionLayerBase<'_, G> {
+        // SAFETY: all layer structs start with the common composition layer header
+        unsafe {
+            match &self.layer {
+                RawLayer::Quad(quad) => mem::transmute(quad),
+                RawLayer::Cylinder(cylinder) => mem::transmute(cylinder),
+                RawLayer::Equirect(equirect) => mem::transmute(equirect),
+                RawLayer::Cube(cube) => mem::transmute(cube),
+            }
+        }
+    }
+}
+
+/// A layer of a frame, in the order it is submitted in
+pub enum FrameLayer {
+    Passthrough,
+    Projection,
+    Composition(RawCompositionLayer),
+}
@@ -1,75 +1,238 @@
+pub mod anchors;
+pub mod android;
+pub mod audio;
+pub mod calibration;
+pub mod capture;
+pub mod clock;
+pub mod comfort_vignette;
+pub mod debug_utils;
+pub mod device_loss;
+pub mod display_color_space;
+pub mod display_refresh_rate;
+pub mod dynamic_resolution;
+pub mod error;
+pub mod external_rendering;
+pub mod foveation;
+pub mod frame_diagnostics;
+pub mod frame_hooks;
+pub mod gpu_timing;
 pub mod graphics;
 pub mod input;
+pub mod input_script;
+pub mod latency;
+pub mod layers;
+pub mod mirror;
+pub mod overlay;
 pub mod passthrough;
+pub mod performance_hud;
+pub mod performance_settings;
+pub mod play_bounds;
 pub mod prelude;
+pub mod raw;
+pub mod recording;
 pub mod resource_macros;
 pub mod resources;
+pub mod scene;
+pub mod secondary_view;
+pub mod session_recovery;
+#[cfg(feature = "settings-profile")]
+pub mod settings;
+pub mod simulator;
+pub mod skybox;
+pub mod startup;
+pub mod swapchain_resize;
+pub mod system_properties;
+pub mod threading;
+pub mod tracked_snapshot;
+pub mod user_presence;
+pub mod visibility_mask;
+pub mod world_ui;
 pub mod xr_init;
 pub mod xr_input;
+pub mod xr_state;
 
+use std::mem::MaybeUninit;
 use std::sync::atomic::AtomicBool;
 
-use crate::xr_init::{StartXrSession, XrInitPlugin};
+use crate::anchors::XrSpatialEntityEvent;
+use crate::xr_init::{XrInitPlugin, XrLifecycleEvents};
+use crate::xr_input::interaction_profiles::XrInteractionProfileChanged;
 use crate::xr_input::oculus_touch::ActionSets;
 use crate::xr_input::trackers::verify_quat;
+use crate::xr_input::vive_trackers::XrViveTrackerConnected;
+use android::XrAndroidPlugin;
 use bevy::app::{AppExit, PluginGroupBuilder};
 use bevy::core::TaskPoolThreadAssignmentPolicy;
-use bevy::ecs::system::SystemState;
+use bevy::ecs::system::{SystemParam, SystemState};
 use bevy::prelude::*;
 use bevy::render::camera::{ManualTextureView, ManualTextureViewHandle, ManualTextureViews};
+use bevy::render::extract_resource::ExtractResourcePlugin;
 use bevy::render::pipelined_rendering::PipelinedRenderingPlugin;
 use bevy::render::renderer::{render_system, RenderInstance};
-use bevy::render::settings::RenderCreation;
+use bevy::render::settings::{RenderCreation, WgpuSettings};
 use bevy::render::{Render, RenderApp, RenderPlugin, RenderSet};
 use bevy::window::{PresentMode, PrimaryWindow, RawHandleWrapper};
-use graphics::extensions::XrExtensions;
-use graphics::{XrAppInfo, XrPreferdBlendMode};
-use input::XrInput;
+use capture::FrameCapturePlugin;
+use debug_utils::XrDebugUtilsPlugin;
+use device_loss::XrDeviceLossPlugin;
+use display_color_space::DisplayColorSpacePlugin;
+use display_refresh_rate::{DisplayRefreshRatePlugin, XrDisplayRefreshRateChanged};
+use dynamic_resolution::{XrDynamicResolutionPlugin, XrViewportScale};
+use error::{send_errors, XrError, XrErrorEvent, XrErrors, XrResultExt};
+use external_rendering::XrExternalRendering;
+use foveation::FoveationPlugin;
+use frame_diagnostics::XrFrameDiagnosticsPlugin;
+use frame_hooks::XrFrameHooks;
+use gpu_timing::XrGpuTimingPlugin;
+use graphics::extensions::{XrEnabledExtensions, XrExtensions};
+use graphics::{XrApiLayers, XrAppInfo, XrPreferdBlendMode, XrSessionConfig, XrViewConfig};
+use input::{XrInput, XrReferenceSpaceChanged};
+use layers::{
+    CompositionLayerPlugin, ExtractedXrLayer, FrameLayer, RawCompositionLayer, XrLayerAlpha,
+    XrLayerId, XrLayerStack,
+};
+use mirror::{MirrorPlugin, XrMirrorMode};
 use openxr as xr;
+use overlay::XrMainSessionVisibilityChanged;
 use passthrough::{PassthroughPlugin, XrPassthroughLayer, XrPassthroughState};
+use performance_settings::{PerformanceSettingsPlugin, XrPerformanceNotification};
+use play_bounds::PlayBoundsPlugin;
 use resources::*;
+use secondary_view::{
+    enabled_secondary_view_types, ExtractedSecondaryView, SecondaryViewPlugin, XrSecondaryViewState,
+};
+use session_recovery::XrSessionRecoveryPlugin;
+use simulator::XrSimulatorPlugin;
+use skybox::{ExtractedXrSkybox, XrSkyboxPlugin};
+use startup::{XrStartupEvent, XrStartupPolicy};
+use swapchain_resize::XrSwapchainResizePlugin;
+use user_presence::{XrPolledEvent, XrUserPresenceChanged, XrUserPresencePlugin};
+use visibility_mask::{VisibilityMaskPlugin, XrVisibilityMaskChanged, XrVisibilityMasks};
 use xr_init::{
-    xr_after_wait_only, xr_only, xr_render_only, CleanupRenderWorld, CleanupXrData,
-    ExitAppOnSessionExit, SetupXrData, StartSessionOnStartup, XrCleanup, XrEarlyInitPlugin,
-    XrHasWaited, XrPostCleanup, XrShouldRender, XrStatus,
+    xr_after_wait_only, xr_only, xr_render_only, xr_unavailable_only, CleanupRenderWorld,
+    CleanupXrData, ExitAppOnSessionExit, SetupXrData, StartSessionOnStartup, XrBeginFrame,
+    XrEarlyInitPlugin, XrEndFrame, XrHasWaited, XrPollEvents, XrPostCleanup, XrSessionState,
+    XrSessionStateChanged, XrShouldRender, XrStatus, XrUnavailableReason, XrWaitFrame,
 };
-use xr_input::actions::XrActionsPlugin;
+use xr_input::actions::{XrActionSync, XrActionsPlugin};
+use xr_input::controller_models::XrControllerModelSourcePlugin;
 use xr_input::hands::emulated::HandEmulationPlugin;
 use xr_input::hands::hand_tracking::HandTrackingPlugin;
 use xr_input::hands::HandPlugin;
-use xr_input::xr_camera::XrCameraPlugin;
-use xr_input::XrInputPlugin;
+use xr_input::haptics::XrHapticsPlugin;
+use xr_input::xr_camera::{XrCameraPlugin, XrClipPlanes};
+use xr_input::{XrInputPlugin, XrTrackingUpdate};
 
+/// The view configuration that is queried before a session exists, every runtime supports it
 const VIEW_TYPE: xr::ViewConfigurationType = xr::ViewConfigurationType::PRIMARY_STEREO;
 
 pub const LEFT_XR_TEXTURE_HANDLE: ManualTextureViewHandle = ManualTextureViewHandle(1208214591);
 pub const RIGHT_XR_TEXTURE_HANDLE: ManualTextureViewHandle = ManualTextureViewHandle(3383858418);
+/// The focus views of [`XrViewConfig::Quad`]
+pub const LEFT_FOCUS_XR_TEXTURE_HANDLE: ManualTextureViewHandle =
+    ManualTextureViewHandle(2946116733);
+pub const RIGHT_FOCUS_XR_TEXTURE_HANDLE: ManualTextureViewHandle =
+    ManualTextureViewHandle(1734927264);
+/// The texture of each view, in the order of [`XrViews`]
+pub const XR_TEXTURE_HANDLES: [ManualTextureViewHandle; 4] = [
+    LEFT_XR_TEXTURE_HANDLE,
+    RIGHT_XR_TEXTURE_HANDLE,
+    LEFT_FOCUS_XR_TEXTURE_HANDLE,
+    RIGHT_FOCUS_XR_TEXTURE_HANDLE,
+];
 
 /// Adds OpenXR support to an App
 pub struct OpenXrPlugin {
     pub backend_preference: Vec<Backend>,
     pub reqeusted_extensions: XrExtensions,
+    /// Fail to create the instance if one of the requested extensions isn't supported,
+    /// instead of running without it
+    pub strict_extensions: bool,
+    pub api_layers: XrApiLayers,
     pub prefered_blend_mode: XrPreferdBlendMode,
     pub app_info: XrAppInfo,
+    pub session_config: XrSessionConfig,
     pub synchronous_pipeline_compilation: bool,
+    /// What the primary window shows while a session is running,
+    /// can be changed at runtime through the [`XrMirrorMode`] resource
+    pub mirror: XrMirrorMode,
+    /// What to do when the runtime or the headset isn't available at startup
+    pub startup_policy: XrStartupPolicy,
+    /// Where poses are predicted relative to the display time,
+    /// can be changed at runtime through the [`XrPredictionOffset`] resource
+    pub prediction_offset: XrPredictionOffset,
+    /// Features and limits the app needs, added to what the XR rendering needs. The runtime
+    /// picks the adapter, so the instance fails to initialize if it doesn't support them,
+    /// [`WgpuSettings::power_preference`] and [`WgpuSettings::backends`] only apply without XR.
+    /// `None` creates the device with only the features and limits the XR rendering needs.
+    pub wgpu_settings: Option<WgpuSettings>,
 }
 
 impl Plugin for OpenXrPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(XrSessionRunning::new(AtomicBool::new(false)));
         app.insert_resource(ExitAppOnSessionExit::default());
+        app.insert_resource(self.session_config.clone());
+        app.add_plugins(ExtractResourcePlugin::<XrSessionConfig>::default());
+        app.init_resource::<XrLayerStack>();
+        app.add_plugins(ExtractResourcePlugin::<XrLayerStack>::default());
+        app.init_resource::<XrFrameHooks>();
+        app.add_plugins(ExtractResourcePlugin::<XrFrameHooks>::default());
+        app.insert_resource(self.mirror);
+        app.insert_resource(self.prediction_offset);
+        app.init_resource::<XrViewInfo>();
+        app.register_type::<XrViewInfo>();
+        app.register_type::<XrPredictionOffset>();
+        app.add_event::<XrReferenceSpaceChanged>();
+        app.add_event::<XrDisplayRefreshRateChanged>();
+        app.add_event::<XrMainSessionVisibilityChanged>();
+        app.add_event::<XrVisibilityMaskChanged>();
+        app.add_event::<XrPerformanceNotification>();
+        app.add_event::<XrInteractionProfileChanged>();
+        app.add_event::<XrViveTrackerConnected>();
+        app.add_event::<XrSpatialEntityEvent>();
+        app.add_event::<XrUserPresenceChanged>();
+        let errors = XrErrors::default();
+        app.insert_resource(errors.clone());
+        app.add_event::<XrErrorEvent>();
+        app.init_resource::<XrFrameTime>();
+        app.register_type::<XrFrameTime>().register_type::<XrTime>();
+        let mut reqeusted_extensions = self.reqeusted_extensions.clone();
+        if self.session_config.reference_space == xr::ReferenceSpaceType::UNBOUNDED_MSFT {
+            reqeusted_extensions.enable_unbounded_reference_space();
+        }
+        if self.session_config.view_config == XrViewConfig::Quad {
+            reqeusted_extensions.enable_quad_views();
+        }
+        app.add_event::<XrStartupEvent>();
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut startup_events = Vec::new();
+        #[cfg(not(target_arch = "wasm32"))]
+        let initialized = self.startup_policy.run(
+            || {
+                graphics::initialize_xr_instance(
+                    &self.backend_preference,
+                    SystemState::<Query<&RawHandleWrapper, With<PrimaryWindow>>>::new(
+                        &mut app.world,
+                    )
+                    .get(&app.world)
+                    .get_single()
+                    .ok()
+                    .cloned(),
+                    reqeusted_extensions.clone(),
+                    self.strict_extensions,
+                    &self.api_layers,
+                    self.prefered_blend_mode,
+                    self.app_info.clone(),
+                    self.wgpu_settings.as_ref(),
+                )
+            },
+            &mut startup_events,
+        );
         #[cfg(not(target_arch = "wasm32"))]
-        match graphics::initialize_xr_instance(
-            &self.backend_preference,
-            SystemState::<Query<&RawHandleWrapper, With<PrimaryWindow>>>::new(&mut app.world)
-                .get(&app.world)
-                .get_single()
-                .ok()
-                .cloned(),
-            self.reqeusted_extensions.clone(),
-            self.prefered_blend_mode,
-            self.app_info.clone(),
-        ) {
+        app.world.send_event_batch(startup_events);
+        #[cfg(not(target_arch = "wasm32"))]
+        match initialized {
             Ok((
                 xr_instance,
                 oxr_session_setup_info,
@@ -85,9 +248,47 @@ impl Plugin for OpenXrPlugin {
                 warn!("Starting with OpenXR Instance");
                 app.insert_resource(xr_instance.clone());
                 app.insert_resource(blend_mode);
+                match xr_instance
+                    .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+                    .and_then(|system| {
+                        xr_instance.enumerate_environment_blend_modes(system, VIEW_TYPE)
+                    }) {
+                    Ok(blend_modes) => {
+                        app.insert_resource(XrSupportedBlendModes::new(blend_modes));
+                    }
+                    Err(err) => warn!("Unable to enumerate environment blend modes: {}", err),
+                }
+                match xr_instance.entry().enumerate_extensions() {
+                    Ok(available) => {
+                        app.insert_resource(XrEnabledExtensions::new(
+                            &reqeusted_extensions,
+                            &available.into(),
+                        ));
+                    }
+                    Err(err) => warn!("Unable to enumerate the available extensions: {}", err),
+                }
+                match xr_instance.runtime_info() {
+                    Ok(runtime_info) => {
+                        info!("Using OpenXR runtime {}", runtime_info);
+                        app.insert_resource(runtime_info);
+                    }
+                    Err(err) => warn!("Unable to get the runtime properties: {}", err),
+                }
+                match xr_instance.hmd_system_properties() {
+                    Ok(system_properties) => {
+                        info!("Running on {}", system_properties.name);
+                        app.world
+                            .resource_mut::<XrLayerStack>()
+                            .set_max_layer_count(system_properties.max_layer_count);
+                        app.insert_resource(system_properties);
+                    }
+                    Err(err) => warn!("Unable to get the system properties: {}", err),
+                }
                 app.insert_resource(ActionSets(vec![]));
                 app.insert_resource(xr_instance);
                 app.insert_resource(blend_mode);
+                info!("Using the {:?} backend", oxr_session_setup_info.backend());
+                app.insert_resource(oxr_session_setup_info.backend());
                 app.insert_non_send_resource(oxr_session_setup_info);
                 let render_instance = RenderInstance(instance.into());
                 app.insert_resource(render_instance.clone());
@@ -106,12 +307,26 @@ impl Plugin for OpenXrPlugin {
                 // app.world.send_event(StartXrSession);
             }
             Err(err) => {
-                warn!("OpenXR Instance Failed to initialize: {}", err);
+                let reason = XrUnavailableReason::from_error(&err);
+                if reason == XrUnavailableReason::Simulated {
+                    info!("Skipping OpenXR, the simulator runs instead");
+                } else if self.startup_policy == XrStartupPolicy::FailFast {
+                    error!(
+                        "OpenXR Instance Failed to initialize ({:?}), exiting: {}",
+                        reason, err
+                    );
+                    app.world.send_event(AppExit);
+                } else {
+                    error!(
+                        "OpenXR Instance Failed to initialize ({:?}), continuing without XR: {}",
+                        reason, err
+                    );
+                }
                 app.add_plugins(RenderPlugin {
+                    render_creation: self.wgpu_settings.clone().unwrap_or_default().into(),
                     synchronous_pipeline_compilation: self.synchronous_pipeline_compilation,
-                    ..Default::default()
                 });
-                app.insert_resource(XrStatus::NoInstance);
+                app.insert_resource(XrStatus::Unavailable(reason));
             }
         }
         #[cfg(target_arch = "wasm32")]
@@ -120,47 +335,73 @@ impl Plugin for OpenXrPlugin {
             app.insert_resource(XrStatus::Disabled);
         }
         app.add_systems(XrPostCleanup, clean_resources);
+        app.add_systems(
+            PostUpdate,
+            xr_validate_blend_mode.run_if(resource_exists_and_changed::<XrEnvironmentBlendMode>),
+        );
+        app.add_systems(
+            PostUpdate,
+            xr_validate_msaa
+                .run_if(xr_only())
+                .run_if(resource_exists_and_changed::<Msaa>),
+        );
         app.add_systems(XrPostCleanup, || info!("Main World Post Cleanup!"));
+        app.configure_sets(
+            PreUpdate,
+            (XrPollEvents, XrWaitFrame, XrActionSync, XrTrackingUpdate).chain(),
+        );
         app.add_systems(
             PreUpdate,
-            xr_poll_events.run_if(|status: Res<XrStatus>| *status != XrStatus::NoInstance),
+            (
+                xr_poll_events.run_if(not(xr_unavailable_only())),
+                send_errors,
+            )
+                .chain()
+                .in_set(XrPollEvents),
         );
         app.add_systems(
             PreUpdate,
             (
                 xr_reset_per_frame_resources,
                 xr_wait_frame.run_if(xr_only()),
+                mirror_app_time.run_if(not(xr_after_wait_only())),
                 locate_views.run_if(xr_only()),
                 apply_deferred,
             )
                 .chain()
-                .after(xr_poll_events),
+                .in_set(XrWaitFrame),
         );
         let render_app = app.sub_app_mut(RenderApp);
-        render_app.add_systems(
+        render_app.init_resource::<XrViewTargets>();
+        render_app.init_resource::<XrFrameInFlight>();
+        render_app.insert_resource(errors);
+        render_app.configure_sets(
             Render,
-            xr_pre_frame
-                .run_if(xr_only())
-                .run_if(xr_after_wait_only())
-                .run_if(xr_render_only())
-                .before(render_system)
-                .after(RenderSet::ExtractCommands),
+            (
+                XrBeginFrame
+                    .after(RenderSet::ExtractCommands)
+                    .before(render_system),
+                XrEndFrame.in_set(RenderSet::Cleanup),
+            )
+                .chain(),
         );
         render_app.add_systems(
             Render,
-            xr_end_frame
+            (xr_begin_frame, xr_pre_frame.run_if(xr_render_only()))
+                .chain()
                 .run_if(xr_only())
                 .run_if(xr_after_wait_only())
-                .run_if(xr_render_only())
-                .in_set(RenderSet::Cleanup),
+                .in_set(XrBeginFrame),
         );
         render_app.add_systems(
             Render,
-            xr_skip_frame
+            (
+                xr_end_frame.run_if(xr_render_only()),
+                xr_skip_frame.run_if(not(xr_render_only())),
+            )
                 .run_if(xr_only())
                 .run_if(xr_after_wait_only())
-                .run_if(not(xr_render_only()))
-                .in_set(RenderSet::Cleanup),
+                .in_set(XrEndFrame),
         );
         render_app.add_systems(
             Render,
@@ -171,15 +412,43 @@ impl Plugin for OpenXrPlugin {
     }
 }
 
-#[cfg(all(not(feature = "vulkan"), not(all(feature = "d3d12", windows))))]
+#[cfg(all(
+    not(feature = "vulkan"),
+    not(all(feature = "d3d12", windows)),
+    not(all(feature = "gles", target_os = "android"))
+))]
 compile_error!("At least one platform-compatible backend feature must be enabled.");
 
-#[derive(Debug)]
+/// The graphics apis the OpenXR instance and the wgpu device can be created with.
+/// The first backend of [`DefaultXrPlugins::backend_preference`] the runtime supports is used,
+/// it's inserted as a resource once the instance is created.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Resource)]
 pub enum Backend {
     #[cfg(feature = "vulkan")]
     Vulkan,
     #[cfg(all(feature = "d3d12", windows))]
     D3D12,
+    /// Only available on Android, needs the `gles` feature.
+    /// Composition layers and secondary views aren't supported yet.
+    #[cfg(all(feature = "gles", target_os = "android"))]
+    Gles,
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    /// Parses the name of a backend, like `vulkan` or `d3d12`, ignoring case
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            #[cfg(feature = "vulkan")]
+            "vulkan" => Ok(Backend::Vulkan),
+            #[cfg(all(feature = "d3d12", windows))]
+            "d3d12" => Ok(Backend::D3D12),
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            "gles" => Ok(Backend::Gles),
+            _ => Err(format!("{:?} isn't a backend this build supports", name)),
+        }
+    }
 }
 
 fn clean_resources_render(cmds: &mut World) {
@@ -187,12 +456,16 @@ fn clean_resources_render(cmds: &mut World) {
     cmds.remove_resource::<XrSession>();
     cmds.remove_resource::<XrResolution>();
     cmds.remove_resource::<XrFormat>();
+    cmds.remove_resource::<XrColorSpace>();
     // cmds.remove_resource::<XrSessionRunning>();
     cmds.remove_resource::<XrFrameWaiter>();
     cmds.remove_resource::<XrSwapchain>();
     cmds.remove_resource::<XrInput>();
     cmds.remove_resource::<XrViews>();
     cmds.remove_resource::<XrFrameState>();
+    cmds.remove_resource::<XrVisibilityMasks>();
+    cmds.resource_mut::<XrViewTargets>().0.clear();
+    *cmds.resource_mut::<XrFrameInFlight>() = XrFrameInFlight::Idle;
     cmds.remove_resource::<CleanupRenderWorld>();
     // unsafe {
     //     (session.instance().fp().destroy_session)(session.as_raw());
@@ -203,12 +476,16 @@ fn clean_resources(cmds: &mut World) {
     cmds.remove_resource::<XrSession>();
     cmds.remove_resource::<XrResolution>();
     cmds.remove_resource::<XrFormat>();
+    cmds.remove_resource::<XrColorSpace>();
     // cmds.remove_resource::<XrSessionRunning>();
     cmds.remove_resource::<XrFrameWaiter>();
+    cmds.remove_resource::<XrFrameWaiterThread>();
     cmds.remove_resource::<XrSwapchain>();
     cmds.remove_resource::<XrInput>();
     cmds.remove_resource::<XrViews>();
+    cmds.insert_resource(XrViewInfo::default());
     cmds.remove_resource::<XrFrameState>();
+    cmds.remove_resource::<XrSupportedMsaaSamples>();
     // cmds.remove_resource::<CleanupRenderWorld>();
     // unsafe {
     //     (session.instance().fp().destroy_session)(session.as_raw());
@@ -216,44 +493,107 @@ fn clean_resources(cmds: &mut World) {
     warn!("Cleanup Resources");
 }
 
+/// Switches back to OPAQUE when the environment blend mode was set to one the runtime doesn't support
+fn xr_validate_blend_mode(
+    mut blend_mode: ResMut<XrEnvironmentBlendMode>,
+    supported: Option<Res<XrSupportedBlendModes>>,
+) {
+    let Some(supported) = supported else {
+        return;
+    };
+    if !supported.contains(&blend_mode) {
+        warn!(
+            "Environment blend mode {:?} is not supported, using OPAQUE",
+            **blend_mode
+        );
+        **blend_mode = xr::EnvironmentBlendMode::OPAQUE;
+    }
+}
+
+/// Lowers the sample count when [`Msaa`] was set to one the xr swapchain can't be rendered with
+fn xr_validate_msaa(mut msaa: ResMut<Msaa>, supported: Option<Res<XrSupportedMsaaSamples>>) {
+    let Some(supported) = supported else {
+        return;
+    };
+    if supported.contains(&msaa.samples()) {
+        return;
+    }
+    let samples = supported
+        .iter()
+        .copied()
+        .filter(|&samples| samples <= msaa.samples())
+        .max()
+        .unwrap_or(1);
+    warn!(
+        "MSAA with {} samples is not supported, using {} samples",
+        msaa.samples(),
+        samples
+    );
+    *msaa = xr_init::msaa_from_samples(samples);
+}
+
 fn xr_skip_frame(
     xr_swapchain: Res<XrSwapchain>,
     xr_frame_state: Res<XrFrameState>,
     environment_blend_mode: Res<XrEnvironmentBlendMode>,
+    mut in_flight: ResMut<XrFrameInFlight>,
+    errors: Res<XrErrors>,
+    hooks: Res<XrFrameHooks>,
 ) {
-    let swapchain: &Swapchain = &xr_swapchain;
-    match swapchain {
-        #[cfg(feature = "vulkan")]
-        Swapchain::Vulkan(swap) => &swap
-            .stream
-            .lock()
-            .unwrap()
-            .end(
-                xr_frame_state.predicted_display_time,
-                **environment_blend_mode,
-                &[],
-            )
-            .unwrap(),
-        #[cfg(all(feature = "d3d12", windows))]
-        Swapchain::D3D12(swap) => &swap
-            .stream
-            .lock()
-            .unwrap()
-            .end(
-                xr_frame_state.predicted_display_time,
-                **environment_blend_mode,
-                &[],
-            )
-            .unwrap(),
-    };
+    if *in_flight == XrFrameInFlight::Idle {
+        return;
+    }
+    *in_flight = XrFrameInFlight::Idle;
+    if let Err(err) = xr_swapchain
+        .end_without_layers(
+            &hooks,
+            xr_frame_state.predicted_display_time,
+            **environment_blend_mode,
+        )
+        .call("xrEndFrame")
+    {
+        errors.frame(err);
+    }
 }
 
 pub struct DefaultXrPlugins {
+    /// The backends to try in order, defaults to Vulkan and then D3D12 on windows or GLES on Android
     pub backend_preference: Vec<Backend>,
     pub reqeusted_extensions: XrExtensions,
+    /// Fail to create the instance if one of the requested extensions isn't supported,
+    /// instead of running without it
+    pub strict_extensions: bool,
+    pub api_layers: XrApiLayers,
     pub prefered_blend_mode: XrPreferdBlendMode,
     pub app_info: XrAppInfo,
+    pub session_config: XrSessionConfig,
     pub synchronous_pipeline_compilation: bool,
+    /// What the primary window shows while a session is running,
+    /// can be changed at runtime through the [`XrMirrorMode`] resource
+    pub mirror: XrMirrorMode,
+    /// Keep bevy's [`PipelinedRenderingPlugin`], so the render world renders a frame while the
+    /// main world already simulates the next one. `xrWaitFrame` still paces the main world,
+    /// the frame is begun and submitted in the render world.
+    pub pipelined_rendering: bool,
+    /// What to do when the runtime or the headset isn't available at startup
+    pub startup_policy: XrStartupPolicy,
+    /// Log the messages of the runtime and of validation layers with [`XrDebugUtilsPlugin`]
+    pub debug_utils: bool,
+    /// Measure the gpu time of the eyes with [`XrGpuTimingPlugin`]
+    pub gpu_timing: bool,
+    /// Permissions requested at startup on Android, see [`XrAndroidPlugin`]
+    pub android_permissions: Vec<String>,
+    /// Where poses are predicted relative to the display time,
+    /// can be changed at runtime through the [`XrPredictionOffset`] resource
+    pub prediction_offset: XrPredictionOffset,
+    /// Simulate a headset with [`XrSimulatorPlugin`] when none is available,
+    /// `BEVY_OXR_SIMULATOR=1` also skips OpenXR when there is one
+    pub simulator: bool,
+    /// Features and limits the app needs, added to what the XR rendering needs. The runtime
+    /// picks the adapter, so the instance fails to initialize if it doesn't support them,
+    /// [`WgpuSettings::power_preference`] and [`WgpuSettings::backends`] only apply without XR.
+    /// `None` creates the device with only the features and limits the XR rendering needs.
+    pub wgpu_settings: Option<WgpuSettings>,
 }
 impl Default for DefaultXrPlugins {
     fn default() -> Self {
@@ -263,18 +603,41 @@ impl Default for DefaultXrPlugins {
                 Backend::Vulkan,
                 #[cfg(all(feature = "d3d12", windows))]
                 Backend::D3D12,
+                #[cfg(all(feature = "gles", target_os = "android"))]
+                Backend::Gles,
             ],
             reqeusted_extensions: default(),
+            strict_extensions: false,
+            api_layers: default(),
             prefered_blend_mode: default(),
             app_info: default(),
+            session_config: default(),
             synchronous_pipeline_compilation: false,
+            mirror: default(),
+            pipelined_rendering: false,
+            startup_policy: default(),
+            debug_utils: false,
+            gpu_timing: false,
+            android_permissions: Vec::new(),
+            prediction_offset: default(),
+            simulator: false,
+            wgpu_settings: None,
         }
     }
 }
 
 impl PluginGroup for DefaultXrPlugins {
-    fn build(self) -> PluginGroupBuilder {
-        DefaultPlugins
+    fn build(mut self) -> PluginGroupBuilder {
+        if self.debug_utils {
+            self.reqeusted_extensions.enable_debug_utils();
+        }
+        if simulator::simulator_requested() {
+            self.startup_policy = XrStartupPolicy::Simulate;
+        }
+        if self.startup_policy == XrStartupPolicy::Simulate {
+            self.simulator = true;
+        }
+        let mut plugins = DefaultPlugins
             .build()
             .set(TaskPoolPlugin {
                 task_pool_options: TaskPoolOptions {
@@ -287,25 +650,63 @@ impl PluginGroup for DefaultXrPlugins {
                     ..default()
                 },
             })
-            .disable::<RenderPlugin>()
-            .disable::<PipelinedRenderingPlugin>()
+            .disable::<RenderPlugin>();
+        if !self.pipelined_rendering {
+            plugins = plugins.disable::<PipelinedRenderingPlugin>();
+        }
+        if self.debug_utils {
+            plugins = plugins.add(XrDebugUtilsPlugin::default());
+        }
+        if self.simulator {
+            plugins = plugins.add(XrSimulatorPlugin);
+        }
+        plugins = plugins
             .add_before::<RenderPlugin, _>(OpenXrPlugin {
                 backend_preference: self.backend_preference,
                 prefered_blend_mode: self.prefered_blend_mode,
                 reqeusted_extensions: self.reqeusted_extensions,
+                strict_extensions: self.strict_extensions,
+                api_layers: self.api_layers,
                 app_info: self.app_info.clone(),
+                session_config: self.session_config,
                 synchronous_pipeline_compilation: self.synchronous_pipeline_compilation,
+                mirror: self.mirror,
+                startup_policy: self.startup_policy,
+                prediction_offset: self.prediction_offset,
+                wgpu_settings: self.wgpu_settings,
             })
             .add_after::<OpenXrPlugin, _>(XrInitPlugin)
             .add(XrInputPlugin)
             .add(XrActionsPlugin)
+            .add(XrHapticsPlugin)
             .add(XrCameraPlugin)
             .add_before::<OpenXrPlugin, _>(XrEarlyInitPlugin)
+            .add_before::<AssetPlugin, _>(XrControllerModelSourcePlugin)
             .add(HandPlugin)
             .add(HandTrackingPlugin)
             .add(HandEmulationPlugin)
             .add(PassthroughPlugin)
+            .add(CompositionLayerPlugin)
+            .add(XrSkyboxPlugin)
+            .add(XrUserPresencePlugin::default())
+            .add(FoveationPlugin)
+            .add(DisplayRefreshRatePlugin)
+            .add(DisplayColorSpacePlugin)
+            .add(PerformanceSettingsPlugin)
+            .add(XrFrameDiagnosticsPlugin)
+            .add(XrDynamicResolutionPlugin)
+            .add(XrSwapchainResizePlugin)
+            .add(SecondaryViewPlugin)
+            .add(MirrorPlugin)
+            .add(FrameCapturePlugin)
+            .add(PlayBoundsPlugin)
+            .add(VisibilityMaskPlugin)
             .add(XrResourcePlugin)
+            .add(XrSessionRecoveryPlugin)
+            .add(XrDeviceLossPlugin)
+            .add(XrAndroidPlugin {
+                permissions: self.android_permissions,
+            })
             .add(StartSessionOnStartup)
             .set(WindowPlugin {
                 #[cfg(not(target_os = "android"))]
@@ -325,7 +726,12 @@ impl PluginGroup for DefaultXrPlugins {
                 #[cfg(target_os = "android")]
                 close_when_requested: true,
                 ..default()
-            })
+            });
+        // needs the render app
+        if self.gpu_timing {
+            plugins = plugins.add(XrGpuTimingPlugin);
+        }
+        plugins
     }
 }
 
@@ -337,58 +743,187 @@ fn xr_reset_per_frame_resources(
     **waited = false;
 }
 
+/// The events sent by [`xr_poll_events`]
+#[derive(SystemParam)]
+struct XrPolledEvents<'w> {
+    exit_type: Res<'w, ExitAppOnSessionExit>,
+    app_exit: EventWriter<'w, AppExit>,
+    setup_xr: EventWriter<'w, SetupXrData>,
+    cleanup_xr: EventWriter<'w, CleanupXrData>,
+    lifecycle: XrLifecycleEvents<'w>,
+    state_changed: EventWriter<'w, XrSessionStateChanged>,
+    space_changed: EventWriter<'w, XrReferenceSpaceChanged>,
+    refresh_rate_changed: EventWriter<'w, XrDisplayRefreshRateChanged>,
+    main_session_visibility_changed: EventWriter<'w, XrMainSessionVisibilityChanged>,
+    visibility_mask_changed: EventWriter<'w, XrVisibilityMaskChanged>,
+    performance_notification: EventWriter<'w, XrPerformanceNotification>,
+    interaction_profile_changed: EventWriter<'w, XrInteractionProfileChanged>,
+    vive_tracker_connected: EventWriter<'w, XrViveTrackerConnected>,
+    spatial_entity: EventWriter<'w, XrSpatialEntityEvent>,
+}
+
+impl XrPolledEvents<'_> {
+    /// Exits the app when the session exits or, with [`ExitAppOnSessionExit::Always`], is lost
+    fn exit_app(&mut self, lost: bool) {
+        let exit = match *self.exit_type {
+            ExitAppOnSessionExit::Always => true,
+            ExitAppOnSessionExit::OnlyOnExit => !lost,
+            ExitAppOnSessionExit::Never => false,
+        };
+        if exit {
+            self.app_exit.send_default();
+        }
+    }
+}
+
 fn xr_poll_events(
     instance: Option<Res<XrInstance>>,
     session: Option<Res<XrSession>>,
     session_running: Res<XrSessionRunning>,
-    exit_type: Res<ExitAppOnSessionExit>,
-    mut app_exit: EventWriter<AppExit>,
-    mut start_session: EventWriter<StartXrSession>,
-    mut setup_xr: EventWriter<SetupXrData>,
-    mut cleanup_xr: EventWriter<CleanupXrData>,
+    mut session_state: ResMut<XrSessionState>,
+    mut events: XrPolledEvents,
+    session_config: Res<XrSessionConfig>,
+    errors: Res<XrErrors>,
 ) {
     if let (Some(instance), Some(session)) = (instance, session) {
         let _span = info_span!("xr_poll_events");
-        while let Some(event) = instance.poll_event(&mut Default::default()).unwrap() {
+        let mut buffer = MaybeUninit::uninit();
+        loop {
+            let event = match user_presence::poll_event(&instance, &mut buffer).call("xrPollEvent")
+            {
+                Ok(Some(XrPolledEvent::Event(event))) => event,
+                Ok(Some(XrPolledEvent::UserPresenceChanged(present))) => {
+                    info!("user present: {}", present);
+                    events.lifecycle.send_user_presence(present);
+                    continue;
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    errors.fatal(err);
+                    break;
+                }
+            };
             use xr::Event::*;
             match event {
                 SessionStateChanged(e) => {
                     // Session state change is where we can begin and end sessions, as well as
                     // find quit messages!
                     info!("entered XR state {:?}", e.state());
+                    let previous = session_state.0;
+                    *session_state = XrSessionState(e.state());
+                    events.state_changed.send(XrSessionStateChanged {
+                        state: e.state(),
+                        time: e.time().into(),
+                    });
+                    events
+                        .lifecycle
+                        .send_state_change(previous, e.state(), e.time().into());
                     match e.state() {
                         xr::SessionState::READY => {
                             info!("Calling Session begin :3");
-                            session.begin(VIEW_TYPE).unwrap();
-                            setup_xr.send_default();
+                            let secondary_views =
+                                enabled_secondary_view_types(&instance, &session_config);
+                            let view_type = session_config.view_config.view_type();
+                            let began = match secondary_views.is_empty() {
+                                true => session.begin(view_type),
+                                false => session.begin_with_secondary(view_type, &secondary_views),
+                            };
+                            if let Err(err) = began.call("xrBeginSession") {
+                                errors.fatal(err);
+                                continue;
+                            }
+                            events.setup_xr.send_default();
                             session_running.store(true, std::sync::atomic::Ordering::Relaxed);
                         }
                         xr::SessionState::STOPPING => {
-                            session.end().unwrap();
+                            if let Err(err) = session.end().call("xrEndSession") {
+                                errors.warn(err);
+                            }
                             session_running.store(false, std::sync::atomic::Ordering::Relaxed);
-                            cleanup_xr.send_default();
+                            events.cleanup_xr.send_default();
                         }
                         xr::SessionState::EXITING => {
-                            if *exit_type == ExitAppOnSessionExit::Always
-                                || *exit_type == ExitAppOnSessionExit::OnlyOnExit
-                            {
-                                app_exit.send_default();
-                            }
+                            events.exit_app(false);
                         }
                         xr::SessionState::LOSS_PENDING => {
-                            if *exit_type == ExitAppOnSessionExit::Always {
-                                app_exit.send_default();
-                            }
-                            if *exit_type == ExitAppOnSessionExit::OnlyOnExit {
-                                start_session.send_default();
-                            }
+                            events.exit_app(true);
+                            // the session is cleaned up and restarted by XrSessionRecoveryPlugin
                         }
 
                         _ => {}
                     }
                 }
-                InstanceLossPending(_) => {
-                    app_exit.send_default();
+                InstanceLossPending(e) => {
+                    events.lifecycle.send_instance_lost(e.loss_time().into());
+                    events.app_exit.send_default();
+                }
+                ReferenceSpaceChangePending(e) if e.pose_valid() => {
+                    events.space_changed.send(XrReferenceSpaceChanged {
+                        space_type: e.reference_space_type(),
+                        pose_in_previous_space: e.pose_in_previous_space(),
+                    });
+                }
+                DisplayRefreshRateChangedFB(e) => {
+                    info!(
+                        "display refresh rate changed from {} Hz to {} Hz",
+                        e.from_display_refresh_rate(),
+                        e.to_display_refresh_rate()
+                    );
+                    events
+                        .refresh_rate_changed
+                        .send(XrDisplayRefreshRateChanged {
+                            from: e.from_display_refresh_rate(),
+                            to: e.to_display_refresh_rate(),
+                        });
+                }
+                MainSessionVisibilityChangedEXTX(e) => {
+                    info!("main session visible: {}", e.visible());
+                    let depth_layer_enabled = e.flags().contains(
+                        xr::OverlayMainSessionFlagsEXTX::ENABLED_COMPOSITION_LAYER_INFO_DEPTH,
+                    );
+                    events
+                        .main_session_visibility_changed
+                        .send(XrMainSessionVisibilityChanged {
+                            visible: e.visible(),
+                            depth_layer_enabled,
+                        });
+                }
+                VisibilityMaskChangedKHR(e) => {
+                    info!("visibility mask of view {} changed", e.view_index());
+                    events
+                        .visibility_mask_changed
+                        .send(XrVisibilityMaskChanged {
+                            view_index: e.view_index(),
+                        });
+                }
+                PerfSettingsEXT(e) => {
+                    if let Some(notification) = XrPerformanceNotification::from_raw(e) {
+                        info!("performance notification: {:?}", notification);
+                        events.performance_notification.send(notification);
+                    }
+                }
+                InteractionProfileChanged(_) => {
+                    info!("interaction profile changed");
+                    events.interaction_profile_changed.send_default();
+                }
+                ViveTrackerConnectedHTCX(e) => {
+                    match XrViveTrackerConnected::from_paths(&instance, e.paths()) {
+                        Ok(connected) => {
+                            events.vive_tracker_connected.send(connected);
+                        }
+                        Err(err) => warn!("Unable to read the connected vive tracker: {}", err),
+                    }
+                }
+                SpatialAnchorCreateCompleteFB(_)
+                | SpaceSetStatusCompleteFB(_)
+                | SpaceQueryResultsAvailableFB(_)
+                | SpaceQueryCompleteFB(_)
+                | SpaceSaveCompleteFB(_)
+                | SpaceEraseCompleteFB(_)
+                | SceneCaptureCompleteFB(_) => {
+                    if let Some(event) = XrSpatialEntityEvent::from_event(event) {
+                        events.spatial_entity.send(event);
+                    }
                 }
                 EventsLost(e) => {
                     warn!("lost {} XR events", e.lost_event_count());
@@ -399,64 +934,137 @@ fn xr_poll_events(
     }
 }
 
-pub fn xr_wait_frame(
-    world: &mut World,
-    // mut frame_state: ResMut<XrFrameState>,
-    // mut frame_waiter: ResMut<XrFrameWaiter>,
-    // mut should_render: ResMut<XrShouldRender>,
-    // mut waited: ResMut<XrHasWaited>,
-) {
-    let mut frame_waiter = world.get_resource_mut::<XrFrameWaiter>().unwrap();
-    {
-        let _span = info_span!("xr_wait_frame").entered();
-
-        *world.get_resource_mut::<XrFrameState>().unwrap() = match frame_waiter.wait() {
-            Ok(a) => a.into(),
-            Err(e) => {
-                warn!("error: {}", e);
-                return;
-            }
+/// Takes the next frame from the [`XrFrameWaiterThread`], which is spawned for the first frame
+/// of a session.
+///
+/// `xrWaitFrame` blocks for most of a frame, so it runs on its own thread. The thread hands every
+/// frame over through a channel without buffer, it only starts waiting for frame N+1 once the
+/// main world took frame N. The runtime then blocks that wait until frame N was begun by the
+/// render world, that's how OpenXR paces the app. The main world only blocks here when it's
+/// faster than the runtime, so it simulates frame N+1 while the render world renders frame N.
+///
+/// Frames are always begun in the order they were waited for: every frame the main world took
+/// sets [`XrHasWaited`], so the render world begins it after extracting it, and the thread can't
+/// wait for another frame before that. After a failed wait no frame is begun and the thread
+/// simply waits for the next one.
+pub fn xr_wait_frame(world: &mut World) {
+    let _span = info_span!("xr_wait_frame").entered();
+    if !world.contains_resource::<XrFrameWaiterThread>() {
+        let Some(waiter) = world.remove_resource::<XrFrameWaiter>() else {
+            return;
         };
-        let should_render = world.get_resource::<XrFrameState>().unwrap().should_render;
-        **world.get_resource_mut::<XrShouldRender>().unwrap() = should_render;
-        **world.get_resource_mut::<XrHasWaited>().unwrap() = true;
+        let secondary_views = world.contains_resource::<XrSecondaryViewState>();
+        world.insert_resource(XrFrameWaiterThread::spawn(waiter, secondary_views));
+    }
+    let (frame_state, secondary_active) = match world.resource::<XrFrameWaiterThread>().recv() {
+        Some(Ok(frame)) => frame,
+        Some(Err(result)) => {
+            world.resource::<XrErrors>().warn(XrError::Runtime {
+                call: "xrWaitFrame",
+                result,
+                context: None,
+            });
+            return;
+        }
+        None => {
+            error!("The xrWaitFrame thread stopped");
+            world.remove_resource::<XrFrameWaiterThread>();
+            return;
+        }
+    };
+    *world.get_resource_mut::<XrFrameState>().unwrap() = frame_state.into();
+    if let Some(active) = secondary_active {
+        let mut secondary = world.resource_mut::<XrSecondaryViewState>();
+        if secondary.active != active {
+            secondary.active = active;
+        }
     }
+    let elapsed = world.resource::<Time>().elapsed();
     world
-        .get_resource::<XrSwapchain>()
-        .unwrap()
-        .begin()
-        .unwrap();
+        .resource_mut::<XrFrameTime>()
+        .update(frame_state, elapsed);
+    **world.get_resource_mut::<XrShouldRender>().unwrap() = frame_state.should_render;
+    **world.get_resource_mut::<XrHasWaited>().unwrap() = true;
+}
+
+/// Keeps [`XrFrameTime`] going with the app's [`Time`] while no frame is waited on
+fn mirror_app_time(time: Res<Time>, mut frame_time: ResMut<XrFrameTime>) {
+    frame_time.mirror(&time);
+}
+
+/// Begins the frame the main world waited for, in the render world so the whole frame from
+/// `xrBeginFrame` to `xrEndFrame` happens on one thread, also with pipelined rendering.
+/// The main world can wait for the next frame meanwhile, the runtime blocks that until here.
+pub fn xr_begin_frame(
+    swapchain: Res<XrSwapchain>,
+    mut in_flight: ResMut<XrFrameInFlight>,
+    errors: Res<XrErrors>,
+    hooks: Res<XrFrameHooks>,
+) {
+    let _span = info_span!("xr_begin_frame").entered();
+    if *in_flight != XrFrameInFlight::Idle {
+        // xrEndFrame of the last frame never ran, beginning now implicitly discards it
+        warn!(
+            "Beginning a frame while the last one is still {:?}",
+            *in_flight
+        );
+    }
+    match swapchain.begin(&hooks).call("xrBeginFrame") {
+        Ok(()) => *in_flight = XrFrameInFlight::Begun,
+        Err(err) => {
+            *in_flight = XrFrameInFlight::Idle;
+            errors.frame(err);
+        }
+    }
 }
 
 pub fn xr_pre_frame(
     resolution: Res<XrResolution>,
-    format: Res<XrFormat>,
     swapchain: Res<XrSwapchain>,
     mut manual_texture_views: ResMut<ManualTextureViews>,
+    mut view_targets: ResMut<XrViewTargets>,
+    mut in_flight: ResMut<XrFrameInFlight>,
+    errors: Res<XrErrors>,
+    external_rendering: Option<Res<XrExternalRendering>>,
 ) {
+    // an external renderer acquires the image itself
+    if *in_flight != XrFrameInFlight::Begun || external_rendering.is_some() {
+        return;
+    }
     {
         let _span = info_span!("xr_acquire_image").entered();
-        swapchain.acquire_image().unwrap()
+        if let Err(err) = swapchain.acquire_image().call("xrAcquireSwapchainImage") {
+            errors.frame(err);
+            return;
+        }
+        *in_flight = XrFrameInFlight::Acquired;
     }
     {
         let _span = info_span!("xr_wait_image").entered();
-        swapchain.wait_image().unwrap();
+        if let Err(err) = swapchain.wait_image().call("xrWaitSwapchainImage") {
+            errors.frame(err);
+            return;
+        }
+        *in_flight = XrFrameInFlight::Ready;
     }
     {
         let _span = info_span!("xr_update_manual_texture_views").entered();
-        let (left, right) = swapchain.get_render_views();
-        let left = ManualTextureView {
-            texture_view: left.into(),
-            size: **resolution,
-            format: **format,
-        };
-        let right = ManualTextureView {
-            texture_view: right.into(),
-            size: **resolution,
-            format: **format,
-        };
-        manual_texture_views.insert(LEFT_XR_TEXTURE_HANDLE, left);
-        manual_texture_views.insert(RIGHT_XR_TEXTURE_HANDLE, right);
+        view_targets.0.clear();
+        for (handle, view) in XR_TEXTURE_HANDLES.into_iter().zip(swapchain.render_views()) {
+            view_targets.0.push(XrViewTarget {
+                view: view.clone(),
+                size: **resolution,
+                format: swapchain.view_format(),
+            });
+            manual_texture_views.insert(
+                handle,
+                ManualTextureView {
+                    texture_view: view.clone(),
+                    size: **resolution,
+                    format: swapchain.view_format(),
+                },
+            );
+        }
     }
 }
 
@@ -470,6 +1078,19 @@ pub fn xr_end_frame(
     environment_blend_mode: Res<XrEnvironmentBlendMode>,
     passthrough_layer: Option<Res<XrPassthroughLayer>>,
     passthrough_state: Option<Res<XrPassthroughState>>,
+    clip_planes: Res<XrClipPlanes>,
+    composition_layers: Query<&ExtractedXrLayer>,
+    secondary_view: Option<Res<ExtractedSecondaryView>>,
+    session_config: Res<XrSessionConfig>,
+    viewport_scale: Option<Res<XrViewportScale>>,
+    skybox: Option<Res<ExtractedXrSkybox>>,
+    (mut view_targets, mut in_flight, mut layers, mut too_many_layers): (
+        ResMut<XrViewTargets>,
+        ResMut<XrFrameInFlight>,
+        Local<Vec<FrameLayer>>,
+        Local<bool>,
+    ),
+    (errors, layer_stack, hooks): (Res<XrErrors>, Res<XrLayerStack>, Res<XrFrameHooks>),
 ) {
     #[cfg(target_os = "android")]
     {
@@ -478,9 +1099,56 @@ pub fn xr_end_frame(
         let env = vm.attach_current_thread_as_daemon();
     }
 
+    let frame = std::mem::take(&mut *in_flight);
+    if frame == XrFrameInFlight::Idle {
+        return;
+    }
     {
         let _span = info_span!("xr_release_image").entered();
-        swapchain.release_image().unwrap();
+        view_targets.0.clear();
+        // an image that was acquired has to be released, even if nothing was rendered into it
+        let released = match frame {
+            XrFrameInFlight::Acquired => swapchain
+                .wait_image()
+                .call("xrWaitSwapchainImage")
+                .and_then(|()| swapchain.release_image().call("xrReleaseSwapchainImage"))
+                .map(|()| false),
+            XrFrameInFlight::Ready => swapchain
+                .release_image()
+                .call("xrReleaseSwapchainImage")
+                .map(|()| true),
+            XrFrameInFlight::Released => Ok(true),
+            _ => Ok(false),
+        };
+        let rendered = match released {
+            Ok(rendered) => rendered,
+            Err(err) => {
+                errors.frame(err);
+                false
+            }
+        };
+        if !rendered {
+            // the frame still has to be ended, or beginning the next one discards it
+            if let Err(err) = swapchain
+                .end_without_layers(
+                    &hooks,
+                    xr_frame_state.predicted_display_time,
+                    **environment_blend_mode,
+                )
+                .call("xrEndFrame")
+            {
+                errors.frame(err);
+            }
+            return;
+        }
+        for layer in &composition_layers {
+            if let Err(err) = layer.release_image() {
+                warn!("Unable to release composition layer image: {}", err);
+            }
+        }
+        if let Some(Err(err)) = secondary_view.as_ref().map(|view| view.release_image()) {
+            warn!("Unable to release secondary view image: {}", err);
+        }
     }
     {
         let _span = info_span!("xr_end_frame").entered();
@@ -488,53 +1156,563 @@ pub fn xr_end_frame(
             Some(XrPassthroughState::Running) => passthrough_layer.as_deref(),
             _ => None,
         };
+        // reused every frame, it's emptied again once the frame was submitted
+        layers.extend(layer_stack.enabled().filter_map(|id| {
+            match id {
+                XrLayerId::Passthrough => pass_layer.map(|_| FrameLayer::Passthrough),
+                XrLayerId::Skybox => skybox.as_deref().map(|skybox| {
+                    FrameLayer::Composition(RawCompositionLayer::cube(
+                        &skybox.swapchain,
+                        skybox.orientation,
+                        &input.stage,
+                    ))
+                }),
+                XrLayerId::Projection => Some(FrameLayer::Projection),
+                XrLayerId::Composition(entity) => composition_layers
+                    .iter()
+                    .find(|layer| layer.entity == entity)
+                    .map(|layer| FrameLayer::Composition(layer.to_raw(&input.stage))),
+            }
+        }));
+        // the runtime rejects the whole frame, dropping the front layers keeps the rest visible
+        match layer_stack.check_layer_count(layers.len()) {
+            Err(err) => {
+                if !*too_many_layers {
+                    errors.warn(err);
+                    *too_many_layers = true;
+                }
+                layers.truncate(layer_stack.max_layer_count().unwrap_or(u32::MAX) as usize);
+            }
+            Ok(()) => *too_many_layers = false,
+        }
         let result = swapchain.end(
+            &hooks,
             xr_frame_state.predicted_display_time,
             &views,
             &input.stage,
-            **resolution,
+            // only the part the cameras rendered into is submitted
+            viewport_scale.map_or(**resolution, |scale| scale.viewport_size(**resolution)),
             **environment_blend_mode,
             pass_layer,
+            *clip_planes,
+            &layers,
+            secondary_view.as_deref(),
+            session_config.projection_alpha.unwrap_or(
+                // overlays are blended on top of the main session, the skybox and passthrough
+                // show through wherever the cameras didn't render anything
+                if pass_layer.is_some() || session_config.overlay.is_some() || skybox.is_some() {
+                    XrLayerAlpha::Premultiplied
+                } else {
+                    XrLayerAlpha::Opaque
+                },
+            ),
         );
-        match result {
-            Ok(_) => {}
-            Err(e) => warn!("error: {}", e),
+        layers.clear();
+        if let Err(err) = result.call("xrEndFrame") {
+            errors.frame(err);
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn locate_views(
     mut views: ResMut<XrViews>,
     input: Res<XrInput>,
     session: Res<XrSession>,
     xr_frame_state: Res<XrFrameState>,
+    session_config: Res<XrSessionConfig>,
+    mut located: Local<Vec<xr::View>>,
+    mut view_info: ResMut<XrViewInfo>,
+    hooks: Res<XrFrameHooks>,
 ) {
     let _span = info_span!("xr_locate_views").entered();
-    **views = match session.locate_views(
-        VIEW_TYPE,
-        xr_frame_state.predicted_display_time,
-        &input.stage,
+    let time = input.pose_time(xr_frame_state.predicted_display_time.into());
+    if let Err(err) = locate_views_with_hooks(
+        &session,
+        &input,
+        XrTime(time.as_nanos()),
+        &mut located,
+        Some(&hooks),
     ) {
-        Ok(this) => this
-            .1
-            .into_iter()
-            .map(|mut view| {
-                use crate::prelude::*;
-                let quat = view.pose.orientation.to_quat();
-                let fixed_quat = verify_quat(quat);
-                let oxr_quat = xr::Quaternionf {
+        warn!("error: {}", err);
+        return;
+    }
+    view_info.set_if_neq(XrViewInfo::from_views(&located));
+    // the buffers keep their capacity, so nothing is allocated after the first frame
+    views.clear();
+    match session_config.view_config {
+        XrViewConfig::Stereo | XrViewConfig::Quad => views.extend_from_slice(&located),
+        XrViewConfig::Mono => views.extend(combine_views(&located)),
+    }
+}
+
+/// The distance between the positions of the first two views, the interpupillary distance
+/// for stereo headsets. Zero with fewer views.
+pub fn view_separation(views: &[xr::View]) -> f32 {
+    use crate::prelude::*;
+    match views {
+        [left, right, ..] => left
+            .pose
+            .position
+            .to_vec3()
+            .distance(right.pose.position.to_vec3()),
+        _ => 0.0,
+    }
+}
+
+/// Combines the eye views into a single view centered between the eyes,
+/// with a field of view that covers the ones of all eyes
+pub fn combine_views(views: &[xr::View]) -> Option<xr::View> {
+    use crate::prelude::*;
+    let first = views.first()?;
+    let count = views.len() as f32;
+    let position = views
+        .iter()
+        .map(|view| view.pose.position.to_vec3())
+        .sum::<Vec3>()
+        / count;
+    // running average of the orientations
+    let orientation = views.iter().enumerate().skip(1).fold(
+        first.pose.orientation.to_quat(),
+        |orientation, (i, view)| {
+            orientation.slerp(view.pose.orientation.to_quat(), 1.0 / (i + 1) as f32)
+        },
+    );
+    // canted displays rotate the views apart, so the corners of every view are measured in the
+    // combined view, the edges between them stay straight lines in tangent space
+    let inverse = orientation.inverse();
+    let (mut min, mut max) = (Vec2::INFINITY, Vec2::NEG_INFINITY);
+    for view in views {
+        let rotation = inverse * view.pose.orientation.to_quat();
+        let fov = view.fov;
+        for (x, y) in [
+            (fov.angle_left, fov.angle_up),
+            (fov.angle_right, fov.angle_up),
+            (fov.angle_right, fov.angle_down),
+            (fov.angle_left, fov.angle_down),
+        ] {
+            let direction = rotation * Vec3::new(x.tan(), y.tan(), -1.0);
+            let tangent = Vec2::new(direction.x, direction.y) / (-direction.z).max(f32::EPSILON);
+            min = min.min(tangent);
+            max = max.max(tangent);
+        }
+    }
+    let fov = xr::Fovf {
+        angle_left: min.x.atan(),
+        angle_right: max.x.atan(),
+        angle_up: max.y.atan(),
+        angle_down: min.y.atan(),
+    };
+    Some(xr::View {
+        pose: xr::Posef {
+            orientation: xr::Quaternionf {
+                x: orientation.x,
+                y: orientation.y,
+                z: orientation.z,
+                w: orientation.w,
+            },
+            position: xr::Vector3f {
+                x: position.x,
+                y: position.y,
+                z: position.z,
+            },
+        },
+        fov,
+    })
+}
+
+/// Locates the views in the stage space at an explicit time,
+/// [`locate_views`] uses the predicted display time of the current frame
+pub fn locate_views_at(
+    session: &xr::Session<xr::AnyGraphics>,
+    input: &XrInput,
+    time: XrTime,
+) -> xr::Result<Vec<xr::View>> {
+    let mut views = Vec::new();
+    locate_views_into(session, input, time, &mut views)?;
+    Ok(views)
+}
+
+/// Like [`locate_views_at`], but reuses the allocation of `views`
+pub fn locate_views_into(
+    session: &xr::Session<xr::AnyGraphics>,
+    input: &XrInput,
+    time: XrTime,
+    views: &mut Vec<xr::View>,
+) -> xr::Result<()> {
+    locate_views_with_hooks(session, input, time, views, None)
+}
+
+fn locate_views_with_hooks(
+    session: &xr::Session<xr::AnyGraphics>,
+    input: &XrInput,
+    time: XrTime,
+    views: &mut Vec<xr::View>,
+    hooks: Option<&XrFrameHooks>,
+) -> xr::Result<()> {
+    use crate::prelude::*;
+    // stereo has two views and quad views four
+    let mut raw_views = [xr::sys::View {
+        ty: xr::sys::View::TYPE,
+        next: std::ptr::null_mut(),
+        pose: default(),
+        fov: default(),
+    }; 4];
+    let mut info = xr::sys::ViewLocateInfo {
+        ty: xr::sys::ViewLocateInfo::TYPE,
+        next: std::ptr::null(),
+        view_configuration_type: input.view_type,
+        display_time: time.into(),
+        space: input.stage.as_raw(),
+    };
+    if let Some(hooks) = hooks {
+        hooks.locate_views(&mut info);
+    }
+    let mut state = xr::sys::ViewState {
+        ty: xr::sys::ViewState::TYPE,
+        next: std::ptr::null_mut(),
+        view_state_flags: xr::ViewStateFlags::EMPTY,
+    };
+    let mut count = 0;
+    let result = unsafe {
+        (session.instance().fp().locate_views)(
+            session.as_raw(),
+            &info,
+            &mut state,
+            raw_views.len() as u32,
+            &mut count,
+            raw_views.as_mut_ptr(),
+        )
+    };
+    if result.into_raw() < 0 {
+        return Err(result);
+    }
+    views.clear();
+    views.extend(raw_views.iter().take(count as usize).map(|view| {
+        let fixed_quat = verify_quat(view.pose.orientation.to_quat());
+        xr::View {
+            pose: xr::Posef {
+                orientation: xr::Quaternionf {
                     x: fixed_quat.x,
                     y: fixed_quat.y,
                     z: fixed_quat.z,
                     w: fixed_quat.w,
+                },
+                position: view.pose.position,
+            },
+            fov: view.fov,
+        }
+    }));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xr_input::xr_camera::XRProjection;
+    use crate::xr_input::QuatConv;
+    use bevy::render::camera::CameraProjection;
+
+    fn view(x: f32, yaw_degrees: f32, fov_degrees: [f32; 4]) -> xr::View {
+        let orientation = Quat::from_rotation_y(yaw_degrees.to_radians());
+        let [left, right, down, up] = fov_degrees.map(f32::to_radians);
+        xr::View {
+            pose: xr::Posef {
+                orientation: xr::Quaternionf {
+                    x: orientation.x,
+                    y: orientation.y,
+                    z: orientation.z,
+                    w: orientation.w,
+                },
+                position: xr::Vector3f { x, y: 1.6, z: 0.0 },
+            },
+            fov: xr::Fovf {
+                angle_left: left,
+                angle_right: right,
+                angle_down: down,
+                angle_up: up,
+            },
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{} isn't {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn combined_fov_covers_asymmetric_views() {
+        let views = [
+            view(-0.03, 0.0, [-50.0, 40.0, -45.0, 35.0]),
+            view(0.03, 0.0, [-40.0, 50.0, -45.0, 35.0]),
+        ];
+        let combined = combine_views(&views).unwrap();
+        assert_eq!(combined.pose.position.x, 0.0);
+        assert_close(combined.fov.angle_left, -50f32.to_radians());
+        assert_close(combined.fov.angle_right, 50f32.to_radians());
+        assert_close(combined.fov.angle_down, -45f32.to_radians());
+        assert_close(combined.fov.angle_up, 35f32.to_radians());
+    }
+
+    #[test]
+    fn combined_fov_covers_canted_views() {
+        // each display is turned 10 degrees outwards
+        let views = [
+            view(-0.03, 10.0, [-50.0, 40.0, -45.0, 35.0]),
+            view(0.03, -10.0, [-40.0, 50.0, -45.0, 35.0]),
+        ];
+        let combined = combine_views(&views).unwrap();
+        let orientation = combined.pose.orientation;
+        assert_close(orientation.w.abs(), 1.0);
+        // turning around y keeps the horizontal angles of the outer edges
+        assert_close(combined.fov.angle_left, -60f32.to_radians());
+        assert_close(combined.fov.angle_right, 60f32.to_radians());
+        // the outer corners are farther to the side, so their vertical tangents grow by the
+        // ratio of the cosines of their horizontal angles before and after turning
+        let scale = 50f32.to_radians().cos() / 60f32.to_radians().cos();
+        assert_close(
+            combined.fov.angle_up,
+            (35f32.to_radians().tan() * scale).atan(),
+        );
+        assert_close(
+            combined.fov.angle_down,
+            (-45f32.to_radians().tan() * scale).atan(),
+        );
+    }
+
+    #[test]
+    fn combined_frustum_contains_the_frusta_of_the_views() {
+        let views = [
+            view(0.0, 10.0, [-50.0, 40.0, -45.0, 35.0]),
+            view(0.0, -10.0, [-40.0, 50.0, -45.0, 35.0]),
+        ];
+        let combined = combine_views(&views).unwrap();
+        let corners = XRProjection::new(0.1, 100.0, combined.fov).get_frustum_corners(-0.1, -10.0);
+        let (bottom_right, top_left) = (corners[4], corners[6]);
+        // the far corners keep the sides of the asymmetric fov
+        assert_close(bottom_right.x, 60f32.to_radians().tan() * 10.0);
+        assert_close(top_left.x, -60f32.to_radians().tan() * 10.0);
+        assert!(top_left.y > 0.0 && bottom_right.y < 0.0);
+        for view in &views {
+            let rotation = view.pose.orientation.to_quat();
+            let corners = XRProjection::new(0.1, 100.0, view.fov).get_frustum_corners(-0.1, -10.0);
+            for corner in &corners[4..] {
+                let corner = rotation * Vec3::from(*corner);
+                let tangent = corner.truncate() / -corner.z;
+                let (min, max) = (
+                    Vec2::new(top_left.x, bottom_right.y) / 10.0,
+                    Vec2::new(bottom_right.x, top_left.y) / 10.0,
+                );
+                assert!(
+                    tangent.cmpge(min - 1e-5).all() && tangent.cmple(max + 1e-5).all(),
+                    "{} is outside of {}..{}",
+                    tangent,
+                    min,
+                    max
+                );
+            }
+        }
+    }
+
+    /// Counts the allocations of the current thread while [`count_allocations`] runs
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: std::cell::Cell<Option<usize>> = const { std::cell::Cell::new(None) };
+    }
+
+    fn count_allocation() {
+        // the thread locals are gone while a thread shuts down
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get().map(|count| count + 1)));
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            count_allocation();
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: std::alloc::Layout) -> *mut u8 {
+            count_allocation();
+            std::alloc::System.alloc_zeroed(layout)
+        }
+
+        unsafe fn realloc(
+            &self,
+            ptr: *mut u8,
+            layout: std::alloc::Layout,
+            new_size: usize,
+        ) -> *mut u8 {
+            count_allocation();
+            std::alloc::System.realloc(ptr, layout, new_size)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn count_allocations(f: impl FnOnce()) -> usize {
+        ALLOCATIONS.with(|count| count.set(Some(0)));
+        f();
+        ALLOCATIONS.with(|count| count.take()).unwrap()
+    }
+
+    /// The views and projection views submitted by the last `xrEndFrame` of [`fake_runtime`]
+    static SUBMITTED: std::sync::Mutex<(u32, u32)> = std::sync::Mutex::new((0, 0));
+
+    /// A runtime that locates two views and accepts every frame, everything else is unsupported
+    unsafe extern "system" fn fake_runtime(
+        _instance: xr::sys::Instance,
+        name: *const std::ffi::c_char,
+        function: *mut Option<xr::sys::pfn::VoidFunction>,
+    ) -> xr::sys::Result {
+        unsafe extern "system" fn locate_views(
+            _session: xr::sys::Session,
+            _info: *const xr::sys::ViewLocateInfo,
+            _state: *mut xr::sys::ViewState,
+            capacity: u32,
+            count: *mut u32,
+            views: *mut xr::sys::View,
+        ) -> xr::sys::Result {
+            *count = 2;
+            if capacity < 2 {
+                return xr::sys::Result::ERROR_SIZE_INSUFFICIENT;
+            }
+            for (i, x) in [-0.03, 0.03].into_iter().enumerate() {
+                let view = &mut *views.add(i);
+                view.pose = xr::Posef {
+                    orientation: xr::Quaternionf::IDENTITY,
+                    position: xr::Vector3f { x, y: 1.6, z: 0.0 },
                 };
-                view.pose.orientation = oxr_quat;
-                view
-            })
-            .collect(),
-        Err(err) => {
-            warn!("error: {}", err);
-            return;
+                view.fov = xr::Fovf {
+                    angle_left: -0.8,
+                    angle_right: 0.8,
+                    angle_up: 0.8,
+                    angle_down: -0.8,
+                };
+            }
+            xr::sys::Result::SUCCESS
+        }
+        unsafe extern "system" fn end_frame(
+            _session: xr::sys::Session,
+            info: *const xr::sys::FrameEndInfo,
+        ) -> xr::sys::Result {
+            let info = &*info;
+            let projection = &**(info.layers as *const *const xr::sys::CompositionLayerProjection);
+            *SUBMITTED.lock().unwrap() = (info.layer_count, projection.view_count);
+            xr::sys::Result::SUCCESS
+        }
+        unsafe extern "system" fn destroy(_handle: u64) -> xr::sys::Result {
+            xr::sys::Result::SUCCESS
         }
+        unsafe extern "system" fn unsupported() -> xr::sys::Result {
+            xr::sys::Result::ERROR_FUNCTION_UNSUPPORTED
+        }
+        type Destroy = unsafe extern "system" fn(u64) -> xr::sys::Result;
+        type Unsupported = unsafe extern "system" fn() -> xr::sys::Result;
+        let name = std::ffi::CStr::from_ptr(name).to_bytes();
+        *function = Some(match name {
+            b"xrLocateViews" => std::mem::transmute(locate_views as xr::sys::pfn::LocateViews),
+            b"xrEndFrame" => std::mem::transmute(end_frame as xr::sys::pfn::EndFrame),
+            _ if name.starts_with(b"xrDestroy") => std::mem::transmute(destroy as Destroy),
+            _ => std::mem::transmute(unsupported as Unsupported),
+        });
+        xr::sys::Result::SUCCESS
+    }
+
+    #[test]
+    #[cfg(feature = "vulkan")]
+    fn steady_frames_do_not_allocate() {
+        let (session, swapchain, input) = unsafe {
+            let entry = xr::Entry::from_get_instance_proc_addr(fake_runtime).unwrap();
+            let instance = xr::Instance::from_raw(
+                entry,
+                xr::sys::Instance::from_raw(1),
+                xr::InstanceExtensions::default(),
+            )
+            .unwrap();
+            let (session, _, _) = xr::Session::<xr::Vulkan>::from_raw(
+                instance,
+                xr::sys::Session::from_raw(1),
+                Box::new(()),
+            );
+            let handle = xr::Swapchain::from_raw(session.clone(), xr::sys::Swapchain::from_raw(1));
+            let swapchain = Swapchain::Vulkan(SwapchainInner::new(
+                session.clone(),
+                handle,
+                Vec::new(),
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                None,
+            ));
+            let space = |handle| {
+                std::sync::Arc::new(xr::Space::reference_from_raw(
+                    session.clone(),
+                    xr::sys::Space::from_raw(handle),
+                ))
+            };
+            let input = XrInput {
+                stage: space(1),
+                stage_type: xr::ReferenceSpaceType::STAGE,
+                emulated_floor: None,
+                stage_offset: xr::Posef::IDENTITY,
+                calibration: default(),
+                head: space(2),
+                prediction_offset: default(),
+                view_type: xr::ViewConfigurationType::PRIMARY_STEREO,
+            };
+            (session.into_any_graphics(), swapchain, input)
+        };
+        let hooks = XrFrameHooks::default();
+        let layer_stack = XrLayerStack::default();
+        let mut located = Vec::new();
+        let mut views = Vec::new();
+        let mut layers = Vec::new();
+        let mut frame = |frame: i64| {
+            let time = xr::Time::from_nanos(frame * 11_111_111);
+            locate_views_with_hooks(
+                &session,
+                &input,
+                XrTime(time.as_nanos()),
+                &mut located,
+                Some(&hooks),
+            )
+            .unwrap();
+            views.clear();
+            views.extend_from_slice(&located);
+            // only the projection layer is enabled by default
+            layers.extend(layer_stack.enabled().filter_map(|id| match id {
+                XrLayerId::Projection => Some(FrameLayer::Projection),
+                _ => None,
+            }));
+            swapchain
+                .end(
+                    &hooks,
+                    time,
+                    &views,
+                    &input.stage,
+                    UVec2::new(1832, 1920),
+                    xr::EnvironmentBlendMode::OPAQUE,
+                    None,
+                    XrClipPlanes::default(),
+                    &layers,
+                    None,
+                    XrLayerAlpha::Opaque,
+                )
+                .unwrap();
+            layers.clear();
+        };
+        // the first frame allocates the buffers that are reused afterwards
+        frame(1);
+        assert_eq!(*SUBMITTED.lock().unwrap(), (1, 2));
+        assert_eq!(count_allocations(|| (2..100).for_each(&mut frame)), 0);
+        assert_eq!(views.len(), 2);
     }
 }
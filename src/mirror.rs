@@ -0,0 +1,217 @@
+use bevy::core_pipeline::blit::{BlitPipeline, BlitPipelineKey};
+use bevy::ecs::query::QueryItem;
+use bevy::prelude::*;
+use bevy::render::camera::{CameraRenderGraph, ExtractedCamera, ManualTextureViews, Viewport};
+use bevy::render::extract_component::{ExtractComponent, ExtractComponentPlugin};
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, RenderSubGraph, ViewNode,
+    ViewNodeRunner,
+};
+use bevy::render::render_resource::{
+    BindGroupEntries, CachedRenderPipelineId, LoadOp, Operations, PipelineCache,
+    RenderPassColorAttachment, RenderPassDescriptor, SpecializedRenderPipelines, StoreOp,
+};
+use bevy::render::renderer::RenderContext;
+use bevy::render::view::ViewTarget;
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy::window::PrimaryWindow;
+
+use crate::resources::XrResolution;
+use crate::xr_init::{XrShouldRender, XrStatus};
+use crate::{LEFT_XR_TEXTURE_HANDLE, RIGHT_XR_TEXTURE_HANDLE};
+
+/// What the primary window shows while a session is running, set through `OpenXrPlugin::mirror`.
+/// The eyes are scaled to fit the window and keep their aspect ratio.
+/// The window doesn't wait for vsync when it's created by [`DefaultXrPlugins`](crate::DefaultXrPlugins),
+/// so mirroring doesn't hold back the xr frame loop.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Resource, ExtractResource, Reflect)]
+#[reflect(Resource)]
+pub enum XrMirrorMode {
+    /// The window is left alone
+    #[default]
+    None,
+    LeftEye,
+    RightEye,
+    /// Both eyes next to each other, the left one on the left
+    SideBySide,
+}
+
+/// Copies the eye images into the primary window according to the [`XrMirrorMode`] resource
+pub struct MirrorPlugin;
+
+impl Plugin for MirrorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<XrMirrorMode>();
+        app.init_resource::<XrMirrorMode>();
+        app.add_systems(PostUpdate, update_mirror_camera);
+        app.add_plugins(ExtractResourcePlugin::<XrMirrorMode>::default());
+        app.add_plugins(ExtractComponentPlugin::<XrMirrorCamera>::default());
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_render_sub_graph(XrMirrorGraph)
+            .add_render_graph_node::<ViewNodeRunner<XrMirrorNode>>(XrMirrorGraph, XrMirrorLabel);
+        render_app.add_systems(Render, prepare_mirror_pipelines.in_set(RenderSet::Prepare));
+    }
+}
+
+/// Marks the camera that draws the mirrored eyes into the primary window
+#[derive(Clone, Copy, Debug, Component, ExtractComponent)]
+pub struct XrMirrorCamera;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderSubGraph)]
+pub struct XrMirrorGraph;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct XrMirrorLabel;
+
+/// The mirror camera only exists while a session is running and mirroring is enabled
+fn update_mirror_camera(
+    mut commands: Commands,
+    mode: Res<XrMirrorMode>,
+    status: Res<XrStatus>,
+    cameras: Query<Entity, With<XrMirrorCamera>>,
+    primary_window: Query<(), With<PrimaryWindow>>,
+) {
+    let wanted =
+        *status == XrStatus::Enabled && *mode != XrMirrorMode::None && !primary_window.is_empty();
+    if wanted != cameras.is_empty() {
+        return;
+    }
+    if !wanted {
+        for e in &cameras {
+            commands.entity(e).despawn_recursive();
+        }
+        return;
+    }
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                // after the xr cameras and replacing anything else rendered to the window
+                order: isize::MAX,
+                ..default()
+            },
+            camera_render_graph: CameraRenderGraph::new(XrMirrorGraph),
+            ..default()
+        },
+        XrMirrorCamera,
+    ));
+}
+
+#[derive(Component)]
+struct XrMirrorPipeline(CachedRenderPipelineId);
+
+fn prepare_mirror_pipelines(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<BlitPipeline>>,
+    blit_pipeline: Res<BlitPipeline>,
+    views: Query<(Entity, &ViewTarget), With<XrMirrorCamera>>,
+) {
+    for (entity, target) in &views {
+        let pipeline = pipelines.specialize(
+            &pipeline_cache,
+            &blit_pipeline,
+            BlitPipelineKey {
+                texture_format: target.out_texture_format(),
+                blend_state: None,
+                samples: 1,
+            },
+        );
+        commands.entity(entity).insert(XrMirrorPipeline(pipeline));
+    }
+}
+
+/// Fits a rect with `aspect_ratio` into the center of `area`, `area` is `(min, size)`
+fn fit_rect(min: Vec2, size: Vec2, aspect_ratio: f32) -> (Vec2, Vec2) {
+    let fitted = match size.x / size.y > aspect_ratio {
+        true => Vec2::new(size.y * aspect_ratio, size.y),
+        false => Vec2::new(size.x, size.x / aspect_ratio),
+    };
+    (min + (size - fitted) / 2.0, fitted)
+}
+
+#[derive(Default)]
+struct XrMirrorNode;
+
+impl ViewNode for XrMirrorNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static ExtractedCamera,
+        &'static XrMirrorPipeline,
+    );
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (target, camera, pipeline): QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let blit_pipeline = world.resource::<BlitPipeline>();
+        let manual_texture_views = world.resource::<ManualTextureViews>();
+        let Some(target_size) = camera.physical_target_size else {
+            return Ok(());
+        };
+        // the eye images are only valid on frames that are rendered, clear the window otherwise
+        let rendered = world
+            .get_resource::<XrShouldRender>()
+            .is_some_and(|should_render| **should_render);
+        let handles: &[_] = match world.resource::<XrMirrorMode>() {
+            _ if !rendered => &[],
+            XrMirrorMode::None => &[],
+            XrMirrorMode::LeftEye => &[LEFT_XR_TEXTURE_HANDLE],
+            XrMirrorMode::RightEye => &[RIGHT_XR_TEXTURE_HANDLE],
+            XrMirrorMode::SideBySide => &[LEFT_XR_TEXTURE_HANDLE, RIGHT_XR_TEXTURE_HANDLE],
+        };
+        let eye_aspect_ratio = world
+            .get_resource::<XrResolution>()
+            .map_or(1.0, |resolution| {
+                resolution.x as f32 / resolution.y.max(1) as f32
+            });
+        let bind_groups: Vec<_> = handles
+            .iter()
+            .filter_map(|handle| manual_texture_views.get(handle))
+            .map(|view| {
+                render_context.render_device().create_bind_group(
+                    "xr_mirror_bind_group",
+                    &blit_pipeline.texture_bind_group,
+                    &BindGroupEntries::sequential((&view.texture_view, &blit_pipeline.sampler)),
+                )
+            })
+            .collect();
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("xr_mirror_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target.out_texture(),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK.into()),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        let Some(render_pipeline) = pipeline_cache.get_render_pipeline(pipeline.0) else {
+            return Ok(());
+        };
+        render_pass.set_render_pipeline(render_pipeline);
+        let slot_size = target_size.as_vec2() / Vec2::new(bind_groups.len().max(1) as f32, 1.0);
+        for (i, bind_group) in bind_groups.iter().enumerate() {
+            let slot_min = Vec2::new(slot_size.x * i as f32, 0.0);
+            let (min, size) = fit_rect(slot_min, slot_size, eye_aspect_ratio);
+            render_pass.set_camera_viewport(&Viewport {
+                physical_position: min.as_uvec2(),
+                physical_size: size.as_uvec2().max(UVec2::ONE),
+                depth: 0.0..1.0,
+            });
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        Ok(())
+    }
+}
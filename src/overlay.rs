@@ -0,0 +1,80 @@
+use std::ffi::c_void;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::graphics::XrSessionConfig;
+use crate::resources::XrInstance;
+
+pub const EXTX_OVERLAY_EXTENSION_NAME: &str = "XR_EXTX_overlay";
+
+/// Creates the session as an overlay that is composited on top of the main session of another app.
+/// Needs `XR_EXTX_overlay`, see [`XrExtensions::enable_overlay`](crate::graphics::extensions::XrExtensions::enable_overlay).
+/// A regular session is created with a warning if the runtime doesn't support overlays.
+///
+/// Overlays usually never get input focus, so actions are synced in every running state
+/// and report inactive until the runtime decides otherwise.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub struct XrOverlaySettings {
+    /// Where the layers of this session are placed relative to other overlays,
+    /// higher values are composited on top of lower ones
+    pub placement: u32,
+}
+
+/// Sent to overlay sessions when the main session is shown or hidden
+#[derive(Clone, Copy, Debug, Event)]
+pub struct XrMainSessionVisibilityChanged {
+    pub visible: bool,
+    /// The main session submits depth, so the layers of the overlay can be depth tested against it
+    pub depth_layer_enabled: bool,
+}
+
+/// True if the runtime supports `XR_EXTX_overlay`, it also has to be requested when the
+/// instance is created
+pub fn overlay_supported(instance: &XrInstance) -> bool {
+    instance.entry().enumerate_extensions().is_ok_and(|exts| {
+        exts.other
+            .iter()
+            .any(|ext| ext == EXTX_OVERLAY_EXTENSION_NAME)
+    })
+}
+
+/// Only true if the session is configured as an overlay
+pub fn xr_overlay_only() -> impl FnMut(Res<XrSessionConfig>) -> bool {
+    |config: Res<XrSessionConfig>| config.overlay.is_some()
+}
+
+/// Creates an overlay session, `graphics_binding` has to point to the graphics binding struct of `G`.
+///
+/// # Safety
+///
+/// Same as [`xr::Instance::create_session`], the graphics binding has to contain valid handles.
+pub(crate) unsafe fn create_overlay_session<G: xr::Graphics>(
+    instance: &xr::Instance,
+    system: xr::SystemId,
+    graphics_binding: *const c_void,
+    settings: &XrOverlaySettings,
+) -> xr::Result<(xr::Session<G>, xr::FrameWaiter, xr::FrameStream<G>)> {
+    let overlay_info = xr::sys::SessionCreateInfoOverlayEXTX {
+        ty: xr::sys::SessionCreateInfoOverlayEXTX::TYPE,
+        next: graphics_binding,
+        create_flags: xr::sys::OverlaySessionCreateFlagsEXTX::EMPTY,
+        session_layers_placement: settings.placement,
+    };
+    let info = xr::sys::SessionCreateInfo {
+        ty: xr::sys::SessionCreateInfo::TYPE,
+        next: &overlay_info as *const _ as *const c_void,
+        create_flags: xr::SessionCreateFlags::EMPTY,
+        system_id: system,
+    };
+    let mut handle = xr::sys::Session::NULL;
+    let result = (instance.fp().create_session)(instance.as_raw(), &info, &mut handle);
+    if result.into_raw() < 0 {
+        return Err(result);
+    }
+    Ok(xr::Session::from_raw(
+        instance.clone(),
+        handle,
+        Box::new(()),
+    ))
+}
@@ -10,7 +10,7 @@ use crate::{
 };
 use openxr as xr;
 use xr::{
-    sys::{Space, SystemPassthroughProperties2FB},
+    sys::{Space, SystemPassthroughProperties2FB, SystemPassthroughPropertiesFB},
     CompositionLayerBase, CompositionLayerFlags, FormFactor, Graphics,
     PassthroughCapabilityFlagsFB,
 };
@@ -18,6 +18,7 @@ use xr::{
 #[derive(
     Clone, Copy, Default, Debug, Resource, PartialEq, PartialOrd, Ord, Eq, Reflect, ExtractResource,
 )]
+#[reflect(Resource)]
 pub enum XrPassthroughState {
     #[default]
     Unsupported,
@@ -66,11 +67,16 @@ fn check_passthrough_support(mut cmds: Commands, instance: Option<Res<XrInstance
         None => cmds.insert_resource(XrPassthroughState::Unsupported),
         Some(instance) => {
             let supported = instance.exts().fb_passthrough.is_some()
-                && supports_passthrough(
-                    &instance,
-                    instance.system(FormFactor::HEAD_MOUNTED_DISPLAY).unwrap(),
-                )
-                .is_ok_and(|v| v);
+                && instance
+                    .system(FormFactor::HEAD_MOUNTED_DISPLAY)
+                    .and_then(|system| supports_passthrough(&instance, system))
+                    .unwrap_or_else(|e| {
+                        warn!("Unable to query passthrough support: {}", e);
+                        false
+                    });
+            if instance.exts().fb_passthrough.is_some() && !supported {
+                info!("XR_FB_passthrough is enabled, but the system does not support passthrough");
+            }
             match supported {
                 false => cmds.insert_resource(XrPassthroughState::Unsupported),
                 true => cmds.insert_resource(XrPassthroughState::Paused),
@@ -79,37 +85,30 @@ fn check_passthrough_support(mut cmds: Commands, instance: Option<Res<XrInstance
     }
 }
 
-fn resume_passthrough(
-    layer: Res<XrPassthroughLayer>,
-    mut state: ResMut<XrPassthroughState>,
-    mut clear_color: ResMut<ClearColor>,
-) {
+// the clear color of the xr cameras is switched to transparent while passthrough is running,
+// see `update_camera_clear_color`
+fn resume_passthrough(layer: Res<XrPassthroughLayer>, mut state: ResMut<XrPassthroughState>) {
     if let Err(e) = layer.resume() {
         warn!("Unable to resume Passthrough: {}", e);
         return;
     }
-    clear_color.set_a(0.0);
-    clear_color.set_r(0.0);
-    clear_color.set_g(0.0);
-    clear_color.set_b(0.0);
     *state = XrPassthroughState::Running;
 }
-fn pause_passthrough(
-    layer: Res<XrPassthroughLayer>,
-    mut state: ResMut<XrPassthroughState>,
-    mut clear_color: ResMut<ClearColor>,
-) {
+fn pause_passthrough(layer: Res<XrPassthroughLayer>, mut state: ResMut<XrPassthroughState>) {
     if let Err(e) = layer.pause() {
-        warn!("Unable to resume Passthrough: {}", e);
+        warn!("Unable to pause Passthrough: {}", e);
         return;
     }
-    clear_color.set_a(1.0);
     *state = XrPassthroughState::Paused;
 }
 
-fn cleanup_passthrough(mut cmds: Commands) {
+fn cleanup_passthrough(mut cmds: Commands, mut state: ResMut<XrPassthroughState>) {
     cmds.remove_resource::<XrPassthrough>();
     cmds.remove_resource::<XrPassthroughLayer>();
+    // the layer of the next session is created paused
+    if *state == XrPassthroughState::Running {
+        *state = XrPassthroughState::Paused;
+    }
 }
 
 fn setup_passthrough(mut cmds: Commands, session: Res<XrSession>) {
@@ -166,15 +165,23 @@ impl<'a, G: xr::Graphics> CompositionLayerPassthrough<'a, G> {
         }
     }
 }
+/// Checks the system properties for passthrough support,
+/// the extension being available doesn't mean the device has passthrough cameras
 #[inline]
 pub fn supports_passthrough(instance: &XrInstance, system: xr::SystemId) -> xr::Result<bool> {
     unsafe {
-        let mut hand = xr::sys::SystemPassthroughProperties2FB {
+        // runtimes that only know the first version of the properties leave this untouched
+        let mut properties2 = xr::sys::SystemPassthroughProperties2FB {
             ty: SystemPassthroughProperties2FB::TYPE,
             next: ptr::null(),
-            capabilities: PassthroughCapabilityFlagsFB::PASSTHROUGH_CAPABILITY,
+            capabilities: PassthroughCapabilityFlagsFB::EMPTY,
         };
-        let mut p = xr::sys::SystemProperties::out(&mut hand as *mut _ as _);
+        let mut properties = xr::sys::SystemPassthroughPropertiesFB {
+            ty: SystemPassthroughPropertiesFB::TYPE,
+            next: &mut properties2 as *mut _ as _,
+            supports_passthrough: false.into(),
+        };
+        let mut p = xr::sys::SystemProperties::out(&mut properties as *mut _ as _);
         cvt((instance.fp().get_system_properties)(
             instance.as_raw(),
             system,
@@ -182,12 +189,12 @@ pub fn supports_passthrough(instance: &XrInstance, system: xr::SystemId) -> xr::
         ))?;
         bevy::log::info!(
             "From supports_passthrough: Passthrough capabilities: {:?}",
-            hand.capabilities
+            properties2.capabilities
         );
-        Ok(
-            (hand.capabilities & PassthroughCapabilityFlagsFB::PASSTHROUGH_CAPABILITY)
-                == PassthroughCapabilityFlagsFB::PASSTHROUGH_CAPABILITY,
-        )
+        Ok(properties2
+            .capabilities
+            .contains(PassthroughCapabilityFlagsFB::PASSTHROUGH_CAPABILITY)
+            || properties.supports_passthrough.into())
     }
 }
 
@@ -195,7 +202,8 @@ pub fn supports_passthrough(instance: &XrInstance, system: xr::SystemId) -> xr::
 pub fn create_passthrough(
     xr_session: &XrSession,
 ) -> xr::Result<(xr::Passthrough, xr::PassthroughLayer)> {
-    let flags = xr::PassthroughFlagsFB::IS_RUNNING_AT_CREATION;
+    // the layer starts out paused to match `XrPassthroughState::Paused`
+    let flags = xr::PassthroughFlagsFB::EMPTY;
     let purpose = xr::PassthroughLayerPurposeFB::RECONSTRUCTION;
     let passthrough = match xr_session {
         #[cfg(feature = "vulkan")]
@@ -206,6 +214,10 @@ pub fn create_passthrough(
         XrSession::D3D12(session) => {
             session.create_passthrough(xr::PassthroughFlagsFB::IS_RUNNING_AT_CREATION)
         }
+        #[cfg(all(feature = "gles", target_os = "android"))]
+        XrSession::Gles(session) => {
+            session.create_passthrough(xr::PassthroughFlagsFB::IS_RUNNING_AT_CREATION)
+        }
     }?;
     let passthrough_layer = match xr_session {
         #[cfg(feature = "vulkan")]
@@ -214,6 +226,8 @@ pub fn create_passthrough(
         }
         #[cfg(all(feature = "d3d12", windows))]
         XrSession::D3D12(session) => session.create_passthrough_layer(&passthrough, flags, purpose),
+        #[cfg(all(feature = "gles", target_os = "android"))]
+        XrSession::Gles(session) => session.create_passthrough_layer(&passthrough, flags, purpose),
     }?;
     Ok((passthrough, passthrough_layer))
 }
@@ -0,0 +1,352 @@
+//! A small performance hud in front of the head with the frame times, the dropped frames, the
+//! gpu time of each eye, the render scale and the state of the session.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy::transform::TransformSystem;
+
+use crate::dynamic_resolution::XrViewportScale;
+use crate::frame_diagnostics::XrFrameDiagnostics;
+use crate::graphics::XrSessionConfig;
+use crate::layers::{XrCompositionLayer, XrCompositionLayerBundle};
+use crate::xr_init::{xr_only, XrSessionState, XrStatus};
+use crate::xr_input::actions::XrButton;
+use crate::xr_input::trackers::{OpenXRHMD, OpenXRTrackingRoot};
+use crate::xr_input::xr_camera::RootTransform;
+
+/// Frame times shown by the graph
+const GRAPH_SAMPLES: usize = 64;
+
+/// Shows the [`XrPerformanceHud`] in its own quad [composition layer](crate::layers), so it
+/// costs the same however long the scene takes to render and stays readable when frames are
+/// dropped. The gpu time of the eyes needs `gpu_timing` in the
+/// [`DefaultXrPlugins`](crate::DefaultXrPlugins).
+#[derive(Default)]
+pub struct XrPerformanceHudPlugin {
+    /// Pressing the button shows or hides the hud
+    pub toggle: Option<XrButton>,
+}
+
+impl Plugin for XrPerformanceHudPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<XrPerformanceHud>();
+        app.init_resource::<XrPerformanceHud>();
+        app.init_resource::<HudSamples>();
+        app.add_systems(Startup, spawn_hud);
+        if let Some(button) = self.toggle {
+            app.add_systems(
+                Update,
+                (move |buttons: Option<Res<ButtonInput<XrButton>>>,
+                       mut hud: ResMut<XrPerformanceHud>| {
+                    if buttons.is_some_and(|buttons| buttons.just_pressed(button)) {
+                        hud.enabled = !hud.enabled;
+                    }
+                })
+                .before(apply_hud_settings),
+            );
+        }
+        app.add_systems(
+            Update,
+            (
+                apply_hud_settings.run_if(resource_changed::<XrPerformanceHud>),
+                (record_frame_time, update_hud).chain().run_if(xr_only()),
+            )
+                .chain(),
+        );
+        app.add_systems(
+            PostUpdate,
+            follow_head
+                .run_if(xr_only())
+                .after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Settings of the hud, changes apply right away
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct XrPerformanceHud {
+    pub enabled: bool,
+    /// Where the hud is relative to the head, it faces its +Z axis
+    pub offset: Transform,
+    /// Size of the hud in meters
+    pub size: Vec2,
+    /// Resolution of the swapchain, small ones keep the cost of the hud low
+    pub resolution: UVec2,
+    /// How often the text and the graph are redrawn
+    pub refresh_interval: Duration,
+    /// See [`XrCompositionLayer::exclude_from_capture`], the frames the app captures and the
+    /// mirror window never show the hud
+    pub exclude_from_capture: bool,
+}
+
+impl Default for XrPerformanceHud {
+    fn default() -> Self {
+        let position = Vec3::new(0.0, -0.2, -0.7);
+        Self {
+            enabled: true,
+            // below the view direction, tilted towards the eyes
+            offset: Transform::from_translation(position).looking_to(position, Vec3::Y),
+            size: Vec2::new(0.3, 0.15),
+            resolution: UVec2::new(384, 192),
+            refresh_interval: Duration::from_millis(100),
+            exclude_from_capture: false,
+        }
+    }
+}
+
+/// The frame times of the last frames and whether they were dropped, the newest last
+#[derive(Resource, Default)]
+struct HudSamples(VecDeque<(Duration, bool)>);
+
+#[derive(Component)]
+struct PerformanceHud;
+
+#[derive(Component)]
+struct HudText;
+
+#[derive(Component)]
+struct HudBar(usize);
+
+fn spawn_hud(mut commands: Commands, settings: Res<XrPerformanceHud>) {
+    let camera = commands
+        .spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    is_active: false,
+                    clear_color: ClearColorConfig::Custom(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+                    ..default()
+                },
+                ..default()
+            },
+            // only the ui of the hud is rendered
+            RenderLayers::none(),
+        ))
+        .id();
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(8.0)),
+                    row_gap: Val::Px(6.0),
+                    ..default()
+                },
+                ..default()
+            },
+            TargetCamera(camera),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                HudText,
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        flex_grow: 1.0,
+                        align_items: AlignItems::FlexEnd,
+                        ..default()
+                    },
+                    background_color: Color::rgba(1.0, 1.0, 1.0, 0.05).into(),
+                    ..default()
+                })
+                .with_children(|graph| {
+                    // the graph is two display periods high, the line marks one
+                    graph.spawn(NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            bottom: Val::Percent(50.0),
+                            width: Val::Percent(100.0),
+                            height: Val::Px(1.0),
+                            ..default()
+                        },
+                        background_color: Color::rgba(1.0, 1.0, 1.0, 0.4).into(),
+                        ..default()
+                    });
+                    for index in 0..GRAPH_SAMPLES {
+                        graph.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Percent(100.0 / GRAPH_SAMPLES as f32),
+                                    height: Val::Percent(0.0),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            HudBar(index),
+                        ));
+                    }
+                });
+        });
+    let mut layer = XrCompositionLayer::quad(settings.size, settings.resolution)
+        .with_camera(camera)
+        // on top of all other layers
+        .with_sort_order(i32::MAX);
+    layer.exclude_from_capture = settings.exclude_from_capture;
+    let mut bundle = XrCompositionLayerBundle::new(layer, settings.offset);
+    if !settings.enabled {
+        bundle.spatial.visibility = Visibility::Hidden;
+    }
+    commands.spawn((bundle, PerformanceHud));
+}
+
+fn apply_hud_settings(
+    settings: Res<XrPerformanceHud>,
+    mut huds: Query<(&mut XrCompositionLayer, &mut Visibility), With<PerformanceHud>>,
+) {
+    for (mut layer, mut visibility) in &mut huds {
+        // hiding the layer also stops its camera
+        visibility.set_if_neq(match settings.enabled {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        });
+        let shape = XrCompositionLayer::quad(settings.size, settings.resolution).shape;
+        if layer.shape != shape
+            || layer.resolution != settings.resolution
+            || layer.exclude_from_capture != settings.exclude_from_capture
+        {
+            layer.shape = shape;
+            layer.resolution = settings.resolution;
+            layer.exclude_from_capture = settings.exclude_from_capture;
+        }
+    }
+}
+
+fn record_frame_time(
+    time: Res<Time>,
+    settings: Res<XrPerformanceHud>,
+    diagnostics: Option<Res<XrFrameDiagnostics>>,
+    mut samples: ResMut<HudSamples>,
+) {
+    if !settings.enabled {
+        samples.0.clear();
+        return;
+    }
+    let dropped = diagnostics.is_some_and(|diagnostics| diagnostics.dropped);
+    samples.0.push_back((time.delta(), dropped));
+    if samples.0.len() > GRAPH_SAMPLES {
+        samples.0.pop_front();
+    }
+}
+
+fn milliseconds(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_hud(
+    time: Res<Time>,
+    settings: Res<XrPerformanceHud>,
+    samples: Res<HudSamples>,
+    diagnostics: Option<Res<XrFrameDiagnostics>>,
+    session_config: Option<Res<XrSessionConfig>>,
+    viewport_scale: Option<Res<XrViewportScale>>,
+    status: Res<XrStatus>,
+    session_state: Option<Res<XrSessionState>>,
+    mut since_refresh: Local<Duration>,
+    mut texts: Query<&mut Text, With<HudText>>,
+    mut bars: Query<(&HudBar, &mut Style, &mut BackgroundColor)>,
+) {
+    *since_refresh += time.delta();
+    if !settings.enabled || *since_refresh < settings.refresh_interval {
+        return;
+    }
+    *since_refresh = Duration::ZERO;
+    let diagnostics = diagnostics
+        .map(|diagnostics| *diagnostics)
+        .unwrap_or_default();
+    let display_period = match diagnostics.predicted_display_period {
+        Duration::ZERO => Duration::from_secs_f32(1.0 / 90.0),
+        period => period,
+    };
+
+    let frame_time = samples
+        .0
+        .iter()
+        .map(|(frame_time, _)| *frame_time)
+        .sum::<Duration>()
+        .checked_div(samples.0.len() as u32)
+        .unwrap_or_default();
+    let gpu_time = |time: Option<Duration>| {
+        time.map_or("-".to_string(), |time| {
+            format!("{:.1} ms", milliseconds(time))
+        })
+    };
+    let render_scale = session_config.map_or(1.0, |config| config.render_scale);
+    let viewport_scale = viewport_scale.map_or(1.0, |scale| **scale);
+    let session_state = session_state.map_or("-".to_string(), |state| format!("{:?}", **state));
+    let text = format!(
+        "frame {:.1} ms / {:.1} ms\ndropped {} of {}\ngpu left {}, right {}\nscale {:.2} x {:.2}\n{:?} {}",
+        milliseconds(frame_time),
+        milliseconds(display_period),
+        diagnostics.dropped_frames,
+        diagnostics.frames,
+        gpu_time(diagnostics.left_eye_gpu_time),
+        gpu_time(diagnostics.right_eye_gpu_time),
+        render_scale,
+        viewport_scale,
+        *status,
+        session_state,
+    );
+    for mut hud_text in &mut texts {
+        hud_text.sections[0].value.clone_from(&text);
+    }
+
+    // the newest sample is on the right
+    let first = GRAPH_SAMPLES - samples.0.len();
+    for (bar, mut style, mut color) in &mut bars {
+        let Some((frame_time, dropped)) = bar.0.checked_sub(first).map(|index| samples.0[index])
+        else {
+            style.height = Val::Percent(0.0);
+            continue;
+        };
+        let height = frame_time.as_secs_f32() / (2.0 * display_period.as_secs_f32());
+        style.height = Val::Percent(height.min(1.0) * 100.0);
+        color.0 = match dropped {
+            true => Color::rgb(0.9, 0.2, 0.2),
+            false if frame_time > display_period => Color::rgb(0.9, 0.8, 0.2),
+            false => Color::rgb(0.3, 0.8, 0.3),
+        };
+    }
+}
+
+/// Places the hud at its offset from the head pose of this frame. Runs after the transforms were
+/// propagated, so the layer is submitted relative to the same tracking root as the views.
+#[allow(clippy::type_complexity)]
+fn follow_head(
+    settings: Res<XrPerformanceHud>,
+    root: Query<&GlobalTransform, With<OpenXRTrackingRoot>>,
+    head: Query<&Transform, (With<OpenXRHMD>, Without<OpenXRTrackingRoot>)>,
+    mut huds: Query<
+        (&mut Transform, &mut GlobalTransform, &mut RootTransform),
+        (
+            With<PerformanceHud>,
+            Without<OpenXRHMD>,
+            Without<OpenXRTrackingRoot>,
+        ),
+    >,
+) {
+    let (Ok(root), Ok(head)) = (root.get_single(), head.get_single()) else {
+        return;
+    };
+    let pose = root.mul_transform(head.mul_transform(settings.offset));
+    for (mut transform, mut global_transform, mut root_transform) in &mut huds {
+        *transform = pose.compute_transform();
+        *global_transform = pose;
+        **root_transform = *root;
+    }
+}
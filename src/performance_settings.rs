@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::resources::XrSession;
+use crate::xr_init::{xr_only, XrSetup};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum XrPerformanceDomain {
+    Cpu,
+    Gpu,
+}
+
+/// How much the runtime should prioritize performance over power and heat
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum XrPerformanceLevel {
+    /// For menus and loading screens, power savings are prioritized
+    PowerSavings,
+    /// For simple scenes, an occasional late frame is fine if it saves power
+    SustainedLow,
+    /// For complex scenes, frames should be on time within the thermal limits
+    #[default]
+    SustainedHigh,
+    /// For very complex sections, the runtime may go beyond the thermal limits for a while
+    Boost,
+}
+
+/// Which part of the frame a [`XrPerformanceNotification`] is about
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum XrPerformanceSubDomain {
+    Compositing,
+    Rendering,
+    Thermal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub enum XrPerformanceNotificationLevel {
+    Normal,
+    /// The runtime is close to not keeping up, the app should reduce its workload
+    Warning,
+    /// The runtime doesn't keep up anymore and might throttle or reproject
+    Impaired,
+}
+
+/// Sent when the runtime moves a domain to a different notification level,
+/// needs `XR_EXT_performance_settings`
+#[derive(Clone, Copy, Debug, Event)]
+pub struct XrPerformanceNotification {
+    pub domain: XrPerformanceDomain,
+    pub sub_domain: XrPerformanceSubDomain,
+    pub from: XrPerformanceNotificationLevel,
+    pub to: XrPerformanceNotificationLevel,
+}
+
+impl XrPerformanceNotification {
+    /// `None` if the runtime reported values this version doesn't know about
+    pub fn from_raw(event: xr::PerfSettingsEXT) -> Option<Self> {
+        let level = |level: xr::PerfSettingsNotificationLevelEXT| {
+            Some(match level {
+                xr::PerfSettingsNotificationLevelEXT::NORMAL => {
+                    XrPerformanceNotificationLevel::Normal
+                }
+                xr::PerfSettingsNotificationLevelEXT::WARNING => {
+                    XrPerformanceNotificationLevel::Warning
+                }
+                xr::PerfSettingsNotificationLevelEXT::IMPAIRED => {
+                    XrPerformanceNotificationLevel::Impaired
+                }
+                _ => return None,
+            })
+        };
+        Some(Self {
+            domain: match event.domain() {
+                xr::PerfSettingsDomainEXT::CPU => XrPerformanceDomain::Cpu,
+                xr::PerfSettingsDomainEXT::GPU => XrPerformanceDomain::Gpu,
+                _ => return None,
+            },
+            sub_domain: match event.sub_domain() {
+                xr::PerfSettingsSubDomainEXT::COMPOSITING => XrPerformanceSubDomain::Compositing,
+                xr::PerfSettingsSubDomainEXT::RENDERING => XrPerformanceSubDomain::Rendering,
+                xr::PerfSettingsSubDomainEXT::THERMAL => XrPerformanceSubDomain::Thermal,
+                _ => return None,
+            },
+            from: level(event.from_level())?,
+            to: level(event.to_level())?,
+        })
+    }
+}
+
+impl From<XrPerformanceDomain> for xr::PerfSettingsDomainEXT {
+    fn from(value: XrPerformanceDomain) -> Self {
+        match value {
+            XrPerformanceDomain::Cpu => xr::PerfSettingsDomainEXT::CPU,
+            XrPerformanceDomain::Gpu => xr::PerfSettingsDomainEXT::GPU,
+        }
+    }
+}
+
+impl From<XrPerformanceLevel> for xr::PerfSettingsLevelEXT {
+    fn from(value: XrPerformanceLevel) -> Self {
+        match value {
+            XrPerformanceLevel::PowerSavings => xr::PerfSettingsLevelEXT::POWER_SAVINGS,
+            XrPerformanceLevel::SustainedLow => xr::PerfSettingsLevelEXT::SUSTAINED_LOW,
+            XrPerformanceLevel::SustainedHigh => xr::PerfSettingsLevelEXT::SUSTAINED_HIGH,
+            XrPerformanceLevel::Boost => xr::PerfSettingsLevelEXT::BOOST,
+        }
+    }
+}
+
+impl XrSession {
+    /// Hints the runtime how to clock the cpu or gpu.
+    /// Returns `ERROR_EXTENSION_NOT_PRESENT` if `XR_EXT_performance_settings` isn't enabled.
+    pub fn set_performance_level(
+        &self,
+        domain: XrPerformanceDomain,
+        level: XrPerformanceLevel,
+    ) -> xr::Result<()> {
+        let Some(performance_settings) = self.instance().exts().ext_performance_settings else {
+            return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+        };
+        let result = unsafe {
+            (performance_settings.perf_settings_set_performance_level)(
+                self.as_raw(),
+                domain.into(),
+                level.into(),
+            )
+        };
+        match result.into_raw() < 0 {
+            true => Err(result),
+            false => Ok(()),
+        }
+    }
+}
+
+/// The performance levels requested from the runtime, changing them sends the new levels.
+/// They're also sent when a session starts, if `XR_EXT_performance_settings` is enabled,
+/// see [`XrExtensions::enable_performance_settings`](crate::graphics::extensions::XrExtensions::enable_performance_settings).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct XrPerformanceLevels {
+    pub cpu: XrPerformanceLevel,
+    pub gpu: XrPerformanceLevel,
+}
+
+pub struct PerformanceSettingsPlugin;
+
+impl Plugin for PerformanceSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<XrPerformanceLevels>();
+        app.init_resource::<XrPerformanceLevels>();
+        app.add_systems(XrSetup, setup_performance_levels);
+        app.add_systems(
+            PostUpdate,
+            apply_performance_levels
+                .run_if(xr_only())
+                .run_if(resource_changed::<XrPerformanceLevels>),
+        );
+    }
+}
+
+fn setup_performance_levels(mut levels: ResMut<XrPerformanceLevels>, session: Res<XrSession>) {
+    // the levels of a new session are only changed if the app opted in with the extension
+    if session.instance().exts().ext_performance_settings.is_some() {
+        levels.set_changed();
+    }
+}
+
+fn apply_performance_levels(levels: Res<XrPerformanceLevels>, session: Res<XrSession>) {
+    for (domain, level) in [
+        (XrPerformanceDomain::Cpu, levels.cpu),
+        (XrPerformanceDomain::Gpu, levels.gpu),
+    ] {
+        match session.set_performance_level(domain, level) {
+            Ok(()) => info!("Set {:?} performance level to {:?}", domain, level),
+            Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT) => {
+                warn!(
+                    "Unable to set performance levels: XR_EXT_performance_settings is not enabled"
+                );
+                return;
+            }
+            Err(err) => warn!(
+                "Unable to set {:?} performance level to {:?}: {}",
+                domain, level, err
+            ),
+        }
+    }
+}
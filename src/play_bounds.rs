@@ -0,0 +1,380 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::PrimitiveTopology;
+use openxr as xr;
+
+use crate::input::{XrInput, XrReferenceSpaceChanged};
+use crate::resources::{XrFrameState, XrInstance, XrSession};
+use crate::xr_init::{xr_only, XrCleanup};
+use crate::xr_input::trackers::OpenXRTrackingRoot;
+use crate::xr_input::{QuatConv, Vec3Conv};
+
+/// The rectangle the user can move in without leaving the boundary they set up.
+/// Only exists while the runtime reports bounds, seated and unbounded setups have none.
+///
+/// Updated after the runtime changed the reference spaces, for example because the user redrew
+/// the boundary, and after the stage was recentered.
+/// Positions are relative to the stage space, which is the local space of the
+/// [`OpenXRTrackingRoot`](crate::xr_input::trackers::OpenXRTrackingRoot).
+#[derive(Clone, Copy, Debug, PartialEq, Resource)]
+pub struct XrPlayBounds {
+    /// Width along the x axis and depth along the z axis of `origin`, in meters
+    pub size: Vec2,
+    /// The center of the rectangle on the floor, the rectangle is aligned with its axes
+    pub origin: Transform,
+}
+
+impl XrPlayBounds {
+    /// Horizontal distance of `point` to the closest edge, negative if it's outside of the bounds
+    pub fn distance_to_edge(&self, point: Vec3) -> f32 {
+        let local = self
+            .origin
+            .compute_affine()
+            .inverse()
+            .transform_point3(point);
+        let half_size = self.size / 2.0;
+        (half_size.x - local.x.abs()).min(half_size.y - local.z.abs())
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        self.distance_to_edge(point) >= 0.0
+    }
+
+    /// The corners of the rectangle on the floor, counter clockwise when seen from above
+    pub fn corners(&self) -> [Vec3; 4] {
+        let half_size = self.size / 2.0;
+        [
+            Vec3::new(-half_size.x, 0.0, -half_size.y),
+            Vec3::new(-half_size.x, 0.0, half_size.y),
+            Vec3::new(half_size.x, 0.0, half_size.y),
+            Vec3::new(half_size.x, 0.0, -half_size.y),
+        ]
+        .map(|corner| self.origin.transform_point(corner))
+    }
+
+    /// The rectangle as a mesh relative to `origin`, `None` if it has no area
+    pub fn mesh(&self, style: XrPlayAreaStyle) -> Option<Mesh> {
+        let half_size = self.size / 2.0;
+        play_area_mesh(
+            &[
+                Vec2::new(-half_size.x, -half_size.y),
+                Vec2::new(-half_size.x, half_size.y),
+                Vec2::new(half_size.x, half_size.y),
+                Vec2::new(half_size.x, -half_size.y),
+            ],
+            style,
+        )
+    }
+}
+
+/// How the play area is shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum XrPlayAreaStyle {
+    /// The area on the floor
+    Floor,
+    /// Walls along the edges, facing inwards
+    Walls { height: f32 },
+}
+
+/// A mesh of the play area with the floor outline `outline`, the x and y of its vertices are
+/// the x and z on the floor. The outline has to be convex, like the boundary of a room.
+/// `None` if the outline has no area, like the bounds of seated setups.
+pub fn play_area_mesh(outline: &[Vec2], style: XrPlayAreaStyle) -> Option<Mesh> {
+    let area = outline
+        .iter()
+        .zip(outline.iter().cycle().skip(1))
+        .map(|(a, b)| a.perp_dot(*b))
+        .sum::<f32>()
+        / 2.0;
+    if outline.len() < 3 || area.abs() < f32::EPSILON {
+        return None;
+    }
+    // the triangles and normals below expect the winding of XrPlayBounds::corners
+    let mut outline = outline
+        .iter()
+        .map(|v| Vec3::new(v.x, 0.0, v.y))
+        .collect::<Vec<_>>();
+    if area > 0.0 {
+        outline.reverse();
+    }
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+    match style {
+        XrPlayAreaStyle::Floor => {
+            for vertex in &outline {
+                positions.push(vertex.to_array());
+                normals.push([0.0, 1.0, 0.0]);
+                uvs.push([vertex.x, vertex.z]);
+            }
+            for i in 1..outline.len() as u32 - 1 {
+                indices.extend([0, i, i + 1]);
+            }
+        }
+        XrPlayAreaStyle::Walls { height } => {
+            let mut length = 0.0;
+            for (a, b) in outline.iter().zip(outline.iter().cycle().skip(1)) {
+                let inwards = Vec3::Y.cross(*b - *a).normalize_or_zero().to_array();
+                let up = Vec3::Y * height;
+                let first = positions.len() as u32;
+                let next_length = length + a.distance(*b);
+                positions.extend([*a, *b, *b + up, *a + up].map(|v| v.to_array()));
+                normals.extend([inwards; 4]);
+                uvs.extend([
+                    [length, 0.0],
+                    [next_length, 0.0],
+                    [next_length, height],
+                    [length, height],
+                ]);
+                indices.extend([first, first + 2, first + 1, first, first + 3, first + 2]);
+                length = next_length;
+            }
+        }
+    }
+    Some(
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices)),
+    )
+}
+
+impl XrSession {
+    /// Size of the bounds rectangle of a reference space, centered on its origin.
+    /// `None` if the runtime doesn't know the bounds, like for seated or unbounded setups.
+    pub fn reference_space_bounds(&self, ty: xr::ReferenceSpaceType) -> xr::Result<Option<Vec2>> {
+        Ok(self
+            .reference_space_bounds_rect(ty)?
+            .map(|extent| Vec2::new(extent.width, extent.height)))
+    }
+}
+
+pub struct PlayBoundsPlugin;
+
+impl Plugin for PlayBoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(XrCleanup, cleanup_play_bounds);
+        app.add_systems(
+            PreUpdate,
+            (
+                mark_play_bounds_outdated.run_if(
+                    on_event::<XrReferenceSpaceChanged>()
+                        .or_else(resource_exists_and_changed::<XrInput>),
+                ),
+                update_play_bounds.run_if(resource_exists::<PlayBoundsOutdated>),
+            )
+                .chain()
+                .run_if(xr_only())
+                .after(crate::xr_poll_events),
+        );
+    }
+}
+
+/// Spawns the [`XrPlayBounds`] as an [`XrPlayArea`] mesh under the
+/// [`OpenXRTrackingRoot`] and replaces it when the bounds change.
+/// Nothing is spawned while there are no bounds.
+pub struct XrPlayAreaPlugin {
+    pub style: XrPlayAreaStyle,
+    pub material: StandardMaterial,
+}
+
+impl Default for XrPlayAreaPlugin {
+    fn default() -> Self {
+        Self {
+            style: XrPlayAreaStyle::Walls { height: 2.0 },
+            material: StandardMaterial {
+                base_color: Color::rgba(0.2, 0.6, 1.0, 0.2),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            },
+        }
+    }
+}
+
+impl Plugin for XrPlayAreaPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(XrPlayAreaSettings {
+            style: self.style,
+            material: self.material.clone(),
+            material_handle: None,
+        });
+        app.add_systems(Update, update_play_area);
+    }
+}
+
+/// The mesh of the play area spawned by [`XrPlayAreaPlugin`]
+#[derive(Component, Clone, Copy, Debug)]
+pub struct XrPlayArea;
+
+#[derive(Resource)]
+struct XrPlayAreaSettings {
+    style: XrPlayAreaStyle,
+    material: StandardMaterial,
+    /// Added once the first area is spawned
+    material_handle: Option<Handle<StandardMaterial>>,
+}
+
+fn update_play_area(
+    mut commands: Commands,
+    bounds: Option<Res<XrPlayBounds>>,
+    mut settings: ResMut<XrPlayAreaSettings>,
+    areas: Query<Entity, With<XrPlayArea>>,
+    root: Query<Entity, With<OpenXRTrackingRoot>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let outdated = match &bounds {
+        Some(bounds) => bounds.is_changed() || areas.is_empty(),
+        None => !areas.is_empty(),
+    };
+    if !outdated {
+        return;
+    }
+    let Ok(root) = root.get_single() else {
+        return;
+    };
+    for entity in &areas {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(bounds) = bounds else {
+        return;
+    };
+    let Some(mesh) = bounds.mesh(settings.style) else {
+        return;
+    };
+    let settings = &mut *settings;
+    let material = settings
+        .material_handle
+        .get_or_insert_with(|| materials.add(settings.material.clone()))
+        .clone();
+    let area = commands
+        .spawn((
+            PbrBundle {
+                mesh: meshes.add(mesh),
+                material,
+                transform: bounds.origin,
+                ..default()
+            },
+            XrPlayArea,
+        ))
+        .id();
+    commands.entity(root).add_child(area);
+}
+
+/// The bounds are fetched again once the frame loop provides a valid time
+#[derive(Resource)]
+struct PlayBoundsOutdated;
+
+fn mark_play_bounds_outdated(mut commands: Commands) {
+    commands.insert_resource(PlayBoundsOutdated);
+}
+
+fn cleanup_play_bounds(mut commands: Commands) {
+    commands.remove_resource::<XrPlayBounds>();
+    commands.remove_resource::<PlayBoundsOutdated>();
+}
+
+fn update_play_bounds(
+    mut commands: Commands,
+    session: Res<XrSession>,
+    xr_input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+    play_bounds: Option<Res<XrPlayBounds>>,
+) {
+    if frame_state.predicted_display_time.as_nanos() == 0 {
+        return;
+    }
+    // the runtime only reports bounds for the stage space
+    let size = match session.reference_space_bounds(xr::ReferenceSpaceType::STAGE) {
+        Ok(Some(size)) => size,
+        Ok(None) => {
+            commands.remove_resource::<XrPlayBounds>();
+            commands.remove_resource::<PlayBoundsOutdated>();
+            return;
+        }
+        Err(err) => {
+            warn!("Unable to query the play bounds: {}", err);
+            commands.remove_resource::<PlayBoundsOutdated>();
+            return;
+        }
+    };
+    let location = session
+        .create_reference_space(xr::ReferenceSpaceType::STAGE, xr::Posef::IDENTITY)
+        .and_then(|bounds_space| {
+            bounds_space.locate(&xr_input.stage, frame_state.predicted_display_time)
+        });
+    let location = match location {
+        Ok(location) => location,
+        Err(err) => {
+            warn!("Unable to locate the play bounds: {}", err);
+            commands.remove_resource::<PlayBoundsOutdated>();
+            return;
+        }
+    };
+    if !location.location_flags.contains(
+        xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
+    ) {
+        // tracking isn't ready yet, try again next frame
+        return;
+    }
+    commands.remove_resource::<PlayBoundsOutdated>();
+    let bounds = XrPlayBounds {
+        size,
+        origin: Transform::from_translation(location.pose.position.to_vec3())
+            .with_rotation(location.pose.orientation.to_quat()),
+    };
+    if play_bounds.as_deref() != Some(&bounds) {
+        info!("Play bounds: {} x {} m", size.x, size.y);
+        commands.insert_resource(bounds);
+    }
+}
+
+/// The boundary polygon of a scene anchor space, like the floor of the room the user captured.
+/// The vertices are in the xy plane of `space`.
+/// Needs `XR_FB_scene`, returns `ERROR_EXTENSION_NOT_PRESENT` if it isn't enabled.
+pub fn space_boundary_2d(
+    instance: &XrInstance,
+    session: &XrSession,
+    space: &xr::Space,
+) -> xr::Result<Vec<Vec2>> {
+    let Some(scene) = instance.exts().fb_scene else {
+        return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let mut boundary = xr::sys::Boundary2DFB {
+        ty: xr::sys::Boundary2DFB::TYPE,
+        next: ptr::null(),
+        vertex_capacity_input: 0,
+        vertex_count_output: 0,
+        vertices: ptr::null_mut(),
+    };
+    // the first call only returns the number of vertices
+    let get_boundary = |boundary: &mut xr::sys::Boundary2DFB| {
+        let result =
+            unsafe { (scene.get_space_boundary2_d)(session.as_raw(), space.as_raw(), boundary) };
+        match result.into_raw() < 0 {
+            true => Err(result),
+            false => Ok(()),
+        }
+    };
+    get_boundary(&mut boundary)?;
+    let mut vertices = vec![xr::Vector2f::default(); boundary.vertex_count_output as usize];
+    boundary.vertex_capacity_input = vertices.len() as u32;
+    boundary.vertices = vertices.as_mut_ptr();
+    get_boundary(&mut boundary)?;
+    vertices.truncate(boundary.vertex_count_output as usize);
+    Ok(vertices
+        .into_iter()
+        .map(|vertex| Vec2::new(vertex.x, vertex.y))
+        .collect())
+}
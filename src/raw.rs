@@ -0,0 +1,81 @@
+//! The OpenXR handles behind the plugin's resources, for calling extensions the plugin doesn't
+//! wrap. The plugin still owns them: they must not be destroyed or kept across sessions, and the
+//! frame loop, the session state, action syncing and the calls the plugin makes are left to it.
+
+use openxr as xr;
+
+use crate::input::XrInput;
+use crate::resources::{XrInstance, XrSession, XrSwapchain};
+use crate::xr_input::actions::XrActionSets;
+
+pub fn instance(instance: &XrInstance) -> &xr::Instance {
+    instance
+}
+
+/// The session without its graphics api, enough for everything but swapchains. It's begun and
+/// ended through [`StartXrSession`](crate::xr_init::StartXrSession) and
+/// [`EndXrSession`](crate::xr_init::EndXrSession), not directly.
+pub fn session(session: &XrSession) -> &xr::Session<xr::AnyGraphics> {
+    session
+}
+
+/// `None` if the session doesn't use Vulkan
+#[cfg(feature = "vulkan")]
+pub fn vulkan_session(session: &XrSession) -> Option<&xr::Session<xr::Vulkan>> {
+    match session {
+        XrSession::Vulkan(session) => Some(session),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+/// `None` if the session doesn't use D3D12
+#[cfg(all(feature = "d3d12", windows))]
+pub fn d3d12_session(session: &XrSession) -> Option<&xr::Session<xr::D3D12>> {
+    match session {
+        XrSession::D3D12(session) => Some(session),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+/// `None` if the session doesn't use OpenGL ES
+#[cfg(all(feature = "gles", target_os = "android"))]
+pub fn gles_session(session: &XrSession) -> Option<&xr::Session<xr::OpenGlEs>> {
+    match session {
+        XrSession::Gles(session) => Some(session),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+/// The space views and tracked poses are located in, see [`XrInput::stage_type`]
+pub fn reference_space(input: &XrInput) -> xr::sys::Space {
+    input.stage.as_raw()
+}
+
+/// The space of the headset
+pub fn view_space(input: &XrInput) -> xr::sys::Space {
+    input.head.as_raw()
+}
+
+/// The color swapchain the xr cameras render into. Its images are acquired and released by the
+/// frame loop, other layers are added through [`layers`](crate::layers).
+pub fn swapchain(swapchain: &XrSwapchain) -> xr::sys::Swapchain {
+    swapchain.as_raw()
+}
+
+/// `None` if there is no set with that name. The sets are attached and synced by
+/// [`XrActionSets`].
+pub fn action_set(action_sets: &XrActionSets, action_set: &str) -> Option<xr::sys::ActionSet> {
+    action_sets.raw_action_set(action_set)
+}
+
+/// `None` if there is no such action in the set
+pub fn action(
+    action_sets: &XrActionSets,
+    action_set: &str,
+    action_name: &str,
+) -> Option<xr::sys::Action> {
+    action_sets.raw_action(action_set, action_name)
+}
@@ -0,0 +1,573 @@
+//! Records the tracked poses and the input of every frame and plays them back through the
+//! [simulator](crate::simulator), for reproducing bugs and for tests without a headset.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use crate::simulator::{SimulatedController, SimulatedEye, SimulatedHead, XrSimulatorPlugin};
+use crate::xr_input::actions::{XrActionSync, XrButton};
+use crate::xr_input::tracked_controllers::{XrController, XrControllerInput};
+use crate::xr_input::trackers::{OpenXRLeftEye, OpenXRRightEye, OpenXRTrackingRoot};
+use crate::xr_input::{Hand, XrTrackingUpdate};
+
+const MAGIC: &[u8; 6] = b"OXRREC";
+const VERSION: u8 = 1;
+
+/// Recorded frames, stored in a compact little endian binary format
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XrRecording {
+    pub frames: Vec<XrRecordedFrame>,
+}
+
+/// The state of a frame once the tracking was updated. Poses are relative to the
+/// [`OpenXRTrackingRoot`], so moving the player is left to the app, like with a headset.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XrRecordedFrame {
+    /// Time since the recording started
+    pub time: Duration,
+    /// The left and right eye
+    pub views: [Transform; 2],
+    /// The left and right controller, `None` while it was inactive
+    pub controllers: [Option<XrRecordedController>; 2],
+    /// The buttons that were pressed
+    pub buttons: Vec<XrButton>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrRecordedController {
+    pub transform: Transform,
+    pub input: XrControllerInput,
+}
+
+impl XrRecording {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// The button names are stored once in a table at the start, the frames only hold
+    /// their indices
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        let mut buttons = Vec::new();
+        let mut indices = HashMap::new();
+        for button in self.frames.iter().flat_map(|frame| &frame.buttons) {
+            indices.entry(*button).or_insert_with(|| {
+                buttons.push(*button);
+                buttons.len() - 1
+            });
+        }
+        write_len(writer, buttons.len())?;
+        for button in &buttons {
+            write_str(writer, button.action_set)?;
+            write_str(writer, button.action)?;
+        }
+        writer.write_all(&(self.frames.len() as u32).to_le_bytes())?;
+        for frame in &self.frames {
+            writer.write_all(&(frame.time.as_nanos() as u64).to_le_bytes())?;
+            for view in &frame.views {
+                write_transform(writer, view)?;
+            }
+            let present = frame
+                .controllers
+                .iter()
+                .enumerate()
+                .fold(0u8, |bits, (i, c)| bits | (c.is_some() as u8) << i);
+            writer.write_all(&[present])?;
+            for controller in frame.controllers.iter().flatten() {
+                write_transform(writer, &controller.transform)?;
+                let input = &controller.input;
+                write_f32s(
+                    writer,
+                    &[
+                        input.trigger,
+                        input.squeeze,
+                        input.thumbstick.x,
+                        input.thumbstick.y,
+                    ],
+                )?;
+                let bits = [
+                    input.trigger_touched,
+                    input.thumbstick_click,
+                    input.thumbstick_touched,
+                    input.primary_button,
+                    input.secondary_button,
+                ]
+                .iter()
+                .enumerate()
+                .fold(0u8, |bits, (i, set)| bits | (*set as u8) << i);
+                writer.write_all(&[bits])?;
+            }
+            write_len(writer, frame.buttons.len())?;
+            for button in &frame.buttons {
+                write_len(writer, indices[button])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The names of the buttons are leaked, as [`XrButton`] needs them to be `'static`.
+    /// That's once per distinct button in the recording.
+    pub fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0; 6];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a bevy_oxr recording"));
+        }
+        let version = read_array::<1>(reader)?[0];
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported recording version {}",
+                version
+            )));
+        }
+        let mut buttons = Vec::new();
+        for _ in 0..read_len(reader)? {
+            let action_set = Box::leak(read_str(reader)?.into_boxed_str());
+            let action = Box::leak(read_str(reader)?.into_boxed_str());
+            buttons.push(XrButton::new(action_set, action));
+        }
+        let count = u32::from_le_bytes(read_array(reader)?);
+        let mut frames = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let time = Duration::from_nanos(u64::from_le_bytes(read_array(reader)?));
+            let views = [read_transform(reader)?, read_transform(reader)?];
+            let present = read_array::<1>(reader)?[0];
+            let mut controllers = [None; 2];
+            for (i, controller) in controllers.iter_mut().enumerate() {
+                if present & (1 << i) == 0 {
+                    continue;
+                }
+                let transform = read_transform(reader)?;
+                let [trigger, squeeze, x, y] = read_f32s(reader)?;
+                let bits = read_array::<1>(reader)?[0];
+                let bit = |i: u8| bits & (1 << i) != 0;
+                *controller = Some(XrRecordedController {
+                    transform,
+                    input: XrControllerInput {
+                        trigger,
+                        trigger_touched: bit(0),
+                        squeeze,
+                        thumbstick: Vec2::new(x, y),
+                        thumbstick_click: bit(1),
+                        thumbstick_touched: bit(2),
+                        primary_button: bit(3),
+                        secondary_button: bit(4),
+                    },
+                });
+            }
+            let mut pressed = Vec::new();
+            for _ in 0..read_len(reader)? {
+                let index = read_len(reader)?;
+                let button = buttons
+                    .get(index)
+                    .ok_or_else(|| invalid_data("button index out of range"))?;
+                pressed.push(*button);
+            }
+            frames.push(XrRecordedFrame {
+                time,
+                views,
+                controllers,
+                buttons: pressed,
+            });
+        }
+        Ok(Self { frames })
+    }
+
+    /// The time of the last frame
+    pub fn duration(&self) -> Duration {
+        self.frames
+            .last()
+            .map_or(Duration::ZERO, |frame| frame.time)
+    }
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn write_len(writer: &mut impl Write, len: usize) -> io::Result<()> {
+    let len = u16::try_from(len).map_err(|_| invalid_data("more than 65535 entries"))?;
+    writer.write_all(&len.to_le_bytes())
+}
+
+fn write_str(writer: &mut impl Write, string: &str) -> io::Result<()> {
+    write_len(writer, string.len())?;
+    writer.write_all(string.as_bytes())
+}
+
+fn write_f32s(writer: &mut impl Write, values: &[f32]) -> io::Result<()> {
+    for value in values {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// The scale is always one, only the position and orientation are stored
+fn write_transform(writer: &mut impl Write, transform: &Transform) -> io::Result<()> {
+    write_f32s(writer, &transform.translation.to_array())?;
+    write_f32s(writer, &transform.rotation.to_array())
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_len(reader: &mut impl Read) -> io::Result<usize> {
+    Ok(u16::from_le_bytes(read_array(reader)?) as usize)
+}
+
+fn read_str(reader: &mut impl Read) -> io::Result<String> {
+    let mut bytes = vec![0; read_len(reader)?];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| invalid_data("invalid utf-8 in a button name"))
+}
+
+fn read_f32s<const N: usize>(reader: &mut impl Read) -> io::Result<[f32; N]> {
+    let mut values = [0.0; N];
+    for value in &mut values {
+        *value = f32::from_le_bytes(read_array(reader)?);
+    }
+    Ok(values)
+}
+
+fn read_transform(reader: &mut impl Read) -> io::Result<Transform> {
+    let [x, y, z, qx, qy, qz, qw] = read_f32s(reader)?;
+    Ok(Transform::from_xyz(x, y, z).with_rotation(Quat::from_xyzw(qx, qy, qz, qw)))
+}
+
+/// Records every frame between [`XrRecorder::start`] and [`XrRecorder::stop`]
+pub struct XrRecorderPlugin {
+    /// Records from the start and saves the recording to this file when the app exits
+    pub path: Option<PathBuf>,
+}
+
+impl Plugin for XrRecorderPlugin {
+    fn build(&self, app: &mut App) {
+        let mut recorder = XrRecorder::default();
+        if self.path.is_some() {
+            recorder.start();
+        }
+        app.insert_resource(recorder);
+        app.add_systems(PreUpdate, record_frame.after(XrTrackingUpdate));
+        if let Some(path) = self.path.clone() {
+            app.add_systems(
+                Last,
+                move |mut recorder: ResMut<XrRecorder>, mut exit: EventReader<AppExit>| {
+                    if exit.read().count() == 0 {
+                        return;
+                    }
+                    let Some(recording) = recorder.stop() else {
+                        return;
+                    };
+                    match recording.save(&path) {
+                        Ok(()) => info!(
+                            "Saved {} recorded frames to {}",
+                            recording.frames.len(),
+                            path.display()
+                        ),
+                        Err(err) => {
+                            error!(
+                                "Unable to save the recording to {}: {}",
+                                path.display(),
+                                err
+                            )
+                        }
+                    }
+                },
+            );
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct XrRecorder {
+    recording: Option<XrRecording>,
+    start: Option<Duration>,
+}
+
+impl XrRecorder {
+    /// Starts a new recording, dropping the current one
+    pub fn start(&mut self) {
+        self.recording = Some(default());
+        self.start = None;
+    }
+
+    /// The frames recorded since [`XrRecorder::start`]
+    pub fn stop(&mut self) -> Option<XrRecording> {
+        self.recording.take()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+}
+
+/// The transform of `entity` relative to the tracking root it's below
+pub(crate) fn relative_to_root(
+    entity: Entity,
+    transforms: &Query<(&Transform, Option<&Parent>, Has<OpenXRTrackingRoot>)>,
+) -> Option<Transform> {
+    let (transform, mut parent, _) = transforms.get(entity).ok()?;
+    let mut transform = *transform;
+    while let Some(entity) = parent {
+        let (parent_transform, next, root) = transforms.get(entity.get()).ok()?;
+        if root {
+            return Some(transform);
+        }
+        transform = parent_transform.mul_transform(transform);
+        parent = next;
+    }
+    None
+}
+
+fn record_frame(
+    time: Res<Time>,
+    mut recorder: ResMut<XrRecorder>,
+    buttons: Res<ButtonInput<XrButton>>,
+    transforms: Query<(&Transform, Option<&Parent>, Has<OpenXRTrackingRoot>)>,
+    left_eye: Query<Entity, With<OpenXRLeftEye>>,
+    right_eye: Query<Entity, With<OpenXRRightEye>>,
+    controllers: Query<(Entity, &XrController, &XrControllerInput)>,
+) {
+    let recorder = &mut *recorder;
+    let Some(recording) = &mut recorder.recording else {
+        return;
+    };
+    // nothing is tracked before the session or the simulator spawned the trackers
+    let (Ok(left), Ok(right)) = (left_eye.get_single(), right_eye.get_single()) else {
+        return;
+    };
+    let (Some(left), Some(right)) = (
+        relative_to_root(left, &transforms),
+        relative_to_root(right, &transforms),
+    ) else {
+        return;
+    };
+    let start = *recorder.start.get_or_insert(time.elapsed());
+    let mut recorded = [None; 2];
+    for (entity, controller, input) in &controllers {
+        if !controller.active {
+            continue;
+        }
+        let Some(transform) = relative_to_root(entity, &transforms) else {
+            continue;
+        };
+        let index = match controller.hand {
+            Hand::Left => 0,
+            Hand::Right => 1,
+        };
+        recorded[index] = Some(XrRecordedController {
+            transform,
+            input: *input,
+        });
+    }
+    recording.frames.push(XrRecordedFrame {
+        time: time.elapsed() - start,
+        views: [left, right],
+        controllers: recorded,
+        buttons: buttons.get_pressed().copied().collect(),
+    });
+}
+
+/// How fast [`XrReplayPlugin`] plays the frames back
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum XrReplaySpeed {
+    /// At the times they were recorded at
+    #[default]
+    Recorded,
+    /// One recorded frame per frame of the app, for tests
+    AsFastAsPossible,
+}
+
+/// Plays an [`XrRecording`] back through the simulator, it needs OpenXR to be unavailable, like
+/// with [`XrStartupPolicy::Simulate`](crate::startup::XrStartupPolicy::Simulate)
+pub struct XrReplayPlugin {
+    pub recording: XrRecording,
+    pub speed: XrReplaySpeed,
+}
+
+impl XrReplayPlugin {
+    pub fn new(recording: XrRecording) -> Self {
+        Self {
+            recording,
+            speed: default(),
+        }
+    }
+
+    pub fn as_fast_as_possible(mut self) -> Self {
+        self.speed = XrReplaySpeed::AsFastAsPossible;
+        self
+    }
+}
+
+impl Plugin for XrReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<XrSimulatorPlugin>() {
+            app.add_plugins(XrSimulatorPlugin);
+        }
+        app.insert_resource(XrReplay {
+            recording: self.recording.clone(),
+            speed: self.speed,
+            frame: None,
+            start: None,
+            finished: false,
+        });
+        app.add_event::<XrReplayFinished>();
+        app.add_systems(
+            PreUpdate,
+            (
+                advance_replay.before(XrActionSync),
+                replay_buttons.in_set(XrActionSync),
+                (replay_head, replay_controllers).in_set(XrTrackingUpdate),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// The state of the replay, while it exists the simulator ignores the keyboard and mouse
+#[derive(Resource)]
+pub struct XrReplay {
+    recording: XrRecording,
+    speed: XrReplaySpeed,
+    frame: Option<usize>,
+    start: Option<Duration>,
+    finished: bool,
+}
+
+impl XrReplay {
+    /// The index of the frame that is played back
+    pub fn frame(&self) -> Option<usize> {
+        self.frame
+    }
+
+    /// All frames were played back
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn recording(&self) -> &XrRecording {
+        &self.recording
+    }
+
+    /// The frame that is played back
+    pub fn current(&self) -> Option<&XrRecordedFrame> {
+        self.recording.frames.get(self.frame?)
+    }
+}
+
+/// Sent once after the last frame of the recording was played back
+#[derive(Event, Clone, Copy, Debug)]
+pub struct XrReplayFinished;
+
+fn advance_replay(
+    time: Res<Time>,
+    mut replay: ResMut<XrReplay>,
+    mut finished: EventWriter<XrReplayFinished>,
+) {
+    if replay.finished {
+        return;
+    }
+    let len = replay.recording.frames.len();
+    let next = match replay.speed {
+        XrReplaySpeed::AsFastAsPossible => replay.frame.map_or(0, |frame| frame + 1),
+        XrReplaySpeed::Recorded => {
+            let elapsed = time.elapsed() - *replay.start.get_or_insert(time.elapsed());
+            let played = replay
+                .recording
+                .frames
+                .partition_point(|frame| frame.time <= elapsed);
+            // past the last frame as soon as its time is over
+            if played == len && elapsed > replay.recording.duration() {
+                len
+            } else {
+                played.saturating_sub(1)
+            }
+        }
+    };
+    if next >= len {
+        info!("Finished replaying {} frames", len);
+        replay.finished = true;
+        finished.send(XrReplayFinished);
+        return;
+    }
+    replay.frame = Some(next);
+}
+
+fn replay_buttons(replay: Res<XrReplay>, mut buttons: ResMut<ButtonInput<XrButton>>) {
+    let Some(frame) = replay.current() else {
+        return;
+    };
+    buttons.clear();
+    let released = buttons
+        .get_pressed()
+        .filter(|button| !frame.buttons.contains(button))
+        .copied()
+        .collect::<Vec<_>>();
+    for button in released {
+        buttons.release(button);
+    }
+    for button in &frame.buttons {
+        buttons.press(*button);
+    }
+}
+
+fn replay_head(
+    replay: Res<XrReplay>,
+    mut head: Query<&mut Transform, (With<SimulatedHead>, Without<SimulatedEye>)>,
+    mut eyes: Query<(&mut Transform, Has<OpenXRLeftEye>), With<SimulatedEye>>,
+) {
+    let Some(frame) = replay.current() else {
+        return;
+    };
+    let Ok(mut head) = head.get_single_mut() else {
+        return;
+    };
+    let [left, right] = frame.views;
+    *head = Transform::from_translation(left.translation.lerp(right.translation, 0.5))
+        .with_rotation(left.rotation);
+    let to_head = head.compute_matrix().inverse();
+    for (mut eye, is_left) in &mut eyes {
+        let view = if is_left { left } else { right };
+        *eye = Transform::from_matrix(to_head * view.compute_matrix());
+    }
+}
+
+fn replay_controllers(
+    replay: Res<XrReplay>,
+    mut controllers: Query<
+        (&mut XrController, &mut XrControllerInput, &mut Transform),
+        With<SimulatedController>,
+    >,
+) {
+    let Some(frame) = replay.current() else {
+        return;
+    };
+    for (mut controller, mut input, mut transform) in &mut controllers {
+        let index = match controller.hand {
+            Hand::Left => 0,
+            Hand::Right => 1,
+        };
+        let recorded = frame.controllers[index];
+        controller.active = recorded.is_some();
+        if let Some(recorded) = recorded {
+            *transform = recorded.transform;
+        }
+        input.set_if_neq(recorded.map(|recorded| recorded.input).unwrap_or_default());
+    }
+}
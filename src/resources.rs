@@ -1,17 +1,27 @@
 use std::ffi::c_void;
+use std::ops::{Add, Sub};
 use std::sync::atomic::AtomicBool;
-use std::sync::Mutex;
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::input::XrInput;
+use crate::frame_hooks::XrFrameHooks;
+#[cfg(all(feature = "gles", target_os = "android"))]
+use crate::graphics::GlesContext;
+use crate::input::{pose_to_transform, XrInput};
+use crate::layers::{FrameLayer, XrLayerAlpha};
 use crate::passthrough::{CompositionLayerPassthrough, XrPassthroughLayer};
 use crate::resource_macros::*;
+use crate::secondary_view::ExtractedSecondaryView;
 use crate::xr::sys::CompositionLayerPassthroughFB;
-use crate::xr::{CompositionLayerBase, CompositionLayerFlags};
+use crate::xr::CompositionLayerBase;
+use crate::xr_input::xr_camera::{Eye, XrClipPlanes};
+use crate::Backend;
 use crate::{resource_macros::*, xr_resource_wrapper_copy};
 use bevy::prelude::*;
 use bevy::prelude::*;
 use bevy::render::extract_component::ExtractComponent;
 use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_resource::TextureView;
 use core::ptr;
 use openxr as xr;
 #[cfg(all(feature = "d3d12", windows))]
@@ -19,6 +29,8 @@ use winapi::um::d3d12::{ID3D12CommandQueue, ID3D12Device};
 
 xr_resource_wrapper!(XrInstance, xr::Instance);
 xr_resource_wrapper_copy!(XrEnvironmentBlendMode, xr::EnvironmentBlendMode);
+xr_resource_wrapper!(XrSupportedBlendModes, Vec<xr::EnvironmentBlendMode>);
+xr_resource_wrapper!(XrSupportedMsaaSamples, Vec<u32>);
 xr_resource_wrapper_copy!(XrResolution, UVec2);
 xr_resource_wrapper_copy!(XrFormat, wgpu::TextureFormat);
 xr_resource_wrapper_copy!(XrFrameState, xr::FrameState);
@@ -27,12 +39,317 @@ xr_arc_resource_wrapper!(XrSessionRunning, AtomicBool);
 xr_arc_resource_wrapper!(XrSwapchain, Swapchain);
 xr_no_clone_resource_wrapper!(XrFrameWaiter, xr::FrameWaiter);
 
+impl XrViews {
+    /// The views of the left and right eye, which are the context views with quad views and the
+    /// combined view in mono view configurations. `None` until the views were located.
+    pub fn stereo(&self) -> Option<(&xr::View, &xr::View)> {
+        match self.as_slice() {
+            [] => None,
+            [mono] => Some((mono, mono)),
+            [left, right, ..] => Some((left, right)),
+        }
+    }
+}
+
+/// The state of a waited frame and whether the secondary views are active, if they are enabled
+pub(crate) type WaitedFrame = (xr::FrameState, Option<bool>);
+
+/// Calls `xrWaitFrame` on its own thread, so the main world only blocks when it needs the
+/// next frame and it isn't there yet. See [`xr_wait_frame`](crate::xr_wait_frame) for how this
+/// is ordered with beginning and ending frames.
+#[derive(Resource)]
+pub struct XrFrameWaiterThread {
+    frames: Mutex<mpsc::Receiver<xr::Result<WaitedFrame>>>,
+}
+
+impl XrFrameWaiterThread {
+    pub(crate) fn spawn(mut waiter: XrFrameWaiter, secondary_views: bool) -> Self {
+        // without a buffer the thread only waits for the next frame once this one was taken
+        let (sender, frames) = mpsc::sync_channel(0);
+        std::thread::Builder::new()
+            .name("xr_wait_frame".into())
+            .spawn(move || loop {
+                let _span = info_span!("xr_wait_frame_thread").entered();
+                let result = match secondary_views {
+                    true => waiter
+                        .wait_secondary()
+                        .map(|(frame_state, secondary)| (frame_state, Some(secondary.active))),
+                    false => waiter.wait().map(|frame_state| (frame_state, None)),
+                };
+                if let Ok((frame_state, _)) = &result {
+                    crate::clock::observe_frame(frame_state, Instant::now());
+                }
+                // the receiver is dropped when the session is cleaned up, which also drops
+                // the waiter and with it the last handle to the session on this thread
+                if sender.send(result).is_err() {
+                    break;
+                }
+            })
+            .expect("Unable to spawn the xrWaitFrame thread");
+        Self {
+            frames: Mutex::new(frames),
+        }
+    }
+
+    /// Blocks until the next frame was waited for, `None` if the thread stopped
+    pub(crate) fn recv(&self) -> Option<xr::Result<WaitedFrame>> {
+        self.frames.lock().unwrap().recv().ok()
+    }
+}
+
+/// The swapchain image a view renders into this frame
+#[derive(Clone)]
+pub struct XrViewTarget {
+    pub view: TextureView,
+    pub size: UVec2,
+    pub format: wgpu::TextureFormat,
+}
+
+/// Render world resource with the acquired swapchain images of the frame, indexed by
+/// [`Eye`](crate::xr_input::xr_camera::Eye), for render graph nodes that write into the eyes
+/// directly. In mono view configurations both entries point at the same image.
+///
+/// It's filled once the image was acquired in [`XrBeginFrame`](crate::xr_init::XrBeginFrame)
+/// and emptied when it's released in [`XrEndFrame`](crate::xr_init::XrEndFrame), so it's only
+/// valid for the render graph and systems between them. The swapchain cycles through its
+/// images, so the views mustn't be kept around for later frames.
+#[derive(Resource, Clone, Default, Deref)]
+pub struct XrViewTargets(pub(crate) Vec<XrViewTarget>);
+
+impl XrViewTargets {
+    pub fn get(&self, eye: Eye) -> Option<&XrViewTarget> {
+        self.0.get(eye as usize)
+    }
+}
+
+/// How far the render world got with the current frame, lives in the render world.
+/// After a failed call the rest of the frame is skipped, so the next frame starts from
+/// [`XrFrameInFlight::Idle`] after the next successful `xrWaitFrame`.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum XrFrameInFlight {
+    /// No frame was begun, or the last one was ended
+    #[default]
+    Idle,
+    /// `xrBeginFrame` succeeded, the frame has to be ended
+    Begun,
+    /// The swapchain image was acquired, but waiting for it failed
+    Acquired,
+    /// The swapchain image can be rendered to, it has to be released before ending the frame
+    Ready,
+    /// The swapchain image was rendered to and released by an external renderer, see
+    /// [`XrFrameImages`](crate::external_rendering::XrFrameImages)
+    Released,
+}
+
+/// A point in time on the runtime's clock, in nanoseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub struct XrTime(pub i64);
+
+impl XrTime {
+    pub fn from_nanos(nanos: i64) -> Self {
+        Self(nanos)
+    }
+    pub fn as_nanos(self) -> i64 {
+        self.0
+    }
+}
+
+impl From<xr::Time> for XrTime {
+    fn from(value: xr::Time) -> Self {
+        Self(value.as_nanos())
+    }
+}
+
+impl From<XrTime> for xr::Time {
+    fn from(value: XrTime) -> Self {
+        xr::Time::from_nanos(value.0)
+    }
+}
+
+impl Add<Duration> for XrTime {
+    type Output = XrTime;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        Self(self.0 + rhs.as_nanos() as i64)
+    }
+}
+
+impl Sub<Duration> for XrTime {
+    type Output = XrTime;
+
+    fn sub(self, rhs: Duration) -> Self::Output {
+        Self(self.0 - rhs.as_nanos() as i64)
+    }
+}
+
+/// How the eyes sit relative to each other, computed from the views [`locate_views`](crate::locate_views)
+/// located this frame. It follows the IPD slider of the headset while the app runs,
+/// and stays at its default without a session.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Reflect, ExtractResource)]
+pub struct XrViewInfo {
+    /// Distance between the positions of the eyes in meters, see [`view_separation`](crate::view_separation)
+    pub separation: f32,
+    /// The pose of the left and the right eye relative to the point centered between them,
+    /// oriented like the head. Canted displays show up in the rotations.
+    pub eye_offsets: [Transform; 2],
+}
+
+impl XrViewInfo {
+    /// Needs the located views of both eyes, with fewer views everything stays at zero
+    pub fn from_views(views: &[xr::View]) -> Self {
+        let (Some(center), [left, right, ..]) = (crate::combine_views(views), views) else {
+            return Self::default();
+        };
+        let to_center = pose_to_transform(&center.pose).compute_matrix().inverse();
+        let offset = |view: &xr::View| {
+            Transform::from_matrix(to_center * pose_to_transform(&view.pose).compute_matrix())
+        };
+        Self {
+            separation: crate::view_separation(views),
+            eye_offsets: [offset(left), offset(right)],
+        }
+    }
+}
+
+/// Shifts the time the views, the headset and the controller poses are located at away from the
+/// predicted display time. Fast paced games keep the default of zero, capture tools that want
+/// the poses of "now" locate them earlier. Changes apply from the next frame on:
+///
+/// ```ignore
+/// fn locate_now(mut offset: ResMut<XrPredictionOffset>, frame_time: Res<XrFrameTime>) {
+///     *offset = XrPredictionOffset::earlier(frame_time.predicted_display_period * 2);
+/// }
+/// ```
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub struct XrPredictionOffset {
+    /// Negative locates the poses before the predicted display time
+    nanos: i64,
+}
+
+impl XrPredictionOffset {
+    /// Offsets are clamped to this in both directions,
+    /// runtimes only predict poses a short time ahead
+    pub const MAX: Duration = Duration::from_millis(100);
+    pub const ZERO: Self = Self { nanos: 0 };
+
+    /// Locates the poses after the predicted display time
+    pub fn later(offset: Duration) -> Self {
+        Self {
+            nanos: offset.min(Self::MAX).as_nanos() as i64,
+        }
+    }
+
+    /// Locates the poses before the predicted display time, closer to the current time
+    pub fn earlier(offset: Duration) -> Self {
+        Self {
+            nanos: -(offset.min(Self::MAX).as_nanos() as i64),
+        }
+    }
+
+    pub fn as_nanos(self) -> i64 {
+        self.nanos
+    }
+
+    /// The time poses are located at for a frame displayed at `predicted_display_time`
+    pub fn apply(self, predicted_display_time: XrTime) -> XrTime {
+        XrTime(predicted_display_time.0 + self.nanos)
+    }
+}
+
+/// Timing information of the current frame, updated every time a frame is waited on.
+/// `predicted_display_time` is the time the frame is expected to be shown on the display,
+/// animations and predictions should be evaluated at it instead of at [`Time::elapsed`].
+///
+/// While no frame is waited on, like before the session started, the resource mirrors [`Time`]
+/// instead, so systems can use it whether the session runs or not.
+/// Overwritten every frame, so edits from an inspector don't stick.
+#[derive(Clone, Copy, Debug, Default, Resource, ExtractResource, Reflect)]
+#[reflect(Resource)]
+pub struct XrFrameTime {
+    pub predicted_display_time: XrTime,
+    pub predicted_display_period: Duration,
+    /// How far the predicted display time advanced since the last frame,
+    /// a multiple of the display period when frames were dropped
+    pub delta: Duration,
+    /// The runtime's time at [`Time::elapsed`] zero
+    epoch: XrTime,
+    waited: bool,
+}
+
+impl XrFrameTime {
+    /// The predicted display time in the epoch of [`Time::elapsed`]
+    pub fn display_elapsed(&self) -> Duration {
+        self.to_elapsed(self.predicted_display_time)
+    }
+
+    /// Converts a time of the runtime to the epoch of [`Time::elapsed`],
+    /// times before the app started are clamped to zero
+    pub fn to_elapsed(&self, time: XrTime) -> Duration {
+        Duration::from_nanos((time.0 - self.epoch.0).max(0) as u64)
+    }
+
+    /// Converts a time in the epoch of [`Time::elapsed`] to the runtime's clock
+    pub fn from_elapsed(&self, elapsed: Duration) -> XrTime {
+        self.epoch + elapsed
+    }
+
+    /// Whether the times come from a frame waited on and not from [`Time`]
+    pub fn is_predicted(&self) -> bool {
+        self.waited
+    }
+
+    pub(crate) fn update(&mut self, frame_state: xr::FrameState, elapsed: Duration) {
+        let display_time = XrTime::from(frame_state.predicted_display_time);
+        let period =
+            Duration::from_nanos(frame_state.predicted_display_period.as_nanos().max(0) as u64);
+        if !self.waited {
+            // the first frame is expected to be shown a display period after it was waited on,
+            // the clocks are kept in sync from then on
+            self.epoch = display_time - (elapsed + period);
+            self.delta = period;
+        } else {
+            let delta = display_time.0 - self.predicted_display_time.0;
+            self.delta = Duration::from_nanos(delta.max(0) as u64);
+        }
+        self.predicted_display_time = display_time;
+        self.predicted_display_period = period;
+        self.waited = true;
+    }
+
+    pub(crate) fn mirror(&mut self, time: &Time) {
+        self.predicted_display_time = self.epoch + time.elapsed();
+        self.predicted_display_period = time.delta();
+        self.delta = time.delta();
+        self.waited = false;
+    }
+}
+
+/// How the color values the xr cameras write end up in the swapchain images.
+/// With [`XrColorSpace::Srgb`] shaders output linear values that are encoded to sRGB on write,
+/// with [`XrColorSpace::Linear`] the values are stored as they are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Resource, ExtractResource, Reflect)]
+pub enum XrColorSpace {
+    Srgb,
+    Linear,
+}
+
+impl XrColorSpace {
+    pub fn from_format(format: wgpu::TextureFormat) -> Self {
+        match format.is_srgb() {
+            true => XrColorSpace::Srgb,
+            false => XrColorSpace::Linear,
+        }
+    }
+}
+
 #[derive(Clone, Resource, ExtractResource)]
 pub enum XrSession {
     #[cfg(feature = "vulkan")]
     Vulkan(xr::Session<xr::Vulkan>),
     #[cfg(all(feature = "d3d12", windows))]
     D3D12(xr::Session<xr::D3D12>),
+    #[cfg(all(feature = "gles", target_os = "android"))]
+    Gles(xr::Session<xr::OpenGlEs>),
 }
 
 impl std::ops::Deref for XrSession {
@@ -46,6 +363,8 @@ impl std::ops::Deref for XrSession {
                 XrSession::Vulkan(sess) => std::mem::transmute(sess),
                 #[cfg(all(feature = "d3d12", windows))]
                 XrSession::D3D12(sess) => std::mem::transmute(sess),
+                #[cfg(all(feature = "gles", target_os = "android"))]
+                XrSession::Gles(sess) => std::mem::transmute(sess),
             }
         }
     }
@@ -67,11 +386,35 @@ pub struct D3D12OXrSessionSetupInfo {
     pub(crate) xr_system_id: xr::SystemId,
 }
 
+#[cfg(all(feature = "gles", target_os = "android"))]
+pub struct GlesOXrSessionSetupInfo {
+    pub(crate) display: *mut c_void,
+    pub(crate) config: *mut c_void,
+    pub(crate) context: *mut c_void,
+    pub(crate) xr_system_id: xr::SystemId,
+}
+
 pub enum OXrSessionSetupInfo {
     #[cfg(feature = "vulkan")]
     Vulkan(VulkanOXrSessionSetupInfo),
     #[cfg(all(feature = "d3d12", windows))]
     D3D12(D3D12OXrSessionSetupInfo),
+    #[cfg(all(feature = "gles", target_os = "android"))]
+    Gles(GlesOXrSessionSetupInfo),
+}
+
+impl OXrSessionSetupInfo {
+    /// The graphics backend the instance was created for
+    pub fn backend(&self) -> Backend {
+        match self {
+            #[cfg(feature = "vulkan")]
+            OXrSessionSetupInfo::Vulkan(_) => Backend::Vulkan,
+            #[cfg(all(feature = "d3d12", windows))]
+            OXrSessionSetupInfo::D3D12(_) => Backend::D3D12,
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            OXrSessionSetupInfo::Gles(_) => Backend::Gles,
+        }
+    }
 }
 
 pub struct XrResourcePlugin;
@@ -80,9 +423,12 @@ impl Plugin for XrResourcePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(ExtractResourcePlugin::<XrResolution>::default());
         app.add_plugins(ExtractResourcePlugin::<XrFormat>::default());
+        app.add_plugins(ExtractResourcePlugin::<XrColorSpace>::default());
         app.add_plugins(ExtractResourcePlugin::<XrSwapchain>::default());
         app.add_plugins(ExtractResourcePlugin::<XrFrameState>::default());
+        app.add_plugins(ExtractResourcePlugin::<XrFrameTime>::default());
         app.add_plugins(ExtractResourcePlugin::<XrViews>::default());
+        app.add_plugins(ExtractResourcePlugin::<XrViewInfo>::default());
         app.add_plugins(ExtractResourcePlugin::<XrInput>::default());
         app.add_plugins(ExtractResourcePlugin::<XrEnvironmentBlendMode>::default());
         // app.add_plugins(ExtractResourcePlugin::<XrSessionRunning>::default());
@@ -95,24 +441,128 @@ pub enum Swapchain {
     Vulkan(SwapchainInner<xr::Vulkan>),
     #[cfg(all(feature = "d3d12", windows))]
     D3D12(SwapchainInner<xr::D3D12>),
+    /// The GL context has to be current for the calls on the swapchain
+    #[cfg(all(feature = "gles", target_os = "android"))]
+    Gles(SwapchainInner<xr::OpenGlEs>, GlesContext),
 }
 
 impl Swapchain {
-    pub(crate) fn begin(&self) -> xr::Result<()> {
+    pub(crate) fn begin(&self, hooks: &XrFrameHooks) -> xr::Result<()> {
+        match self {
+            #[cfg(feature = "vulkan")]
+            Swapchain::Vulkan(swapchain) => swapchain.begin(hooks),
+            #[cfg(all(feature = "d3d12", windows))]
+            Swapchain::D3D12(swapchain) => swapchain.begin(hooks),
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, context) => context.with(|| swapchain.begin(hooks)),
+        }
+    }
+
+    /// Ends a frame that nothing was rendered for
+    pub(crate) fn end_without_layers(
+        &self,
+        hooks: &XrFrameHooks,
+        predicted_display_time: xr::Time,
+        environment_blend_mode: xr::EnvironmentBlendMode,
+    ) -> xr::Result<()> {
+        match self {
+            #[cfg(feature = "vulkan")]
+            Swapchain::Vulkan(swapchain) => {
+                swapchain.end_without_layers(hooks, predicted_display_time, environment_blend_mode)
+            }
+            #[cfg(all(feature = "d3d12", windows))]
+            Swapchain::D3D12(swapchain) => {
+                swapchain.end_without_layers(hooks, predicted_display_time, environment_blend_mode)
+            }
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, context) => context.with(|| {
+                swapchain.end_without_layers(hooks, predicted_display_time, environment_blend_mode)
+            }),
+        }
+    }
+
+    /// Views into the swapchain image of the current frame, in the order of [`XrViews`].
+    /// In mono view configurations both eyes render into the only array layer.
+    pub(crate) fn render_views(&self) -> &[TextureView] {
+        match self {
+            #[cfg(feature = "vulkan")]
+            Swapchain::Vulkan(swapchain) => swapchain.render_views(),
+            #[cfg(all(feature = "d3d12", windows))]
+            Swapchain::D3D12(swapchain) => swapchain.render_views(),
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, _) => swapchain.render_views(),
+        }
+    }
+
+    /// Views into the depth swapchain image of the current frame, one per eye.
+    /// `None` when no depth layer is submitted.
+    pub fn get_depth_views(&self) -> Option<(TextureView, TextureView)> {
+        match self {
+            #[cfg(feature = "vulkan")]
+            Swapchain::Vulkan(swapchain) => swapchain.get_depth_views(),
+            #[cfg(all(feature = "d3d12", windows))]
+            Swapchain::D3D12(swapchain) => swapchain.get_depth_views(),
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, _) => swapchain.get_depth_views(),
+        }
+    }
+
+    /// The index of the acquired image in the swapchain
+    pub(crate) fn image_index(&self) -> usize {
+        match self {
+            #[cfg(feature = "vulkan")]
+            Swapchain::Vulkan(swapchain) => *swapchain.image_index.lock().unwrap(),
+            #[cfg(all(feature = "d3d12", windows))]
+            Swapchain::D3D12(swapchain) => *swapchain.image_index.lock().unwrap(),
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, _) => *swapchain.image_index.lock().unwrap(),
+        }
+    }
+
+    /// The swapchain image of the current frame, with one array layer per eye
+    pub fn color_texture(&self) -> &wgpu::Texture {
         match self {
             #[cfg(feature = "vulkan")]
-            Swapchain::Vulkan(swapchain) => swapchain.begin(),
+            Swapchain::Vulkan(swapchain) => swapchain.color_texture(),
             #[cfg(all(feature = "d3d12", windows))]
-            Swapchain::D3D12(swapchain) => swapchain.begin(),
+            Swapchain::D3D12(swapchain) => swapchain.color_texture(),
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, _) => swapchain.color_texture(),
         }
     }
 
-    pub(crate) fn get_render_views(&self) -> (wgpu::TextureView, wgpu::TextureView) {
+    /// The depth swapchain image of the current frame, with one array layer per eye
+    pub fn depth_texture(&self) -> Option<&wgpu::Texture> {
         match self {
             #[cfg(feature = "vulkan")]
-            Swapchain::Vulkan(swapchain) => swapchain.get_render_views(),
+            Swapchain::Vulkan(swapchain) => swapchain.depth_texture(),
             #[cfg(all(feature = "d3d12", windows))]
-            Swapchain::D3D12(swapchain) => swapchain.get_render_views(),
+            Swapchain::D3D12(swapchain) => swapchain.depth_texture(),
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, _) => swapchain.depth_texture(),
+        }
+    }
+
+    /// The format of the views cameras render into, [`XrFormat`] is the format of the swapchain itself
+    pub fn view_format(&self) -> wgpu::TextureFormat {
+        match self {
+            #[cfg(feature = "vulkan")]
+            Swapchain::Vulkan(swapchain) => swapchain.view_format,
+            #[cfg(all(feature = "d3d12", windows))]
+            Swapchain::D3D12(swapchain) => swapchain.view_format,
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, _) => swapchain.view_format,
+        }
+    }
+
+    pub(crate) fn as_raw(&self) -> xr::sys::Swapchain {
+        match self {
+            #[cfg(feature = "vulkan")]
+            Swapchain::Vulkan(swapchain) => swapchain.handle.lock().unwrap().as_raw(),
+            #[cfg(all(feature = "d3d12", windows))]
+            Swapchain::D3D12(swapchain) => swapchain.handle.lock().unwrap().as_raw(),
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, _) => swapchain.handle.lock().unwrap().as_raw(),
         }
     }
 
@@ -122,6 +572,8 @@ impl Swapchain {
             Swapchain::Vulkan(swapchain) => swapchain.acquire_image(),
             #[cfg(all(feature = "d3d12", windows))]
             Swapchain::D3D12(swapchain) => swapchain.acquire_image(),
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, context) => context.with(|| swapchain.acquire_image()),
         }
     }
 
@@ -131,6 +583,8 @@ impl Swapchain {
             Swapchain::Vulkan(swapchain) => swapchain.wait_image(),
             #[cfg(all(feature = "d3d12", windows))]
             Swapchain::D3D12(swapchain) => swapchain.wait_image(),
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, context) => context.with(|| swapchain.wait_image()),
         }
     }
 
@@ -140,46 +594,103 @@ impl Swapchain {
             Swapchain::Vulkan(swapchain) => swapchain.release_image(),
             #[cfg(all(feature = "d3d12", windows))]
             Swapchain::D3D12(swapchain) => swapchain.release_image(),
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, context) => context.with(|| swapchain.release_image()),
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn end(
         &self,
+        hooks: &XrFrameHooks,
         predicted_display_time: xr::Time,
         views: &[openxr::View],
         stage: &xr::Space,
         resolution: UVec2,
         environment_blend_mode: xr::EnvironmentBlendMode,
         passthrough_layer: Option<&XrPassthroughLayer>,
+        clip_planes: XrClipPlanes,
+        frame_layers: &[FrameLayer],
+        secondary_view: Option<&ExtractedSecondaryView>,
+        projection_alpha: XrLayerAlpha,
     ) -> xr::Result<()> {
         match self {
             #[cfg(feature = "vulkan")]
             Swapchain::Vulkan(swapchain) => swapchain.end(
+                hooks,
                 predicted_display_time,
                 views,
                 stage,
                 resolution,
                 environment_blend_mode,
                 passthrough_layer,
+                clip_planes,
+                frame_layers,
+                secondary_view,
+                projection_alpha,
             ),
             #[cfg(all(feature = "d3d12", windows))]
             Swapchain::D3D12(swapchain) => swapchain.end(
+                hooks,
                 predicted_display_time,
                 views,
                 stage,
                 resolution,
                 environment_blend_mode,
                 passthrough_layer,
+                clip_planes,
+                frame_layers,
+                secondary_view,
+                projection_alpha,
             ),
+            #[cfg(all(feature = "gles", target_os = "android"))]
+            Swapchain::Gles(swapchain, context) => {
+                // bevy renders with the origin in the top left while GL has it in the bottom
+                // left, so the compositor would show the images upside down.
+                // Swapping the angles of the fov flips them back.
+                let views: Vec<xr::View> = views
+                    .iter()
+                    .map(|view| xr::View {
+                        fov: xr::Fovf {
+                            angle_up: view.fov.angle_down,
+                            angle_down: view.fov.angle_up,
+                            ..view.fov
+                        },
+                        ..*view
+                    })
+                    .collect();
+                context.with(|| {
+                    swapchain.end(
+                        hooks,
+                        predicted_display_time,
+                        &views,
+                        stage,
+                        resolution,
+                        environment_blend_mode,
+                        passthrough_layer,
+                        clip_planes,
+                        frame_layers,
+                        secondary_view,
+                        projection_alpha,
+                    )
+                })
+            }
         }
     }
 }
 
 pub struct SwapchainInner<G: xr::Graphics> {
-    pub(crate) stream: Mutex<xr::FrameStream<G>>,
+    /// The session the frames are begun and ended on, locked so the calls never overlap
+    pub(crate) session: Mutex<xr::Session<G>>,
     pub(crate) handle: Mutex<xr::Swapchain<G>>,
     pub(crate) buffers: Vec<wgpu::Texture>,
+    /// The views of every image in the order of [`XrViews`]
+    views: SwapchainViews,
+    /// The allocation of the layer list passed to `xrEndFrame`, it's empty between frames
+    layer_buffer: Mutex<Vec<usize>>,
     pub(crate) image_index: Mutex<usize>,
+    pub(crate) view_format: wgpu::TextureFormat,
+    pub(crate) depth: Option<SwapchainImages<G>>,
 }
 impl<G: xr::Graphics> Drop for SwapchainInner<G> {
     fn drop(&mut self) {
@@ -191,31 +702,72 @@ impl<G: xr::Graphics> Drop for SwapchainInner<G> {
 }
 
 impl<G: xr::Graphics> SwapchainInner<G> {
-    fn begin(&self) -> xr::Result<()> {
-        self.stream.lock().unwrap().begin()
+    pub(crate) fn new(
+        session: xr::Session<G>,
+        handle: xr::Swapchain<G>,
+        buffers: Vec<wgpu::Texture>,
+        view_format: wgpu::TextureFormat,
+        depth: Option<SwapchainImages<G>>,
+    ) -> Self {
+        // in mono mode both views use the only layer
+        let views = SwapchainViews::new(&buffers, Some(view_format), 2);
+        Self {
+            session: Mutex::new(session),
+            handle: Mutex::new(handle),
+            buffers,
+            views,
+            layer_buffer: default(),
+            image_index: Mutex::new(0),
+            view_format,
+            depth,
+        }
     }
 
-    fn get_render_views(&self) -> (wgpu::TextureView, wgpu::TextureView) {
-        let texture = &self.buffers[*self.image_index.lock().unwrap()];
+    fn begin(&self, hooks: &XrFrameHooks) -> xr::Result<()> {
+        hooks.begin_frame(&self.session.lock().unwrap())
+    }
 
-        (
-            texture.create_view(&wgpu::TextureViewDescriptor {
-                dimension: Some(wgpu::TextureViewDimension::D2),
-                array_layer_count: Some(1),
-                ..Default::default()
-            }),
-            texture.create_view(&wgpu::TextureViewDescriptor {
-                dimension: Some(wgpu::TextureViewDimension::D2),
-                array_layer_count: Some(1),
-                base_array_layer: 1,
-                ..Default::default()
-            }),
+    fn end_without_layers(
+        &self,
+        hooks: &XrFrameHooks,
+        predicted_display_time: xr::Time,
+        environment_blend_mode: xr::EnvironmentBlendMode,
+    ) -> xr::Result<()> {
+        hooks.end_frame(
+            &self.session.lock().unwrap(),
+            predicted_display_time,
+            environment_blend_mode,
+            &[],
+            None,
         )
     }
 
+    fn render_views(&self) -> &[TextureView] {
+        self.views.image(*self.image_index.lock().unwrap())
+    }
+
+    fn get_depth_views(&self) -> Option<(TextureView, TextureView)> {
+        let depth = self.depth.as_ref()?;
+        Some((
+            depth.view(0),
+            depth.view(depth.texture().depth_or_array_layers() - 1),
+        ))
+    }
+
+    fn color_texture(&self) -> &wgpu::Texture {
+        &self.buffers[*self.image_index.lock().unwrap()]
+    }
+
+    fn depth_texture(&self) -> Option<&wgpu::Texture> {
+        self.depth.as_ref().map(|depth| depth.texture())
+    }
+
     fn acquire_image(&self) -> xr::Result<()> {
         let image_index = self.handle.lock().unwrap().acquire_image()?;
         *self.image_index.lock().unwrap() = image_index as _;
+        if let Some(depth) = &self.depth {
+            depth.acquire_image()?;
+        }
         Ok(())
     }
 
@@ -223,21 +775,35 @@ impl<G: xr::Graphics> SwapchainInner<G> {
         self.handle
             .lock()
             .unwrap()
-            .wait_image(xr::Duration::INFINITE)
+            .wait_image(xr::Duration::INFINITE)?;
+        if let Some(depth) = &self.depth {
+            depth.wait_image()?;
+        }
+        Ok(())
     }
 
     fn release_image(&self) -> xr::Result<()> {
-        self.handle.lock().unwrap().release_image()
+        self.handle.lock().unwrap().release_image()?;
+        if let Some(depth) = &self.depth {
+            depth.release_image()?;
+        }
+        Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn end(
         &self,
+        hooks: &XrFrameHooks,
         predicted_display_time: xr::Time,
         views: &[openxr::View],
         stage: &xr::Space,
         resolution: UVec2,
         environment_blend_mode: xr::EnvironmentBlendMode,
         passthrough_layer: Option<&XrPassthroughLayer>,
+        clip_planes: XrClipPlanes,
+        frame_layers: &[FrameLayer],
+        secondary_view: Option<&ExtractedSecondaryView>,
+        projection_alpha: XrLayerAlpha,
     ) -> xr::Result<()> {
         let rect = xr::Rect2Di {
             offset: xr::Offset2Di { x: 0, y: 0 },
@@ -251,69 +817,306 @@ impl<G: xr::Graphics> SwapchainInner<G> {
             warn!("views are len of 0");
             return Ok(());
         }
-        match passthrough_layer {
-            Some(pass) => {
-                //bevy::log::info!("Rendering with pass through");
-
-                self.stream.lock().unwrap().end(
+        // in mono mode there is one view and one array layer, which both eyes are submitted with
+        let mono = views.len() == 1;
+        let view_count = views.len().clamp(2, 4);
+        let layer = |eye: usize| if mono { 0 } else { eye.min(views.len() - 1) };
+        // bevy renders with a reversed projection, so the smallest depth value is at the far plane
+        let depth_infos = self.depth.as_ref().map(|depth| {
+            [0, 1, 2, 3].map(|i| xr::sys::CompositionLayerDepthInfoKHR {
+                ty: xr::sys::CompositionLayerDepthInfoKHR::TYPE,
+                next: ptr::null(),
+                sub_image: xr::sys::SwapchainSubImage {
+                    swapchain: depth.as_raw(),
+                    image_rect: rect,
+                    image_array_index: layer(i) as u32,
+                },
+                min_depth: 0.0,
+                max_depth: 1.0,
+                near_z: clip_planes.far.unwrap_or(f32::INFINITY),
+                far_z: clip_planes.near,
+            })
+        });
+        // stereo submits the first two, quad views all of them
+        let projection_views = [0, 1, 2, 3].map(|i| {
+            let view = &views[layer(i)];
+            let view = xr::CompositionLayerProjectionView::new()
+                .pose(view.pose)
+                .fov(view.fov)
+                .sub_image(
+                    xr::SwapchainSubImage::new()
+                        .swapchain(&swapchain)
+                        .image_array_index(layer(i) as u32)
+                        .image_rect(rect),
+                );
+            match &depth_infos {
+                Some(depth_infos) => {
+                    let mut raw = view.into_raw();
+                    raw.next = &depth_infos[i] as *const _ as *const c_void;
+                    // SAFETY: CompositionLayerProjectionView is a transparent wrapper around the
+                    // raw struct, the depth info lives until the frame is submitted
+                    unsafe { std::mem::transmute(raw) }
+                }
+                None => view,
+            }
+        });
+        let passthrough =
+            passthrough_layer.map(CompositionLayerPassthrough::from_xr_passthrough_layer);
+        let projection = xr::CompositionLayerProjection::new()
+            .layer_flags(projection_alpha.layer_flags())
+            .space(stage)
+            .views(&projection_views[..view_count]);
+        let mut layer_buffer = self.layer_buffer.lock().unwrap();
+        let mut layers: Vec<&xr::CompositionLayerBase<G>> =
+            recycle_vec(std::mem::take(&mut *layer_buffer));
+        // the frame layers are already in the order of the layer stack
+        for layer in frame_layers {
+            match layer {
+                FrameLayer::Passthrough => {
+                    if let Some(pass) = passthrough.as_ref() {
+                        layers.push(pass);
+                    }
+                }
+                FrameLayer::Projection => layers.push(&projection),
+                FrameLayer::Composition(layer) => layers.push(layer.as_base()),
+            }
+        }
+        let session = self.session.lock().unwrap();
+        let result = match secondary_view {
+            None => hooks.end_frame(
+                &session,
+                predicted_display_time,
+                environment_blend_mode,
+                &layers,
+                None,
+            ),
+            Some(secondary_view) => {
+                let secondary_views = [secondary_view.projection_view()];
+                let secondary_projection = xr::CompositionLayerProjection::new()
+                    .space(stage)
+                    .views(&secondary_views);
+                hooks.end_frame(
+                    &session,
                     predicted_display_time,
                     environment_blend_mode,
-                    &[
-                        &CompositionLayerPassthrough::from_xr_passthrough_layer(pass),
-                        &xr::CompositionLayerProjection::new()
-                            .layer_flags(CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA)
-                            .space(stage)
-                            .views(&[
-                                xr::CompositionLayerProjectionView::new()
-                                    .pose(views[0].pose)
-                                    .fov(views[0].fov)
-                                    .sub_image(
-                                        xr::SwapchainSubImage::new()
-                                            .swapchain(&swapchain)
-                                            .image_array_index(0)
-                                            .image_rect(rect),
-                                    ),
-                                xr::CompositionLayerProjectionView::new()
-                                    .pose(views[1].pose)
-                                    .fov(views[1].fov)
-                                    .sub_image(
-                                        xr::SwapchainSubImage::new()
-                                            .swapchain(&swapchain)
-                                            .image_array_index(1)
-                                            .image_rect(rect),
-                                    ),
-                            ]),
-                    ],
+                    &layers,
+                    Some(xr::SecondaryEndInfo {
+                        ty: secondary_view.ty(),
+                        environment_blend_mode: secondary_view.environment_blend_mode(),
+                        layers: &[&secondary_projection],
+                    }),
                 )
             }
+        };
+        *layer_buffer = recycle_vec(layers);
+        result
+    }
+}
 
-            None => {
-                // bevy::log::info!("Rendering without pass through");
-                self.stream.lock().unwrap().end(
-                    predicted_display_time,
-                    environment_blend_mode,
-                    &[&xr::CompositionLayerProjection::new().space(stage).views(&[
-                        xr::CompositionLayerProjectionView::new()
-                            .pose(views[0].pose)
-                            .fov(views[0].fov)
-                            .sub_image(
-                                xr::SwapchainSubImage::new()
-                                    .swapchain(&swapchain)
-                                    .image_array_index(0)
-                                    .image_rect(rect),
-                            ),
-                        xr::CompositionLayerProjectionView::new()
-                            .pose(views[1].pose)
-                            .fov(views[1].fov)
-                            .sub_image(
-                                xr::SwapchainSubImage::new()
-                                    .swapchain(&swapchain)
-                                    .image_array_index(1)
-                                    .image_rect(rect),
-                            ),
-                    ])],
-                )
+/// Empties `vec` and keeps its allocation for elements of another type with the same size and
+/// alignment, like references with another lifetime. Other types get a new, empty `Vec`.
+pub(crate) fn recycle_vec<T, U>(mut vec: Vec<T>) -> Vec<U> {
+    vec.clear();
+    vec.into_iter().map(|_| unreachable!()).collect()
+}
+
+/// The views of every image of a swapchain, created once with the swapchain so the frames only
+/// hand out clones of them
+pub(crate) struct SwapchainViews(Vec<Vec<TextureView>>);
+
+impl SwapchainViews {
+    /// A view of every array layer, and at least `min_layers` views per image. The views past the
+    /// last layer of an image view its last layer.
+    pub(crate) fn new(
+        buffers: &[wgpu::Texture],
+        format: Option<wgpu::TextureFormat>,
+        min_layers: u32,
+    ) -> Self {
+        let views = buffers
+            .iter()
+            .map(|texture| {
+                let layers = texture.depth_or_array_layers();
+                (0..layers.max(min_layers))
+                    .map(|layer| {
+                        #[cfg(test)]
+                        tests::CREATED_VIEWS.with(|created| created.set(created.get() + 1));
+                        texture
+                            .create_view(&wgpu::TextureViewDescriptor {
+                                format,
+                                dimension: Some(wgpu::TextureViewDimension::D2),
+                                array_layer_count: Some(1),
+                                base_array_layer: layer.min(layers - 1),
+                                ..Default::default()
+                            })
+                            .into()
+                    })
+                    .collect()
+            })
+            .collect();
+        Self(views)
+    }
+
+    /// The views of the image at `index` in the swapchain
+    pub(crate) fn image(&self, index: usize) -> &[TextureView] {
+        &self.0[index]
+    }
+}
+
+/// A swapchain that isn't tied to the frame stream, like the depth swapchain or the
+/// swapchains of composition layers
+pub struct SwapchainImages<G: xr::Graphics> {
+    pub(crate) handle: Mutex<xr::Swapchain<G>>,
+    pub(crate) buffers: Vec<wgpu::Texture>,
+    /// A view of every array layer of every image
+    views: SwapchainViews,
+    pub(crate) image_index: Mutex<usize>,
+}
+impl<G: xr::Graphics> Drop for SwapchainImages<G> {
+    fn drop(&mut self) {
+        for _ in 0..self.buffers.len() {
+            let v = self.buffers.remove(0);
+            Box::leak(Box::new(v));
+        }
+    }
+}
+
+impl<G: xr::Graphics> SwapchainImages<G> {
+    pub(crate) fn new(handle: xr::Swapchain<G>, buffers: Vec<wgpu::Texture>) -> Self {
+        let views = SwapchainViews::new(&buffers, None, 1);
+        Self {
+            handle: Mutex::new(handle),
+            buffers,
+            views,
+            image_index: Mutex::new(0),
+        }
+    }
+
+    pub(crate) fn texture(&self) -> &wgpu::Texture {
+        &self.buffers[*self.image_index.lock().unwrap()]
+    }
+
+    /// The view of an array layer of the acquired image
+    pub(crate) fn view(&self, array_layer: u32) -> TextureView {
+        self.views.image(*self.image_index.lock().unwrap())[array_layer as usize].clone()
+    }
+
+    pub(crate) fn acquire_image(&self) -> xr::Result<()> {
+        let image_index = self.handle.lock().unwrap().acquire_image()?;
+        *self.image_index.lock().unwrap() = image_index as _;
+        Ok(())
+    }
+
+    pub(crate) fn wait_image(&self) -> xr::Result<()> {
+        self.handle
+            .lock()
+            .unwrap()
+            .wait_image(xr::Duration::INFINITE)
+    }
+
+    pub(crate) fn release_image(&self) -> xr::Result<()> {
+        self.handle.lock().unwrap().release_image()
+    }
+
+    pub(crate) fn as_raw(&self) -> xr::sys::Swapchain {
+        self.handle.lock().unwrap().as_raw()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use bevy::tasks::block_on;
+
+    use super::*;
+
+    thread_local! {
+        /// The views [`SwapchainViews`] created on this thread
+        pub(super) static CREATED_VIEWS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn created_views() -> usize {
+        CREATED_VIEWS.with(Cell::get)
+    }
+
+    /// `None` without an adapter, like on machines without a gpu or software renderer
+    fn device() -> Option<wgpu::Device> {
+        let instance = wgpu::Instance::default();
+        let adapter = block_on(instance.request_adapter(&default()))?;
+        let (device, _) = block_on(adapter.request_device(&default(), None)).ok()?;
+        Some(device)
+    }
+
+    fn images(device: &wgpu::Device, count: usize, layers: u32) -> Vec<wgpu::Texture> {
+        (0..count)
+            .map(|_| {
+                device.create_texture(&wgpu::TextureDescriptor {
+                    label: None,
+                    size: wgpu::Extent3d {
+                        width: 16,
+                        height: 16,
+                        depth_or_array_layers: layers,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn views_are_only_created_with_the_swapchain() {
+        let Some(device) = device() else {
+            eprintln!("no wgpu adapter, skipping");
+            return;
+        };
+        let buffers = images(&device, 3, 2);
+        let before = created_views();
+        let views = SwapchainViews::new(&buffers, None, 1);
+        assert_eq!(created_views() - before, 6);
+
+        // the runtime hands out the images in any order
+        let created = created_views();
+        for frame in 0..100 {
+            let index = (frame * 7 + frame / 3) % buffers.len();
+            let first = views.image(index).to_vec();
+            let second = views.image(index).to_vec();
+            assert_eq!(first.len(), 2);
+            for (first, second) in first.iter().zip(&second) {
+                assert_eq!(first.id(), second.id());
             }
         }
+        assert_eq!(created_views(), created);
+    }
+
+    #[test]
+    fn mono_images_view_their_only_layer_twice() {
+        let Some(device) = device() else {
+            eprintln!("no wgpu adapter, skipping");
+            return;
+        };
+        let buffers = images(&device, 2, 1);
+        let before = created_views();
+        let views = SwapchainViews::new(&buffers, None, 2);
+        assert_eq!(created_views() - before, 4);
+        assert_eq!(views.image(1).len(), 2);
+        assert_ne!(views.image(0)[0].id(), views.image(1)[0].id());
+    }
+
+    #[test]
+    fn new_swapchains_get_new_views() {
+        let Some(device) = device() else {
+            eprintln!("no wgpu adapter, skipping");
+            return;
+        };
+        let old = SwapchainViews::new(&images(&device, 3, 2), None, 1);
+        let before = created_views();
+        let new = SwapchainViews::new(&images(&device, 3, 2), None, 1);
+        assert_eq!(created_views() - before, 6);
+        assert_ne!(old.image(0)[0].id(), new.image(0)[0].id());
     }
 }
@@ -0,0 +1,469 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use bevy::render::mesh::Meshable;
+use bevy::utils::{HashSet, Uuid};
+use openxr as xr;
+
+use crate::{
+    anchors::{
+        enable_fb_component, query_fb_spaces, retrieve_fb_query_results, XrAnchorUuid,
+        XrSpatialEntityEvent,
+    },
+    input::XrInput,
+    resources::{XrFrameState, XrInstance, XrSession},
+    xr_init::{xr_only, XrCleanup, XrPostSetup},
+    xr_input::{trackers::OpenXRTrackingRoot, QuatConv, Vec3Conv, XrTrackingUpdate},
+};
+
+/// Loads the room the user set up in the runtime into [`XrSceneModel`], needs `XR_FB_scene`
+/// and `XR_FB_spatial_entity_query`, see
+/// [`XrExtensions::enable_scene`](crate::graphics::extensions::XrExtensions::enable_scene).
+///
+/// The scene is loaded when the session starts and after every [`XrRequestSceneCapture`],
+/// the new model replaces the old one once all of it was loaded.
+pub struct XrScenePlugin {
+    /// Spawns an invisible entity with a mesh and [`XrSceneEntity`] for every plane and volume
+    /// below the [`OpenXRTrackingRoot`], for example to generate colliders from
+    pub spawn_meshes: bool,
+}
+
+impl Default for XrScenePlugin {
+    fn default() -> Self {
+        Self { spawn_meshes: true }
+    }
+}
+
+impl Plugin for XrScenePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<XrRequestSceneCapture>();
+        app.add_systems(XrPostSetup, setup_scene);
+        app.add_systems(
+            PreUpdate,
+            (handle_scene_events, locate_scene_spaces)
+                .chain()
+                .in_set(XrTrackingUpdate)
+                .run_if(xr_only()),
+        );
+        if self.spawn_meshes {
+            app.add_systems(
+                PreUpdate,
+                spawn_scene_meshes
+                    .after(locate_scene_spaces)
+                    .run_if(resource_exists_and_changed::<XrSceneModel>),
+            );
+        }
+        app.add_systems(XrCleanup, cleanup_scene);
+    }
+}
+
+/// Lets the user set up the room again in the runtime,
+/// the scene is reloaded once they are done, needs `XR_FB_scene_capture`
+#[derive(Clone, Copy, Debug, Default, Event)]
+pub struct XrRequestSceneCapture;
+
+/// A flat part of the room, like a wall or the floor, it lies in the XY plane of its pose
+/// and faces along its Z axis
+#[derive(Clone, Debug, PartialEq)]
+pub struct XrScenePlane {
+    pub uuid: XrAnchorUuid,
+    /// Like `FLOOR`, `CEILING`, `WALL_FACE`, `TABLE` or `DOOR_FRAME`
+    pub labels: Vec<String>,
+    /// In the session's stage space, below the [`OpenXRTrackingRoot`]
+    pub pose: Transform,
+    /// The corner of the plane with the lowest coordinates
+    pub offset: Vec2,
+    pub extent: Vec2,
+}
+
+/// A box of the room, like a table or a couch
+#[derive(Clone, Debug, PartialEq)]
+pub struct XrSceneVolume {
+    pub uuid: XrAnchorUuid,
+    pub labels: Vec<String>,
+    /// In the session's stage space, below the [`OpenXRTrackingRoot`]
+    pub pose: Transform,
+    /// The corner of the box with the lowest coordinates
+    pub offset: Vec3,
+    pub extent: Vec3,
+}
+
+impl XrScenePlane {
+    pub fn has_label(&self, label: &str) -> bool {
+        self.labels.iter().any(|l| l == label)
+    }
+
+    /// A rectangle covering the plane, relative to its pose
+    pub fn mesh(&self) -> Mesh {
+        let center = self.offset + self.extent / 2.0;
+        Rectangle::new(self.extent.x, self.extent.y)
+            .mesh()
+            .translated_by(center.extend(0.0))
+    }
+}
+
+impl XrSceneVolume {
+    pub fn has_label(&self, label: &str) -> bool {
+        self.labels.iter().any(|l| l == label)
+    }
+
+    /// A cuboid filling the volume, relative to its pose
+    pub fn mesh(&self) -> Mesh {
+        let center = self.offset + self.extent / 2.0;
+        Cuboid::new(self.extent.x, self.extent.y, self.extent.z)
+            .mesh()
+            .translated_by(center)
+    }
+}
+
+/// The planes and volumes of the room, empty until the scene is loaded
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrSceneModel {
+    pub planes: Vec<XrScenePlane>,
+    pub volumes: Vec<XrSceneVolume>,
+}
+
+/// The entity spawned for a plane or volume of the [`XrSceneModel`]
+#[derive(Component, Clone, Debug)]
+pub struct XrSceneEntity {
+    pub uuid: XrAnchorUuid,
+    pub labels: Vec<String>,
+}
+
+struct SceneSpace {
+    space: xr::Space,
+    uuid: XrAnchorUuid,
+    labels: Vec<String>,
+    plane: Option<xr::Rect2Df>,
+    volume: Option<xr::sys::Rect3DfFB>,
+}
+
+#[derive(Resource, Default)]
+struct XrSceneSpaces {
+    /// Spaces of the current model that weren't located yet
+    unlocated: Vec<SceneSpace>,
+    /// Spaces of a running query, they replace the model once it completes
+    incoming: Vec<SceneSpace>,
+    query: Option<xr::AsyncRequestIdFB>,
+    capture: Option<xr::AsyncRequestIdFB>,
+    /// Component requests of the scene, their completion only needs to be checked for errors
+    enabling: HashSet<xr::AsyncRequestIdFB>,
+}
+
+fn check(result: xr::sys::Result) -> xr::Result<()> {
+    match result.into_raw() < 0 {
+        true => Err(result),
+        false => Ok(()),
+    }
+}
+
+fn setup_scene(mut commands: Commands, instance: Res<XrInstance>, session: Res<XrSession>) {
+    if instance.exts().fb_scene.is_none() || instance.exts().fb_spatial_entity_query.is_none() {
+        warn!("The runtime doesn't support loading the scene");
+        return;
+    }
+    let mut spaces = XrSceneSpaces::default();
+    match query_fb_spaces(
+        &instance,
+        &session,
+        xr::sys::SpaceComponentTypeFB::SEMANTIC_LABELS,
+    ) {
+        Ok(request) => spaces.query = Some(request),
+        Err(err) => warn!("Unable to load the scene: {}", err),
+    }
+    commands.insert_resource(spaces);
+    commands.insert_resource(XrSceneModel::default());
+}
+
+fn handle_scene_events(
+    spaces: Option<ResMut<XrSceneSpaces>>,
+    model: Option<ResMut<XrSceneModel>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    mut events: EventReader<XrSpatialEntityEvent>,
+    mut capture_requests: EventReader<XrRequestSceneCapture>,
+) {
+    let (Some(mut spaces), Some(mut model)) = (spaces, model) else {
+        events.clear();
+        capture_requests.clear();
+        return;
+    };
+    if capture_requests.read().count() > 0 && spaces.capture.is_none() {
+        match request_scene_capture(&instance, &session) {
+            Ok(request) => spaces.capture = Some(request),
+            Err(err) => warn!("Unable to start the room setup: {}", err),
+        }
+    }
+    for event in events.read() {
+        match *event {
+            XrSpatialEntityEvent::QueryResultsAvailable { request }
+                if spaces.query == Some(request) =>
+            {
+                let results = match retrieve_fb_query_results(&instance, &session, request) {
+                    Ok(results) => results,
+                    Err(err) => {
+                        warn!("Unable to retrieve the scene: {}", err);
+                        continue;
+                    }
+                };
+                for result in results {
+                    match load_scene_space(&instance, &session, result, &mut spaces.enabling) {
+                        Ok(space) => spaces.incoming.push(space),
+                        Err(err) => warn!("Unable to load a part of the scene: {}", err),
+                    }
+                }
+            }
+            XrSpatialEntityEvent::QueryComplete { request, result }
+                if spaces.query == Some(request) =>
+            {
+                spaces.query = None;
+                if let Err(err) = check(result) {
+                    warn!("Unable to load the scene: {}", err);
+                    spaces.incoming.clear();
+                    continue;
+                }
+                spaces.unlocated = std::mem::take(&mut spaces.incoming);
+                *model = XrSceneModel::default();
+            }
+            XrSpatialEntityEvent::SceneCaptured { request, result }
+                if spaces.capture == Some(request) =>
+            {
+                spaces.capture = None;
+                if let Err(err) = check(result) {
+                    warn!("The room setup failed: {}", err);
+                    continue;
+                }
+                spaces.incoming.clear();
+                match query_fb_spaces(
+                    &instance,
+                    &session,
+                    xr::sys::SpaceComponentTypeFB::SEMANTIC_LABELS,
+                ) {
+                    Ok(request) => spaces.query = Some(request),
+                    Err(err) => warn!("Unable to reload the scene: {}", err),
+                }
+            }
+            XrSpatialEntityEvent::ComponentStatusSet {
+                request,
+                result,
+                component,
+                ..
+            } if spaces.enabling.remove(&request) => {
+                if let Err(err) = check(result) {
+                    warn!("Unable to enable the {:?} component: {}", component, err);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn request_scene_capture(
+    instance: &XrInstance,
+    session: &XrSession,
+) -> xr::Result<xr::AsyncRequestIdFB> {
+    let Some(capture) = instance.exts().fb_scene_capture else {
+        return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let info = xr::sys::SceneCaptureRequestInfoFB {
+        ty: xr::sys::SceneCaptureRequestInfoFB::TYPE,
+        next: ptr::null(),
+        request_byte_count: 0,
+        request: ptr::null(),
+    };
+    let mut request = xr::AsyncRequestIdFB::default();
+    check(unsafe { (capture.request_scene_capture)(session.as_raw(), &info, &mut request) })?;
+    Ok(request)
+}
+
+/// Reads the labels and bounds of a queried space and makes it locatable
+fn load_scene_space(
+    instance: &XrInstance,
+    session: &XrSession,
+    result: xr::sys::SpaceQueryResultFB,
+    enabling: &mut HashSet<xr::AsyncRequestIdFB>,
+) -> xr::Result<SceneSpace> {
+    let scene = instance.exts().fb_scene.unwrap();
+    let raw = result.space;
+    // owned from here on, so errors below destroy it
+    let space = unsafe { xr::Space::reference_from_raw(xr::Session::clone(session), raw) };
+    if let Some(request) =
+        enable_fb_component(instance, raw, xr::sys::SpaceComponentTypeFB::LOCATABLE)?
+    {
+        enabling.insert(request);
+    }
+    let mut labels = xr::sys::SemanticLabelsFB {
+        ty: xr::sys::SemanticLabelsFB::TYPE,
+        next: ptr::null(),
+        buffer_capacity_input: 0,
+        buffer_count_output: 0,
+        buffer: ptr::null_mut(),
+    };
+    check(unsafe { (scene.get_space_semantic_labels)(session.as_raw(), raw, &mut labels) })?;
+    let mut buffer = vec![0u8; labels.buffer_count_output as usize];
+    labels.buffer_capacity_input = buffer.len() as u32;
+    labels.buffer = buffer.as_mut_ptr() as _;
+    check(unsafe { (scene.get_space_semantic_labels)(session.as_raw(), raw, &mut labels) })?;
+    let labels = String::from_utf8_lossy(&buffer)
+        .trim_end_matches('\0')
+        .split(',')
+        .filter(|label| !label.is_empty())
+        .map(str::to_owned)
+        .collect();
+    let component_enabled = |component| {
+        let mut status = xr::sys::SpaceComponentStatusFB {
+            ty: xr::sys::SpaceComponentStatusFB::TYPE,
+            next: ptr::null_mut(),
+            enabled: false.into(),
+            change_pending: false.into(),
+        };
+        let fb = instance.exts().fb_spatial_entity.unwrap();
+        let result = unsafe { (fb.get_space_component_status)(raw, component, &mut status) };
+        check(result).is_ok() && bool::from(status.enabled)
+    };
+    let mut plane = None;
+    if component_enabled(xr::sys::SpaceComponentTypeFB::BOUNDED_2D) {
+        let mut rect = xr::Rect2Df::default();
+        check(unsafe { (scene.get_space_bounding_box2_d)(session.as_raw(), raw, &mut rect) })?;
+        plane = Some(rect);
+    }
+    let mut volume = None;
+    if component_enabled(xr::sys::SpaceComponentTypeFB::BOUNDED_3D) {
+        let mut rect = xr::sys::Rect3DfFB {
+            offset: xr::sys::Offset3DfFB {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            extent: xr::sys::Extent3DfFB {
+                width: 0.0,
+                height: 0.0,
+                depth: 0.0,
+            },
+        };
+        check(unsafe { (scene.get_space_bounding_box3_d)(session.as_raw(), raw, &mut rect) })?;
+        volume = Some(rect);
+    }
+    Ok(SceneSpace {
+        space,
+        uuid: XrAnchorUuid(Uuid::from_bytes(result.uuid.data)),
+        labels,
+        plane,
+        volume,
+    })
+}
+
+/// Adds the spaces to the model once the runtime can locate them
+fn locate_scene_spaces(
+    spaces: Option<ResMut<XrSceneSpaces>>,
+    model: Option<ResMut<XrSceneModel>>,
+    input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+) {
+    let (Some(mut spaces), Some(mut model)) = (spaces, model) else {
+        return;
+    };
+    if spaces.unlocated.is_empty() {
+        return;
+    }
+    let mut located = Vec::new();
+    spaces.unlocated.retain(|space| {
+        let Ok(location) = space
+            .space
+            .locate(&input.stage, frame_state.predicted_display_time)
+        else {
+            return true;
+        };
+        if !location.location_flags.contains(
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
+        ) {
+            return true;
+        }
+        let pose = Transform {
+            translation: location.pose.position.to_vec3(),
+            rotation: location.pose.orientation.to_quat(),
+            ..default()
+        };
+        located.push((
+            space.uuid,
+            space.labels.clone(),
+            pose,
+            space.plane,
+            space.volume,
+        ));
+        false
+    });
+    if located.is_empty() {
+        return;
+    }
+    for (uuid, labels, pose, plane, volume) in located {
+        if let Some(rect) = plane {
+            model.planes.push(XrScenePlane {
+                uuid,
+                labels: labels.clone(),
+                pose,
+                offset: Vec2::new(rect.offset.x, rect.offset.y),
+                extent: Vec2::new(rect.extent.width, rect.extent.height),
+            });
+        }
+        if let Some(rect) = volume {
+            model.volumes.push(XrSceneVolume {
+                uuid,
+                labels,
+                pose,
+                offset: Vec3::new(rect.offset.x, rect.offset.y, rect.offset.z),
+                extent: Vec3::new(rect.extent.width, rect.extent.height, rect.extent.depth),
+            });
+        }
+    }
+}
+
+/// Replaces the scene entities with ones for the current model
+fn spawn_scene_meshes(
+    mut commands: Commands,
+    model: Res<XrSceneModel>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    entities: Query<Entity, With<XrSceneEntity>>,
+    root: Query<Entity, With<OpenXRTrackingRoot>>,
+) {
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Ok(root) = root.get_single() else {
+        return;
+    };
+    let planes = model
+        .planes
+        .iter()
+        .map(|plane| (plane.uuid, &plane.labels, plane.pose, plane.mesh()));
+    let volumes = model
+        .volumes
+        .iter()
+        .map(|volume| (volume.uuid, &volume.labels, volume.pose, volume.mesh()));
+    for (uuid, labels, pose, mesh) in planes.chain(volumes) {
+        let entity = commands
+            .spawn((
+                SpatialBundle {
+                    transform: pose,
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                meshes.add(mesh),
+                Name::new(format!("Scene {}", labels.join(", "))),
+                XrSceneEntity {
+                    uuid,
+                    labels: labels.clone(),
+                },
+            ))
+            .id();
+        commands.entity(root).add_child(entity);
+    }
+}
+
+fn cleanup_scene(mut commands: Commands, entities: Query<Entity, With<XrSceneEntity>>) {
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<XrSceneSpaces>();
+    commands.remove_resource::<XrSceneModel>();
+}
@@ -0,0 +1,404 @@
+use std::sync::Arc;
+
+use bevy::core_pipeline::core_3d::graph::Core3d;
+use bevy::core_pipeline::tonemapping::{DebandDither, Tonemapping};
+use bevy::prelude::*;
+use bevy::render::camera::{
+    CameraMainTextureUsages, CameraRenderGraph, ManualTextureView, ManualTextureViewHandle,
+    ManualTextureViews, RenderTarget,
+};
+use bevy::render::primitives::Frustum;
+use bevy::render::renderer::{render_system, RenderDevice};
+use bevy::render::view::{ColorGrading, VisibleEntities};
+use bevy::render::{Extract, ExtractSchedule, Render, RenderApp, RenderSet};
+use openxr as xr;
+use wgpu::TextureUsages;
+
+use crate::graphics::{self, XrSessionConfig};
+use crate::input::XrInput;
+use crate::layers::LayerSwapchain;
+use crate::locate_views;
+use crate::resources::{XrFormat, XrFrameState, XrInstance, XrSession, XrTime};
+use crate::xr_init::{xr_after_wait_only, xr_only, xr_render_only, XrCleanup, XrSetup};
+use crate::xr_input::trackers::{verify_quat, OpenXRTracker};
+use crate::xr_input::xr_camera::{RootTransform, XRProjection, XrClipPlanes};
+use crate::xr_input::{QuatConv, Vec3Conv};
+
+pub const SECONDARY_XR_TEXTURE_HANDLE: ManualTextureViewHandle =
+    ManualTextureViewHandle(2867653162);
+
+/// The view configuration used for mixed reality capture
+pub const FIRST_PERSON_OBSERVER_VIEW_TYPE: xr::ViewConfigurationType =
+    xr::ViewConfigurationType::SECONDARY_MONO_FIRST_PERSON_OBSERVER_MSFT;
+
+/// Renders the first person observer view the runtime asks for while it records
+/// or streams the headset view, see [`XrSessionConfig::first_person_observer`].
+pub struct SecondaryViewPlugin;
+
+impl Plugin for SecondaryViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(XrSetup, setup_secondary_view);
+        app.add_systems(XrCleanup, cleanup_secondary_view);
+        app.add_systems(
+            PreUpdate,
+            (update_secondary_view_camera, secondary_view_head_sync)
+                .chain()
+                .run_if(xr_only())
+                .run_if(resource_exists::<XrSecondaryViewState>)
+                .after(locate_views),
+        );
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.add_systems(ExtractSchedule, extract_secondary_view);
+        render_app.add_systems(
+            Render,
+            acquire_secondary_view_image
+                .run_if(xr_only())
+                .run_if(xr_after_wait_only())
+                .run_if(xr_render_only())
+                .run_if(resource_exists::<ExtractedSecondaryView>)
+                .before(render_system)
+                .after(RenderSet::ExtractCommands),
+        );
+    }
+}
+
+/// Secondary view configurations that are enabled when the session begins.
+/// Empty unless [`XrSessionConfig::first_person_observer`] is set and the runtime supports it.
+pub fn enabled_secondary_view_types(
+    instance: &XrInstance,
+    config: &XrSessionConfig,
+) -> Vec<xr::ViewConfigurationType> {
+    let exts = instance.exts();
+    if !config.first_person_observer
+        || exts.msft_secondary_view_configuration.is_none()
+        || exts.msft_first_person_observer.is_none()
+    {
+        return Vec::new();
+    }
+    let supported = instance
+        .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+        .and_then(|system| instance.enumerate_view_configurations(system));
+    match supported {
+        Ok(supported) if supported.contains(&FIRST_PERSON_OBSERVER_VIEW_TYPE) => {
+            vec![FIRST_PERSON_OBSERVER_VIEW_TYPE]
+        }
+        Ok(_) => {
+            info!("The system has no first person observer view");
+            Vec::new()
+        }
+        Err(err) => {
+            warn!("Unable to enumerate view configurations: {}", err);
+            Vec::new()
+        }
+    }
+}
+
+/// The first person observer view, exists while a session with the view enabled is running.
+/// `active` is updated every time a frame is waited on, the camera, swapchain and views are
+/// only used while the runtime reports the view as active.
+#[derive(Clone, Resource)]
+pub struct XrSecondaryViewState {
+    pub ty: xr::ViewConfigurationType,
+    pub active: bool,
+    pub environment_blend_mode: xr::EnvironmentBlendMode,
+    pub resolution: UVec2,
+    /// The located views in the stage space, empty while the view is inactive
+    pub views: Vec<xr::View>,
+    swapchain: Option<Arc<LayerSwapchain>>,
+}
+
+/// Marks the camera that renders the secondary view
+#[derive(Clone, Copy, Debug, Component)]
+pub struct XrSecondaryViewCamera;
+
+fn setup_secondary_view(
+    mut commands: Commands,
+    instance: Res<XrInstance>,
+    config: Res<XrSessionConfig>,
+) {
+    let Some(&ty) = enabled_secondary_view_types(&instance, &config).first() else {
+        return;
+    };
+    let state = instance
+        .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+        .and_then(|system| {
+            let views = instance.enumerate_view_configuration_views(system, ty)?;
+            let blend_modes = instance.enumerate_environment_blend_modes(system, ty)?;
+            Ok((views, blend_modes))
+        });
+    let (views, blend_modes) = match state {
+        Ok(state) => state,
+        Err(err) => {
+            warn!("Unable to query the secondary view configuration: {}", err);
+            return;
+        }
+    };
+    let (Some(view), Some(&environment_blend_mode)) = (views.first(), blend_modes.first()) else {
+        warn!("The secondary view configuration {:?} has no views", ty);
+        return;
+    };
+    let resolution = UVec2::new(
+        view.recommended_image_rect_width,
+        view.recommended_image_rect_height,
+    );
+    info!(
+        "Enabled secondary view {:?} with resolution {}",
+        ty, resolution
+    );
+    commands.insert_resource(XrSecondaryViewState {
+        ty,
+        active: false,
+        environment_blend_mode,
+        resolution,
+        views: Vec::new(),
+        swapchain: None,
+    });
+}
+
+fn cleanup_secondary_view(
+    mut commands: Commands,
+    cameras: Query<Entity, With<XrSecondaryViewCamera>>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+) {
+    for e in &cameras {
+        commands.entity(e).despawn_recursive();
+    }
+    manual_texture_views.remove(&SECONDARY_XR_TEXTURE_HANDLE);
+    commands.remove_resource::<XrSecondaryViewState>();
+}
+
+/// Spawns the secondary view camera when the view becomes active and despawns it again
+/// once it's inactive, the swapchain is created the first time the view is used.
+fn update_secondary_view_camera(
+    mut commands: Commands,
+    mut state: ResMut<XrSecondaryViewState>,
+    cameras: Query<Entity, With<XrSecondaryViewCamera>>,
+    session: Res<XrSession>,
+    render_device: Res<RenderDevice>,
+    format: Res<XrFormat>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+) {
+    if state.active != cameras.is_empty() {
+        return;
+    }
+    if !state.active {
+        info!("Secondary view {:?} became inactive", state.ty);
+        for e in &cameras {
+            commands.entity(e).despawn_recursive();
+        }
+        state.views.clear();
+        return;
+    }
+    if state.swapchain.is_none() {
+        let swapchain = match graphics::create_layer_swapchain(
+            &session,
+            &render_device,
+            **format,
+            state.resolution,
+        ) {
+            Ok(swapchain) => swapchain,
+            Err(err) => {
+                error!("Unable to create the secondary view swapchain: {}", err);
+                // the view stays disabled for the rest of the session instead of retrying every frame
+                commands.remove_resource::<XrSecondaryViewState>();
+                return;
+            }
+        };
+        // the main world only needs this to know the size and format of the render target
+        manual_texture_views.insert(
+            SECONDARY_XR_TEXTURE_HANDLE,
+            ManualTextureView {
+                texture_view: swapchain.view(),
+                size: state.resolution,
+                format: **format,
+            },
+        );
+        state.swapchain = Some(Arc::new(swapchain));
+    }
+    info!("Secondary view {:?} became active", state.ty);
+    commands.spawn((
+        Camera {
+            order: -1,
+            target: RenderTarget::TextureView(SECONDARY_XR_TEXTURE_HANDLE),
+            // the capture is composited on top of the camera feed
+            clear_color: match state.environment_blend_mode {
+                xr::EnvironmentBlendMode::ALPHA_BLEND => ClearColorConfig::Custom(Color::NONE),
+                _ => ClearColorConfig::Default,
+            },
+            ..default()
+        },
+        CameraRenderGraph::new(Core3d),
+        XRProjection::default(),
+        VisibleEntities::default(),
+        Frustum::default(),
+        SpatialBundle::default(),
+        Camera3d::default(),
+        Tonemapping::default(),
+        DebandDither::Enabled,
+        ColorGrading::default(),
+        CameraMainTextureUsages(
+            TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
+        ),
+        RootTransform::default(),
+        OpenXRTracker,
+        XrSecondaryViewCamera,
+    ));
+}
+
+fn secondary_view_head_sync(
+    mut state: ResMut<XrSecondaryViewState>,
+    session: Res<XrSession>,
+    input: Res<XrInput>,
+    xr_frame_state: Res<XrFrameState>,
+    clip_planes: Res<XrClipPlanes>,
+    mut cameras: Query<(&mut Transform, &mut XRProjection), With<XrSecondaryViewCamera>>,
+) {
+    if !state.active {
+        return;
+    }
+    let _span = info_span!("xr_locate_secondary_views").entered();
+    state.views = match locate_secondary_views(
+        &session,
+        &input,
+        state.ty,
+        xr_frame_state.predicted_display_time.into(),
+    ) {
+        Ok(views) => views,
+        Err(err) => {
+            warn!("Unable to locate the secondary views: {}", err);
+            return;
+        }
+    };
+    let Some(view) = state.views.first() else {
+        return;
+    };
+    for (mut transform, mut xr_projection) in &mut cameras {
+        xr_projection.fov = view.fov;
+        clip_planes.apply(&mut xr_projection);
+        transform.rotation = view.pose.orientation.to_quat();
+        transform.translation = view.pose.position.to_vec3();
+    }
+}
+
+/// Locates the views of a secondary view configuration in the stage space,
+/// the same as [`locate_views_at`](crate::locate_views_at) for the primary views
+pub fn locate_secondary_views(
+    session: &xr::Session<xr::AnyGraphics>,
+    input: &XrInput,
+    ty: xr::ViewConfigurationType,
+    time: XrTime,
+) -> xr::Result<Vec<xr::View>> {
+    let (_, views) = session.locate_views(ty, time.into(), &input.stage)?;
+    Ok(views
+        .into_iter()
+        .map(|mut view| {
+            let fixed_quat = verify_quat(view.pose.orientation.to_quat());
+            view.pose.orientation = xr::Quaternionf {
+                x: fixed_quat.x,
+                y: fixed_quat.y,
+                z: fixed_quat.z,
+                w: fixed_quat.w,
+            };
+            view
+        })
+        .collect())
+}
+
+/// The active secondary view of the current frame, lives in the render world
+#[derive(Resource)]
+pub struct ExtractedSecondaryView {
+    ty: xr::ViewConfigurationType,
+    environment_blend_mode: xr::EnvironmentBlendMode,
+    swapchain: Arc<LayerSwapchain>,
+    resolution: UVec2,
+    view: xr::View,
+}
+
+fn extract_secondary_view(
+    mut commands: Commands,
+    state: Extract<Option<Res<XrSecondaryViewState>>>,
+) {
+    let extracted = state
+        .as_ref()
+        .filter(|state| state.active)
+        .and_then(|state| {
+            Some(ExtractedSecondaryView {
+                ty: state.ty,
+                environment_blend_mode: state.environment_blend_mode,
+                swapchain: state.swapchain.clone()?,
+                resolution: state.resolution,
+                view: *state.views.first()?,
+            })
+        });
+    match extracted {
+        Some(extracted) => commands.insert_resource(extracted),
+        None => commands.remove_resource::<ExtractedSecondaryView>(),
+    }
+}
+
+fn acquire_secondary_view_image(
+    mut commands: Commands,
+    secondary_view: Res<ExtractedSecondaryView>,
+    format: Res<XrFormat>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+) {
+    let _span = info_span!("xr_acquire_secondary_view_image").entered();
+    if let Err(err) = secondary_view
+        .swapchain
+        .acquire_image()
+        .and_then(|_| secondary_view.swapchain.wait_image())
+    {
+        warn!("Unable to acquire secondary view image: {}", err);
+        // without an image the view can't be submitted this frame
+        commands.remove_resource::<ExtractedSecondaryView>();
+        return;
+    }
+    manual_texture_views.insert(
+        SECONDARY_XR_TEXTURE_HANDLE,
+        ManualTextureView {
+            texture_view: secondary_view.swapchain.view(),
+            size: secondary_view.resolution,
+            format: **format,
+        },
+    );
+}
+
+impl ExtractedSecondaryView {
+    pub(crate) fn release_image(&self) -> xr::Result<()> {
+        self.swapchain.release_image()
+    }
+
+    pub(crate) fn ty(&self) -> xr::ViewConfigurationType {
+        self.ty
+    }
+
+    pub(crate) fn environment_blend_mode(&self) -> xr::EnvironmentBlendMode {
+        self.environment_blend_mode
+    }
+
+    pub(crate) fn projection_view<G: xr::Graphics>(
+        &self,
+    ) -> xr::CompositionLayerProjectionView<'_, G> {
+        let raw = xr::sys::CompositionLayerProjectionView {
+            ty: xr::sys::CompositionLayerProjectionView::TYPE,
+            next: std::ptr::null(),
+            pose: self.view.pose,
+            fov: self.view.fov,
+            sub_image: xr::sys::SwapchainSubImage {
+                swapchain: self.swapchain.as_raw(),
+                image_rect: xr::Rect2Di {
+                    offset: xr::Offset2Di { x: 0, y: 0 },
+                    extent: xr::Extent2Di {
+                        width: self.resolution.x as _,
+                        height: self.resolution.y as _,
+                    },
+                },
+                image_array_index: 0,
+            },
+        };
+        // SAFETY: the swapchain is kept alive by self, which outlives the returned view
+        unsafe { xr::CompositionLayerProjectionView::from_raw(raw) }
+    }
+}
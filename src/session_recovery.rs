@@ -0,0 +1,189 @@
+//! Brings the session back after the runtime lost it, like when SteamVR was restarted or the
+//! headset disconnected, by starting new sessions until one succeeds.
+
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::time::Stopwatch;
+use openxr as xr;
+
+use crate::device_loss::{XrDeviceLoss, XrDeviceLost};
+use crate::error::XrErrorEvent;
+use crate::resources::XrSessionRunning;
+use crate::xr_init::{
+    CleanupXrData, ExitAppOnSessionExit, StartXrSession, XrInstanceLost, XrPollEvents,
+    XrSessionStateChanged, XrStatus, XrWaitFrame,
+};
+
+/// Cleans up lost sessions and starts new ones, see [`XrRecoverySettings`]. The lost session is
+/// cleaned up like a stopped one, which despawns the tracked entities. Whether the app exits,
+/// recovers or stays in the window is decided by [`ExitAppOnSessionExit`]. An [`XrDeviceLost`]
+/// is handled like a lost session, but no new session is started once the render device itself
+/// is lost.
+pub struct XrSessionRecoveryPlugin;
+
+impl Plugin for XrSessionRecoveryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrRecoverySettings>();
+        app.add_event::<XrRecoveryEvent>();
+        app.add_event::<XrDeviceLost>();
+        app.add_systems(
+            PreUpdate,
+            (handle_session_loss, retry_session)
+                .chain()
+                .after(XrPollEvents)
+                .before(XrWaitFrame),
+        );
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrRecoverySettings {
+    /// Time between two attempts to start a new session
+    pub retry_interval: Duration,
+    /// The app stays in the window after this many failed attempts, `None` retries forever
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for XrRecoverySettings {
+    fn default() -> Self {
+        Self {
+            retry_interval: Duration::from_secs(2),
+            max_attempts: None,
+        }
+    }
+}
+
+/// The progress of bringing a lost session back
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrRecoveryEvent {
+    /// The runtime or the render device lost the session, it's cleaned up now
+    SessionLost,
+    /// A new session is being started
+    Reconnecting { attempt: u32 },
+    /// A new session is running
+    Recovered,
+    /// [`XrRecoverySettings::max_attempts`] sessions failed to start, or the render device is lost
+    GaveUp,
+    /// The runtime is going away for good, the app exits. The render device was created
+    /// through the instance, so it can't be recovered.
+    InstanceLost,
+}
+
+/// Present from losing a session until a new one runs
+#[derive(Resource, Debug, Default)]
+pub struct XrRecovering {
+    attempts: u32,
+    since_attempt: Stopwatch,
+}
+
+impl XrRecovering {
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_session_loss(
+    mut commands: Commands,
+    mut state_changed: EventReader<XrSessionStateChanged>,
+    mut errors: EventReader<XrErrorEvent>,
+    mut instance_lost: EventReader<XrInstanceLost>,
+    mut device_lost: EventReader<XrDeviceLost>,
+    status: Res<XrStatus>,
+    recovering: Option<Res<XrRecovering>>,
+    exit_type: Res<ExitAppOnSessionExit>,
+    session_running: Res<XrSessionRunning>,
+    mut cleanup_xr: EventWriter<CleanupXrData>,
+    mut recovery_events: EventWriter<XrRecoveryEvent>,
+) {
+    if instance_lost.read().count() > 0 {
+        recovery_events.send(XrRecoveryEvent::InstanceLost);
+        return;
+    }
+    let loss_pending = state_changed
+        .read()
+        .any(|event| event.state == xr::SessionState::LOSS_PENDING);
+    let device_lost = device_lost.read().count() > 0;
+    let lost = errors.read().any(|event| event.error.is_session_lost()) || device_lost;
+    let has_session = matches!(*status, XrStatus::Enabled | XrStatus::Enabling);
+    // the frame loop keeps failing until the cleanup ran
+    if !(loss_pending || lost) || !has_session || recovering.is_some() {
+        return;
+    }
+    if *exit_type == ExitAppOnSessionExit::Always {
+        // the app exits
+        return;
+    }
+    match device_lost {
+        true => warn!("The render device was lost, cleaning up the OpenXR session"),
+        false => warn!("The OpenXR session was lost, cleaning it up"),
+    }
+    session_running.store(false, Ordering::Relaxed);
+    cleanup_xr.send_default();
+    recovery_events.send(XrRecoveryEvent::SessionLost);
+    if *exit_type == ExitAppOnSessionExit::OnlyOnExit {
+        commands.init_resource::<XrRecovering>();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn retry_session(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    settings: Res<XrRecoverySettings>,
+    status: Res<XrStatus>,
+    device_loss: Option<Res<XrDeviceLoss>>,
+    recovering: Option<ResMut<XrRecovering>>,
+    mut start_session: EventWriter<StartXrSession>,
+    mut recovery_events: EventWriter<XrRecoveryEvent>,
+) {
+    let Some(mut recovering) = recovering else {
+        return;
+    };
+    match *status {
+        // the lost session is still there until the cleanup ran
+        XrStatus::Enabled if recovering.attempts > 0 => {
+            info!("The OpenXR session recovered");
+            commands.remove_resource::<XrRecovering>();
+            recovery_events.send(XrRecoveryEvent::Recovered);
+        }
+        XrStatus::Disabled => {
+            // the swapchains of a new session would be created on the same lost device
+            if device_loss.is_some_and(|loss| loss.is_lost()) {
+                warn!("The render device is lost, restart the app to enter VR again");
+                commands.remove_resource::<XrRecovering>();
+                recovery_events.send(XrRecoveryEvent::GaveUp);
+                return;
+            }
+            recovering.since_attempt.tick(time.delta());
+            // the first attempt happens right after the cleanup
+            if recovering.attempts > 0
+                && recovering.since_attempt.elapsed() < settings.retry_interval
+            {
+                return;
+            }
+            if settings
+                .max_attempts
+                .is_some_and(|max| recovering.attempts >= max)
+            {
+                warn!(
+                    "Unable to start a new OpenXR session after {} attempts, giving up",
+                    recovering.attempts
+                );
+                commands.remove_resource::<XrRecovering>();
+                recovery_events.send(XrRecoveryEvent::GaveUp);
+                return;
+            }
+            recovering.attempts += 1;
+            recovering.since_attempt.reset();
+            start_session.send_default();
+            recovery_events.send(XrRecoveryEvent::Reconnecting {
+                attempt: recovering.attempts,
+            });
+        }
+        // waiting for the runtime to get the new session ready
+        _ => {}
+    }
+}
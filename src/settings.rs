@@ -0,0 +1,477 @@
+//! Saves the settings players change, like the render scale, the foveation, the calibration and
+//! their bindings, in one profile file that's applied when the app starts.
+
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::calibration::XrCalibration;
+use crate::comfort_vignette::XrComfortVignette;
+use crate::display_refresh_rate::{RequestXrDisplayRefreshRate, XrDisplayRefreshRate};
+use crate::dynamic_resolution::XrDynamicResolution;
+use crate::foveation::{XrFoveationLevel, XrFoveationSettings};
+use crate::graphics::XrSessionConfig;
+use crate::xr_init::XrPostSetup;
+use crate::xr_input::rebinding::XrBindingOverrides;
+use crate::xr_input::turning::{XrTurnMode, XrTurnSettings};
+use crate::xr_input::xr_camera::XrClipPlanes;
+
+/// Loads the [`XrSettings`] when the app starts, applies them and saves them again whenever one
+/// of them changes. With the profile the calibration and rebinding plugins don't need their own
+/// files, their `path` can be `None`.
+pub struct XrSettingsPlugin {
+    /// The RON or JSON file the profile is loaded from and saved to, `None` keeps it in memory
+    pub path: Option<PathBuf>,
+}
+
+impl Default for XrSettingsPlugin {
+    fn default() -> Self {
+        Self {
+            path: Some("xr_settings.ron".into()),
+        }
+    }
+}
+
+impl Plugin for XrSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = match &self.path {
+            Some(path) => XrSettings::load(path).unwrap_or_else(|err| {
+                warn!("Unable to load the settings from {:?}: {}", path, err);
+                default()
+            }),
+            None => default(),
+        };
+        app.insert_resource(settings);
+        app.add_event::<RequestXrDisplayRefreshRate>();
+        app.add_systems(XrPostSetup, request_refresh_rate);
+        app.add_systems(First, apply_settings.run_if(resource_changed::<XrSettings>));
+        app.add_systems(Last, collect_settings);
+        if let Some(path) = self.path.clone() {
+            app.add_systems(
+                Last,
+                (move |settings: Res<XrSettings>| {
+                    if settings.is_added() {
+                        return;
+                    }
+                    if let Err(err) = settings.save(&path) {
+                        warn!("Unable to save the settings to {:?}: {}", path, err);
+                    }
+                })
+                .after(collect_settings)
+                .run_if(resource_changed::<XrSettings>),
+            );
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        // before the session that starts with the app is created
+        apply_settings(&mut app.world);
+    }
+}
+
+/// The settings of [`XrTurnSettings`] a player picks, the input is chosen by the app
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct XrTurnProfile {
+    pub mode: XrTurnMode,
+    pub deadzone: f32,
+    pub snap_threshold: f32,
+}
+
+impl Default for XrTurnProfile {
+    fn default() -> Self {
+        XrTurnSettings::default().into()
+    }
+}
+
+impl From<XrTurnSettings> for XrTurnProfile {
+    fn from(value: XrTurnSettings) -> Self {
+        Self {
+            mode: value.mode,
+            deadzone: value.deadzone,
+            snap_threshold: value.snap_threshold,
+        }
+    }
+}
+
+impl XrTurnProfile {
+    pub fn apply_to(&self, settings: &mut XrTurnSettings) {
+        settings.mode = self.mode;
+        settings.deadzone = self.deadzone;
+        settings.snap_threshold = self.snap_threshold;
+    }
+}
+
+/// Every setting of the profile. Changing the resource applies it, changes to the resources the
+/// settings live in are copied back into it.
+///
+/// The render scale and the foveation are read when a session is created, so changes take effect
+/// with the next session. The refresh rate is requested once a session runs. Settings of plugins
+/// the app doesn't use are kept but not applied. Fields missing from a profile keep their
+/// defaults and unknown fields are skipped, so profiles of older and newer versions still load.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct XrSettings {
+    /// [`XrSessionConfig::render_scale`], used by the next session
+    pub render_scale: f32,
+    pub dynamic_resolution: XrDynamicResolution,
+    /// The display refresh rate requested when a session starts, `None` lets the runtime choose
+    pub refresh_rate: Option<f32>,
+    /// Used by the next session if it isn't [`XrFoveationLevel::None`] and the app didn't set
+    /// [`XrSessionConfig::foveation`], the running session changes right away
+    pub foveation: XrFoveationSettings,
+    pub clip_planes: XrClipPlanes,
+    pub calibration: XrCalibration,
+    pub turning: XrTurnProfile,
+    pub comfort_vignette: XrComfortVignette,
+    pub bindings: XrBindingOverrides,
+}
+
+impl Default for XrSettings {
+    fn default() -> Self {
+        Self {
+            render_scale: 1.0,
+            dynamic_resolution: default(),
+            refresh_rate: None,
+            foveation: default(),
+            clip_planes: default(),
+            calibration: default(),
+            turning: default(),
+            comfort_vignette: default(),
+            bindings: default(),
+        }
+    }
+}
+
+impl XrSettings {
+    /// Reads a RON or JSON profile, picked by the extension of the file.
+    /// A missing file has the default settings.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, XrSettingsError> {
+        let path = path.as_ref();
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(default()),
+            Err(err) => return Err(err.into()),
+        };
+        match is_json(path) {
+            true => Self::from_json(&text),
+            false => Self::from_ron(&text),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), XrSettingsError> {
+        let path = path.as_ref();
+        let text = match is_json(path) {
+            true => self.to_json()?,
+            false => self.to_ron()?,
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, XrSettingsError> {
+        Ok(ron::from_str(text)?)
+    }
+
+    pub fn to_ron(&self) -> Result<String, XrSettingsError> {
+        Ok(ron::ser::to_string_pretty(self, default())?)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, XrSettingsError> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, XrSettingsError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+#[derive(Debug)]
+pub enum XrSettingsError {
+    Io(std::io::Error),
+    Ron(ron::Error),
+    /// A RON profile that couldn't be read
+    Parse(ron::error::SpannedError),
+    Json(serde_json::Error),
+}
+
+impl std::fmt::Display for XrSettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XrSettingsError::Io(err) => write!(f, "{}", err),
+            XrSettingsError::Ron(err) => write!(f, "{}", err),
+            XrSettingsError::Parse(err) => write!(f, "Invalid settings: {}", err),
+            XrSettingsError::Json(err) => write!(f, "Invalid settings: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for XrSettingsError {}
+
+impl From<std::io::Error> for XrSettingsError {
+    fn from(value: std::io::Error) -> Self {
+        XrSettingsError::Io(value)
+    }
+}
+
+impl From<ron::Error> for XrSettingsError {
+    fn from(value: ron::Error) -> Self {
+        XrSettingsError::Ron(value)
+    }
+}
+
+impl From<ron::error::SpannedError> for XrSettingsError {
+    fn from(value: ron::error::SpannedError) -> Self {
+        XrSettingsError::Parse(value)
+    }
+}
+
+impl From<serde_json::Error> for XrSettingsError {
+    fn from(value: serde_json::Error) -> Self {
+        XrSettingsError::Json(value)
+    }
+}
+
+/// Only marks the resource as changed if the value is different, so applying the settings
+/// doesn't copy them back and save them again
+fn set_resource<T: Resource + PartialEq>(world: &mut World, value: T) {
+    if let Some(mut resource) = world.get_resource_mut::<T>() {
+        resource.set_if_neq(value);
+    }
+}
+
+fn apply_settings(world: &mut World) {
+    let Some(settings) = world.get_resource::<XrSettings>().cloned() else {
+        return;
+    };
+    if let Some(mut config) = world.get_resource_mut::<XrSessionConfig>() {
+        if config.render_scale != settings.render_scale {
+            config.render_scale = settings.render_scale;
+        }
+        if settings.foveation.level != XrFoveationLevel::None && config.foveation.is_none() {
+            config.foveation = Some(settings.foveation);
+        }
+    }
+    set_resource(world, settings.dynamic_resolution);
+    set_resource(world, settings.foveation);
+    set_resource(world, settings.clip_planes);
+    set_resource(world, settings.calibration);
+    set_resource(world, settings.comfort_vignette);
+    set_resource(world, settings.bindings);
+    if let Some(mut turning) = world.get_resource_mut::<XrTurnSettings>() {
+        let mut changed = *turning;
+        settings.turning.apply_to(&mut changed);
+        turning.set_if_neq(changed);
+    }
+    if let Some(refresh_rate) = settings.refresh_rate {
+        let current = world
+            .get_resource::<XrDisplayRefreshRate>()
+            .map(|display| display.current);
+        if current.is_some_and(|current| current != refresh_rate) {
+            world.send_event(RequestXrDisplayRefreshRate(refresh_rate));
+        }
+    }
+}
+
+fn request_refresh_rate(
+    settings: Res<XrSettings>,
+    mut requests: EventWriter<RequestXrDisplayRefreshRate>,
+) {
+    if let Some(refresh_rate) = settings.refresh_rate {
+        requests.send(RequestXrDisplayRefreshRate(refresh_rate));
+    }
+}
+
+/// Copies the changed resources into the settings
+#[allow(clippy::too_many_arguments)]
+fn collect_settings(
+    mut settings: ResMut<XrSettings>,
+    session_config: Option<Res<XrSessionConfig>>,
+    dynamic_resolution: Option<Res<XrDynamicResolution>>,
+    foveation: Option<Res<XrFoveationSettings>>,
+    clip_planes: Option<Res<XrClipPlanes>>,
+    calibration: Option<Res<XrCalibration>>,
+    turning: Option<Res<XrTurnSettings>>,
+    comfort_vignette: Option<Res<XrComfortVignette>>,
+    bindings: Option<Res<XrBindingOverrides>>,
+    mut refresh_requests: EventReader<RequestXrDisplayRefreshRate>,
+) {
+    fn changed<'a, T: Resource>(resource: &'a Option<Res<'_, T>>) -> Option<&'a T> {
+        resource
+            .as_ref()
+            .filter(|resource| resource.is_changed())
+            .map(|resource| &**resource)
+    }
+
+    let mut collected = settings.clone();
+    if let Some(config) = changed(&session_config) {
+        collected.render_scale = config.render_scale;
+    }
+    if let Some(dynamic_resolution) = changed(&dynamic_resolution) {
+        collected.dynamic_resolution = *dynamic_resolution;
+    }
+    if let Some(foveation) = changed(&foveation) {
+        collected.foveation = *foveation;
+    }
+    if let Some(clip_planes) = changed(&clip_planes) {
+        collected.clip_planes = *clip_planes;
+    }
+    if let Some(calibration) = changed(&calibration) {
+        collected.calibration = *calibration;
+    }
+    if let Some(turning) = changed(&turning) {
+        collected.turning = (*turning).into();
+    }
+    if let Some(comfort_vignette) = changed(&comfort_vignette) {
+        collected.comfort_vignette = *comfort_vignette;
+    }
+    if let Some(bindings) = changed(&bindings) {
+        collected.bindings = bindings.clone();
+    }
+    // a rate of 0.0 lets the runtime choose
+    if let Some(request) = refresh_requests.read().last() {
+        collected.refresh_rate = Some(request.0).filter(|rate| *rate > 0.0);
+    }
+    settings.set_if_neq(collected);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comfort_vignette::{XrVignetteCurve, XrVignetteInput};
+
+    /// Every field differs from the defaults
+    fn profile() -> XrSettings {
+        let mut bindings = XrBindingOverrides::default();
+        bindings.set(
+            "gameplay",
+            "fire",
+            "/interaction_profiles/oculus/touch_controller",
+            [
+                "/user/hand/left/input/x/click",
+                "/user/hand/right/input/a/click",
+            ],
+        );
+        bindings.set(
+            "menu",
+            "select",
+            "/interaction_profiles/valve/index_controller",
+            ["/user/hand/right/input/trigger/click"],
+        );
+        XrSettings {
+            render_scale: 0.8,
+            dynamic_resolution: XrDynamicResolution {
+                enabled: true,
+                min_scale: 0.5,
+                max_scale: 0.9,
+                budget: 0.85,
+                headroom: 0.6,
+                frames: 20,
+                step: 0.1,
+            },
+            refresh_rate: Some(120.0),
+            foveation: XrFoveationSettings {
+                level: XrFoveationLevel::High,
+                dynamic: true,
+                vertical_offset: -5.0,
+                eye_tracked: true,
+            },
+            clip_planes: XrClipPlanes {
+                near: 0.05,
+                far: Some(500.0),
+            },
+            calibration: XrCalibration {
+                height_offset: 0.12,
+                yaw_offset: -0.5,
+            },
+            turning: XrTurnProfile {
+                mode: XrTurnMode::Smooth {
+                    degrees_per_second: 90.0,
+                },
+                deadzone: 0.3,
+                snap_threshold: 0.8,
+            },
+            comfort_vignette: XrComfortVignette {
+                enabled: false,
+                max_intensity: 0.7,
+                inner_radius: 0.4,
+                outer_radius: 0.9,
+                color: Color::rgb(0.1, 0.2, 0.3),
+                curve: XrVignetteCurve::Power(2.0),
+                attack: 0.2,
+                release: 0.5,
+                linear_speed: None,
+                angular_speed: Some(XrVignetteInput::new(0.1, 1.0)),
+                head_acceleration: Some(XrVignetteInput::new(1.0, 4.0)),
+                teleport_distance: 1.0,
+                snap_turn_angle: 0.3,
+            },
+            bindings,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("bevy_oxr_{}_{}", std::process::id(), name))
+    }
+
+    fn round_trip(name: &str) {
+        let path = temp_path(name);
+        let settings = profile();
+        settings.save(&path).unwrap();
+        let loaded = XrSettings::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), settings);
+    }
+
+    #[test]
+    fn profile_differs_from_defaults() {
+        let profile = profile();
+        let defaults = XrSettings::default();
+        assert_ne!(profile.render_scale, defaults.render_scale);
+        assert_ne!(profile.dynamic_resolution, defaults.dynamic_resolution);
+        assert_ne!(profile.refresh_rate, defaults.refresh_rate);
+        assert_ne!(profile.foveation, defaults.foveation);
+        assert_ne!(profile.clip_planes, defaults.clip_planes);
+        assert_ne!(profile.calibration, defaults.calibration);
+        assert_ne!(profile.turning, defaults.turning);
+        assert_ne!(profile.comfort_vignette, defaults.comfort_vignette);
+        assert_ne!(profile.bindings, defaults.bindings);
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        round_trip("settings.ron");
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip("settings.json");
+    }
+
+    #[test]
+    fn missing_file_has_defaults() {
+        let loaded = XrSettings::load(temp_path("missing.ron")).unwrap();
+        assert_eq!(loaded, XrSettings::default());
+    }
+
+    #[test]
+    fn missing_fields_keep_defaults() {
+        let loaded = XrSettings::from_ron("(render_scale: 0.5, unknown: true)").unwrap();
+        assert_eq!(
+            loaded,
+            XrSettings {
+                render_scale: 0.5,
+                ..default()
+            }
+        );
+        let loaded = XrSettings::from_json(r#"{"refresh_rate": 72.0, "unknown": true}"#).unwrap();
+        assert_eq!(loaded.refresh_rate, Some(72.0));
+        assert_eq!(loaded.bindings, XrBindingOverrides::default());
+    }
+}
@@ -0,0 +1,473 @@
+//! Runs the app without a headset, with a simulated headset and controllers driven by the
+//! keyboard and mouse. Enabled with [`DefaultXrPlugins::simulator`](crate::DefaultXrPlugins) or
+//! [`SIMULATOR_ENV_VAR`].
+
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::recording::XrReplay;
+use crate::xr_init::XrStatus;
+use crate::xr_input::actions::{XrActionSync, XrButton};
+use crate::xr_input::tracked_controllers::{XrController, XrControllerInput};
+use crate::xr_input::trackers::{
+    OpenXRController, OpenXRHMD, OpenXRLeftController, OpenXRLeftEye, OpenXRRightController,
+    OpenXRRightEye, OpenXRTracker, OpenXRTrackingRoot,
+};
+use crate::xr_input::{Hand, XrTrackingUpdate};
+
+/// Set to anything but `0` to run the simulator instead of OpenXR
+pub const SIMULATOR_ENV_VAR: &str = "BEVY_OXR_SIMULATOR";
+
+/// [`SIMULATOR_ENV_VAR`] selects the simulator
+pub fn simulator_requested() -> bool {
+    std::env::var(SIMULATOR_ENV_VAR).is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Simulates a headset and controllers while OpenXR is unavailable. The simulator isn't an
+/// OpenXR runtime, [`XrStatus`] stays [`XrStatus::Unavailable`] so systems running with
+/// [`xr_only`](crate::xr_init::xr_only) don't run. What it provides is the
+/// [`OpenXRTrackingRoot`] with a camera as the head, eye trackers half the
+/// [`ipd`](XrSimulatorSettings::ipd) to each side of it, [`XrController`] entities with
+/// [`XrControllerInput`] and the [`XrButton`]s of [`XrSimulatorBindings::actions`].
+pub struct XrSimulatorPlugin;
+
+impl Plugin for XrSimulatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrSimulatorSettings>()
+            .init_resource::<XrSimulatorBindings>();
+    }
+
+    // the OpenXrPlugin decided whether OpenXR is available once every plugin is built
+    fn finish(&self, app: &mut App) {
+        if !matches!(
+            app.world.get_resource::<XrStatus>(),
+            Some(XrStatus::Unavailable(_))
+        ) {
+            return;
+        }
+        info!("Simulating a headset in the window");
+        app.init_resource::<XrSimulator>();
+        app.add_systems(Startup, spawn_simulated_rig);
+        // a replay moves the simulated entities instead of the keyboard and mouse
+        let controlled = not(resource_exists::<XrReplay>);
+        app.add_systems(
+            PreUpdate,
+            update_simulated_input
+                .in_set(XrActionSync)
+                .run_if(controlled.clone()),
+        );
+        app.add_systems(
+            PreUpdate,
+            (move_simulated_head, move_simulated_controllers)
+                .chain()
+                .in_set(XrTrackingUpdate)
+                .run_if(controlled.clone()),
+        );
+        app.add_systems(
+            PreUpdate,
+            update_simulated_ipd
+                .run_if(resource_changed::<XrSimulatorSettings>)
+                .run_if(controlled),
+        );
+    }
+}
+
+/// How the simulated headset and controllers are moved. By default WASD moves the head, space
+/// and shift move it up and down, and the mouse looks around while the right mouse button is
+/// held. Holding Q or E moves the left or right controller with the mouse instead, the scroll
+/// wheel moves it forward and back.
+#[derive(Resource, Clone, Debug)]
+pub struct XrSimulatorSettings {
+    /// Meters per second
+    pub move_speed: f32,
+    /// Radians per pixel the mouse moved
+    pub look_sensitivity: f32,
+    /// Meters per pixel the mouse moved while moving a controller
+    pub controller_sensitivity: f32,
+    /// Distance between the eyes in meters
+    pub ipd: f32,
+    /// Height of the head above the floor when the simulator starts
+    pub height: f32,
+    /// Looks around with the mouse while held
+    pub look_button: MouseButton,
+    pub forward: KeyCode,
+    pub back: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub up: KeyCode,
+    pub down: KeyCode,
+    /// Moves the left controller with the mouse while held
+    pub move_left_controller: KeyCode,
+    /// Moves the right controller with the mouse while held
+    pub move_right_controller: KeyCode,
+}
+
+impl Default for XrSimulatorSettings {
+    fn default() -> Self {
+        Self {
+            move_speed: 1.5,
+            look_sensitivity: 0.004,
+            controller_sensitivity: 0.001,
+            ipd: 0.063,
+            height: 1.6,
+            look_button: MouseButton::Right,
+            forward: KeyCode::KeyW,
+            back: KeyCode::KeyS,
+            left: KeyCode::KeyA,
+            right: KeyCode::KeyD,
+            up: KeyCode::Space,
+            down: KeyCode::ShiftLeft,
+            move_left_controller: KeyCode::KeyQ,
+            move_right_controller: KeyCode::KeyE,
+        }
+    }
+}
+
+/// What is pressed to simulate an input path
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrSimulatedInput {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// A value from -1 to 1, like `/user/hand/left/input/thumbstick/x`
+    Axis {
+        negative: KeyCode,
+        positive: KeyCode,
+    },
+}
+
+/// Which keys and mouse buttons stand in for the inputs of the controllers:
+///
+/// ```ignore
+/// let mut bindings = app.world.resource_mut::<XrSimulatorBindings>();
+/// bindings
+///     .bind("/user/hand/right/input/trigger/value", XrSimulatedInput::Key(KeyCode::KeyG))
+///     .bind_action(XrButton::new("gameplay", "jump"), "/user/hand/right/input/a/click");
+/// ```
+///
+/// The defaults cover the Oculus Touch inputs. The left controller has the trigger on Z,
+/// squeeze on X, X and Y on C and V, menu on Tab and the thumbstick on IJKL with B to click it.
+/// The right controller has the trigger on the left mouse button, squeeze on F, A and B on R
+/// and T and the thumbstick on the arrow keys with Enter to click it.
+#[derive(Resource, Clone, Debug)]
+pub struct XrSimulatorBindings {
+    /// The input paths and what simulates them
+    pub inputs: HashMap<String, XrSimulatedInput>,
+    /// Boolean actions and the input paths they're bound to, their [`XrButton`] is pressed
+    /// while any of their paths is pressed
+    pub actions: Vec<(XrButton, String)>,
+}
+
+impl XrSimulatorBindings {
+    /// Simulates `path`, like `/user/hand/right/input/trigger/value`, with `input`
+    pub fn bind(&mut self, path: impl Into<String>, input: XrSimulatedInput) -> &mut Self {
+        self.inputs.insert(path.into(), input);
+        self
+    }
+
+    /// Presses `button` while the input at `path` is pressed
+    pub fn bind_action(&mut self, button: XrButton, path: impl Into<String>) -> &mut Self {
+        self.actions.push((button, path.into()));
+        self
+    }
+
+    /// The simulated value of `path`, 0 when nothing simulates it
+    pub fn value(
+        &self,
+        path: &str,
+        keys: &ButtonInput<KeyCode>,
+        mouse: &ButtonInput<MouseButton>,
+    ) -> f32 {
+        let key = |key: KeyCode| if keys.pressed(key) { 1.0 } else { 0.0 };
+        match self.inputs.get(path) {
+            Some(XrSimulatedInput::Key(code)) => key(*code),
+            Some(XrSimulatedInput::Mouse(button)) => {
+                if mouse.pressed(*button) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Some(XrSimulatedInput::Axis { negative, positive }) => key(*positive) - key(*negative),
+            None => 0.0,
+        }
+    }
+
+    /// `path` counts as pressed, for axes in both directions
+    pub fn pressed(
+        &self,
+        path: &str,
+        keys: &ButtonInput<KeyCode>,
+        mouse: &ButtonInput<MouseButton>,
+    ) -> bool {
+        self.value(path, keys, mouse).abs() > 0.5
+    }
+}
+
+impl Default for XrSimulatorBindings {
+    fn default() -> Self {
+        use XrSimulatedInput::{Axis, Key, Mouse};
+
+        let mut bindings = Self {
+            inputs: HashMap::new(),
+            actions: Vec::new(),
+        };
+        bindings
+            .bind("/user/hand/left/input/trigger/value", Key(KeyCode::KeyZ))
+            .bind("/user/hand/left/input/squeeze/value", Key(KeyCode::KeyX))
+            .bind("/user/hand/left/input/x/click", Key(KeyCode::KeyC))
+            .bind("/user/hand/left/input/y/click", Key(KeyCode::KeyV))
+            .bind("/user/hand/left/input/menu/click", Key(KeyCode::Tab))
+            .bind(
+                "/user/hand/left/input/thumbstick/x",
+                Axis {
+                    negative: KeyCode::KeyJ,
+                    positive: KeyCode::KeyL,
+                },
+            )
+            .bind(
+                "/user/hand/left/input/thumbstick/y",
+                Axis {
+                    negative: KeyCode::KeyK,
+                    positive: KeyCode::KeyI,
+                },
+            )
+            .bind("/user/hand/left/input/thumbstick/click", Key(KeyCode::KeyB))
+            .bind(
+                "/user/hand/right/input/trigger/value",
+                Mouse(MouseButton::Left),
+            )
+            .bind("/user/hand/right/input/squeeze/value", Key(KeyCode::KeyF))
+            .bind("/user/hand/right/input/a/click", Key(KeyCode::KeyR))
+            .bind("/user/hand/right/input/b/click", Key(KeyCode::KeyT))
+            .bind(
+                "/user/hand/right/input/thumbstick/x",
+                Axis {
+                    negative: KeyCode::ArrowLeft,
+                    positive: KeyCode::ArrowRight,
+                },
+            )
+            .bind(
+                "/user/hand/right/input/thumbstick/y",
+                Axis {
+                    negative: KeyCode::ArrowDown,
+                    positive: KeyCode::ArrowUp,
+                },
+            )
+            .bind(
+                "/user/hand/right/input/thumbstick/click",
+                Key(KeyCode::Enter),
+            );
+        bindings
+    }
+}
+
+/// Exists while the simulator runs instead of OpenXR
+#[derive(Resource, Debug)]
+pub struct XrSimulator {
+    yaw: f32,
+    pitch: f32,
+    /// Controller positions relative to the head
+    left_controller: Vec3,
+    right_controller: Vec3,
+}
+
+impl Default for XrSimulator {
+    fn default() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.0,
+            left_controller: Vec3::new(-0.2, -0.3, -0.4),
+            right_controller: Vec3::new(0.2, -0.3, -0.4),
+        }
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct SimulatedHead;
+
+#[derive(Component)]
+pub(crate) struct SimulatedEye;
+
+#[derive(Component)]
+pub(crate) struct SimulatedController;
+
+fn spawn_simulated_rig(mut commands: Commands, settings: Res<XrSimulatorSettings>) {
+    let eye = |x: f32| SpatialBundle::from_transform(Transform::from_xyz(x, 0.0, 0.0));
+    commands
+        .spawn((
+            SpatialBundle::default(),
+            Name::new("Simulated Tracking Root"),
+            OpenXRTrackingRoot,
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Camera3dBundle {
+                    transform: Transform::from_xyz(0.0, settings.height, 0.0),
+                    ..default()
+                },
+                Name::new("Simulated Head"),
+                OpenXRHMD,
+                OpenXRTracker,
+                SimulatedHead,
+            ))
+            .with_children(|head| {
+                head.spawn((
+                    eye(-settings.ipd / 2.0),
+                    Name::new("Simulated Left Eye"),
+                    OpenXRLeftEye,
+                    SimulatedEye,
+                ));
+                head.spawn((
+                    eye(settings.ipd / 2.0),
+                    Name::new("Simulated Right Eye"),
+                    OpenXRRightEye,
+                    SimulatedEye,
+                ));
+            });
+            root.spawn((
+                SpatialBundle::default(),
+                Name::new("Simulated Left Controller"),
+                XrController {
+                    hand: Hand::Left,
+                    active: true,
+                },
+                XrControllerInput::default(),
+                OpenXRLeftController,
+                OpenXRController,
+                SimulatedController,
+            ));
+            root.spawn((
+                SpatialBundle::default(),
+                Name::new("Simulated Right Controller"),
+                XrController {
+                    hand: Hand::Right,
+                    active: true,
+                },
+                XrControllerInput::default(),
+                OpenXRRightController,
+                OpenXRController,
+                SimulatedController,
+            ));
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn move_simulated_head(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    settings: Res<XrSimulatorSettings>,
+    mut simulator: ResMut<XrSimulator>,
+    mut head: Query<&mut Transform, With<SimulatedHead>>,
+) {
+    let delta: Vec2 = motion.read().map(|motion| motion.delta).sum();
+    let scroll: f32 = wheel
+        .read()
+        .map(|wheel| match wheel.unit {
+            MouseScrollUnit::Line => wheel.y * 0.05,
+            MouseScrollUnit::Pixel => wheel.y * settings.controller_sensitivity,
+        })
+        .sum();
+    let moved =
+        Vec3::new(delta.x, -delta.y, 0.0) * settings.controller_sensitivity - Vec3::Z * scroll;
+    if keys.pressed(settings.move_left_controller) {
+        simulator.left_controller += moved;
+    } else if keys.pressed(settings.move_right_controller) {
+        simulator.right_controller += moved;
+    } else if mouse.pressed(settings.look_button) {
+        simulator.yaw -= delta.x * settings.look_sensitivity;
+        simulator.pitch = (simulator.pitch - delta.y * settings.look_sensitivity).clamp(-1.5, 1.5);
+    }
+
+    let Ok(mut head) = head.get_single_mut() else {
+        return;
+    };
+    let axis = |negative: KeyCode, positive: KeyCode| {
+        keys.pressed(positive) as i8 as f32 - keys.pressed(negative) as i8 as f32
+    };
+    // walking stays level, no matter where the head looks
+    let yaw = Quat::from_rotation_y(simulator.yaw);
+    let direction = yaw * Vec3::NEG_Z * axis(settings.back, settings.forward)
+        + yaw * Vec3::X * axis(settings.left, settings.right)
+        + Vec3::Y * axis(settings.down, settings.up);
+    head.translation += direction.normalize_or_zero() * settings.move_speed * time.delta_seconds();
+    head.rotation = Quat::from_euler(EulerRot::YXZ, simulator.yaw, simulator.pitch, 0.0);
+}
+
+fn update_simulated_ipd(
+    settings: Res<XrSimulatorSettings>,
+    mut eyes: Query<(&mut Transform, Has<OpenXRLeftEye>), With<SimulatedEye>>,
+) {
+    for (mut eye, left) in &mut eyes {
+        eye.translation.x = if left { -settings.ipd } else { settings.ipd } / 2.0;
+    }
+}
+
+/// The controllers are held at a fixed position in front of the head
+fn move_simulated_controllers(
+    simulator: Res<XrSimulator>,
+    head: Query<&Transform, (With<SimulatedHead>, Without<SimulatedController>)>,
+    mut controllers: Query<(&XrController, &mut Transform), With<SimulatedController>>,
+) {
+    let Ok(head) = head.get_single() else {
+        return;
+    };
+    for (controller, mut transform) in &mut controllers {
+        let offset = match controller.hand {
+            Hand::Left => simulator.left_controller,
+            Hand::Right => simulator.right_controller,
+        };
+        *transform = head.mul_transform(Transform::from_translation(offset));
+    }
+}
+
+/// Updates the controller inputs and presses the buttons of the bound actions,
+/// instead of syncing the actions
+fn update_simulated_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    bindings: Res<XrSimulatorBindings>,
+    mut buttons: ResMut<ButtonInput<XrButton>>,
+    mut controllers: Query<(&XrController, &mut XrControllerInput), With<SimulatedController>>,
+) {
+    let value = |path: &str| bindings.value(path, &keys, &mouse);
+    let pressed = |path: &str| bindings.pressed(path, &keys, &mouse);
+    for (controller, mut input) in &mut controllers {
+        let (side, primary, secondary) = match controller.hand {
+            Hand::Left => ("left", "x", "y"),
+            Hand::Right => ("right", "a", "b"),
+        };
+        let path = |input: &str| format!("/user/hand/{}/input/{}", side, input);
+        let trigger = value(&path("trigger/value"));
+        let thumbstick = Vec2::new(value(&path("thumbstick/x")), value(&path("thumbstick/y")));
+        let thumbstick_click = pressed(&path("thumbstick/click"));
+        input.set_if_neq(XrControllerInput {
+            trigger,
+            trigger_touched: trigger > 0.0,
+            squeeze: value(&path("squeeze/value")),
+            thumbstick,
+            thumbstick_click,
+            thumbstick_touched: thumbstick_click || thumbstick != Vec2::ZERO,
+            primary_button: pressed(&path(&format!("{}/click", primary))),
+            secondary_button: pressed(&path(&format!("{}/click", secondary))),
+        });
+    }
+
+    buttons.clear();
+    let held = bindings
+        .actions
+        .iter()
+        .filter(|(_, path)| pressed(path))
+        .map(|(button, _)| *button)
+        .collect::<HashSet<_>>();
+    for (button, _) in &bindings.actions {
+        if held.contains(button) {
+            buttons.press(*button);
+        } else {
+            buttons.release(*button);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! A skybox composited behind the projection layer by the runtime with
+//! `XR_KHR_composition_layer_cube`, which saves rendering it into both eyes.
+
+use std::sync::Arc;
+
+use bevy::core_pipeline::Skybox;
+use bevy::prelude::*;
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::{Extract, ExtractSchedule, RenderApp};
+
+use crate::graphics;
+use crate::layers::LayerSwapchain;
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::{xr_only, XrCleanup};
+use crate::xr_input::xr_camera::XrCamera;
+
+/// Submits the [`XrSkybox`] as a cube layer, it needs
+/// [`XrExtensions::enable_cube_layer`](crate::graphics::extensions::XrExtensions::enable_cube_layer).
+/// While the layer is submitted the XR cameras clear to transparent and their [`Skybox`] is
+/// taken off, it's put back when the layer goes away. Without the extension the [`Skybox`] of
+/// the app keeps being rendered as usual.
+pub struct XrSkyboxPlugin;
+
+impl Plugin for XrSkyboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (upload_skybox.run_if(xr_only()), hide_camera_skyboxes).chain(),
+        );
+        app.add_systems(XrCleanup, cleanup_skybox);
+        app.sub_app_mut(RenderApp)
+            .add_systems(ExtractSchedule, extract_skybox);
+    }
+}
+
+/// The skybox shown behind everything, replace the image to swap it.
+/// The image has to be a cubemap with six array layers, or six square faces stacked on top of
+/// each other, in a format the runtime can create swapchains with.
+#[derive(Resource, Clone, Debug)]
+pub struct XrSkybox {
+    pub image: Handle<Image>,
+    /// Rotation of the cube relative to the stage space
+    pub orientation: Quat,
+}
+
+impl XrSkybox {
+    pub fn new(image: Handle<Image>) -> Self {
+        Self {
+            image,
+            orientation: Quat::IDENTITY,
+        }
+    }
+
+    pub fn with_orientation(mut self, orientation: Quat) -> Self {
+        self.orientation = orientation;
+        self
+    }
+}
+
+/// Present while the [`XrSkybox`] is submitted as a cube layer
+#[derive(Resource, Clone)]
+pub struct XrSkyboxLayer {
+    swapchain: Arc<LayerSwapchain>,
+    image: AssetId<Image>,
+}
+
+/// The [`Skybox`] of an XR camera while the cube layer replaces it
+#[derive(Component)]
+struct HiddenSkybox(Skybox);
+
+/// The cube layer to submit, lives in the render world
+#[derive(Resource)]
+pub struct ExtractedXrSkybox {
+    pub(crate) swapchain: Arc<LayerSwapchain>,
+    pub(crate) orientation: Quat,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn upload_skybox(
+    mut commands: Commands,
+    skybox: Option<Res<XrSkybox>>,
+    layer: Option<Res<XrSkyboxLayer>>,
+    images: Res<Assets<Image>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut failed: Local<Option<AssetId<Image>>>,
+) {
+    let Some(skybox) = skybox else {
+        if layer.is_some() {
+            commands.remove_resource::<XrSkyboxLayer>();
+        }
+        return;
+    };
+    let id = skybox.image.id();
+    if layer.as_ref().is_some_and(|layer| layer.image == id) || *failed == Some(id) {
+        return;
+    }
+    // still loading
+    let Some(image) = images.get(id) else {
+        return;
+    };
+    match create_cube_layer(image, &instance, &session, &render_device, &render_queue) {
+        Ok(swapchain) => {
+            commands.insert_resource(XrSkyboxLayer {
+                swapchain: Arc::new(swapchain),
+                image: id,
+            });
+        }
+        Err(err) => {
+            // the app's own skybox keeps rendering
+            error!("Unable to show the skybox as a cube layer: {}", err);
+            *failed = Some(id);
+            if layer.is_some() {
+                commands.remove_resource::<XrSkyboxLayer>();
+            }
+        }
+    }
+}
+
+fn create_cube_layer(
+    image: &Image,
+    instance: &XrInstance,
+    session: &XrSession,
+    render_device: &RenderDevice,
+    render_queue: &RenderQueue,
+) -> eyre::Result<LayerSwapchain> {
+    if instance.exts().khr_composition_layer_cube.is_none() {
+        eyre::bail!("XR_KHR_composition_layer_cube is not enabled or not supported by the runtime");
+    }
+    let extent = image.texture_descriptor.size;
+    let size = extent.width;
+    let stacked = match (extent.depth_or_array_layers, extent.height) {
+        (6, height) if height == size => false,
+        (1, height) if height == size * 6 => true,
+        _ => eyre::bail!(
+            "{}x{}x{} is not the size of a cubemap",
+            extent.width,
+            extent.height,
+            extent.depth_or_array_layers
+        ),
+    };
+    let format = image.texture_descriptor.format;
+    if !graphics::enumerate_swapchain_formats(session)?.contains(&format) {
+        eyre::bail!("The runtime doesn't support {:?} swapchains", format);
+    }
+
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = format
+        .block_copy_size(None)
+        .ok_or_else(|| eyre::eyre!("{:?} can't be copied", format))?;
+    let bytes_per_row = size.div_ceil(block_width) * block_size;
+    let rows_per_image = size.div_ceil(block_height);
+    let face_bytes = (bytes_per_row * rows_per_image) as usize;
+    // array layers are stored one after the other including their mips
+    let face_stride = match stacked {
+        true => face_bytes,
+        false => image.data.len() / 6,
+    };
+    if face_stride < face_bytes || image.data.len() < face_stride * 6 {
+        eyre::bail!("The image data is smaller than its size");
+    }
+
+    let swapchain = graphics::create_cube_swapchain(session, render_device, format, size)?;
+    // static swapchains only get a single image, which is kept after releasing it
+    swapchain.acquire_image()?;
+    swapchain.wait_image()?;
+    for face in 0..6 {
+        let start = face * face_stride;
+        render_queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: swapchain.texture(),
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: face as u32,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &image.data[start..start + face_bytes],
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(rows_per_image),
+            },
+            wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+    // the copies have to be submitted before the image is released
+    render_queue.submit([]);
+    swapchain.release_image()?;
+    Ok(swapchain)
+}
+
+fn hide_camera_skyboxes(
+    mut commands: Commands,
+    layer: Option<Res<XrSkyboxLayer>>,
+    shown: Query<(Entity, &Skybox), With<XrCamera>>,
+    hidden: Query<(Entity, &HiddenSkybox)>,
+) {
+    match layer {
+        Some(_) => {
+            for (entity, skybox) in &shown {
+                commands
+                    .entity(entity)
+                    .remove::<Skybox>()
+                    .insert(HiddenSkybox(skybox.clone()));
+            }
+        }
+        None => {
+            for (entity, hidden) in &hidden {
+                commands
+                    .entity(entity)
+                    .remove::<HiddenSkybox>()
+                    .insert(hidden.0.clone());
+            }
+        }
+    }
+}
+
+/// The swapchain belongs to the session
+fn cleanup_skybox(mut commands: Commands) {
+    commands.remove_resource::<XrSkyboxLayer>();
+}
+
+fn extract_skybox(
+    mut commands: Commands,
+    skybox: Extract<Option<Res<XrSkybox>>>,
+    layer: Extract<Option<Res<XrSkyboxLayer>>>,
+) {
+    match (skybox.as_deref(), layer.as_deref()) {
+        (Some(skybox), Some(layer)) => commands.insert_resource(ExtractedXrSkybox {
+            swapchain: layer.swapchain.clone(),
+            orientation: skybox.orientation,
+        }),
+        _ => commands.remove_resource::<ExtractedXrSkybox>(),
+    }
+}
@@ -0,0 +1,121 @@
+//! What happens when no headset is available while the app starts, selected with
+//! [`DefaultXrPlugins::startup_policy`](crate::DefaultXrPlugins).
+
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+
+use crate::xr_init::XrUnavailableReason;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum XrStartupPolicy {
+    /// Run as a regular flatscreen app when OpenXR can't be used
+    #[default]
+    FallbackToFlatscreen,
+    /// Exit the app when OpenXR can't be used
+    FailFast,
+    /// Keep trying while the runtime or the headset is unavailable, other errors and running
+    /// into the timeout fall back to flatscreen. A headset that is asleep or not connected yet
+    /// makes `xrGetSystem` fail with `FORM_FACTOR_UNAVAILABLE`, a runtime that isn't started yet
+    /// fails instance creation with `RUNTIME_UNAVAILABLE`.
+    ///
+    /// The render device is created through the OpenXR instance, so waiting happens while the
+    /// [`OpenXrPlugin`](crate::OpenXrPlugin) is built, before the window and the event loop
+    /// exist. Once the headset shows up startup continues like it was there all along.
+    WaitForHeadset {
+        /// Time between two attempts
+        poll_interval: Duration,
+        /// `None` waits forever
+        timeout: Option<Duration>,
+    },
+    /// Don't try OpenXR at all and run the [`XrSimulatorPlugin`](crate::simulator::XrSimulatorPlugin)
+    /// in the window instead, which is what `BEVY_OXR_SIMULATOR=1` selects
+    Simulate,
+}
+
+/// How finding the headset at startup went, the events can be read during the first frame
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrStartupEvent {
+    /// An attempt failed because the runtime or the headset is unavailable
+    WaitingForHeadset {
+        reason: XrUnavailableReason,
+        attempt: u32,
+        waited: Duration,
+    },
+    /// The headset showed up after waiting
+    HeadsetFound { waited: Duration },
+    /// [`XrStartupPolicy::WaitForHeadset`] ran into its timeout
+    TimedOut { reason: XrUnavailableReason },
+}
+
+impl XrStartupPolicy {
+    /// Calls `init` until it succeeds or waiting is pointless, the events describe the wait
+    pub(crate) fn run<T>(
+        &self,
+        mut init: impl FnMut() -> eyre::Result<T>,
+        events: &mut Vec<XrStartupEvent>,
+    ) -> eyre::Result<T> {
+        if *self == XrStartupPolicy::Simulate {
+            return Err(SimulatorSelected.into());
+        }
+        let XrStartupPolicy::WaitForHeadset {
+            poll_interval,
+            timeout,
+        } = *self
+        else {
+            return init();
+        };
+        let start = Instant::now();
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let err = match init() {
+                Ok(value) => {
+                    if attempt > 1 {
+                        let waited = start.elapsed();
+                        info!("Found the headset after waiting {:?}", waited);
+                        events.push(XrStartupEvent::HeadsetFound { waited });
+                    }
+                    return Ok(value);
+                }
+                Err(err) => err,
+            };
+            let reason = XrUnavailableReason::from_error(&err);
+            if !matches!(
+                reason,
+                XrUnavailableReason::RuntimeUnavailable
+                    | XrUnavailableReason::FormFactorUnavailable
+            ) {
+                return Err(err);
+            }
+            let waited = start.elapsed();
+            if timeout.is_some_and(|timeout| waited + poll_interval > timeout) {
+                warn!("Gave up waiting for the headset after {:?}", waited);
+                events.push(XrStartupEvent::TimedOut { reason });
+                return Err(err);
+            }
+            info!(
+                "Waiting for the headset ({:?}, attempt {}): {}",
+                reason, attempt, err
+            );
+            events.push(XrStartupEvent::WaitingForHeadset {
+                reason,
+                attempt,
+                waited,
+            });
+            std::thread::sleep(poll_interval);
+        }
+    }
+}
+
+/// The error [`XrStartupPolicy::Simulate`] fails startup with
+#[derive(Debug)]
+pub(crate) struct SimulatorSelected;
+
+impl std::fmt::Display for SimulatorSelected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The simulator was selected instead of a headset")
+    }
+}
+
+impl std::error::Error for SimulatorSelected {}
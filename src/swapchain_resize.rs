@@ -0,0 +1,205 @@
+//! Recreating the swapchain of the views with another resolution while the session runs, when
+//! the app sends [`ResizeXrSwapchain`]. See the `swapchain_resize` example.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::render::camera::{CameraUpdateSystem, ManualTextureViews};
+use bevy::render::renderer::RenderDevice;
+use bevy::time::Stopwatch;
+use openxr as xr;
+
+use crate::error::{Result, XrResultExt};
+use crate::foveation::XrFoveationSettings;
+use crate::graphics::{self, XrSessionConfig};
+use crate::resources::{XrFormat, XrInstance, XrResolution, XrSession, XrSwapchain, XrViews};
+use crate::xr_init::{insert_manual_texture_views, xr_only, XrWaitFrame};
+
+/// How often the recommended resolution is queried, the runtime doesn't send an event for it
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Fov angles closer than this, in radians, count as the same
+const FOV_EPSILON: f32 = 1e-3;
+
+pub struct XrSwapchainResizePlugin;
+
+impl Plugin for XrSwapchainResizePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<XrViewConfigurationChanged>();
+        app.add_event::<ResizeXrSwapchain>();
+        app.add_systems(
+            PreUpdate,
+            detect_view_configuration_changes
+                .after(XrWaitFrame)
+                .run_if(xr_only()),
+        );
+        app.add_systems(
+            PostUpdate,
+            resize_swapchain
+                .run_if(xr_only())
+                .before(CameraUpdateSystem),
+        );
+    }
+}
+
+/// The recommended resolution or the fov of the views changed while the session runs, nothing
+/// is resized until the app sends [`ResizeXrSwapchain`]
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrViewConfigurationChanged {
+    /// The recommended resolution scaled by [`XrSessionConfig::render_scale`], the size a new
+    /// session would create its swapchain with
+    pub recommended_resolution: UVec2,
+    /// `true` if the fov of a view changed since the last frame
+    pub fov_changed: bool,
+}
+
+/// Recreates the swapchain of the views with another resolution before the cameras update.
+/// The new swapchain keeps the format and the depth swapchain of the old one. It allocates new
+/// images, unlike [`XrDynamicResolution`](crate::dynamic_resolution::XrDynamicResolution), so it
+/// isn't meant to happen often. The GLES backend can't resize its swapchain.
+#[derive(Event, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResizeXrSwapchain {
+    /// The size of each view, `None` uses the recommended resolution
+    pub resolution: Option<UVec2>,
+}
+
+/// The recommended resolution of the views scaled by [`XrSessionConfig::render_scale`]
+pub fn recommended_resolution(
+    instance: &XrInstance,
+    session_config: &XrSessionConfig,
+) -> Result<UVec2> {
+    let system = instance
+        .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+        .call("xrGetSystem")?;
+    let views = instance
+        .enumerate_view_configuration_views(system, session_config.view_config.view_type())
+        .call("xrEnumerateViewConfigurationViews")?;
+    graphics::swapchain_resolution(instance, system, &views, session_config.render_scale)
+        .call("xrGetSystemProperties")
+}
+
+fn fov_differs(a: &xr::Fovf, b: &xr::Fovf) -> bool {
+    [
+        a.angle_left - b.angle_left,
+        a.angle_right - b.angle_right,
+        a.angle_up - b.angle_up,
+        a.angle_down - b.angle_down,
+    ]
+    .into_iter()
+    .any(|difference| difference.abs() > FOV_EPSILON)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn detect_view_configuration_changes(
+    mut changed: EventWriter<XrViewConfigurationChanged>,
+    instance: Res<XrInstance>,
+    session_config: Res<XrSessionConfig>,
+    views: Res<XrViews>,
+    time: Res<Time<Real>>,
+    mut since_poll: Local<Stopwatch>,
+    mut recommended: Local<Option<UVec2>>,
+    mut fovs: Local<Vec<xr::Fovf>>,
+) {
+    // the views are empty until they were located once
+    let mut fov_changed = false;
+    if !views.is_empty() {
+        fov_changed = !fovs.is_empty()
+            && (fovs.len() != views.len()
+                || fovs
+                    .iter()
+                    .zip(views.iter())
+                    .any(|(last, view)| fov_differs(last, &view.fov)));
+        fovs.clear();
+        fovs.extend(views.iter().map(|view| view.fov));
+    }
+
+    since_poll.tick(time.delta());
+    let mut resolution_changed = false;
+    if recommended.is_none() || since_poll.elapsed() >= POLL_INTERVAL {
+        since_poll.reset();
+        match recommended_resolution(&instance, &session_config) {
+            Ok(resolution) => {
+                resolution_changed = recommended.is_some_and(|last| last != resolution);
+                *recommended = Some(resolution);
+            }
+            Err(err) => warn!("Unable to query the recommended resolution: {}", err),
+        }
+    }
+
+    if let Some(recommended_resolution) = *recommended {
+        if fov_changed || resolution_changed {
+            if resolution_changed {
+                info!(
+                    "The runtime recommends a resolution of {} now",
+                    recommended_resolution
+                );
+            }
+            changed.send(XrViewConfigurationChanged {
+                recommended_resolution,
+                fov_changed,
+            });
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn resize_swapchain(
+    mut events: EventReader<ResizeXrSwapchain>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    session_config: Res<XrSessionConfig>,
+    render_device: Res<RenderDevice>,
+    format: Res<XrFormat>,
+    mut swapchain: ResMut<XrSwapchain>,
+    mut resolution: ResMut<XrResolution>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+    foveation: Option<ResMut<XrFoveationSettings>>,
+    mut retired: Local<Vec<(XrSwapchain, u32)>>,
+) {
+    // the render world keeps rendering into the old swapchain until it's extracted again, and
+    // the gpu may still read it after that
+    retired.retain_mut(|(_, frames)| {
+        *frames = frames.saturating_sub(1);
+        *frames > 0
+    });
+    let Some(event) = events.read().last().copied() else {
+        return;
+    };
+    let target = match event.resolution {
+        Some(target) => target.max(UVec2::ONE),
+        None => match recommended_resolution(&instance, &session_config) {
+            Ok(target) => target,
+            Err(err) => {
+                warn!("Unable to resize the swapchain: {}", err);
+                return;
+            }
+        },
+    };
+    if target == **resolution {
+        return;
+    }
+    let resized = match graphics::resize_swapchain(
+        &instance,
+        &session,
+        &swapchain,
+        &render_device,
+        **format,
+        target,
+        session_config.view_config.view_count(),
+    ) {
+        Ok(resized) => resized,
+        Err(err) => {
+            error!("Unable to resize the swapchain to {}: {}", target, err);
+            return;
+        }
+    };
+    info!("Resized the swapchain from {} to {}", **resolution, target);
+    insert_manual_texture_views(&mut manual_texture_views, &resized, target);
+    let old = std::mem::replace(&mut *swapchain, resized.into());
+    retired.push((old, 3));
+    **resolution = target;
+    // the foveation profile is set per swapchain
+    if let Some(mut foveation) = foveation {
+        foveation.set_changed();
+    }
+}
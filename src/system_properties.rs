@@ -0,0 +1,138 @@
+use std::ffi::CStr;
+use std::{mem, ptr};
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::resources::XrInstance;
+
+/// What the headset the instance runs on can do, inserted together with the [`XrInstance`]
+/// so it's available to startup systems.
+#[derive(Clone, Debug, PartialEq, Eq, Resource)]
+pub struct XrSystemProperties {
+    /// Human readable name of the system, like the headset model
+    pub name: String,
+    pub vendor_id: u32,
+    pub orientation_tracking: bool,
+    pub position_tracking: bool,
+    pub max_swapchain_image_width: u32,
+    pub max_swapchain_image_height: u32,
+    /// Maximum number of layers per frame, including the projection layer
+    pub max_layer_count: u32,
+    /// Only `true` if `XR_EXT_hand_tracking` is enabled and the system supports it
+    pub hand_tracking: bool,
+    /// Only `true` if `XR_EXT_eye_gaze_interaction` is enabled and the system supports it
+    pub eye_gaze_interaction: bool,
+    /// Only `true` if `XR_FB_face_tracking` is enabled and the system supports it
+    pub face_tracking: bool,
+    /// Only `true` if `XR_FB_body_tracking` is enabled and the system supports it
+    pub body_tracking: bool,
+    /// Only `true` if `XR_META_foveation_eye_tracked` is enabled and the system supports it
+    pub foveation_eye_tracked: bool,
+}
+
+impl XrInstance {
+    /// The properties of the head mounted display system,
+    /// the capabilities of enabled extensions are queried in the same call
+    pub fn hmd_system_properties(&self) -> xr::Result<XrSystemProperties> {
+        let system = self.system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)?;
+        let exts = self.exts();
+        unsafe {
+            let mut hand_tracking = xr::sys::SystemHandTrackingPropertiesEXT {
+                ty: xr::sys::SystemHandTrackingPropertiesEXT::TYPE,
+                next: ptr::null_mut(),
+                supports_hand_tracking: false.into(),
+            };
+            let mut eye_gaze = xr::sys::SystemEyeGazeInteractionPropertiesEXT {
+                ty: xr::sys::SystemEyeGazeInteractionPropertiesEXT::TYPE,
+                next: ptr::null_mut(),
+                supports_eye_gaze_interaction: false.into(),
+            };
+            let mut face_tracking = xr::sys::SystemFaceTrackingPropertiesFB {
+                ty: xr::sys::SystemFaceTrackingPropertiesFB::TYPE,
+                next: ptr::null_mut(),
+                supports_face_tracking: false.into(),
+            };
+            let mut body_tracking = xr::sys::SystemBodyTrackingPropertiesFB {
+                ty: xr::sys::SystemBodyTrackingPropertiesFB::TYPE,
+                next: ptr::null_mut(),
+                supports_body_tracking: false.into(),
+            };
+            let mut foveation_eye_tracked = xr::sys::SystemFoveationEyeTrackedPropertiesMETA {
+                ty: xr::sys::SystemFoveationEyeTrackedPropertiesMETA::TYPE,
+                next: ptr::null_mut(),
+                supports_foveation_eye_tracked: false.into(),
+            };
+            let mut properties = xr::sys::SystemProperties {
+                ty: xr::sys::SystemProperties::TYPE,
+                ..mem::zeroed()
+            };
+            // structs of extensions that aren't enabled must not be chained
+            if exts.ext_hand_tracking.is_some() {
+                hand_tracking.next = properties.next;
+                properties.next = &mut hand_tracking as *mut _ as _;
+            }
+            if exts.ext_eye_gaze_interaction.is_some() {
+                eye_gaze.next = properties.next;
+                properties.next = &mut eye_gaze as *mut _ as _;
+            }
+            if exts.fb_face_tracking.is_some() {
+                face_tracking.next = properties.next;
+                properties.next = &mut face_tracking as *mut _ as _;
+            }
+            if exts.fb_body_tracking.is_some() {
+                body_tracking.next = properties.next;
+                properties.next = &mut body_tracking as *mut _ as _;
+            }
+            if exts.meta_foveation_eye_tracked.is_some() {
+                foveation_eye_tracked.next = properties.next;
+                properties.next = &mut foveation_eye_tracked as *mut _ as _;
+            }
+            let result = (self.fp().get_system_properties)(self.as_raw(), system, &mut properties);
+            if result.into_raw() < 0 {
+                return Err(result);
+            }
+            Ok(XrSystemProperties {
+                name: CStr::from_ptr(properties.system_name.as_ptr())
+                    .to_string_lossy()
+                    .into_owned(),
+                vendor_id: properties.vendor_id,
+                orientation_tracking: properties.tracking_properties.orientation_tracking.into(),
+                position_tracking: properties.tracking_properties.position_tracking.into(),
+                max_swapchain_image_width: properties.graphics_properties.max_swapchain_image_width,
+                max_swapchain_image_height: properties
+                    .graphics_properties
+                    .max_swapchain_image_height,
+                max_layer_count: properties.graphics_properties.max_layer_count,
+                hand_tracking: hand_tracking.supports_hand_tracking.into(),
+                eye_gaze_interaction: eye_gaze.supports_eye_gaze_interaction.into(),
+                face_tracking: face_tracking.supports_face_tracking.into(),
+                body_tracking: body_tracking.supports_body_tracking.into(),
+                foveation_eye_tracked: foveation_eye_tracked.supports_foveation_eye_tracked.into(),
+            })
+        }
+    }
+}
+
+/// The runtime the instance was created with, useful to work around runtime specific behavior
+#[derive(Clone, Debug, PartialEq, Eq, Resource)]
+pub struct XrRuntimeInfo {
+    pub name: String,
+    pub version: xr::Version,
+}
+
+impl std::fmt::Display for XrRuntimeInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name, self.version)
+    }
+}
+
+impl XrInstance {
+    pub fn runtime_info(&self) -> xr::Result<XrRuntimeInfo> {
+        let properties = self.properties()?;
+        Ok(XrRuntimeInfo {
+            name: properties.runtime_name,
+            version: properties.runtime_version,
+        })
+    }
+}
@@ -0,0 +1,114 @@
+//! Which handles can be used off the main thread. [`XrInstance`], [`XrSession`], [`XrInput`]
+//! and the actions of [`XrActionSets`] are `Send + Sync`, so actions can be read, spaces located
+//! and haptics applied from tasks, with the values of the last
+//! [`XrActionSync`](crate::xr_input::actions::XrActionSync).
+//!
+//! Waiting on, beginning and ending frames is left to the frame loop of the plugin, calling
+//! them through the handles of [`raw`](crate::raw) or a taken
+//! [`XrFrameWaiter`](crate::resources::XrFrameWaiter) races with it. The handles stop working
+//! once the session ends in [`XrCleanup`](crate::xr_init::XrCleanup), tasks should finish
+//! before that.
+
+use openxr as xr;
+
+use crate::input::XrInput;
+use crate::resources::{XrInstance, XrSession, XrViews};
+use crate::xr_input::actions::XrActionSets;
+
+// fails to compile if a handle stops being shareable between threads
+const _: fn() = || {
+    fn send_sync<T: Send + Sync>() {}
+    send_sync::<XrInstance>();
+    send_sync::<XrSession>();
+    send_sync::<xr::Session<xr::AnyGraphics>>();
+    send_sync::<XrInput>();
+    send_sync::<xr::Space>();
+    send_sync::<XrViews>();
+    send_sync::<XrActionSets>();
+    send_sync::<xr::Action<bool>>();
+    send_sync::<xr::Action<f32>>();
+    send_sync::<xr::Action<xr::Vector2f>>();
+    send_sync::<xr::Action<xr::Posef>>();
+    send_sync::<xr::Action<xr::Haptic>>();
+};
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+
+    use crate::input_script::XrInputScript;
+    use crate::recording::XrReplay;
+    use crate::simulator::SimulatedController;
+    use crate::xr_init::{XrStatus, XrUnavailableReason};
+    use crate::xr_input::actions::{XrActionSync, XrButton};
+    use crate::xr_input::tracked_controllers::XrController;
+    use crate::xr_input::{Hand, XrTrackingUpdate};
+
+    const FIRE: XrButton = XrButton {
+        action_set: "gameplay",
+        action: "fire",
+    };
+
+    /// What a task saw of the frame it was spawned in
+    #[derive(Resource, Default)]
+    struct Reads(Vec<(usize, Task<(bool, Vec3)>)>);
+
+    fn read_in_tasks(
+        replay: Res<XrReplay>,
+        buttons: Res<ButtonInput<XrButton>>,
+        controllers: Query<(&XrController, &Transform), With<SimulatedController>>,
+        mut reads: ResMut<Reads>,
+    ) {
+        let Some(frame) = replay.frame().filter(|_| !replay.is_finished()) else {
+            return;
+        };
+        let buttons = buttons.clone();
+        let controllers = controllers
+            .iter()
+            .map(|(controller, transform)| (controller.hand, *transform))
+            .collect::<Vec<_>>();
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            let (_, right) = controllers
+                .iter()
+                .find(|(hand, _)| *hand == Hand::Right)
+                .unwrap();
+            (buttons.pressed(FIRE), right.translation)
+        });
+        reads.0.push((frame, task));
+    }
+
+    // there is no OpenXR runtime in tests, the simulator provides the input instead
+    #[test]
+    fn input_is_read_from_tasks() {
+        let script = XrInputScript::new()
+            .controller(Hand::Right, 0.1, Transform::from_xyz(1.0, 1.0, -1.0))
+            .click(0.03, FIRE, 0.04);
+        let period = XrInputScript::FRAME_PERIOD;
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(XrStatus::Unavailable(XrUnavailableReason::Simulated))
+            .init_resource::<ButtonInput<XrButton>>()
+            .init_resource::<Reads>()
+            .configure_sets(PreUpdate, (XrActionSync, XrTrackingUpdate).chain())
+            .add_plugins(script.replay(period))
+            .add_systems(Update, read_in_tasks);
+        app.finish();
+        app.cleanup();
+        while !app.world.resource::<XrReplay>().is_finished() {
+            app.update();
+        }
+        let reads = std::mem::take(&mut app.world.resource_mut::<Reads>().0);
+        assert_eq!(
+            reads.len(),
+            XrInputScript::frame_at(script.end(), period) + 1
+        );
+        for (frame, task) in reads {
+            let expected = script.frame(frame, period);
+            let right = expected.controllers[1].unwrap();
+            let (pressed, translation) = block_on(task);
+            assert_eq!(pressed, expected.buttons.contains(&FIRE), "frame {}", frame);
+            assert_eq!(translation, right.transform.translation, "frame {}", frame);
+        }
+    }
+}
@@ -0,0 +1,735 @@
+//! Sharing the tracked poses of a player with other devices, like colocated headsets on a LAN.
+//! [`XrTrackedSnapshot::encode`] packs the poses of a frame into a few bytes for any transport,
+//! see the `pose_streaming` example.
+
+use std::collections::VecDeque;
+use std::f32::consts::FRAC_1_SQRT_2;
+use std::time::{Duration, SystemTime};
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::recording::relative_to_root;
+use crate::resources::{XrFrameState, XrInstance};
+use crate::xr_input::hands::hand_tracking::TrackedHand;
+use crate::xr_input::trackers::{
+    OpenXRHMD, OpenXRLeftController, OpenXRRightController, OpenXRTrackingRoot, TrackingConfidence,
+};
+use crate::xr_input::Hand;
+
+/// The version of the format written by [`XrTrackedSnapshot::encode`]
+pub const SNAPSHOT_VERSION: u8 = 1;
+/// The joints of a hand, in the order of
+/// [`HandBone::get_all_bones`](crate::xr_input::hands::HandBone::get_all_bones)
+pub const SNAPSHOT_JOINTS: usize = 26;
+
+/// Bits of the presence byte of the encoding
+const HEAD: u8 = 1 << 0;
+const CONTROLLERS: [u8; 2] = [1 << 1, 1 << 2];
+const HANDS: [u8; 2] = [1 << 3, 1 << 4];
+/// Joint radii are stored in tenths of a millimeter
+const RADIUS_SCALE: f32 = 10_000.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XrSnapshotPose {
+    pub position: Vec3,
+    pub orientation: Quat,
+}
+
+impl XrSnapshotPose {
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.position).with_rotation(self.orientation)
+    }
+
+    /// `t` past one extrapolates from the motion between the poses
+    pub fn interpolate(&self, other: &Self, t: f32) -> Self {
+        let mut delta = other.orientation * self.orientation.inverse();
+        if delta.w < 0.0 {
+            delta = -delta;
+        }
+        Self {
+            position: self.position.lerp(other.position, t),
+            orientation: (Quat::from_scaled_axis(delta.to_scaled_axis() * t) * self.orientation)
+                .normalize(),
+        }
+    }
+}
+
+impl From<Transform> for XrSnapshotPose {
+    fn from(transform: Transform) -> Self {
+        Self {
+            position: transform.translation,
+            orientation: transform.rotation,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XrSnapshotJoint {
+    pub pose: XrSnapshotPose,
+    pub radius: f32,
+}
+
+/// The tracked poses of a player in one frame, relative to their tracking root. Aligning the
+/// spaces of colocated devices is up to the app, like with a shared anchor.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XrTrackedSnapshot {
+    /// Counts up with every snapshot of the sender, wrapping around
+    pub sequence: u32,
+    /// The predicted display time of the frame since the unix epoch, in the system clock of
+    /// the sender
+    pub time: Duration,
+    pub head: Option<XrSnapshotPose>,
+    /// The left and right controller, `None` while it isn't tracked
+    pub controllers: [Option<XrSnapshotPose>; 2],
+    /// The joints of the left and right hand, `None` without hand tracking
+    pub hands: [Option<[XrSnapshotJoint; SNAPSHOT_JOINTS]>; 2],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrSnapshotDecodeError {
+    /// Written by another version of the format
+    UnsupportedVersion(u8),
+    /// The bytes end before the snapshot does
+    Truncated,
+    /// More bytes follow the snapshot
+    TrailingBytes,
+}
+
+impl std::fmt::Display for XrSnapshotDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XrSnapshotDecodeError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported snapshot version {}", version)
+            }
+            XrSnapshotDecodeError::Truncated => write!(f, "Truncated snapshot"),
+            XrSnapshotDecodeError::TrailingBytes => write!(f, "Bytes after the snapshot"),
+        }
+    }
+}
+
+impl std::error::Error for XrSnapshotDecodeError {}
+
+impl XrTrackedSnapshot {
+    /// The snapshot in a compact little endian format, a version byte, a byte marking the
+    /// present poses, the sequence and the time, then the poses. Positions are stored as floats,
+    /// orientations in 32 bits, which is accurate to a quarter of a degree. The head and both
+    /// controllers take 62 bytes, each hand adds 468.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.encode_into(&mut bytes);
+        bytes
+    }
+
+    /// Appends the encoded snapshot to `bytes`, see [`XrTrackedSnapshot::encode`]
+    pub fn encode_into(&self, bytes: &mut Vec<u8>) {
+        let mut present = 0;
+        if self.head.is_some() {
+            present |= HEAD;
+        }
+        for (i, (controller, hand)) in self.controllers.iter().zip(&self.hands).enumerate() {
+            if controller.is_some() {
+                present |= CONTROLLERS[i];
+            }
+            if hand.is_some() {
+                present |= HANDS[i];
+            }
+        }
+        bytes.extend_from_slice(&[SNAPSHOT_VERSION, present]);
+        bytes.extend_from_slice(&self.sequence.to_le_bytes());
+        bytes.extend_from_slice(&(self.time.as_nanos() as u64).to_le_bytes());
+        for pose in self.head.iter().chain(self.controllers.iter().flatten()) {
+            write_pose(bytes, pose);
+        }
+        for joints in self.hands.iter().flatten() {
+            for joint in joints {
+                write_pose(bytes, &joint.pose);
+                let radius = (joint.radius * RADIUS_SCALE)
+                    .round()
+                    .clamp(0.0, u16::MAX as f32);
+                bytes.extend_from_slice(&(radius as u16).to_le_bytes());
+            }
+        }
+    }
+
+    /// Reads a snapshot written by [`XrTrackedSnapshot::encode`]
+    pub fn decode(bytes: &[u8]) -> Result<Self, XrSnapshotDecodeError> {
+        let mut reader = Reader(bytes);
+        let [version, present] = reader.array()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(XrSnapshotDecodeError::UnsupportedVersion(version));
+        }
+        let mut snapshot = XrTrackedSnapshot {
+            sequence: u32::from_le_bytes(reader.array()?),
+            time: Duration::from_nanos(u64::from_le_bytes(reader.array()?)),
+            ..default()
+        };
+        if present & HEAD != 0 {
+            snapshot.head = Some(reader.pose()?);
+        }
+        for (controller, bit) in snapshot.controllers.iter_mut().zip(CONTROLLERS) {
+            if present & bit != 0 {
+                *controller = Some(reader.pose()?);
+            }
+        }
+        for (hand, bit) in snapshot.hands.iter_mut().zip(HANDS) {
+            if present & bit == 0 {
+                continue;
+            }
+            let mut joints = [XrSnapshotJoint::default(); SNAPSHOT_JOINTS];
+            for joint in &mut joints {
+                joint.pose = reader.pose()?;
+                joint.radius = u16::from_le_bytes(reader.array()?) as f32 / RADIUS_SCALE;
+            }
+            *hand = Some(joints);
+        }
+        match reader.0.is_empty() {
+            true => Ok(snapshot),
+            false => Err(XrSnapshotDecodeError::TrailingBytes),
+        }
+    }
+
+    /// The poses between `self` and `other`, `t` past one extrapolates. Poses missing from one
+    /// of the snapshots are taken from the one `t` is closer to.
+    pub fn interpolate(&self, other: &Self, t: f32) -> Self {
+        fn pick<T: Copy>(
+            a: Option<T>,
+            b: Option<T>,
+            closer: Option<T>,
+            f: impl Fn(T, T) -> T,
+        ) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(f(a, b)),
+                _ => closer,
+            }
+        }
+        let closer = if t < 0.5 { self } else { other };
+        let span = other.time.as_nanos() as i128 - self.time.as_nanos() as i128;
+        let time = self.time.as_nanos() as i128 + (span as f64 * t as f64) as i128;
+        XrTrackedSnapshot {
+            sequence: closer.sequence,
+            time: u64::try_from(time).map_or(closer.time, Duration::from_nanos),
+            head: pick(self.head, other.head, closer.head, |a, b| {
+                a.interpolate(&b, t)
+            }),
+            controllers: [0, 1].map(|i| {
+                pick(
+                    self.controllers[i],
+                    other.controllers[i],
+                    closer.controllers[i],
+                    |a, b| a.interpolate(&b, t),
+                )
+            }),
+            hands: [0, 1].map(|i| {
+                pick(
+                    self.hands[i],
+                    other.hands[i],
+                    closer.hands[i],
+                    |mut a, b| {
+                        for (joint, b) in a.iter_mut().zip(b) {
+                            joint.pose = joint.pose.interpolate(&b.pose, t);
+                            joint.radius += (b.radius - joint.radius) * t;
+                        }
+                        a
+                    },
+                )
+            }),
+        }
+    }
+}
+
+fn write_pose(bytes: &mut Vec<u8>, pose: &XrSnapshotPose) {
+    for value in pose.position.to_array() {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes.extend_from_slice(&pack_quat(pose.orientation).to_le_bytes());
+}
+
+/// The three smallest components with 10 bits each and the index of the largest one in the top
+/// two bits, the largest one follows from the quaternion being normalized
+fn pack_quat(quat: Quat) -> u32 {
+    let mut values = quat.normalize().to_array();
+    let largest = (0..4)
+        .max_by(|&a, &b| values[a].abs().total_cmp(&values[b].abs()))
+        .unwrap_or(3);
+    if values[largest] < 0.0 {
+        values = values.map(|value| -value);
+    }
+    let mut packed = (largest as u32) << 30;
+    let mut shift = 20;
+    for (i, value) in values.into_iter().enumerate() {
+        if i == largest {
+            continue;
+        }
+        let normalized = (value / FRAC_1_SQRT_2 * 0.5 + 0.5).clamp(0.0, 1.0);
+        packed |= ((normalized * 1023.0).round() as u32) << shift;
+        shift -= 10;
+    }
+    packed
+}
+
+fn unpack_quat(packed: u32) -> Quat {
+    let largest = (packed >> 30) as usize;
+    let mut values = [0.0; 4];
+    let mut shift = 20;
+    for (i, value) in values.iter_mut().enumerate() {
+        if i == largest {
+            continue;
+        }
+        let normalized = ((packed >> shift) & 1023) as f32 / 1023.0;
+        *value = (normalized - 0.5) * 2.0 * FRAC_1_SQRT_2;
+        shift -= 10;
+    }
+    let rest = values.iter().map(|value| value * value).sum::<f32>();
+    values[largest] = (1.0 - rest).max(0.0).sqrt();
+    Quat::from_array(values).normalize()
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], XrSnapshotDecodeError> {
+        if self.0.len() < N {
+            return Err(XrSnapshotDecodeError::Truncated);
+        }
+        let (bytes, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn pose(&mut self) -> Result<XrSnapshotPose, XrSnapshotDecodeError> {
+        let [x, y, z] = [(); 3].map(|_| self.array().map(f32::from_le_bytes));
+        Ok(XrSnapshotPose {
+            position: Vec3::new(x?, y?, z?),
+            orientation: unpack_quat(u32::from_le_bytes(self.array()?)),
+        })
+    }
+}
+
+/// Captures the poses of this frame into an [`XrTrackedSnapshot`]
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub struct XrSnapshotCapture<'w, 's> {
+    instance: Option<Res<'w, XrInstance>>,
+    frame_state: Option<Res<'w, XrFrameState>>,
+    transforms: Query<
+        'w,
+        's,
+        (
+            &'static Transform,
+            Option<&'static Parent>,
+            Has<OpenXRTrackingRoot>,
+        ),
+    >,
+    head: Query<'w, 's, Entity, With<OpenXRHMD>>,
+    controllers: Query<
+        'w,
+        's,
+        (
+            Entity,
+            Has<OpenXRLeftController>,
+            Option<&'static TrackingConfidence>,
+        ),
+        Or<(With<OpenXRLeftController>, With<OpenXRRightController>)>,
+    >,
+    hands: Query<'w, 's, (&'static Hand, &'static TrackedHand)>,
+}
+
+impl XrSnapshotCapture<'_, '_> {
+    /// The poses relative to the tracking root, `None` until the head is tracked.
+    /// Without a session, like with the simulator, the time is the current system time.
+    pub fn capture(&self, sequence: u32) -> Option<XrTrackedSnapshot> {
+        let head = relative_to_root(self.head.get_single().ok()?, &self.transforms)?;
+        let display_time = match (&self.instance, &self.frame_state) {
+            (Some(instance), Some(frame_state)) => {
+                instance.to_system_time(frame_state.predicted_display_time.into())
+            }
+            _ => None,
+        };
+        let time = display_time
+            .unwrap_or_else(SystemTime::now)
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut snapshot = XrTrackedSnapshot {
+            sequence,
+            time,
+            head: Some(head.into()),
+            ..default()
+        };
+        for (entity, left, confidence) in &self.controllers {
+            if confidence == Some(&TrackingConfidence::Lost) {
+                continue;
+            }
+            snapshot.controllers[usize::from(!left)] =
+                relative_to_root(entity, &self.transforms).map(XrSnapshotPose::from);
+        }
+        for (hand, tracked) in &self.hands {
+            let Some(joints) = &tracked.joints else {
+                continue;
+            };
+            let index = match hand {
+                Hand::Left => 0,
+                Hand::Right => 1,
+            };
+            let mut captured = [XrSnapshotJoint::default(); SNAPSHOT_JOINTS];
+            for (captured, joint) in captured.iter_mut().zip(joints.inner()) {
+                *captured = XrSnapshotJoint {
+                    pose: XrSnapshotPose {
+                        position: joint.position,
+                        orientation: joint.orientation,
+                    },
+                    radius: joint.radius,
+                };
+            }
+            snapshot.hands[index] = Some(captured);
+        }
+        Some(snapshot)
+    }
+}
+
+/// The snapshots received from one sender, for smooth remote avatars. Snapshots arrive with
+/// jitter, so they are shown [`delay`](Self::delay) behind the newest one and interpolated,
+/// late ones are extrapolated for up to [`max_extrapolation`](Self::max_extrapolation).
+/// The offset to the clock of the sender is estimated from the times the snapshots arrive at,
+/// so the clocks don't have to be synchronized.
+#[derive(Component, Clone, Debug)]
+pub struct XrSnapshotBuffer {
+    /// How far the sampled poses are behind the snapshots arriving, should cover a few
+    /// snapshot intervals
+    pub delay: Duration,
+    /// How far past the newest snapshot poses are extrapolated before they stop
+    pub max_extrapolation: Duration,
+    /// Older snapshots are dropped
+    pub capacity: usize,
+    snapshots: VecDeque<XrTrackedSnapshot>,
+    /// Estimated local minus remote time, in nanoseconds
+    offset: Option<i128>,
+}
+
+impl Default for XrSnapshotBuffer {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(100))
+    }
+}
+
+impl XrSnapshotBuffer {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            max_extrapolation: Duration::from_millis(250),
+            capacity: 64,
+            snapshots: VecDeque::new(),
+            offset: None,
+        }
+    }
+
+    /// Adds a snapshot that arrived at the local time `received`, like
+    /// [`Time<Real>::elapsed`]. Snapshots older than the newest one are dropped and `false`
+    /// is returned.
+    pub fn push(&mut self, snapshot: XrTrackedSnapshot, received: Duration) -> bool {
+        if let Some(newest) = self.snapshots.back() {
+            let newer = (snapshot.sequence.wrapping_sub(newest.sequence) as i32) > 0;
+            if !newer || snapshot.time <= newest.time {
+                return false;
+            }
+        }
+        // the snapshot that arrived fastest bounds the offset, the estimate slowly drifts up
+        // again in case the clocks drift apart
+        let sample = received.as_nanos() as i128 - snapshot.time.as_nanos() as i128;
+        self.offset = Some(match self.offset {
+            Some(estimate) if sample >= estimate => estimate + (sample - estimate) / 64,
+            _ => sample,
+        });
+        self.snapshots.push_back(snapshot);
+        while self.snapshots.len() > self.capacity.max(2) {
+            self.snapshots.pop_front();
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.offset = None;
+    }
+
+    /// The received snapshots, oldest first
+    pub fn snapshots(&self) -> impl DoubleEndedIterator<Item = &XrTrackedSnapshot> {
+        self.snapshots.iter()
+    }
+
+    pub fn latest(&self) -> Option<&XrTrackedSnapshot> {
+        self.snapshots.back()
+    }
+
+    /// The poses to show at the local time `now`, in the clock [`XrSnapshotBuffer::push`] got.
+    /// `None` until a snapshot arrived.
+    pub fn sample(&self, now: Duration) -> Option<XrTrackedSnapshot> {
+        let offset = self.offset?;
+        let newest = self.snapshots.back()?;
+        let target = now.as_nanos() as i128 - offset - self.delay.as_nanos() as i128;
+        let nanos = |snapshot: &XrTrackedSnapshot| snapshot.time.as_nanos() as i128;
+
+        if target >= nanos(newest) {
+            let Some(previous) = self.snapshots.iter().rev().nth(1) else {
+                return Some(newest.clone());
+            };
+            let ahead = (target - nanos(newest)).min(self.max_extrapolation.as_nanos() as i128);
+            let span = nanos(newest) - nanos(previous);
+            let t = 1.0 + ahead as f64 / span as f64;
+            return Some(previous.interpolate(newest, t as f32));
+        }
+        let next = self
+            .snapshots
+            .iter()
+            .position(|snapshot| nanos(snapshot) > target)?;
+        if next == 0 {
+            return self.snapshots.front().cloned();
+        }
+        let (a, b) = (&self.snapshots[next - 1], &self.snapshots[next]);
+        let t = (target - nanos(a)) as f64 / (nanos(b) - nanos(a)) as f64;
+        Some(a.interpolate(b, t as f32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(10);
+
+    fn pose(x: f32, yaw: f32) -> XrSnapshotPose {
+        XrSnapshotPose {
+            position: Vec3::new(x, 1.6, -0.5),
+            orientation: Quat::from_euler(EulerRot::YXZ, yaw, 0.3, -0.2),
+        }
+    }
+
+    fn snapshot(sequence: u32, time: Duration) -> XrTrackedSnapshot {
+        let x = time.as_secs_f32();
+        let mut hand = [XrSnapshotJoint::default(); SNAPSHOT_JOINTS];
+        for (i, joint) in hand.iter_mut().enumerate() {
+            *joint = XrSnapshotJoint {
+                pose: pose(x + i as f32 * 0.01, i as f32 * 0.1),
+                radius: 0.005 + i as f32 * 0.0003,
+            };
+        }
+        XrTrackedSnapshot {
+            sequence,
+            time,
+            head: Some(pose(x, x)),
+            controllers: [None, Some(pose(x + 0.2, -x))],
+            hands: [Some(hand), None],
+        }
+    }
+
+    fn assert_pose_close(actual: &XrSnapshotPose, expected: &XrSnapshotPose) {
+        assert_eq!(actual.position, expected.position);
+        let angle = actual.orientation.angle_between(expected.orientation);
+        assert!(
+            angle < 0.2f32.to_radians(),
+            "off by {} degrees",
+            angle.to_degrees()
+        );
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let snapshot = snapshot(7, Duration::new(1_700_000_000, 123_456_789));
+        let bytes = snapshot.encode();
+        // a head, a controller and a hand
+        assert_eq!(bytes.len(), 14 + 2 * 16 + 468);
+        let decoded = XrTrackedSnapshot::decode(&bytes).unwrap();
+        assert_eq!(decoded.sequence, snapshot.sequence);
+        assert_eq!(decoded.time, snapshot.time);
+        assert_pose_close(&decoded.head.unwrap(), &snapshot.head.unwrap());
+        assert_eq!(decoded.controllers[0], None);
+        assert_pose_close(
+            &decoded.controllers[1].unwrap(),
+            &snapshot.controllers[1].unwrap(),
+        );
+        assert_eq!(decoded.hands[1], None);
+        let joints = decoded.hands[0].unwrap().into_iter();
+        for (decoded, joint) in joints.zip(snapshot.hands[0].unwrap()) {
+            assert_pose_close(&decoded.pose, &joint.pose);
+            assert!((decoded.radius - joint.radius).abs() <= 0.5 / RADIUS_SCALE);
+        }
+    }
+
+    #[test]
+    fn empty_snapshot_round_trip() {
+        let snapshot = XrTrackedSnapshot::default();
+        let bytes = snapshot.encode();
+        assert_eq!(bytes.len(), 14);
+        assert_eq!(XrTrackedSnapshot::decode(&bytes), Ok(snapshot));
+    }
+
+    /// In f64, as acos of a dot product close to one is too coarse in f32
+    fn angle_between(a: Quat, b: Quat) -> f64 {
+        let dot = a
+            .to_array()
+            .iter()
+            .zip(b.to_array())
+            .map(|(a, b)| *a as f64 * b as f64)
+            .sum::<f64>();
+        2.0 * dot.abs().min(1.0).acos()
+    }
+
+    #[test]
+    fn quaternions_are_packed_to_a_quarter_of_a_degree() {
+        let mut worst = 0f64;
+        for i in 0..10000 {
+            let i = i as f32;
+            let quat = Quat::from_euler(EulerRot::YXZ, i * 0.37, i * 0.11, i * 0.53);
+            for quat in [quat, -quat] {
+                let angle = angle_between(unpack_quat(pack_quat(quat)), quat);
+                worst = worst.max(angle);
+            }
+        }
+        assert!(
+            worst < 0.25f64.to_radians(),
+            "off by {} degrees",
+            worst.to_degrees()
+        );
+    }
+
+    #[test]
+    fn invalid_bytes_are_rejected() {
+        let bytes = snapshot(1, FRAME).encode();
+        for len in 0..bytes.len() {
+            assert_eq!(
+                XrTrackedSnapshot::decode(&bytes[..len]),
+                Err(XrSnapshotDecodeError::Truncated)
+            );
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            XrTrackedSnapshot::decode(&trailing),
+            Err(XrSnapshotDecodeError::TrailingBytes)
+        );
+        let mut version = bytes;
+        version[0] = SNAPSHOT_VERSION + 1;
+        assert_eq!(
+            XrTrackedSnapshot::decode(&version),
+            Err(XrSnapshotDecodeError::UnsupportedVersion(
+                SNAPSHOT_VERSION + 1
+            ))
+        );
+    }
+
+    #[test]
+    fn interpolation_between_snapshots() {
+        let a = XrTrackedSnapshot {
+            head: Some(pose(0.0, 0.0)),
+            controllers: [Some(pose(1.0, 0.0)), None],
+            ..snapshot(1, FRAME)
+        };
+        let b = XrTrackedSnapshot {
+            head: Some(pose(1.0, 1.0)),
+            controllers: [None, Some(pose(1.0, 0.0))],
+            ..snapshot(2, FRAME * 2)
+        };
+        let middle = a.interpolate(&b, 0.25);
+        assert_eq!(middle.time, FRAME + FRAME / 4);
+        assert_eq!(middle.sequence, 1);
+        assert_pose_close(&middle.head.unwrap(), &pose(0.25, 0.25));
+        // missing from one of them, taken from the closer one
+        assert_eq!(middle.controllers, a.controllers);
+        assert_eq!(a.interpolate(&b, 0.75).controllers, b.controllers);
+        // past the newer one the motion goes on
+        assert_pose_close(&a.interpolate(&b, 1.5).head.unwrap(), &pose(1.5, 1.5));
+    }
+
+    #[test]
+    fn orientations_take_the_short_way() {
+        let a = XrSnapshotPose {
+            orientation: Quat::from_rotation_y(0.1),
+            ..default()
+        };
+        let b = XrSnapshotPose {
+            orientation: -Quat::from_rotation_y(0.3),
+            ..default()
+        };
+        let middle = a.interpolate(&b, 0.5).orientation;
+        assert!(middle.angle_between(Quat::from_rotation_y(0.2)) < 1e-4);
+    }
+
+    #[test]
+    fn stale_snapshots_are_dropped() {
+        let mut buffer = XrSnapshotBuffer::default();
+        assert!(buffer.push(snapshot(5, FRAME * 5), FRAME * 5));
+        assert!(!buffer.push(snapshot(4, FRAME * 4), FRAME * 6));
+        assert!(!buffer.push(snapshot(5, FRAME * 5), FRAME * 6));
+        assert!(buffer.push(snapshot(6, FRAME * 6), FRAME * 6));
+        // the sequence wraps around
+        let mut buffer = XrSnapshotBuffer::default();
+        assert!(buffer.push(snapshot(u32::MAX, FRAME), FRAME));
+        assert!(buffer.push(snapshot(0, FRAME * 2), FRAME * 2));
+        assert_eq!(buffer.latest().unwrap().sequence, 0);
+    }
+
+    #[test]
+    fn capacity_keeps_the_newest() {
+        let mut buffer = XrSnapshotBuffer {
+            capacity: 3,
+            ..default()
+        };
+        for i in 1..10 {
+            buffer.push(snapshot(i, FRAME * i), FRAME * i);
+        }
+        let sequences = buffer.snapshots().map(|snapshot| snapshot.sequence);
+        assert_eq!(sequences.collect::<Vec<_>>(), [7, 8, 9]);
+    }
+
+    #[test]
+    fn sampled_behind_the_newest_snapshot() {
+        let mut buffer = XrSnapshotBuffer::new(FRAME * 2);
+        assert_eq!(buffer.sample(FRAME), None);
+        // the remote clock is a second ahead, arriving takes 5 to 8 ms
+        let remote = Duration::from_secs(1);
+        for i in 1..=10 {
+            let latency = Duration::from_millis(5 + (i as u64 * 7) % 4);
+            buffer.push(snapshot(i, remote + FRAME * i), FRAME * i + latency);
+        }
+        // the offset is estimated from the fastest arrival, slowly drifting towards later ones
+        let assert_time = |now: Duration, expected: Duration| {
+            let sampled = buffer.sample(now).unwrap();
+            let error = sampled.time.as_secs_f64() - expected.as_secs_f64();
+            assert!(
+                error.abs() < 0.001,
+                "{:?} isn't {:?}",
+                sampled.time,
+                expected
+            );
+            sampled
+        };
+        assert_time(FRAME * 10 + Duration::from_millis(5), remote + FRAME * 8);
+        let sampled = assert_time(
+            FRAME * 7 + Duration::from_millis(10),
+            remote + FRAME * 5 + FRAME / 2,
+        );
+        let expected = snapshot(0, sampled.time).head.unwrap();
+        assert!(sampled
+            .head
+            .unwrap()
+            .position
+            .abs_diff_eq(expected.position, 1e-4));
+        // before the oldest one
+        assert_eq!(buffer.sample(FRAME).unwrap().sequence, 1);
+    }
+
+    #[test]
+    fn extrapolation_stops_after_the_limit() {
+        let mut buffer = XrSnapshotBuffer::new(Duration::ZERO);
+        buffer.max_extrapolation = FRAME * 3;
+        buffer.push(snapshot(1, FRAME), FRAME);
+        // a single snapshot is held
+        assert_eq!(buffer.sample(FRAME * 3).unwrap().time, FRAME);
+        buffer.push(snapshot(2, FRAME * 2), FRAME * 2);
+        assert_eq!(buffer.sample(FRAME * 4).unwrap().time, FRAME * 4);
+        assert_eq!(buffer.sample(FRAME * 100).unwrap().time, FRAME * 5);
+    }
+}
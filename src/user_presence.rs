@@ -0,0 +1,245 @@
+//! Whether the user wears the headset, to pause the game the moment it's taken off instead of
+//! when the session eventually stops being visible.
+
+use std::ffi::c_void;
+use std::mem::MaybeUninit;
+use std::time::Duration;
+use std::{mem, ptr};
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::graphics::extensions::XrEnabledExtensions;
+use crate::resources::XrInstance;
+use crate::xr_init::{XrCleanup, XrPollEvents, XrSessionState, XrSetup, XrWaitFrame};
+
+pub const EXT_USER_PRESENCE_EXTENSION_NAME: &str = "XR_EXT_user_presence";
+
+// openxr 0.18 predates the extension
+const TYPE_EVENT_DATA_USER_PRESENCE_CHANGED_EXT: i32 = 1000470000;
+const TYPE_SYSTEM_USER_PRESENCE_PROPERTIES_EXT: i32 = 1000470001;
+
+#[allow(dead_code)]
+#[repr(C)]
+struct EventDataUserPresenceChangedEXT {
+    ty: xr::sys::StructureType,
+    next: *const c_void,
+    session: xr::sys::Session,
+    is_user_present: xr::sys::Bool32,
+}
+
+#[allow(dead_code)]
+#[repr(C)]
+struct SystemUserPresencePropertiesEXT {
+    ty: xr::sys::StructureType,
+    next: *mut c_void,
+    supports_user_presence: xr::sys::Bool32,
+}
+
+/// The user put the headset on or took it off
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrUserPresenceChanged {
+    pub present: bool,
+}
+
+/// Whether the user wears the headset, `true` until the runtime or the session state says otherwise
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Deref)]
+pub struct XrUserPresent(pub bool);
+
+impl Default for XrUserPresent {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Keeps [`XrUserPresent`] up to date, part of the [`DefaultXrPlugins`](crate::DefaultXrPlugins).
+/// Runtimes that support `XR_EXT_user_presence` report it from the proximity sensor, it's
+/// requested by default. Otherwise it's inferred from the session state.
+pub struct XrUserPresencePlugin {
+    /// Without `XR_EXT_user_presence` the user counts as gone once the session isn't visible
+    /// anymore, or once the session didn't have the focus for this long. The dashboard takes
+    /// the focus while the user still wears the headset, which shouldn't pause the game, so
+    /// brief focus losses are ignored.
+    pub focus_loss_delay: Duration,
+}
+
+impl Default for XrUserPresencePlugin {
+    fn default() -> Self {
+        Self {
+            focus_loss_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Plugin for XrUserPresencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrUserPresent>();
+        app.insert_resource(XrUserPresenceInference {
+            focus_loss_delay: self.focus_loss_delay,
+            reported: false,
+            was_visible: false,
+            focus_lost_at: None,
+        });
+        app.add_systems(XrSetup, check_reported_presence);
+        app.add_systems(XrCleanup, reset_user_presence);
+        app.add_systems(
+            PreUpdate,
+            (
+                apply_reported_presence.run_if(presence_reported),
+                infer_user_presence.run_if(not(presence_reported)),
+            )
+                .after(XrPollEvents)
+                .before(XrWaitFrame),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct XrUserPresenceInference {
+    focus_loss_delay: Duration,
+    /// The runtime sends [`XrUserPresenceChanged`] itself
+    reported: bool,
+    /// The session was visible since it began, before that the state says nothing about the user
+    was_visible: bool,
+    /// Real time at which the session lost the focus
+    focus_lost_at: Option<Duration>,
+}
+
+fn presence_reported(inference: Res<XrUserPresenceInference>) -> bool {
+    inference.reported
+}
+
+fn check_reported_presence(
+    instance: Res<XrInstance>,
+    extensions: Option<Res<XrEnabledExtensions>>,
+    mut inference: ResMut<XrUserPresenceInference>,
+) {
+    let enabled = extensions.is_some_and(|extensions| {
+        extensions
+            .enabled
+            .raw()
+            .other
+            .iter()
+            .any(|ext| ext == EXT_USER_PRESENCE_EXTENSION_NAME)
+    });
+    inference.reported = enabled
+        && supports_user_presence(&instance).unwrap_or_else(|err| {
+            warn!("Unable to query user presence support: {}", err);
+            false
+        });
+    if !inference.reported {
+        info!("The runtime doesn't report user presence, inferring it from the session state");
+    }
+}
+
+fn reset_user_presence(
+    mut present: ResMut<XrUserPresent>,
+    mut inference: ResMut<XrUserPresenceInference>,
+) {
+    present.set_if_neq(default());
+    inference.was_visible = false;
+    inference.focus_lost_at = None;
+}
+
+fn apply_reported_presence(
+    mut changed: EventReader<XrUserPresenceChanged>,
+    mut present: ResMut<XrUserPresent>,
+) {
+    if let Some(event) = changed.read().last() {
+        present.set_if_neq(XrUserPresent(event.present));
+    }
+}
+
+fn infer_user_presence(
+    state: Res<XrSessionState>,
+    time: Res<Time<Real>>,
+    mut inference: ResMut<XrUserPresenceInference>,
+    mut present: ResMut<XrUserPresent>,
+    mut changed: EventWriter<XrUserPresenceChanged>,
+) {
+    let now = time.elapsed();
+    let inferred = match **state {
+        xr::SessionState::FOCUSED => {
+            inference.was_visible = true;
+            inference.focus_lost_at = None;
+            true
+        }
+        xr::SessionState::VISIBLE => {
+            inference.was_visible = true;
+            let lost_at = *inference.focus_lost_at.get_or_insert(now);
+            now - lost_at < inference.focus_loss_delay
+        }
+        _ if inference.was_visible => false,
+        _ => return,
+    };
+    if present.set_if_neq(XrUserPresent(inferred)) {
+        info!("inferred user presence: {}", inferred);
+        changed.send(XrUserPresenceChanged { present: inferred });
+    }
+}
+
+/// Whether the system reports [`XrUserPresenceChanged`] events,
+/// `XR_EXT_user_presence` has to be enabled
+pub fn supports_user_presence(instance: &XrInstance) -> xr::Result<bool> {
+    let system = instance.system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)?;
+    unsafe {
+        let mut user_presence = SystemUserPresencePropertiesEXT {
+            ty: xr::sys::StructureType::from_raw(TYPE_SYSTEM_USER_PRESENCE_PROPERTIES_EXT),
+            next: ptr::null_mut(),
+            supports_user_presence: false.into(),
+        };
+        let mut properties = xr::sys::SystemProperties {
+            ty: xr::sys::SystemProperties::TYPE,
+            next: &mut user_presence as *mut _ as _,
+            ..mem::zeroed()
+        };
+        let result =
+            (instance.fp().get_system_properties)(instance.as_raw(), system, &mut properties);
+        if result.into_raw() < 0 {
+            return Err(result);
+        }
+        Ok(user_presence.supports_user_presence.into())
+    }
+}
+
+/// An event of the instance, including the ones openxr doesn't know about
+pub(crate) enum XrPolledEvent<'a> {
+    Event(xr::Event<'a>),
+    UserPresenceChanged(bool),
+}
+
+/// Like [`xr::Instance::poll_event`], which skips the events it doesn't know
+pub(crate) fn poll_event<'a>(
+    instance: &xr::Instance,
+    storage: &'a mut MaybeUninit<xr::sys::EventDataBuffer>,
+) -> xr::Result<Option<XrPolledEvent<'a>>> {
+    // returning the borrow from inside the loop needs a raw pointer, like in openxr
+    let storage: *mut MaybeUninit<xr::sys::EventDataBuffer> = storage;
+    unsafe {
+        loop {
+            ((*storage).as_mut_ptr() as *mut xr::sys::BaseInStructure).write(
+                xr::sys::BaseInStructure {
+                    ty: xr::sys::EventDataBuffer::TYPE,
+                    next: ptr::null(),
+                },
+            );
+            let status = (instance.fp().poll_event)(instance.as_raw(), (*storage).as_mut_ptr());
+            if status.into_raw() < 0 {
+                return Err(status);
+            }
+            if status == xr::sys::Result::EVENT_UNAVAILABLE {
+                return Ok(None);
+            }
+            let ty = (*((*storage).as_ptr() as *const xr::sys::BaseInStructure)).ty;
+            if ty.into_raw() == TYPE_EVENT_DATA_USER_PRESENCE_CHANGED_EXT {
+                let event = &*((*storage).as_ptr() as *const EventDataUserPresenceChangedEXT);
+                return Ok(Some(XrPolledEvent::UserPresenceChanged(
+                    event.is_user_present.into(),
+                )));
+            }
+            if let Some(event) = xr::Event::from_raw(&*storage) {
+                return Ok(Some(XrPolledEvent::Event(event)));
+            }
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use bevy::asset::load_internal_asset;
+use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
+use bevy::core_pipeline::core_3d::CORE_3D_DEPTH_FORMAT;
+use bevy::ecs::query::QueryItem;
+use bevy::prelude::*;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+};
+use bevy::render::render_resource::{
+    Buffer, BufferInitDescriptor, BufferUsages, CachedRenderPipelineId, CompareFunction,
+    DepthStencilState, IndexFormat, MultisampleState, PipelineCache, PrimitiveState,
+    RenderPassDescriptor, RenderPipelineDescriptor, SpecializedRenderPipeline,
+    SpecializedRenderPipelines, StoreOp, VertexBufferLayout, VertexFormat, VertexState,
+    VertexStepMode,
+};
+use bevy::render::renderer::{RenderContext, RenderDevice, RenderQueue};
+use bevy::render::view::ViewDepthTexture;
+use bevy::render::{Render, RenderApp, RenderSet};
+use openxr as xr;
+
+use crate::graphics::{XrSessionConfig, XrViewConfig};
+use crate::resources::{XrInstance, XrSession, XrViews};
+use crate::xr_init::{xr_only, XrCleanup, XrSetup, XrShouldRender};
+use crate::xr_input::xr_camera::XrCamera;
+
+const VISIBILITY_MASK_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x5c1b_3e8f_27d4_4a09_9b6e_f0a1_d2c3_8e47);
+
+/// The area of a view that can't be seen through the lenses, as a triangle mesh.
+/// The vertices are tangents of the view angles, so they lie on the z = -1 plane of the view.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XrVisibilityMask {
+    pub vertices: Vec<Vec2>,
+    pub indices: Vec<u32>,
+}
+
+impl XrVisibilityMask {
+    /// The vertices in normalized device coordinates of a view with `fov`
+    pub fn ndc_vertices(&self, fov: &xr::Fovf) -> Vec<Vec2> {
+        let min = Vec2::new(fov.angle_left.tan(), fov.angle_down.tan());
+        let max = Vec2::new(fov.angle_right.tan(), fov.angle_up.tan());
+        self.vertices
+            .iter()
+            .map(|&vertex| (vertex * 2.0 - (max + min)) / (max - min))
+            .collect()
+    }
+
+    /// How much of the image of a view with `fov` is hidden, from 0.0 to 1.0.
+    /// That's the share of fragments which aren't shaded anymore.
+    pub fn hidden_fraction(&self, fov: &xr::Fovf) -> f32 {
+        let vertices = self.ndc_vertices(fov);
+        let area: f32 = self
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize]);
+                (b - a).perp_dot(c - a).abs() / 2.0
+            })
+            .sum();
+        // normalized device coordinates span an area of 2 x 2
+        (area / 4.0).clamp(0.0, 1.0)
+    }
+}
+
+/// The visibility mask of every view, indexed by [`Eye`](crate::xr_input::xr_camera::Eye).
+/// Only exists while a session is running and `XR_KHR_visibility_mask` is enabled,
+/// see [`XrExtensions::enable_visibility_mask`](crate::graphics::extensions::XrExtensions::enable_visibility_mask).
+///
+/// The hidden area is written into the depth buffer of each xr camera before anything else is
+/// drawn, so the fragments behind it are skipped. Masks aren't used in mono mode.
+#[derive(Clone, Debug, Default, Resource, ExtractResource)]
+pub struct XrVisibilityMasks(pub Vec<XrVisibilityMask>);
+
+/// Sent when the runtime changed the visibility mask of a view, the masks are fetched again
+#[derive(Clone, Copy, Debug, Event)]
+pub struct XrVisibilityMaskChanged {
+    pub view_index: u32,
+}
+
+impl XrSession {
+    /// The hidden area mesh of a view of the primary stereo view configuration
+    pub fn visibility_mask(&self, view_index: u32) -> xr::Result<XrVisibilityMask> {
+        let mask = self.get_visibility_mask_khr(
+            crate::VIEW_TYPE,
+            view_index,
+            xr::VisibilityMaskTypeKHR::HIDDEN_TRIANGLE_MESH,
+        )?;
+        Ok(XrVisibilityMask {
+            vertices: mask
+                .vertices
+                .iter()
+                .map(|vertex| Vec2::new(vertex.x, vertex.y))
+                .collect(),
+            indices: mask.indices,
+        })
+    }
+}
+
+pub struct VisibilityMaskPlugin;
+
+impl Plugin for VisibilityMaskPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            VISIBILITY_MASK_SHADER_HANDLE,
+            "visibility_mask.wgsl",
+            Shader::from_wgsl
+        );
+        app.add_systems(XrSetup, fetch_visibility_masks);
+        app.add_systems(XrCleanup, cleanup_visibility_masks);
+        app.add_systems(
+            PreUpdate,
+            fetch_visibility_masks
+                .run_if(xr_only())
+                .run_if(on_event::<XrVisibilityMaskChanged>())
+                .after(crate::xr_poll_events),
+        );
+        app.add_plugins(ExtractResourcePlugin::<XrVisibilityMasks>::default());
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .init_resource::<XrVisibilityMaskPipeline>()
+            .init_resource::<SpecializedRenderPipelines<XrVisibilityMaskPipeline>>()
+            .init_resource::<GpuVisibilityMasks>()
+            .add_systems(
+                Render,
+                prepare_visibility_masks
+                    .run_if(resource_exists::<XrVisibilityMasks>)
+                    .in_set(RenderSet::Prepare),
+            )
+            .add_render_graph_node::<ViewNodeRunner<XrVisibilityMaskNode>>(
+                Core3d,
+                XrVisibilityMaskLabel,
+            )
+            // the prepass is the first pass that uses the depth buffer
+            .add_render_graph_edge(Core3d, XrVisibilityMaskLabel, Node3d::Prepass);
+    }
+}
+
+fn fetch_visibility_masks(
+    mut commands: Commands,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    session_config: Res<XrSessionConfig>,
+) {
+    if instance.exts().khr_visibility_mask.is_none()
+        || session_config.view_config != XrViewConfig::Stereo
+    {
+        return;
+    }
+    // one mask for each eye of the primary stereo view configuration
+    match (0..2)
+        .map(|view_index| session.visibility_mask(view_index))
+        .collect::<xr::Result<Vec<_>>>()
+    {
+        Ok(masks) => commands.insert_resource(XrVisibilityMasks(masks)),
+        Err(err) => {
+            warn!("Unable to get the visibility masks: {}", err);
+            commands.remove_resource::<XrVisibilityMasks>();
+        }
+    }
+}
+
+fn cleanup_visibility_masks(mut commands: Commands) {
+    commands.remove_resource::<XrVisibilityMasks>();
+}
+
+#[derive(Resource, Default)]
+struct XrVisibilityMaskPipeline;
+
+impl SpecializedRenderPipeline for XrVisibilityMaskPipeline {
+    /// The sample count of the depth texture
+    type Key = u32;
+
+    fn specialize(&self, samples: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("xr_visibility_mask_pipeline".into()),
+            layout: vec![],
+            push_constant_ranges: vec![],
+            vertex: VertexState {
+                shader: VISIBILITY_MASK_SHADER_HANDLE,
+                shader_defs: vec![],
+                entry_point: "vertex".into(),
+                buffers: vec![VertexBufferLayout::from_vertex_formats(
+                    VertexStepMode::Vertex,
+                    [VertexFormat::Float32x2],
+                )],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: Some(DepthStencilState {
+                format: CORE_3D_DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Always,
+                stencil: default(),
+                bias: default(),
+            }),
+            multisample: MultisampleState {
+                count: samples,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: None,
+        }
+    }
+}
+
+struct GpuVisibilityMask {
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_count: u32,
+}
+
+/// The masks uploaded to the gpu, the vertices are updated every frame as the fov can change
+#[derive(Resource, Default)]
+struct GpuVisibilityMasks(Vec<GpuVisibilityMask>);
+
+/// The pipeline and the mask a view renders with
+#[derive(Component)]
+struct XrVisibilityMaskView {
+    index: usize,
+    pipeline: CachedRenderPipelineId,
+}
+
+fn vertex_data(vertices: &[Vec2]) -> Vec<u8> {
+    vertices
+        .iter()
+        .flat_map(|vertex| vertex.to_array())
+        .flat_map(f32::to_le_bytes)
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prepare_visibility_masks(
+    mut commands: Commands,
+    masks: Res<XrVisibilityMasks>,
+    views: Res<XrViews>,
+    mut gpu_masks: ResMut<GpuVisibilityMasks>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<XrVisibilityMaskPipeline>>,
+    pipeline: Res<XrVisibilityMaskPipeline>,
+    cameras: Query<(Entity, &XrCamera, &ViewDepthTexture)>,
+) {
+    let ndc_vertices: Vec<_> = masks
+        .0
+        .iter()
+        .zip(views.iter())
+        .map(|(mask, view)| mask.ndc_vertices(&view.fov))
+        .collect();
+    if masks.is_changed() || gpu_masks.0.len() != ndc_vertices.len() {
+        gpu_masks.0 = masks
+            .0
+            .iter()
+            .zip(&ndc_vertices)
+            .map(|(mask, vertices)| GpuVisibilityMask {
+                vertex_buffer: render_device.create_buffer_with_data(&BufferInitDescriptor {
+                    label: Some("xr_visibility_mask_vertex_buffer"),
+                    contents: &vertex_data(vertices),
+                    usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                }),
+                index_buffer: render_device.create_buffer_with_data(&BufferInitDescriptor {
+                    label: Some("xr_visibility_mask_index_buffer"),
+                    contents: &mask
+                        .indices
+                        .iter()
+                        .flat_map(|index| index.to_le_bytes())
+                        .collect::<Vec<_>>(),
+                    usage: BufferUsages::INDEX,
+                }),
+                index_count: mask.indices.len() as u32,
+            })
+            .collect();
+    } else {
+        for (gpu_mask, vertices) in gpu_masks.0.iter().zip(&ndc_vertices) {
+            render_queue.write_buffer(&gpu_mask.vertex_buffer, 0, &vertex_data(vertices));
+        }
+    }
+    for (entity, camera, depth) in &cameras {
+        let index = camera.eye() as usize;
+        if index >= gpu_masks.0.len() {
+            continue;
+        }
+        let pipeline =
+            pipelines.specialize(&pipeline_cache, &pipeline, depth.texture.sample_count());
+        commands
+            .entity(entity)
+            .insert(XrVisibilityMaskView { index, pipeline });
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct XrVisibilityMaskLabel;
+
+/// Clears the depth buffer of each xr camera and writes the hidden area at the near plane,
+/// the passes after it load the depth buffer instead of clearing it
+#[derive(Default)]
+struct XrVisibilityMaskNode;
+
+impl ViewNode for XrVisibilityMaskNode {
+    type ViewQuery = (&'static ViewDepthTexture, &'static XrVisibilityMaskView);
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (depth, mask_view): QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        if !world
+            .get_resource::<XrShouldRender>()
+            .is_some_and(|should_render| **should_render)
+        {
+            return Ok(());
+        }
+        let Some(mask) = world
+            .resource::<GpuVisibilityMasks>()
+            .0
+            .get(mask_view.index)
+        else {
+            return Ok(());
+        };
+        if mask.index_count == 0 {
+            return Ok(());
+        }
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(mask_view.pipeline)
+        else {
+            return Ok(());
+        };
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("xr_visibility_mask_pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(depth.get_attachment(StoreOp::Store)),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_vertex_buffer(0, mask.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(mask.index_buffer.slice(..), 0, IndexFormat::Uint32);
+        render_pass.draw_indexed(0..mask.index_count, 0, 0..1);
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+// Writes the hidden area of a view at the near plane, so nothing behind it gets shaded.
+// The positions are already in normalized device coordinates.
+@vertex
+fn vertex(@location(0) position: vec2<f32>) -> @builtin(position) vec4<f32> {
+    // bevy uses reverse z, 1.0 is the near plane
+    return vec4<f32>(position, 1.0, 1.0);
+}
@@ -0,0 +1,439 @@
+//! bevy_ui shown on panels in the world and pointed at with the [`XrPointer`]s of the
+//! controllers, see the `world_ui` example.
+
+use bevy::ecs::component::Tick;
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::render_resource::{
+    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::transform::TransformSystem;
+use bevy::ui::{FocusPolicy, UiStack, UiSystem};
+use bevy::utils::HashSet;
+
+use crate::layers::XrCompositionLayer;
+use crate::xr_init::{xr_only, XrPostCleanup};
+use crate::xr_input::pointer::{XrPointer, XrPointerPlugin, XrPointerTarget, XrPointerUpdate};
+use crate::xr_input::trackers::OpenXRLeftEye;
+use crate::xr_input::xr_camera::RootTransform;
+use crate::xr_input::Hand;
+use crate::Backend;
+
+/// Renders and drives the [`WorldSpaceUi`] panels, each ui is rendered into a texture by its
+/// own camera
+pub struct WorldSpaceUiPlugin;
+
+impl Plugin for WorldSpaceUiPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<XrPointerPlugin>() {
+            app.add_plugins(XrPointerPlugin);
+        }
+        app.init_resource::<SavedInteractions>();
+        app.add_systems(
+            PreUpdate,
+            (
+                save_interactions.before(UiSystem::Focus),
+                update_pointers
+                    .after(UiSystem::Focus)
+                    .after(XrPointerUpdate),
+            ),
+        );
+        app.add_systems(
+            Update,
+            (
+                setup_panels,
+                update_panels,
+                use_composition_layers.run_if(xr_only()),
+            )
+                .chain(),
+        );
+        app.add_systems(
+            PostUpdate,
+            follow_head
+                .run_if(xr_only())
+                .before(TransformSystem::TransformPropagate),
+        );
+        app.add_systems(XrPostCleanup, restore_quads);
+    }
+}
+
+/// A panel showing the ui below `root`, facing its +Z axis.
+/// The ui is laid out in pixels of `resolution` and stretched over `size` meters.
+/// [`Interaction`] on its nodes is driven by the aim poses, pressing the trigger presses them.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct WorldSpaceUi {
+    /// The ui root node, it gets a [`TargetCamera`] rendering into the panel
+    pub root: Entity,
+    /// Size of the panel in meters
+    pub size: Vec2,
+    pub resolution: UVec2,
+    pub follow: WorldSpaceUiFollow,
+    /// Show the panel as a quad composition layer while a session is running, which keeps text
+    /// sharper. The panel is a textured quad without a session or on backends without layers.
+    pub composition_layer: bool,
+}
+
+impl WorldSpaceUi {
+    pub fn new(root: Entity, size: Vec2, resolution: UVec2) -> Self {
+        Self {
+            root,
+            size,
+            resolution,
+            follow: WorldSpaceUiFollow::Fixed,
+            composition_layer: false,
+        }
+    }
+
+    pub fn with_follow(mut self, follow: WorldSpaceUiFollow) -> Self {
+        self.follow = follow;
+        self
+    }
+
+    pub fn with_composition_layer(mut self) -> Self {
+        self.composition_layer = true;
+        self
+    }
+}
+
+/// How a [`WorldSpaceUi`] panel moves with the head. Panels that move shouldn't have a parent,
+/// their [`Transform`] is set in world space.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WorldSpaceUiFollow {
+    /// The panel stays where its [`Transform`] puts it
+    #[default]
+    Fixed,
+    /// The panel stays in place but turns around the Y axis to face the head
+    Billboard,
+    /// The panel floats `distance` meters in front of the head and faces it.
+    /// `speed` is how fast it catches up with the head, higher values follow more tightly.
+    Head { distance: f32, speed: f32 },
+}
+
+/// Added to [`WorldSpaceUi`] panels once their camera and quad were spawned
+#[derive(Component, Clone, Debug)]
+pub struct WorldSpaceUiPanel {
+    pub camera: Entity,
+    pub quad: Entity,
+    pub image: Handle<Image>,
+    resolution: UVec2,
+    size: Vec2,
+    layer: bool,
+}
+
+fn panel_image(resolution: UVec2) -> Image {
+    let size = Extent3d {
+        width: resolution.x,
+        height: resolution.y,
+        depth_or_array_layers: 1,
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("world_space_ui"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    image
+}
+
+fn setup_panels(
+    mut commands: Commands,
+    panels: Query<(Entity, &WorldSpaceUi), Without<WorldSpaceUiPanel>>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (entity, ui) in &panels {
+        let image = images.add(panel_image(ui.resolution));
+        let camera = commands
+            .spawn(Camera2dBundle {
+                camera: Camera {
+                    order: -1,
+                    target: RenderTarget::Image(image.clone()),
+                    clear_color: ClearColorConfig::Custom(Color::NONE),
+                    ..default()
+                },
+                ..default()
+            })
+            .id();
+        commands.entity(ui.root).insert(TargetCamera(camera));
+        let quad = commands
+            .spawn(PbrBundle {
+                mesh: meshes.add(Rectangle::from_size(ui.size)),
+                material: materials.add(StandardMaterial {
+                    base_color_texture: Some(image.clone()),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                }),
+                ..default()
+            })
+            .id();
+        commands
+            .entity(entity)
+            .add_child(quad)
+            .insert(XrPointerTarget::Rectangle(ui.size))
+            .insert(WorldSpaceUiPanel {
+                camera,
+                quad,
+                image,
+                resolution: ui.resolution,
+                size: ui.size,
+                layer: false,
+            });
+    }
+}
+
+/// Resizes the texture and quad of panels whose resolution or size changed
+fn update_panels(
+    mut commands: Commands,
+    mut panels: Query<(Entity, &WorldSpaceUi, &mut WorldSpaceUiPanel), Changed<WorldSpaceUi>>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (entity, ui, mut panel) in &mut panels {
+        if panel.resolution != ui.resolution {
+            if let Some(image) = images.get_mut(&panel.image) {
+                image.resize(Extent3d {
+                    width: ui.resolution.x,
+                    height: ui.resolution.y,
+                    depth_or_array_layers: 1,
+                });
+            }
+            panel.resolution = ui.resolution;
+        }
+        if panel.size != ui.size {
+            commands
+                .entity(panel.quad)
+                .insert(meshes.add(Rectangle::from_size(ui.size)));
+            commands
+                .entity(entity)
+                .insert(XrPointerTarget::Rectangle(ui.size));
+            panel.size = ui.size;
+        }
+    }
+}
+
+fn layers_supported(backend: &Backend) -> bool {
+    match backend {
+        #[cfg(all(feature = "gles", target_os = "android"))]
+        Backend::Gles => false,
+        #[allow(unreachable_patterns)]
+        _ => true,
+    }
+}
+
+fn use_composition_layers(
+    mut commands: Commands,
+    backend: Res<Backend>,
+    mut panels: Query<(
+        Entity,
+        &WorldSpaceUi,
+        &mut WorldSpaceUiPanel,
+        Option<&mut XrCompositionLayer>,
+    )>,
+) {
+    if !layers_supported(&backend) {
+        return;
+    }
+    for (entity, ui, mut panel, layer) in &mut panels {
+        match (ui.composition_layer, layer) {
+            (true, Some(mut layer)) => {
+                let resolution = ui.resolution;
+                let shape = XrCompositionLayer::quad(ui.size, resolution).shape;
+                if layer.resolution != resolution || layer.shape != shape {
+                    layer.resolution = resolution;
+                    layer.shape = shape;
+                }
+            }
+            (true, None) => {
+                commands.entity(entity).insert((
+                    XrCompositionLayer::quad(ui.size, ui.resolution).with_camera(panel.camera),
+                    RootTransform::default(),
+                ));
+                commands.entity(panel.quad).insert(Visibility::Hidden);
+                panel.layer = true;
+            }
+            (false, _) if panel.layer => restore_quad(&mut commands, entity, &mut panel),
+            (false, _) => {}
+        }
+    }
+}
+
+fn restore_quad(commands: &mut Commands, entity: Entity, panel: &mut WorldSpaceUiPanel) {
+    commands
+        .entity(entity)
+        .remove::<(XrCompositionLayer, RootTransform)>();
+    commands.entity(panel.quad).insert(Visibility::Inherited);
+    let (camera, image) = (panel.camera, panel.image.clone());
+    commands.add(move |world: &mut World| {
+        if let Some(mut camera) = world.get_mut::<Camera>(camera) {
+            camera.target = RenderTarget::Image(image);
+            camera.is_active = true;
+        }
+    });
+    panel.layer = false;
+}
+
+/// The layer swapchains are gone with the session, the panels go back to their quads
+fn restore_quads(mut commands: Commands, mut panels: Query<(Entity, &mut WorldSpaceUiPanel)>) {
+    for (entity, mut panel) in &mut panels {
+        if panel.layer {
+            restore_quad(&mut commands, entity, &mut panel);
+        }
+    }
+}
+
+fn follow_head(
+    time: Res<Time>,
+    head: Query<&GlobalTransform, With<OpenXRLeftEye>>,
+    mut panels: Query<(&WorldSpaceUi, &mut Transform)>,
+) {
+    let Ok(head) = head.get_single() else {
+        return;
+    };
+    let head_position = head.translation();
+    for (ui, mut transform) in &mut panels {
+        let position = match ui.follow {
+            WorldSpaceUiFollow::Fixed => continue,
+            WorldSpaceUiFollow::Billboard => transform.translation,
+            WorldSpaceUiFollow::Head { distance, speed } => {
+                let forward = (head.forward() * Vec3::new(1.0, 0.0, 1.0)).normalize_or_zero();
+                if forward == Vec3::ZERO {
+                    continue;
+                }
+                let target = head_position + forward * distance;
+                let t = (speed * time.delta_seconds()).min(1.0);
+                transform.translation.lerp(target, t)
+            }
+        };
+        // the panel faces +Z, so it looks away from the head
+        let away = Vec3::new(
+            position.x - head_position.x,
+            0.0,
+            position.z - head_position.z,
+        );
+        if away.length_squared() < f32::EPSILON {
+            continue;
+        }
+        *transform = transform
+            .with_translation(position)
+            .looking_to(away, Vec3::Y);
+    }
+}
+
+/// Interactions of the nodes on panels, from before bevy's focus system reset them because the
+/// panel cameras don't render to a window
+#[derive(Resource, Default)]
+struct SavedInteractions(EntityHashMap<(Interaction, Tick)>);
+
+fn save_interactions(
+    mut saved: ResMut<SavedInteractions>,
+    panels: Query<&WorldSpaceUiPanel>,
+    nodes: Query<(Entity, Ref<Interaction>, &TargetCamera)>,
+) {
+    saved.0.clear();
+    let cameras: Vec<_> = panels.iter().map(|panel| panel.camera).collect();
+    for (entity, interaction, target) in &nodes {
+        if cameras.contains(&target.entity()) {
+            saved
+                .0
+                .insert(entity, (*interaction, interaction.last_changed()));
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_pointers(
+    saved: Res<SavedInteractions>,
+    panels: Query<(&WorldSpaceUi, &WorldSpaceUiPanel)>,
+    pointers: Query<&XrPointer>,
+    ui_stack: Res<UiStack>,
+    ui_scale: Res<UiScale>,
+    mut nodes: Query<(
+        &Node,
+        &GlobalTransform,
+        &TargetCamera,
+        Option<&mut Interaction>,
+        Option<&FocusPolicy>,
+        Option<&ViewVisibility>,
+        Option<&CalculatedClip>,
+    )>,
+    mut pressed: Local<HashSet<(Entity, Hand)>>,
+) {
+    // undo bevy's focus system, including the change ticks
+    for (&entity, &(interaction, tick)) in &saved.0 {
+        if let Ok((.., Some(mut current), _, _, _)) = nodes.get_mut(entity) {
+            *current.bypass_change_detection() = interaction;
+            current.set_last_changed(tick);
+        }
+    }
+
+    let mut interactions = EntityHashMap::default();
+    for (&entity, _) in &saved.0 {
+        interactions.insert(entity, Interaction::None);
+    }
+    for pointer in &pointers {
+        let hand = pointer.hand;
+        if !pointer.pressed() {
+            pressed.retain(|(_, pressed_hand)| *pressed_hand != hand);
+        }
+
+        // where the pointer hits a panel, in logical pixels of its ui
+        let Some((ui, panel, point)) = pointer.hit().and_then(|hit| {
+            let (ui, panel) = panels.get(hit.target).ok()?;
+            Some((ui, panel, hit.local_position.truncate()))
+        }) else {
+            continue;
+        };
+        let uv = Vec2::new(point.x / ui.size.x + 0.5, 0.5 - point.y / ui.size.y);
+        let pixel = uv * ui.resolution.as_vec2() / ui_scale.0;
+        let camera = panel.camera;
+
+        // top most nodes first, like bevy's focus system
+        for &entity in ui_stack.uinodes.iter().rev() {
+            let Ok((node, transform, target, interaction, focus_policy, visibility, clip)) =
+                nodes.get(entity)
+            else {
+                continue;
+            };
+            if target.entity() != camera || !visibility.is_some_and(|v| v.get()) {
+                continue;
+            }
+            let rect = node.logical_rect(transform);
+            let visible_rect = clip.map_or(rect, |clip| rect.intersect(clip.clip));
+            if !visible_rect.contains(pixel) {
+                continue;
+            }
+            if interaction.is_some() {
+                if pointer.just_pressed() {
+                    pressed.insert((entity, hand));
+                }
+                interactions
+                    .entry(entity)
+                    .and_modify(|state| *state = Interaction::Hovered);
+            }
+            if *focus_policy.unwrap_or(&FocusPolicy::Block) == FocusPolicy::Block {
+                break;
+            }
+        }
+    }
+    for &(entity, _) in pressed.iter() {
+        interactions.insert(entity, Interaction::Pressed);
+    }
+    for (entity, state) in interactions {
+        if let Ok((.., Some(mut interaction), _, _, _)) = nodes.get_mut(entity) {
+            interaction.set_if_neq(state);
+        }
+    }
+}
@@ -2,6 +2,7 @@ pub mod schedules;
 pub use schedules::*;
 
 use bevy::{
+    ecs::system::SystemParam,
     prelude::*,
     render::{
         camera::{ManualTextureView, ManualTextureViews},
@@ -11,22 +12,67 @@ use bevy::{
     },
     window::{PrimaryWindow, RawHandleWrapper},
 };
+use openxr as xr;
 
 use crate::{
-    clean_resources, graphics,
-    resources::{OXrSessionSetupInfo, XrFormat, XrInstance, XrResolution, XrSession, XrSwapchain},
-    LEFT_XR_TEXTURE_HANDLE, RIGHT_XR_TEXTURE_HANDLE,
+    clean_resources,
+    error::XrError,
+    graphics::{self, XrSessionConfig},
+    resources::{
+        OXrSessionSetupInfo, Swapchain, XrColorSpace, XrInstance, XrResolution, XrSession,
+        XrSupportedMsaaSamples, XrSwapchain, XrTime,
+    },
+    startup::SimulatorSelected,
+    user_presence::XrUserPresenceChanged,
+    XR_TEXTURE_HANDLES,
 };
 
 #[derive(Resource, Event, Clone, Copy, PartialEq, Eq, Reflect, Debug, ExtractResource)]
 pub enum XrStatus {
-    NoInstance,
+    /// No OpenXR instance could be created, the app keeps running as a regular flatscreen app
+    Unavailable(XrUnavailableReason),
     Enabled,
     Enabling,
     Disabled,
     Disabling,
 }
 
+/// Why [`XrStatus::Unavailable`], the full error is logged at startup
+#[derive(Clone, Copy, PartialEq, Eq, Reflect, Debug)]
+pub enum XrUnavailableReason {
+    /// The OpenXR loader library wasn't found
+    LoaderMissing,
+    /// No OpenXR runtime is installed or it isn't running, like SteamVR not being started
+    RuntimeUnavailable,
+    /// The runtime has no headset available, it might not be connected
+    FormFactorUnavailable,
+    /// Anything else, like none of the backends being supported by the runtime
+    Other,
+    /// [`XrStartupPolicy::Simulate`](crate::startup::XrStartupPolicy::Simulate) skipped OpenXR
+    Simulated,
+}
+
+impl XrUnavailableReason {
+    pub(crate) fn from_error(err: &eyre::Report) -> Self {
+        if err.downcast_ref::<SimulatorSelected>().is_some() {
+            return Self::Simulated;
+        }
+        #[cfg(not(windows))]
+        if err.downcast_ref::<xr::LoadError>().is_some() {
+            return Self::LoaderMissing;
+        }
+        let result = err
+            .downcast_ref::<XrError>()
+            .and_then(XrError::result)
+            .or_else(|| err.downcast_ref::<xr::sys::Result>().copied());
+        match result {
+            Some(xr::sys::Result::ERROR_RUNTIME_UNAVAILABLE) => Self::RuntimeUnavailable,
+            Some(xr::sys::Result::ERROR_FORM_FACTOR_UNAVAILABLE) => Self::FormFactorUnavailable,
+            _ => Self::Other,
+        }
+    }
+}
+
 #[derive(
     Resource, Clone, Copy, PartialEq, Eq, Reflect, Debug, ExtractResource, Default, Deref, DerefMut,
 )]
@@ -36,6 +82,133 @@ pub struct XrShouldRender(bool);
 )]
 pub struct XrHasWaited(bool);
 
+/// The last session state reported by the runtime
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, ExtractResource, Deref)]
+pub struct XrSessionState(pub xr::SessionState);
+impl Default for XrSessionState {
+    fn default() -> Self {
+        Self(xr::SessionState::IDLE)
+    }
+}
+
+/// Sent every time the runtime changes the state of the session
+#[derive(Event, Clone, Copy, Debug)]
+pub struct XrSessionStateChanged {
+    pub state: xr::SessionState,
+    pub time: XrTime,
+}
+
+/// The session was created, sent with the first [`xr::SessionState::IDLE`]
+#[derive(Event, Clone, Copy, Debug)]
+pub struct XrSessionCreated {
+    pub state: xr::SessionState,
+    pub time: XrTime,
+}
+
+/// The session began once the runtime was ready, frames are submitted from now on
+#[derive(Event, Clone, Copy, Debug)]
+pub struct XrSessionBegan {
+    pub state: xr::SessionState,
+    pub time: XrTime,
+}
+
+/// The session became [`xr::SessionState::FOCUSED`], the app receives input now
+#[derive(Event, Clone, Copy, Debug)]
+pub struct XrFocusGained {
+    pub state: xr::SessionState,
+    pub time: XrTime,
+}
+
+/// The session isn't focused anymore, like when a system menu opened or the headset was
+/// taken off, the app doesn't receive input until it regains focus
+#[derive(Event, Clone, Copy, Debug)]
+pub struct XrFocusLost {
+    pub state: xr::SessionState,
+    pub time: XrTime,
+}
+
+/// The frames of the app started or stopped being shown to the user
+#[derive(Event, Clone, Copy, Debug)]
+pub struct XrVisibilityChanged {
+    pub visible: bool,
+    pub state: xr::SessionState,
+    pub time: XrTime,
+}
+
+/// The session is stopping or was lost, save state here, it might be followed by the app exiting
+#[derive(Event, Clone, Copy, Debug)]
+pub struct XrSessionEnding {
+    pub state: xr::SessionState,
+    pub time: XrTime,
+}
+
+/// The runtime is going to destroy the instance, the session ends with it
+#[derive(Event, Clone, Copy, Debug)]
+pub struct XrInstanceLost {
+    pub loss_time: XrTime,
+}
+
+/// Sends the lifecycle events for a session state change, in [`PreUpdate`] right after
+/// [`XrSessionStateChanged`]. Each event type keeps the order the runtime reported the
+/// changes in, use [`XrSessionStateChanged`] to see the order across all of them.
+#[derive(SystemParam)]
+pub(crate) struct XrLifecycleEvents<'w> {
+    created: EventWriter<'w, XrSessionCreated>,
+    began: EventWriter<'w, XrSessionBegan>,
+    focus_gained: EventWriter<'w, XrFocusGained>,
+    focus_lost: EventWriter<'w, XrFocusLost>,
+    visibility_changed: EventWriter<'w, XrVisibilityChanged>,
+    ending: EventWriter<'w, XrSessionEnding>,
+    instance_lost: EventWriter<'w, XrInstanceLost>,
+    user_presence: EventWriter<'w, XrUserPresenceChanged>,
+}
+
+impl XrLifecycleEvents<'_> {
+    pub(crate) fn send_state_change(
+        &mut self,
+        previous: xr::SessionState,
+        state: xr::SessionState,
+        time: XrTime,
+    ) {
+        let visible =
+            |state| matches!(state, xr::SessionState::VISIBLE | xr::SessionState::FOCUSED);
+        if previous == xr::SessionState::FOCUSED && state != xr::SessionState::FOCUSED {
+            self.focus_lost.send(XrFocusLost { state, time });
+        }
+        if visible(previous) != visible(state) {
+            self.visibility_changed.send(XrVisibilityChanged {
+                visible: visible(state),
+                state,
+                time,
+            });
+        }
+        match state {
+            // the session also goes back to idle after it stopped
+            xr::SessionState::IDLE if previous != xr::SessionState::STOPPING => {
+                self.created.send(XrSessionCreated { state, time });
+            }
+            xr::SessionState::READY => {
+                self.began.send(XrSessionBegan { state, time });
+            }
+            xr::SessionState::FOCUSED if previous != xr::SessionState::FOCUSED => {
+                self.focus_gained.send(XrFocusGained { state, time });
+            }
+            xr::SessionState::STOPPING | xr::SessionState::LOSS_PENDING => {
+                self.ending.send(XrSessionEnding { state, time });
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn send_instance_lost(&mut self, loss_time: XrTime) {
+        self.instance_lost.send(XrInstanceLost { loss_time });
+    }
+
+    pub(crate) fn send_user_presence(&mut self, present: bool) {
+        self.user_presence.send(XrUserPresenceChanged { present });
+    }
+}
+
 pub struct XrEarlyInitPlugin;
 
 pub struct XrInitPlugin;
@@ -43,9 +216,17 @@ pub struct XrInitPlugin;
 pub fn xr_only() -> impl FnMut(Res<XrStatus>) -> bool {
     resource_equals(XrStatus::Enabled)
 }
+/// Only true when there is no OpenXR runtime, for setting up the flatscreen version of the app
+pub fn xr_unavailable_only() -> impl FnMut(Res<XrStatus>) -> bool {
+    |status: Res<XrStatus>| matches!(*status, XrStatus::Unavailable(_))
+}
 pub fn xr_render_only() -> impl FnMut(Res<XrShouldRender>) -> bool {
     resource_equals(XrShouldRender(true))
 }
+/// Only true while the session is focused, which is the only state the app receives input in
+pub fn xr_focused_only() -> impl FnMut(Res<XrSessionState>) -> bool {
+    resource_equals(XrSessionState(xr::SessionState::FOCUSED))
+}
 pub fn xr_after_wait_only() -> impl FnMut(Res<XrHasWaited>) -> bool {
     resource_equals(XrHasWaited(true))
 }
@@ -59,15 +240,27 @@ impl Plugin for XrEarlyInitPlugin {
         app.add_event::<SetupXrData>()
             .add_event::<CleanupXrData>()
             .add_event::<StartXrSession>()
-            .add_event::<EndXrSession>();
+            .add_event::<EndXrSession>()
+            .add_event::<XrSessionStateChanged>()
+            .add_event::<XrSessionCreated>()
+            .add_event::<XrSessionBegan>()
+            .add_event::<XrFocusGained>()
+            .add_event::<XrFocusLost>()
+            .add_event::<XrVisibilityChanged>()
+            .add_event::<XrSessionEnding>()
+            .add_event::<XrInstanceLost>()
+            .init_resource::<XrSessionState>();
     }
 }
 
 impl Plugin for XrInitPlugin {
     fn build(&self, app: &mut App) {
+        app.register_type::<XrStatus>()
+            .register_type::<XrUnavailableReason>();
         app.add_plugins(ExtractResourcePlugin::<XrStatus>::default());
         app.add_plugins(ExtractResourcePlugin::<XrShouldRender>::default());
         app.add_plugins(ExtractResourcePlugin::<XrHasWaited>::default());
+        app.add_plugins(ExtractResourcePlugin::<XrSessionState>::default());
         app.add_plugins(ExtractResourcePlugin::<CleanupRenderWorld>::default());
         app.init_resource::<XrShouldRender>();
         app.init_resource::<XrHasWaited>();
@@ -82,7 +275,7 @@ impl Plugin for XrInitPlugin {
             stop_xr_session.run_if(on_event::<EndXrSession>()),
         );
         app.add_systems(XrSetup, setup_manual_texture_views);
-        app.add_systems(XrCleanup, set_cleanup_res);
+        app.add_systems(XrCleanup, (set_cleanup_res, cleanup_manual_texture_views));
         app.add_systems(PreUpdate, remove_cleanup_res.before(cleanup_xr));
         let render_app = app.sub_app_mut(RenderApp);
         render_app.add_systems(
@@ -105,6 +298,8 @@ pub enum ExitAppOnSessionExit {
     Never,
 }
 
+/// Sends [`StartXrSession`] on startup, disable it in [`DefaultXrPlugins`](crate::DefaultXrPlugins)
+/// to start in the window and only enter VR once [`StartXrSession`] is sent
 pub struct StartSessionOnStartup;
 
 impl Plugin for StartSessionOnStartup {
@@ -127,22 +322,32 @@ fn setup_manual_texture_views(
     mut manual_texture_views: ResMut<ManualTextureViews>,
     swapchain: Res<XrSwapchain>,
     xr_resolution: Res<XrResolution>,
-    xr_format: Res<XrFormat>,
 ) {
     info!("Creating Texture views");
-    let (left, right) = swapchain.get_render_views();
-    let left = ManualTextureView {
-        texture_view: left.into(),
-        size: **xr_resolution,
-        format: **xr_format,
-    };
-    let right = ManualTextureView {
-        texture_view: right.into(),
-        size: **xr_resolution,
-        format: **xr_format,
-    };
-    manual_texture_views.insert(LEFT_XR_TEXTURE_HANDLE, left);
-    manual_texture_views.insert(RIGHT_XR_TEXTURE_HANDLE, right);
+    insert_manual_texture_views(&mut manual_texture_views, &swapchain, **xr_resolution);
+}
+
+/// The main world only needs the views for the size and format of the eye cameras' targets
+pub(crate) fn insert_manual_texture_views(
+    manual_texture_views: &mut ManualTextureViews,
+    swapchain: &Swapchain,
+    resolution: UVec2,
+) {
+    for (handle, view) in XR_TEXTURE_HANDLES.into_iter().zip(swapchain.render_views()) {
+        let view = ManualTextureView {
+            texture_view: view.clone(),
+            size: resolution,
+            format: swapchain.view_format(),
+        };
+        manual_texture_views.insert(handle, view);
+    }
+}
+
+/// The views keep the swapchain images alive, so they're removed with the session
+fn cleanup_manual_texture_views(mut manual_texture_views: ResMut<ManualTextureViews>) {
+    for handle in XR_TEXTURE_HANDLES {
+        manual_texture_views.remove(&handle);
+    }
 }
 
 pub fn setup_xr(world: &mut World) {
@@ -161,9 +366,13 @@ fn cleanup_xr(world: &mut World) {
     *world.resource_mut::<XrStatus>() = XrStatus::Disabled;
 }
 
+/// Creates the session, its swapchain, cameras and input, the render device created with the
+/// instance at startup is reused. Only works while [`XrStatus::Disabled`].
 #[derive(Event, Clone, Copy, Default)]
 pub struct StartXrSession;
 
+/// Asks the runtime to end the session, everything created for it is cleaned up once it stopped.
+/// Insert [`ExitAppOnSessionExit::Never`] to return to the window instead of exiting the app.
 #[derive(Event, Clone, Copy, Default)]
 pub struct EndXrSession;
 
@@ -177,17 +386,24 @@ fn start_xr_session(
     mut commands: Commands,
     mut status: ResMut<XrStatus>,
     instance: Option<Res<XrInstance>>,
+    mut session_config: ResMut<XrSessionConfig>,
     primary_window: Query<&RawHandleWrapper, With<PrimaryWindow>>,
     setup_info: Option<NonSend<OXrSessionSetupInfo>>,
     render_device: Option<Res<RenderDevice>>,
     render_adapter: Option<Res<RenderAdapter>>,
     render_instance: Option<Res<RenderInstance>>,
+    msaa: Option<Res<Msaa>>,
 ) {
     info!("start Session");
     match *status {
         XrStatus::Disabled => {}
-        XrStatus::NoInstance => {
-            warn!("Trying to start OpenXR Session without instance, ignoring");
+        XrStatus::Unavailable(reason) => {
+            error!(
+                "Unable to start OpenXR Session: no OpenXR instance was created at startup ({:?}), \
+                so the render device isn't compatible with OpenXR. \
+                The app has to be restarted with a runtime available to enter VR",
+                reason
+            );
             return;
         }
         XrStatus::Enabled | XrStatus::Enabling => {
@@ -216,6 +432,10 @@ fn start_xr_session(
         error!("Missing resources after passing status check");
         return;
     };
+    let view_config = session_config.view_config.resolve(&instance);
+    if view_config != session_config.view_config {
+        session_config.view_config = view_config;
+    }
     let (
         xr_session,
         xr_resolution,
@@ -230,6 +450,7 @@ fn start_xr_session(
         primary_window.get_single().cloned().ok(),
         &setup_info,
         &instance,
+        &session_config,
         &render_device,
         &render_adapter,
         &render_instance,
@@ -240,11 +461,44 @@ fn start_xr_session(
             return;
         }
     };
+    match graphics::supported_msaa_samples(
+        &instance,
+        &render_adapter,
+        *xr_format,
+        session_config.view_config,
+    ) {
+        Ok(supported) => {
+            let requested = session_config
+                .samples
+                .unwrap_or_else(|| msaa.map_or(Msaa::default(), |msaa| *msaa).samples());
+            let samples = supported
+                .iter()
+                .copied()
+                .filter(|&samples| samples <= requested)
+                .max()
+                .unwrap_or(1);
+            if samples != requested {
+                warn!(
+                    "MSAA with {} samples is not supported, using {} samples",
+                    requested, samples
+                );
+            }
+            commands.insert_resource(msaa_from_samples(samples));
+            commands.insert_resource(XrSupportedMsaaSamples::new(supported));
+        }
+        Err(err) => warn!("Unable to query supported MSAA sample counts: {}", err),
+    }
     commands.insert_resource(xr_session);
     commands.insert_resource(xr_resolution);
     commands.insert_resource(xr_format);
     commands.insert_resource(xr_session_running);
     commands.insert_resource(xr_frame_waiter);
+    info!(
+        "Rendering with swapchain view format {:?}, color space {:?}",
+        xr_swapchain.view_format(),
+        XrColorSpace::from_format(xr_swapchain.view_format())
+    );
+    commands.insert_resource(XrColorSpace::from_format(xr_swapchain.view_format()));
     commands.insert_resource(xr_swapchain);
     commands.insert_resource(xr_input);
     commands.insert_resource(xr_views);
@@ -252,7 +506,20 @@ fn start_xr_session(
     *status = XrStatus::Enabling;
 }
 
-fn stop_xr_session(session: ResMut<XrSession>, mut status: ResMut<XrStatus>) {
+pub(crate) fn msaa_from_samples(samples: u32) -> Msaa {
+    match samples {
+        8.. => Msaa::Sample8,
+        4..=7 => Msaa::Sample4,
+        2..=3 => Msaa::Sample2,
+        _ => Msaa::Off,
+    }
+}
+
+fn stop_xr_session(session: Option<Res<XrSession>>, mut status: ResMut<XrStatus>) {
+    let Some(session) = session.filter(|_| *status == XrStatus::Enabled) else {
+        warn!("Trying to end OpenXR Session while none is running, ignoring");
+        return;
+    };
     match session.request_exit() {
         Ok(_) => {}
         Err(err) => {
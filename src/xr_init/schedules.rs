@@ -1,8 +1,42 @@
+//! The frame loop runs in these sets, in this order:
+//!
+//! - [`XrPollEvents`] in [`PreUpdate`](bevy::app::PreUpdate): session events are handled and
+//!   turned into bevy events
+//! - [`XrWaitFrame`] in `PreUpdate`: the frame is waited on and the views are located
+//! - [`XrActionSync`](crate::xr_input::actions::XrActionSync) in `PreUpdate`: the actions are synced
+//! - [`XrTrackingUpdate`](crate::xr_input::XrTrackingUpdate) in `PreUpdate`: controllers, hands
+//!   and other tracked entities get their poses
+//! - app logic in [`Update`](bevy::app::Update) and `PostUpdate` sees the poses of this frame,
+//!   systems like IK can run there before the transforms are propagated and extracted
+//! - [`XrBeginFrame`] in the render world, after the extraction: the frame is begun and the
+//!   swapchain images acquired
+//! - [`XrEndFrame`] in the render world's cleanup: the frame is submitted, or skipped while the
+//!   runtime doesn't show it
+//!
+//! The render world sets only run after the main world waited on a frame,
+//! so a frame can't be ended before it began, also not after the session restarted.
+
 use bevy::{
     app::App,
-    ecs::schedule::{ExecutorKind, Schedule, ScheduleLabel},
+    ecs::schedule::{ExecutorKind, Schedule, ScheduleLabel, SystemSet},
 };
 
+/// See the [module docs](self) for the frame loop order
+#[derive(SystemSet, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct XrPollEvents;
+
+/// See the [module docs](self) for the frame loop order
+#[derive(SystemSet, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct XrWaitFrame;
+
+/// See the [module docs](self) for the frame loop order
+#[derive(SystemSet, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct XrBeginFrame;
+
+/// See the [module docs](self) for the frame loop order
+#[derive(SystemSet, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct XrEndFrame;
+
 #[derive(Debug, ScheduleLabel, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct XrPreSetup;
 
@@ -0,0 +1,132 @@
+//! Actions named by a type implementing [`XrAction`], so their values can be read as
+//! [`XrActionValue`] resources once an [`XrActionValuePlugin`] was added for them.
+
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::{
+    graphics::XrSessionConfig,
+    resources::XrSession,
+    xr_init::{XrSessionState, XrStatus},
+};
+
+use super::actions::{sync_actions, synced_this_frame, ActionValue, XrActionSets, XrActionSync};
+
+/// An action created in [`SetupActionSets`](super::actions::SetupActionSets) or loaded from the
+/// bindings, identified by its action set and name
+pub trait XrAction: Send + Sync + 'static {
+    type Value: XrActionValueType;
+    const ACTION_SET: &'static str;
+    const ACTION: &'static str;
+}
+
+/// Values [`XrActionValue`] can hold, `bool`, `f32` and [`Vec2`]
+pub trait XrActionValueType: Copy + Default + PartialEq + Send + Sync + 'static {
+    type Raw: ActionValue;
+    fn from_raw(raw: Self::Raw) -> Self;
+}
+
+impl XrActionValueType for bool {
+    type Raw = bool;
+    fn from_raw(raw: bool) -> Self {
+        raw
+    }
+}
+
+impl XrActionValueType for f32 {
+    type Raw = f32;
+    fn from_raw(raw: f32) -> Self {
+        raw
+    }
+}
+
+impl XrActionValueType for Vec2 {
+    type Raw = xr::Vector2f;
+    fn from_raw(raw: xr::Vector2f) -> Self {
+        Vec2::new(raw.x, raw.y)
+    }
+}
+
+/// Inserts [`XrActionValue<A>`] and updates it in [`XrActionSync`] every frame
+pub struct XrActionValuePlugin<A: XrAction>(PhantomData<A>);
+
+impl<A: XrAction> Default for XrActionValuePlugin<A> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<A: XrAction> Plugin for XrActionValuePlugin<A> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrActionValue<A>>();
+        app.add_systems(
+            PreUpdate,
+            update_action_value::<A>
+                .in_set(XrActionSync)
+                .after(sync_actions),
+        );
+    }
+}
+
+/// The value of an action as of the last sync, combined over all subaction paths.
+/// It's only marked as changed when the value or [`XrActionValue::is_active`] changed.
+#[derive(Resource)]
+pub struct XrActionValue<A: XrAction> {
+    pub value: A::Value,
+    /// `false` if nothing is bound to the action or the session doesn't get input,
+    /// the value is the default value then
+    pub is_active: bool,
+    marker: PhantomData<A>,
+}
+
+impl<A: XrAction> Default for XrActionValue<A> {
+    fn default() -> Self {
+        Self {
+            value: default(),
+            is_active: false,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<A: XrAction> PartialEq for XrActionValue<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.is_active == other.is_active
+    }
+}
+
+fn update_action_value<A: XrAction>(
+    mut value: ResMut<XrActionValue<A>>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Option<Res<XrSession>>,
+    status: Res<XrStatus>,
+    session_state: Res<XrSessionState>,
+    config: Res<XrSessionConfig>,
+) {
+    let state = match (action_sets, session) {
+        (Some(action_sets), Some(session))
+            if synced_this_frame(&status, &session_state, &config) =>
+        {
+            action_sets
+                .get_state::<<A::Value as XrActionValueType>::Raw>(
+                    &session,
+                    A::ACTION_SET,
+                    A::ACTION,
+                    xr::Path::NULL,
+                )
+                .ok()
+        }
+        _ => None,
+    };
+    let new = match state {
+        Some(state) if state.is_active => XrActionValue {
+            value: A::Value::from_raw(state.value),
+            is_active: true,
+            marker: PhantomData,
+        },
+        _ => XrActionValue::default(),
+    };
+    value.set_if_neq(new);
+}
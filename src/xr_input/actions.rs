@@ -1,22 +1,92 @@
+//! Actions are synced once per frame in [`PreUpdate`], after the frame was waited on,
+//! in the [`XrActionSync`] set. Their values stay the same until the next sync,
+//! so systems in [`Update`] all see the input of the current frame.
+//! Systems that read actions in [`PreUpdate`] have to run after [`XrActionSync`],
+//! before the first sync reading a value fails with [`ActionError::NotSynced`].
+//! The bool actions are also available as `Res<ButtonInput<XrButton>>`, updated in the same set,
+//! and single actions as resources with [`XrActionValuePlugin`](super::action_values::XrActionValuePlugin).
+
 use std::error::Error;
+use std::time::Duration;
 
-use bevy::{prelude::*, utils::HashMap};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 use openxr as xr;
 use xr::{Action, Binding, Haptic, Posef, Vector2f};
 
 use crate::{
-    resources::{XrInstance, XrSession},
-    xr_init::{xr_only, XrCleanup, XrPrePostSetup, XrPreSetup},
+    error::{XrError, XrErrors, XrResultExt},
+    graphics::XrSessionConfig,
+    overlay::xr_overlay_only,
+    resources::{XrInstance, XrSession, XrTime},
+    simulator::XrSimulator,
+    xr_init::{
+        xr_focused_only, xr_only, XrCleanup, XrFocusGained, XrFocusLost, XrPollEvents,
+        XrPrePostSetup, XrPreSetup, XrSessionEnding, XrSessionState, XrStatus,
+    },
 };
 
-use super::oculus_touch::ActionSets;
+use super::dpad::{dpad_binding_supported, suggest_bindings_with_dpads, XrDpadBinding};
+use super::input_contexts::{update_input_contexts, XrInputContext, XrInputContexts};
+use super::oculus_touch::{subaction_path, ActionSets};
+use super::palm_pose::{palm_pose_fallback_path, palm_pose_supported, XrPalmPoseFallback};
+use super::processing::XrActionProcessing;
+use super::Hand;
 
 pub use xr::sys::NULL_PATH;
 
+/// The set [`sync_actions`] runs in, see the module docs for the frame ordering
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XrActionSync;
+
 pub struct XrActionsPlugin;
 impl Plugin for XrActionsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PreUpdate, sync_actions.run_if(xr_only()));
+        app.register_type::<XrButton>().register_type::<XrBinding>();
+        app.add_systems(
+            PreUpdate,
+            sync_actions
+                .in_set(XrActionSync)
+                .run_if(xr_only())
+                // overlays usually never get focus, their actions simply stay inactive
+                .run_if(xr_focused_only().or_else(xr_overlay_only())),
+        );
+        app.add_systems(
+            PreUpdate,
+            release_actions
+                .in_set(XrActionSync)
+                .run_if(xr_only())
+                .run_if(not(xr_focused_only().or_else(xr_overlay_only())))
+                .run_if(resource_exists::<XrActionSets>),
+        );
+        app.init_resource::<ButtonInput<XrButton>>();
+        app.init_resource::<XrFocusLossSettings>();
+        app.register_type::<XrInputContext>();
+        app.init_resource::<XrInputContexts>();
+        app.add_systems(
+            PreUpdate,
+            update_input_contexts
+                .before(XrActionSync)
+                .after(XrPollEvents)
+                .run_if(resource_exists::<XrActionSets>),
+        );
+        app.add_systems(
+            PreUpdate,
+            update_xr_buttons
+                .in_set(XrActionSync)
+                .after(sync_actions)
+                .after(release_actions)
+                // the simulator presses the buttons instead
+                .run_if(not(resource_exists::<XrSimulator>)),
+        );
+        app.add_systems(
+            PreUpdate,
+            (stop_haptics_on_focus_loss, pause_time_without_focus).after(XrPollEvents),
+        );
+        app.add_event::<XrActionHapticEvent>();
+        app.add_systems(PostUpdate, apply_haptic_events.run_if(xr_only()));
         app.add_systems(
             XrPreSetup,
             (insert_setup_action_sets, apply_deferred).chain(),
@@ -26,6 +96,51 @@ impl Plugin for XrActionsPlugin {
     }
 }
 
+/// What happens while a system menu or the dashboard has the input focus. The actions are
+/// always put into their neutral state then, so held buttons read as released.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrFocusLossSettings {
+    /// Pause the virtual [`Time`] until the focus comes back
+    pub pause_time: bool,
+}
+
+fn release_actions(mut action_sets: ResMut<XrActionSets>) {
+    action_sets.release_all();
+}
+
+fn stop_haptics_on_focus_loss(
+    mut focus_lost: EventReader<XrFocusLost>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Option<Res<XrSession>>,
+) {
+    if focus_lost.read().count() == 0 {
+        return;
+    }
+    if let (Some(action_sets), Some(session)) = (action_sets, session) {
+        action_sets.stop_all_haptics(&session);
+    }
+}
+
+fn pause_time_without_focus(
+    settings: Res<XrFocusLossSettings>,
+    mut focus_lost: EventReader<XrFocusLost>,
+    mut focus_gained: EventReader<XrFocusGained>,
+    mut session_ending: EventReader<XrSessionEnding>,
+    mut time: ResMut<Time<Virtual>>,
+    mut paused: Local<bool>,
+) {
+    let lost = focus_lost.read().count() > 0;
+    // a session that ends never regains focus
+    let regained = focus_gained.read().count() > 0 || session_ending.read().count() > 0;
+    if regained && *paused {
+        time.unpause();
+        *paused = false;
+    } else if lost && settings.pause_time && !time.is_paused() {
+        time.pause();
+        *paused = true;
+    }
+}
+
 fn insert_setup_action_sets(mut cmds: Commands) {
     info!("WHAT?!");
     cmds.insert_resource(SetupActionSets {
@@ -44,64 +159,209 @@ fn create_action<T: xr::ActionTy>(
     action_name: &'static str,
     oxr_action_set: &xr::ActionSet,
     hands: &[xr::Path],
-) -> xr::Action<T> {
-    match action.handednes {
-        ActionHandednes::Single => oxr_action_set
-            .create_action(action_name, &action.pretty_name, &[])
-            .unwrap_or_else(|_| panic!("Unable to create action: {}", action_name)),
-        ActionHandednes::Double => oxr_action_set
-            .create_action(action_name, &action.pretty_name, hands)
-            .unwrap_or_else(|_| panic!("Unable to create action: {}", action_name)),
+    locale: Option<&str>,
+) -> Result<xr::Action<T>, XrError> {
+    validate_name(action_name, xr::sys::MAX_ACTION_NAME_SIZE).map_err(|reason| {
+        XrError::InvalidName {
+            kind: "action",
+            name: action_name.into(),
+            reason,
+        }
+    })?;
+    let localized_name = localized_name(
+        action_name,
+        &action.pretty_name,
+        &action.localized_names,
+        locale,
+        xr::sys::MAX_LOCALIZED_ACTION_NAME_SIZE,
+    );
+    let subaction_paths = match action.handednes {
+        ActionHandednes::Single => &[],
+        ActionHandednes::Double => hands,
+    };
+    oxr_action_set
+        .create_action(action_name, &localized_name, subaction_paths)
+        .call("xrCreateAction")
+        .map_err(|err| err.with_context(format!("creating the action {}", action_name)))
+}
+
+/// Checks the rules openxr has for action and action set names,
+/// `max_size` includes the null terminator
+pub(super) fn validate_name(name: &str, max_size: usize) -> Result<(), &'static str> {
+    if name.is_empty() {
+        return Err("names can't be empty");
+    }
+    if name.len() >= max_size {
+        return Err("the name is too long");
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-_.".contains(c))
+    {
+        return Err("names may only contain lowercase letters, digits, '-', '_' and '.'");
+    }
+    Ok(())
+}
+
+/// The locale of the system, like `de_DE`, used to pick localized names
+fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|locale| !locale.is_empty() && locale != "C" && locale != "POSIX")
+        .map(|locale| {
+            let locale = locale.split(['.', '@']).next().unwrap_or_default();
+            locale.replace('-', "_")
+        })
+}
+
+/// The name for `locale`, falling back to the name for its language, the pretty name
+/// and lastly a name derived from the identifier like "Trigger Touched" for `trigger_touched`
+fn localized_name(
+    name: &str,
+    pretty_name: &str,
+    localized_names: &HashMap<&'static str, String>,
+    locale: Option<&str>,
+    max_size: usize,
+) -> String {
+    let localized = locale.and_then(|locale| {
+        let language = locale.split('_').next().unwrap_or(locale);
+        localized_names
+            .get(locale)
+            .or_else(|| localized_names.get(language))
+    });
+    let mut localized = match localized {
+        Some(localized) => localized.clone(),
+        None if !pretty_name.is_empty() => pretty_name.to_owned(),
+        None => name
+            .split(['_', '-', '.'])
+            .filter(|word| !word.is_empty())
+            .map(|word| {
+                let mut chars = word.chars();
+                chars.next().unwrap().to_uppercase().chain(chars).collect()
+            })
+            .collect::<Vec<String>>()
+            .join(" "),
+    };
+    if localized.len() >= max_size {
+        warn!("The localized name of {} is too long, shortening it", name);
+        let mut end = max_size - 1;
+        while !localized.is_char_boundary(end) {
+            end -= 1;
+        }
+        localized.truncate(end);
     }
+    localized
 }
 pub fn setup_oxr_actions(world: &mut World) {
     let actions = world.remove_resource::<SetupActionSets>().unwrap();
     let instance = world.get_resource::<XrInstance>().unwrap();
     let session = world.get_resource::<XrSession>().unwrap();
+    let errors = world
+        .get_resource::<XrErrors>()
+        .cloned()
+        .unwrap_or_default();
     let left_path = instance.string_to_path("/user/hand/left").unwrap();
     let right_path = instance.string_to_path("/user/hand/right").unwrap();
     let hands = [left_path, right_path];
 
-    let mut action_sets = XrActionSets { sets: default() };
+    let mut action_sets = XrActionSets {
+        sets: default(),
+        synced: false,
+        released: false,
+        emulated_dpads: Vec::new(),
+        emulated_palm_poses: HashSet::new(),
+        palm_pose_fallback: world
+            .get_resource::<XrPalmPoseFallback>()
+            .copied()
+            .unwrap_or_default(),
+    };
+    let palm_poses_supported = palm_pose_supported(instance);
+    let mut dpads: Vec<(&'static str, XrDpadBinding)> = Vec::new();
+    let locale = system_locale();
+    let locale = locale.as_deref();
     // let mut action_bindings: HashMap<&'static str, Vec<xr::Path>> = HashMap::new();
     let mut action_bindings: HashMap<
         (&'static str, &'static str),
         HashMap<&'static str, Vec<xr::Path>>,
     > = HashMap::new();
     for (set_name, set) in actions.sets.into_iter() {
+        dpads.extend(set.dpads.into_iter().map(|dpad| (set_name, dpad)));
         let mut actions: HashMap<&'static str, TypedAction> = default();
-        let oxr_action_set = instance
-            .create_action_set(set_name, &set.pretty_name, set.priority)
-            .expect("Unable to create action set");
+        let mut handed_actions: HashSet<&'static str> = default();
+        if let Err(reason) = validate_name(set_name, xr::sys::MAX_ACTION_SET_NAME_SIZE) {
+            errors.warn(XrError::InvalidName {
+                kind: "action set",
+                name: set_name.into(),
+                reason,
+            });
+            continue;
+        }
+        let localized_name = localized_name(
+            set_name,
+            &set.pretty_name,
+            &set.localized_names,
+            locale,
+            xr::sys::MAX_LOCALIZED_ACTION_SET_NAME_SIZE,
+        );
+        let oxr_action_set = match instance
+            .create_action_set(set_name, &localized_name, set.priority)
+            .call("xrCreateActionSet")
+        {
+            Ok(set) => set,
+            Err(err) => {
+                errors.warn(err.with_context(format!("creating the action set {}", set_name)));
+                continue;
+            }
+        };
         for (action_name, action) in set.actions.into_iter() {
             use self::create_action as ca;
-            let typed_action = match action.action_type {
-                ActionType::Vec2 => {
-                    TypedAction::Vec2(ca(&action, action_name, &oxr_action_set, &hands))
-                }
-                ActionType::F32 => {
-                    TypedAction::F32(ca(&action, action_name, &oxr_action_set, &hands))
-                }
-                ActionType::Bool => {
-                    TypedAction::Bool(ca(&action, action_name, &oxr_action_set, &hands))
-                }
-                ActionType::PoseF => {
-                    TypedAction::PoseF(ca(&action, action_name, &oxr_action_set, &hands))
-                }
-                ActionType::Haptic => {
-                    TypedAction::Haptic(ca(&action, action_name, &oxr_action_set, &hands))
+            let typed_action =
+                match action.action_type {
+                    ActionType::Vec2 => ca(&action, action_name, &oxr_action_set, &hands, locale)
+                        .map(TypedAction::Vec2),
+                    ActionType::F32 => ca(&action, action_name, &oxr_action_set, &hands, locale)
+                        .map(TypedAction::F32),
+                    ActionType::Bool => ca(&action, action_name, &oxr_action_set, &hands, locale)
+                        .map(TypedAction::Bool),
+                    ActionType::PoseF => ca(&action, action_name, &oxr_action_set, &hands, locale)
+                        .map(TypedAction::PoseF),
+                    ActionType::Haptic => ca(&action, action_name, &oxr_action_set, &hands, locale)
+                        .map(TypedAction::Haptic),
+                };
+            // the other actions still work without it
+            let typed_action = match typed_action {
+                Ok(typed_action) => typed_action,
+                Err(err) => {
+                    errors.warn(err);
+                    continue;
                 }
             };
             actions.insert(action_name, typed_action);
+            if let ActionHandednes::Double = action.handednes {
+                handed_actions.insert(action_name);
+            }
             for (device_path, bindings) in action.bindings.into_iter() {
-                for b in bindings {
+                for mut b in bindings {
                     // info!("binding {} to {}", action_name, b);
+                    if !palm_poses_supported {
+                        if let Some(grip) = palm_pose_fallback_path(b) {
+                            action_sets
+                                .emulated_palm_poses
+                                .insert((set_name, action_name));
+                            b = grip;
+                        }
+                    }
+                    let Ok(path) = instance.string_to_path(b) else {
+                        warn!("Skipping invalid binding path {} of {}", b, action_name);
+                        continue;
+                    };
                     action_bindings
                         .entry((set_name, action_name))
                         .or_default()
                         .entry(device_path)
                         .or_default()
-                        .push(instance.string_to_path(b).unwrap());
+                        .push(path);
                 }
             }
         }
@@ -111,10 +371,19 @@ pub fn setup_oxr_actions(world: &mut World) {
             ActionSet {
                 oxr_action_set,
                 actions,
-                enabled: true,
+                handed_actions,
+                processing: set.processing,
+                priority: set.priority,
+                enabled: set.enabled,
+                was_enabled: set.enabled,
+                synced: default(),
             },
         );
     }
+    let dpads_supported = dpad_binding_supported(instance);
+    if !dpads_supported {
+        action_sets.emulated_dpads = dpads.clone();
+    }
     let mut b_indings: HashMap<&'static str, Vec<Binding>> = HashMap::new();
     for (dev, mut bindings) in action_sets
         .sets
@@ -150,12 +419,60 @@ pub fn setup_oxr_actions(world: &mut World) {
     {
         b_indings.entry(dev).or_default().append(&mut bindings);
     }
+    let mut profile_dpads: HashMap<&'static str, Vec<(xr::sys::ActionSet, XrDpadBinding)>> =
+        HashMap::new();
+    if dpads_supported {
+        for (set_name, dpad) in &dpads {
+            let set = &action_sets.sets[set_name];
+            for (action_name, suffix) in dpad.directions() {
+                let Some(TypedAction::Bool(action)) = set.actions.get(action_name) else {
+                    warn!("Dpad action {} isn't a bool action", action_name);
+                    continue;
+                };
+                let path = format!("{}{}", dpad.source_path(), suffix);
+                b_indings
+                    .entry(dpad.profile)
+                    .or_default()
+                    .push(Binding::new(
+                        action,
+                        instance.string_to_path(&path).unwrap(),
+                    ));
+            }
+            profile_dpads
+                .entry(dpad.profile)
+                .or_default()
+                .push((set.oxr_action_set.as_raw(), *dpad));
+        }
+    }
+    let suggest = |dev: &str, profile: xr::Path, bindings: &[Binding]| match profile_dpads.get(dev)
+    {
+        Some(dpads) => suggest_bindings_with_dpads(instance, profile, bindings, dpads),
+        None => instance.suggest_interaction_profile_bindings(profile, bindings),
+    };
     for (dev, bindings) in b_indings.into_iter() {
-        instance
-            .suggest_interaction_profile_bindings(instance.string_to_path(dev).unwrap(), &bindings)
-            .expect("Unable to suggest interaction bindings!");
+        let Ok(profile) = instance.string_to_path(dev) else {
+            warn!("Skipping bindings for invalid interaction profile {}", dev);
+            continue;
+        };
+        if suggest(dev, profile, &bindings).is_ok() {
+            continue;
+        }
+        // one unsupported path rejects the whole suggestion, every suggestion replaces the last one
+        let supported = bindings
+            .iter()
+            .copied()
+            .filter(|binding| suggest(dev, profile, &[*binding]).is_ok())
+            .collect::<Vec<_>>();
+        warn!(
+            "Skipping {} bindings that {} doesn't support",
+            bindings.len() - supported.len(),
+            dev
+        );
+        if let Err(err) = suggest(dev, profile, &supported) {
+            warn!("Unable to suggest bindings for {}: {}", dev, err);
+        }
     }
-    session
+    if let Err(err) = session
         .attach_action_sets(
             &action_sets
                 .sets
@@ -163,17 +480,24 @@ pub fn setup_oxr_actions(world: &mut World) {
                 .map(|set| &set.oxr_action_set)
                 .collect::<Vec<_>>(),
         )
-        .expect("Unable to attach action sets!");
+        .call("xrAttachSessionActionSets")
+    {
+        // without attached action sets there's no input at all
+        errors.fatal(err);
+    }
 
     world.insert_resource(action_sets);
 }
 
 pub enum ActionHandednes {
     Single,
+    /// Created with the subaction paths of both hands, so the state can be queried per hand
+    /// with [`XrActionSets::get_state_for`]
     Double,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "binding-assets", derive(serde::Deserialize))]
 pub enum ActionType {
     F32,
     Bool,
@@ -192,15 +516,20 @@ pub enum TypedAction {
 
 pub struct SetupAction {
     pretty_name: String,
+    localized_names: HashMap<&'static str, String>,
     action_type: ActionType,
     handednes: ActionHandednes,
-    bindings: HashMap<&'static str, Vec<&'static str>>,
+    pub(super) bindings: HashMap<&'static str, Vec<&'static str>>,
 }
 
 pub struct SetupActionSet {
     pretty_name: String,
+    localized_names: HashMap<&'static str, String>,
     priority: u32,
-    actions: HashMap<&'static str, SetupAction>,
+    enabled: bool,
+    pub(super) actions: HashMap<&'static str, SetupAction>,
+    pub(super) dpads: Vec<XrDpadBinding>,
+    pub(super) processing: HashMap<&'static str, XrActionProcessing>,
 }
 
 impl SetupActionSet {
@@ -215,12 +544,41 @@ impl SetupActionSet {
             name,
             SetupAction {
                 pretty_name,
+                localized_names: default(),
                 action_type,
                 handednes,
                 bindings: default(),
             },
         );
     }
+    /// The name runtimes show for the set when the system uses `locale`, like `de_DE` or `de`.
+    /// Without a name for the system's locale the pretty name is used.
+    pub fn localize(&mut self, locale: &'static str, name: impl Into<String>) {
+        self.localized_names.insert(locale, name.into());
+    }
+    /// The name runtimes show for an action when the system uses `locale`, see [`SetupActionSet::localize`].
+    /// Actions with an empty pretty name get one derived from their name.
+    pub fn localize_action(
+        &mut self,
+        action: &'static str,
+        locale: &'static str,
+        name: impl Into<String>,
+    ) {
+        self.actions
+            .get_mut(action)
+            .ok_or(eyre::eyre!("Missing Action: {}", action))
+            .unwrap()
+            .localized_names
+            .insert(locale, name.into());
+    }
+    /// Leaves the set disabled until it's enabled with [`XrActionSets::set_enabled`] or an
+    /// [`XrInputContext`](super::input_contexts::XrInputContext)
+    pub fn start_disabled(&mut self) {
+        self.enabled = false;
+    }
+    pub fn action_type(&self, name: &'static str) -> Option<ActionType> {
+        self.actions.get(name).map(|action| action.action_type)
+    }
     pub fn suggest_binding(&mut self, device_path: &'static str, bindings: &[XrBinding]) {
         for binding in bindings {
             self.actions
@@ -234,6 +592,7 @@ impl SetupActionSet {
         }
     }
 }
+#[derive(Clone, Copy, Debug, Reflect)]
 pub struct XrBinding {
     action: &'static str,
     path: &'static str,
@@ -250,10 +609,14 @@ impl XrBinding {
 
 #[derive(Resource)]
 pub struct SetupActionSets {
-    sets: HashMap<&'static str, SetupActionSet>,
+    pub(super) sets: HashMap<&'static str, SetupActionSet>,
 }
 
 impl SetupActionSets {
+    /// Action sets with a higher priority override the bindings of lower priority sets
+    /// to the same inputs, like a menu set overriding a gameplay set while it's enabled.
+    /// All sets start out enabled unless they [`start_disabled`](SetupActionSet::start_disabled),
+    /// see [`XrActionSets::set_enabled`].
     pub fn add_action_set(
         &mut self,
         name: &'static str,
@@ -264,8 +627,12 @@ impl SetupActionSets {
             name,
             SetupActionSet {
                 pretty_name,
+                localized_names: HashMap::new(),
                 priority,
+                enabled: true,
                 actions: HashMap::new(),
+                dpads: Vec::new(),
+                processing: HashMap::new(),
             },
         );
         self.sets.get_mut(name).unwrap()
@@ -273,26 +640,297 @@ impl SetupActionSets {
 }
 
 pub struct ActionSet {
-    // add functionality to enable/disable action sets
     enabled: bool,
+    /// Whether the set was enabled in the last sync
+    was_enabled: bool,
+    /// Fixed when the set is created, the runtime can't change it afterwards
+    priority: u32,
     actions: HashMap<&'static str, TypedAction>,
+    /// The actions created with [`ActionHandednes::Double`]
+    pub(super) handed_actions: HashSet<&'static str>,
+    pub(super) processing: HashMap<&'static str, XrActionProcessing>,
     oxr_action_set: xr::ActionSet,
+    /// The states read right after the last sync
+    synced: SyncedStates,
+}
+
+/// The states of the actions of a set by action name and subaction path, read once per sync so
+/// systems reading them don't call into the runtime and can run in parallel
+#[derive(Default)]
+struct SyncedStates {
+    bools: HashMap<(&'static str, xr::Path), ActionState<bool>>,
+    floats: HashMap<(&'static str, xr::Path), ActionState<f32>>,
+    vec2s: HashMap<(&'static str, xr::Path), ActionState<Vector2f>>,
+    /// Bools that were already pressed when the focus came back, they read as released until
+    /// they are let go so the first frame back doesn't see a press
+    held: HashSet<(&'static str, xr::Path)>,
+}
+
+impl SyncedStates {
+    fn clear(&mut self) {
+        self.bools.clear();
+        self.floats.clear();
+        self.vec2s.clear();
+    }
+
+    /// Sets every state to its neutral value, the first call after losing focus reports the
+    /// values that were held as changed
+    fn release(&mut self) {
+        fn release<T: Default + PartialEq>(
+            states: &mut HashMap<(&'static str, xr::Path), ActionState<T>>,
+        ) {
+            for state in states.values_mut() {
+                state.changed = state.value != T::default();
+                state.value = T::default();
+                state.is_active = false;
+            }
+        }
+        release(&mut self.bools);
+        release(&mut self.floats);
+        release(&mut self.vec2s);
+        self.held.clear();
+    }
+
+    fn hold_pressed(&mut self) {
+        let pressed = self.bools.iter().filter(|(_, state)| state.value);
+        self.held.extend(pressed.map(|(&key, _)| key));
+    }
+
+    fn mask_held(&mut self) {
+        let bools = &mut self.bools;
+        self.held.retain(|key| {
+            let Some(state) = bools.get_mut(key) else {
+                return false;
+            };
+            // neither the press nor the release is reported
+            state.changed = false;
+            std::mem::take(&mut state.value)
+        });
+    }
+}
+
+impl ActionSet {
+    fn read_synced_states(&mut self, session: &xr::Session<xr::AnyGraphics>) {
+        let was_enabled = std::mem::replace(&mut self.was_enabled, self.enabled);
+        if !self.enabled {
+            // the first sync without the set reports the held values as released
+            match was_enabled {
+                true => self.synced.release(),
+                false => self.synced.clear(),
+            }
+            return;
+        }
+        // the maps keep their capacity, so this doesn't allocate after the first sync
+        self.synced.clear();
+        for (&name, action) in &self.actions {
+            let handed = self.handed_actions.contains(name);
+            let paths = [
+                Some(xr::Path::NULL),
+                handed.then(|| subaction_path(Hand::Left)),
+                handed.then(|| subaction_path(Hand::Right)),
+            ];
+            for path in paths.into_iter().flatten() {
+                // unreadable states are queried from the runtime when they're read
+                match action {
+                    TypedAction::Bool(a) => {
+                        if let Ok(state) = a.state(session, path) {
+                            self.synced.bools.insert((name, path), state.into());
+                        }
+                    }
+                    TypedAction::F32(a) => {
+                        if let Ok(state) = a.state(session, path) {
+                            self.synced.floats.insert((name, path), state.into());
+                        }
+                    }
+                    TypedAction::Vec2(a) => {
+                        if let Ok(state) = a.state(session, path) {
+                            self.synced.vec2s.insert((name, path), state.into());
+                        }
+                    }
+                    TypedAction::PoseF(_) | TypedAction::Haptic(_) => {}
+                }
+            }
+        }
+    }
 }
 
 #[derive(Resource)]
 pub struct XrActionSets {
     sets: HashMap<&'static str, ActionSet>,
+    synced: bool,
+    /// The states were released because the actions weren't synced
+    released: bool,
+    /// Dpads the runtime can't bind, they are emulated in `ButtonInput<XrButton>`
+    emulated_dpads: Vec<(&'static str, XrDpadBinding)>,
+    /// Palm pose actions bound to the grip pose as the runtime doesn't support the palm pose
+    pub(super) emulated_palm_poses: HashSet<(&'static str, &'static str)>,
+    pub(super) palm_pose_fallback: XrPalmPoseFallback,
+}
+
+/// The state of an action as of the last sync
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActionState<T> {
+    pub value: T,
+    /// `false` if nothing is bound to the action right now, like when the controller is asleep,
+    /// the value is the default value then
+    pub is_active: bool,
+    /// The value is different from the one of the sync before
+    pub changed: bool,
+    /// When the value last changed, only meaningful while the action is active
+    pub last_change_time: XrTime,
+}
+
+impl<T: xr::ActionInput> From<xr::ActionState<T>> for ActionState<T> {
+    fn from(state: xr::ActionState<T>) -> Self {
+        Self {
+            value: state.current_state,
+            is_active: state.is_active,
+            changed: state.changed_since_last_sync,
+            last_change_time: state.last_change_time.into(),
+        }
+    }
+}
+
+impl ActionState<bool> {
+    /// The button went down with the last sync
+    pub fn just_pressed(&self) -> bool {
+        self.is_active && self.changed && self.value
+    }
+    /// The button went up with the last sync, an action that stops being active while it's held
+    /// doesn't report this, [`ButtonInput<XrButton>`] releases it anyway
+    pub fn just_released(&self) -> bool {
+        self.is_active && self.changed && !self.value
+    }
+}
+
+/// A bool action, the key of the `ButtonInput<XrButton>` resource.
+/// The buttons are pressed while the action is active and true for any subaction path,
+/// they are released when the action becomes inactive, like when the session loses focus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub struct XrButton {
+    pub action_set: &'static str,
+    pub action: &'static str,
+}
+
+impl XrButton {
+    pub fn new(action_set: &'static str, action: &'static str) -> Self {
+        Self { action_set, action }
+    }
+}
+
+/// Vibrates or stops vibrating the controllers bound to a haptic action,
+/// ignored while the session isn't focused. To simply rumble a controller send an
+/// [`XrHapticEvent`](super::haptics::XrHapticEvent) instead.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct XrActionHapticEvent {
+    pub action_set: &'static str,
+    pub action: &'static str,
+    /// `None` for all controllers bound to the action
+    pub hand: Option<Hand>,
+    pub feedback: XrHapticFeedback,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum XrHapticFeedback {
+    /// See [`XrActionSets::apply_haptic`]
+    Vibrate {
+        amplitude: f32,
+        frequency: Option<f32>,
+        duration: Duration,
+    },
+    Stop,
+}
+
+impl XrHapticFeedback {
+    /// The shortest vibration the runtime supports, like a click
+    pub fn click(amplitude: f32) -> Self {
+        Self::Vibrate {
+            amplitude,
+            frequency: None,
+            duration: Duration::ZERO,
+        }
+    }
+}
+
+/// Types whose state can be read with [`XrActionSets::get_state`]
+pub trait ActionValue: xr::ActionInput {
+    fn from_typed_action(action: &TypedAction) -> Option<&Action<Self>>;
+    /// The state read right after the last sync, if there is one
+    fn synced_state(
+        set: &ActionSet,
+        action_name: &'static str,
+        subaction_path: xr::Path,
+    ) -> Option<ActionState<Self>>;
+}
+
+impl ActionValue for bool {
+    fn from_typed_action(action: &TypedAction) -> Option<&Action<Self>> {
+        match action {
+            TypedAction::Bool(a) => Some(a),
+            _ => None,
+        }
+    }
+    fn synced_state(
+        set: &ActionSet,
+        action_name: &'static str,
+        subaction_path: xr::Path,
+    ) -> Option<ActionState<Self>> {
+        set.synced
+            .bools
+            .get(&(action_name, subaction_path))
+            .copied()
+    }
+}
+
+impl ActionValue for f32 {
+    fn from_typed_action(action: &TypedAction) -> Option<&Action<Self>> {
+        match action {
+            TypedAction::F32(a) => Some(a),
+            _ => None,
+        }
+    }
+    fn synced_state(
+        set: &ActionSet,
+        action_name: &'static str,
+        subaction_path: xr::Path,
+    ) -> Option<ActionState<Self>> {
+        set.synced
+            .floats
+            .get(&(action_name, subaction_path))
+            .copied()
+    }
+}
+
+impl ActionValue for Vector2f {
+    fn from_typed_action(action: &TypedAction) -> Option<&Action<Self>> {
+        match action {
+            TypedAction::Vec2(a) => Some(a),
+            _ => None,
+        }
+    }
+    fn synced_state(
+        set: &ActionSet,
+        action_name: &'static str,
+        subaction_path: xr::Path,
+    ) -> Option<ActionState<Self>> {
+        set.synced
+            .vec2s
+            .get(&(action_name, subaction_path))
+            .copied()
+    }
 }
 
 use std::fmt::Display as FmtDisplay;
 impl FmtDisplay for ActionError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let err = match self {
-            ActionError::NoActionSet => "Action Set Not Found!",
-            ActionError::NoAction => "Action Not Found!",
-            ActionError::WrongActionType => "Wrong Action Type!",
-        };
-        write!(f, "{}", err)
+        match self {
+            ActionError::NoActionSet => write!(f, "Action Set Not Found!"),
+            ActionError::NoAction => write!(f, "Action Not Found!"),
+            ActionError::WrongActionType => write!(f, "Wrong Action Type!"),
+            ActionError::NotSynced => write!(f, "Actions Not Synced Yet!"),
+            ActionError::NotHanded => write!(f, "Action Has No Subaction Paths For The Hands!"),
+            ActionError::Xr(err) => write!(f, "OpenXR Error: {}", err),
+        }
     }
 }
 impl Error for ActionError {}
@@ -301,9 +939,275 @@ pub enum ActionError {
     NoActionSet,
     NoAction,
     WrongActionType,
+    /// The actions haven't been synced since the session started, so there are no values yet
+    NotSynced,
+    /// The action was created without the subaction paths of the hands
+    NotHanded,
+    Xr(xr::sys::Result),
+}
+
+impl From<xr::sys::Result> for ActionError {
+    fn from(value: xr::sys::Result) -> Self {
+        ActionError::Xr(value)
+    }
+}
+
+impl TypedAction {
+    fn as_raw(&self) -> xr::sys::Action {
+        match self {
+            TypedAction::F32(a) => a.as_raw(),
+            TypedAction::Bool(a) => a.as_raw(),
+            TypedAction::PoseF(a) => a.as_raw(),
+            TypedAction::Haptic(a) => a.as_raw(),
+            TypedAction::Vec2(a) => a.as_raw(),
+        }
+    }
 }
 
 impl XrActionSets {
+    /// The names and handles of all action sets and actions, names of actions include their set
+    pub(crate) fn raw_handles(&self) -> Vec<(String, xr::sys::ObjectType, u64)> {
+        let mut handles = Vec::new();
+        for (set_name, set) in &self.sets {
+            handles.push((
+                set_name.to_string(),
+                xr::sys::ObjectType::ACTION_SET,
+                set.oxr_action_set.as_raw().into_raw(),
+            ));
+            for (action_name, action) in &set.actions {
+                handles.push((
+                    format!("{}/{}", set_name, action_name),
+                    xr::sys::ObjectType::ACTION,
+                    action.as_raw().into_raw(),
+                ));
+            }
+        }
+        handles
+    }
+    pub(crate) fn raw_action_set(&self, action_set: &str) -> Option<xr::sys::ActionSet> {
+        Some(self.sets.get(action_set)?.oxr_action_set.as_raw())
+    }
+    pub(crate) fn raw_action(
+        &self,
+        action_set: &str,
+        action_name: &str,
+    ) -> Option<xr::sys::Action> {
+        Some(
+            self.sets
+                .get(action_set)?
+                .actions
+                .get(action_name)?
+                .as_raw(),
+        )
+    }
+    /// Syncs the enabled action sets, this already happens in [`XrActionSync`] every frame
+    pub fn sync(&mut self, session: &xr::Session<xr::AnyGraphics>) -> xr::Result<()> {
+        let active_sets = self
+            .sets
+            .values()
+            .filter(|set| set.enabled)
+            .map(|set| xr::ActiveActionSet::new(&set.oxr_action_set))
+            .collect::<Vec<_>>();
+        session.sync_actions(&active_sets)?;
+        self.synced = true;
+        let focus_regained = std::mem::take(&mut self.released);
+        for set in self.sets.values_mut() {
+            let newly_enabled = set.enabled && !set.was_enabled;
+            set.read_synced_states(session);
+            // inputs held while a set gets enabled don't press its buttons either
+            if focus_regained || newly_enabled {
+                set.synced.hold_pressed();
+            }
+            set.synced.mask_held();
+        }
+        Ok(())
+    }
+    /// Puts every action into its neutral state while the actions aren't synced, like when a
+    /// system menu took the focus. Inputs that are still held when the focus comes back only
+    /// read as pressed after they were let go.
+    pub fn release_all(&mut self) {
+        self.released = true;
+        for set in self.sets.values_mut() {
+            set.synced.release();
+        }
+    }
+    /// Stops the vibration of every haptic action
+    pub fn stop_all_haptics(&self, session: &xr::Session<xr::AnyGraphics>) {
+        for set in self.sets.values() {
+            for action in set.actions.values() {
+                if let TypedAction::Haptic(action) = action {
+                    if let Err(err) = action.stop_feedback(session, xr::Path::NULL) {
+                        warn!("Unable to stop haptic feedback: {}", err);
+                    }
+                }
+            }
+        }
+    }
+    /// Whether the actions were synced at least once, only then they have values
+    pub fn has_synced(&self) -> bool {
+        self.synced
+    }
+    /// The state of an action as of the last sync, pass `xr::Path::NULL` as the `subaction_path`
+    /// to combine the states of all subaction paths.
+    /// The states of enabled sets are read once per sync for the `NULL` path and the paths of
+    /// the hands, reading them doesn't call into the runtime.
+    pub fn get_state<T: ActionValue>(
+        &self,
+        session: &xr::Session<xr::AnyGraphics>,
+        action_set: &'static str,
+        action_name: &'static str,
+        subaction_path: xr::Path,
+    ) -> Result<ActionState<T>, ActionError> {
+        let set = self.sets.get(action_set).ok_or(ActionError::NoActionSet)?;
+        let action = set.actions.get(action_name).ok_or(ActionError::NoAction)?;
+        let action = T::from_typed_action(action).ok_or(ActionError::WrongActionType)?;
+        if !self.synced {
+            return Err(ActionError::NotSynced);
+        }
+        if let Some(state) = T::synced_state(set, action_name, subaction_path) {
+            return Ok(state);
+        }
+        Ok(action.state(session, subaction_path)?.into())
+    }
+    /// The state of an action for one hand, the action has to be created with
+    /// [`ActionHandednes::Double`]
+    pub fn get_state_for<T: ActionValue>(
+        &self,
+        session: &xr::Session<xr::AnyGraphics>,
+        action_set: &'static str,
+        action_name: &'static str,
+        hand: Hand,
+    ) -> Result<ActionState<T>, ActionError> {
+        let set = self.sets.get(action_set).ok_or(ActionError::NoActionSet)?;
+        if set.actions.contains_key(action_name) && !set.handed_actions.contains(action_name) {
+            return Err(ActionError::NotHanded);
+        }
+        self.get_state(session, action_set, action_name, subaction_path(hand))
+    }
+    /// Vibrates the controllers bound to a haptic action, replacing a running vibration.
+    /// `amplitude` is between 0.0 and 1.0, a `frequency` of `None` lets the runtime choose one
+    /// and a zero `duration` is the shortest vibration the runtime supports.
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply_haptic(
+        &self,
+        session: &xr::Session<xr::AnyGraphics>,
+        action_set: &'static str,
+        action_name: &'static str,
+        subaction_path: xr::Path,
+        amplitude: f32,
+        frequency: Option<f32>,
+        duration: Duration,
+    ) -> Result<(), ActionError> {
+        let action = self.get_action_haptic(action_set, action_name)?;
+        let duration = match duration.is_zero() {
+            true => xr::Duration::MIN_HAPTIC,
+            false => xr::Duration::from_nanos(duration.as_nanos() as i64),
+        };
+        let vibration = xr::HapticVibration::new()
+            .amplitude(amplitude.clamp(0.0, 1.0))
+            .frequency(frequency.unwrap_or(xr::FREQUENCY_UNSPECIFIED))
+            .duration(duration);
+        action.apply_feedback(session, subaction_path, &vibration)?;
+        Ok(())
+    }
+    /// Stops the vibration of the controllers bound to a haptic action
+    pub fn stop_haptic(
+        &self,
+        session: &xr::Session<xr::AnyGraphics>,
+        action_set: &'static str,
+        action_name: &'static str,
+        subaction_path: xr::Path,
+    ) -> Result<(), ActionError> {
+        let action = self.get_action_haptic(action_set, action_name)?;
+        action.stop_feedback(session, subaction_path)?;
+        Ok(())
+    }
+    /// Shorthand for the value of [`XrActionSets::get_state`]
+    pub fn get_value<T: ActionValue>(
+        &self,
+        session: &xr::Session<xr::AnyGraphics>,
+        action_set: &'static str,
+        action_name: &'static str,
+        subaction_path: xr::Path,
+    ) -> Result<T, ActionError> {
+        self.get_state(session, action_set, action_name, subaction_path)
+            .map(|state| state.value)
+    }
+    /// Shorthand for the value of [`XrActionSets::get_state_for`]
+    pub fn get_value_for<T: ActionValue>(
+        &self,
+        session: &xr::Session<xr::AnyGraphics>,
+        action_set: &'static str,
+        action_name: &'static str,
+        hand: Hand,
+    ) -> Result<T, ActionError> {
+        self.get_state_for(session, action_set, action_name, hand)
+            .map(|state| state.value)
+    }
+    /// Only enabled action sets are synced, the actions of disabled sets report that they
+    /// aren't active instead of keeping their last values. The first sync after disabling a
+    /// set releases its held actions, bools held while enabling it read as released until
+    /// they are let go.
+    /// Takes effect with the next sync at the start of the frame.
+    pub fn set_enabled(
+        &mut self,
+        action_set: &'static str,
+        enabled: bool,
+    ) -> Result<(), ActionError> {
+        let set = self
+            .sets
+            .get_mut(action_set)
+            .ok_or(ActionError::NoActionSet)?;
+        set.enabled = enabled;
+        Ok(())
+    }
+    /// The priority the set was created with, see [`SetupActionSets::add_action_set`]
+    pub fn priority(&self, action_set: &'static str) -> Result<u32, ActionError> {
+        self.sets
+            .get(action_set)
+            .map(|set| set.priority)
+            .ok_or(ActionError::NoActionSet)
+    }
+    pub fn is_enabled(&self, action_set: &'static str) -> Result<bool, ActionError> {
+        self.sets
+            .get(action_set)
+            .map(|set| set.enabled)
+            .ok_or(ActionError::NoActionSet)
+    }
+    /// The names of the action sets that are synced
+    pub fn enabled_sets(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.sets
+            .iter()
+            .filter(|(_, set)| set.enabled)
+            .map(|(name, _)| *name)
+    }
+    /// The bool actions of the action sets that are synced
+    pub fn enabled_buttons(&self) -> impl Iterator<Item = XrButton> + '_ {
+        self.sets
+            .iter()
+            .filter(|(_, set)| set.enabled)
+            .flat_map(|(set_name, set)| {
+                set.actions
+                    .iter()
+                    .filter(|(_, action)| matches!(action, TypedAction::Bool(_)))
+                    .map(|(action_name, _)| XrButton::new(set_name, action_name))
+            })
+    }
+    pub(super) fn action_set(&self, action_set: &'static str) -> Result<&ActionSet, ActionError> {
+        self.sets.get(action_set).ok_or(ActionError::NoActionSet)
+    }
+    pub(super) fn typed_action(
+        &self,
+        action_set: &'static str,
+        action_name: &'static str,
+    ) -> Result<&TypedAction, ActionError> {
+        self.sets
+            .get(action_set)
+            .ok_or(ActionError::NoActionSet)?
+            .actions
+            .get(action_name)
+            .ok_or(ActionError::NoAction)
+    }
     pub fn get_action_vec2(
         &self,
         action_set: &'static str,
@@ -391,19 +1295,267 @@ impl XrActionSets {
     }
 }
 
-pub fn sync_actions(action_sets: Res<XrActionSets>, session: Res<XrSession>) {
-    let active_sets = action_sets
-        .sets
-        .values()
-        .filter_map(|set| {
-            if set.enabled {
-                Some(xr::ActiveActionSet::new(&set.oxr_action_set))
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<_>>();
-    if let Err(err) = session.sync_actions(&active_sets) {
+pub fn sync_actions(mut action_sets: ResMut<XrActionSets>, session: Res<XrSession>) {
+    if let Err(err) = action_sets.sync(&session) {
         warn!("OpenXR action sync error: {}", err);
     }
 }
+
+/// The same conditions as [`sync_actions`], outside of them the action values would be stale
+pub(super) fn synced_this_frame(
+    status: &XrStatus,
+    session_state: &XrSessionState,
+    config: &XrSessionConfig,
+) -> bool {
+    *status == XrStatus::Enabled
+        && (**session_state == xr::SessionState::FOCUSED || config.overlay.is_some())
+}
+
+fn update_xr_buttons(
+    mut buttons: ResMut<ButtonInput<XrButton>>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Option<Res<XrSession>>,
+    status: Res<XrStatus>,
+    session_state: Res<XrSessionState>,
+    config: Res<XrSessionConfig>,
+) {
+    buttons.clear();
+    let synced = synced_this_frame(&status, &session_state, &config);
+    let (Some(action_sets), Some(session), true) = (action_sets, session, synced) else {
+        buttons.release_all();
+        return;
+    };
+    let emulated = action_sets
+        .emulated_dpads
+        .iter()
+        .flat_map(|(set_name, dpad)| {
+            dpad.directions()
+                .map(move |(action, _)| XrButton::new(set_name, action))
+        })
+        .collect::<HashSet<_>>();
+    for (set_name, set) in action_sets.sets.iter() {
+        for (action_name, action) in set.actions.iter() {
+            let TypedAction::Bool(action) = action else {
+                continue;
+            };
+            let button = XrButton::new(set_name, action_name);
+            if emulated.contains(&button) {
+                continue;
+            }
+            let pressed = set.enabled
+                && !set.synced.held.contains(&(*action_name, xr::Path::NULL))
+                && action
+                    .state(&session, xr::Path::NULL)
+                    .is_ok_and(|state| state.is_active && state.current_state);
+            match pressed {
+                true => buttons.press(button),
+                false => buttons.release(button),
+            }
+        }
+    }
+    for (set_name, dpad) in &action_sets.emulated_dpads {
+        let value = action_sets
+            .sets
+            .get(set_name)
+            .filter(|set| set.enabled)
+            .and_then(|set| match set.actions.get(dpad.source_action()) {
+                Some(TypedAction::Vec2(action)) => action.state(&session, xr::Path::NULL).ok(),
+                _ => None,
+            })
+            .filter(|state| state.is_active)
+            .map_or(Vec2::ZERO, |state| {
+                Vec2::new(state.current_state.x, state.current_state.y)
+            });
+        for (action, active) in dpad.emulate(value) {
+            let button = XrButton::new(set_name, action);
+            match active {
+                true => buttons.press(button),
+                false => buttons.release(button),
+            }
+        }
+    }
+}
+
+fn apply_haptic_events(
+    mut events: EventReader<XrActionHapticEvent>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Option<Res<XrSession>>,
+    session_state: Res<XrSessionState>,
+) {
+    let (Some(action_sets), Some(session)) = (action_sets, session) else {
+        events.clear();
+        return;
+    };
+    if **session_state != xr::SessionState::FOCUSED {
+        events.clear();
+        return;
+    }
+    for event in events.read() {
+        let path = event.hand.map_or(xr::Path::NULL, subaction_path);
+        let result = match event.feedback {
+            XrHapticFeedback::Vibrate {
+                amplitude,
+                frequency,
+                duration,
+            } => action_sets.apply_haptic(
+                &session,
+                event.action_set,
+                event.action,
+                path,
+                amplitude,
+                frequency,
+                duration,
+            ),
+            XrHapticFeedback::Stop => {
+                action_sets.stop_haptic(&session, event.action_set, event.action, path)
+            }
+        };
+        if let Err(err) = result {
+            warn!(
+                "Unable to apply haptic feedback to {}: {}",
+                event.action, err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// How often the runtime was asked for the state of the trigger
+    static STATE_READS: AtomicU32 = AtomicU32::new(0);
+
+    /// A runtime with a trigger action whose value goes up with every read of its state
+    unsafe extern "system" fn fake_runtime(
+        _instance: xr::sys::Instance,
+        name: *const std::ffi::c_char,
+        function: *mut Option<xr::sys::pfn::VoidFunction>,
+    ) -> xr::sys::Result {
+        unsafe extern "system" fn sync_actions(
+            _session: xr::sys::Session,
+            _info: *const xr::sys::ActionsSyncInfo,
+        ) -> xr::sys::Result {
+            xr::sys::Result::SUCCESS
+        }
+        unsafe extern "system" fn get_action_state_float(
+            _session: xr::sys::Session,
+            _info: *const xr::sys::ActionStateGetInfo,
+            state: *mut xr::sys::ActionStateFloat,
+        ) -> xr::sys::Result {
+            let state = &mut *state;
+            state.current_state = (STATE_READS.fetch_add(1, Ordering::SeqCst) + 1) as f32;
+            state.changed_since_last_sync = true.into();
+            state.is_active = true.into();
+            xr::sys::Result::SUCCESS
+        }
+        unsafe extern "system" fn destroy(_handle: u64) -> xr::sys::Result {
+            xr::sys::Result::SUCCESS
+        }
+        unsafe extern "system" fn unsupported() -> xr::sys::Result {
+            xr::sys::Result::ERROR_FUNCTION_UNSUPPORTED
+        }
+        type Destroy = unsafe extern "system" fn(u64) -> xr::sys::Result;
+        type Unsupported = unsafe extern "system" fn() -> xr::sys::Result;
+        let name = std::ffi::CStr::from_ptr(name).to_bytes();
+        *function = Some(match name {
+            b"xrSyncActions" => std::mem::transmute(sync_actions as xr::sys::pfn::SyncActions),
+            b"xrGetActionStateFloat" => {
+                std::mem::transmute(get_action_state_float as xr::sys::pfn::GetActionStateFloat)
+            }
+            _ if name.starts_with(b"xrDestroy") => std::mem::transmute(destroy as Destroy),
+            _ => std::mem::transmute(unsupported as Unsupported),
+        });
+        xr::sys::Result::SUCCESS
+    }
+
+    #[test]
+    #[cfg(feature = "vulkan")]
+    fn every_reader_sees_the_same_sync() {
+        let (session, action_sets) = unsafe {
+            let entry = xr::Entry::from_get_instance_proc_addr(fake_runtime).unwrap();
+            let instance = xr::Instance::from_raw(
+                entry,
+                xr::sys::Instance::from_raw(1),
+                xr::InstanceExtensions::default(),
+            )
+            .unwrap();
+            let (session, _, _) = xr::Session::<xr::Vulkan>::from_raw(
+                instance.clone(),
+                xr::sys::Session::from_raw(1),
+                Box::new(()),
+            );
+            let set = xr::ActionSet::from_raw(instance, xr::sys::ActionSet::from_raw(1));
+            let trigger = xr::Action::from_raw(set.clone(), xr::sys::Action::from_raw(1));
+            let set = ActionSet {
+                enabled: true,
+                was_enabled: true,
+                priority: 0,
+                actions: HashMap::from([("trigger", TypedAction::F32(trigger))]),
+                handed_actions: HashSet::new(),
+                processing: HashMap::new(),
+                oxr_action_set: set,
+                synced: default(),
+            };
+            let action_sets = XrActionSets {
+                sets: HashMap::from([("game", set)]),
+                synced: false,
+                released: false,
+                emulated_dpads: Vec::new(),
+                emulated_palm_poses: HashSet::new(),
+                palm_pose_fallback: default(),
+            };
+            (XrSession::Vulkan(session), action_sets)
+        };
+        let reads = Arc::new(Mutex::new(Vec::new()));
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(session)
+            .insert_resource(action_sets)
+            .add_systems(PreUpdate, sync_actions);
+        // the readers only read resources, so they run in parallel
+        for _ in 0..4 {
+            let reads = reads.clone();
+            app.add_systems(
+                Update,
+                move |action_sets: Res<XrActionSets>, session: Res<XrSession>| {
+                    let state = action_sets
+                        .get_state::<f32>(&session, "game", "trigger", xr::Path::NULL)
+                        .unwrap();
+                    reads.lock().unwrap().push(state);
+                },
+            );
+        }
+        for sync in 1..=10 {
+            app.update();
+            let mut reads = reads.lock().unwrap();
+            assert_eq!(reads.len(), 4);
+            assert!(reads.iter().all(|state| *state == reads[0]));
+            assert_eq!(reads[0].value, sync as f32);
+            reads.clear();
+        }
+        // the systems never asked the runtime themselves
+        assert_eq!(STATE_READS.load(Ordering::SeqCst), 10);
+
+        let action_sets = app.world.resource::<XrActionSets>();
+        let session = app.world.resource::<XrSession>();
+        std::thread::scope(|scope| {
+            let readers: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        action_sets
+                            .get_state::<f32>(session, "game", "trigger", xr::Path::NULL)
+                            .unwrap()
+                    })
+                })
+                .collect();
+            for reader in readers {
+                assert_eq!(reader.join().unwrap().value, 10.0);
+            }
+        });
+        assert_eq!(STATE_READS.load(Ordering::SeqCst), 10);
+    }
+}
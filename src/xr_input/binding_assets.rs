@@ -0,0 +1,334 @@
+use std::error::Error;
+use std::fmt::Display;
+
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::{BoxedFuture, HashMap, HashSet};
+use openxr as xr;
+use serde::Deserialize;
+
+use crate::xr_init::XrSetup;
+
+use super::actions::{
+    validate_name, ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrBinding,
+};
+use super::steamvr_manifest::write_steamvr_manifest;
+
+/// Loads the action sets from a `.xr.ron` or `.xr.json` asset,
+/// they are added every time a session is set up:
+///
+/// ```ron
+/// (
+///     action_sets: [(
+///         name: "gameplay",
+///         pretty_name: "Gameplay",
+///         priority: 0,
+///         actions: [(
+///             name: "grab",
+///             pretty_name: "Grab",
+///             action_type: F32,
+///             handed: true,
+///             bindings: {
+///                 "/interaction_profiles/oculus/touch_controller": [
+///                     "/user/hand/left/input/squeeze/value",
+///                     "/user/hand/right/input/squeeze/value",
+///                 ],
+///             },
+///         )],
+///     )],
+/// )
+/// ```
+///
+/// A session can't attach new action sets once it started,
+/// so changes to the file are used from the next session on.
+pub struct XrBindingsAssetPlugin {
+    pub path: String,
+    /// Writes the bindings as a SteamVR action manifest next to the executable,
+    /// see [`XrBindings::to_steamvr_manifest`]
+    pub steamvr_manifest: bool,
+}
+
+impl Default for XrBindingsAssetPlugin {
+    fn default() -> Self {
+        Self {
+            path: "input.xr.ron".into(),
+            steamvr_manifest: false,
+        }
+    }
+}
+
+impl Plugin for XrBindingsAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<XrBindings>();
+        app.register_asset_loader(XrBindingsLoader);
+        let path = self.path.clone();
+        app.add_systems(
+            Startup,
+            move |mut cmds: Commands, asset_server: Res<AssetServer>| {
+                cmds.insert_resource(XrBindingsHandle(asset_server.load(path.clone())));
+            },
+        );
+        app.add_systems(XrSetup, add_asset_action_sets);
+        app.add_systems(Update, report_changed_bindings);
+        if self.steamvr_manifest {
+            app.add_systems(Update, write_steamvr_manifest);
+        }
+    }
+}
+
+/// The bindings loaded by [`XrBindingsAssetPlugin`]
+#[derive(Resource, Clone, Debug, Deref)]
+pub struct XrBindingsHandle(pub Handle<XrBindings>);
+
+/// Action sets loaded from a file, the names are leaked when loading
+/// as the action setup only takes static names
+#[derive(Asset, TypePath, Clone, Debug)]
+pub struct XrBindings {
+    pub action_sets: Vec<XrBindingsActionSet>,
+}
+
+#[derive(Clone, Debug)]
+pub struct XrBindingsActionSet {
+    pub name: &'static str,
+    pub pretty_name: String,
+    pub priority: u32,
+    pub actions: Vec<XrBindingsAction>,
+}
+
+#[derive(Clone, Debug)]
+pub struct XrBindingsAction {
+    pub name: &'static str,
+    pub pretty_name: String,
+    pub action_type: ActionType,
+    /// Whether the action is created with the subaction paths of both hands
+    pub handed: bool,
+    /// Binding paths for each interaction profile
+    pub bindings: Vec<(&'static str, Vec<&'static str>)>,
+}
+
+impl XrBindings {
+    pub fn add_to(&self, setup: &mut SetupActionSets) {
+        for set in &self.action_sets {
+            let setup_set = setup.add_action_set(set.name, set.pretty_name.clone(), set.priority);
+            for action in &set.actions {
+                setup_set.new_action(
+                    action.name,
+                    action.pretty_name.clone(),
+                    action.action_type,
+                    match action.handed {
+                        true => ActionHandednes::Double,
+                        false => ActionHandednes::Single,
+                    },
+                );
+                for (profile, paths) in &action.bindings {
+                    let bindings = paths
+                        .iter()
+                        .map(|path| XrBinding::new(action.name, path))
+                        .collect::<Vec<_>>();
+                    setup_set.suggest_binding(profile, &bindings);
+                }
+            }
+        }
+    }
+}
+
+fn add_asset_action_sets(
+    handle: Option<Res<XrBindingsHandle>>,
+    bindings: Res<Assets<XrBindings>>,
+    mut setup: ResMut<SetupActionSets>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+    match bindings.get(&handle.0) {
+        Some(bindings) => bindings.add_to(&mut setup),
+        None => warn!("The xr bindings aren't loaded yet, the session starts without them"),
+    }
+}
+
+fn report_changed_bindings(
+    mut events: EventReader<AssetEvent<XrBindings>>,
+    handle: Option<Res<XrBindingsHandle>>,
+    action_sets: Option<Res<XrActionSets>>,
+) {
+    let Some(handle) = handle else {
+        events.clear();
+        return;
+    };
+    for event in events.read() {
+        if event.is_modified(&handle.0) && action_sets.is_some() {
+            info!("The xr bindings changed, they are used once the session restarts");
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub(super) struct RawBindings {
+    pub(super) action_sets: Vec<RawActionSet>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct RawActionSet {
+    pub(super) name: String,
+    pub(super) pretty_name: String,
+    #[serde(default)]
+    pub(super) priority: u32,
+    pub(super) actions: Vec<RawAction>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct RawAction {
+    pub(super) name: String,
+    pub(super) pretty_name: String,
+    pub(super) action_type: ActionType,
+    #[serde(default)]
+    pub(super) handed: bool,
+    #[serde(default)]
+    pub(super) bindings: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug)]
+pub enum XrBindingsLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    Json(serde_json::Error),
+    /// The file parsed but an action set or action in it can't be created
+    Invalid {
+        action_set: String,
+        action: Option<String>,
+        reason: &'static str,
+    },
+}
+
+impl Display for XrBindingsLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XrBindingsLoaderError::Io(err) => write!(f, "Unable to read the xr bindings: {}", err),
+            XrBindingsLoaderError::Ron(err) => write!(f, "Invalid xr bindings: {}", err),
+            XrBindingsLoaderError::Json(err) => write!(f, "Invalid xr bindings: {}", err),
+            XrBindingsLoaderError::Invalid {
+                action_set,
+                action: Some(action),
+                reason,
+            } => write!(f, "Invalid action {}/{}: {}", action_set, action, reason),
+            XrBindingsLoaderError::Invalid {
+                action_set,
+                action: None,
+                reason,
+            } => write!(f, "Invalid action set {}: {}", action_set, reason),
+        }
+    }
+}
+
+impl Error for XrBindingsLoaderError {}
+
+impl From<std::io::Error> for XrBindingsLoaderError {
+    fn from(value: std::io::Error) -> Self {
+        XrBindingsLoaderError::Io(value)
+    }
+}
+
+/// Loads [`XrBindings`] from `.xr.ron` and `.xr.json` files
+pub struct XrBindingsLoader;
+
+impl AssetLoader for XrBindingsLoader {
+    type Asset = XrBindings;
+    type Settings = ();
+    type Error = XrBindingsLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let json = load_context
+                .path()
+                .extension()
+                .is_some_and(|extension| extension == "json");
+            let raw: RawBindings = match json {
+                true => serde_json::from_slice(&bytes).map_err(XrBindingsLoaderError::Json)?,
+                false => ron::de::from_bytes(&bytes).map_err(XrBindingsLoaderError::Ron)?,
+            };
+            validate(&raw)?;
+            Ok(leak_names(raw))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xr.ron", "xr.json"]
+    }
+}
+
+pub(super) fn validate(raw: &RawBindings) -> Result<(), XrBindingsLoaderError> {
+    let mut set_names = HashSet::new();
+    for set in &raw.action_sets {
+        let invalid_set = |reason| XrBindingsLoaderError::Invalid {
+            action_set: set.name.clone(),
+            action: None,
+            reason,
+        };
+        validate_name(&set.name, xr::sys::MAX_ACTION_SET_NAME_SIZE).map_err(invalid_set)?;
+        if !set_names.insert(&set.name) {
+            return Err(invalid_set("the name is used by another action set"));
+        }
+        let mut action_names = HashSet::new();
+        for action in &set.actions {
+            let invalid_action = |reason| XrBindingsLoaderError::Invalid {
+                action_set: set.name.clone(),
+                action: Some(action.name.clone()),
+                reason,
+            };
+            validate_name(&action.name, xr::sys::MAX_ACTION_NAME_SIZE).map_err(invalid_action)?;
+            if !action_names.insert(&action.name) {
+                return Err(invalid_action(
+                    "the name is used by another action in the set",
+                ));
+            }
+            let relative_path = action.bindings.iter().any(|(profile, paths)| {
+                !profile.starts_with('/') || paths.iter().any(|path| !path.starts_with('/'))
+            });
+            if relative_path {
+                return Err(invalid_action(
+                    "interaction profiles and binding paths have to start with '/'",
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn leak_names(raw: RawBindings) -> XrBindings {
+    let leak = |name: String| -> &'static str { Box::leak(name.into_boxed_str()) };
+    XrBindings {
+        action_sets: raw
+            .action_sets
+            .into_iter()
+            .map(|set| XrBindingsActionSet {
+                name: leak(set.name),
+                pretty_name: set.pretty_name,
+                priority: set.priority,
+                actions: set
+                    .actions
+                    .into_iter()
+                    .map(|action| XrBindingsAction {
+                        name: leak(action.name),
+                        pretty_name: action.pretty_name,
+                        action_type: action.action_type,
+                        handed: action.handed,
+                        bindings: action
+                            .bindings
+                            .into_iter()
+                            .map(|(profile, paths)| {
+                                (leak(profile), paths.into_iter().map(leak).collect())
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
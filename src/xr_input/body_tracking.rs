@@ -0,0 +1,312 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::{
+    input::XrInput,
+    resources::{XrFrameState, XrInstance, XrSession, XrTime},
+    system_properties::XrSystemProperties,
+    xr_init::{xr_only, XrCleanup, XrPostSetup},
+};
+
+use super::{QuatConv, Vec3Conv, XrTrackingUpdate};
+
+/// Updates [`XrBodyJoints`] with `XR_FB_body_tracking` in [`XrTrackingUpdate`], together with the hands, see
+/// [`XrExtensions::enable_body_tracking`](crate::graphics::extensions::XrExtensions::enable_body_tracking).
+/// Nothing is inserted if the system can't track bodies.
+pub struct XrBodyTrackingPlugin;
+
+impl Plugin for XrBodyTrackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(XrPostSetup, create_body_tracker);
+        app.add_systems(
+            PreUpdate,
+            update_body_joints
+                .in_set(XrTrackingUpdate)
+                .run_if(xr_only()),
+        );
+        app.add_systems(XrCleanup, cleanup_body_tracker);
+    }
+}
+
+/// The joints of the user's body in the reference space of the session
+#[derive(Resource, Clone, Debug)]
+pub struct XrBodyJoints {
+    /// Indexed by [`BodyJoint`], `None` if the runtime doesn't know where the joint is
+    pub joints: [Option<Transform>; BodyJoint::COUNT],
+    /// Whether the body is tracked right now, all joints are `None` otherwise
+    pub is_active: bool,
+    /// Between 0.0 and 1.0
+    pub confidence: f32,
+    /// Increases when the runtime changed the proportions of the skeleton
+    pub skeleton_changed_count: u32,
+    pub time: XrTime,
+}
+
+impl Default for XrBodyJoints {
+    fn default() -> Self {
+        Self {
+            joints: [None; BodyJoint::COUNT],
+            is_active: false,
+            confidence: 0.0,
+            skeleton_changed_count: 0,
+            time: default(),
+        }
+    }
+}
+
+impl XrBodyJoints {
+    pub fn get(&self, joint: BodyJoint) -> Option<&Transform> {
+        self.joints[joint as usize].as_ref()
+    }
+}
+
+/// The joints of `XR_FB_body_tracking`, the hand joints are in the same order as [`HandBone`](super::hands::HandBone)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BodyJoint {
+    Root,
+    Hips,
+    SpineLower,
+    SpineMiddle,
+    SpineUpper,
+    Chest,
+    Neck,
+    Head,
+    LeftShoulder,
+    LeftScapula,
+    LeftArmUpper,
+    LeftArmLower,
+    LeftHandWristTwist,
+    RightShoulder,
+    RightScapula,
+    RightArmUpper,
+    RightArmLower,
+    RightHandWristTwist,
+    LeftHandPalm,
+    LeftHandWrist,
+    LeftHandThumbMetacarpal,
+    LeftHandThumbProximal,
+    LeftHandThumbDistal,
+    LeftHandThumbTip,
+    LeftHandIndexMetacarpal,
+    LeftHandIndexProximal,
+    LeftHandIndexIntermediate,
+    LeftHandIndexDistal,
+    LeftHandIndexTip,
+    LeftHandMiddleMetacarpal,
+    LeftHandMiddleProximal,
+    LeftHandMiddleIntermediate,
+    LeftHandMiddleDistal,
+    LeftHandMiddleTip,
+    LeftHandRingMetacarpal,
+    LeftHandRingProximal,
+    LeftHandRingIntermediate,
+    LeftHandRingDistal,
+    LeftHandRingTip,
+    LeftHandLittleMetacarpal,
+    LeftHandLittleProximal,
+    LeftHandLittleIntermediate,
+    LeftHandLittleDistal,
+    LeftHandLittleTip,
+    RightHandPalm,
+    RightHandWrist,
+    RightHandThumbMetacarpal,
+    RightHandThumbProximal,
+    RightHandThumbDistal,
+    RightHandThumbTip,
+    RightHandIndexMetacarpal,
+    RightHandIndexProximal,
+    RightHandIndexIntermediate,
+    RightHandIndexDistal,
+    RightHandIndexTip,
+    RightHandMiddleMetacarpal,
+    RightHandMiddleProximal,
+    RightHandMiddleIntermediate,
+    RightHandMiddleDistal,
+    RightHandMiddleTip,
+    RightHandRingMetacarpal,
+    RightHandRingProximal,
+    RightHandRingIntermediate,
+    RightHandRingDistal,
+    RightHandRingTip,
+    RightHandLittleMetacarpal,
+    RightHandLittleProximal,
+    RightHandLittleIntermediate,
+    RightHandLittleDistal,
+    RightHandLittleTip,
+}
+
+impl BodyJoint {
+    pub const COUNT: usize = 70;
+    pub const ALL: [Self; Self::COUNT] = [
+        Self::Root,
+        Self::Hips,
+        Self::SpineLower,
+        Self::SpineMiddle,
+        Self::SpineUpper,
+        Self::Chest,
+        Self::Neck,
+        Self::Head,
+        Self::LeftShoulder,
+        Self::LeftScapula,
+        Self::LeftArmUpper,
+        Self::LeftArmLower,
+        Self::LeftHandWristTwist,
+        Self::RightShoulder,
+        Self::RightScapula,
+        Self::RightArmUpper,
+        Self::RightArmLower,
+        Self::RightHandWristTwist,
+        Self::LeftHandPalm,
+        Self::LeftHandWrist,
+        Self::LeftHandThumbMetacarpal,
+        Self::LeftHandThumbProximal,
+        Self::LeftHandThumbDistal,
+        Self::LeftHandThumbTip,
+        Self::LeftHandIndexMetacarpal,
+        Self::LeftHandIndexProximal,
+        Self::LeftHandIndexIntermediate,
+        Self::LeftHandIndexDistal,
+        Self::LeftHandIndexTip,
+        Self::LeftHandMiddleMetacarpal,
+        Self::LeftHandMiddleProximal,
+        Self::LeftHandMiddleIntermediate,
+        Self::LeftHandMiddleDistal,
+        Self::LeftHandMiddleTip,
+        Self::LeftHandRingMetacarpal,
+        Self::LeftHandRingProximal,
+        Self::LeftHandRingIntermediate,
+        Self::LeftHandRingDistal,
+        Self::LeftHandRingTip,
+        Self::LeftHandLittleMetacarpal,
+        Self::LeftHandLittleProximal,
+        Self::LeftHandLittleIntermediate,
+        Self::LeftHandLittleDistal,
+        Self::LeftHandLittleTip,
+        Self::RightHandPalm,
+        Self::RightHandWrist,
+        Self::RightHandThumbMetacarpal,
+        Self::RightHandThumbProximal,
+        Self::RightHandThumbDistal,
+        Self::RightHandThumbTip,
+        Self::RightHandIndexMetacarpal,
+        Self::RightHandIndexProximal,
+        Self::RightHandIndexIntermediate,
+        Self::RightHandIndexDistal,
+        Self::RightHandIndexTip,
+        Self::RightHandMiddleMetacarpal,
+        Self::RightHandMiddleProximal,
+        Self::RightHandMiddleIntermediate,
+        Self::RightHandMiddleDistal,
+        Self::RightHandMiddleTip,
+        Self::RightHandRingMetacarpal,
+        Self::RightHandRingProximal,
+        Self::RightHandRingIntermediate,
+        Self::RightHandRingDistal,
+        Self::RightHandRingTip,
+        Self::RightHandLittleMetacarpal,
+        Self::RightHandLittleProximal,
+        Self::RightHandLittleIntermediate,
+        Self::RightHandLittleDistal,
+        Self::RightHandLittleTip,
+    ];
+}
+
+#[derive(Resource)]
+struct XrBodyTracker {
+    handle: xr::sys::BodyTrackerFB,
+    locate: xr::sys::pfn::LocateBodyJointsFB,
+    destroy: xr::sys::pfn::DestroyBodyTrackerFB,
+}
+
+impl Drop for XrBodyTracker {
+    fn drop(&mut self) {
+        unsafe {
+            (self.destroy)(self.handle);
+        }
+    }
+}
+
+fn create_body_tracker(
+    mut commands: Commands,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    system_properties: Option<Res<XrSystemProperties>>,
+) {
+    let Some(ext) = instance.exts().fb_body_tracking else {
+        return;
+    };
+    if !system_properties.is_some_and(|properties| properties.body_tracking) {
+        return;
+    }
+    let info = xr::sys::BodyTrackerCreateInfoFB {
+        ty: xr::sys::BodyTrackerCreateInfoFB::TYPE,
+        next: ptr::null(),
+        body_joint_set: xr::sys::BodyJointSetFB::DEFAULT,
+    };
+    let mut handle = xr::sys::BodyTrackerFB::NULL;
+    let result = unsafe { (ext.create_body_tracker)(session.as_raw(), &info, &mut handle) };
+    if result.into_raw() < 0 {
+        warn!("Unable to create the body tracker: {}", result);
+        return;
+    }
+    commands.insert_resource(XrBodyTracker {
+        handle,
+        locate: ext.locate_body_joints,
+        destroy: ext.destroy_body_tracker,
+    });
+    commands.insert_resource(XrBodyJoints::default());
+}
+
+fn update_body_joints(
+    tracker: Option<Res<XrBodyTracker>>,
+    joints: Option<ResMut<XrBodyJoints>>,
+    input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+) {
+    let (Some(tracker), Some(mut joints)) = (tracker, joints) else {
+        return;
+    };
+    let info = xr::sys::BodyJointsLocateInfoFB {
+        ty: xr::sys::BodyJointsLocateInfoFB::TYPE,
+        next: ptr::null(),
+        base_space: input.stage.as_raw(),
+        time: frame_state.predicted_display_time,
+    };
+    let mut locations = [xr::sys::BodyJointLocationFB::default(); BodyJoint::COUNT];
+    let mut output = xr::sys::BodyJointLocationsFB {
+        ty: xr::sys::BodyJointLocationsFB::TYPE,
+        next: ptr::null_mut(),
+        is_active: false.into(),
+        confidence: 0.0,
+        joint_count: locations.len() as u32,
+        joint_locations: locations.as_mut_ptr(),
+        skeleton_changed_count: 0,
+        time: xr::Time::from_nanos(0),
+    };
+    let result = unsafe { (tracker.locate)(tracker.handle, &info, &mut output) };
+    if result.into_raw() < 0 {
+        warn!("Unable to locate the body joints: {}", result);
+        *joints = XrBodyJoints::default();
+        return;
+    }
+    let is_active = output.is_active.into();
+    joints.is_active = is_active;
+    joints.confidence = output.confidence;
+    joints.skeleton_changed_count = output.skeleton_changed_count;
+    joints.time = output.time.into();
+    let valid = xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID;
+    for (joint, location) in joints.joints.iter_mut().zip(&locations) {
+        *joint = (is_active && location.location_flags.contains(valid)).then(|| Transform {
+            translation: location.pose.position.to_vec3(),
+            rotation: location.pose.orientation.to_quat(),
+            ..default()
+        });
+    }
+}
+
+fn cleanup_body_tracker(mut commands: Commands) {
+    commands.remove_resource::<XrBodyTracker>();
+    commands.remove_resource::<XrBodyJoints>();
+}
@@ -0,0 +1,97 @@
+use openxr as xr;
+
+use crate::resources::{XrInstance, XrSession};
+
+use super::actions::{ActionError, TypedAction, XrActionSets};
+
+/// An input an action is bound to on the current controllers
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct XrBoundSource {
+    /// Like `/user/hand/right/input/trigger/value`
+    pub path: String,
+    /// The name the runtime shows the user for the input, like "Right Hand Oculus Touch Controller Trigger"
+    pub localized_name: String,
+}
+
+/// Which parts of a source's path are part of its localized name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct XrSourceNameComponents {
+    /// Like "Right Hand"
+    pub user_path: bool,
+    /// Like "Oculus Touch Controller"
+    pub interaction_profile: bool,
+    /// Like "Trigger"
+    pub component: bool,
+}
+
+impl Default for XrSourceNameComponents {
+    fn default() -> Self {
+        Self {
+            user_path: true,
+            interaction_profile: true,
+            component: true,
+        }
+    }
+}
+
+impl XrSourceNameComponents {
+    fn flags(self) -> xr::InputSourceLocalizedNameFlags {
+        let mut flags = xr::InputSourceLocalizedNameFlags::EMPTY;
+        if self.user_path {
+            flags |= xr::InputSourceLocalizedNameFlags::USER_PATH;
+        }
+        if self.interaction_profile {
+            flags |= xr::InputSourceLocalizedNameFlags::INTERACTION_PROFILE;
+        }
+        if self.component {
+            flags |= xr::InputSourceLocalizedNameFlags::COMPONENT;
+        }
+        flags
+    }
+}
+
+impl TypedAction {
+    fn bound_sources(&self, session: &xr::Session<xr::AnyGraphics>) -> xr::Result<Vec<xr::Path>> {
+        match self {
+            TypedAction::F32(a) => a.bound_sources(session),
+            TypedAction::Bool(a) => a.bound_sources(session),
+            TypedAction::PoseF(a) => a.bound_sources(session),
+            TypedAction::Haptic(a) => a.bound_sources(session),
+            TypedAction::Vec2(a) => a.bound_sources(session),
+        }
+    }
+}
+
+impl XrActionSets {
+    /// The inputs an action is bound to right now, empty while it's unbound.
+    /// The runtime picks bindings when the actions are synced, so query again after an
+    /// [`XrInteractionProfileChanged`](super::interaction_profiles::XrInteractionProfileChanged)
+    /// instead of keeping the result.
+    /// `components` are ignored if all of them are false, the name then is empty.
+    pub fn bound_sources(
+        &self,
+        session: &XrSession,
+        instance: &XrInstance,
+        action_set: &'static str,
+        action_name: &'static str,
+        components: XrSourceNameComponents,
+    ) -> Result<Vec<XrBoundSource>, ActionError> {
+        let action = self.typed_action(action_set, action_name)?;
+        if !self.has_synced() {
+            return Err(ActionError::NotSynced);
+        }
+        let flags = components.flags();
+        let mut sources = Vec::new();
+        for path in action.bound_sources(session)? {
+            let localized_name = match flags.is_empty() {
+                true => String::new(),
+                false => session.input_source_localized_name(path, flags)?,
+            };
+            sources.push(XrBoundSource {
+                path: instance.path_to_string(path)?,
+                localized_name,
+            });
+        }
+        Ok(sources)
+    }
+}
@@ -0,0 +1,277 @@
+use std::path::Path;
+use std::ptr;
+
+use bevy::asset::io::{
+    memory::{Dir, MemoryAssetReader},
+    AssetSource, AssetSourceId,
+};
+use bevy::asset::AssetPath;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use openxr as xr;
+
+use crate::{
+    resources::{XrInstance, XrSession},
+    xr_init::{xr_only, XrCleanup, XrPostSetup},
+};
+
+use super::{
+    hands::hand_tracking::TrackedHand,
+    interaction_profiles::{XrInteractionProfile, XrInteractionProfileChanged},
+    trackers::{OpenXRLeftController, OpenXRRightController},
+    Hand,
+};
+
+/// The asset source the models of `XR_MSFT_controller_model` are loaded from
+pub const CONTROLLER_MODEL_SOURCE: &str = "xr_controller_models";
+
+/// Registers the [`CONTROLLER_MODEL_SOURCE`], asset sources have to be added before the `AssetPlugin`
+/// so [`DefaultXrPlugins`](crate::DefaultXrPlugins) already adds it
+pub struct XrControllerModelSourcePlugin;
+
+impl Plugin for XrControllerModelSourcePlugin {
+    fn build(&self, app: &mut App) {
+        let dir = Dir::default();
+        let root = dir.clone();
+        app.register_asset_source(
+            CONTROLLER_MODEL_SOURCE,
+            AssetSource::build()
+                .with_reader(move || Box::new(MemoryAssetReader { root: root.clone() })),
+        );
+        app.insert_resource(XrControllerModelDir(dir));
+    }
+}
+
+#[derive(Resource, Clone)]
+struct XrControllerModelDir(Dir);
+
+/// Shows the model of the controller in each hand as a child of the
+/// [`OpenXRLeftController`] and [`OpenXRRightController`] entities, which follow the grip pose.
+/// The runtime's models of `XR_MSFT_controller_model` are preferred, see
+/// [`XrExtensions::enable_controller_model`](crate::graphics::extensions::XrExtensions::enable_controller_model),
+/// otherwise the glTF scenes in `fallback_models` are used.
+/// Models are swapped when the interaction profile changes and hidden while the hand is tracked.
+#[derive(Default)]
+pub struct XrControllerModelPlugin {
+    /// Asset paths of the left and right controller's glTF files for each profile
+    pub fallback_models: HashMap<XrInteractionProfile, [String; 2]>,
+}
+
+impl Plugin for XrControllerModelPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(XrFallbackControllerModels(self.fallback_models.clone()));
+        app.add_systems(XrPostSetup, |mut commands: Commands| {
+            commands.insert_resource(XrControllerModels::default())
+        });
+        app.add_systems(XrCleanup, despawn_controller_models);
+        app.add_systems(
+            Update,
+            (update_controller_models, hide_controllers_of_tracked_hands)
+                .chain()
+                .run_if(xr_only()),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct XrFallbackControllerModels(HashMap<XrInteractionProfile, [String; 2]>);
+
+/// The spawned model of a hand's controller
+#[derive(Component, Clone, Debug)]
+pub struct XrControllerModel {
+    pub hand: Hand,
+    pub profile: XrInteractionProfile,
+}
+
+#[derive(Resource)]
+struct XrControllerModels {
+    /// Hands whose model has to be checked, like after the interaction profile changed
+    pending: [bool; 2],
+    models: [Option<Entity>; 2],
+}
+
+impl Default for XrControllerModels {
+    fn default() -> Self {
+        Self {
+            pending: [true; 2],
+            models: [None; 2],
+        }
+    }
+}
+
+/// A glTF binary of `XR_MSFT_controller_model`
+#[derive(Clone, Debug)]
+pub struct XrControllerModelData {
+    /// Identifies the model, the runtime returns a different key once the model changes
+    pub key: u64,
+    pub glb: Vec<u8>,
+}
+
+impl XrSession {
+    /// The runtime's model of the controller in a hand, `None` without `XR_MSFT_controller_model`
+    /// or while the runtime doesn't have a model for it yet.
+    /// The model's origin is the grip pose of the hand.
+    pub fn controller_model(
+        &self,
+        instance: &XrInstance,
+        hand: Hand,
+    ) -> xr::Result<Option<XrControllerModelData>> {
+        let Some(ext) = instance.exts().msft_controller_model else {
+            return Ok(None);
+        };
+        let user_path = instance.string_to_path(match hand {
+            Hand::Left => "/user/hand/left",
+            Hand::Right => "/user/hand/right",
+        })?;
+        let mut key_state = xr::sys::ControllerModelKeyStateMSFT {
+            ty: xr::sys::ControllerModelKeyStateMSFT::TYPE,
+            next: ptr::null_mut(),
+            model_key: xr::sys::ControllerModelKeyMSFT::NULL,
+        };
+        check(unsafe { (ext.get_controller_model_key)(self.as_raw(), user_path, &mut key_state) })?;
+        if key_state.model_key == xr::sys::ControllerModelKeyMSFT::NULL {
+            return Ok(None);
+        }
+        let mut size = 0;
+        check(unsafe {
+            (ext.load_controller_model)(
+                self.as_raw(),
+                key_state.model_key,
+                0,
+                &mut size,
+                ptr::null_mut(),
+            )
+        })?;
+        let mut glb = vec![0; size as usize];
+        check(unsafe {
+            (ext.load_controller_model)(
+                self.as_raw(),
+                key_state.model_key,
+                size,
+                &mut size,
+                glb.as_mut_ptr(),
+            )
+        })?;
+        glb.truncate(size as usize);
+        Ok(Some(XrControllerModelData {
+            key: key_state.model_key.into_raw(),
+            glb,
+        }))
+    }
+}
+
+fn check(result: xr::sys::Result) -> xr::Result<()> {
+    match result.into_raw() < 0 {
+        true => Err(result),
+        false => Ok(()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_controller_models(
+    mut commands: Commands,
+    mut models: ResMut<XrControllerModels>,
+    mut profile_changed: EventReader<XrInteractionProfileChanged>,
+    fallback: Res<XrFallbackControllerModels>,
+    dir: Option<Res<XrControllerModelDir>>,
+    asset_server: Res<AssetServer>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    left_controller: Query<Entity, With<OpenXRLeftController>>,
+    right_controller: Query<Entity, With<OpenXRRightController>>,
+) {
+    if profile_changed.read().count() > 0 {
+        models.pending = [true; 2];
+    }
+    for (index, hand) in [Hand::Left, Hand::Right].into_iter().enumerate() {
+        if !models.pending[index] {
+            continue;
+        }
+        let controller = match hand {
+            Hand::Left => left_controller.get_single(),
+            Hand::Right => right_controller.get_single(),
+        };
+        let Ok(controller) = controller else {
+            continue;
+        };
+        let profile = match session.current_interaction_profile(&instance, hand) {
+            Ok(profile) => profile,
+            Err(err) => {
+                warn!("Unable to get the {:?} interaction profile: {}", hand, err);
+                None
+            }
+        };
+        if let Some(model) = models.models[index].take() {
+            commands.entity(model).despawn_recursive();
+        }
+        let Some(profile) = profile else {
+            models.pending[index] = false;
+            continue;
+        };
+        let runtime_model = match session.controller_model(&instance, hand) {
+            Ok(model) => model,
+            Err(err) => {
+                warn!("Unable to load the {:?} controller model: {}", hand, err);
+                None
+            }
+        };
+        let scene = match (runtime_model, dir.as_ref()) {
+            (Some(model), Some(dir)) => {
+                let path = format!("{:x}.glb", model.key);
+                dir.0.insert_asset(Path::new(&path), model.glb);
+                asset_server.load(
+                    AssetPath::from(format!("{}#Scene0", path))
+                        .with_source(AssetSourceId::from(CONTROLLER_MODEL_SOURCE)),
+                )
+            }
+            _ => match fallback.0.get(&profile) {
+                Some(paths) => asset_server.load(format!("{}#Scene0", paths[index])),
+                None => {
+                    // the runtime might not have loaded its model yet, keep checking
+                    if instance.exts().msft_controller_model.is_none() {
+                        models.pending[index] = false;
+                    }
+                    continue;
+                }
+            },
+        };
+        let model = commands
+            .spawn((
+                Name::new(format!("{:?} Controller Model", hand)),
+                SceneBundle { scene, ..default() },
+                XrControllerModel { hand, profile },
+            ))
+            .set_parent(controller)
+            .id();
+        models.models[index] = Some(model);
+        models.pending[index] = false;
+    }
+}
+
+fn hide_controllers_of_tracked_hands(
+    hands: Query<(&Hand, &TrackedHand)>,
+    mut models: Query<(&XrControllerModel, &mut Visibility)>,
+) {
+    for (model, mut visibility) in &mut models {
+        let tracked = hands
+            .iter()
+            .any(|(hand, tracked)| *hand == model.hand && tracked.joints.is_some());
+        let target = match tracked {
+            true => Visibility::Hidden,
+            false => Visibility::Inherited,
+        };
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+}
+
+fn despawn_controller_models(
+    mut commands: Commands,
+    models: Query<Entity, With<XrControllerModel>>,
+) {
+    for e in &models {
+        commands.entity(e).despawn_recursive();
+    }
+    commands.remove_resource::<XrControllerModels>();
+}
@@ -2,24 +2,31 @@ use bevy::ecs::schedule::IntoSystemConfigs;
 use bevy::log::{debug, info};
 use bevy::math::primitives::Direction3d;
 use bevy::prelude::{
-    Color, Gizmos, GlobalTransform, Plugin, Quat, Query, Res, Transform, Update, Vec2, Vec3, With,
-    Without,
+    default, Color, Gizmos, GlobalTransform, Plugin, PostUpdate, Quat, Query, Res, Resource,
+    Transform, Update, Vec2, Vec3, With, Without,
 };
+use bevy::transform::TransformSystem;
 
+use crate::anchors::XrAnchor;
+use crate::play_bounds::XrPlayBounds;
+use crate::resources::XrViews;
 use crate::xr_init::xr_only;
 use crate::{
     input::XrInput,
-    resources::{XrFrameState, XrSession},
+    resources::{XrFrameState, XrPredictionOffset, XrSession},
 };
 
 use crate::xr_input::{
     oculus_touch::{OculusController, OculusControllerRef},
-    Hand,
+    Hand, QuatConv, Vec3Conv,
 };
 
 use super::{
     actions::XrActionSets,
-    trackers::{OpenXRLeftController, OpenXRRightController, OpenXRTrackingRoot},
+    hands::{common::HandBoneRadius, BoneTrackingStatus},
+    trackers::{
+        OpenXRLeftController, OpenXRRightController, OpenXRTrackingRoot, XrAimPose, XrGripPose,
+    },
 };
 
 /// add debug renderer for controllers
@@ -32,6 +39,184 @@ impl Plugin for OpenXrDebugRenderer {
     }
 }
 
+/// Draws what the runtime tracks, to see offsets between the reference space and the scene.
+/// Everything is drawn below the [`OpenXRTrackingRoot`], which categories are drawn is set
+/// with the [`XrDebugGizmos`] resource.
+pub struct XrDebugGizmosPlugin;
+
+impl Plugin for XrDebugGizmosPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<XrDebugGizmos>();
+        app.add_systems(
+            PostUpdate,
+            draw_debug_gizmos
+                .run_if(xr_only())
+                .after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// The categories [`XrDebugGizmosPlugin`] draws, all of them by default
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrDebugGizmos {
+    /// Axes of the [`XrGripPose`] entities
+    pub grip_poses: bool,
+    /// Axes and ray of the [`XrAimPose`] entities
+    pub aim_poses: bool,
+    /// Forward vector of the headset
+    pub head: bool,
+    /// Frustums of the located views, from their poses and fields of view
+    pub view_frustums: bool,
+    /// The [`XrPlayBounds`] rectangle on the floor
+    pub play_bounds: bool,
+    /// Joints of the tracked or emulated hands
+    pub hand_joints: bool,
+    /// Located [`XrAnchor`]s
+    pub anchors: bool,
+    /// The headset at the predicted display time, as a ghost next to the one drawn for
+    /// [`head`](Self::head), while an [`XrPredictionOffset`] is set
+    pub prediction_ghost: bool,
+}
+
+impl Default for XrDebugGizmos {
+    fn default() -> Self {
+        Self {
+            grip_poses: true,
+            aim_poses: true,
+            head: true,
+            view_frustums: true,
+            play_bounds: true,
+            hand_joints: true,
+            anchors: true,
+            prediction_ghost: true,
+        }
+    }
+}
+
+impl XrDebugGizmos {
+    pub fn none() -> Self {
+        Self {
+            grip_poses: false,
+            aim_poses: false,
+            head: false,
+            view_frustums: false,
+            play_bounds: false,
+            hand_joints: false,
+            anchors: false,
+            prediction_ghost: false,
+        }
+    }
+}
+
+fn draw_axes(gizmos: &mut Gizmos, transform: &GlobalTransform, length: f32) {
+    let origin = transform.translation();
+    gizmos.line(origin, origin + transform.right() * length, Color::RED);
+    gizmos.line(origin, origin + transform.up() * length, Color::GREEN);
+    gizmos.line(origin, origin + transform.back() * length, Color::BLUE);
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn draw_debug_gizmos(
+    mut gizmos: Gizmos,
+    config: Res<XrDebugGizmos>,
+    root: Query<&GlobalTransform, With<OpenXRTrackingRoot>>,
+    grip_poses: Query<&GlobalTransform, With<XrGripPose>>,
+    aim_poses: Query<&GlobalTransform, With<XrAimPose>>,
+    views: Res<XrViews>,
+    play_bounds: Option<Res<XrPlayBounds>>,
+    hand_joints: Query<(&GlobalTransform, &HandBoneRadius, &BoneTrackingStatus)>,
+    anchors: Query<(&GlobalTransform, &XrAnchor)>,
+    (xr_input, frame_state): (Res<XrInput>, Res<XrFrameState>),
+) {
+    let root = root.get_single().copied().unwrap_or_default();
+    if config.grip_poses {
+        for transform in &grip_poses {
+            draw_axes(&mut gizmos, transform, 0.05);
+        }
+    }
+    if config.aim_poses {
+        for transform in &aim_poses {
+            draw_axes(&mut gizmos, transform, 0.03);
+            let origin = transform.translation();
+            gizmos.line(origin, origin + transform.forward() * 0.5, Color::WHITE);
+        }
+    }
+    // the views are in stage space, below the root
+    let views: Vec<_> = views
+        .iter()
+        .map(|view| {
+            let pose = Transform {
+                translation: view.pose.position.to_vec3(),
+                rotation: view.pose.orientation.to_quat(),
+                ..default()
+            };
+            (root.mul_transform(pose), view.fov)
+        })
+        .collect();
+    if config.head && !views.is_empty() {
+        let center = views
+            .iter()
+            .map(|(transform, _)| transform.translation())
+            .sum::<Vec3>()
+            / views.len() as f32;
+        gizmos.arrow(center, center + views[0].0.forward() * 0.3, Color::CYAN);
+    }
+    if config.prediction_ghost && xr_input.prediction_offset != XrPredictionOffset::ZERO {
+        let head = xr_input
+            .head_state(frame_state.predicted_display_time.into())
+            .ok()
+            .and_then(|state| state.transform());
+        if let Some(head) = head {
+            let head = root.mul_transform(head);
+            let origin = head.translation();
+            gizmos.arrow(origin, origin + head.forward() * 0.3, Color::GRAY);
+            gizmos.sphere(origin, head.compute_transform().rotation, 0.05, Color::GRAY);
+        }
+    }
+    if config.view_frustums {
+        const DISTANCE: f32 = 0.2;
+        for (transform, fov) in &views {
+            let origin = transform.translation();
+            let corners = [
+                (fov.angle_left, fov.angle_up),
+                (fov.angle_right, fov.angle_up),
+                (fov.angle_right, fov.angle_down),
+                (fov.angle_left, fov.angle_down),
+            ]
+            .map(|(x, y)| transform.transform_point(Vec3::new(x.tan(), y.tan(), -1.0) * DISTANCE));
+            for (i, corner) in corners.iter().enumerate() {
+                gizmos.line(origin, *corner, Color::YELLOW);
+                gizmos.line(*corner, corners[(i + 1) % corners.len()], Color::YELLOW);
+            }
+        }
+    }
+    if config.play_bounds {
+        if let Some(bounds) = play_bounds {
+            let corners = bounds.corners().map(|corner| root.transform_point(corner));
+            gizmos.linestrip(corners.iter().copied().chain([corners[0]]), Color::ORANGE);
+        }
+    }
+    if config.hand_joints {
+        for (transform, radius, status) in &hand_joints {
+            let color = match status {
+                BoneTrackingStatus::Tracked => Color::LIME_GREEN,
+                BoneTrackingStatus::Emulated => Color::ORANGE_RED,
+            };
+            gizmos.sphere(transform.translation(), Quat::IDENTITY, radius.0, color);
+        }
+    }
+    if config.anchors {
+        for (transform, anchor) in &anchors {
+            match anchor.tracked {
+                true => draw_axes(&mut gizmos, transform, 0.1),
+                false => {
+                    gizmos.sphere(transform.translation(), Quat::IDENTITY, 0.02, Color::GRAY);
+                }
+            }
+        }
+    }
+}
+
 #[allow(clippy::too_many_arguments, clippy::complexity)]
 pub fn draw_gizmos(
     mut gizmos: Gizmos,
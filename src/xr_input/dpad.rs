@@ -0,0 +1,200 @@
+use std::{f32::consts::FRAC_PI_2, ptr};
+
+use bevy::math::Vec2;
+use openxr as xr;
+
+use crate::resources::XrInstance;
+
+use super::actions::{ActionHandednes, ActionType, SetupActionSet, XrBinding};
+use super::Hand;
+
+/// The stick or pad a dpad is emulated on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum XrDpadSource {
+    Thumbstick,
+    Trackpad,
+}
+
+/// Thresholds of `XR_EXT_dpad_binding`, the software emulation only uses
+/// `center_region` and `wedge_angle`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrDpadSettings {
+    /// How far the source has to be pressed before a direction is active
+    pub force_threshold: f32,
+    /// How far the source has to be released before a direction is inactive again
+    pub force_threshold_released: f32,
+    /// Radius around the center in which no direction is active
+    pub center_region: f32,
+    /// Angle of each direction's wedge in radians
+    pub wedge_angle: f32,
+    /// Keeps the direction the source was pressed in active until it's released
+    pub is_sticky: bool,
+    /// Vibrates briefly when a direction becomes active, only with the extension
+    pub haptic_click: bool,
+}
+
+impl Default for XrDpadSettings {
+    fn default() -> Self {
+        Self {
+            force_threshold: 0.5,
+            force_threshold_released: 0.4,
+            center_region: 0.5,
+            wedge_angle: FRAC_PI_2,
+            is_sticky: false,
+            haptic_click: false,
+        }
+    }
+}
+
+/// Bool actions that are active while the source of a hand points in their direction.
+/// With `XR_EXT_dpad_binding` and `XR_KHR_binding_modification` enabled, see
+/// [`XrExtensions::enable_dpad_binding`](crate::graphics::extensions::XrExtensions::enable_dpad_binding),
+/// the runtime does this, otherwise it's emulated from the source's value.
+/// Read the emulated actions through `ButtonInput<XrButton>` so both work the same.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrDpadBinding {
+    pub profile: &'static str,
+    pub hand: Hand,
+    pub source: XrDpadSource,
+    pub up: Option<&'static str>,
+    pub down: Option<&'static str>,
+    pub left: Option<&'static str>,
+    pub right: Option<&'static str>,
+    pub settings: XrDpadSettings,
+}
+
+impl XrDpadBinding {
+    pub(super) fn source_path(&self) -> &'static str {
+        match (self.hand, self.source) {
+            (Hand::Left, XrDpadSource::Thumbstick) => "/user/hand/left/input/thumbstick",
+            (Hand::Right, XrDpadSource::Thumbstick) => "/user/hand/right/input/thumbstick",
+            (Hand::Left, XrDpadSource::Trackpad) => "/user/hand/left/input/trackpad",
+            (Hand::Right, XrDpadSource::Trackpad) => "/user/hand/right/input/trackpad",
+        }
+    }
+
+    /// The vec2 action the emulation reads the source from
+    pub(super) fn source_action(&self) -> &'static str {
+        match (self.hand, self.source) {
+            (Hand::Left, XrDpadSource::Thumbstick) => "dpad_source_left_thumbstick",
+            (Hand::Right, XrDpadSource::Thumbstick) => "dpad_source_right_thumbstick",
+            (Hand::Left, XrDpadSource::Trackpad) => "dpad_source_left_trackpad",
+            (Hand::Right, XrDpadSource::Trackpad) => "dpad_source_right_trackpad",
+        }
+    }
+
+    /// The actions with the path suffix the extension binds them to
+    pub(super) fn directions(&self) -> impl Iterator<Item = (&'static str, &'static str)> {
+        [
+            (self.up, "/dpad_up"),
+            (self.down, "/dpad_down"),
+            (self.left, "/dpad_left"),
+            (self.right, "/dpad_right"),
+        ]
+        .into_iter()
+        .filter_map(|(action, suffix)| action.map(|action| (action, suffix)))
+    }
+
+    /// The emulated state of each direction's action for a source value
+    pub(super) fn emulate(&self, value: Vec2) -> impl Iterator<Item = (&'static str, bool)> {
+        let settings = self.settings;
+        let outside_center = value.length() > settings.center_region;
+        [
+            (self.up, Vec2::Y),
+            (self.down, Vec2::NEG_Y),
+            (self.left, Vec2::NEG_X),
+            (self.right, Vec2::X),
+        ]
+        .into_iter()
+        .filter_map(move |(action, direction)| {
+            let active = outside_center
+                && value.angle_between(direction).abs() <= settings.wedge_angle / 2.0;
+            action.map(|action| (action, active))
+        })
+    }
+}
+
+impl SetupActionSet {
+    /// Adds a dpad to the set, the direction actions have to be bool actions of this set
+    pub fn suggest_dpad_binding(&mut self, dpad: XrDpadBinding) {
+        if self.action_type(dpad.source_action()).is_none() {
+            self.new_action(
+                dpad.source_action(),
+                "Dpad Source".into(),
+                ActionType::Vec2,
+                ActionHandednes::Single,
+            );
+        }
+        self.suggest_binding(
+            dpad.profile,
+            &[XrBinding::new(dpad.source_action(), dpad.source_path())],
+        );
+        self.dpads.push(dpad);
+    }
+}
+
+/// Whether the runtime can bind the dpads itself
+pub(super) fn dpad_binding_supported(instance: &XrInstance) -> bool {
+    let exts = instance.exts();
+    exts.ext_dpad_binding.is_some() && exts.khr_binding_modification.is_some()
+}
+
+/// `xrSuggestInteractionProfileBindings` with the dpad modifications of the profile chained
+pub(super) fn suggest_bindings_with_dpads(
+    instance: &XrInstance,
+    profile: xr::Path,
+    bindings: &[xr::Binding],
+    dpads: &[(xr::sys::ActionSet, XrDpadBinding)],
+) -> xr::Result<()> {
+    let haptic = xr::sys::HapticVibration {
+        ty: xr::sys::HapticVibration::TYPE,
+        next: ptr::null(),
+        duration: xr::Duration::MIN_HAPTIC,
+        frequency: xr::FREQUENCY_UNSPECIFIED,
+        amplitude: 0.5,
+    };
+    let mut dpad_bindings = Vec::with_capacity(dpads.len());
+    for (action_set, dpad) in dpads {
+        let settings = dpad.settings;
+        dpad_bindings.push(xr::sys::InteractionProfileDpadBindingEXT {
+            ty: xr::sys::InteractionProfileDpadBindingEXT::TYPE,
+            next: ptr::null(),
+            binding: instance.string_to_path(dpad.source_path())?,
+            action_set: *action_set,
+            force_threshold: settings.force_threshold,
+            force_threshold_released: settings.force_threshold_released,
+            center_region: settings.center_region,
+            wedge_angle: settings.wedge_angle,
+            is_sticky: settings.is_sticky.into(),
+            on_haptic: match settings.haptic_click {
+                true => &haptic as *const _ as _,
+                false => ptr::null(),
+            },
+            off_haptic: ptr::null(),
+        });
+    }
+    let modifications = dpad_bindings
+        .iter()
+        .map(|dpad| dpad as *const _ as *const xr::sys::BindingModificationBaseHeaderKHR)
+        .collect::<Vec<_>>();
+    let binding_modifications = xr::sys::BindingModificationsKHR {
+        ty: xr::sys::BindingModificationsKHR::TYPE,
+        next: ptr::null(),
+        binding_modification_count: modifications.len() as u32,
+        binding_modifications: modifications.as_ptr(),
+    };
+    let suggested = xr::sys::InteractionProfileSuggestedBinding {
+        ty: xr::sys::InteractionProfileSuggestedBinding::TYPE,
+        next: &binding_modifications as *const _ as _,
+        interaction_profile: profile,
+        count_suggested_bindings: bindings.len() as u32,
+        suggested_bindings: bindings.as_ptr() as _,
+    };
+    let result = unsafe {
+        (instance.fp().suggest_interaction_profile_bindings)(instance.as_raw(), &suggested)
+    };
+    if result.into_raw() < 0 {
+        return Err(result);
+    }
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::{
+    input::XrInput,
+    resources::{XrFrameState, XrInstance, XrSession, XrTime},
+    system_properties::XrSystemProperties,
+    xr_init::{xr_focused_only, xr_only, XrCleanup, XrPostSetup, XrSetup},
+};
+
+use super::{
+    actions::{
+        ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrActionSync, XrBinding,
+    },
+    trackers::OpenXRTrackingRoot,
+    QuatConv, Vec3Conv,
+};
+
+pub const EYE_GAZE_PROFILE: &str = "/interaction_profiles/ext/eye_gaze_interaction";
+/// The combined gaze of both eyes
+pub const EYE_GAZE_POSE_PATH: &str = "/user/eyes_ext/input/gaze_ext/pose";
+const ACTION_SET: &str = "eye_gaze";
+const ACTION: &str = "gaze_pose";
+
+/// Updates [`XrEyeGaze`] while the session is focused, needs `XR_EXT_eye_gaze_interaction`, see
+/// [`XrExtensions::enable_eye_gaze_interaction`](crate::graphics::extensions::XrExtensions::enable_eye_gaze_interaction).
+/// Nothing is inserted if the system doesn't support eye gaze,
+/// runtimes often also ask the user for permission first.
+pub struct XrEyeGazePlugin;
+
+impl Plugin for XrEyeGazePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(XrSetup, setup_eye_gaze_action);
+        app.add_systems(XrPostSetup, create_eye_gaze_space);
+        app.add_systems(
+            PreUpdate,
+            update_eye_gaze
+                .after(XrActionSync)
+                .run_if(xr_only())
+                .run_if(xr_focused_only()),
+        );
+        app.add_systems(XrCleanup, cleanup_eye_gaze);
+    }
+}
+
+/// Where the user is looking, the gaze points along the forward axis of `transform`
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct XrEyeGaze {
+    /// Whether the runtime tracked the gaze this frame, the other fields keep their last values otherwise
+    pub valid: bool,
+    /// In world space, below the [`OpenXRTrackingRoot`]
+    pub transform: Transform,
+    /// When the eyes were sampled, which is usually earlier than the predicted display time
+    pub sample_time: Option<XrTime>,
+}
+
+impl XrEyeGaze {
+    pub fn ray(&self) -> Ray3d {
+        Ray3d {
+            origin: self.transform.translation,
+            direction: self.transform.forward(),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct XrEyeGazeSpace(xr::Space);
+
+fn setup_eye_gaze_action(
+    system_properties: Option<Res<XrSystemProperties>>,
+    mut action_sets: ResMut<SetupActionSets>,
+) {
+    if !system_properties.is_some_and(|properties| properties.eye_gaze_interaction) {
+        return;
+    }
+    let set = action_sets.add_action_set(ACTION_SET, "Eye Gaze".into(), 0);
+    set.new_action(
+        ACTION,
+        "Gaze Pose".into(),
+        ActionType::PoseF,
+        ActionHandednes::Single,
+    );
+    set.suggest_binding(
+        EYE_GAZE_PROFILE,
+        &[XrBinding::new(ACTION, EYE_GAZE_POSE_PATH)],
+    );
+}
+
+fn create_eye_gaze_space(
+    mut commands: Commands,
+    action_sets: Res<XrActionSets>,
+    session: Res<XrSession>,
+) {
+    let Ok(action) = action_sets.get_action_posef(ACTION_SET, ACTION) else {
+        return;
+    };
+    match action.create_space(
+        xr::Session::clone(&session),
+        xr::Path::NULL,
+        xr::Posef::IDENTITY,
+    ) {
+        Ok(space) => {
+            commands.insert_resource(XrEyeGazeSpace(space));
+            commands.insert_resource(XrEyeGaze::default());
+        }
+        Err(err) => warn!("Unable to create the eye gaze space: {}", err),
+    }
+}
+
+fn update_eye_gaze(
+    space: Option<Res<XrEyeGazeSpace>>,
+    gaze: Option<ResMut<XrEyeGaze>>,
+    instance: Res<XrInstance>,
+    input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+    root: Query<&GlobalTransform, With<OpenXRTrackingRoot>>,
+) {
+    let (Some(space), Some(mut gaze)) = (space, gaze) else {
+        return;
+    };
+    let mut sample_time = xr::sys::EyeGazeSampleTimeEXT {
+        ty: xr::sys::EyeGazeSampleTimeEXT::TYPE,
+        next: ptr::null_mut(),
+        time: xr::Time::from_nanos(0),
+    };
+    let mut location = xr::sys::SpaceLocation {
+        ty: xr::sys::SpaceLocation::TYPE,
+        next: &mut sample_time as *mut _ as _,
+        location_flags: xr::SpaceLocationFlags::EMPTY,
+        pose: xr::Posef::IDENTITY,
+    };
+    let result = unsafe {
+        (instance.fp().locate_space)(
+            space.0.as_raw(),
+            input.stage.as_raw(),
+            frame_state.predicted_display_time,
+            &mut location,
+        )
+    };
+    if result.into_raw() < 0 {
+        warn!("Unable to locate the eye gaze: {}", result);
+        gaze.valid = false;
+        return;
+    }
+    gaze.valid = location.location_flags.contains(
+        xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
+    );
+    if !gaze.valid {
+        return;
+    }
+    let local = Transform {
+        translation: location.pose.position.to_vec3(),
+        rotation: location.pose.orientation.to_quat(),
+        ..default()
+    };
+    gaze.transform = match root.get_single() {
+        Ok(root) => root.mul_transform(local).compute_transform(),
+        Err(_) => local,
+    };
+    gaze.sample_time = Some(sample_time.time.into());
+}
+
+fn cleanup_eye_gaze(mut commands: Commands) {
+    commands.remove_resource::<XrEyeGazeSpace>();
+    commands.remove_resource::<XrEyeGaze>();
+}
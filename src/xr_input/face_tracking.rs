@@ -0,0 +1,373 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::{
+    resources::{XrFrameState, XrInstance, XrSession, XrTime},
+    system_properties::XrSystemProperties,
+    xr_init::{xr_only, XrCleanup, XrPostSetup},
+};
+
+use super::XrTrackingUpdate;
+
+/// Updates [`XrFaceExpressions`] every frame with `XR_FB_face_tracking`, see
+/// [`XrExtensions::enable_face_tracking`](crate::graphics::extensions::XrExtensions::enable_face_tracking).
+/// Nothing is inserted if the system can't track faces.
+pub struct XrFaceTrackingPlugin;
+
+impl Plugin for XrFaceTrackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(XrPostSetup, create_face_tracker);
+        app.add_systems(
+            PreUpdate,
+            update_face_expressions
+                .in_set(XrTrackingUpdate)
+                .run_if(xr_only()),
+        );
+        app.add_systems(XrCleanup, cleanup_face_tracker);
+    }
+}
+
+/// The blendshape weights of the user's face as of the predicted display time
+#[derive(Resource, Clone, Debug)]
+pub struct XrFaceExpressions {
+    /// Between 0.0 and 1.0, indexed by [`FaceExpression`]
+    pub weights: [f32; FaceExpression::COUNT],
+    /// How sure the runtime is about the weights of each [`FaceRegion`]
+    pub confidences: [f32; 2],
+    /// Whether the face was tracked this frame, the weights keep their last values otherwise
+    pub valid: bool,
+    /// Whether the eye blendshapes follow the eyes, they are only estimated otherwise
+    pub eye_following_valid: bool,
+    pub time: XrTime,
+}
+
+impl Default for XrFaceExpressions {
+    fn default() -> Self {
+        Self {
+            weights: [0.0; FaceExpression::COUNT],
+            confidences: [0.0; 2],
+            valid: false,
+            eye_following_valid: false,
+            time: default(),
+        }
+    }
+}
+
+impl XrFaceExpressions {
+    pub fn weight(&self, expression: FaceExpression) -> f32 {
+        self.weights[expression as usize]
+    }
+    pub fn confidence(&self, region: FaceRegion) -> f32 {
+        self.confidences[region as usize]
+    }
+}
+
+/// The regions [`XrFaceExpressions::confidences`] are reported for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FaceRegion {
+    LowerFace,
+    UpperFace,
+}
+
+/// The blendshapes of `XR_FB_face_tracking`, `L` and `R` are the user's left and right,
+/// `T` and `B` the top and bottom
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FaceExpression {
+    BrowLowererL,
+    BrowLowererR,
+    CheekPuffL,
+    CheekPuffR,
+    CheekRaiserL,
+    CheekRaiserR,
+    CheekSuckL,
+    CheekSuckR,
+    ChinRaiserB,
+    ChinRaiserT,
+    DimplerL,
+    DimplerR,
+    EyesClosedL,
+    EyesClosedR,
+    EyesLookDownL,
+    EyesLookDownR,
+    EyesLookLeftL,
+    EyesLookLeftR,
+    EyesLookRightL,
+    EyesLookRightR,
+    EyesLookUpL,
+    EyesLookUpR,
+    InnerBrowRaiserL,
+    InnerBrowRaiserR,
+    JawDrop,
+    JawSidewaysLeft,
+    JawSidewaysRight,
+    JawThrust,
+    LidTightenerL,
+    LidTightenerR,
+    LipCornerDepressorL,
+    LipCornerDepressorR,
+    LipCornerPullerL,
+    LipCornerPullerR,
+    LipFunnelerLB,
+    LipFunnelerLT,
+    LipFunnelerRB,
+    LipFunnelerRT,
+    LipPressorL,
+    LipPressorR,
+    LipPuckerL,
+    LipPuckerR,
+    LipStretcherL,
+    LipStretcherR,
+    LipSuckLB,
+    LipSuckLT,
+    LipSuckRB,
+    LipSuckRT,
+    LipTightenerL,
+    LipTightenerR,
+    LipsToward,
+    LowerLipDepressorL,
+    LowerLipDepressorR,
+    MouthLeft,
+    MouthRight,
+    NoseWrinklerL,
+    NoseWrinklerR,
+    OuterBrowRaiserL,
+    OuterBrowRaiserR,
+    UpperLidRaiserL,
+    UpperLidRaiserR,
+    UpperLipRaiserL,
+    UpperLipRaiserR,
+}
+
+impl FaceExpression {
+    pub const COUNT: usize = 63;
+    pub const ALL: [Self; Self::COUNT] = [
+        Self::BrowLowererL,
+        Self::BrowLowererR,
+        Self::CheekPuffL,
+        Self::CheekPuffR,
+        Self::CheekRaiserL,
+        Self::CheekRaiserR,
+        Self::CheekSuckL,
+        Self::CheekSuckR,
+        Self::ChinRaiserB,
+        Self::ChinRaiserT,
+        Self::DimplerL,
+        Self::DimplerR,
+        Self::EyesClosedL,
+        Self::EyesClosedR,
+        Self::EyesLookDownL,
+        Self::EyesLookDownR,
+        Self::EyesLookLeftL,
+        Self::EyesLookLeftR,
+        Self::EyesLookRightL,
+        Self::EyesLookRightR,
+        Self::EyesLookUpL,
+        Self::EyesLookUpR,
+        Self::InnerBrowRaiserL,
+        Self::InnerBrowRaiserR,
+        Self::JawDrop,
+        Self::JawSidewaysLeft,
+        Self::JawSidewaysRight,
+        Self::JawThrust,
+        Self::LidTightenerL,
+        Self::LidTightenerR,
+        Self::LipCornerDepressorL,
+        Self::LipCornerDepressorR,
+        Self::LipCornerPullerL,
+        Self::LipCornerPullerR,
+        Self::LipFunnelerLB,
+        Self::LipFunnelerLT,
+        Self::LipFunnelerRB,
+        Self::LipFunnelerRT,
+        Self::LipPressorL,
+        Self::LipPressorR,
+        Self::LipPuckerL,
+        Self::LipPuckerR,
+        Self::LipStretcherL,
+        Self::LipStretcherR,
+        Self::LipSuckLB,
+        Self::LipSuckLT,
+        Self::LipSuckRB,
+        Self::LipSuckRT,
+        Self::LipTightenerL,
+        Self::LipTightenerR,
+        Self::LipsToward,
+        Self::LowerLipDepressorL,
+        Self::LowerLipDepressorR,
+        Self::MouthLeft,
+        Self::MouthRight,
+        Self::NoseWrinklerL,
+        Self::NoseWrinklerR,
+        Self::OuterBrowRaiserL,
+        Self::OuterBrowRaiserR,
+        Self::UpperLidRaiserL,
+        Self::UpperLidRaiserR,
+        Self::UpperLipRaiserL,
+        Self::UpperLipRaiserR,
+    ];
+
+    /// The name of the blendshape in the extension, like `jaw_drop`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::BrowLowererL => "brow_lowerer_l",
+            Self::BrowLowererR => "brow_lowerer_r",
+            Self::CheekPuffL => "cheek_puff_l",
+            Self::CheekPuffR => "cheek_puff_r",
+            Self::CheekRaiserL => "cheek_raiser_l",
+            Self::CheekRaiserR => "cheek_raiser_r",
+            Self::CheekSuckL => "cheek_suck_l",
+            Self::CheekSuckR => "cheek_suck_r",
+            Self::ChinRaiserB => "chin_raiser_b",
+            Self::ChinRaiserT => "chin_raiser_t",
+            Self::DimplerL => "dimpler_l",
+            Self::DimplerR => "dimpler_r",
+            Self::EyesClosedL => "eyes_closed_l",
+            Self::EyesClosedR => "eyes_closed_r",
+            Self::EyesLookDownL => "eyes_look_down_l",
+            Self::EyesLookDownR => "eyes_look_down_r",
+            Self::EyesLookLeftL => "eyes_look_left_l",
+            Self::EyesLookLeftR => "eyes_look_left_r",
+            Self::EyesLookRightL => "eyes_look_right_l",
+            Self::EyesLookRightR => "eyes_look_right_r",
+            Self::EyesLookUpL => "eyes_look_up_l",
+            Self::EyesLookUpR => "eyes_look_up_r",
+            Self::InnerBrowRaiserL => "inner_brow_raiser_l",
+            Self::InnerBrowRaiserR => "inner_brow_raiser_r",
+            Self::JawDrop => "jaw_drop",
+            Self::JawSidewaysLeft => "jaw_sideways_left",
+            Self::JawSidewaysRight => "jaw_sideways_right",
+            Self::JawThrust => "jaw_thrust",
+            Self::LidTightenerL => "lid_tightener_l",
+            Self::LidTightenerR => "lid_tightener_r",
+            Self::LipCornerDepressorL => "lip_corner_depressor_l",
+            Self::LipCornerDepressorR => "lip_corner_depressor_r",
+            Self::LipCornerPullerL => "lip_corner_puller_l",
+            Self::LipCornerPullerR => "lip_corner_puller_r",
+            Self::LipFunnelerLB => "lip_funneler_lb",
+            Self::LipFunnelerLT => "lip_funneler_lt",
+            Self::LipFunnelerRB => "lip_funneler_rb",
+            Self::LipFunnelerRT => "lip_funneler_rt",
+            Self::LipPressorL => "lip_pressor_l",
+            Self::LipPressorR => "lip_pressor_r",
+            Self::LipPuckerL => "lip_pucker_l",
+            Self::LipPuckerR => "lip_pucker_r",
+            Self::LipStretcherL => "lip_stretcher_l",
+            Self::LipStretcherR => "lip_stretcher_r",
+            Self::LipSuckLB => "lip_suck_lb",
+            Self::LipSuckLT => "lip_suck_lt",
+            Self::LipSuckRB => "lip_suck_rb",
+            Self::LipSuckRT => "lip_suck_rt",
+            Self::LipTightenerL => "lip_tightener_l",
+            Self::LipTightenerR => "lip_tightener_r",
+            Self::LipsToward => "lips_toward",
+            Self::LowerLipDepressorL => "lower_lip_depressor_l",
+            Self::LowerLipDepressorR => "lower_lip_depressor_r",
+            Self::MouthLeft => "mouth_left",
+            Self::MouthRight => "mouth_right",
+            Self::NoseWrinklerL => "nose_wrinkler_l",
+            Self::NoseWrinklerR => "nose_wrinkler_r",
+            Self::OuterBrowRaiserL => "outer_brow_raiser_l",
+            Self::OuterBrowRaiserR => "outer_brow_raiser_r",
+            Self::UpperLidRaiserL => "upper_lid_raiser_l",
+            Self::UpperLidRaiserR => "upper_lid_raiser_r",
+            Self::UpperLipRaiserL => "upper_lip_raiser_l",
+            Self::UpperLipRaiserR => "upper_lip_raiser_r",
+        }
+    }
+}
+
+#[derive(Resource)]
+struct XrFaceTracker {
+    handle: xr::sys::FaceTrackerFB,
+    get_weights: xr::sys::pfn::GetFaceExpressionWeightsFB,
+    destroy: xr::sys::pfn::DestroyFaceTrackerFB,
+}
+
+impl Drop for XrFaceTracker {
+    fn drop(&mut self) {
+        unsafe {
+            (self.destroy)(self.handle);
+        }
+    }
+}
+
+fn create_face_tracker(
+    mut commands: Commands,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    system_properties: Option<Res<XrSystemProperties>>,
+) {
+    let Some(ext) = instance.exts().fb_face_tracking else {
+        return;
+    };
+    if !system_properties.is_some_and(|properties| properties.face_tracking) {
+        return;
+    }
+    let info = xr::sys::FaceTrackerCreateInfoFB {
+        ty: xr::sys::FaceTrackerCreateInfoFB::TYPE,
+        next: ptr::null(),
+        face_expression_set: xr::sys::FaceExpressionSetFB::DEFAULT,
+    };
+    let mut handle = xr::sys::FaceTrackerFB::NULL;
+    let result = unsafe { (ext.create_face_tracker)(session.as_raw(), &info, &mut handle) };
+    if result.into_raw() < 0 {
+        warn!("Unable to create the face tracker: {}", result);
+        return;
+    }
+    commands.insert_resource(XrFaceTracker {
+        handle,
+        get_weights: ext.get_face_expression_weights,
+        destroy: ext.destroy_face_tracker,
+    });
+    commands.insert_resource(XrFaceExpressions::default());
+}
+
+fn update_face_expressions(
+    tracker: Option<Res<XrFaceTracker>>,
+    expressions: Option<ResMut<XrFaceExpressions>>,
+    frame_state: Res<XrFrameState>,
+) {
+    let (Some(tracker), Some(mut expressions)) = (tracker, expressions) else {
+        return;
+    };
+    let info = xr::sys::FaceExpressionInfoFB {
+        ty: xr::sys::FaceExpressionInfoFB::TYPE,
+        next: ptr::null(),
+        time: frame_state.predicted_display_time,
+    };
+    let mut weights = [0.0; FaceExpression::COUNT];
+    let mut confidences = [0.0; 2];
+    let mut output = xr::sys::FaceExpressionWeightsFB {
+        ty: xr::sys::FaceExpressionWeightsFB::TYPE,
+        next: ptr::null_mut(),
+        weight_count: weights.len() as u32,
+        weights: weights.as_mut_ptr(),
+        confidence_count: confidences.len() as u32,
+        confidences: confidences.as_mut_ptr(),
+        status: xr::sys::FaceExpressionStatusFB {
+            is_valid: false.into(),
+            is_eye_following_blendshapes_valid: false.into(),
+        },
+        time: xr::Time::from_nanos(0),
+    };
+    let result = unsafe { (tracker.get_weights)(tracker.handle, &info, &mut output) };
+    if result.into_raw() < 0 {
+        warn!("Unable to get the face expressions: {}", result);
+        expressions.valid = false;
+        return;
+    }
+    expressions.valid = output.status.is_valid.into();
+    expressions.eye_following_valid = output.status.is_eye_following_blendshapes_valid.into();
+    if expressions.valid {
+        expressions.weights = weights;
+        expressions.confidences = confidences;
+        expressions.time = output.time.into();
+    }
+}
+
+fn cleanup_face_tracker(mut commands: Commands) {
+    commands.remove_resource::<XrFaceTracker>();
+    commands.remove_resource::<XrFaceExpressions>();
+}
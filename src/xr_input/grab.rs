@@ -0,0 +1,578 @@
+//! Grabbing [`XrGrabbable`] entities with the controllers, up close at the [`XrGripPose`] or
+//! from a distance along the [`XrAimPose`]. See the `distance_grab` module of the demo for
+//! throwing them with a physics engine.
+
+use std::time::Duration;
+
+use bevy::ecs::query::ROQueryItem;
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::time::Stopwatch;
+use bevy::transform::helper::TransformHelper;
+use bevy::transform::TransformSystem;
+
+use crate::resources::XrSession;
+use crate::xr_init::{xr_only, XrSetup};
+
+use super::actions::{
+    ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrActionSync, XrBinding, XrButton,
+};
+use super::eye_gaze::XrEyeGaze;
+use super::pointer::{intersect, XrPointerTarget};
+use super::pose_history::XrPoseHistory;
+use super::trackers::{OpenXRTrackingRoot, XrAimPose, XrGripPose, XrVelocity};
+use super::{Hand, XrTrackingUpdate};
+
+const ACTION_SET: &str = "xr_grab";
+const ACTION: &str = "grab";
+/// How far back the release velocity is fitted
+const RELEASE_WINDOW: Duration = Duration::from_millis(80);
+
+/// Adds the `grab` action and moves the grabbed entities. Touching an entity wins over pointing
+/// at one, when both hands pick the same entity in a frame the better candidate wins, the left
+/// hand on a tie, and the other hand takes its next best one. Grabbing an entity held by the
+/// other hand takes it over.
+pub struct XrGrabPlugin;
+
+impl Plugin for XrGrabPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrGrabSettings>();
+        app.init_resource::<XrGrabbers>();
+        app.register_type::<XrGrabbable>();
+        app.add_event::<GrabStart>();
+        app.add_event::<GrabEnd>();
+        app.add_event::<Hover>();
+        app.add_systems(XrSetup, setup_grab_action);
+        app.add_systems(
+            PreUpdate,
+            update_grabs
+                .in_set(XrGrabUpdate)
+                .after(XrActionSync)
+                .after(XrTrackingUpdate)
+                .run_if(xr_only()),
+        );
+        app.add_systems(
+            PostUpdate,
+            move_grabbed
+                .run_if(xr_only())
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Where grabs start and end and their events are sent
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct XrGrabUpdate;
+
+/// How distance candidates are found
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum XrGrabSelection {
+    /// The aim ray has to hit the [`XrPointerTarget`] of the entity, entities without one
+    /// can only be touched
+    Ray,
+    /// The center of the entity has to be within `max_angle` radians of the aim ray
+    Cone { max_angle: f32 },
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct XrGrabSettings {
+    /// A bool action read for each hand, the default one is bound to the grips of the
+    /// controllers
+    pub action: XrButton,
+    /// How far from the grip pose an entity counts as touched, in meters
+    pub touch_radius: f32,
+    pub selection: XrGrabSelection,
+    /// How far away entities can be grabbed, in meters
+    pub max_distance: f32,
+    /// Score of a distance candidate per radian between it and the aim ray, the lowest score
+    /// is grabbed
+    pub angle_weight: f32,
+    /// Score of a distance candidate per meter
+    pub distance_weight: f32,
+    /// Score of a distance candidate per radian between it and the eye gaze, only used while
+    /// [`XrEyeGaze`] is valid
+    pub gaze_weight: f32,
+    /// How long entities grabbed from a distance take to fly to the hand, `None` holds them
+    /// where they were grabbed
+    pub pull_duration: Option<Duration>,
+}
+
+impl Default for XrGrabSettings {
+    fn default() -> Self {
+        Self {
+            action: XrButton::new(ACTION_SET, ACTION),
+            touch_radius: 0.05,
+            selection: XrGrabSelection::Cone {
+                max_angle: 10f32.to_radians(),
+            },
+            max_distance: 5.0,
+            angle_weight: 1.0,
+            distance_weight: 0.05,
+            gaze_weight: 0.5,
+            pull_duration: Some(Duration::from_millis(250)),
+        }
+    }
+}
+
+/// An entity the hands can grab. Touching uses the [`Aabb`] of the entity if it has one.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct XrGrabbable {
+    /// Can be grabbed along the aim ray, otherwise it has to be touched
+    pub distance_grab: bool,
+    /// The size of the entity around its origin for touching it without an [`Aabb`], in meters
+    pub radius: f32,
+}
+
+impl Default for XrGrabbable {
+    fn default() -> Self {
+        Self {
+            distance_grab: true,
+            radius: 0.05,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum XrGrabKind {
+    /// Touched with the grip pose
+    Direct,
+    /// Picked along the aim pose
+    Distance,
+}
+
+/// An entity a hand would grab
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrGrabCandidate {
+    pub entity: Entity,
+    pub kind: XrGrabKind,
+    /// Meters from the grip for direct candidates, the weighted score for distance ones
+    pub score: f32,
+}
+
+/// Added to an entity while a hand holds it. It follows the grip pose with
+/// [`offset`](Self::offset) after [`XrGrabUpdate`], so physics should leave its transform
+/// alone until it's removed.
+#[derive(Component, Clone, Debug)]
+pub struct XrGrabbed {
+    pub hand: Hand,
+    pub kind: XrGrabKind,
+    /// The pose of the entity relative to the grip pose
+    pub offset: Transform,
+    pull: Option<(GlobalTransform, Stopwatch)>,
+}
+
+impl XrGrabbed {
+    /// The entity still flies to the hand
+    pub fn is_pulling(&self) -> bool {
+        self.pull.is_some()
+    }
+}
+
+/// What a hand hovers and holds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrGrabber {
+    pub pressed: bool,
+    /// The entity the hand would grab now, `None` while it holds one
+    pub hovered: Option<XrGrabCandidate>,
+    pub held: Option<Entity>,
+}
+
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrGrabbers {
+    left: XrGrabber,
+    right: XrGrabber,
+}
+
+impl XrGrabbers {
+    pub fn get(&self, hand: Hand) -> &XrGrabber {
+        match hand {
+            Hand::Left => &self.left,
+            Hand::Right => &self.right,
+        }
+    }
+
+    fn get_mut(&mut self, hand: Hand) -> &mut XrGrabber {
+        match hand {
+            Hand::Left => &mut self.left,
+            Hand::Right => &mut self.right,
+        }
+    }
+
+    /// The hand holding `entity`
+    pub fn holder(&self, entity: Entity) -> Option<Hand> {
+        [Hand::Left, Hand::Right]
+            .into_iter()
+            .find(|&hand| self.get(hand).held == Some(entity))
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct GrabStart {
+    pub hand: Hand,
+    pub entity: Entity,
+    pub kind: XrGrabKind,
+}
+
+/// A hand let go of an entity, or the other hand took it over. The velocities come from the
+/// [`XrPoseHistory`] of the controller if it has one.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct GrabEnd {
+    pub hand: Hand,
+    pub entity: Entity,
+    /// Velocity of the entity in world space, in meters per second
+    pub linear_velocity: Vec3,
+    /// In world space, in radians per second
+    pub angular_velocity: Vec3,
+}
+
+/// The candidate of a hand changed
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct Hover {
+    pub hand: Hand,
+    pub candidate: Option<XrGrabCandidate>,
+}
+
+fn setup_grab_action(mut action_sets: ResMut<SetupActionSets>) {
+    let set = action_sets.add_action_set(ACTION_SET, "Grab".into(), 0);
+    set.new_action(
+        ACTION,
+        "Grab".into(),
+        ActionType::Bool,
+        ActionHandednes::Double,
+    );
+    set.suggest_binding(
+        "/interaction_profiles/oculus/touch_controller",
+        &[
+            XrBinding::new(ACTION, "/user/hand/left/input/squeeze/value"),
+            XrBinding::new(ACTION, "/user/hand/right/input/squeeze/value"),
+        ],
+    );
+}
+
+/// Distance from `point` to the entity, zero inside of it
+fn touch_distance(
+    grabbable: &XrGrabbable,
+    transform: &GlobalTransform,
+    aabb: Option<&Aabb>,
+    point: Vec3,
+) -> f32 {
+    match aabb {
+        Some(aabb) => {
+            let local = transform.affine().inverse().transform_point3(point);
+            let closest = local.clamp(aabb.min().into(), aabb.max().into());
+            transform.transform_point(closest).distance(point)
+        }
+        None => (transform.translation().distance(point) - grabbable.radius).max(0.0),
+    }
+}
+
+fn center(transform: &GlobalTransform, aabb: Option<&Aabb>) -> Vec3 {
+    match aabb {
+        Some(aabb) => transform.transform_point(aabb.center.into()),
+        None => transform.translation(),
+    }
+}
+
+type GrabbableQuery = (
+    Entity,
+    &'static XrGrabbable,
+    &'static GlobalTransform,
+    Option<&'static Aabb>,
+    Option<&'static XrPointerTarget>,
+    Option<&'static InheritedVisibility>,
+);
+
+/// The candidates of a hand, best first
+fn find_candidates<'a>(
+    settings: &XrGrabSettings,
+    grip: &GlobalTransform,
+    aim: Option<&GlobalTransform>,
+    gaze: Option<Ray3d>,
+    grabbables: impl Iterator<Item = ROQueryItem<'a, GrabbableQuery>>,
+) -> Vec<XrGrabCandidate> {
+    let grip = grip.translation();
+    let mut candidates = grabbables
+        .filter(|(.., visibility)| visibility.map_or(true, |v| v.get()))
+        .filter_map(|(entity, grabbable, transform, aabb, target, _)| {
+            let touch = touch_distance(grabbable, transform, aabb, grip);
+            if touch <= settings.touch_radius {
+                return Some(XrGrabCandidate {
+                    entity,
+                    kind: XrGrabKind::Direct,
+                    score: touch,
+                });
+            }
+            let aim = aim.filter(|_| grabbable.distance_grab)?;
+            let origin = aim.translation();
+            let direction = aim.forward();
+            let center = center(transform, aabb);
+            let (distance, angle) = match settings.selection {
+                XrGrabSelection::Ray => {
+                    let inverse = transform.affine().inverse();
+                    let distance = intersect(
+                        target?,
+                        aabb,
+                        inverse.transform_point3(origin),
+                        inverse.transform_vector3(direction),
+                    )?;
+                    (distance, 0.0)
+                }
+                XrGrabSelection::Cone { max_angle } => {
+                    let to_center = center - origin;
+                    let angle = direction.angle_between(to_center);
+                    if to_center.length_squared() <= f32::EPSILON || angle > max_angle {
+                        return None;
+                    }
+                    (to_center.length(), angle)
+                }
+            };
+            if distance > settings.max_distance {
+                return None;
+            }
+            let gaze_angle = gaze.map_or(0.0, |gaze| {
+                gaze.direction.angle_between(center - gaze.origin)
+            });
+            Some(XrGrabCandidate {
+                entity,
+                kind: XrGrabKind::Distance,
+                score: angle * settings.angle_weight
+                    + distance * settings.distance_weight
+                    + gaze_angle * settings.gaze_weight,
+            })
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| {
+        a.kind
+            .cmp(&b.kind)
+            .then(a.score.total_cmp(&b.score))
+            .then(a.entity.cmp(&b.entity))
+    });
+    candidates
+}
+
+/// World space velocity of the controller holding `grip`, rotated out of the tracking root
+fn release_velocity(
+    history: Option<&XrPoseHistory>,
+    velocity: Option<&XrVelocity>,
+    root: Quat,
+) -> (Vec3, Vec3) {
+    let (linear, angular) = match (history, velocity) {
+        (Some(history), _) => history.release_velocity(RELEASE_WINDOW),
+        (None, Some(velocity)) => (velocity.linear, velocity.angular),
+        (None, None) => default(),
+    };
+    (root * Vec3::from(linear), root * Vec3::from(angular))
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn update_grabs(
+    mut commands: Commands,
+    settings: Res<XrGrabSettings>,
+    mut grabbers: ResMut<XrGrabbers>,
+    action_sets: Res<XrActionSets>,
+    session: Res<XrSession>,
+    gaze: Option<Res<XrEyeGaze>>,
+    grips: Query<(&XrGripPose, &GlobalTransform, Option<&Parent>)>,
+    aims: Query<(&XrAimPose, &GlobalTransform)>,
+    grabbables: Query<GrabbableQuery>,
+    controllers: Query<(Option<&XrPoseHistory>, Option<&XrVelocity>)>,
+    root: Query<&GlobalTransform, With<OpenXRTrackingRoot>>,
+    mut starts: EventWriter<GrabStart>,
+    mut ends: EventWriter<GrabEnd>,
+    mut hovers: EventWriter<Hover>,
+) {
+    const HANDS: [Hand; 2] = [Hand::Left, Hand::Right];
+    let grabbers = grabbers.as_mut();
+    let root = root
+        .get_single()
+        .map_or(Quat::IDENTITY, |root| root.compute_transform().rotation);
+    let grip = |hand: Hand| grips.iter().find(|(grip, ..)| grip.0 == hand);
+    let mut release = |grabbers: &mut XrGrabbers, hand: Hand, commands: &mut Commands| {
+        let Some(entity) = grabbers.get_mut(hand).held.take() else {
+            return;
+        };
+        // despawned entities are just forgotten
+        let Some(mut entity_commands) = commands.get_entity(entity) else {
+            return;
+        };
+        entity_commands.remove::<XrGrabbed>();
+        let (mut linear_velocity, mut angular_velocity) = (Vec3::ZERO, Vec3::ZERO);
+        if let Some((_, grip, parent)) = grip(hand) {
+            let (history, velocity) = parent
+                .and_then(|parent| controllers.get(parent.get()).ok())
+                .unwrap_or_default();
+            (linear_velocity, angular_velocity) = release_velocity(history, velocity, root);
+            // the entity swings around the grip
+            if let Ok((_, _, transform, ..)) = grabbables.get(entity) {
+                let arm = transform.translation() - grip.translation();
+                linear_velocity += angular_velocity.cross(arm);
+            }
+        }
+        ends.send(GrabEnd {
+            hand,
+            entity,
+            linear_velocity,
+            angular_velocity,
+        });
+    };
+
+    let mut just_pressed = [false; 2];
+    for (index, hand) in HANDS.into_iter().enumerate() {
+        let pressed = action_sets
+            .get_value_for::<bool>(
+                &session,
+                settings.action.action_set,
+                settings.action.action,
+                hand,
+            )
+            .unwrap_or(false);
+        let grabber = grabbers.get(hand);
+        just_pressed[index] = pressed && !grabber.pressed;
+        let lost = grabber.held.is_some_and(|held| !grabbables.contains(held));
+        if grabber.held.is_some() && (!pressed || lost) {
+            release(grabbers, hand, &mut commands);
+        }
+        grabbers.get_mut(hand).pressed = pressed;
+    }
+
+    let gaze = gaze.filter(|gaze| gaze.valid).map(|gaze| gaze.ray());
+    let candidates = HANDS.map(|hand| {
+        let grabber = grabbers.get(hand);
+        let Some((_, grip, _)) = grip(hand).filter(|_| grabber.held.is_none()) else {
+            return Vec::new();
+        };
+        let aim = aims
+            .iter()
+            .find(|(aim, _)| aim.0 == hand)
+            .map(|(_, transform)| transform);
+        find_candidates(&settings, grip, aim, gaze, grabbables.iter())
+    });
+
+    // the better candidate wins when both hands grab the same entity, the left hand on a tie
+    let mut grabs: [Option<XrGrabCandidate>; 2] = [None, None];
+    for (index, hand) in HANDS.into_iter().enumerate() {
+        if just_pressed[index] && grabbers.get(hand).held.is_none() {
+            grabs[index] = candidates[index].first().copied();
+        }
+    }
+    if let [Some(left), Some(right)] = grabs {
+        if left.entity == right.entity {
+            let right_wins = (right.kind, right.score) < (left.kind, left.score);
+            let loser = usize::from(!right_wins);
+            grabs[loser] = candidates[loser]
+                .iter()
+                .find(|candidate| candidate.entity != left.entity)
+                .copied();
+        }
+    }
+
+    for (index, hand) in HANDS.into_iter().enumerate() {
+        let Some(candidate) = grabs[index] else {
+            continue;
+        };
+        let (Some((_, grip, _)), Ok((_, _, transform, ..))) =
+            (grip(hand), grabbables.get(candidate.entity))
+        else {
+            continue;
+        };
+        if let Some(other) = grabbers.holder(candidate.entity) {
+            release(grabbers, other, &mut commands);
+        }
+        let pull = match (candidate.kind, settings.pull_duration) {
+            (XrGrabKind::Distance, Some(_)) => Some((*transform, Stopwatch::new())),
+            _ => None,
+        };
+        let mut offset = transform.reparented_to(grip);
+        if pull.is_some() {
+            offset.translation = Vec3::ZERO;
+        }
+        commands.entity(candidate.entity).insert(XrGrabbed {
+            hand,
+            kind: candidate.kind,
+            offset,
+            pull,
+        });
+        grabbers.get_mut(hand).held = Some(candidate.entity);
+        starts.send(GrabStart {
+            hand,
+            entity: candidate.entity,
+            kind: candidate.kind,
+        });
+    }
+
+    for (index, hand) in HANDS.into_iter().enumerate() {
+        let grabber = grabbers.get_mut(hand);
+        let hovered = match grabber.held {
+            Some(_) => None,
+            None => candidates[index].first().copied(),
+        };
+        let changed =
+            grabber.hovered.map(|c| (c.entity, c.kind)) != hovered.map(|c| (c.entity, c.kind));
+        grabber.hovered = hovered;
+        if changed {
+            hovers.send(Hover {
+                hand,
+                candidate: hovered,
+            });
+        }
+    }
+}
+
+/// Moves the grabbed entities to the grip poses, the grips are located before the transforms
+/// are propagated
+fn move_grabbed(
+    time: Res<Time>,
+    settings: Res<XrGrabSettings>,
+    grips: Query<(Entity, &XrGripPose)>,
+    mut grabbed: Query<(Entity, &mut XrGrabbed, Option<&Parent>)>,
+    mut transforms: ParamSet<(TransformHelper, Query<&mut Transform>)>,
+    mut targets: Local<Vec<(Entity, Transform)>>,
+) {
+    let duration = settings.pull_duration.unwrap_or_default();
+    for (entity, mut grabbed, parent) in &mut grabbed {
+        let Some((grip, _)) = grips.iter().find(|(_, grip)| grip.0 == grabbed.hand) else {
+            continue;
+        };
+        let helper = transforms.p0();
+        let Ok(grip) = helper.compute_global_transform(grip) else {
+            continue;
+        };
+        let mut target = grip.mul_transform(grabbed.offset);
+        if let Some((start, elapsed)) = &mut grabbed.pull {
+            elapsed.tick(time.delta());
+            let t = match duration.is_zero() {
+                true => 1.0,
+                false => (elapsed.elapsed_secs() / duration.as_secs_f32()).min(1.0),
+            };
+            if t < 1.0 {
+                let start = start.compute_transform();
+                let end = target.compute_transform();
+                // eases out, so the entity slows down before it reaches the hand
+                let t = 1.0 - (1.0 - t).powi(3);
+                target = Transform {
+                    translation: start.translation.lerp(end.translation, t),
+                    rotation: start.rotation.slerp(end.rotation, t),
+                    scale: start.scale.lerp(end.scale, t),
+                }
+                .into();
+            } else {
+                grabbed.pull = None;
+            }
+        }
+        let local = match parent {
+            Some(parent) => match helper.compute_global_transform(parent.get()) {
+                Ok(parent) => target.reparented_to(&parent),
+                Err(_) => continue,
+            },
+            None => target.compute_transform(),
+        };
+        targets.push((entity, local));
+    }
+    let mut transforms = transforms.p1();
+    for (entity, local) in targets.drain(..) {
+        if let Ok(mut transform) = transforms.get_mut(entity) {
+            *transform = local;
+        }
+    }
+}
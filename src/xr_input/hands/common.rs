@@ -9,7 +9,7 @@ use bevy::{
 
 use crate::xr_input::{trackers::OpenXRTracker, Hand};
 
-use super::{BoneTrackingStatus, HandBone};
+use super::{hand_tracking::TrackedHand, BoneTrackingStatus, HandBone};
 
 /// add debug renderer for controllers
 // #[derive(Default)]
@@ -162,6 +162,13 @@ pub fn spawn_hand_entities(mut commands: Commands) {
     //hand resource
     let mut hand_resource = HandsResource { ..default() };
     for hand in hands.iter() {
+        commands.spawn((
+            Name::new(format!("{:?} Hand", hand)),
+            SpatialBundle::default(),
+            OpenXRTracker,
+            *hand,
+            TrackedHand::default(),
+        ));
         for bone in bones.iter() {
             let boneid = commands
                 .spawn((
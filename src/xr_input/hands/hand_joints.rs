@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+
+use crate::xr_init::xr_only;
+use crate::xr_input::{trackers::OpenXRTrackingRoot, Hand, XrTrackingUpdate};
+
+use super::hand_tracking::{update_tracked_hands, HandJoints, TrackedHand};
+use super::HandBone;
+
+/// Spawns an entity with [`XrHandJoints`] below the [`OpenXRTrackingRoot`] the first time a hand
+/// is tracked, with one [`XrHandJoint`] child for each joint whose transform follows the joint.
+/// Parent colliders or effects to the joint entities to attach them to the hand.
+///
+/// The entities stay while the hand isn't tracked and are reused once it's tracked again.
+pub struct XrHandJointsPlugin;
+
+impl Plugin for XrHandJointsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            update_hand_joints
+                .after(update_tracked_hands)
+                .in_set(XrTrackingUpdate)
+                .run_if(xr_only()),
+        );
+    }
+}
+
+/// The parent of the joint entities of a hand, it keeps an identity transform
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrHandJoints {
+    pub hand: Hand,
+    /// `false` while the runtime doesn't track the hand, the joints keep their last pose then
+    pub tracked: bool,
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct XrHandJoint {
+    pub joint: HandBone,
+    /// Radius of the joint in meters
+    pub radius: f32,
+    /// `false` while the runtime doesn't know the pose of the joint, it keeps its last pose then
+    pub tracked: bool,
+}
+
+fn spawn_hand_joints(commands: &mut Commands, root: Entity, hand: Hand, joints: &HandJoints) {
+    commands.entity(root).with_children(|parent| {
+        parent
+            .spawn((
+                SpatialBundle::default(),
+                Name::new(format!("{:?} Hand", hand)),
+                XrHandJoints {
+                    hand,
+                    tracked: true,
+                },
+            ))
+            .with_children(|parent| {
+                for bone in HandBone::get_all_bones() {
+                    let joint = joints.get_joint(bone);
+                    parent.spawn((
+                        SpatialBundle::from_transform(Transform {
+                            translation: joint.position,
+                            rotation: joint.orientation,
+                            ..default()
+                        }),
+                        Name::new(format!("{:?} {:?}", hand, bone)),
+                        XrHandJoint {
+                            joint: bone,
+                            radius: joint.radius,
+                            tracked: joints.get_pose(bone).is_some(),
+                        },
+                    ));
+                }
+            });
+    });
+}
+
+fn update_hand_joints(
+    mut commands: Commands,
+    tracked_hands: Query<(&Hand, &TrackedHand)>,
+    root: Query<Entity, With<OpenXRTrackingRoot>>,
+    mut hands: Query<(&mut XrHandJoints, &Children)>,
+    mut joints: Query<(&mut XrHandJoint, &mut Transform)>,
+) {
+    for (&hand, tracked_hand) in &tracked_hands {
+        let existing = hands
+            .iter_mut()
+            .find(|(hand_joints, _)| hand_joints.hand == hand);
+        let Some((mut hand_joints, children)) = existing else {
+            // only spawned once the hand was tracked
+            if let (Some(data), Ok(root)) = (&tracked_hand.joints, root.get_single()) {
+                spawn_hand_joints(&mut commands, root, hand, data);
+            }
+            continue;
+        };
+        let tracked = tracked_hand.joints.is_some();
+        if hand_joints.tracked != tracked {
+            hand_joints.tracked = tracked;
+        }
+        let mut children = joints.iter_many_mut(children);
+        while let Some((mut joint, mut transform)) = children.fetch_next() {
+            let pose = tracked_hand
+                .joints
+                .as_ref()
+                .and_then(|data| data.get_pose(joint.joint));
+            let Some(pose) = pose else {
+                if joint.tracked {
+                    joint.tracked = false;
+                }
+                continue;
+            };
+            joint.set_if_neq(XrHandJoint {
+                joint: joint.joint,
+                radius: pose.radius,
+                tracked: true,
+            });
+            transform.translation = pose.position;
+            transform.rotation = pose.orientation;
+        }
+    }
+}
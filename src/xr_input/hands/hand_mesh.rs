@@ -0,0 +1,330 @@
+use std::{mem::MaybeUninit, ptr};
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{
+            skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+            Indices, VertexAttributeValues,
+        },
+        render_asset::RenderAssetUsages,
+        render_resource::PrimitiveTopology,
+    },
+};
+use openxr as xr;
+
+use crate::{
+    input::XrInput,
+    resources::{XrFrameState, XrInstance},
+    xr_init::{xr_only, XrCleanup, XrPostSetup},
+    xr_input::{Hand, QuatConv, Vec3Conv},
+};
+
+use super::{hand_tracking::HandTrackingData, HandBone};
+
+/// Spawns the skinned hand mesh of `XR_FB_hand_tracking_mesh` for both hands, skinned to the
+/// hand bone entities, enable the extension with
+/// [`XrExtensions::enable_hand_tracking_mesh`](crate::graphics::extensions::XrExtensions::enable_hand_tracking_mesh).
+/// Nothing is spawned if the runtime doesn't have the extension.
+pub struct HandMeshPlugin {
+    pub material: StandardMaterial,
+}
+
+impl Default for HandMeshPlugin {
+    fn default() -> Self {
+        Self {
+            material: StandardMaterial {
+                base_color: Color::rgb(0.8, 0.7, 0.6),
+                perceptual_roughness: 0.7,
+                ..default()
+            },
+        }
+    }
+}
+
+impl Plugin for HandMeshPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HandMeshMaterial(self.material.clone()));
+        app.add_systems(XrPostSetup, spawn_hand_meshes);
+        app.add_systems(XrCleanup, despawn_hand_meshes);
+        app.add_systems(PostUpdate, update_hand_mesh_scale.run_if(xr_only()));
+    }
+}
+
+#[derive(Resource)]
+struct HandMeshMaterial(StandardMaterial);
+
+/// The skinned mesh of a hand, the joints are the bone entities in [`HandBone::get_all_bones`] order
+#[derive(Component, Clone, Debug)]
+pub struct HandMesh {
+    /// The scale of the user's hand compared to the mesh, applied to the bind poses
+    pub scale: f32,
+    bind_poses: Vec<Mat4>,
+}
+
+/// The hand mesh as reported by the runtime, the joints are in [`HandBone::get_all_bones`] order
+#[derive(Clone, Debug, Default)]
+pub struct HandMeshData {
+    pub joint_bind_poses: Vec<Transform>,
+    pub joint_radii: Vec<f32>,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub blend_indices: Vec<[u16; 4]>,
+    pub blend_weights: Vec<[f32; 4]>,
+    pub indices: Vec<u16>,
+}
+
+impl HandMeshData {
+    pub fn to_mesh(&self) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions.clone())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals.clone())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs.clone())
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_JOINT_INDEX,
+            VertexAttributeValues::Uint16x4(self.blend_indices.clone()),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT, self.blend_weights.clone())
+        .with_inserted_indices(Indices::U16(self.indices.clone()))
+    }
+}
+
+impl HandTrackingData {
+    /// The hand mesh of `XR_FB_hand_tracking_mesh`, `None` without the extension
+    pub fn hand_mesh(&self, instance: &XrInstance, hand: Hand) -> xr::Result<Option<HandMeshData>> {
+        let Some(ext) = instance.exts().fb_hand_tracking_mesh else {
+            return Ok(None);
+        };
+        let tracker = self.tracker(hand).as_raw();
+        let mut mesh = xr::sys::HandTrackingMeshFB {
+            ty: xr::sys::HandTrackingMeshFB::TYPE,
+            next: ptr::null_mut(),
+            joint_capacity_input: 0,
+            joint_count_output: 0,
+            joint_bind_poses: ptr::null_mut(),
+            joint_radii: ptr::null_mut(),
+            joint_parents: ptr::null_mut(),
+            vertex_capacity_input: 0,
+            vertex_count_output: 0,
+            vertex_positions: ptr::null_mut(),
+            vertex_normals: ptr::null_mut(),
+            vertex_u_vs: ptr::null_mut(),
+            vertex_blend_indices: ptr::null_mut(),
+            vertex_blend_weights: ptr::null_mut(),
+            index_capacity_input: 0,
+            index_count_output: 0,
+            indices: ptr::null_mut(),
+        };
+        // the first call only reports the sizes
+        check(unsafe { (ext.get_hand_mesh)(tracker, &mut mesh) })?;
+        let joints = mesh.joint_count_output as usize;
+        let vertices = mesh.vertex_count_output as usize;
+        let index_count = mesh.index_count_output as usize;
+        let mut bind_poses = vec![xr::Posef::IDENTITY; joints];
+        let mut radii = vec![0.0; joints];
+        let mut parents = vec![xr::HandJointEXT::PALM; joints];
+        let mut positions = vec![xr::Vector3f::default(); vertices];
+        let mut normals = vec![xr::Vector3f::default(); vertices];
+        let mut uvs = vec![xr::Vector2f::default(); vertices];
+        let mut blend_indices = vec![
+            xr::sys::Vector4sFB {
+                x: 0,
+                y: 0,
+                z: 0,
+                w: 0
+            };
+            vertices
+        ];
+        let mut blend_weights = vec![xr::Vector4f::default(); vertices];
+        let mut indices = vec![0i16; index_count];
+        mesh.joint_capacity_input = joints as u32;
+        mesh.joint_bind_poses = bind_poses.as_mut_ptr();
+        mesh.joint_radii = radii.as_mut_ptr();
+        mesh.joint_parents = parents.as_mut_ptr();
+        mesh.vertex_capacity_input = vertices as u32;
+        mesh.vertex_positions = positions.as_mut_ptr();
+        mesh.vertex_normals = normals.as_mut_ptr();
+        mesh.vertex_u_vs = uvs.as_mut_ptr();
+        mesh.vertex_blend_indices = blend_indices.as_mut_ptr();
+        mesh.vertex_blend_weights = blend_weights.as_mut_ptr();
+        mesh.index_capacity_input = index_count as u32;
+        mesh.indices = indices.as_mut_ptr();
+        check(unsafe { (ext.get_hand_mesh)(tracker, &mut mesh) })?;
+        Ok(Some(HandMeshData {
+            joint_bind_poses: bind_poses
+                .iter()
+                .map(|pose| Transform {
+                    translation: pose.position.to_vec3(),
+                    rotation: pose.orientation.to_quat(),
+                    ..default()
+                })
+                .collect(),
+            joint_radii: radii,
+            positions: positions.iter().map(|v| [v.x, v.y, v.z]).collect(),
+            normals: normals.iter().map(|v| [v.x, v.y, v.z]).collect(),
+            uvs: uvs.iter().map(|v| [v.x, v.y]).collect(),
+            blend_indices: blend_indices
+                .iter()
+                .map(|v| [v.x as u16, v.y as u16, v.z as u16, v.w as u16])
+                .collect(),
+            blend_weights: blend_weights.iter().map(|v| [v.x, v.y, v.z, v.w]).collect(),
+            indices: indices.into_iter().map(|i| i as u16).collect(),
+        }))
+    }
+
+    /// The size of the user's hand compared to the hand mesh, `None` while the hand isn't tracked
+    pub fn hand_scale(
+        &self,
+        instance: &XrInstance,
+        input: &XrInput,
+        frame_state: &XrFrameState,
+        hand: Hand,
+    ) -> xr::Result<Option<f32>> {
+        let Some(ext) = instance.exts().ext_hand_tracking else {
+            return Ok(None);
+        };
+        let mut scale = xr::sys::HandTrackingScaleFB {
+            ty: xr::sys::HandTrackingScaleFB::TYPE,
+            next: ptr::null_mut(),
+            sensor_output: 1.0,
+            current_output: 1.0,
+            override_hand_scale: false.into(),
+            override_value_input: 1.0,
+        };
+        let mut joints = [MaybeUninit::<xr::sys::HandJointLocationEXT>::uninit();
+            xr::sys::HAND_JOINT_COUNT_EXT as usize];
+        let mut locations = xr::sys::HandJointLocationsEXT {
+            ty: xr::sys::HandJointLocationsEXT::TYPE,
+            next: &mut scale as *mut _ as _,
+            is_active: false.into(),
+            joint_count: xr::sys::HAND_JOINT_COUNT_EXT,
+            joint_locations: joints.as_mut_ptr() as _,
+        };
+        let info = xr::sys::HandJointsLocateInfoEXT {
+            ty: xr::sys::HandJointsLocateInfoEXT::TYPE,
+            next: ptr::null(),
+            base_space: input.stage.as_raw(),
+            time: frame_state.predicted_display_time,
+        };
+        check(unsafe {
+            (ext.locate_hand_joints)(self.tracker(hand).as_raw(), &info, &mut locations)
+        })?;
+        Ok(bool::from(locations.is_active).then_some(scale.current_output))
+    }
+}
+
+fn check(result: xr::sys::Result) -> xr::Result<()> {
+    match result.into_raw() < 0 {
+        true => Err(result),
+        false => Ok(()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_hand_meshes(
+    mut commands: Commands,
+    instance: Res<XrInstance>,
+    hand_tracking: Option<Res<HandTrackingData>>,
+    material: Res<HandMeshMaterial>,
+    bones: Query<(Entity, &Hand, &HandBone)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut inverse_bindposes: ResMut<Assets<SkinnedMeshInverseBindposes>>,
+) {
+    let Some(hand_tracking) = hand_tracking else {
+        return;
+    };
+    let material = materials.add(material.0.clone());
+    for hand in [Hand::Left, Hand::Right] {
+        let data = match hand_tracking.hand_mesh(&instance, hand) {
+            Ok(Some(data)) => data,
+            Ok(None) => return,
+            Err(err) => {
+                warn!("Unable to get the {:?} hand mesh: {}", hand, err);
+                continue;
+            }
+        };
+        let mut joints = vec![Entity::PLACEHOLDER; HandBone::get_all_bones().len()];
+        for (entity, bone_hand, bone) in &bones {
+            if *bone_hand == hand {
+                joints[bone.get_index_from_bone()] = entity;
+            }
+        }
+        if joints.contains(&Entity::PLACEHOLDER) || data.joint_bind_poses.len() != joints.len() {
+            warn!("The {:?} hand mesh doesn't match the hand bones", hand);
+            continue;
+        }
+        let bind_poses = data
+            .joint_bind_poses
+            .iter()
+            .map(|pose| pose.compute_matrix().inverse())
+            .collect::<Vec<_>>();
+        commands.spawn((
+            Name::new(format!("{:?} Hand Mesh", hand)),
+            PbrBundle {
+                mesh: meshes.add(data.to_mesh()),
+                material: material.clone(),
+                ..default()
+            },
+            SkinnedMesh {
+                inverse_bindposes: inverse_bindposes.add(bind_poses.clone()),
+                joints,
+            },
+            HandMesh {
+                scale: 1.0,
+                bind_poses,
+            },
+            hand,
+        ));
+    }
+}
+
+fn despawn_hand_meshes(mut commands: Commands, meshes: Query<Entity, With<HandMesh>>) {
+    for e in &meshes {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+fn update_hand_mesh_scale(
+    instance: Res<XrInstance>,
+    hand_tracking: Option<Res<HandTrackingData>>,
+    input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+    mut inverse_bindposes: ResMut<Assets<SkinnedMeshInverseBindposes>>,
+    mut meshes: Query<(&Hand, &mut HandMesh, &SkinnedMesh, &mut Visibility)>,
+) {
+    let Some(hand_tracking) = hand_tracking else {
+        return;
+    };
+    for (hand, mut mesh, skinned, mut visibility) in &mut meshes {
+        let scale = match hand_tracking.hand_scale(&instance, &input, &frame_state, *hand) {
+            Ok(scale) => scale,
+            Err(err) => {
+                warn!("Unable to get the {:?} hand scale: {}", hand, err);
+                None
+            }
+        };
+        *visibility = match scale.is_some() {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+        let Some(scale) = scale else {
+            continue;
+        };
+        if (mesh.scale - scale).abs() < 0.001 {
+            continue;
+        }
+        mesh.scale = scale;
+        // scaling the mesh around each joint keeps it matching the tracked joint positions
+        let scaled = mesh
+            .bind_poses
+            .iter()
+            .map(|inverse| Mat4::from_scale(Vec3::splat(scale)) * *inverse)
+            .collect::<Vec<_>>();
+        inverse_bindposes.insert(&skinned.inverse_bindposes, scaled.into());
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     input::XrInput,
     resources::{XrFrameState, XrSession},
     xr_init::xr_only,
-    xr_input::{hands::HandBone, Hand, QuatConv, Vec3Conv},
+    xr_input::{hands::HandBone, Hand, QuatConv, Vec3Conv, XrTrackingUpdate},
 };
 
 use super::BoneTrackingStatus;
@@ -34,6 +34,12 @@ impl HandTrackingData {
             right_hand: right,
         })
     }
+    pub fn tracker(&self, hand: Hand) -> &HandTracker {
+        match hand {
+            Hand::Left => &self.left_hand,
+            Hand::Right => &self.right_hand,
+        }
+    }
     pub fn get_ref<'a>(
         &'a self,
         input: &'a XrInput,
@@ -52,7 +58,7 @@ pub struct HandTrackingRef<'a> {
     input: &'a XrInput,
     frame_state: &'a XrFrameState,
 }
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct HandJoint {
     pub position: Vec3,
     pub position_valid: bool,
@@ -63,7 +69,7 @@ pub struct HandJoint {
     pub radius: f32,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct HandJoints {
     inner: [HandJoint; 26],
 }
@@ -77,6 +83,19 @@ impl HandJoints {
     pub fn get_joint(&self, bone: HandBone) -> &HandJoint {
         &self.inner[bone.get_index_from_bone()]
     }
+    /// The joint if the runtime knows both its position and orientation this frame
+    pub fn get_pose(&self, bone: HandBone) -> Option<&HandJoint> {
+        let joint = self.get_joint(bone);
+        (joint.position_valid && joint.orientation_valid).then_some(joint)
+    }
+}
+
+/// All joints of a hand, on an entity with the [`Hand`] next to the bone entities.
+/// `None` while the hand isn't tracked, like when the user holds controllers,
+/// the bones are emulated from the controllers then.
+#[derive(Component, Clone, Debug, Default)]
+pub struct TrackedHand {
+    pub joints: Option<HandJoints>,
 }
 
 impl<'a> HandTrackingRef<'a> {
@@ -130,7 +149,9 @@ impl Plugin for HandTrackingPlugin {
                     })
                     .run_if(xr_only()),
                 update_tracking_state_on_disable,
-            ),
+                update_tracked_hands.run_if(xr_only()),
+            )
+                .in_set(XrTrackingUpdate),
         );
     }
 }
@@ -154,6 +175,29 @@ fn update_tracking_state_on_disable(
         .is_some_and(|t| **t == DisableHandTracking::Both);
 }
 
+pub fn update_tracked_hands(
+    disabled_tracking: Option<Res<DisableHandTracking>>,
+    hand_tracking: Option<Res<HandTrackingData>>,
+    xr_input: Res<XrInput>,
+    xr_frame_state: Res<XrFrameState>,
+    mut hands: Query<(&Hand, &mut TrackedHand)>,
+) {
+    for (hand, mut tracked) in &mut hands {
+        let disabled = match disabled_tracking.as_deref() {
+            Some(DisableHandTracking::Both) => true,
+            Some(DisableHandTracking::OnlyLeft) => *hand == Hand::Left,
+            Some(DisableHandTracking::OnlyRight) => *hand == Hand::Right,
+            None => false,
+        };
+        tracked.joints = match (disabled, hand_tracking.as_ref()) {
+            (false, Some(tracking)) => tracking
+                .get_ref(&xr_input, &xr_frame_state)
+                .get_poses(*hand),
+            _ => None,
+        };
+    }
+}
+
 pub fn update_hand_bones(
     disabled_tracking: Option<Res<DisableHandTracking>>,
     hand_tracking: Option<Res<HandTrackingData>>,
@@ -8,13 +8,15 @@ use crate::{
 
 use self::{
     common::{spawn_hand_entities, HandBoneRadius, HandsResource},
-    hand_tracking::{DisableHandTracking, HandTrackingData},
+    hand_tracking::{DisableHandTracking, HandTrackingData, TrackedHand},
 };
 
 use super::{trackers::OpenXRTracker, Hand};
 
 pub mod common;
 pub mod emulated;
+pub mod hand_joints;
+pub mod hand_mesh;
 pub mod hand_tracking;
 
 pub struct HandPlugin;
@@ -23,7 +25,7 @@ impl Plugin for HandPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(XrPreSetup, check_for_handtracking);
         app.add_systems(XrSetup, spawn_hand_entities);
-        app.add_systems(XrCleanup, despawn_hand_entities);
+        app.add_systems(XrCleanup, (despawn_hand_entities, despawn_tracked_hands));
     }
 }
 
@@ -45,6 +47,12 @@ fn despawn_hand_entities(
     commands.remove_resource::<HandsResource>()
 }
 
+fn despawn_tracked_hands(mut commands: Commands, hands: Query<Entity, With<TrackedHand>>) {
+    for e in &hands {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
 fn check_for_handtracking(
     mut commands: Commands,
     instance: Res<XrInstance>,
@@ -68,7 +76,7 @@ pub enum BoneTrackingStatus {
     Tracked,
 }
 
-#[derive(Component, Debug, Clone, Copy)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HandBone {
     Palm,
     Wrist,
@@ -0,0 +1,123 @@
+use std::ptr;
+use std::time::Duration;
+
+use openxr as xr;
+
+use crate::resources::XrInstance;
+
+use super::actions::{ActionError, XrActionSets};
+
+/// What happened to the samples passed to [`XrActionSets::apply_pcm`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrPcmFeedback {
+    /// How many samples the runtime took, pass the rest with `append` in a later frame
+    pub samples_consumed: u32,
+    /// The runtime doesn't have `XR_FB_haptic_pcm`, the samples were approximated
+    /// with a single vibration and all of them count as consumed
+    pub emulated: bool,
+}
+
+impl XrActionSets {
+    /// Plays PCM samples between -1.0 and 1.0 on the controllers bound to a haptic action with
+    /// `XR_FB_haptic_pcm`, see
+    /// [`XrExtensions::enable_haptic_pcm`](crate::graphics::extensions::XrExtensions::enable_haptic_pcm).
+    /// With `append` the samples are queued after the ones still playing, so long effects can
+    /// be streamed in chunks using [`XrPcmFeedback::samples_consumed`].
+    /// Without the extension the samples become a vibration with their average amplitude
+    /// and the length they would have played for.
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply_pcm(
+        &self,
+        session: &xr::Session<xr::AnyGraphics>,
+        instance: &XrInstance,
+        action_set: &'static str,
+        action_name: &'static str,
+        subaction_path: xr::Path,
+        samples: &[f32],
+        sample_rate: f32,
+        append: bool,
+    ) -> Result<XrPcmFeedback, ActionError> {
+        let action = self.get_action_haptic(action_set, action_name)?;
+        if instance.exts().fb_haptic_pcm.is_none() {
+            let amplitude = match samples.is_empty() {
+                true => 0.0,
+                false => samples.iter().map(|s| s.abs()).sum::<f32>() / samples.len() as f32,
+            };
+            let duration = Duration::from_secs_f32(samples.len() as f32 / sample_rate.max(1.0));
+            self.apply_haptic(
+                session,
+                action_set,
+                action_name,
+                subaction_path,
+                amplitude,
+                None,
+                duration,
+            )?;
+            return Ok(XrPcmFeedback {
+                samples_consumed: samples.len() as u32,
+                emulated: true,
+            });
+        }
+        let info = xr::sys::HapticActionInfo {
+            ty: xr::sys::HapticActionInfo::TYPE,
+            next: ptr::null(),
+            action: action.as_raw(),
+            subaction_path,
+        };
+        let mut samples_consumed = 0;
+        let vibration = xr::sys::HapticPcmVibrationFB {
+            ty: xr::sys::HapticPcmVibrationFB::TYPE,
+            next: ptr::null(),
+            buffer_size: samples.len() as u32,
+            buffer: samples.as_ptr(),
+            sample_rate,
+            append: append.into(),
+            samples_consumed: &mut samples_consumed,
+        };
+        let result = unsafe {
+            (instance.fp().apply_haptic_feedback)(
+                session.as_raw(),
+                &info,
+                &vibration as *const _ as _,
+            )
+        };
+        if result.into_raw() < 0 {
+            return Err(result.into());
+        }
+        Ok(XrPcmFeedback {
+            samples_consumed,
+            emulated: false,
+        })
+    }
+    /// The sample rate the controller bound to a haptic action plays PCM samples at,
+    /// `None` without `XR_FB_haptic_pcm`
+    pub fn pcm_sample_rate(
+        &self,
+        session: &xr::Session<xr::AnyGraphics>,
+        instance: &XrInstance,
+        action_set: &'static str,
+        action_name: &'static str,
+        subaction_path: xr::Path,
+    ) -> Result<Option<f32>, ActionError> {
+        let action = self.get_action_haptic(action_set, action_name)?;
+        let Some(ext) = instance.exts().fb_haptic_pcm else {
+            return Ok(None);
+        };
+        let info = xr::sys::HapticActionInfo {
+            ty: xr::sys::HapticActionInfo::TYPE,
+            next: ptr::null(),
+            action: action.as_raw(),
+            subaction_path,
+        };
+        let mut state = xr::sys::DevicePcmSampleRateStateFB {
+            ty: xr::sys::DevicePcmSampleRateStateFB::TYPE,
+            next: ptr::null_mut(),
+            sample_rate: 0.0,
+        };
+        let result = unsafe { (ext.get_device_sample_rate)(session.as_raw(), &info, &mut state) };
+        if result.into_raw() < 0 {
+            return Err(result.into());
+        }
+        Ok(Some(state.sample_rate))
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::{
+    resources::XrSession,
+    xr_init::{xr_only, XrSessionState},
+};
+
+use super::{actions::XrActionSets, oculus_touch::subaction_path, Hand};
+
+/// Forwards [`XrHapticEvent`]s to the haptic action of the default controller bindings
+pub struct XrHapticsPlugin;
+
+impl Plugin for XrHapticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<XrHapticEvent>();
+        app.add_systems(PostUpdate, apply_hand_haptics.run_if(xr_only()));
+    }
+}
+
+/// Vibrates the controller in `hand`. Events for the same hand in one frame are combined into a
+/// single vibration with the highest amplitude and the longest duration, events sent while the
+/// session isn't focused are dropped.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct XrHapticEvent {
+    pub hand: Hand,
+    /// Between 0.0 and 1.0
+    pub amplitude: f32,
+    /// [`Duration::ZERO`] for the shortest vibration the runtime supports, like a click
+    pub duration: Duration,
+    /// In Hz, `None` lets the runtime pick
+    pub frequency: Option<f32>,
+}
+
+impl XrHapticEvent {
+    pub fn new(hand: Hand, amplitude: f32, duration: Duration) -> Self {
+        Self {
+            hand,
+            amplitude,
+            duration,
+            frequency: None,
+        }
+    }
+
+    /// Combines two vibrations for the same hand, the frequency is the one of the stronger one
+    fn coalesce(self, other: Self) -> Self {
+        let stronger = match other.amplitude > self.amplitude {
+            true => other,
+            false => self,
+        };
+        Self {
+            duration: self.duration.max(other.duration),
+            ..stronger
+        }
+    }
+}
+
+fn apply_hand_haptics(
+    mut events: EventReader<XrHapticEvent>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Option<Res<XrSession>>,
+    session_state: Res<XrSessionState>,
+) {
+    let (Some(action_sets), Some(session)) = (action_sets, session) else {
+        events.clear();
+        return;
+    };
+    if **session_state != xr::SessionState::FOCUSED {
+        events.clear();
+        return;
+    }
+    let mut left: Option<XrHapticEvent> = None;
+    let mut right: Option<XrHapticEvent> = None;
+    for event in events.read() {
+        let slot = match event.hand {
+            Hand::Left => &mut left,
+            Hand::Right => &mut right,
+        };
+        *slot = Some(match *slot {
+            Some(previous) => previous.coalesce(*event),
+            None => *event,
+        });
+    }
+    for event in [left, right].into_iter().flatten() {
+        if let Err(err) = action_sets.apply_haptic(
+            &session,
+            "oculus_input",
+            "haptic_feedback",
+            subaction_path(event.hand),
+            event.amplitude,
+            event.frequency,
+            event.duration,
+        ) {
+            warn!("Unable to vibrate the {:?} controller: {}", event.hand, err);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Swaps the input with what the player holds or sits in, by enabling the action set of an
+//! [`XrInputContext`] while an entity has it.
+
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use super::actions::XrActionSets;
+
+/// Enables [`action_set`](Self::action_set) with the next sync while the entity has this
+/// component. The set should be added with [`start_disabled`] and a priority above the sets
+/// binding the same inputs, as the priority is fixed when it's created. Of the contexts whose
+/// sets have the same priority only the one attached last is enabled, contexts attached in the
+/// same frame are ordered by their entity. Disabling a set releases its held actions, see
+/// [`XrActionSets::set_enabled`].
+///
+/// [`start_disabled`]: super::actions::SetupActionSet::start_disabled
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct XrInputContext {
+    pub action_set: &'static str,
+}
+
+impl XrInputContext {
+    pub fn new(action_set: &'static str) -> Self {
+        Self { action_set }
+    }
+}
+
+/// The [`XrInputContext`]s in the order they were attached and the action sets they enabled
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrInputContexts {
+    attached: Vec<(Entity, &'static str)>,
+    /// Every set named by a context so far, they are disabled without one
+    managed: HashSet<&'static str>,
+    active: Vec<&'static str>,
+}
+
+impl XrInputContexts {
+    /// The action sets enabled by contexts, by descending priority
+    pub fn active(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.active.iter().copied()
+    }
+    pub fn is_active(&self, action_set: &str) -> bool {
+        self.active.contains(&action_set)
+    }
+    /// The entities with a context, the one attached first first
+    pub fn attached(&self) -> impl Iterator<Item = (Entity, &'static str)> + '_ {
+        self.attached.iter().copied()
+    }
+}
+
+/// Enables the action sets of the attached contexts and disables the ones left behind,
+/// runs right before [`XrActionSync`](super::actions::XrActionSync)
+pub fn update_input_contexts(
+    mut contexts: ResMut<XrInputContexts>,
+    mut action_sets: ResMut<XrActionSets>,
+    query: Query<(Entity, &XrInputContext)>,
+) {
+    let contexts = &mut *contexts;
+    contexts
+        .attached
+        .retain(|&(entity, set)| query.get(entity).is_ok_and(|(_, c)| c.action_set == set));
+    let mut added = query
+        .iter()
+        .map(|(entity, context)| (entity, context.action_set))
+        .filter(|attached| !contexts.attached.contains(attached))
+        .collect::<Vec<_>>();
+    added.sort_by_key(|&(entity, _)| entity);
+    for &(entity, set) in &added {
+        match action_sets.priority(set) {
+            Ok(_) => {
+                contexts.managed.insert(set);
+            }
+            Err(err) => warn!("Input context of {:?} can't use {}: {}", entity, set, err),
+        }
+    }
+    contexts.attached.extend(added);
+
+    // the context attached last wins its priority
+    let mut winners: HashMap<u32, &'static str> = HashMap::new();
+    for &(_, set) in &contexts.attached {
+        if let Ok(priority) = action_sets.priority(set) {
+            winners.insert(priority, set);
+        }
+    }
+    let mut active = winners.into_iter().collect::<Vec<_>>();
+    active.sort_by(|a, b| b.cmp(a));
+    contexts.active = active.into_iter().map(|(_, set)| set).collect();
+
+    for &set in &contexts.managed {
+        let enabled = contexts.active.contains(&set);
+        if action_sets
+            .is_enabled(set)
+            .is_ok_and(|current| current != enabled)
+        {
+            let _ = action_sets.set_enabled(set, enabled);
+        }
+    }
+}
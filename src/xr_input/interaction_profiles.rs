@@ -0,0 +1,350 @@
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::resources::{XrInstance, XrSession};
+
+use super::actions::{ActionType, SetupActionSet, XrBinding};
+use super::Hand;
+
+/// Interaction profiles of common controllers, see [`SetupActionSet::suggest_default_bindings`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum XrInteractionProfile {
+    OculusTouch,
+    ValveIndex,
+    HtcVive,
+    MicrosoftMotion,
+    KhrSimple,
+    /// Any other profile, by its path
+    Other(String),
+}
+
+/// Sent when the runtime changed the interaction profile of a hand, like when the user switches
+/// controllers, query the new ones with [`XrSession::current_interaction_profile`]
+#[derive(Clone, Copy, Debug, Default, Event)]
+pub struct XrInteractionProfileChanged;
+
+/// Inputs most controllers have, used to suggest bindings for several interaction profiles at once
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum XrCommonInput {
+    GripPose,
+    AimPose,
+    /// Falls back to the grip pose without `XR_EXT_palm_pose`
+    PalmPose,
+    /// Float, the select button on controllers without a trigger
+    Trigger,
+    /// Float, a click on controllers with a grip button
+    Squeeze,
+    /// Vector2, the trackpad on controllers without a thumbstick
+    Thumbstick,
+    ThumbstickX,
+    ThumbstickY,
+    /// Bool, only on the left hand of controllers with a single menu button
+    Menu,
+    Haptic,
+}
+
+impl XrInteractionProfile {
+    /// The profiles [`SetupActionSet::suggest_default_bindings`] suggests bindings for
+    pub const ALL: [Self; 5] = [
+        Self::OculusTouch,
+        Self::ValveIndex,
+        Self::HtcVive,
+        Self::MicrosoftMotion,
+        Self::KhrSimple,
+    ];
+
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Other(path) => path,
+            profile => profile.known_path().unwrap(),
+        }
+    }
+
+    fn known_path(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::OculusTouch => "/interaction_profiles/oculus/touch_controller",
+            Self::ValveIndex => "/interaction_profiles/valve/index_controller",
+            Self::HtcVive => "/interaction_profiles/htc/vive_controller",
+            Self::MicrosoftMotion => "/interaction_profiles/microsoft/motion_controller",
+            Self::KhrSimple => "/interaction_profiles/khr/simple_controller",
+            Self::Other(_) => return None,
+        })
+    }
+
+    pub fn from_path(path: &str) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|profile| profile.path() == path)
+            .unwrap_or_else(|| Self::Other(path.to_owned()))
+    }
+
+    /// The binding paths of both hands for an input, empty if the profile doesn't have it
+    pub fn binding_paths(&self, input: XrCommonInput) -> &'static [&'static str] {
+        use XrCommonInput as I;
+        use XrInteractionProfile as P;
+        match (self, input) {
+            (P::Other(_), _) => &[],
+            (_, I::GripPose) => &[
+                "/user/hand/left/input/grip/pose",
+                "/user/hand/right/input/grip/pose",
+            ],
+            (_, I::AimPose) => &[
+                "/user/hand/left/input/aim/pose",
+                "/user/hand/right/input/aim/pose",
+            ],
+            (_, I::PalmPose) => &[
+                "/user/hand/left/input/palm_ext/pose",
+                "/user/hand/right/input/palm_ext/pose",
+            ],
+            (_, I::Haptic) => &[
+                "/user/hand/left/output/haptic",
+                "/user/hand/right/output/haptic",
+            ],
+            (P::KhrSimple, I::Trigger) => &[
+                "/user/hand/left/input/select/click",
+                "/user/hand/right/input/select/click",
+            ],
+            (_, I::Trigger) => &[
+                "/user/hand/left/input/trigger/value",
+                "/user/hand/right/input/trigger/value",
+            ],
+            (P::OculusTouch | P::ValveIndex, I::Squeeze) => &[
+                "/user/hand/left/input/squeeze/value",
+                "/user/hand/right/input/squeeze/value",
+            ],
+            (P::HtcVive | P::MicrosoftMotion, I::Squeeze) => &[
+                "/user/hand/left/input/squeeze/click",
+                "/user/hand/right/input/squeeze/click",
+            ],
+            (P::HtcVive, I::Thumbstick) => &[
+                "/user/hand/left/input/trackpad",
+                "/user/hand/right/input/trackpad",
+            ],
+            (P::HtcVive, I::ThumbstickX) => &[
+                "/user/hand/left/input/trackpad/x",
+                "/user/hand/right/input/trackpad/x",
+            ],
+            (P::HtcVive, I::ThumbstickY) => &[
+                "/user/hand/left/input/trackpad/y",
+                "/user/hand/right/input/trackpad/y",
+            ],
+            (P::KhrSimple, I::Thumbstick | I::ThumbstickX | I::ThumbstickY) => &[],
+            (_, I::Thumbstick) => &[
+                "/user/hand/left/input/thumbstick",
+                "/user/hand/right/input/thumbstick",
+            ],
+            (_, I::ThumbstickX) => &[
+                "/user/hand/left/input/thumbstick/x",
+                "/user/hand/right/input/thumbstick/x",
+            ],
+            (_, I::ThumbstickY) => &[
+                "/user/hand/left/input/thumbstick/y",
+                "/user/hand/right/input/thumbstick/y",
+            ],
+            (P::OculusTouch, I::Menu) => &["/user/hand/left/input/menu/click"],
+            // the index controller only has the system button, which is reserved for the runtime
+            (P::ValveIndex, I::Menu) => &[],
+            (_, I::Menu) => &[
+                "/user/hand/left/input/menu/click",
+                "/user/hand/right/input/menu/click",
+            ],
+            (_, I::Squeeze) => &[],
+        }
+    }
+}
+
+/// An input of an interaction profile that bool, float and vector2 actions can be bound to
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct XrProfileComponent {
+    /// Like `/user/hand/right/input/a/click`
+    pub path: String,
+    /// The type of action that reads the component directly,
+    /// bool and float components can be bound to either kind of action
+    pub action_type: ActionType,
+}
+
+impl XrProfileComponent {
+    pub fn hand(&self) -> Hand {
+        match self.path.starts_with("/user/hand/left/") {
+            true => Hand::Left,
+            false => Hand::Right,
+        }
+    }
+
+    /// Whether an action of `action_type` can be bound to the component
+    pub fn accepts(&self, action_type: ActionType) -> bool {
+        use ActionType as T;
+        match (self.action_type, action_type) {
+            (T::Bool | T::F32, T::Bool | T::F32) => true,
+            (component, action) => component == action,
+        }
+    }
+}
+
+impl XrInteractionProfile {
+    /// The buttons, triggers and sticks of both hands, without poses,
+    /// haptics and the system buttons that are reserved for the runtime
+    pub fn components(&self) -> Vec<XrProfileComponent> {
+        use ActionType::{Bool, Vec2, F32};
+        let (left, right): (&[_], &[_]) = match self {
+            Self::OculusTouch => (
+                &[
+                    ("x/click", Bool),
+                    ("x/touch", Bool),
+                    ("y/click", Bool),
+                    ("y/touch", Bool),
+                    ("menu/click", Bool),
+                    ("squeeze/value", F32),
+                    ("trigger/value", F32),
+                    ("trigger/touch", Bool),
+                    ("thumbstick", Vec2),
+                    ("thumbstick/click", Bool),
+                    ("thumbstick/touch", Bool),
+                    ("thumbrest/touch", Bool),
+                ],
+                &[
+                    ("a/click", Bool),
+                    ("a/touch", Bool),
+                    ("b/click", Bool),
+                    ("b/touch", Bool),
+                    ("squeeze/value", F32),
+                    ("trigger/value", F32),
+                    ("trigger/touch", Bool),
+                    ("thumbstick", Vec2),
+                    ("thumbstick/click", Bool),
+                    ("thumbstick/touch", Bool),
+                    ("thumbrest/touch", Bool),
+                ],
+            ),
+            Self::ValveIndex => {
+                const INDEX: &[(&str, ActionType)] = &[
+                    ("a/click", Bool),
+                    ("a/touch", Bool),
+                    ("b/click", Bool),
+                    ("b/touch", Bool),
+                    ("squeeze/value", F32),
+                    ("squeeze/force", F32),
+                    ("trigger/click", Bool),
+                    ("trigger/value", F32),
+                    ("trigger/touch", Bool),
+                    ("thumbstick", Vec2),
+                    ("thumbstick/click", Bool),
+                    ("thumbstick/touch", Bool),
+                    ("trackpad", Vec2),
+                    ("trackpad/force", F32),
+                    ("trackpad/touch", Bool),
+                ];
+                (INDEX, INDEX)
+            }
+            Self::HtcVive => {
+                const VIVE: &[(&str, ActionType)] = &[
+                    ("menu/click", Bool),
+                    ("squeeze/click", Bool),
+                    ("trigger/click", Bool),
+                    ("trigger/value", F32),
+                    ("trackpad", Vec2),
+                    ("trackpad/click", Bool),
+                    ("trackpad/touch", Bool),
+                ];
+                (VIVE, VIVE)
+            }
+            Self::MicrosoftMotion => {
+                const MOTION: &[(&str, ActionType)] = &[
+                    ("menu/click", Bool),
+                    ("squeeze/click", Bool),
+                    ("trigger/value", F32),
+                    ("thumbstick", Vec2),
+                    ("thumbstick/click", Bool),
+                    ("trackpad", Vec2),
+                    ("trackpad/click", Bool),
+                    ("trackpad/touch", Bool),
+                ];
+                (MOTION, MOTION)
+            }
+            Self::KhrSimple => {
+                const SIMPLE: &[(&str, ActionType)] =
+                    &[("select/click", Bool), ("menu/click", Bool)];
+                (SIMPLE, SIMPLE)
+            }
+            Self::Other(_) => (&[], &[]),
+        };
+        let hand = |user_path: &'static str, components: &'static [(&'static str, ActionType)]| {
+            components
+                .iter()
+                .map(move |(component, action_type)| XrProfileComponent {
+                    path: format!("{}/input/{}", user_path, component),
+                    action_type: *action_type,
+                })
+        };
+        hand("/user/hand/left", left)
+            .chain(hand("/user/hand/right", right))
+            .collect()
+    }
+}
+
+impl SetupActionSet {
+    /// Suggests bindings for every profile in [`XrInteractionProfile::ALL`],
+    /// inputs a profile doesn't have are left unbound for it
+    pub fn suggest_default_bindings(&mut self, bindings: &[(&'static str, XrCommonInput)]) {
+        for profile in XrInteractionProfile::ALL {
+            self.suggest_profile_bindings(profile, bindings);
+        }
+    }
+    /// Suggests bindings of common inputs for one profile
+    pub fn suggest_profile_bindings(
+        &mut self,
+        profile: XrInteractionProfile,
+        bindings: &[(&'static str, XrCommonInput)],
+    ) {
+        let profile_bindings = bindings
+            .iter()
+            .flat_map(|(action, input)| {
+                profile
+                    .binding_paths(*input)
+                    .iter()
+                    .map(|path| XrBinding::new(action, path))
+            })
+            .collect::<Vec<_>>();
+        match profile.known_path() {
+            Some(path) => self.suggest_binding(path, &profile_bindings),
+            None => warn!("No common bindings known for {}", profile.path()),
+        }
+    }
+}
+
+impl XrSession {
+    /// The interaction profile the runtime picked for a hand, `None` while nothing is bound to it.
+    /// Changes are reported with [`XrInteractionProfileChanged`].
+    pub fn current_interaction_profile(
+        &self,
+        instance: &XrInstance,
+        hand: Hand,
+    ) -> xr::Result<Option<XrInteractionProfile>> {
+        let user_path = instance.string_to_path(match hand {
+            Hand::Left => "/user/hand/left",
+            Hand::Right => "/user/hand/right",
+        })?;
+        let profile = (**self).current_interaction_profile(user_path)?;
+        if profile == xr::Path::NULL {
+            return Ok(None);
+        }
+        Ok(Some(XrInteractionProfile::from_path(
+            &instance.path_to_string(profile)?,
+        )))
+    }
+
+    /// The components of the current interaction profile of a hand that actions can be bound to,
+    /// empty while nothing is bound to the hand or the profile isn't a known one
+    pub fn assignable_components(
+        &self,
+        instance: &XrInstance,
+        hand: Hand,
+    ) -> xr::Result<Vec<XrProfileComponent>> {
+        let Some(profile) = self.current_interaction_profile(instance, hand)? else {
+            return Ok(Vec::new());
+        };
+        let mut components = profile.components();
+        components.retain(|component| component.hand() == hand);
+        Ok(components)
+    }
+}
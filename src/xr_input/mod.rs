@@ -1,19 +1,52 @@
+pub mod action_values;
 pub mod actions;
+#[cfg(feature = "binding-assets")]
+pub mod binding_assets;
+pub mod body_tracking;
+pub mod bound_sources;
+pub mod controller_models;
 pub mod controllers;
 pub mod debug_gizmos;
+pub mod dpad;
+pub mod eye_gaze;
+pub mod face_tracking;
+pub mod grab;
 pub mod hand_poses;
 pub mod hands;
+pub mod haptic_pcm;
+pub mod haptics;
+pub mod input_contexts;
+pub mod interaction_profiles;
 pub mod interactions;
 pub mod oculus_touch;
+pub mod palm_pose;
+pub mod paths;
+pub mod pointer;
+pub mod pose_history;
+pub mod processing;
 pub mod prototype_locomotion;
+#[cfg(feature = "binding-assets")]
+pub mod rebinding;
+pub mod relative_poses;
+#[cfg(feature = "binding-assets")]
+pub mod steamvr_manifest;
+pub mod tracked_controllers;
 pub mod trackers;
+pub mod tracking_loss;
+pub mod turning;
+pub mod vive_trackers;
 pub mod xr_camera;
 
+use crate::input::{
+    apply_prediction_offset, apply_reference_space_change, recenter_xr_space,
+    update_emulated_floor, RecenterXrSpace,
+};
 use crate::resources::{XrInstance, XrSession};
-use crate::xr_init::{xr_only, XrCleanup, XrPostSetup, XrPreSetup, XrSetup};
+use crate::xr_init::{
+    xr_only, XrCleanup, XrPollEvents, XrPostSetup, XrPreSetup, XrSetup, XrWaitFrame,
+};
 use crate::xr_input::oculus_touch::setup_oculus_controller;
 use crate::xr_input::xr_camera::{xr_camera_head_sync, Eye, XRProjection, XrCameraBundle};
-use crate::{locate_views, xr_wait_frame};
 use bevy::app::{App, PostUpdate, Startup};
 use bevy::ecs::entity::Entity;
 use bevy::ecs::query::With;
@@ -21,8 +54,9 @@ use bevy::ecs::system::Query;
 use bevy::hierarchy::DespawnRecursiveExt;
 use bevy::log::{info, warn};
 use bevy::math::Vec2;
-use bevy::prelude::{BuildChildren, Component, Deref, DerefMut, IntoSystemConfigs, Resource};
-use bevy::prelude::{Commands, Plugin, PreUpdate, Quat, Res, SpatialBundle, Update, Vec3};
+use bevy::prelude::{Commands, Plugin, PreUpdate, Quat, SpatialBundle, Vec3};
+use bevy::prelude::{Component, IntoSystemConfigs, SystemSet};
+use bevy::reflect::Reflect;
 use bevy::render::camera::CameraProjectionPlugin;
 use bevy::render::extract_component::ExtractComponentPlugin;
 use bevy::render::view::{update_frusta, VisibilitySystems};
@@ -34,15 +68,25 @@ use self::actions::{setup_oxr_actions, XrActionsPlugin};
 use self::oculus_touch::{
     init_subaction_path, post_action_setup_oculus_controller, ActionSets, OculusController,
 };
+use self::pose_history::record_pose_history;
 use self::trackers::{
-    adopt_open_xr_trackers, update_open_xr_controllers, OpenXRLeftEye, OpenXRRightEye,
-    OpenXRTrackingRoot,
+    adopt_open_xr_trackers, spawn_controller_poses, update_controller_aim_poses,
+    update_open_xr_controllers, update_open_xr_velocities, AimPose, OpenXRController, OpenXRHMD,
+    OpenXRLeftController, OpenXRLeftEye, OpenXRRightController, OpenXRRightEye, OpenXRTracker,
+    OpenXRTrackingRoot, TrackingConfidence, XrAimPose, XrGripPose, XrVelocity,
 };
+use self::tracking_loss::{update_tracking_confidence, Untracked, XrTrackingTimeout};
 use self::xr_camera::{/* GlobalTransformExtract, TransformExtract, */ XrCamera};
 
 #[derive(Copy, Clone)]
 pub struct XrInputPlugin;
-#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Component)]
+
+/// Where controllers, hands, faces and bodies are located, so systems after it see all of them
+/// at the same time, see [`xr_init::schedules`](crate::xr_init::schedules) for the frame order
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct XrTrackingUpdate;
+
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Component, Reflect)]
 pub enum Hand {
     Left,
     Right,
@@ -50,6 +94,23 @@ pub enum Hand {
 
 impl Plugin for XrInputPlugin {
     fn build(&self, app: &mut App) {
+        app.register_type::<Hand>()
+            .register_type::<OpenXRTrackingRoot>()
+            .register_type::<OpenXRTracker>()
+            .register_type::<OpenXRLeftEye>()
+            .register_type::<OpenXRRightEye>()
+            .register_type::<OpenXRHMD>()
+            .register_type::<OpenXRLeftController>()
+            .register_type::<OpenXRRightController>()
+            .register_type::<OpenXRController>()
+            .register_type::<AimPose>()
+            .register_type::<XrGripPose>()
+            .register_type::<XrAimPose>()
+            .register_type::<XrVelocity>()
+            .register_type::<TrackingConfidence>()
+            .register_type::<Untracked>()
+            .register_type::<XrTrackingTimeout>();
+        app.init_resource::<XrTrackingTimeout>();
         app.add_systems(XrPostSetup, post_action_setup_oculus_controller);
         app.add_systems(XrSetup, setup_oculus_controller);
         app.add_systems(XrCleanup, cleanup_oculus_controller);
@@ -57,10 +118,38 @@ impl Plugin for XrInputPlugin {
         app.add_systems(PreUpdate, adopt_open_xr_trackers.run_if(xr_only()));
         // app.add_systems(PreUpdate, action_set_system.run_if(xr_only()));
         //update controller trackers
-        app.add_systems(Update, update_open_xr_controllers.run_if(xr_only()));
+        app.add_systems(
+            PreUpdate,
+            (
+                (update_open_xr_controllers, update_tracking_confidence).chain(),
+                (spawn_controller_poses, update_controller_aim_poses).chain(),
+                update_open_xr_velocities,
+            )
+                .in_set(XrTrackingUpdate)
+                .run_if(xr_only()),
+        );
+        app.add_systems(
+            PreUpdate,
+            record_pose_history
+                .after(XrTrackingUpdate)
+                .run_if(xr_only()),
+        );
         app.add_systems(XrPreSetup, init_subaction_path);
         app.add_systems(XrSetup, setup_xr_root);
         app.add_systems(XrCleanup, cleanup_xr_root);
+        app.add_event::<RecenterXrSpace>();
+        app.add_systems(
+            PreUpdate,
+            (
+                recenter_xr_space,
+                apply_reference_space_change,
+                update_emulated_floor.after(apply_reference_space_change),
+                apply_prediction_offset,
+            )
+                .run_if(xr_only())
+                .after(XrPollEvents)
+                .before(XrWaitFrame),
+        );
     }
 }
 
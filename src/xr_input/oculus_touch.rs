@@ -1,5 +1,5 @@
 use crate::input::XrInput;
-use crate::resources::{XrInstance, XrSession};
+use crate::resources::{XrInstance, XrSession, XrTime};
 use crate::xr_input::controllers::Handed;
 use crate::xr_input::Hand;
 use bevy::prelude::{default, Commands, Res, ResMut, Resource};
@@ -11,6 +11,8 @@ use openxr::{
 use std::sync::OnceLock;
 
 use super::actions::{ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrBinding};
+use super::interaction_profiles::{XrCommonInput, XrInteractionProfile};
+use super::trackers::XrSpaceState;
 
 pub fn post_action_setup_oculus_controller(
     action_sets: Res<XrActionSets>,
@@ -80,6 +82,12 @@ pub fn subaction_path(hand: Hand) -> Path {
 }
 
 impl OculusControllerRef<'_> {
+    /// The predicted display time shifted by the
+    /// [`XrPredictionOffset`](crate::resources::XrPredictionOffset)
+    fn pose_time(&self) -> XrTime {
+        self.xr_input
+            .pose_time(self.frame_state.predicted_display_time.into())
+    }
     pub fn grip_space(&self, hand: Hand) -> (SpaceLocation, SpaceVelocity) {
         let d = match hand {
             Hand::Left => self
@@ -88,20 +96,14 @@ impl OculusControllerRef<'_> {
                 .as_ref()
                 .unwrap()
                 .left
-                .relate(
-                    &self.xr_input.stage,
-                    self.frame_state.predicted_display_time,
-                ),
+                .relate(&self.xr_input.stage, self.pose_time().into()),
             Hand::Right => self
                 .oculus_controller
                 .grip_space
                 .as_ref()
                 .unwrap()
                 .right
-                .relate(
-                    &self.xr_input.stage,
-                    self.frame_state.predicted_display_time,
-                ),
+                .relate(&self.xr_input.stage, self.pose_time().into()),
         };
         match d {
             Ok(d) => d,
@@ -116,26 +118,41 @@ impl OculusControllerRef<'_> {
                 .as_ref()
                 .unwrap()
                 .left
-                .relate(
-                    &self.xr_input.stage,
-                    self.frame_state.predicted_display_time,
-                ),
+                .relate(&self.xr_input.stage, self.pose_time().into()),
             Hand::Right => self
                 .oculus_controller
                 .aim_space
                 .as_ref()
                 .unwrap()
                 .right
-                .relate(
-                    &self.xr_input.stage,
-                    self.frame_state.predicted_display_time,
-                ),
+                .relate(&self.xr_input.stage, self.pose_time().into()),
         };
         match d {
             Ok(d) => d,
             Err(_) => (SpaceLocation::default(), SpaceVelocity::default()),
         }
     }
+    /// [`OculusControllerRef::grip_space`] with the validity of each part applied
+    pub fn grip_state(&self, hand: Hand) -> XrSpaceState {
+        let spaces = self.oculus_controller.grip_space.as_ref().unwrap();
+        self.locate(match hand {
+            Hand::Left => &spaces.left,
+            Hand::Right => &spaces.right,
+        })
+    }
+    /// [`OculusControllerRef::aim_space`] with the validity of each part applied
+    pub fn aim_state(&self, hand: Hand) -> XrSpaceState {
+        let spaces = self.oculus_controller.aim_space.as_ref().unwrap();
+        self.locate(match hand {
+            Hand::Left => &spaces.left,
+            Hand::Right => &spaces.right,
+        })
+    }
+    fn locate(&self, space: &Space) -> XrSpaceState {
+        self.xr_input
+            .locate(space, self.pose_time())
+            .unwrap_or_default()
+    }
     pub fn squeeze(&self, hand: Hand) -> f32 {
         match &self
             .action_sets
@@ -541,6 +558,25 @@ impl OculusController {
                 XrBinding::new("thumbrest_touch", "/user/hand/right/input/thumbrest/touch"),
             ],
         );
+        // the inputs other controllers have too, so the app works with them
+        for profile in XrInteractionProfile::ALL {
+            if profile == XrInteractionProfile::OculusTouch {
+                continue;
+            }
+            action_set.suggest_profile_bindings(
+                profile,
+                &[
+                    ("hand_pose", XrCommonInput::GripPose),
+                    ("pointer_pose", XrCommonInput::AimPose),
+                    ("squeeze", XrCommonInput::Squeeze),
+                    ("trigger", XrCommonInput::Trigger),
+                    ("haptic_feedback", XrCommonInput::Haptic),
+                    ("menu_button", XrCommonInput::Menu),
+                    ("thumbstick_x", XrCommonInput::ThumbstickX),
+                    ("thumbstick_y", XrCommonInput::ThumbstickY),
+                ],
+            );
+        }
         Ok(this)
     }
 }
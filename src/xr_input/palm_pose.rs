@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::resources::{XrInstance, XrSession};
+
+use super::actions::{ActionError, XrActionSets};
+use super::oculus_touch::subaction_path;
+use super::paths::XrComponent;
+use super::Hand;
+
+/// Where the palm is relative to the grip pose, used for actions bound to
+/// [`XrComponent::PalmPose`] when the runtime doesn't support `XR_EXT_palm_pose`, see
+/// [`XrExtensions::enable_palm_pose`](crate::graphics::extensions::XrExtensions::enable_palm_pose).
+/// Those actions are bound to the grip pose instead and their spaces created with
+/// [`XrActionSets::create_pose_space`] are offset by this.
+///
+/// Insert it before the session starts to change it, the default is a rough estimate
+/// for a hand closed around the controller.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrPalmPoseFallback {
+    /// The offset on the left hand, it's mirrored for the right hand
+    pub left_offset: Transform,
+}
+
+impl Default for XrPalmPoseFallback {
+    fn default() -> Self {
+        Self {
+            left_offset: Transform {
+                translation: Vec3::new(0.03, 0.0, 0.02),
+                rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+                ..default()
+            },
+        }
+    }
+}
+
+impl XrPalmPoseFallback {
+    /// The offset from the grip pose of a hand
+    pub fn offset(&self, hand: Hand) -> xr::Posef {
+        let Transform {
+            translation,
+            rotation,
+            ..
+        } = self.left_offset;
+        let (translation, rotation) = match hand {
+            Hand::Left => (translation, rotation),
+            Hand::Right => (
+                Vec3::new(-translation.x, translation.y, translation.z),
+                Quat::from_xyzw(rotation.x, -rotation.y, -rotation.z, rotation.w),
+            ),
+        };
+        xr::Posef {
+            orientation: xr::Quaternionf {
+                x: rotation.x,
+                y: rotation.y,
+                z: rotation.z,
+                w: rotation.w,
+            },
+            position: xr::Vector3f {
+                x: translation.x,
+                y: translation.y,
+                z: translation.z,
+            },
+        }
+    }
+}
+
+/// Whether the runtime provides the palm pose itself
+pub(super) fn palm_pose_supported(instance: &XrInstance) -> bool {
+    instance.exts().ext_palm_pose.is_some()
+}
+
+/// The grip pose path replacing a palm pose binding without `XR_EXT_palm_pose`
+pub(super) fn palm_pose_fallback_path(path: &str) -> Option<&'static str> {
+    [Hand::Left, Hand::Right]
+        .into_iter()
+        .find(|hand| XrComponent::PalmPose.path(*hand) == path)
+        .map(|hand| XrComponent::GripPose.path(hand))
+}
+
+impl XrActionSets {
+    /// Creates the space of a pose action for one hand, actions created without the subaction
+    /// paths of the hands are located wherever the runtime bound them.
+    /// Palm pose actions that fell back to the grip pose are offset by [`XrPalmPoseFallback`].
+    pub fn create_pose_space(
+        &self,
+        session: &XrSession,
+        action_set: &'static str,
+        action_name: &'static str,
+        hand: Hand,
+    ) -> Result<xr::Space, ActionError> {
+        let action = self.get_action_posef(action_set, action_name)?;
+        let subaction_path = match self
+            .action_set(action_set)?
+            .handed_actions
+            .contains(action_name)
+        {
+            true => subaction_path(hand),
+            false => xr::Path::NULL,
+        };
+        let offset = match self
+            .emulated_palm_poses
+            .contains(&(action_set, action_name))
+        {
+            true => self.palm_pose_fallback.offset(hand),
+            false => xr::Posef::IDENTITY,
+        };
+        Ok(action.create_space(xr::Session::clone(session), subaction_path, offset)?)
+    }
+}
@@ -0,0 +1,151 @@
+use super::actions::{ActionError, ActionType, SetupActionSet, XrBinding};
+use super::Hand;
+
+/// A path literal checked at compile time, like `xr_path!("/user/hand/left/input/trigger/touch")`,
+/// so typos in the characters or slashes fail to build instead of failing the binding suggestion
+#[macro_export]
+macro_rules! xr_path {
+    ($path:literal) => {{
+        const PATH: &str = $path;
+        const _: () = assert!(
+            $crate::xr_input::paths::is_valid_path(PATH),
+            "openxr paths start with '/' and only contain lowercase letters, digits, '-', '_', '.' and single '/' between them"
+        );
+        PATH
+    }};
+}
+
+/// Whether openxr accepts `path` as a path string
+pub const fn is_valid_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    if bytes.len() < 2 || bytes[0] != b'/' || bytes[bytes.len() - 1] == b'/' {
+        return false;
+    }
+    let mut i = 1;
+    while i < bytes.len() {
+        let valid = match bytes[i] {
+            b'/' => bytes[i - 1] != b'/',
+            b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' => true,
+            _ => false,
+        };
+        if !valid {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+macro_rules! components {
+    ($($(#[$attr:meta])* $name:ident => $suffix:literal, $ty:ident;)*) => {
+        /// Inputs and outputs of a hand's controller, not every profile has all of them
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum XrComponent {
+            $($(#[$attr])* $name,)*
+        }
+
+        impl XrComponent {
+            /// The path of the component below the hand, like `/input/trigger/touch`
+            pub fn suffix(self) -> &'static str {
+                match self {
+                    $(Self::$name => $suffix,)*
+                }
+            }
+
+            /// The type of the value the component provides
+            pub fn action_type(self) -> ActionType {
+                match self {
+                    $(Self::$name => ActionType::$ty,)*
+                }
+            }
+
+            /// The full path of the component on one hand
+            pub fn path(self, hand: Hand) -> &'static str {
+                match hand {
+                    Hand::Left => match self {
+                        $(Self::$name => concat!("/user/hand/left", $suffix),)*
+                    },
+                    Hand::Right => match self {
+                        $(Self::$name => concat!("/user/hand/right", $suffix),)*
+                    },
+                }
+            }
+        }
+    };
+}
+
+components! {
+    /// Where the hand holds the controller, for attaching held objects
+    GripPose => "/input/grip/pose", PoseF;
+    /// Points forward out of the controller, for pointers and raycasts
+    AimPose => "/input/aim/pose", PoseF;
+    /// Needs `XR_EXT_palm_pose`, falls back to the grip pose, see
+    /// [`XrPalmPoseFallback`](super::palm_pose::XrPalmPoseFallback)
+    PalmPose => "/input/palm_ext/pose", PoseF;
+    TriggerValue => "/input/trigger/value", F32;
+    TriggerClick => "/input/trigger/click", Bool;
+    TriggerTouch => "/input/trigger/touch", Bool;
+    SqueezeValue => "/input/squeeze/value", F32;
+    SqueezeClick => "/input/squeeze/click", Bool;
+    /// Valve Index
+    SqueezeForce => "/input/squeeze/force", F32;
+    Thumbstick => "/input/thumbstick", Vec2;
+    ThumbstickX => "/input/thumbstick/x", F32;
+    ThumbstickY => "/input/thumbstick/y", F32;
+    ThumbstickClick => "/input/thumbstick/click", Bool;
+    ThumbstickTouch => "/input/thumbstick/touch", Bool;
+    Trackpad => "/input/trackpad", Vec2;
+    TrackpadX => "/input/trackpad/x", F32;
+    TrackpadY => "/input/trackpad/y", F32;
+    TrackpadClick => "/input/trackpad/click", Bool;
+    TrackpadTouch => "/input/trackpad/touch", Bool;
+    /// Valve Index
+    TrackpadForce => "/input/trackpad/force", F32;
+    ThumbrestTouch => "/input/thumbrest/touch", Bool;
+    /// Right hand on Oculus Touch, both hands on Valve Index
+    AClick => "/input/a/click", Bool;
+    ATouch => "/input/a/touch", Bool;
+    BClick => "/input/b/click", Bool;
+    BTouch => "/input/b/touch", Bool;
+    /// Left hand on Oculus Touch
+    XClick => "/input/x/click", Bool;
+    XTouch => "/input/x/touch", Bool;
+    YClick => "/input/y/click", Bool;
+    YTouch => "/input/y/touch", Bool;
+    MenuClick => "/input/menu/click", Bool;
+    SelectClick => "/input/select/click", Bool;
+    Haptic => "/output/haptic", Haptic;
+}
+
+/// Whether an action of type `action` can be bound to a component providing `component`,
+/// the runtime converts between bool and float values
+pub fn types_compatible(action: ActionType, component: ActionType) -> bool {
+    use ActionType as T;
+    matches!(
+        (action, component),
+        (T::Bool | T::F32, T::Bool | T::F32)
+            | (T::Vec2, T::Vec2)
+            | (T::PoseF, T::PoseF)
+            | (T::Haptic, T::Haptic)
+    )
+}
+
+impl SetupActionSet {
+    /// Suggests binding a component of a hand's controller to an action,
+    /// fails with [`ActionError::WrongActionType`] if the component can't provide
+    /// the action's type, like a trigger for a [`ActionType::Vec2`] action
+    pub fn suggest_component_binding(
+        &mut self,
+        profile: &'static str,
+        action: &'static str,
+        hand: Hand,
+        component: XrComponent,
+    ) -> Result<(), ActionError> {
+        let action_type = self.action_type(action).ok_or(ActionError::NoAction)?;
+        if !types_compatible(action_type, component.action_type()) {
+            return Err(ActionError::WrongActionType);
+        }
+        self.suggest_binding(profile, &[XrBinding::new(action, component.path(hand))]);
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+//! Laser pointers on the aim poses of the controllers, casting rays at the entities with an
+//! [`XrPointerTarget`], see the `world_ui` example.
+
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::transform::TransformSystem;
+
+use crate::resources::XrSession;
+use crate::xr_init::xr_only;
+
+use super::actions::{XrActionSets, XrActionSync, XrButton};
+use super::trackers::XrAimPose;
+use super::{Hand, XrTrackingUpdate};
+
+/// Adds an [`XrPointer`] to the aim poses and updates them
+pub struct XrPointerPlugin;
+
+impl Plugin for XrPointerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<XrPointerEvent>();
+        app.add_systems(
+            PreUpdate,
+            update_pointers
+                .in_set(XrPointerUpdate)
+                .after(XrActionSync)
+                .after(XrTrackingUpdate)
+                .run_if(xr_only()),
+        );
+        app.add_systems(Update, add_pointers);
+        app.add_systems(
+            PostUpdate,
+            draw_lasers
+                .run_if(xr_only())
+                .after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Where the pointers are cast and their events are sent
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct XrPointerUpdate;
+
+/// What presses a pointer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrPointerPress {
+    /// The trigger of the default controller bindings, pulled more than half way
+    Trigger,
+    /// A bool action, read for the hand of the pointer
+    Action(XrButton),
+}
+
+/// A ray along -Z of the aim pose, the distance is the one in world space
+#[derive(Component, Clone, Debug)]
+pub struct XrPointer {
+    pub hand: Hand,
+    pub press: XrPointerPress,
+    pub max_distance: f32,
+    /// Color of the laser line and its hit dot, `None` hides them
+    pub laser: Option<Color>,
+    pressed: bool,
+    just_pressed: bool,
+    hit: Option<XrPointerHit>,
+    pressed_target: Option<Entity>,
+}
+
+impl XrPointer {
+    pub fn new(hand: Hand) -> Self {
+        Self {
+            hand,
+            press: XrPointerPress::Trigger,
+            max_distance: 10.0,
+            laser: Some(Color::WHITE),
+            pressed: false,
+            just_pressed: false,
+            hit: None,
+            pressed_target: None,
+        }
+    }
+
+    pub fn with_press(mut self, press: XrPointerPress) -> Self {
+        self.press = press;
+        self
+    }
+
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn with_laser(mut self, laser: Option<Color>) -> Self {
+        self.laser = laser;
+        self
+    }
+
+    pub fn pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// The pointer got pressed this frame
+    pub fn just_pressed(&self) -> bool {
+        self.just_pressed
+    }
+
+    /// The closest target the pointer points at
+    pub fn hit(&self) -> Option<&XrPointerHit> {
+        self.hit.as_ref()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrPointerHit {
+    pub target: Entity,
+    /// In world space
+    pub position: Vec3,
+    /// In the space of the target
+    pub local_position: Vec3,
+    pub distance: f32,
+}
+
+/// The shape the pointers are tested against, in the space of the entity.
+/// Hidden entities can't be pointed at.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub enum XrPointerTarget {
+    /// The [`Aabb`] bevy calculates for meshes
+    Aabb,
+    /// A rectangle on the XY plane centered on the entity, like a [`Rectangle`] mesh
+    Rectangle(Vec2),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrPointerEventKind {
+    /// The pointer started pointing at the target
+    Over,
+    /// The pointer stopped pointing at the target
+    Out,
+    /// The pointer got pressed while pointing at the target
+    Down,
+    /// The pointer got released while pointing at the target
+    Up,
+    /// The pointer got pressed and released while pointing at the target
+    Click,
+}
+
+/// Follows the pointer events of bevy_mod_picking, with one pointer per hand, so both hands can
+/// hover and press the same target independently
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrPointerEvent {
+    pub pointer: Hand,
+    pub target: Entity,
+    pub kind: XrPointerEventKind,
+}
+
+#[allow(clippy::type_complexity)]
+fn add_pointers(
+    mut commands: Commands,
+    aim_poses: Query<(Entity, &XrAimPose), (Added<XrAimPose>, Without<XrPointer>)>,
+) {
+    for (entity, aim) in &aim_poses {
+        commands.entity(entity).insert(XrPointer::new(aim.0));
+    }
+}
+
+/// Where the ray first hits the shape, in the parameter of the ray
+pub(crate) fn intersect(
+    shape: &XrPointerTarget,
+    aabb: Option<&Aabb>,
+    origin: Vec3,
+    direction: Vec3,
+) -> Option<f32> {
+    match shape {
+        XrPointerTarget::Aabb => {
+            let aabb = aabb?;
+            let min = Vec3::from(aabb.min());
+            let max = Vec3::from(aabb.max());
+            let inverse = direction.recip();
+            let a = (min - origin) * inverse;
+            let b = (max - origin) * inverse;
+            let near = a.min(b).max_element();
+            let far = a.max(b).min_element();
+            (near <= far && far >= 0.0).then_some(near.max(0.0))
+        }
+        XrPointerTarget::Rectangle(size) => {
+            if direction.z.abs() < f32::EPSILON {
+                return None;
+            }
+            let distance = -origin.z / direction.z;
+            let point = (origin + direction * distance).truncate();
+            let inside = point.x.abs() <= size.x / 2.0 && point.y.abs() <= size.y / 2.0;
+            (distance >= 0.0 && inside).then_some(distance)
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_pointers(
+    mut pointers: Query<(&mut XrPointer, &GlobalTransform)>,
+    targets: Query<(
+        Entity,
+        &XrPointerTarget,
+        &GlobalTransform,
+        Option<&Aabb>,
+        Option<&InheritedVisibility>,
+    )>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Option<Res<XrSession>>,
+    mut events: EventWriter<XrPointerEvent>,
+) {
+    for (mut pointer, transform) in &mut pointers {
+        let hand = pointer.hand;
+        let pressed = match (&action_sets, &session, pointer.press) {
+            (Some(action_sets), Some(session), XrPointerPress::Trigger) => action_sets
+                .get_value_for::<f32>(session, "oculus_input", "trigger", hand)
+                .is_ok_and(|value| value > 0.5),
+            (Some(action_sets), Some(session), XrPointerPress::Action(button)) => action_sets
+                .get_value_for::<bool>(session, button.action_set, button.action, hand)
+                .unwrap_or(false),
+            _ => false,
+        };
+        let just_pressed = pressed && !pointer.pressed;
+        let just_released = !pressed && pointer.pressed;
+        pointer.pressed = pressed;
+        pointer.just_pressed = just_pressed;
+
+        let origin = transform.translation();
+        let direction = transform.forward();
+        let hit = targets
+            .iter()
+            .filter(|(.., visibility)| visibility.map_or(true, |v| v.get()))
+            .filter_map(|(entity, shape, target_transform, aabb, _)| {
+                let inverse = target_transform.affine().inverse();
+                let local_origin = inverse.transform_point3(origin);
+                let local_direction = inverse.transform_vector3(direction);
+                let distance = intersect(shape, aabb, local_origin, local_direction)?;
+                (distance <= pointer.max_distance).then(|| XrPointerHit {
+                    target: entity,
+                    position: origin + direction * distance,
+                    local_position: local_origin + local_direction * distance,
+                    distance,
+                })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance));
+
+        let previous = pointer.hit.map(|hit| hit.target);
+        let current = hit.map(|hit| hit.target);
+        pointer.hit = hit;
+        let mut send = |target: Entity, kind: XrPointerEventKind| {
+            events.send(XrPointerEvent {
+                pointer: hand,
+                target,
+                kind,
+            });
+        };
+        if previous != current {
+            if let Some(previous) = previous {
+                send(previous, XrPointerEventKind::Out);
+            }
+            if let Some(current) = current {
+                send(current, XrPointerEventKind::Over);
+            }
+        }
+        if let (true, Some(current)) = (just_pressed, current) {
+            send(current, XrPointerEventKind::Down);
+            pointer.pressed_target = Some(current);
+        }
+        if just_released {
+            if let Some(current) = current {
+                send(current, XrPointerEventKind::Up);
+                if pointer.pressed_target == Some(current) {
+                    send(current, XrPointerEventKind::Click);
+                }
+            }
+            pointer.pressed_target = None;
+        }
+    }
+}
+
+fn draw_lasers(mut gizmos: Gizmos, pointers: Query<(&XrPointer, &GlobalTransform)>) {
+    for (pointer, transform) in &pointers {
+        let Some(color) = pointer.laser else {
+            continue;
+        };
+        let origin = transform.translation();
+        let distance = pointer.hit.map_or(pointer.max_distance, |hit| hit.distance);
+        let end = origin + transform.forward() * distance;
+        gizmos.line(origin, end, color);
+        if pointer.hit.is_some() {
+            gizmos.sphere(end, Quat::IDENTITY, 0.01, color);
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Smoothed release velocities for throwing, fitted over the last few frames as the velocity
+//! the runtime reports in the frame the grip is released is noisy.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::math::{Quat, Vec3A};
+use bevy::prelude::*;
+
+use crate::resources::XrFrameState;
+
+use super::trackers::XrVelocity;
+
+/// A pose of an [`XrPoseHistory`], relative to the tracking root
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrPoseSample {
+    /// Predicted display time of the frame the pose was located for
+    pub time: Duration,
+    pub translation: Vec3A,
+    pub rotation: Quat,
+    /// `false` if the runtime lost tracking in this frame, the pose is the last known one then
+    pub valid: bool,
+}
+
+/// The poses of a tracked entity over the last [`XrPoseHistory::max_age`], recorded every frame
+/// after [`XrTrackingUpdate`](super::XrTrackingUpdate). Insert it on the controller entities
+/// that can throw. Frames in which the entity had no [`XrVelocity`] count as untracked.
+#[derive(Component, Clone, Debug)]
+pub struct XrPoseHistory {
+    /// Samples older than this, relative to the newest one, are dropped
+    pub max_age: Duration,
+    samples: VecDeque<XrPoseSample>,
+}
+
+impl Default for XrPoseHistory {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(250))
+    }
+}
+
+impl XrPoseHistory {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Adds a pose, `None` if tracking was lost at `time`
+    pub fn record(&mut self, time: Duration, pose: Option<Transform>) {
+        let last = self.samples.back().copied();
+        // a new session starts its clock over
+        if last.is_some_and(|last| time < last.time) {
+            self.samples.clear();
+        }
+        if last.is_some_and(|last| time == last.time) {
+            self.samples.pop_back();
+        }
+        let sample = match pose {
+            Some(pose) => XrPoseSample {
+                time,
+                translation: pose.translation.into(),
+                rotation: pose.rotation.normalize(),
+                valid: true,
+            },
+            None => XrPoseSample {
+                time,
+                valid: false,
+                ..last.unwrap_or(XrPoseSample {
+                    time,
+                    translation: Vec3A::ZERO,
+                    rotation: Quat::IDENTITY,
+                    valid: false,
+                })
+            },
+        };
+        self.samples.push_back(sample);
+        while self
+            .samples
+            .front()
+            .is_some_and(|first| time - first.time > self.max_age)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// The recorded samples, oldest first
+    pub fn samples(&self) -> impl DoubleEndedIterator<Item = &XrPoseSample> {
+        self.samples.iter()
+    }
+
+    /// Linear and angular velocity over the tracked samples of the last `window`, fitted with a
+    /// least squares line through the positions and rotations, in meters and radians per second
+    /// relative to the tracking root. Untracked frames are left out of the fit.
+    /// Zero while fewer than two tracked samples are in the window.
+    pub fn release_velocity(&self, window: Duration) -> (Vec3A, Vec3A) {
+        let Some(newest) = self.samples.back() else {
+            return (Vec3A::ZERO, Vec3A::ZERO);
+        };
+        let Some(reference) = self.samples.iter().rev().find(|sample| sample.valid) else {
+            return (Vec3A::ZERO, Vec3A::ZERO);
+        };
+        let samples: Vec<(f32, Vec3A, Vec3A)> = self
+            .samples
+            .iter()
+            .filter(|sample| sample.valid && newest.time - sample.time <= window)
+            .map(|sample| {
+                let t = (sample.time.as_secs_f64() - reference.time.as_secs_f64()) as f32;
+                // rotations relative to the newest one, so they stay well inside of the
+                // range the rotation vectors can represent
+                let mut delta = sample.rotation * reference.rotation.inverse();
+                if delta.w < 0.0 {
+                    delta = -delta;
+                }
+                (t, sample.translation, delta.to_scaled_axis().into())
+            })
+            .collect();
+        if samples.len() < 2 {
+            return (Vec3A::ZERO, Vec3A::ZERO);
+        }
+        (
+            fit_slope(samples.iter().map(|&(t, translation, _)| (t, translation))),
+            fit_slope(samples.iter().map(|&(t, _, rotation)| (t, rotation))),
+        )
+    }
+}
+
+/// Slope of the least squares line through the points, zero if all of them are at the same time
+fn fit_slope(points: impl Iterator<Item = (f32, Vec3A)> + Clone) -> Vec3A {
+    let count = points.clone().count() as f32;
+    let (sum_t, sum_value) = points
+        .clone()
+        .fold((0.0, Vec3A::ZERO), |(t, value), point| {
+            (t + point.0, value + point.1)
+        });
+    let mean_t = sum_t / count;
+    let mean_value = sum_value / count;
+    let (covariance, variance) =
+        points.fold((Vec3A::ZERO, 0.0), |(covariance, variance), (t, value)| {
+            let dt = t - mean_t;
+            (covariance + (value - mean_value) * dt, variance + dt * dt)
+        });
+    if variance <= f32::EPSILON {
+        return Vec3A::ZERO;
+    }
+    covariance / variance
+}
+
+pub fn record_pose_history(
+    frame_state: Res<XrFrameState>,
+    mut histories: Query<(&mut XrPoseHistory, &Transform, Option<&XrVelocity>)>,
+) {
+    let time = Duration::from_nanos(frame_state.predicted_display_time.as_nanos().max(0) as u64);
+    for (mut history, transform, velocity) in &mut histories {
+        history.record(time, velocity.map(|_| *transform));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_nanos(11_111_111);
+    const WINDOW: Duration = Duration::from_millis(80);
+    const LINEAR: Vec3 = Vec3::new(1.0, 2.0, -3.0);
+    const ANGULAR: f32 = 2.0;
+
+    /// The pose of a controller moving and turning around y at constant speeds
+    fn pose(time: Duration) -> Transform {
+        let t = time.as_secs_f32();
+        Transform::from_translation(Vec3::new(0.0, 1.0, 0.0) + LINEAR * t)
+            .with_rotation(Quat::from_rotation_y(ANGULAR * t))
+    }
+
+    fn assert_close(actual: Vec3A, expected: Vec3, epsilon: f32) {
+        assert!(
+            actual.abs_diff_eq(expected.into(), epsilon),
+            "{} isn't {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn constant_velocity() {
+        let mut history = XrPoseHistory::default();
+        for frame in 100..130 {
+            let time = FRAME * frame;
+            history.record(time, Some(pose(time)));
+        }
+        let (linear, angular) = history.release_velocity(WINDOW);
+        assert_close(linear, LINEAR, 1e-3);
+        assert_close(angular, Vec3::Y * ANGULAR, 1e-3);
+    }
+
+    #[test]
+    fn old_samples_are_dropped() {
+        let mut history = XrPoseHistory::new(Duration::from_millis(50));
+        for frame in 0..30 {
+            history.record(FRAME * frame, Some(pose(FRAME * frame)));
+        }
+        let samples = history.samples().collect::<Vec<_>>();
+        assert_eq!(samples.len(), 5);
+        assert_eq!(samples.last().unwrap().time, FRAME * 29);
+    }
+
+    #[test]
+    fn untracked_frames_are_left_out() {
+        let mut history = XrPoseHistory::default();
+        for frame in 100..130 {
+            let time = FRAME * frame;
+            let tracked = !(110..120).contains(&frame) && frame % 3 != 0;
+            history.record(time, tracked.then(|| pose(time)));
+        }
+        // the last known pose is kept while untracked
+        let untracked = history
+            .samples()
+            .filter(|sample| !sample.valid)
+            .collect::<Vec<_>>();
+        assert!(!untracked.is_empty());
+        for sample in untracked {
+            assert!(sample.translation.y < pose(sample.time).translation.y);
+        }
+        let (linear, angular) = history.release_velocity(WINDOW);
+        assert_close(linear, LINEAR, 1e-3);
+        assert_close(angular, Vec3::Y * ANGULAR, 1e-3);
+    }
+
+    #[test]
+    fn too_few_tracked_samples() {
+        let mut history = XrPoseHistory::default();
+        assert_eq!(history.release_velocity(WINDOW), (Vec3A::ZERO, Vec3A::ZERO));
+        history.record(FRAME, Some(pose(FRAME)));
+        for frame in 2..10 {
+            history.record(FRAME * frame, None);
+        }
+        assert_eq!(history.release_velocity(WINDOW), (Vec3A::ZERO, Vec3A::ZERO));
+        // all in the same frame
+        assert_eq!(
+            fit_slope([(0.5, Vec3A::ZERO), (0.5, Vec3A::ONE)].into_iter()),
+            Vec3A::ZERO
+        );
+    }
+
+    #[test]
+    fn new_session_starts_over() {
+        let mut history = XrPoseHistory::default();
+        for frame in 100..130 {
+            history.record(FRAME * frame, Some(pose(FRAME * frame)));
+        }
+        // the clock of the next session starts below the last time
+        let restart = Duration::from_millis(2);
+        history.record(restart, Some(Transform::from_xyz(5.0, 0.0, 0.0)));
+        assert_eq!(history.samples().count(), 1);
+        assert_eq!(history.release_velocity(WINDOW), (Vec3A::ZERO, Vec3A::ZERO));
+        history.record(restart + FRAME, Some(Transform::from_xyz(5.0, 0.0, 0.0)));
+        assert_eq!(history.release_velocity(WINDOW).0, Vec3A::ZERO);
+    }
+
+    #[test]
+    fn same_frame_replaces_the_sample() {
+        let mut history = XrPoseHistory::default();
+        history.record(FRAME, Some(Transform::from_xyz(1.0, 0.0, 0.0)));
+        history.record(FRAME, Some(Transform::from_xyz(2.0, 0.0, 0.0)));
+        let samples = history.samples().collect::<Vec<_>>();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].translation, Vec3A::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rotation_sign_flips_are_ignored() {
+        let mut history = XrPoseHistory::default();
+        for frame in 100..130 {
+            let time = FRAME * frame;
+            let mut pose = pose(time);
+            // -q is the same rotation as q, runtimes report either
+            if frame % 2 == 0 {
+                pose.rotation = -pose.rotation;
+            }
+            history.record(time, Some(pose));
+        }
+        let (_, angular) = history.release_velocity(WINDOW);
+        assert_close(angular, Vec3::Y * ANGULAR, 1e-3);
+    }
+
+    #[test]
+    fn turning_past_half_a_turn() {
+        let mut history = XrPoseHistory::default();
+        // the rotations go from just below to just above 180 degrees around y
+        for frame in 0..10 {
+            let time = FRAME * frame;
+            let angle = std::f32::consts::PI - 0.1 + ANGULAR * time.as_secs_f32();
+            history.record(
+                time,
+                Some(Transform::from_rotation(Quat::from_rotation_y(angle))),
+            );
+        }
+        let (_, angular) = history.release_velocity(WINDOW);
+        assert_close(angular, Vec3::Y * ANGULAR, 1e-3);
+    }
+}
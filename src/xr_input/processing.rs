@@ -0,0 +1,334 @@
+use bevy::math::Vec2;
+use bevy::utils::default;
+use openxr as xr;
+use xr::Vector2f;
+
+use super::actions::{ActionError, ActionState, ActionValue, SetupActionSet, XrActionSets};
+
+/// Range of a stick or trigger that maps to the full value range,
+/// values inside `inner` become zero and values beyond `outer` become one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum XrDeadzone {
+    None,
+    /// Uses the length of vec2 values, keeping their direction
+    Radial {
+        inner: f32,
+        outer: f32,
+    },
+    /// Uses each axis of vec2 values on its own, which snaps to the axes near the center
+    Axial {
+        inner: f32,
+        outer: f32,
+    },
+}
+
+/// Maps the value left after the deadzone, keeping its sign
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum XrResponseCurve {
+    Linear,
+    /// Finer control for small values
+    Squared,
+    /// `value.powf(exponent)`
+    Exponent(f32),
+}
+
+/// How [`XrActionSets::get_processed_state`] changes the value of a float or vec2 action,
+/// set with [`SetupActionSet::set_processing`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrActionProcessing {
+    pub deadzone: XrDeadzone,
+    pub curve: XrResponseCurve,
+    /// Negates float values and the x axis of vec2 values
+    pub invert_x: bool,
+    pub invert_y: bool,
+}
+
+impl Default for XrActionProcessing {
+    fn default() -> Self {
+        Self {
+            deadzone: XrDeadzone::None,
+            curve: XrResponseCurve::Linear,
+            invert_x: false,
+            invert_y: false,
+        }
+    }
+}
+
+impl XrActionProcessing {
+    /// A radial deadzone with a linear response
+    pub fn with_deadzone(inner: f32, outer: f32) -> Self {
+        Self {
+            deadzone: XrDeadzone::Radial { inner, outer },
+            ..default()
+        }
+    }
+
+    pub fn with_curve(self, curve: XrResponseCurve) -> Self {
+        Self { curve, ..self }
+    }
+
+    pub fn inverted(self, invert_x: bool, invert_y: bool) -> Self {
+        Self {
+            invert_x,
+            invert_y,
+            ..self
+        }
+    }
+
+    /// Applies the deadzone and curve to a float value, the inner deadzone of
+    /// [`XrDeadzone::Radial`] and [`XrDeadzone::Axial`] is the same for one axis
+    pub fn process_f32(&self, value: f32) -> f32 {
+        let value = match self.deadzone {
+            XrDeadzone::None => value.clamp(-1.0, 1.0),
+            XrDeadzone::Radial { inner, outer } | XrDeadzone::Axial { inner, outer } => {
+                apply_deadzone(value, inner, outer)
+            }
+        };
+        let value = self.curve.apply(value);
+        match self.invert_x {
+            true => -value,
+            false => value,
+        }
+    }
+
+    /// Applies the deadzone and curve to a vec2 value, the radial deadzone
+    /// and the curve use its length so it keeps its direction
+    pub fn process_vec2(&self, value: Vec2) -> Vec2 {
+        let value = match self.deadzone {
+            XrDeadzone::Axial { inner, outer } => Vec2::new(
+                self.curve.apply(apply_deadzone(value.x, inner, outer)),
+                self.curve.apply(apply_deadzone(value.y, inner, outer)),
+            ),
+            deadzone => {
+                let length = value.length();
+                let processed = match deadzone {
+                    XrDeadzone::Radial { inner, outer } => apply_deadzone(length, inner, outer),
+                    _ => length.min(1.0),
+                };
+                match length > 0.0 {
+                    true => value / length * self.curve.apply(processed),
+                    false => Vec2::ZERO,
+                }
+            }
+        };
+        Vec2::new(
+            if self.invert_x { -value.x } else { value.x },
+            if self.invert_y { -value.y } else { value.y },
+        )
+    }
+}
+
+impl XrResponseCurve {
+    fn apply(self, value: f32) -> f32 {
+        let magnitude = value.abs();
+        let magnitude = match self {
+            XrResponseCurve::Linear => magnitude,
+            XrResponseCurve::Squared => magnitude * magnitude,
+            XrResponseCurve::Exponent(exponent) => magnitude.powf(exponent),
+        };
+        magnitude.copysign(value)
+    }
+}
+
+/// Rescales the magnitude of `value` from `inner..outer` to `0..1`
+fn apply_deadzone(value: f32, inner: f32, outer: f32) -> f32 {
+    let magnitude = value.abs();
+    if magnitude <= inner {
+        return 0.0;
+    }
+    if magnitude >= outer || outer <= inner {
+        return 1.0f32.copysign(value);
+    }
+    ((magnitude - inner) / (outer - inner)).copysign(value)
+}
+
+/// Values [`XrActionProcessing`] applies to
+pub trait ProcessedValue: ActionValue {
+    fn process(self, processing: &XrActionProcessing) -> Self;
+}
+
+impl ProcessedValue for f32 {
+    fn process(self, processing: &XrActionProcessing) -> Self {
+        processing.process_f32(self)
+    }
+}
+
+impl ProcessedValue for Vector2f {
+    fn process(self, processing: &XrActionProcessing) -> Self {
+        let value = processing.process_vec2(Vec2::new(self.x, self.y));
+        Vector2f {
+            x: value.x,
+            y: value.y,
+        }
+    }
+}
+
+impl SetupActionSet {
+    /// Processing for the values of a float or vec2 action of this set,
+    /// [`XrActionSets::get_state`] keeps returning the raw value
+    pub fn set_processing(&mut self, action: &'static str, processing: XrActionProcessing) {
+        self.processing.insert(action, processing);
+    }
+}
+
+impl XrActionSets {
+    /// The processing set for an action, if any
+    pub fn processing(
+        &self,
+        action_set: &'static str,
+        action_name: &'static str,
+    ) -> Result<Option<&XrActionProcessing>, ActionError> {
+        Ok(self.action_set(action_set)?.processing.get(action_name))
+    }
+    /// Like [`XrActionSets::get_state`] with the action's [`XrActionProcessing`] applied
+    /// to the value, actions without processing return their raw value
+    pub fn get_processed_state<T: ProcessedValue>(
+        &self,
+        session: &xr::Session<xr::AnyGraphics>,
+        action_set: &'static str,
+        action_name: &'static str,
+        subaction_path: xr::Path,
+    ) -> Result<ActionState<T>, ActionError> {
+        let mut state = self.get_state::<T>(session, action_set, action_name, subaction_path)?;
+        if let Some(processing) = self.processing(action_set, action_name)? {
+            state.value = state.value.process(processing);
+        }
+        Ok(state)
+    }
+    /// Shorthand for the value of [`XrActionSets::get_processed_state`]
+    pub fn get_processed_value<T: ProcessedValue>(
+        &self,
+        session: &xr::Session<xr::AnyGraphics>,
+        action_set: &'static str,
+        action_name: &'static str,
+        subaction_path: xr::Path,
+    ) -> Result<T, ActionError> {
+        self.get_processed_state(session, action_set, action_name, subaction_path)
+            .map(|state| state.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(
+            actual.abs_diff_eq(expected, 1e-5),
+            "{} isn't {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn no_deadzone_clamps() {
+        let processing = XrActionProcessing::default();
+        assert_eq!(processing.process_f32(0.01), 0.01);
+        assert_eq!(processing.process_f32(1.5), 1.0);
+        assert_eq!(processing.process_f32(-1.5), -1.0);
+        assert_close(
+            processing.process_vec2(Vec2::new(0.3, 0.4)),
+            Vec2::new(0.3, 0.4),
+        );
+        // the length is clamped, the direction is kept
+        assert_close(
+            processing.process_vec2(Vec2::new(3.0, 4.0)),
+            Vec2::new(0.6, 0.8),
+        );
+        assert_eq!(processing.process_vec2(Vec2::ZERO), Vec2::ZERO);
+    }
+
+    #[test]
+    fn radial_deadzone() {
+        let processing = XrActionProcessing::with_deadzone(0.2, 0.8);
+        assert_eq!(processing.process_vec2(Vec2::new(0.1, 0.1)), Vec2::ZERO);
+        // halfway between inner and outer, along the diagonal
+        let diagonal = Vec2::ONE.normalize();
+        assert_close(processing.process_vec2(diagonal * 0.5), diagonal * 0.5);
+        assert_close(processing.process_vec2(diagonal * 0.9), diagonal);
+        // an axis inside the deadzone on its own isn't cut off
+        assert_close(
+            processing.process_vec2(Vec2::new(0.5, 0.1)),
+            Vec2::new(0.5, 0.1).normalize() * ((Vec2::new(0.5, 0.1).length() - 0.2) / 0.6),
+        );
+        assert_eq!(processing.process_f32(0.2), 0.0);
+        assert!((processing.process_f32(-0.5) + 0.5).abs() < 1e-6);
+        assert_eq!(processing.process_f32(0.8), 1.0);
+    }
+
+    #[test]
+    fn axial_deadzone() {
+        let processing = XrActionProcessing {
+            deadzone: XrDeadzone::Axial {
+                inner: 0.2,
+                outer: 0.8,
+            },
+            ..default()
+        };
+        // snaps to the x axis
+        assert_close(
+            processing.process_vec2(Vec2::new(0.5, 0.1)),
+            Vec2::new(0.5, 0.0),
+        );
+        assert_close(
+            processing.process_vec2(Vec2::new(-0.9, 0.5)),
+            Vec2::new(-1.0, 0.5),
+        );
+        assert_eq!(processing.process_vec2(Vec2::new(0.15, -0.2)), Vec2::ZERO);
+        assert!((processing.process_f32(0.5) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn empty_deadzone_range() {
+        let processing = XrActionProcessing::with_deadzone(0.5, 0.5);
+        assert_eq!(processing.process_f32(0.4), 0.0);
+        assert_eq!(processing.process_f32(-0.6), -1.0);
+    }
+
+    #[test]
+    fn curve_endpoints() {
+        for curve in [
+            XrResponseCurve::Linear,
+            XrResponseCurve::Squared,
+            XrResponseCurve::Exponent(0.5),
+            XrResponseCurve::Exponent(3.0),
+        ] {
+            let processing = XrActionProcessing::with_deadzone(0.1, 0.9).with_curve(curve);
+            assert_eq!(processing.process_f32(0.1), 0.0, "{:?}", curve);
+            assert_eq!(processing.process_f32(0.9), 1.0, "{:?}", curve);
+            assert_eq!(processing.process_f32(-0.9), -1.0, "{:?}", curve);
+            assert_eq!(processing.process_vec2(Vec2::new(0.0, 0.05)), Vec2::ZERO);
+            assert_close(processing.process_vec2(Vec2::new(0.0, -1.0)), -Vec2::Y);
+        }
+    }
+
+    #[test]
+    fn curves_keep_the_sign() {
+        let squared = XrActionProcessing::default().with_curve(XrResponseCurve::Squared);
+        assert_eq!(squared.process_f32(0.5), 0.25);
+        assert_eq!(squared.process_f32(-0.5), -0.25);
+        assert_close(
+            squared.process_vec2(Vec2::new(-0.3, 0.4)),
+            Vec2::new(-0.15, 0.2),
+        );
+        let cubed = XrActionProcessing::default().with_curve(XrResponseCurve::Exponent(3.0));
+        assert!((cubed.process_f32(-0.5) + 0.125).abs() < 1e-6);
+    }
+
+    #[test]
+    fn inverted_axes() {
+        let processing = XrActionProcessing::default().inverted(true, false);
+        assert_eq!(processing.process_f32(0.5), -0.5);
+        assert_eq!(
+            processing.process_vec2(Vec2::new(0.5, 0.25)),
+            Vec2::new(-0.5, 0.25)
+        );
+        let processing = XrActionProcessing::default().inverted(false, true);
+        assert_eq!(processing.process_f32(0.5), 0.5);
+        assert_eq!(
+            processing.process_vec2(Vec2::new(0.5, 0.25)),
+            Vec2::new(0.5, -0.25)
+        );
+    }
+}
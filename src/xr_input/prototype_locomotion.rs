@@ -23,6 +23,8 @@ pub enum LocomotionType {
 pub enum RotationType {
     Smooth,
     Snap,
+    /// For apps that turn with the [`XrTurningPlugin`](super::turning::XrTurningPlugin)
+    Disabled,
 }
 
 #[derive(Resource)]
@@ -115,20 +117,15 @@ pub fn proto_locomotion(
                     if rot_input.abs() <= config.rotation_stick_deadzone {
                         return;
                     }
-                    let smoth_rot = Quat::from_axis_angle(
-                        *position.up(),
-                        rot_input * config.smooth_rotation_speed * time.delta_seconds(),
-                    );
+                    let angle = rot_input * config.smooth_rotation_speed * time.delta_seconds();
                     //apply rotation
                     let views = views.first();
                     match views {
                         Some(view) => {
-                            let mut hmd_translation = view.pose.position.to_vec3();
-                            hmd_translation.y = 0.0;
-                            let local = position.translation;
-                            let global = position.rotation.mul_vec3(hmd_translation) + local;
-                            gizmos.circle(global, position.up(), 0.1, Color::GREEN);
-                            position.rotate_around(global, smoth_rot);
+                            let head = view.pose.position.to_vec3();
+                            let pivot = OpenXRTrackingRoot::floor_position(&position, head);
+                            gizmos.circle(pivot, position.up(), 0.1, Color::GREEN);
+                            OpenXRTrackingRoot::rotate_around_head(&mut position, head, angle);
                         }
                         None => return,
                     }
@@ -148,25 +145,23 @@ pub fn proto_locomotion(
                             true => 1.0,
                             false => -1.0,
                         };
-                        let smoth_rot =
-                            Quat::from_axis_angle(*position.up(), config.snap_angle * dir);
+                        let angle = config.snap_angle * dir;
                         //apply rotation
                         let v = views;
                         let views = v.first();
                         match views {
                             Some(view) => {
-                                let mut hmd_translation = view.pose.position.to_vec3();
-                                hmd_translation.y = 0.0;
-                                let local = position.translation;
-                                let global = position.rotation.mul_vec3(hmd_translation) + local;
-                                gizmos.circle(global, position.up(), 0.1, Color::GREEN);
-                                position.rotate_around(global, smoth_rot);
+                                let head = view.pose.position.to_vec3();
+                                let pivot = OpenXRTrackingRoot::floor_position(&position, head);
+                                gizmos.circle(pivot, position.up(), 0.1, Color::GREEN);
+                                OpenXRTrackingRoot::rotate_around_head(&mut position, head, angle);
                             }
                             None => return,
                         }
                         config.rotation_timer.timer.reset();
                     }
                 }
+                RotationType::Disabled => {}
             }
         }
         Err(_) => info!("too many tracking roots"),
@@ -0,0 +1,611 @@
+//! Lets players bind actions to other inputs from inside the game, by binding the next input
+//! they activate after a [`ListenForXrBinding`] to the action.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use openxr as xr;
+use serde::{Deserialize, Serialize};
+
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::{
+    EndXrSession, ExitAppOnSessionExit, StartXrSession, XrCleanup, XrPostSetup, XrPrePostSetup,
+    XrStatus,
+};
+
+use super::actions::{
+    setup_oxr_actions, ActionHandednes, ActionType, SetupActionSets, TypedAction, XrActionSets,
+    XrActionSync, XrBinding,
+};
+use super::binding_assets::XrBindings;
+use super::bound_sources::XrSourceNameComponents;
+use super::interaction_profiles::{XrInteractionProfile, XrProfileComponent};
+
+/// The action set the probe actions are in
+pub const PROBE_ACTION_SET: &str = "rebinding_probe";
+
+/// Listens for new bindings and restores the saved [`XrBindingOverrides`]. While listening, a
+/// probe action set with an action for every component of the known interaction profiles is
+/// synced with the highest priority.
+pub struct XrRebindingPlugin {
+    /// The RON file the overrides are loaded from and saved to, `None` keeps them in memory
+    pub path: Option<PathBuf>,
+}
+
+impl Default for XrRebindingPlugin {
+    fn default() -> Self {
+        Self {
+            path: Some("xr_binding_overrides.ron".into()),
+        }
+    }
+}
+
+impl Plugin for XrRebindingPlugin {
+    fn build(&self, app: &mut App) {
+        let overrides = match &self.path {
+            Some(path) => XrBindingOverrides::load(path).unwrap_or_else(|err| {
+                warn!(
+                    "Unable to load the binding overrides from {:?}: {}",
+                    path, err
+                );
+                default()
+            }),
+            None => default(),
+        };
+        app.insert_resource(overrides);
+        app.init_resource::<XrBindingListener>();
+        app.add_event::<ListenForXrBinding>();
+        app.add_event::<StopListeningForXrBinding>();
+        app.add_event::<XrBindingCaptured>();
+        app.add_event::<ApplyXrBindingOverrides>();
+        app.add_systems(
+            XrPrePostSetup,
+            (add_probe_action_set, apply_binding_overrides).before(setup_oxr_actions),
+        );
+        app.add_systems(XrPostSetup, disable_probe_action_set);
+        app.add_systems(XrCleanup, stop_listening);
+        app.add_systems(
+            PreUpdate,
+            (start_listening, capture_binding.after(XrActionSync))
+                .chain()
+                .run_if(resource_exists::<XrActionSets>),
+        );
+        app.add_systems(PreUpdate, restart_session);
+        if let Some(path) = self.path.clone() {
+            app.add_systems(
+                Last,
+                (move |overrides: Res<XrBindingOverrides>| {
+                    if overrides.is_added() {
+                        return;
+                    }
+                    if let Err(err) = overrides.save(&path) {
+                        warn!(
+                            "Unable to save the binding overrides to {:?}: {}",
+                            path, err
+                        );
+                    }
+                })
+                .run_if(resource_changed::<XrBindingOverrides>),
+            );
+        }
+    }
+}
+
+/// A binding the player picked, it replaces all suggested bindings of the action for the profile
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XrBindingOverride {
+    pub action_set: &'static str,
+    pub action: &'static str,
+    /// Like `/interaction_profiles/oculus/touch_controller`
+    pub profile: &'static str,
+    pub paths: Vec<&'static str>,
+}
+
+/// The bindings the player picked, applied whenever a session is set up.
+/// The names are leaked like the ones of [`XrBindings`],
+/// as the action setup only takes static names.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq)]
+pub struct XrBindingOverrides {
+    overrides: Vec<XrBindingOverride>,
+}
+
+impl XrBindingOverrides {
+    /// Binds an action to `paths` for one interaction profile instead of its suggested bindings
+    pub fn set(
+        &mut self,
+        action_set: &str,
+        action: &str,
+        profile: &str,
+        paths: impl IntoIterator<Item = impl Into<String>>,
+    ) {
+        self.remove(action_set, action, profile);
+        let leak = |name: String| -> &'static str { Box::leak(name.into_boxed_str()) };
+        self.overrides.push(XrBindingOverride {
+            action_set: leak(action_set.into()),
+            action: leak(action.into()),
+            profile: leak(profile.into()),
+            paths: paths.into_iter().map(|path| leak(path.into())).collect(),
+        });
+    }
+
+    /// Goes back to the suggested bindings of an action for a profile,
+    /// `false` if there was no override
+    pub fn remove(&mut self, action_set: &str, action: &str, profile: &str) -> bool {
+        let len = self.overrides.len();
+        self.overrides
+            .retain(|o| (o.action_set, o.action, o.profile) != (action_set, action, profile));
+        self.overrides.len() != len
+    }
+
+    pub fn get(&self, action_set: &str, action: &str, profile: &str) -> Option<&XrBindingOverride> {
+        self.overrides
+            .iter()
+            .find(|o| (o.action_set, o.action, o.profile) == (action_set, action, profile))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &XrBindingOverride> {
+        self.overrides.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.overrides.clear();
+    }
+
+    /// Replaces the suggested bindings, overrides for actions that don't exist are skipped
+    pub fn apply_to(&self, setup: &mut SetupActionSets) {
+        for o in &self.overrides {
+            let Some(action) = setup
+                .sets
+                .get_mut(o.action_set)
+                .and_then(|set| set.actions.get_mut(o.action))
+            else {
+                debug!(
+                    "Skipping the binding override of the missing action {}/{}",
+                    o.action_set, o.action
+                );
+                continue;
+            };
+            action.bindings.insert(o.profile, o.paths.clone());
+        }
+    }
+
+    /// A copy of loaded bindings with the overrides in place of their bindings,
+    /// like for writing them back as an asset
+    pub fn applied_to(&self, bindings: &XrBindings) -> XrBindings {
+        let mut bindings = bindings.clone();
+        for o in &self.overrides {
+            let Some(action) = bindings
+                .action_sets
+                .iter_mut()
+                .filter(|set| set.name == o.action_set)
+                .flat_map(|set| set.actions.iter_mut())
+                .find(|action| action.name == o.action)
+            else {
+                continue;
+            };
+            match action.bindings.iter_mut().find(|(p, _)| *p == o.profile) {
+                Some((_, paths)) => *paths = o.paths.clone(),
+                None => action.bindings.push((o.profile, o.paths.clone())),
+            }
+        }
+        bindings
+    }
+
+    /// Reads the overrides from a RON file, a missing file has no overrides
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, XrBindingOverridesError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(default()),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(ron::from_str(&text)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), XrBindingOverridesError> {
+        let text = ron::ser::to_string_pretty(self, default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// A list of overrides with owned names
+impl Serialize for XrBindingOverrides {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.overrides
+            .iter()
+            .map(|o| RawOverride {
+                action_set: o.action_set.into(),
+                action: o.action.into(),
+                profile: o.profile.into(),
+                paths: o.paths.iter().map(|path| path.to_string()).collect(),
+            })
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for XrBindingOverrides {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut overrides = Self::default();
+        for o in Vec::<RawOverride>::deserialize(deserializer)? {
+            overrides.set(&o.action_set, &o.action, &o.profile, o.paths);
+        }
+        Ok(overrides)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawOverride {
+    action_set: String,
+    action: String,
+    profile: String,
+    paths: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum XrBindingOverridesError {
+    Io(std::io::Error),
+    Ron(ron::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl std::fmt::Display for XrBindingOverridesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XrBindingOverridesError::Io(err) => write!(f, "{}", err),
+            XrBindingOverridesError::Ron(err) => write!(f, "{}", err),
+            XrBindingOverridesError::Parse(err) => write!(f, "Invalid binding overrides: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for XrBindingOverridesError {}
+
+impl From<std::io::Error> for XrBindingOverridesError {
+    fn from(value: std::io::Error) -> Self {
+        XrBindingOverridesError::Io(value)
+    }
+}
+
+impl From<ron::Error> for XrBindingOverridesError {
+    fn from(value: ron::Error) -> Self {
+        XrBindingOverridesError::Ron(value)
+    }
+}
+
+impl From<ron::error::SpannedError> for XrBindingOverridesError {
+    fn from(value: ron::error::SpannedError) -> Self {
+        XrBindingOverridesError::Parse(value)
+    }
+}
+
+/// Binds the next input the player activates to an action. Bool and float actions accept
+/// buttons and triggers, vector2 actions sticks and trackpads. Pose and haptic actions can't
+/// be rebound.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ListenForXrBinding {
+    pub action_set: &'static str,
+    pub action: &'static str,
+}
+
+impl ListenForXrBinding {
+    pub fn new(action_set: &'static str, action: &'static str) -> Self {
+        Self { action_set, action }
+    }
+}
+
+/// Stops listening without changing the binding
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct StopListeningForXrBinding;
+
+/// Sent once the player activated an input while listening,
+/// it's already stored in the [`XrBindingOverrides`]
+#[derive(Event, Clone, Debug)]
+pub struct XrBindingCaptured {
+    pub action_set: &'static str,
+    pub action: &'static str,
+    pub profile: &'static str,
+    /// The input on both hands for actions created with [`ActionHandednes::Double`]
+    pub paths: Vec<&'static str>,
+    /// The name the runtime shows for the input, like "Right Hand A Button"
+    pub localized_name: String,
+}
+
+/// Restarts the running session so it's set up with the current [`XrBindingOverrides`], as
+/// bindings can only be suggested before the action sets are attached to a session. Best sent
+/// once the player left the rebinding screen.
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct ApplyXrBindingOverrides;
+
+/// Which action is waiting for an input
+#[derive(Resource, Default)]
+pub struct XrBindingListener {
+    target: Option<ListenForXrBinding>,
+    action_type: Option<ActionType>,
+    /// The probes were synced once since listening started
+    armed: bool,
+    /// Probes that were already active when listening started, they count once released
+    held: HashSet<&'static str>,
+}
+
+impl XrBindingListener {
+    pub fn target(&self) -> Option<ListenForXrBinding> {
+        self.target
+    }
+
+    pub fn is_listening(&self) -> bool {
+        self.target.is_some()
+    }
+}
+
+/// An action bound to one component in every profile that has it
+struct Probe {
+    name: &'static str,
+    component: XrProfileComponent,
+    profiles: Vec<&'static str>,
+}
+
+/// One probe per component path of the known profiles, created once as the names are leaked
+fn probes() -> &'static [Probe] {
+    static PROBES: OnceLock<Vec<Probe>> = OnceLock::new();
+    PROBES.get_or_init(|| {
+        let mut probes: Vec<Probe> = Vec::new();
+        for profile in XrInteractionProfile::ALL {
+            let profile_path: &'static str = Box::leak(profile.path().to_owned().into_boxed_str());
+            for component in profile.components() {
+                match probes.iter_mut().find(|p| p.component == component) {
+                    Some(probe) => probe.profiles.push(profile_path),
+                    None => probes.push(Probe {
+                        name: Box::leak(format!("probe_{}", probes.len()).into_boxed_str()),
+                        component,
+                        profiles: vec![profile_path],
+                    }),
+                }
+            }
+        }
+        probes
+    })
+}
+
+fn add_probe_action_set(mut setup: ResMut<SetupActionSets>) {
+    let set = setup.add_action_set(PROBE_ACTION_SET, "Rebinding".into(), u32::MAX);
+    for probe in probes() {
+        let action_type = match probe.component.action_type {
+            ActionType::Vec2 => ActionType::Vec2,
+            _ => ActionType::Bool,
+        };
+        set.new_action(
+            probe.name,
+            probe.component.path.clone(),
+            action_type,
+            ActionHandednes::Single,
+        );
+        let path: &'static str = Box::leak(probe.component.path.clone().into_boxed_str());
+        for profile in &probe.profiles {
+            set.suggest_binding(profile, &[XrBinding::new(probe.name, path)]);
+        }
+    }
+}
+
+fn apply_binding_overrides(overrides: Res<XrBindingOverrides>, mut setup: ResMut<SetupActionSets>) {
+    overrides.apply_to(&mut setup);
+}
+
+/// The probes only take the inputs away from the other sets while listening
+fn disable_probe_action_set(mut action_sets: ResMut<XrActionSets>) {
+    let _ = action_sets.set_enabled(PROBE_ACTION_SET, false);
+}
+
+fn stop_listening(mut listener: ResMut<XrBindingListener>) {
+    *listener = default();
+}
+
+fn start_listening(
+    mut listener: ResMut<XrBindingListener>,
+    mut listen: EventReader<ListenForXrBinding>,
+    mut stop: EventReader<StopListeningForXrBinding>,
+    mut action_sets: ResMut<XrActionSets>,
+) {
+    if stop.read().count() > 0 && listener.is_listening() {
+        *listener = default();
+        let _ = action_sets.set_enabled(PROBE_ACTION_SET, false);
+    }
+    for target in listen.read() {
+        let action_type = match action_sets.typed_action(target.action_set, target.action) {
+            Ok(TypedAction::Bool(_)) => ActionType::Bool,
+            Ok(TypedAction::F32(_)) => ActionType::F32,
+            Ok(TypedAction::Vec2(_)) => ActionType::Vec2,
+            Ok(TypedAction::PoseF(_) | TypedAction::Haptic(_)) => {
+                warn!(
+                    "Unable to rebind {}/{}, only bool, float and vector2 actions can be rebound",
+                    target.action_set, target.action
+                );
+                continue;
+            }
+            Err(err) => {
+                warn!(
+                    "Unable to rebind {}/{}: {}",
+                    target.action_set, target.action, err
+                );
+                continue;
+            }
+        };
+        *listener = XrBindingListener {
+            target: Some(*target),
+            action_type: Some(action_type),
+            ..default()
+        };
+        if action_sets.set_enabled(PROBE_ACTION_SET, true).is_err() {
+            warn!("The probe action set doesn't exist, XrRebindingPlugin was added during the session");
+            *listener = default();
+        }
+    }
+}
+
+fn capture_binding(
+    mut listener: ResMut<XrBindingListener>,
+    mut action_sets: ResMut<XrActionSets>,
+    session: Res<XrSession>,
+    instance: Res<XrInstance>,
+    mut overrides: ResMut<XrBindingOverrides>,
+    mut captured: EventWriter<XrBindingCaptured>,
+) {
+    let (Some(target), Some(action_type)) = (listener.target, listener.action_type) else {
+        return;
+    };
+    if !action_sets.has_synced() {
+        return;
+    }
+    let active = probes()
+        .iter()
+        .filter(|probe| {
+            let name = probe.name;
+            match probe.component.action_type {
+                ActionType::Vec2 => action_sets
+                    .get_value::<xr::Vector2f>(&session, PROBE_ACTION_SET, name, xr::Path::NULL)
+                    .is_ok_and(|value| value.x.hypot(value.y) > 0.5),
+                _ => action_sets
+                    .get_value::<bool>(&session, PROBE_ACTION_SET, name, xr::Path::NULL)
+                    .unwrap_or(false),
+            }
+        })
+        .collect::<Vec<_>>();
+    // inputs that were held when listening started only count after they were released
+    if !listener.armed {
+        listener.armed = true;
+        listener.held = active.iter().map(|probe| probe.name).collect();
+        return;
+    }
+    listener
+        .held
+        .retain(|name| active.iter().any(|probe| probe.name == *name));
+    let Some(probe) = active
+        .into_iter()
+        .find(|probe| !listener.held.contains(probe.name) && probe.component.accepts(action_type))
+    else {
+        return;
+    };
+    let hand = probe.component.hand();
+    let profile = match session.current_interaction_profile(&instance, hand) {
+        Ok(Some(profile)) => profile,
+        Ok(None) => return,
+        Err(err) => {
+            warn!("Unable to get the current interaction profile: {}", err);
+            return;
+        }
+    };
+    let Some(&profile_path) = probe.profiles.iter().find(|p| **p == profile.path()) else {
+        debug!(
+            "{} isn't a known profile, ignoring the input",
+            profile.path()
+        );
+        return;
+    };
+    // the runtime knows which input actually activated the probe
+    let source = action_sets
+        .bound_sources(
+            &session,
+            &instance,
+            PROBE_ACTION_SET,
+            probe.name,
+            XrSourceNameComponents {
+                interaction_profile: false,
+                ..default()
+            },
+        )
+        .ok()
+        .and_then(|sources| sources.into_iter().next());
+    let (path, localized_name) = match source {
+        Some(source) => (source.path, source.localized_name),
+        None => (probe.component.path.clone(), probe.component.path.clone()),
+    };
+    let handed = action_sets
+        .action_set(target.action_set)
+        .is_ok_and(|set| set.handed_actions.contains(target.action));
+    let mut paths = vec![path.clone()];
+    if handed {
+        let mirrored = match hand {
+            super::Hand::Left => path.replacen("/user/hand/left/", "/user/hand/right/", 1),
+            super::Hand::Right => path.replacen("/user/hand/right/", "/user/hand/left/", 1),
+        };
+        if profile
+            .components()
+            .iter()
+            .any(|component| component.path == mirrored)
+        {
+            paths.push(mirrored);
+        }
+    }
+    info!(
+        "Binding {}/{} to {:?} for {}",
+        target.action_set, target.action, paths, profile_path
+    );
+    overrides.set(target.action_set, target.action, profile_path, paths);
+    let paths = overrides
+        .get(target.action_set, target.action, profile_path)
+        .map(|o| o.paths.clone())
+        .unwrap_or_default();
+    captured.send(XrBindingCaptured {
+        action_set: target.action_set,
+        action: target.action,
+        profile: profile_path,
+        paths,
+        localized_name,
+    });
+    *listener = default();
+    let _ = action_sets.set_enabled(PROBE_ACTION_SET, false);
+}
+
+/// Ends the running session and starts a new one, the app stays open in between
+#[derive(Resource)]
+struct XrBindingRestart {
+    exit_type: ExitAppOnSessionExit,
+    started: bool,
+}
+
+fn restart_session(
+    mut commands: Commands,
+    mut apply: EventReader<ApplyXrBindingOverrides>,
+    restart: Option<ResMut<XrBindingRestart>>,
+    status: Res<XrStatus>,
+    exit_type: Res<ExitAppOnSessionExit>,
+    mut end_session: EventWriter<EndXrSession>,
+    mut start_session: EventWriter<StartXrSession>,
+) {
+    let Some(mut restart) = restart else {
+        if apply.read().count() == 0 {
+            return;
+        }
+        match *status {
+            XrStatus::Enabled => {
+                info!("Restarting the session to apply the binding overrides");
+                commands.insert_resource(XrBindingRestart {
+                    exit_type: *exit_type,
+                    started: false,
+                });
+                commands.insert_resource(ExitAppOnSessionExit::Never);
+                end_session.send_default();
+            }
+            _ => info!("The binding overrides are applied when the next session starts"),
+        }
+        return;
+    };
+    apply.clear();
+    match *status {
+        XrStatus::Disabled if !restart.started => {
+            restart.started = true;
+            start_session.send_default();
+        }
+        XrStatus::Enabled if restart.started => {
+            commands.insert_resource(restart.exit_type);
+            commands.remove_resource::<XrBindingRestart>();
+        }
+        XrStatus::Unavailable(_) => {
+            commands.insert_resource(restart.exit_type);
+            commands.remove_resource::<XrBindingRestart>();
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,94 @@
+//! Poses of tracked entities relative to each other, like the controllers relative to the head
+//! for holster slots, located by the runtime with [`XrRelativePoses`].
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::input::XrInput;
+use crate::resources::{XrFrameState, XrTime};
+
+use super::oculus_touch::OculusController;
+use super::trackers::{
+    OpenXRHMD, OpenXRLeftController, OpenXRRightController, OpenXRTrackingRoot, XrSpaceState,
+};
+use super::Hand;
+
+/// Locates tracked entities relative to each other, all at the same pose time, so the results
+/// of one system are consistent with each other. Unlike composing [`Transform`]s, which are
+/// only updated once per frame, the poses can't be stale or already moved by other systems.
+/// Only available while XR is running.
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub struct XrRelativePoses<'w, 's> {
+    oculus_controller: Option<Res<'w, OculusController>>,
+    frame_state: Res<'w, XrFrameState>,
+    xr_input: Res<'w, XrInput>,
+    tracked: Query<
+        'w,
+        's,
+        (
+            Has<OpenXRTrackingRoot>,
+            Has<OpenXRHMD>,
+            Has<OpenXRLeftController>,
+            Has<OpenXRRightController>,
+        ),
+    >,
+}
+
+impl XrRelativePoses<'_, '_> {
+    /// The predicted display time of the frame, shifted by the
+    /// [`XrPredictionOffset`](crate::resources::XrPredictionOffset)
+    pub fn pose_time(&self) -> XrTime {
+        self.xr_input
+            .pose_time(self.frame_state.predicted_display_time.into())
+    }
+
+    /// The space an entity is tracked with, for the tracking root, the headset and the
+    /// controllers, which are located at their grip pose
+    pub fn space(&self, entity: Entity) -> Option<&xr::Space> {
+        let (root, head, left, right) = self.tracked.get(entity).ok()?;
+        if root {
+            Some(&self.xr_input.stage)
+        } else if head {
+            Some(&self.xr_input.head)
+        } else if left {
+            self.grip_space(Hand::Left)
+        } else if right {
+            self.grip_space(Hand::Right)
+        } else {
+            None
+        }
+    }
+
+    fn grip_space(&self, hand: Hand) -> Option<&xr::Space> {
+        let spaces = self.oculus_controller.as_ref()?.grip_space.as_ref()?;
+        Some(match hand {
+            Hand::Left => &spaces.left,
+            Hand::Right => &spaces.right,
+        })
+    }
+
+    /// `space` relative to `base` at the pose time, everything invalid if locating failed
+    pub fn locate(&self, space: &xr::Space, base: &xr::Space) -> XrSpaceState {
+        XrSpaceState::locate(space, base, self.pose_time()).unwrap_or_default()
+    }
+
+    /// The headset relative to the tracking root
+    pub fn head_in_root(&self) -> XrSpaceState {
+        self.locate(&self.xr_input.head, &self.xr_input.stage)
+    }
+
+    /// The grip pose of a controller relative to the headset
+    pub fn controller_in_head(&self, hand: Hand) -> XrSpaceState {
+        match self.grip_space(hand) {
+            Some(space) => self.locate(space, &self.xr_input.head),
+            None => XrSpaceState::default(),
+        }
+    }
+
+    /// The pose of `entity` relative to `other`, `None` unless both have a [`space`](Self::space)
+    pub fn relative_to(&self, entity: Entity, other: Entity) -> Option<XrSpaceState> {
+        Some(self.locate(self.space(entity)?, self.space(other)?))
+    }
+}
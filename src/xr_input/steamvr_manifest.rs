@@ -0,0 +1,706 @@
+use std::path::Path;
+
+use bevy::log::{info, warn};
+use bevy::prelude::{AssetEvent, Assets, EventReader, Res};
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use super::actions::ActionType;
+use super::binding_assets::{
+    leak_names, validate, RawAction, RawActionSet, RawBindings, XrBindings, XrBindingsHandle,
+    XrBindingsLoaderError,
+};
+use super::interaction_profiles::XrInteractionProfile;
+
+/// An action manifest with a binding file per interaction profile in the layout SteamVR uses
+/// for rebinding. The action set priorities, the handedness of actions and the interaction
+/// profile of a binding file are stored in extra fields SteamVR ignores.
+#[derive(Clone, Debug)]
+pub struct XrSteamVrManifest {
+    pub manifest: String,
+    /// File names and contents, referenced by the manifest
+    pub binding_files: Vec<(String, String)>,
+}
+
+impl XrSteamVrManifest {
+    pub const MANIFEST_FILE: &'static str = "actions.json";
+
+    /// Writes the manifest as [`XrSteamVrManifest::MANIFEST_FILE`] and the binding files into `dir`
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<()> {
+        std::fs::write(dir.join(Self::MANIFEST_FILE), &self.manifest)?;
+        for (file_name, contents) in &self.binding_files {
+            std::fs::write(dir.join(file_name), contents)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    default_bindings: Vec<DefaultBinding>,
+    actions: Vec<ManifestAction>,
+    action_sets: Vec<ManifestActionSet>,
+    #[serde(default)]
+    localization: Vec<HashMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DefaultBinding {
+    controller_type: String,
+    binding_url: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestAction {
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+    #[serde(default)]
+    handed: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestActionSet {
+    name: String,
+    usage: String,
+    #[serde(default)]
+    priority: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BindingFile {
+    controller_type: String,
+    #[serde(default)]
+    interaction_profile: Option<String>,
+    bindings: HashMap<String, SetBindings>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SetBindings {
+    #[serde(default)]
+    sources: Vec<Source>,
+    #[serde(default)]
+    poses: Vec<PathOutput>,
+    #[serde(default)]
+    haptics: Vec<PathOutput>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Source {
+    path: String,
+    mode: String,
+    inputs: HashMap<String, Output>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Output {
+    output: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PathOutput {
+    output: String,
+    path: String,
+}
+
+fn controller_type(profile: &str) -> String {
+    match XrInteractionProfile::from_path(profile) {
+        XrInteractionProfile::OculusTouch => "oculus_touch".into(),
+        XrInteractionProfile::ValveIndex => "knuckles".into(),
+        XrInteractionProfile::HtcVive => "vive_controller".into(),
+        XrInteractionProfile::MicrosoftMotion => "holographic_controller".into(),
+        XrInteractionProfile::KhrSimple => "khr_simple_controller".into(),
+        XrInteractionProfile::Other(path) => path
+            .trim_start_matches("/interaction_profiles/")
+            .replace('/', "_"),
+    }
+}
+
+fn action_type_name(action_type: ActionType) -> &'static str {
+    match action_type {
+        ActionType::Bool => "boolean",
+        ActionType::F32 => "vector1",
+        ActionType::Vec2 => "vector2",
+        ActionType::PoseF => "pose",
+        ActionType::Haptic => "vibration",
+    }
+}
+
+fn action_type_from_name(name: &str) -> Option<ActionType> {
+    Some(match name {
+        "boolean" => ActionType::Bool,
+        "vector1" => ActionType::F32,
+        "vector2" => ActionType::Vec2,
+        "pose" => ActionType::PoseF,
+        "vibration" => ActionType::Haptic,
+        _ => return None,
+    })
+}
+
+fn action_path(action_set: &str, action: &str, action_type: ActionType) -> String {
+    match action_type {
+        ActionType::Haptic => format!("/actions/{}/out/{}", action_set, action),
+        _ => format!("/actions/{}/in/{}", action_set, action),
+    }
+}
+
+/// Splits `/actions/<set>/in/<action>` into the set and action name
+fn parse_action_path(path: &str) -> Option<(&str, &str)> {
+    let mut parts = path.strip_prefix("/actions/")?.split('/');
+    let set = parts.next()?;
+    let _direction = parts.next().filter(|dir| *dir == "in" || *dir == "out")?;
+    let action = parts.next()?;
+    parts.next().is_none().then_some((set, action))
+}
+
+/// Adds an openxr binding path to the SteamVR bindings of a set
+fn add_binding(bindings: &mut SetBindings, output: String, path: &str) {
+    if let Some(pose) = path.strip_suffix("/pose") {
+        // ".../input/grip/pose" is ".../pose/raw" in SteamVR
+        let (hand, name) = pose.rsplit_once("/input/").unwrap_or((pose, "raw"));
+        let name = match name {
+            "grip" => "raw",
+            "aim" => "tip",
+            name => name,
+        };
+        bindings.poses.push(PathOutput {
+            output,
+            path: format!("{}/pose/{}", hand, name),
+        });
+        return;
+    }
+    if path.contains("/output/") {
+        bindings.haptics.push(PathOutput {
+            output,
+            path: path.into(),
+        });
+        return;
+    }
+    let (source, input) = match path.rsplit_once('/') {
+        Some((source, "value")) => (source, "pull"),
+        Some((source, component @ ("click" | "touch" | "force" | "x" | "y"))) => {
+            (source, component)
+        }
+        _ => (path, "position"),
+    };
+    let mode = match input {
+        "pull" => "trigger",
+        "force" => "force_sensor",
+        "position" | "x" | "y" if source.ends_with("trackpad") => "trackpad",
+        "position" | "x" | "y" => "joystick",
+        _ => "button",
+    };
+    bindings.sources.push(Source {
+        path: source.into(),
+        mode: mode.into(),
+        inputs: [(input.into(), Output { output })].into_iter().collect(),
+    });
+}
+
+impl XrBindings {
+    /// Converts the action sets and their suggested bindings to a SteamVR action manifest
+    pub fn to_steamvr_manifest(&self) -> XrSteamVrManifest {
+        let mut actions = Vec::new();
+        let mut localization = HashMap::new();
+        localization.insert("language_tag".to_string(), "en_US".to_string());
+        let mut files: Vec<(&str, BindingFile)> = Vec::new();
+        for set in &self.action_sets {
+            localization.insert(format!("/actions/{}", set.name), set.pretty_name.clone());
+            for action in &set.actions {
+                let output = action_path(set.name, action.name, action.action_type);
+                localization.insert(output.clone(), action.pretty_name.clone());
+                actions.push(ManifestAction {
+                    name: output.clone(),
+                    ty: action_type_name(action.action_type).into(),
+                    handed: action.handed,
+                });
+                for (profile, paths) in &action.bindings {
+                    let file = match files.iter_mut().find(|(p, _)| p == profile) {
+                        Some((_, file)) => file,
+                        None => {
+                            files.push((
+                                profile,
+                                BindingFile {
+                                    controller_type: controller_type(profile),
+                                    interaction_profile: Some(profile.to_string()),
+                                    bindings: HashMap::new(),
+                                },
+                            ));
+                            &mut files.last_mut().unwrap().1
+                        }
+                    };
+                    let set_bindings = file
+                        .bindings
+                        .entry(format!("/actions/{}", set.name))
+                        .or_default();
+                    for path in paths {
+                        add_binding(set_bindings, output.clone(), path);
+                    }
+                }
+            }
+        }
+        let binding_files = files
+            .into_iter()
+            .map(|(_, file)| {
+                let name = format!("bindings_{}.json", file.controller_type);
+                (name, file)
+            })
+            .collect::<Vec<_>>();
+        let manifest = Manifest {
+            default_bindings: binding_files
+                .iter()
+                .map(|(name, file)| DefaultBinding {
+                    controller_type: file.controller_type.clone(),
+                    binding_url: name.clone(),
+                })
+                .collect(),
+            actions,
+            action_sets: self
+                .action_sets
+                .iter()
+                .map(|set| ManifestActionSet {
+                    name: format!("/actions/{}", set.name),
+                    usage: "leftright".into(),
+                    priority: set.priority,
+                })
+                .collect(),
+            localization: vec![localization],
+        };
+        XrSteamVrManifest {
+            manifest: serde_json::to_string_pretty(&manifest).unwrap(),
+            binding_files: binding_files
+                .into_iter()
+                .map(|(name, file)| (name, serde_json::to_string_pretty(&file).unwrap()))
+                .collect(),
+        }
+    }
+
+    /// Reads action sets and bindings from a SteamVR action manifest,
+    /// `binding_files` holds the contents of the files the manifest references by their name.
+    /// Binding files of controller types without a known interaction profile are skipped.
+    pub fn from_steamvr_manifest(
+        manifest: &str,
+        binding_files: &[(&str, &str)],
+    ) -> Result<XrBindings, XrBindingsLoaderError> {
+        let manifest: Manifest =
+            serde_json::from_str(manifest).map_err(XrBindingsLoaderError::Json)?;
+        let localized = |name: &str| {
+            manifest
+                .localization
+                .iter()
+                .find_map(|strings| strings.get(name).cloned())
+        };
+        let mut raw = RawBindings {
+            action_sets: Vec::new(),
+        };
+        for set in &manifest.action_sets {
+            let name = set.name.strip_prefix("/actions/").unwrap_or(&set.name);
+            raw.action_sets.push(RawActionSet {
+                name: name.into(),
+                pretty_name: localized(&set.name).unwrap_or_else(|| name.into()),
+                priority: set.priority,
+                actions: Vec::new(),
+            });
+        }
+        for action in &manifest.actions {
+            let invalid = |reason| XrBindingsLoaderError::Invalid {
+                action_set: action.name.clone(),
+                action: None,
+                reason,
+            };
+            let (set_name, name) = parse_action_path(&action.name).ok_or_else(|| {
+                invalid("action names have to look like /actions/<set>/in/<action>")
+            })?;
+            let action_type =
+                action_type_from_name(&action.ty).ok_or_else(|| invalid("unknown action type"))?;
+            let set = raw
+                .action_sets
+                .iter_mut()
+                .find(|set| set.name == set_name)
+                .ok_or_else(|| invalid("the action set isn't in the manifest"))?;
+            set.actions.push(RawAction {
+                name: name.into(),
+                pretty_name: localized(&action.name).unwrap_or_else(|| name.into()),
+                action_type,
+                handed: action.handed,
+                bindings: HashMap::new(),
+            });
+        }
+        for default_binding in &manifest.default_bindings {
+            let Some((_, contents)) = binding_files
+                .iter()
+                .find(|(name, _)| *name == default_binding.binding_url)
+            else {
+                warn!(
+                    "Missing SteamVR binding file {}",
+                    default_binding.binding_url
+                );
+                continue;
+            };
+            let file: BindingFile =
+                serde_json::from_str(contents).map_err(XrBindingsLoaderError::Json)?;
+            let profile = match &file.interaction_profile {
+                Some(profile) => profile.clone(),
+                None => match XrInteractionProfile::ALL
+                    .into_iter()
+                    .find(|profile| controller_type(profile.path()) == file.controller_type)
+                {
+                    Some(profile) => profile.path().into(),
+                    None => {
+                        warn!(
+                            "Skipping the bindings for the unknown controller type {}",
+                            file.controller_type
+                        );
+                        continue;
+                    }
+                },
+            };
+            for set_bindings in file.bindings.values() {
+                let sources = set_bindings.sources.iter().flat_map(|source| {
+                    source.inputs.iter().map(|(input, output)| {
+                        let path = match input.as_str() {
+                            "position" => source.path.clone(),
+                            "pull" => format!("{}/value", source.path),
+                            input => format!("{}/{}", source.path, input),
+                        };
+                        (&output.output, path)
+                    })
+                });
+                let poses = set_bindings.poses.iter().map(|pose| {
+                    let path = match pose.path.rsplit_once("/pose/") {
+                        Some((hand, "raw")) => format!("{}/input/grip/pose", hand),
+                        Some((hand, "tip")) => format!("{}/input/aim/pose", hand),
+                        Some((hand, name)) => format!("{}/input/{}/pose", hand, name),
+                        None => pose.path.clone(),
+                    };
+                    (&pose.output, path)
+                });
+                let haptics = set_bindings
+                    .haptics
+                    .iter()
+                    .map(|haptic| (&haptic.output, haptic.path.clone()));
+                for (output, path) in sources.chain(poses).chain(haptics) {
+                    let unknown_action = || XrBindingsLoaderError::Invalid {
+                        action_set: output.clone(),
+                        action: None,
+                        reason: "the bound action isn't in the manifest",
+                    };
+                    let (set_name, name) = parse_action_path(output).ok_or_else(unknown_action)?;
+                    let action = raw
+                        .action_sets
+                        .iter_mut()
+                        .find(|set| set.name == set_name)
+                        .and_then(|set| set.actions.iter_mut().find(|action| action.name == name))
+                        .ok_or_else(unknown_action)?;
+                    action
+                        .bindings
+                        .entry(profile.clone())
+                        .or_default()
+                        .push(path);
+                }
+            }
+        }
+        validate(&raw)?;
+        Ok(leak_names(raw))
+    }
+}
+
+/// Writes the SteamVR manifest next to the executable once the bindings are loaded
+pub(super) fn write_steamvr_manifest(
+    mut events: EventReader<AssetEvent<XrBindings>>,
+    handle: Option<Res<XrBindingsHandle>>,
+    bindings: Res<Assets<XrBindings>>,
+) {
+    let Some(handle) = handle else {
+        events.clear();
+        return;
+    };
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&handle.0) && !event.is_modified(&handle.0) {
+            continue;
+        }
+        let Some(bindings) = bindings.get(&handle.0) else {
+            continue;
+        };
+        let dir = match std::env::current_exe() {
+            Ok(exe) => exe.parent().map(Path::to_path_buf).unwrap_or_default(),
+            Err(err) => {
+                warn!(
+                    "Unable to find the executable for the SteamVR manifest: {}",
+                    err
+                );
+                continue;
+            }
+        };
+        match bindings.to_steamvr_manifest().write_to(&dir) {
+            Ok(()) => info!("Wrote the SteamVR action manifest to {}", dir.display()),
+            Err(err) => warn!("Unable to write the SteamVR action manifest: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::binding_assets::{XrBindingsAction, XrBindingsActionSet};
+    use super::*;
+
+    const TOUCH: &str = "/interaction_profiles/oculus/touch_controller";
+    const INDEX: &str = "/interaction_profiles/valve/index_controller";
+    const CUSTOM: &str = "/interaction_profiles/example/custom_controller";
+
+    fn action(
+        name: &'static str,
+        action_type: ActionType,
+        handed: bool,
+        bindings: Vec<(&'static str, Vec<&'static str>)>,
+    ) -> XrBindingsAction {
+        XrBindingsAction {
+            name,
+            pretty_name: format!("Pretty {}", name),
+            action_type,
+            handed,
+            bindings,
+        }
+    }
+
+    fn bindings() -> XrBindings {
+        XrBindings {
+            action_sets: vec![
+                XrBindingsActionSet {
+                    name: "gameplay",
+                    pretty_name: "Gameplay".into(),
+                    priority: 2,
+                    actions: vec![
+                        action(
+                            "jump",
+                            ActionType::Bool,
+                            false,
+                            vec![
+                                (TOUCH, vec!["/user/hand/right/input/a/click"]),
+                                (INDEX, vec!["/user/hand/right/input/trackpad/force"]),
+                            ],
+                        ),
+                        action(
+                            "fire",
+                            ActionType::F32,
+                            true,
+                            vec![
+                                (
+                                    TOUCH,
+                                    vec![
+                                        "/user/hand/left/input/trigger/value",
+                                        "/user/hand/right/input/trigger/value",
+                                    ],
+                                ),
+                                (CUSTOM, vec!["/user/hand/right/input/trigger/touch"]),
+                            ],
+                        ),
+                        action(
+                            "move",
+                            ActionType::Vec2,
+                            false,
+                            vec![
+                                (TOUCH, vec!["/user/hand/left/input/thumbstick"]),
+                                (INDEX, vec!["/user/hand/left/input/trackpad"]),
+                            ],
+                        ),
+                        action(
+                            "strafe",
+                            ActionType::F32,
+                            false,
+                            vec![(INDEX, vec!["/user/hand/left/input/thumbstick/x"])],
+                        ),
+                    ],
+                },
+                XrBindingsActionSet {
+                    name: "hands",
+                    pretty_name: "Hands".into(),
+                    priority: 0,
+                    actions: vec![
+                        action(
+                            "grip",
+                            ActionType::PoseF,
+                            true,
+                            vec![(TOUCH, vec!["/user/hand/left/input/grip/pose"])],
+                        ),
+                        action(
+                            "aim",
+                            ActionType::PoseF,
+                            true,
+                            vec![(INDEX, vec!["/user/hand/right/input/aim/pose"])],
+                        ),
+                        action(
+                            "rumble",
+                            ActionType::Haptic,
+                            true,
+                            vec![(TOUCH, vec!["/user/hand/left/output/haptic"])],
+                        ),
+                        action("unbound", ActionType::Bool, false, vec![]),
+                    ],
+                },
+            ],
+        }
+    }
+
+    type Flattened = Vec<(String, String, u32, Vec<FlatAction>)>;
+    type FlatAction = (String, String, ActionType, bool, Vec<(String, Vec<String>)>);
+
+    /// The bindings without the order of the profiles, which the manifest doesn't keep
+    fn flatten(bindings: &XrBindings) -> Flattened {
+        bindings
+            .action_sets
+            .iter()
+            .map(|set| {
+                let actions = set
+                    .actions
+                    .iter()
+                    .map(|action| {
+                        let mut profiles = action
+                            .bindings
+                            .iter()
+                            .map(|(profile, paths)| {
+                                let paths = paths.iter().map(|path| path.to_string()).collect();
+                                (profile.to_string(), paths)
+                            })
+                            .collect::<Vec<_>>();
+                        profiles.sort();
+                        (
+                            action.name.to_string(),
+                            action.pretty_name.clone(),
+                            action.action_type,
+                            action.handed,
+                            profiles,
+                        )
+                    })
+                    .collect();
+                (
+                    set.name.to_string(),
+                    set.pretty_name.clone(),
+                    set.priority,
+                    actions,
+                )
+            })
+            .collect()
+    }
+
+    fn import(manifest: &XrSteamVrManifest) -> Result<XrBindings, XrBindingsLoaderError> {
+        let files = manifest
+            .binding_files
+            .iter()
+            .map(|(name, contents)| (name.as_str(), contents.as_str()))
+            .collect::<Vec<_>>();
+        XrBindings::from_steamvr_manifest(&manifest.manifest, &files)
+    }
+
+    #[test]
+    fn export_import_round_trip() {
+        let bindings = bindings();
+        let manifest = bindings.to_steamvr_manifest();
+        let mut names = manifest
+            .binding_files
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "bindings_example_custom_controller.json",
+                "bindings_knuckles.json",
+                "bindings_oculus_touch.json"
+            ]
+        );
+        let imported = import(&manifest).unwrap();
+        assert_eq!(flatten(&imported), flatten(&bindings));
+        // exporting the imported bindings again doesn't change them
+        assert_eq!(
+            flatten(&import(&imported.to_steamvr_manifest()).unwrap()),
+            flatten(&bindings)
+        );
+    }
+
+    #[test]
+    fn steamvr_paths() {
+        let manifest = bindings().to_steamvr_manifest();
+        let (_, touch) = manifest
+            .binding_files
+            .iter()
+            .find(|(name, _)| name == "bindings_oculus_touch.json")
+            .unwrap();
+        let file: BindingFile = serde_json::from_str(touch).unwrap();
+        let gameplay = &file.bindings["/actions/gameplay"];
+        let fire = gameplay
+            .sources
+            .iter()
+            .find(|source| source.path == "/user/hand/left/input/trigger")
+            .unwrap();
+        assert_eq!(fire.mode, "trigger");
+        assert_eq!(fire.inputs["pull"].output, "/actions/gameplay/in/fire");
+        let hands = &file.bindings["/actions/hands"];
+        assert_eq!(hands.poses[0].path, "/user/hand/left/pose/raw");
+        assert_eq!(hands.haptics[0].output, "/actions/hands/out/rumble");
+    }
+
+    #[test]
+    fn bindings_without_interaction_profile() {
+        // written by the SteamVR binding UI, which doesn't know the extra field
+        let manifest = r#"{
+            "default_bindings": [
+                { "controller_type": "knuckles", "binding_url": "knuckles.json" },
+                { "controller_type": "gamepad", "binding_url": "gamepad.json" }
+            ],
+            "actions": [{ "name": "/actions/main/in/select", "type": "boolean" }],
+            "action_sets": [{ "name": "/actions/main", "usage": "leftright" }]
+        }"#;
+        let knuckles = r#"{
+            "controller_type": "knuckles",
+            "bindings": {
+                "/actions/main": {
+                    "sources": [{
+                        "path": "/user/hand/right/input/a",
+                        "mode": "button",
+                        "inputs": { "click": { "output": "/actions/main/in/select" } }
+                    }]
+                }
+            }
+        }"#;
+        let gamepad = r#"{ "controller_type": "gamepad", "bindings": {} }"#;
+        let imported = XrBindings::from_steamvr_manifest(
+            manifest,
+            &[("knuckles.json", knuckles), ("gamepad.json", gamepad)],
+        )
+        .unwrap();
+        let set = &imported.action_sets[0];
+        assert_eq!((set.name, set.pretty_name.as_str()), ("main", "main"));
+        assert_eq!(
+            set.actions[0].bindings,
+            [(INDEX, vec!["/user/hand/right/input/a/click"])]
+        );
+    }
+
+    #[test]
+    fn unknown_actions_are_rejected() {
+        let manifest = r#"{
+            "default_bindings": [{ "controller_type": "knuckles", "binding_url": "knuckles.json" }],
+            "actions": [],
+            "action_sets": [{ "name": "/actions/main", "usage": "leftright" }]
+        }"#;
+        let knuckles = r#"{
+            "controller_type": "knuckles",
+            "bindings": {
+                "/actions/main": {
+                    "poses": [{ "output": "/actions/main/in/missing", "path": "/user/hand/left/pose/raw" }]
+                }
+            }
+        }"#;
+        let result = XrBindings::from_steamvr_manifest(manifest, &[("knuckles.json", knuckles)]);
+        assert!(matches!(
+            result,
+            Err(XrBindingsLoaderError::Invalid {
+                reason: "the bound action isn't in the manifest",
+                ..
+            })
+        ));
+    }
+}
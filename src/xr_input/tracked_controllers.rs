@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::{
+    input::XrInput,
+    resources::{XrFrameState, XrSession},
+    xr_init::{xr_only, XrCleanup, XrPostSetup, XrSessionState},
+};
+
+use super::{
+    actions::{XrActionSets, XrActionSync},
+    oculus_touch::OculusController,
+    trackers::{OpenXRController, OpenXRLeftController, OpenXRRightController, OpenXRTrackingRoot},
+    Hand, XrTrackingUpdate,
+};
+
+/// Spawns an entity with [`XrController`] and [`XrControllerInput`] for each hand below the
+/// [`OpenXRTrackingRoot`] when the session starts, so reading the controllers only takes a query.
+///
+/// They are regular [`OpenXRLeftController`] and [`OpenXRRightController`] entities, so their
+/// transform follows the grip pose and they get [`XrGripPose`](super::trackers::XrGripPose)
+/// and [`XrAimPose`](super::trackers::XrAimPose) children.
+pub struct XrControllerPlugin;
+
+impl Plugin for XrControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<XrController>()
+            .register_type::<XrControllerInput>();
+        app.add_systems(XrPostSetup, spawn_controllers);
+        app.add_systems(
+            PreUpdate,
+            update_controllers
+                .after(XrActionSync)
+                .in_set(XrTrackingUpdate)
+                .run_if(xr_only()),
+        );
+        app.add_systems(XrCleanup, cleanup_controllers);
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct XrController {
+    pub hand: Hand,
+    /// `false` while the runtime can't locate the controller, like when it's asleep,
+    /// or while the session isn't focused and the app doesn't get input
+    pub active: bool,
+}
+
+/// The common controller inputs, refreshed after the actions were synced.
+/// Everything is released while the controller is inactive.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct XrControllerInput {
+    pub trigger: f32,
+    pub trigger_touched: bool,
+    pub squeeze: f32,
+    pub thumbstick: Vec2,
+    pub thumbstick_click: bool,
+    pub thumbstick_touched: bool,
+    /// X on the left and A on the right controller
+    pub primary_button: bool,
+    /// Y on the left and B on the right controller
+    pub secondary_button: bool,
+}
+
+fn spawn_controllers(mut commands: Commands, root: Query<Entity, With<OpenXRTrackingRoot>>) {
+    let Ok(root) = root.get_single() else {
+        warn!("No tracking root to spawn the controllers below");
+        return;
+    };
+    commands.entity(root).with_children(|parent| {
+        parent.spawn((
+            SpatialBundle::default(),
+            Name::new("Left Controller"),
+            XrController {
+                hand: Hand::Left,
+                active: false,
+            },
+            XrControllerInput::default(),
+            OpenXRLeftController,
+            OpenXRController,
+        ));
+        parent.spawn((
+            SpatialBundle::default(),
+            Name::new("Right Controller"),
+            XrController {
+                hand: Hand::Right,
+                active: false,
+            },
+            XrControllerInput::default(),
+            OpenXRRightController,
+            OpenXRController,
+        ));
+    });
+}
+
+fn update_controllers(
+    oculus_controller: Res<OculusController>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    session: Res<XrSession>,
+    session_state: Res<XrSessionState>,
+    action_sets: Res<XrActionSets>,
+    mut controllers: Query<(&mut XrController, &mut XrControllerInput)>,
+) {
+    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let focused = session_state.0 == xr::SessionState::FOCUSED;
+    for (mut xr_controller, mut input) in &mut controllers {
+        let hand = xr_controller.hand;
+        let active = focused && controller.grip_state(hand).position.is_some();
+        if xr_controller.active != active {
+            xr_controller.active = active;
+        }
+        if !active {
+            input.set_if_neq(XrControllerInput::default());
+            continue;
+        }
+        let thumbstick = controller.thumbstick(hand);
+        let (primary_button, secondary_button) = match hand {
+            Hand::Left => (controller.x_button(), controller.y_button()),
+            Hand::Right => (controller.a_button(), controller.b_button()),
+        };
+        input.set_if_neq(XrControllerInput {
+            trigger: controller.trigger(hand),
+            trigger_touched: controller.trigger_touched(hand),
+            squeeze: controller.squeeze(hand),
+            thumbstick: Vec2::new(thumbstick.x, thumbstick.y),
+            thumbstick_click: thumbstick.click,
+            thumbstick_touched: controller.thumbstick_touch(hand),
+            primary_button,
+            secondary_button,
+        });
+    }
+}
+
+fn cleanup_controllers(mut commands: Commands, controllers: Query<Entity, With<XrController>>) {
+    for entity in &controllers {
+        commands.entity(entity).despawn_recursive();
+    }
+}
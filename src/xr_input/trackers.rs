@@ -1,36 +1,207 @@
 use bevy::hierarchy::Parent;
 use bevy::log::{debug, info};
-use bevy::math::Quat;
+use bevy::math::{Quat, Vec3A};
 use bevy::prelude::{
-    Added, BuildChildren, Commands, Component, Entity, Query, Res, Transform, Vec3, With, Without,
+    Added, BuildChildren, Commands, Component, Entity, Name, Or, Query, ReflectComponent, Res,
+    SpatialBundle, Transform, Vec3, With, Without,
 };
+use bevy::reflect::Reflect;
+
+use openxr as xr;
 
 use crate::{
     input::XrInput,
-    resources::{XrFrameState, XrSession},
+    resources::{XrFrameState, XrSession, XrTime},
 };
 
 use super::{actions::XrActionSets, oculus_touch::OculusController, Hand, QuatConv, Vec3Conv};
 
-#[derive(Component)]
+/// Parent of every tracked entity, cameras, controllers, hands and trackers all get their poses
+/// as local transforms below it. The poses are overwritten every frame, so move the player by
+/// moving this entity, for teleports, smooth locomotion and snap turns.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct OpenXRTrackingRoot;
-#[derive(Component)]
+
+pub type XrTrackingRoot = OpenXRTrackingRoot;
+
+impl OpenXRTrackingRoot {
+    /// Rotates the root `transform` by `angle` radians around its up axis through the headset,
+    /// so turning doesn't swing the player around the center of the play space.
+    /// `head` is the headset position relative to the root.
+    pub fn rotate_around_head(transform: &mut Transform, head: Vec3, angle: f32) {
+        let pivot = Self::floor_position(transform, head);
+        let up = transform.up();
+        transform.rotate_around(pivot, Quat::from_axis_angle(*up, angle));
+    }
+
+    /// Moves the root `transform` so the headset ends up above `target`, keeping the height of
+    /// the headset above the floor. `head` is the headset position relative to the root.
+    pub fn teleport_head_to(transform: &mut Transform, head: Vec3, target: Vec3) {
+        let offset = target - Self::floor_position(transform, head);
+        transform.translation += offset;
+    }
+
+    /// The point on the floor of the play space below `head`, in world space
+    pub fn floor_position(transform: &Transform, head: Vec3) -> Vec3 {
+        transform.transform_point(Vec3::new(head.x, 0.0, head.z))
+    }
+}
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct OpenXRTracker;
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct OpenXRLeftEye;
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct OpenXRRightEye;
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct OpenXRHMD;
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct OpenXRLeftController;
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct OpenXRRightController;
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct OpenXRController;
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct AimPose(pub Transform);
 
+/// Child of a controller entity at the grip pose, where the hand holds the controller,
+/// attach held objects to it. The controller entity itself already follows the grip pose,
+/// so this entity keeps an identity transform.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct XrGripPose(pub Hand);
+
+/// Child of a controller entity at the aim pose, which points forward out of the controller,
+/// use it for pointers and raycasts. Its transform is relative to the grip pose.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct XrAimPose(pub Hand);
+
+/// Linear and angular velocity of a tracked entity, relative to the tracking root.
+/// Removed from the entity while the runtime can't provide a valid velocity.
+/// Overwritten every frame, so edits from an inspector don't stick.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct XrVelocity {
+    pub linear: Vec3A,
+    pub angular: Vec3A,
+}
+
+impl XrVelocity {
+    /// Returns `None` unless both velocities are marked as valid by the runtime
+    pub fn from_space_velocity(velocity: &xr::SpaceVelocity) -> Option<Self> {
+        if !velocity
+            .velocity_flags
+            .contains(xr::SpaceVelocityFlags::LINEAR_VALID | xr::SpaceVelocityFlags::ANGULAR_VALID)
+        {
+            return None;
+        }
+        Some(Self {
+            linear: velocity.linear_velocity.to_vec3().into(),
+            angular: velocity.angular_velocity.to_vec3().into(),
+        })
+    }
+}
+
+/// How well the runtime tracks an entity, from the location flags of its last pose lookup.
+/// Kept up to date on the controller entities, which are marked
+/// [`Untracked`](super::tracking_loss::Untracked) once it stays lost for too long.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub enum TrackingConfidence {
+    /// Position and orientation are both tracked
+    Tracked,
+    /// The pose is valid but not tracked, like a controller outside the view of the cameras
+    /// whose position is extrapolated from its IMU
+    Inferred,
+    /// The position or orientation is invalid, the pose shouldn't be used
+    #[default]
+    Lost,
+}
+
+impl TrackingConfidence {
+    pub fn from_flags(flags: xr::SpaceLocationFlags) -> Self {
+        if !flags.contains(
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
+        ) {
+            Self::Lost
+        } else if flags.contains(
+            xr::SpaceLocationFlags::POSITION_TRACKED | xr::SpaceLocationFlags::ORIENTATION_TRACKED,
+        ) {
+            Self::Tracked
+        } else {
+            Self::Inferred
+        }
+    }
+
+    /// `true` unless the pose is [`TrackingConfidence::Lost`]
+    pub fn is_valid(self) -> bool {
+        self != Self::Lost
+    }
+}
+
+/// Pose and velocity of a pose action's space relative to the tracking root,
+/// each part is `None` while the runtime marks it as invalid
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrSpaceState {
+    pub position: Option<Vec3>,
+    pub orientation: Option<Quat>,
+    pub linear_velocity: Option<Vec3>,
+    pub angular_velocity: Option<Vec3>,
+    pub confidence: TrackingConfidence,
+}
+
+impl XrSpaceState {
+    pub fn new(location: &xr::SpaceLocation, velocity: &xr::SpaceVelocity) -> Self {
+        let location_flags = location.location_flags;
+        let velocity_flags = velocity.velocity_flags;
+        Self {
+            position: location_flags
+                .contains(xr::SpaceLocationFlags::POSITION_VALID)
+                .then(|| location.pose.position.to_vec3()),
+            orientation: location_flags
+                .contains(xr::SpaceLocationFlags::ORIENTATION_VALID)
+                .then(|| verify_quat(location.pose.orientation.to_quat())),
+            linear_velocity: velocity_flags
+                .contains(xr::SpaceVelocityFlags::LINEAR_VALID)
+                .then(|| velocity.linear_velocity.to_vec3()),
+            angular_velocity: velocity_flags
+                .contains(xr::SpaceVelocityFlags::ANGULAR_VALID)
+                .then(|| velocity.angular_velocity.to_vec3()),
+            confidence: TrackingConfidence::from_flags(location_flags),
+        }
+    }
+
+    /// Locates `space` relative to `base` at `time`. Reference, action and anchor spaces are all
+    /// [`xr::Space`]s, so any two of them can be related, like a controller to the headset.
+    pub fn locate(space: &xr::Space, base: &xr::Space, time: XrTime) -> xr::Result<Self> {
+        let (location, velocity) = space.relate(base, time.into())?;
+        Ok(Self::new(&location, &velocity))
+    }
+
+    /// The pose while both position and orientation are valid
+    pub fn transform(&self) -> Option<Transform> {
+        Some(Transform::from_translation(self.position?).with_rotation(self.orientation?))
+    }
+
+    /// The velocity while both linear and angular velocity are valid
+    pub fn velocity(&self) -> Option<XrVelocity> {
+        Some(XrVelocity {
+            linear: self.linear_velocity?.into(),
+            angular: self.angular_velocity?.into(),
+        })
+    }
+}
+
 pub fn adopt_open_xr_trackers(
     query: Query<Entity, (With<OpenXRTracker>, Without<Parent>)>,
     mut commands: Commands,
@@ -141,3 +312,107 @@ pub fn update_open_xr_controllers(
         Err(_) => (),
     }
 }
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_open_xr_velocities(
+    mut commands: Commands,
+    oculus_controller: Res<OculusController>,
+    left_controller_query: Query<Entity, With<OpenXRLeftController>>,
+    right_controller_query: Query<Entity, With<OpenXRRightController>>,
+    hmd_query: Query<Entity, With<OpenXRHMD>>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    session: Res<XrSession>,
+    action_sets: Res<XrActionSets>,
+) {
+    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let mut set_velocity = |entity: Entity, velocity: Option<XrVelocity>| match velocity {
+        Some(velocity) => {
+            commands.entity(entity).insert(velocity);
+        }
+        None => {
+            commands.entity(entity).remove::<XrVelocity>();
+        }
+    };
+    let left = controller.grip_state(Hand::Left).velocity();
+    for entity in &left_controller_query {
+        set_velocity(entity, left);
+    }
+    let right = controller.grip_state(Hand::Right).velocity();
+    for entity in &right_controller_query {
+        set_velocity(entity, right);
+    }
+    let head = xr_input
+        .head_velocity(xr_input.pose_time(frame_state.predicted_display_time.into()))
+        .unwrap_or_default();
+    for entity in &hmd_query {
+        set_velocity(entity, head);
+    }
+}
+
+/// Spawns the [`XrGripPose`] and [`XrAimPose`] children of new controller entities
+#[allow(clippy::type_complexity)]
+pub fn spawn_controller_poses(
+    mut commands: Commands,
+    controllers: Query<
+        (Entity, Option<&OpenXRLeftController>),
+        Or<(Added<OpenXRLeftController>, Added<OpenXRRightController>)>,
+    >,
+) {
+    for (entity, left) in &controllers {
+        let hand = match left {
+            Some(_) => Hand::Left,
+            None => Hand::Right,
+        };
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                SpatialBundle::default(),
+                Name::new(format!("{:?} Grip Pose", hand)),
+                XrGripPose(hand),
+            ));
+            parent.spawn((
+                SpatialBundle::default(),
+                Name::new(format!("{:?} Aim Pose", hand)),
+                XrAimPose(hand),
+            ));
+        });
+    }
+}
+
+/// Moves the [`XrAimPose`] entities to the aim pose relative to the grip pose of their hand
+pub fn update_controller_aim_poses(
+    oculus_controller: Res<OculusController>,
+    mut aim_poses: Query<(&XrAimPose, &mut Transform)>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    session: Res<XrSession>,
+    action_sets: Res<XrActionSets>,
+) {
+    if aim_poses.is_empty() {
+        return;
+    }
+    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let local_aim = |hand: Hand| {
+        let grip = controller.grip_space(hand).0.pose;
+        let aim = controller.aim_space(hand).0.pose;
+        let grip = Transform {
+            translation: grip.position.to_vec3(),
+            rotation: verify_quat(grip.orientation.to_quat()),
+            ..Default::default()
+        };
+        let aim = Transform {
+            translation: aim.position.to_vec3(),
+            rotation: verify_quat(aim.orientation.to_quat()),
+            ..Default::default()
+        };
+        Transform::from_matrix(grip.compute_matrix().inverse() * aim.compute_matrix())
+    };
+    let left = local_aim(Hand::Left);
+    let right = local_aim(Hand::Right);
+    for (aim_pose, mut transform) in &mut aim_poses {
+        *transform = match aim_pose.0 {
+            Hand::Left => left,
+            Hand::Right => right,
+        };
+    }
+}
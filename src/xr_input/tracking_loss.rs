@@ -0,0 +1,152 @@
+//! Reacting to controllers that leave the tracking volume, which get the [`Untracked`] marker
+//! once the runtime couldn't locate them for [`XrTrackingTimeout::timeout`].
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::input::XrInput;
+use crate::resources::{XrFrameState, XrSession};
+
+use super::actions::XrActionSets;
+use super::oculus_touch::OculusController;
+use super::trackers::{OpenXRLeftController, OpenXRRightController, TrackingConfidence};
+use super::Hand;
+
+/// Marks a controller entity whose pose has been lost for longer than
+/// [`XrTrackingTimeout::timeout`], removed as soon as the pose is valid again. While the runtime
+/// can't locate a controller its pose freezes or drifts away, the marker is the time to drop
+/// what it holds.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct Untracked;
+
+/// What happens to an [`Untracked`] controller entity
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum UntrackedBehavior {
+    /// Keeps following whatever pose the runtime reports
+    #[default]
+    Follow,
+    /// Stays at the last pose that wasn't lost
+    Freeze,
+    /// Hides the entity with its children, restoring its [`Visibility`] once tracked again
+    Hide,
+}
+
+/// The staleness policy for controller entities
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct XrTrackingTimeout {
+    /// How long the pose has to be lost before the entity is [`Untracked`]
+    pub timeout: Duration,
+    pub behavior: UntrackedBehavior,
+}
+
+impl Default for XrTrackingTimeout {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(300),
+            behavior: UntrackedBehavior::Follow,
+        }
+    }
+}
+
+/// What a controller entity needs to be frozen or shown again
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TrackingLoss {
+    /// The last pose that wasn't lost
+    last_pose: Transform,
+    /// When the pose got lost, `None` while it's valid
+    lost_at: Option<Duration>,
+    /// The visibility before [`UntrackedBehavior::Hide`] hid the entity
+    hidden: Option<Visibility>,
+}
+
+impl TrackingLoss {
+    fn new(pose: Transform) -> Self {
+        Self {
+            last_pose: pose,
+            lost_at: None,
+            hidden: None,
+        }
+    }
+}
+
+/// Updates the [`TrackingConfidence`] of the controller entities and applies the
+/// [`XrTrackingTimeout`], runs after the controller poses are set
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_tracking_confidence(
+    mut commands: Commands,
+    oculus_controller: Res<OculusController>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    session: Res<XrSession>,
+    action_sets: Res<XrActionSets>,
+    timeout: Res<XrTrackingTimeout>,
+    mut controllers: Query<
+        (
+            Entity,
+            &mut Transform,
+            Option<&mut Visibility>,
+            Option<&mut TrackingLoss>,
+            Option<&TrackingConfidence>,
+            Has<Untracked>,
+            Has<OpenXRLeftController>,
+        ),
+        Or<(With<OpenXRLeftController>, With<OpenXRRightController>)>,
+    >,
+) {
+    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let left = controller.grip_state(Hand::Left).confidence;
+    let right = controller.grip_state(Hand::Right).confidence;
+    let now = Duration::from_nanos(frame_state.predicted_display_time.as_nanos().max(0) as u64);
+    for (entity, mut transform, visibility, loss, current, untracked, is_left) in &mut controllers {
+        let confidence = match is_left {
+            true => left,
+            false => right,
+        };
+        if current != Some(&confidence) {
+            commands.entity(entity).insert(confidence);
+        }
+        let Some(mut loss) = loss else {
+            commands
+                .entity(entity)
+                .insert(TrackingLoss::new(*transform));
+            continue;
+        };
+        if confidence.is_valid() {
+            loss.last_pose = *transform;
+            loss.lost_at = None;
+            if untracked {
+                commands.entity(entity).remove::<Untracked>();
+            }
+            if let (Some(hidden), Some(mut visibility)) = (loss.hidden.take(), visibility) {
+                *visibility = hidden;
+            }
+            continue;
+        }
+        let lost_at = match loss.lost_at {
+            // a new session starts its clock over
+            Some(lost_at) if lost_at <= now => lost_at,
+            _ => *loss.lost_at.insert(now),
+        };
+        if !untracked && now - lost_at < timeout.timeout {
+            continue;
+        }
+        if !untracked {
+            commands.entity(entity).insert(Untracked);
+        }
+        match timeout.behavior {
+            UntrackedBehavior::Follow => {}
+            UntrackedBehavior::Freeze => *transform = loss.last_pose,
+            UntrackedBehavior::Hide => {
+                if let Some(mut visibility) = visibility {
+                    if loss.hidden.is_none() {
+                        loss.hidden = Some(*visibility);
+                        *visibility = Visibility::Hidden;
+                    }
+                }
+            }
+        }
+    }
+}
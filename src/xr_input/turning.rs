@@ -0,0 +1,189 @@
+//! Turning the player with a thumbstick, by rotating the [`OpenXRTrackingRoot`] around the
+//! headset so the player stays in place. Add the [`XrTurningPlugin`] and change the
+//! [`XrTurnSettings`] resource to pick the stick and between snap and smooth turning.
+//!
+//! The pivot is the headset position of the current frame, so turning keeps working after a
+//! teleport with [`OpenXRTrackingRoot::teleport_head_to`] or a recenter.
+//! [`XrTurnEvent`]s are sent while turning, to fade the edges of the view for comfort.
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::resources::XrSession;
+use crate::xr_init::{xr_only, XrCleanup};
+
+use super::actions::XrActionSets;
+use super::oculus_touch::subaction_path;
+use super::trackers::{OpenXRHMD, OpenXRTrackingRoot};
+use super::Hand;
+
+/// Turns the tracking root with the input of [`XrTurnSettings`]
+pub struct XrTurningPlugin;
+
+impl Plugin for XrTurningPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrTurnSettings>();
+        app.init_resource::<TurnState>();
+        app.add_event::<XrTurnEvent>();
+        app.add_systems(Update, turn_tracking_root.run_if(xr_only()));
+        app.add_systems(XrCleanup, stop_turning);
+    }
+}
+
+/// The stick that turns the player
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrTurnInput {
+    /// The thumbstick of the built in `oculus_input` action set
+    Thumbstick(Hand),
+    /// A vec2 action, only its x axis is used. `hand` picks the subaction path of actions
+    /// created with [`ActionHandednes::Double`](super::actions::ActionHandednes::Double).
+    Action {
+        action_set: &'static str,
+        action: &'static str,
+        hand: Option<Hand>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum XrTurnMode {
+    /// Turns by a fixed angle every time the stick is pushed past
+    /// [`XrTurnSettings::snap_threshold`], the stick has to return to the deadzone for the next
+    /// turn
+    Snap {
+        degrees: f32,
+    },
+    /// Turns continuously, with the speed scaled by how far the stick is pushed
+    Smooth {
+        degrees_per_second: f32,
+    },
+    Disabled,
+}
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrTurnSettings {
+    pub input: XrTurnInput,
+    pub mode: XrTurnMode,
+    pub deadzone: f32,
+    pub snap_threshold: f32,
+}
+
+impl Default for XrTurnSettings {
+    fn default() -> Self {
+        Self {
+            input: XrTurnInput::Thumbstick(Hand::Right),
+            mode: XrTurnMode::Snap { degrees: 45.0 },
+            deadzone: 0.2,
+            snap_threshold: 0.7,
+        }
+    }
+}
+
+/// Sent by the [`XrTurningPlugin`], positive degrees turn to the left
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub enum XrTurnEvent {
+    /// The player turned by a snap turn
+    Snapped {
+        degrees: f32,
+    },
+    /// Smooth turning started, until [`XrTurnEvent::SmoothStopped`] the view turns every frame
+    SmoothStarted,
+    SmoothStopped,
+}
+
+impl OpenXRTrackingRoot {
+    /// Turns the player by `degrees` around the headset, positive degrees turn to the left.
+    /// `head` is the headset position relative to the root.
+    pub fn snap_turn(transform: &mut Transform, head: Vec3, degrees: f32) {
+        Self::rotate_around_head(transform, head, degrees.to_radians());
+    }
+}
+
+#[derive(Resource, Default)]
+struct TurnState {
+    /// A snap turn happened and the stick didn't return to the deadzone yet
+    snapped: bool,
+    /// Smooth turning is going on
+    smooth: bool,
+}
+
+fn turn_input(
+    input: XrTurnInput,
+    action_sets: &XrActionSets,
+    session: &xr::Session<xr::AnyGraphics>,
+) -> f32 {
+    let value = match input {
+        XrTurnInput::Thumbstick(hand) => action_sets
+            .get_state_for::<f32>(session, "oculus_input", "thumbstick_x", hand)
+            .map(|state| state.value),
+        XrTurnInput::Action {
+            action_set,
+            action,
+            hand,
+        } => {
+            let path = hand.map_or(xr::Path::NULL, subaction_path);
+            action_sets
+                .get_state::<xr::Vector2f>(session, action_set, action, path)
+                .map(|state| state.value.x)
+        }
+    };
+    value.unwrap_or_default()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn turn_tracking_root(
+    settings: Res<XrTurnSettings>,
+    mut state: ResMut<TurnState>,
+    time: Res<Time>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Option<Res<XrSession>>,
+    head: Query<&Transform, (With<OpenXRHMD>, Without<OpenXRTrackingRoot>)>,
+    mut root: Query<&mut Transform, With<OpenXRTrackingRoot>>,
+    mut events: EventWriter<XrTurnEvent>,
+) {
+    let stick = match (action_sets, session) {
+        (Some(action_sets), Some(session)) => turn_input(settings.input, &action_sets, &session),
+        _ => 0.0,
+    };
+    let (Ok(head), Ok(mut root)) = (head.get_single(), root.get_single_mut()) else {
+        return;
+    };
+    let smooth = match settings.mode {
+        XrTurnMode::Smooth { .. } => stick.abs() > settings.deadzone,
+        _ => false,
+    };
+    if smooth != state.smooth {
+        state.smooth = smooth;
+        events.send(match smooth {
+            true => XrTurnEvent::SmoothStarted,
+            false => XrTurnEvent::SmoothStopped,
+        });
+    }
+    if stick.abs() <= settings.deadzone {
+        state.snapped = false;
+    }
+    match settings.mode {
+        XrTurnMode::Snap { degrees }
+            if !state.snapped && stick.abs() >= settings.snap_threshold =>
+        {
+            state.snapped = true;
+            // pushing the stick right turns right
+            let degrees = -degrees.copysign(stick);
+            OpenXRTrackingRoot::snap_turn(&mut root, head.translation, degrees);
+            events.send(XrTurnEvent::Snapped { degrees });
+        }
+        XrTurnMode::Smooth { degrees_per_second } if smooth => {
+            let degrees = -stick * degrees_per_second * time.delta_seconds();
+            OpenXRTrackingRoot::snap_turn(&mut root, head.translation, degrees);
+        }
+        _ => {}
+    }
+}
+
+/// A session that ends mid turn doesn't leave the vignette up
+fn stop_turning(mut state: ResMut<TurnState>, mut events: EventWriter<XrTurnEvent>) {
+    if state.smooth {
+        events.send(XrTurnEvent::SmoothStopped);
+    }
+    *state = default();
+}
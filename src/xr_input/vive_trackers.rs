@@ -0,0 +1,402 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use openxr as xr;
+
+use crate::{
+    graphics::extensions::XrEnabledExtensions,
+    input::XrInput,
+    resources::{XrFrameState, XrInstance, XrSession},
+    xr_init::{xr_only, XrCleanup, XrPostSetup, XrSetup},
+};
+
+use super::{
+    actions::{
+        ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrActionSync, XrBinding,
+    },
+    trackers::OpenXRTrackingRoot,
+    QuatConv, Vec3Conv, XrTrackingUpdate,
+};
+
+pub const HTCX_VIVE_TRACKER_EXTENSION_NAME: &str = "XR_HTCX_vive_tracker_interaction";
+pub const VIVE_TRACKER_PROFILE: &str = "/interaction_profiles/htc/vive_tracker_htcx";
+const ACTION_SET: &str = "vive_trackers";
+
+/// Spawns an entity with [`XrViveTracker`] below the [`OpenXRTrackingRoot`] for every connected
+/// Vive tracker and moves it with the tracker, needs `XR_HTCX_vive_tracker_interaction`, see
+/// [`XrExtensions::enable_vive_trackers`](crate::graphics::extensions::XrExtensions::enable_vive_trackers).
+///
+/// Trackers can only be located once the user assigned them a role in the runtime,
+/// as only the role paths can be bound to actions.
+pub struct XrViveTrackerPlugin;
+
+impl Plugin for XrViveTrackerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(XrSetup, setup_vive_tracker_actions);
+        app.add_systems(
+            XrPostSetup,
+            (create_vive_tracker_spaces, sync_vive_tracker_entities).chain(),
+        );
+        app.add_systems(
+            PreUpdate,
+            (
+                sync_vive_tracker_entities.run_if(on_event::<XrViveTrackerConnected>()),
+                update_vive_trackers.after(XrActionSync),
+            )
+                .chain()
+                .in_set(XrTrackingUpdate)
+                .run_if(xr_only()),
+        );
+        app.add_systems(XrCleanup, cleanup_vive_trackers);
+    }
+}
+
+/// The body part or object a tracker is assigned to in the runtime
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Component)]
+pub enum XrViveTrackerRole {
+    HandheldObject,
+    LeftFoot,
+    RightFoot,
+    LeftShoulder,
+    RightShoulder,
+    LeftElbow,
+    RightElbow,
+    LeftKnee,
+    RightKnee,
+    Waist,
+    Chest,
+    Camera,
+    Keyboard,
+}
+
+impl XrViveTrackerRole {
+    pub const ALL: [Self; 13] = [
+        Self::HandheldObject,
+        Self::LeftFoot,
+        Self::RightFoot,
+        Self::LeftShoulder,
+        Self::RightShoulder,
+        Self::LeftElbow,
+        Self::RightElbow,
+        Self::LeftKnee,
+        Self::RightKnee,
+        Self::Waist,
+        Self::Chest,
+        Self::Camera,
+        Self::Keyboard,
+    ];
+
+    /// The user path of the role, like `/user/vive_tracker_htcx/role/waist`
+    pub fn path(self) -> &'static str {
+        match self {
+            Self::HandheldObject => "/user/vive_tracker_htcx/role/handheld_object",
+            Self::LeftFoot => "/user/vive_tracker_htcx/role/left_foot",
+            Self::RightFoot => "/user/vive_tracker_htcx/role/right_foot",
+            Self::LeftShoulder => "/user/vive_tracker_htcx/role/left_shoulder",
+            Self::RightShoulder => "/user/vive_tracker_htcx/role/right_shoulder",
+            Self::LeftElbow => "/user/vive_tracker_htcx/role/left_elbow",
+            Self::RightElbow => "/user/vive_tracker_htcx/role/right_elbow",
+            Self::LeftKnee => "/user/vive_tracker_htcx/role/left_knee",
+            Self::RightKnee => "/user/vive_tracker_htcx/role/right_knee",
+            Self::Waist => "/user/vive_tracker_htcx/role/waist",
+            Self::Chest => "/user/vive_tracker_htcx/role/chest",
+            Self::Camera => "/user/vive_tracker_htcx/role/camera",
+            Self::Keyboard => "/user/vive_tracker_htcx/role/keyboard",
+        }
+    }
+
+    /// The grip pose of the role the pose action is bound to
+    pub fn pose_path(self) -> &'static str {
+        match self {
+            Self::HandheldObject => "/user/vive_tracker_htcx/role/handheld_object/input/grip/pose",
+            Self::LeftFoot => "/user/vive_tracker_htcx/role/left_foot/input/grip/pose",
+            Self::RightFoot => "/user/vive_tracker_htcx/role/right_foot/input/grip/pose",
+            Self::LeftShoulder => "/user/vive_tracker_htcx/role/left_shoulder/input/grip/pose",
+            Self::RightShoulder => "/user/vive_tracker_htcx/role/right_shoulder/input/grip/pose",
+            Self::LeftElbow => "/user/vive_tracker_htcx/role/left_elbow/input/grip/pose",
+            Self::RightElbow => "/user/vive_tracker_htcx/role/right_elbow/input/grip/pose",
+            Self::LeftKnee => "/user/vive_tracker_htcx/role/left_knee/input/grip/pose",
+            Self::RightKnee => "/user/vive_tracker_htcx/role/right_knee/input/grip/pose",
+            Self::Waist => "/user/vive_tracker_htcx/role/waist/input/grip/pose",
+            Self::Chest => "/user/vive_tracker_htcx/role/chest/input/grip/pose",
+            Self::Camera => "/user/vive_tracker_htcx/role/camera/input/grip/pose",
+            Self::Keyboard => "/user/vive_tracker_htcx/role/keyboard/input/grip/pose",
+        }
+    }
+
+    pub fn from_path(path: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|role| role.path() == path)
+    }
+
+    /// The pose action of the role in the `vive_trackers` action set
+    pub fn action(self) -> &'static str {
+        match self {
+            Self::HandheldObject => "handheld_object_pose",
+            Self::LeftFoot => "left_foot_pose",
+            Self::RightFoot => "right_foot_pose",
+            Self::LeftShoulder => "left_shoulder_pose",
+            Self::RightShoulder => "right_shoulder_pose",
+            Self::LeftElbow => "left_elbow_pose",
+            Self::RightElbow => "right_elbow_pose",
+            Self::LeftKnee => "left_knee_pose",
+            Self::RightKnee => "right_knee_pose",
+            Self::Waist => "waist_pose",
+            Self::Chest => "chest_pose",
+            Self::Camera => "camera_pose",
+            Self::Keyboard => "keyboard_pose",
+        }
+    }
+
+    fn pretty_name(self) -> &'static str {
+        match self {
+            Self::HandheldObject => "Handheld Object",
+            Self::LeftFoot => "Left Foot",
+            Self::RightFoot => "Right Foot",
+            Self::LeftShoulder => "Left Shoulder",
+            Self::RightShoulder => "Right Shoulder",
+            Self::LeftElbow => "Left Elbow",
+            Self::RightElbow => "Right Elbow",
+            Self::LeftKnee => "Left Knee",
+            Self::RightKnee => "Right Knee",
+            Self::Waist => "Waist",
+            Self::Chest => "Chest",
+            Self::Camera => "Camera",
+            Self::Keyboard => "Keyboard",
+        }
+    }
+}
+
+/// Sent when a tracker connects or its role changes, polled with the other session events
+#[derive(Clone, Debug, Event)]
+pub struct XrViveTrackerConnected {
+    /// Identifies the tracker by its serial number, it stays the same across sessions
+    pub persistent_path: String,
+    /// `None` while the tracker has no role
+    pub role: Option<XrViveTrackerRole>,
+}
+
+impl XrViveTrackerConnected {
+    pub(crate) fn from_paths(
+        instance: &XrInstance,
+        paths: xr::ViveTrackerPathsHTCX,
+    ) -> xr::Result<Self> {
+        Ok(Self {
+            persistent_path: instance.path_to_string(paths.persistent)?,
+            role: match paths.role {
+                Some(role) => XrViveTrackerRole::from_path(&instance.path_to_string(role)?),
+                None => None,
+            },
+        })
+    }
+}
+
+/// A connected Vive tracker, the transform of the entity is in the space of the [`OpenXRTrackingRoot`]
+#[derive(Clone, Debug, Component)]
+pub struct XrViveTracker {
+    pub persistent_path: String,
+    /// Trackers without a role aren't located
+    pub role: Option<XrViveTrackerRole>,
+    /// Whether the runtime located the tracker this frame, the transform keeps its last value otherwise
+    pub tracked: bool,
+}
+
+#[derive(Resource)]
+struct XrViveTrackerSpaces(HashMap<XrViveTrackerRole, xr::Space>);
+
+fn vive_trackers_enabled(extensions: Option<Res<XrEnabledExtensions>>) -> bool {
+    extensions.is_some_and(|extensions| {
+        extensions
+            .enabled
+            .raw()
+            .other
+            .iter()
+            .any(|ext| ext == HTCX_VIVE_TRACKER_EXTENSION_NAME)
+    })
+}
+
+/// The actions have to exist before the session starts, so every role gets one up front
+fn setup_vive_tracker_actions(
+    extensions: Option<Res<XrEnabledExtensions>>,
+    mut action_sets: ResMut<SetupActionSets>,
+) {
+    if !vive_trackers_enabled(extensions) {
+        return;
+    }
+    let set = action_sets.add_action_set(ACTION_SET, "Vive Trackers".into(), 0);
+    let mut bindings = Vec::with_capacity(XrViveTrackerRole::ALL.len());
+    for role in XrViveTrackerRole::ALL {
+        set.new_action(
+            role.action(),
+            role.pretty_name().into(),
+            ActionType::PoseF,
+            ActionHandednes::Single,
+        );
+        bindings.push(XrBinding::new(role.action(), role.pose_path()));
+    }
+    set.suggest_binding(VIVE_TRACKER_PROFILE, &bindings);
+}
+
+fn create_vive_tracker_spaces(
+    mut commands: Commands,
+    action_sets: Res<XrActionSets>,
+    session: Res<XrSession>,
+) {
+    let mut spaces = HashMap::new();
+    for role in XrViveTrackerRole::ALL {
+        let Ok(action) = action_sets.get_action_posef(ACTION_SET, role.action()) else {
+            return;
+        };
+        match action.create_space(
+            xr::Session::clone(&session),
+            xr::Path::NULL,
+            xr::Posef::IDENTITY,
+        ) {
+            Ok(space) => {
+                spaces.insert(role, space);
+            }
+            Err(err) => warn!("Unable to create the {:?} tracker space: {}", role, err),
+        }
+    }
+    commands.insert_resource(XrViveTrackerSpaces(spaces));
+}
+
+/// The trackers the runtime currently knows about
+fn enumerate_vive_trackers(instance: &XrInstance) -> xr::Result<Vec<XrViveTrackerConnected>> {
+    fn check(result: xr::sys::Result) -> xr::Result<()> {
+        match result.into_raw() < 0 {
+            true => Err(result),
+            false => Ok(()),
+        }
+    }
+    let mut function = None;
+    check(unsafe {
+        (instance.entry().fp().get_instance_proc_addr)(
+            instance.as_raw(),
+            b"xrEnumerateViveTrackerPathsHTCX\0".as_ptr() as _,
+            &mut function,
+        )
+    })?;
+    let Some(function) = function else {
+        return Err(xr::sys::Result::ERROR_FUNCTION_UNSUPPORTED);
+    };
+    let enumerate: xr::sys::pfn::EnumerateViveTrackerPathsHTCX =
+        unsafe { std::mem::transmute(function) };
+    let mut count = 0;
+    check(unsafe { enumerate(instance.as_raw(), 0, &mut count, ptr::null_mut()) })?;
+    let empty = xr::sys::ViveTrackerPathsHTCX {
+        ty: xr::sys::ViveTrackerPathsHTCX::TYPE,
+        next: ptr::null_mut(),
+        persistent_path: xr::Path::NULL,
+        role_path: xr::Path::NULL,
+    };
+    let mut paths = vec![empty; count as usize];
+    check(unsafe { enumerate(instance.as_raw(), count, &mut count, paths.as_mut_ptr()) })?;
+    paths.truncate(count as usize);
+    paths
+        .into_iter()
+        .map(|paths| XrViveTrackerConnected::from_paths(instance, paths.into()))
+        .collect()
+}
+
+/// Spawns entities for new trackers, updates the roles of known ones
+/// and despawns the ones that disconnected
+fn sync_vive_tracker_entities(
+    mut commands: Commands,
+    spaces: Option<Res<XrViveTrackerSpaces>>,
+    instance: Res<XrInstance>,
+    mut trackers: Query<(Entity, &mut XrViveTracker)>,
+    root: Query<Entity, With<OpenXRTrackingRoot>>,
+) {
+    if spaces.is_none() {
+        return;
+    }
+    let connected = match enumerate_vive_trackers(&instance) {
+        Ok(connected) => connected,
+        Err(err) => {
+            warn!("Unable to enumerate the vive trackers: {}", err);
+            return;
+        }
+    };
+    for (entity, tracker) in &trackers {
+        if !connected
+            .iter()
+            .any(|connected| connected.persistent_path == tracker.persistent_path)
+        {
+            info!("Vive tracker {} disconnected", tracker.persistent_path);
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for connected in connected {
+        let known = trackers
+            .iter_mut()
+            .find(|(_, tracker)| tracker.persistent_path == connected.persistent_path);
+        if let Some((_, mut tracker)) = known {
+            if tracker.role != connected.role {
+                tracker.role = connected.role;
+                tracker.tracked = false;
+            }
+            continue;
+        }
+        info!(
+            "Vive tracker {} connected as {:?}",
+            connected.persistent_path, connected.role
+        );
+        let tracker = commands
+            .spawn((
+                SpatialBundle::default(),
+                Name::new(format!("Vive Tracker {}", connected.persistent_path)),
+                XrViveTracker {
+                    persistent_path: connected.persistent_path,
+                    role: connected.role,
+                    tracked: false,
+                },
+            ))
+            .id();
+        if let Ok(root) = root.get_single() {
+            commands.entity(root).add_child(tracker);
+        }
+    }
+}
+
+fn update_vive_trackers(
+    spaces: Option<Res<XrViveTrackerSpaces>>,
+    input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+    mut trackers: Query<(&mut XrViveTracker, &mut Transform)>,
+) {
+    let Some(spaces) = spaces else {
+        return;
+    };
+    for (mut tracker, mut transform) in &mut trackers {
+        let Some(space) = tracker.role.and_then(|role| spaces.0.get(&role)) else {
+            tracker.tracked = false;
+            continue;
+        };
+        let location = match space.locate(&input.stage, frame_state.predicted_display_time) {
+            Ok(location) => location,
+            Err(err) => {
+                warn!(
+                    "Unable to locate vive tracker {}: {}",
+                    tracker.persistent_path, err
+                );
+                tracker.tracked = false;
+                continue;
+            }
+        };
+        tracker.tracked = location.location_flags.contains(
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
+        );
+        if tracker.tracked {
+            transform.translation = location.pose.position.to_vec3();
+            transform.rotation = location.pose.orientation.to_quat();
+        }
+    }
+}
+
+fn cleanup_vive_trackers(mut commands: Commands, trackers: Query<Entity, With<XrViveTracker>>) {
+    for entity in &trackers {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<XrViveTrackerSpaces>();
+}
@@ -1,23 +1,39 @@
+use crate::graphics::{XrSessionConfig, XrViewConfig};
+use crate::passthrough::XrPassthroughState;
 use crate::prelude::XrSystems;
-use crate::xr_init::{xr_only, XrCleanup, XrSetup};
+use crate::resources::{XrEnvironmentBlendMode, XrSwapchain};
+use crate::secondary_view::XrSecondaryViewCamera;
+use crate::skybox::XrSkyboxLayer;
+use crate::xr_init::{
+    xr_after_wait_only, xr_only, xr_render_only, XrCleanup, XrSetup, XrShouldRender,
+};
 use crate::xr_input::{QuatConv, Vec3Conv};
-use crate::{locate_views, xr_wait_frame, LEFT_XR_TEXTURE_HANDLE, RIGHT_XR_TEXTURE_HANDLE};
-use bevy::core_pipeline::core_3d::graph::Core3d;
+use crate::{locate_views, xr_wait_frame, XR_TEXTURE_HANDLES};
+use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
 use bevy::core_pipeline::tonemapping::{DebandDither, Tonemapping};
+use bevy::ecs::query::QueryItem;
 use bevy::ecs::system::lifetimeless::Read;
 use bevy::math::Vec3A;
 use bevy::prelude::*;
 use bevy::render::camera::{
     CameraMainTextureUsages, CameraProjection, CameraProjectionPlugin, CameraRenderGraph,
-    RenderTarget,
+    CameraUpdateSystem, ManualTextureViewHandle, RenderTarget,
 };
 use bevy::render::extract_component::{ExtractComponent, ExtractComponentPlugin};
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
 use bevy::render::primitives::Frustum;
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+};
+use bevy::render::renderer::RenderContext;
 use bevy::render::view::{
-    update_frusta, ColorGrading, ExtractedView, VisibilitySystems, VisibleEntities,
+    prepare_view_uniforms, update_frusta, ColorGrading, ExtractedView, ViewDepthTexture,
+    VisibilitySystems, VisibleEntities,
 };
 use bevy::render::{Render, RenderApp, RenderSet};
 use bevy::transform::TransformSystem;
+use bevy::utils::warn_once;
+use openxr as xr;
 use openxr::Fovf;
 use wgpu::TextureUsages;
 
@@ -28,6 +44,13 @@ pub struct XrCameraPlugin;
 impl Plugin for XrCameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(CameraProjectionPlugin::<XRProjection>::default());
+        app.register_type::<XrClipPlanes>()
+            .register_type::<XRProjection>()
+            .register_type::<XrCamera>()
+            .register_type::<XrFlatscreenCamera>()
+            .register_type::<RootTransform>();
+        app.init_resource::<XrClipPlanes>();
+        app.add_plugins(ExtractResourcePlugin::<XrClipPlanes>::default());
         app.add_systems(
             PreUpdate,
             xr_camera_head_sync
@@ -42,6 +65,12 @@ impl Plugin for XrCameraPlugin {
                 .before(TransformSystem::TransformPropagate)
                 .run_if(xr_only()),
         );
+        app.add_systems(
+            PostUpdate,
+            update_xr_cameras_active
+                .run_if(xr_only())
+                .before(CameraUpdateSystem),
+        );
         app.add_systems(
             PostUpdate,
             update_frusta::<XRProjection>
@@ -54,40 +83,116 @@ impl Plugin for XrCameraPlugin {
                 .after(TransformSystem::TransformPropagate)
                 .xr_only(),
         );
-        app.add_systems(XrSetup, setup_xr_cameras);
-        app.add_systems(XrCleanup, cleanup_xr_cameras);
+        app.add_systems(
+            PostUpdate,
+            update_camera_clear_color.run_if(resource_exists::<XrEnvironmentBlendMode>),
+        );
+        app.add_systems(
+            PostUpdate,
+            enable_depth_copy.run_if(|config: Res<XrSessionConfig>| config.depth_layer),
+        );
+        app.add_systems(XrSetup, (setup_xr_cameras, deactivate_flatscreen_cameras));
+        app.add_systems(
+            XrCleanup,
+            (
+                cleanup_xr_cameras.before(super::cleanup_xr_root),
+                activate_flatscreen_cameras,
+            ),
+        );
         app.add_plugins(ExtractComponentPlugin::<XrCamera>::default());
         app.add_plugins(ExtractComponentPlugin::<XRProjection>::default());
         app.add_plugins(ExtractComponentPlugin::<RootTransform>::default());
         // app.add_plugins(ExtractComponentPlugin::<TransformExtract>::default());
         // app.add_plugins(ExtractComponentPlugin::<GlobalTransformExtract>::default());
         let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<XrDepthCopyNode>>(Core3d, XrDepthCopyLabel)
+            .add_render_graph_edge(Core3d, Node3d::EndMainPass, XrDepthCopyLabel);
+        // the views are located again as late as possible, right before the view uniforms are
+        // written, the main world only uses its poses for culling
         render_app.add_systems(
             Render,
             (locate_views, xr_camera_head_sync_render_world)
                 .chain()
                 .run_if(xr_only())
-                .in_set(RenderSet::PrepareAssets),
-            // .after(xr_wait_frame)
-            // .after(locate_views),
+                .run_if(xr_after_wait_only())
+                .run_if(xr_render_only())
+                .in_set(RenderSet::PrepareResources)
+                .before(prepare_view_uniforms),
         );
     }
 }
 
-// might be unnesesary since it should be parented to the root
-fn cleanup_xr_cameras(mut commands: Commands, entities: Query<Entity, With<XrCamera>>) {
-    for e in &entities {
-        commands.entity(e).despawn_recursive();
+/// The cameras don't render while the runtime doesn't show the frame, like while the headset is
+/// idle or a system menu is open. The frame is still submitted, just without layers.
+fn update_xr_cameras_active(
+    should_render: Res<XrShouldRender>,
+    mut cameras: Query<&mut Camera, With<XrCamera>>,
+    mut secondary_view_cameras: Query<
+        &mut Camera,
+        (With<XrSecondaryViewCamera>, Without<XrCamera>),
+    >,
+) {
+    for mut camera in cameras.iter_mut().chain(&mut secondary_view_cameras) {
+        if camera.is_active != **should_render {
+            camera.is_active = **should_render;
+        }
+    }
+}
+
+/// The cameras are kept when the session ends so components added to them are still there in
+/// the next session, they are taken out of the tracking root before it's despawned
+fn cleanup_xr_cameras(
+    mut commands: Commands,
+    mut cameras: Query<(Entity, &mut Camera), With<XrCamera>>,
+) {
+    for (entity, mut camera) in &mut cameras {
+        camera.is_active = false;
+        commands.entity(entity).remove_parent();
+    }
+}
+
+/// A camera rendering to the window while no session is running, it's deactivated while one runs
+/// and the window shows the [`XrMirrorMode`](crate::mirror::XrMirrorMode) instead
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct XrFlatscreenCamera;
+
+fn deactivate_flatscreen_cameras(mut cameras: Query<&mut Camera, With<XrFlatscreenCamera>>) {
+    for mut camera in &mut cameras {
+        camera.is_active = false;
+    }
+}
+
+fn activate_flatscreen_cameras(mut cameras: Query<&mut Camera, With<XrFlatscreenCamera>>) {
+    for mut camera in &mut cameras {
+        camera.is_active = true;
     }
 }
 
-fn setup_xr_cameras(mut commands: Commands) {
-    commands.spawn((
-        XrCameraBundle::new(Eye::Right),
-        OpenXRRightEye,
-        OpenXRTracker,
-    ));
-    commands.spawn((XrCameraBundle::new(Eye::Left), OpenXRLeftEye, OpenXRTracker));
+fn setup_xr_cameras(
+    mut commands: Commands,
+    session_config: Res<XrSessionConfig>,
+    cameras: Query<(Entity, &XrCamera)>,
+) {
+    // the left camera renders the combined view in mono mode
+    let eyes = Eye::all(session_config.view_config);
+    let mut existing = Vec::new();
+    for (entity, camera) in &cameras {
+        match eyes.contains(&camera.eye()) {
+            true => existing.push(camera.eye()),
+            false => commands.entity(entity).despawn_recursive(),
+        }
+    }
+    for &eye in eyes.iter().filter(|eye| !existing.contains(eye)) {
+        let mut camera = commands.spawn((XrCameraBundle::new(eye), OpenXRTracker));
+        // the focus views are inside the context views, so they don't get a marker of their own
+        match eye {
+            Eye::Left => camera.insert(OpenXRLeftEye),
+            Eye::Right => camera.insert(OpenXRRightEye),
+            Eye::LeftFocus | Eye::RightFocus => continue,
+        };
+    }
 }
 
 #[derive(Bundle)]
@@ -126,12 +231,122 @@ pub struct XrCameraBundle {
     pub xr_camera_type: XrCamera,
     pub root_transform: RootTransform,
 }
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Component, ExtractComponent)]
+/// One of the cameras rendering a view of the primary view configuration, spawned below the
+/// [`OpenXRTrackingRoot`] when the session starts. Its [`Transform`] follows the pose of the view
+/// and its [`XRProjection`] the asymmetric fov, both are updated every frame.
+///
+/// The entities are reused by later sessions, so components like [`Tonemapping`], bloom or
+/// render layers added to them stay.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Component, ExtractComponent, Reflect,
+)]
+#[reflect(Component)]
 pub struct XrCamera(Eye);
+impl XrCamera {
+    pub fn eye(&self) -> Eye {
+        self.0
+    }
+}
 
-#[derive(Component, ExtractComponent, Clone, Copy, Debug, Default, Deref, DerefMut)]
+#[derive(Component, ExtractComponent, Clone, Copy, Debug, Default, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
 pub struct RootTransform(pub GlobalTransform);
 
+/// The depth texture has to be copyable to be submitted in the depth layer
+fn enable_depth_copy(mut camera_query: Query<&mut Camera3d, Added<XrCamera>>) {
+    for mut camera in &mut camera_query {
+        let usages = TextureUsages::from(camera.depth_texture_usages);
+        camera.depth_texture_usages = (usages | TextureUsages::COPY_SRC).into();
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct XrDepthCopyLabel;
+
+/// Copies the depth buffer of each XR camera into the matching layer of the depth swapchain
+#[derive(Default)]
+struct XrDepthCopyNode;
+
+impl ViewNode for XrDepthCopyNode {
+    type ViewQuery = (&'static XrCamera, &'static ViewDepthTexture);
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (camera, depth): QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        if !world
+            .get_resource::<XrShouldRender>()
+            .is_some_and(|should_render| **should_render)
+        {
+            return Ok(());
+        }
+        let Some(target) = world
+            .get_resource::<XrSwapchain>()
+            .and_then(|swapchain| swapchain.depth_texture())
+        else {
+            return Ok(());
+        };
+        if depth.texture.sample_count() != 1 {
+            warn_once!(
+                "Unable to submit depth while using MSAA, set Msaa::Off to use the depth layer"
+            );
+            return Ok(());
+        }
+        let size = depth.texture.size();
+        render_context.command_encoder().copy_texture_to_texture(
+            depth.texture.as_image_copy(),
+            wgpu::ImageCopyTexture {
+                texture: target,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: camera.0 as u32,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::Extent3d {
+                width: size.width.min(target.width()),
+                height: size.height.min(target.height()),
+                depth_or_array_layers: 1,
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Clears XR cameras to transparent when the real world or the skybox layer should show through
+fn update_camera_clear_color(
+    blend_mode: Res<XrEnvironmentBlendMode>,
+    passthrough_state: Option<Res<XrPassthroughState>>,
+    skybox_layer: Option<Res<XrSkyboxLayer>>,
+    mut had_skybox_layer: Local<bool>,
+    mut camera_query: Query<(Ref<XrCamera>, &mut Camera)>,
+) {
+    let passthrough_changed = passthrough_state.as_ref().is_some_and(|s| s.is_changed());
+    let passthrough_running = passthrough_state.is_some_and(|s| *s == XrPassthroughState::Running);
+    let skybox_changed = skybox_layer.is_some() != *had_skybox_layer;
+    *had_skybox_layer = skybox_layer.is_some();
+    for (xr_camera, mut camera) in &mut camera_query {
+        if !blend_mode.is_changed()
+            && !passthrough_changed
+            && !skybox_changed
+            && !xr_camera.is_added()
+        {
+            continue;
+        }
+        // the environment has to show through wherever nothing was rendered
+        camera.clear_color = match **blend_mode {
+            _ if passthrough_running || *had_skybox_layer => ClearColorConfig::Custom(Color::NONE),
+            xr::EnvironmentBlendMode::ALPHA_BLEND => ClearColorConfig::Custom(Color::NONE),
+            _ => ClearColorConfig::Default,
+        };
+    }
+}
+
 fn update_root_transform_components(
     mut component_query: Query<&mut RootTransform>,
     root_query: Query<&GlobalTransform, With<OpenXRTrackingRoot>>,
@@ -178,10 +393,29 @@ fn update_root_transform_components(
 //     }
 // }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Reflect)]
 pub enum Eye {
     Left = 0,
     Right = 1,
+    /// The focus views of [`XrViewConfig::Quad`], the left and right eye are the context views
+    LeftFocus = 2,
+    RightFocus = 3,
+}
+
+impl Eye {
+    /// The eyes that have a view in the view configuration, in the order of their views
+    pub fn all(view_config: XrViewConfig) -> &'static [Eye] {
+        match view_config {
+            XrViewConfig::Mono => &[Eye::Left],
+            XrViewConfig::Stereo => &[Eye::Left, Eye::Right],
+            XrViewConfig::Quad => &[Eye::Left, Eye::Right, Eye::LeftFocus, Eye::RightFocus],
+        }
+    }
+
+    /// The manual texture view the camera of the eye renders into
+    pub fn texture_handle(self) -> ManualTextureViewHandle {
+        XR_TEXTURE_HANDLES[self as usize]
+    }
 }
 
 impl XrCameraBundle {
@@ -189,10 +423,7 @@ impl XrCameraBundle {
         Self {
             camera: Camera {
                 order: -1,
-                target: RenderTarget::TextureView(match eye {
-                    Eye::Left => LEFT_XR_TEXTURE_HANDLE,
-                    Eye::Right => RIGHT_XR_TEXTURE_HANDLE,
-                }),
+                target: RenderTarget::TextureView(eye.texture_handle()),
                 viewport: None,
                 ..default()
             },
@@ -217,11 +448,47 @@ impl XrCameraBundle {
     }
 }
 
+/// The clip planes of the xr cameras, applied to their [`XRProjection`] every frame.
+/// The depth layer is submitted with the same planes, so the compositor reprojects correctly.
+#[derive(Clone, Copy, Debug, PartialEq, Resource, ExtractResource, Reflect)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[reflect(Resource)]
+pub struct XrClipPlanes {
+    pub near: f32,
+    /// `None` for a far plane at infinity
+    pub far: Option<f32>,
+}
+
+impl Default for XrClipPlanes {
+    fn default() -> Self {
+        Self {
+            near: 0.1,
+            far: None,
+        }
+    }
+}
+
+impl XrClipPlanes {
+    pub fn apply(&self, projection: &mut XRProjection) {
+        let far = self.far.unwrap_or(f32::INFINITY);
+        if projection.near != self.near || projection.far != far {
+            projection.near = self.near;
+            projection.far = far;
+        }
+    }
+}
+
 #[derive(Debug, Clone, Component, Reflect, ExtractComponent)]
 #[reflect(Component, Default)]
 pub struct XRProjection {
     pub near: f32,
+    /// The projection has its far plane at infinity if this isn't finite
     pub far: f32,
+    /// The four angles of the view as reported by the runtime, they don't have to be symmetric
     #[reflect(ignore)]
     pub fov: Fovf,
 }
@@ -230,7 +497,7 @@ impl Default for XRProjection {
     fn default() -> Self {
         Self {
             near: 0.1,
-            far: 1000.,
+            far: f32::INFINITY,
             fov: Default::default(),
         }
     }
@@ -240,118 +507,62 @@ impl XRProjection {
     pub fn new(near: f32, far: f32, fov: Fovf) -> Self {
         XRProjection { near, far, fov }
     }
+
+    /// The reverse-Z projection bevy uses, with the far plane at infinity. The same as
+    /// [`Mat4::perspective_infinite_reverse_rh`] for the asymmetric field of view of a view:
+    /// the near plane is at depth 1, infinity at depth 0 and y points up in clip space.
+    // adapted from XrMatrix4x4f_CreateProjectionFov in
+    // https://github.com/KhronosGroup/OpenXR-SDK-Source/blob/master/src/common/xr_linear.h
+    pub fn reverse_infinite_matrix(fov: Fovf, near: f32) -> Mat4 {
+        let [x_scale, x_offset, y_scale, y_offset] = fov_scale_offset(fov);
+        Mat4::from_cols(
+            Vec4::new(x_scale, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, y_scale, 0.0, 0.0),
+            Vec4::new(x_offset, y_offset, 0.0, -1.0),
+            Vec4::new(0.0, 0.0, near, 0.0),
+        )
+    }
+
+    /// Like [`XRProjection::reverse_infinite_matrix`], but with the far plane at depth 0
+    pub fn reverse_matrix(fov: Fovf, near: f32, far: f32) -> Mat4 {
+        let [x_scale, x_offset, y_scale, y_offset] = fov_scale_offset(fov);
+        Mat4::from_cols(
+            Vec4::new(x_scale, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, y_scale, 0.0, 0.0),
+            Vec4::new(x_offset, y_offset, near / (far - near), -1.0),
+            Vec4::new(0.0, 0.0, far * near / (far - near), 0.0),
+        )
+    }
+}
+
+/// Maps the tangents of the view angles to -1..1 in clip space
+fn fov_scale_offset(fov: Fovf) -> [f32; 4] {
+    let (left, right) = (fov.angle_left.tan(), fov.angle_right.tan());
+    let (down, up) = (fov.angle_down.tan(), fov.angle_up.tan());
+    let width = right - left;
+    let height = up - down;
+    [
+        2.0 / width,
+        (right + left) / width,
+        2.0 / height,
+        (up + down) / height,
+    ]
 }
 
 impl CameraProjection for XRProjection {
-    // =============================================================================
-    // math code adapted from
-    // https://github.com/KhronosGroup/OpenXR-SDK-Source/blob/master/src/common/xr_linear.h
-    // Copyright (c) 2017 The Khronos Group Inc.
-    // Copyright (c) 2016 Oculus VR, LLC.
-    // SPDX-License-Identifier: Apache-2.0
-    // =============================================================================
     fn get_projection_matrix(&self) -> Mat4 {
-        //  symmetric perspective for debugging
-        // let x_fov = (self.fov.angle_left.abs() + self.fov.angle_right.abs());
-        // let y_fov = (self.fov.angle_up.abs() + self.fov.angle_down.abs());
-        // return Mat4::perspective_infinite_reverse_rh(y_fov, x_fov / y_fov, self.near);
-
-        let fov = self.fov;
-        let is_vulkan_api = false; // FIXME wgpu probably abstracts this
-        let near_z = self.near;
-        let far_z = -1.; //   use infinite proj
-                         // let far_z = self.far;
-
-        let tan_angle_left = fov.angle_left.tan();
-        let tan_angle_right = fov.angle_right.tan();
-
-        let tan_angle_down = fov.angle_down.tan();
-        let tan_angle_up = fov.angle_up.tan();
-
-        let tan_angle_width = tan_angle_right - tan_angle_left;
-
-        // Set to tanAngleDown - tanAngleUp for a clip space with positive Y
-        // down (Vulkan). Set to tanAngleUp - tanAngleDown for a clip space with
-        // positive Y up (OpenGL / D3D / Metal).
-        // const float tanAngleHeight =
-        //     graphicsApi == GRAPHICS_VULKAN ? (tanAngleDown - tanAngleUp) : (tanAngleUp - tanAngleDown);
-        let tan_angle_height = if is_vulkan_api {
-            tan_angle_down - tan_angle_up
+        if self.far.is_finite() && self.far > self.near {
+            Self::reverse_matrix(self.fov, self.near, self.far)
         } else {
-            tan_angle_up - tan_angle_down
-        };
-
-        // Set to nearZ for a [-1,1] Z clip space (OpenGL / OpenGL ES).
-        // Set to zero for a [0,1] Z clip space (Vulkan / D3D / Metal).
-        // const float offsetZ =
-        //     (graphicsApi == GRAPHICS_OPENGL || graphicsApi == GRAPHICS_OPENGL_ES) ? nearZ : 0;
-        // FIXME handle enum of graphics apis
-        let offset_z = 0.;
-
-        let mut cols: [f32; 16] = [0.0; 16];
-
-        if far_z <= near_z {
-            // place the far plane at infinity
-            cols[0] = 2. / tan_angle_width;
-            cols[4] = 0.;
-            cols[8] = (tan_angle_right + tan_angle_left) / tan_angle_width;
-            cols[12] = 0.;
-
-            cols[1] = 0.;
-            cols[5] = 2. / tan_angle_height;
-            cols[9] = (tan_angle_up + tan_angle_down) / tan_angle_height;
-            cols[13] = 0.;
-
-            cols[2] = 0.;
-            cols[6] = 0.;
-            cols[10] = -1.;
-            cols[14] = -(near_z + offset_z);
-
-            cols[3] = 0.;
-            cols[7] = 0.;
-            cols[11] = -1.;
-            cols[15] = 0.;
-
-            //  bevy uses the _reverse_ infinite projection
-            //  https://dev.theomader.com/depth-precision/
-            let z_reversal = Mat4::from_cols_array_2d(&[
-                [1f32, 0., 0., 0.],
-                [0., 1., 0., 0.],
-                [0., 0., -1., 0.],
-                [0., 0., 1., 1.],
-            ]);
-
-            return z_reversal * Mat4::from_cols_array(&cols);
-        } else {
-            // normal projection
-            cols[0] = 2. / tan_angle_width;
-            cols[4] = 0.;
-            cols[8] = (tan_angle_right + tan_angle_left) / tan_angle_width;
-            cols[12] = 0.;
-
-            cols[1] = 0.;
-            cols[5] = 2. / tan_angle_height;
-            cols[9] = (tan_angle_up + tan_angle_down) / tan_angle_height;
-            cols[13] = 0.;
-
-            cols[2] = 0.;
-            cols[6] = 0.;
-            cols[10] = -(far_z + offset_z) / (far_z - near_z);
-            cols[14] = -(far_z * (near_z + offset_z)) / (far_z - near_z);
-
-            cols[3] = 0.;
-            cols[7] = 0.;
-            cols[11] = -1.;
-            cols[15] = 0.;
+            Self::reverse_infinite_matrix(self.fov, self.near)
         }
-
-        Mat4::from_cols_array(&cols)
     }
 
     fn update(&mut self, _width: f32, _height: f32) {}
 
     fn far(&self) -> f32 {
-        self.far
+        // the frustum is built from the far plane, an infinite distance would turn it into NaNs
+        self.far.min(f32::MAX)
     }
 
     fn get_frustum_corners(&self, z_near: f32, z_far: f32) -> [Vec3A; 8] {
@@ -361,22 +572,27 @@ impl CameraProjection for XRProjection {
         let tan_angle_bottom = self.fov.angle_down.tan();
         let tan_angle_top = self.fov.angle_up.tan();
 
+        // the depths are negative in view space, scaling the tangents with them would
+        // mirror an asymmetric fov
+        let corner =
+            |tan_x: f32, tan_y: f32, z: f32| Vec3A::new(tan_x * z.abs(), tan_y * z.abs(), z);
         // NOTE: These vertices are in the specific order required by [`calculate_cascade`].
         [
-            Vec3A::new(tan_angle_right, tan_angle_bottom, 1.0) * z_near, // bottom right
-            Vec3A::new(tan_angle_right, tan_angle_top, 1.0) * z_near,    // top right
-            Vec3A::new(tan_angle_left, tan_angle_top, 1.0) * z_near,     // top left
-            Vec3A::new(tan_angle_left, tan_angle_bottom, 1.0) * z_near,  // bottom left
-            Vec3A::new(tan_angle_right, tan_angle_bottom, 1.0) * z_far,  // bottom right
-            Vec3A::new(tan_angle_right, tan_angle_top, 1.0) * z_far,     // top right
-            Vec3A::new(tan_angle_left, tan_angle_top, 1.0) * z_far,      // top left
-            Vec3A::new(tan_angle_left, tan_angle_bottom, 1.0) * z_far,   // bottom left
+            corner(tan_angle_right, tan_angle_bottom, z_near), // bottom right
+            corner(tan_angle_right, tan_angle_top, z_near),    // top right
+            corner(tan_angle_left, tan_angle_top, z_near),     // top left
+            corner(tan_angle_left, tan_angle_bottom, z_near),  // bottom left
+            corner(tan_angle_right, tan_angle_bottom, z_far),  // bottom right
+            corner(tan_angle_right, tan_angle_top, z_far),     // top right
+            corner(tan_angle_left, tan_angle_top, z_far),      // top left
+            corner(tan_angle_left, tan_angle_bottom, z_far),   // bottom left
         ]
     }
 }
 
 pub fn xr_camera_head_sync(
     views: Res<crate::resources::XrViews>,
+    clip_planes: Res<XrClipPlanes>,
     mut query: Query<(&mut Transform, &XrCamera, &mut XRProjection)>,
 ) {
     //TODO calculate HMD position
@@ -387,6 +603,7 @@ pub fn xr_camera_head_sync(
             None => continue,
         };
         xr_projection.fov = view.fov;
+        clip_planes.apply(&mut xr_projection);
         transform.rotation = view.pose.orientation.to_quat();
         transform.translation = view.pose.position.to_vec3();
     }
@@ -394,9 +611,9 @@ pub fn xr_camera_head_sync(
 
 pub fn xr_camera_head_sync_render_world(
     views: Res<crate::resources::XrViews>,
-    mut query: Query<(&mut ExtractedView, &XrCamera, &RootTransform)>,
+    mut query: Query<(&mut ExtractedView, &XrCamera, &XRProjection, &RootTransform)>,
 ) {
-    for (mut extracted_view, camera_type, root) in query.iter_mut() {
+    for (mut extracted_view, camera_type, projection, root) in query.iter_mut() {
         let view_idx = camera_type.0 as usize;
         let view = match views.get(view_idx) {
             Some(views) => views,
@@ -406,5 +623,96 @@ pub fn xr_camera_head_sync_render_world(
         transform.rotation = view.pose.orientation.to_quat();
         transform.translation = view.pose.position.to_vec3();
         extracted_view.transform = root.mul_transform(transform);
+        // runtimes may change the fov between frames too
+        extracted_view.projection = XRProjection {
+            fov: view.fov,
+            ..projection.clone()
+        }
+        .get_projection_matrix();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tangents of -1, 0.5, -0.25 and 0.75, so the scales and offsets are exact
+    fn asymmetric_fov() -> Fovf {
+        Fovf {
+            angle_left: -1f32.atan(),
+            angle_right: 0.5f32.atan(),
+            angle_down: -0.25f32.atan(),
+            angle_up: 0.75f32.atan(),
+        }
+    }
+
+    fn project(matrix: Mat4, point: Vec3) -> Vec3 {
+        matrix.project_point3(point)
+    }
+
+    #[test]
+    fn reverse_infinite_matrix_of_asymmetric_fov() {
+        // width 1.5 and height 1 in tangent space, centered at -0.25 and 0.25
+        let expected = Mat4::from_cols(
+            Vec4::new(4.0 / 3.0, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0, 0.0, 0.0),
+            Vec4::new(-1.0 / 3.0, 0.5, 0.0, -1.0),
+            Vec4::new(0.0, 0.0, 0.1, 0.0),
+        );
+        let matrix = XRProjection::reverse_infinite_matrix(asymmetric_fov(), 0.1);
+        assert!(matrix.abs_diff_eq(expected, 1e-6), "{}", matrix);
+    }
+
+    #[test]
+    fn reverse_matrix_of_asymmetric_fov() {
+        let expected = Mat4::from_cols(
+            Vec4::new(4.0 / 3.0, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0, 0.0, 0.0),
+            Vec4::new(-1.0 / 3.0, 0.5, 0.1 / 99.9, -1.0),
+            Vec4::new(0.0, 0.0, 10.0 / 99.9, 0.0),
+        );
+        let matrix = XRProjection::reverse_matrix(asymmetric_fov(), 0.1, 100.0);
+        assert!(matrix.abs_diff_eq(expected, 1e-6), "{}", matrix);
+    }
+
+    #[test]
+    fn edges_of_the_fov_are_the_edges_of_clip_space() {
+        let fov = asymmetric_fov();
+        let finite = XRProjection::reverse_matrix(fov, 0.1, 100.0);
+        let infinite = XRProjection::reverse_infinite_matrix(fov, 0.1);
+        // bottom left on the near plane, top right on the far plane
+        let near = Vec3::new(-0.1, -0.025, -0.1);
+        let far = Vec3::new(50.0, 75.0, -100.0);
+        for matrix in [finite, infinite] {
+            assert!(project(matrix, near).abs_diff_eq(Vec3::new(-1.0, -1.0, 1.0), 1e-5));
+        }
+        assert!(project(finite, far).abs_diff_eq(Vec3::new(1.0, 1.0, 0.0), 1e-5));
+        let towards_infinity = project(infinite, far);
+        assert!(towards_infinity.truncate().abs_diff_eq(Vec2::ONE, 1e-5));
+        assert!((towards_infinity.z - 0.001).abs() < 1e-6);
+    }
+
+    #[test]
+    fn symmetric_fov_matches_bevy() {
+        let half = 0.6f32;
+        let fov = Fovf {
+            angle_left: -half,
+            angle_right: half,
+            angle_down: -half,
+            angle_up: half,
+        };
+        let matrix = XRProjection::reverse_infinite_matrix(fov, 0.1);
+        let expected = Mat4::perspective_infinite_reverse_rh(half * 2.0, 1.0, 0.1);
+        assert!(matrix.abs_diff_eq(expected, 1e-6), "{}", matrix);
+    }
+
+    #[test]
+    fn far_plane_at_infinity() {
+        let projection = XRProjection::new(0.1, f32::INFINITY, asymmetric_fov());
+        assert_eq!(
+            projection.get_projection_matrix(),
+            XRProjection::reverse_infinite_matrix(asymmetric_fov(), 0.1)
+        );
+        assert_eq!(projection.far(), f32::MAX);
     }
 }
@@ -0,0 +1,75 @@
+//! The session state as a bevy [`State`], for apps that hang systems off the session lifecycle
+//! with [`OnEnter`] and [`OnExit`], like pausing the game when [`XrState::Focused`] is left.
+
+use bevy::ecs::event::ManualEventReader;
+use bevy::ecs::schedule::apply_state_transition;
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::xr_init::{XrPollEvents, XrSessionStateChanged, XrWaitFrame};
+
+/// Keeps [`State<XrState>`] in sync with the session state
+pub struct XrStatePlugin;
+
+impl Plugin for XrStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<XrState>();
+        app.register_type::<XrState>()
+            .register_type::<State<XrState>>();
+        app.add_systems(
+            PreUpdate,
+            apply_session_states.after(XrPollEvents).before(XrWaitFrame),
+        );
+    }
+}
+
+/// The lifecycle of the session, [`XrState::Idle`] until a session was created
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum XrState {
+    #[default]
+    Idle,
+    /// The runtime wants the session to begin
+    Ready,
+    /// Frames are submitted, whether they are shown or not
+    Running,
+    /// The app is shown and receives input
+    Focused,
+    /// The runtime wants the session to end
+    Stopping,
+    /// The session is going away for good, the app might exit next
+    Exiting,
+}
+
+impl XrState {
+    pub fn from_session_state(state: xr::SessionState) -> Option<Self> {
+        match state {
+            xr::SessionState::IDLE => Some(XrState::Idle),
+            xr::SessionState::READY => Some(XrState::Ready),
+            xr::SessionState::SYNCHRONIZED | xr::SessionState::VISIBLE => Some(XrState::Running),
+            xr::SessionState::FOCUSED => Some(XrState::Focused),
+            xr::SessionState::STOPPING => Some(XrState::Stopping),
+            xr::SessionState::EXITING | xr::SessionState::LOSS_PENDING => Some(XrState::Exiting),
+            _ => None,
+        }
+    }
+}
+
+/// Applies every state the runtime reported this frame one after the other, so each of them
+/// runs its [`OnExit`] and [`OnEnter`] schedules even when several arrived in one poll.
+/// [`NextState<XrState>`](NextState) is overwritten by it, the runtime owns this state.
+fn apply_session_states(
+    world: &mut World,
+    mut reader: Local<ManualEventReader<XrSessionStateChanged>>,
+) {
+    let states: Vec<XrState> = reader
+        .read(world.resource::<Events<XrSessionStateChanged>>())
+        .filter_map(|event| XrState::from_session_state(event.state))
+        .collect();
+    for state in states {
+        if *world.resource::<State<XrState>>().get() == state {
+            continue;
+        }
+        world.resource_mut::<NextState<XrState>>().set(state);
+        apply_state_transition::<XrState>(world);
+    }
+}